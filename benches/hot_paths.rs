@@ -0,0 +1,223 @@
+//! 核心热路径的性能基准
+//!
+//! 覆盖三个代表性负载（Dhrystone 风格整数循环、memcpy、浮点
+//! saxpy 内核）加解码与 FlatMemory 访问两组微基准，用于在
+//! exu / memory 重构前后对比，防止性能回退：
+//!
+//! ```text
+//! cargo bench --bench hot_paths [每个负载的指令数]
+//! ```
+//!
+//! 依赖全部 vendored、没有外部基准框架，这里用 `std::time` 手工
+//! 计时：负载打印 MIPS，微基准打印每次操作的纳秒数。解码微基准
+//! 同时给出注册表路径与直连 RV32I 表的对比。
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use allude_sim::isa::{DecoderRegistry, InstrDecoder, RV32I_DECODER, RV32M_DECODER};
+use allude_sim::memory::{FlatMemory, Memory};
+use allude_sim::sim_env::{SimConfig, SimEnv};
+
+// ========== 指令编码辅助 ==========
+
+const OP: u32 = 0x33;
+const OP_IMM: u32 = 0x13;
+const LOAD: u32 = 0x03;
+const STORE: u32 = 0x23;
+const BRANCH: u32 = 0x63;
+const LOAD_FP: u32 = 0x07;
+const STORE_FP: u32 = 0x27;
+const OP_FP: u32 = 0x53;
+
+const fn r_type(funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    (funct7 << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+const fn i_type(imm: i32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    ((imm as u32 & 0xFFF) << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+const fn s_type(imm: i32, rs2: u32, rs1: u32, funct3: u32, opcode: u32) -> u32 {
+    let imm = imm as u32 & 0xFFF;
+    ((imm >> 5) << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | ((imm & 0x1F) << 7) | opcode
+}
+
+const fn b_type(imm: i32, rs2: u32, rs1: u32, funct3: u32) -> u32 {
+    let imm = imm as u32 & 0x1FFF;
+    (((imm >> 12) & 1) << 31)
+        | (((imm >> 5) & 0x3F) << 25)
+        | (rs2 << 20)
+        | (rs1 << 15)
+        | (funct3 << 12)
+        | (((imm >> 1) & 0xF) << 8)
+        | (((imm >> 11) & 1) << 7)
+        | BRANCH
+}
+
+const fn jal(rd: u32, imm: i32) -> u32 {
+    let imm = imm as u32 & 0x1F_FFFF;
+    (((imm >> 20) & 1) << 31)
+        | (((imm >> 1) & 0x3FF) << 21)
+        | (((imm >> 11) & 1) << 20)
+        | (((imm >> 12) & 0xFF) << 12)
+        | (rd << 7)
+        | 0x6F
+}
+
+// ========== 代表性负载 ==========
+
+/// Dhrystone 风格：ALU、移位、逻辑、比较、load/store 与回边的混合
+const DHRYSTONE_LIKE: [u32; 13] = [
+    i_type(100, 0, 0, 1, OP_IMM),       // 0x00 addi x1, x0, 100
+    i_type(1, 2, 0, 2, OP_IMM),         // 0x04 loop: addi x2, x2, 1
+    i_type(3, 2, 1, 3, OP_IMM),         // 0x08 slli x3, x2, 3
+    i_type(1, 3, 5, 4, OP_IMM),         // 0x0C srli x4, x3, 1
+    r_type(0, 4, 3, 4, 5, OP),          // 0x10 xor x5, x3, x4
+    r_type(0, 2, 5, 7, 6, OP),          // 0x14 and x6, x5, x2
+    r_type(0, 1, 6, 6, 7, OP),          // 0x18 or x7, x6, x1
+    s_type(0x200, 7, 0, 2, STORE),      // 0x1C sw x7, 0x200(x0)
+    i_type(0x200, 0, 2, 8, LOAD),       // 0x20 lw x8, 0x200(x0)
+    r_type(0, 2, 8, 0, 9, OP),          // 0x24 add x9, x8, x2
+    i_type(-1, 1, 0, 1, OP_IMM),        // 0x28 addi x1, x1, -1
+    b_type(-0x28, 0, 1, 1),             // 0x2C bne x1, x0, loop
+    jal(0, -0x30),                      // 0x30 回到头部
+];
+
+/// memcpy：64 个字的 load/store 拷贝循环
+const MEMCPY: [u32; 9] = [
+    i_type(0x400, 0, 0, 5, OP_IMM),     // 0x00 addi x5, x0, 0x400 (src)
+    i_type(0x600, 0, 0, 6, OP_IMM),     // 0x04 addi x6, x0, 0x600 (dst)
+    i_type(256, 5, 0, 7, OP_IMM),       // 0x08 addi x7, x5, 256 (end)
+    i_type(0, 5, 2, 28, LOAD),          // 0x0C loop: lw x28, 0(x5)
+    s_type(0, 28, 6, 2, STORE),         // 0x10 sw x28, 0(x6)
+    i_type(4, 5, 0, 5, OP_IMM),         // 0x14 addi x5, x5, 4
+    i_type(4, 6, 0, 6, OP_IMM),         // 0x18 addi x6, x6, 4
+    b_type(-0x10, 7, 5, 1),             // 0x1C bne x5, x7, loop
+    jal(0, -0x20),                      // 0x20 回到头部
+];
+
+/// 浮点 saxpy 内核：y[i] = a * x[i] + y[i]，16 个单精度元素
+const FP_SAXPY: [u32; 12] = [
+    i_type(0x400, 0, 0, 5, OP_IMM),     // 0x00 addi x5, x0, 0x400 (x[])
+    i_type(0x500, 0, 0, 6, OP_IMM),     // 0x04 addi x6, x0, 0x500 (y[])
+    i_type(64, 5, 0, 7, OP_IMM),        // 0x08 addi x7, x5, 64 (end)
+    i_type(0x600, 0, 2, 0, LOAD_FP),    // 0x0C flw f0, 0x600(x0) (a)
+    i_type(0, 5, 2, 1, LOAD_FP),        // 0x10 loop: flw f1, 0(x5)
+    i_type(0, 6, 2, 2, LOAD_FP),        // 0x14 flw f2, 0(x6)
+    r_type(0x08, 1, 0, 0, 3, OP_FP),    // 0x18 fmul.s f3, f0, f1
+    r_type(0x00, 2, 3, 0, 3, OP_FP),    // 0x1C fadd.s f3, f3, f2
+    s_type(0, 3, 6, 2, STORE_FP),       // 0x20 fsw f3, 0(x6)
+    i_type(4, 5, 0, 5, OP_IMM),         // 0x24 addi x5, x5, 4
+    b_type(-0x18, 7, 5, 1),             // 0x28 bne x5, x7, loop
+    jal(0, -0x2C),                      // 0x2C 回到头部
+];
+
+/// 跑一个自循环负载固定的指令数，返回 MIPS
+fn run_workload(isa: &str, program: &[u32], fp_data: bool, instructions: u64) -> f64 {
+    let config = SimConfig::new()
+        .with_entry_pc(0)
+        .with_memory_size(64 * 1024)
+        .with_isa(isa)
+        .expect("ISA 字符串应合法")
+        .with_max_instructions(instructions);
+    let mut env = SimEnv::from_config(config).expect("环境构建应成功");
+    for (i, word) in program.iter().enumerate() {
+        env.memory.store32(i as u32 * 4, *word).unwrap();
+    }
+    if fp_data {
+        // a = 2.0，x[]/y[] 全 1.0
+        env.memory.store32(0x600, 2.0f32.to_bits()).unwrap();
+        for i in 0..16 {
+            env.memory.store32(0x400 + i * 4, 1.0f32.to_bits()).unwrap();
+            env.memory.store32(0x500 + i * 4, 1.0f32.to_bits()).unwrap();
+        }
+    }
+
+    let start = Instant::now();
+    let (executed, state) = env.run_until_halt();
+    let elapsed = start.elapsed().as_secs_f64();
+    assert!(
+        matches!(state, allude_sim::cpu::CpuState::Running),
+        "负载不应停机: {state:?}"
+    );
+    assert_eq!(executed, instructions);
+    executed as f64 / elapsed / 1e6
+}
+
+// ========== 微基准 ==========
+
+/// 对 `f` 计时 `iters` 次，返回每次的纳秒数
+fn time_per_op(iters: u64, mut f: impl FnMut(u64) -> u32) -> f64 {
+    let mut acc = 0u32;
+    let start = Instant::now();
+    for i in 0..iters {
+        acc = acc.wrapping_add(f(i));
+    }
+    let elapsed = start.elapsed().as_nanos() as f64;
+    std::hint::black_box(acc);
+    elapsed / iters as f64
+}
+
+fn bench_decode(iters: u64) {
+    // 混合 opcode 的真实编码，覆盖 ALU / load / store / branch / M
+    let words = [
+        DHRYSTONE_LIKE[1],
+        DHRYSTONE_LIKE[4],
+        DHRYSTONE_LIKE[7],
+        DHRYSTONE_LIKE[8],
+        DHRYSTONE_LIKE[11],
+        r_type(1, 2, 1, 0, 3, OP), // mul x3, x1, x2
+    ];
+
+    let mut registry = DecoderRegistry::with_rv32i();
+    registry
+        .register(Arc::new(RV32M_DECODER))
+        .expect("RV32M 注册应成功");
+    let per_registry = time_per_op(iters, |i| {
+        registry.decode(words[i as usize % words.len()]).raw
+    });
+    println!("decode/registry        {per_registry:8.1} ns/条");
+
+    // 与直连 RV32I 表对比（请求的 registry vs direct 基线）
+    let rv32i_words = &words[..5];
+    let per_direct = time_per_op(iters, |i| {
+        RV32I_DECODER
+            .decode(rv32i_words[i as usize % rv32i_words.len()])
+            .map_or(0, |d| d.raw)
+    });
+    println!("decode/direct-rv32i    {per_direct:8.1} ns/条");
+}
+
+fn bench_flat_memory(iters: u64) {
+    let mut mem = FlatMemory::new(64 * 1024, 0);
+    let per_store = time_per_op(iters, |i| {
+        let addr = (i as u32).wrapping_mul(4) & 0xFFFC;
+        mem.store32(addr, i as u32).unwrap();
+        0
+    });
+    println!("memory/store32         {per_store:8.1} ns/次");
+    let per_load = time_per_op(iters, |i| {
+        mem.load32((i as u32).wrapping_mul(4) & 0xFFFC).unwrap()
+    });
+    println!("memory/load32          {per_load:8.1} ns/次");
+}
+
+fn main() {
+    let instructions: u64 = std::env::args()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(5_000_000);
+
+    println!("每个负载指令数: {instructions}");
+    let dhry = run_workload("rv32i", &DHRYSTONE_LIKE, false, instructions);
+    println!("workload/dhrystone     {dhry:8.1} MIPS");
+    let memcpy = run_workload("rv32i", &MEMCPY, false, instructions);
+    println!("workload/memcpy        {memcpy:8.1} MIPS");
+    let saxpy = run_workload("rv32imf", &FP_SAXPY, true, instructions);
+    println!("workload/fp-saxpy      {saxpy:8.1} MIPS");
+
+    println!();
+    bench_decode(instructions.max(1_000_000));
+    bench_flat_memory(instructions.max(1_000_000));
+}
@@ -2,7 +2,7 @@ use std::error::Error;
 use std::time::Instant;
 
 use allude_sim::cpu::CpuState;
-use allude_sim::sim_env::{IsaExtensions, SimConfig, SimEnv, TestResult};
+use allude_sim::sim_env::{IsaExtensions, SimConfig, SimEnv, TestResult, Verbosity};
 
 const TEST_PATH: &str = "isa_test/rv32ui-p-ma_data";
 const MAX_STEPS: u64 = 10_000;
@@ -17,7 +17,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         .with_elf_path(TEST_PATH)
         .with_memory("ram", 0x8000_0000, 512 * 1024)
         .with_extensions(IsaExtensions::rv32g())
-        .with_verbose(true);
+        .with_verbosity(Verbosity::all(2));
 
     let mut env = SimEnv::from_config(config)?;
     println!("Loaded {TEST_PATH}, entry pc=0x{:08x}", env.cpu.pc());
@@ -0,0 +1,61 @@
+//! 已解码指令缓存的收益测量
+//!
+//! 用一个混合了 ALU / 访存 / 分支的紧循环分别在开关缓存的两个
+//! 环境里跑同样的指令数，打印 MIPS 与加速比：
+//!
+//! ```text
+//! cargo run --release --example decode_cache_bench [指令数]
+//! ```
+
+use std::time::Instant;
+
+use allude_sim::cpu::CpuState;
+use allude_sim::memory::Memory;
+use allude_sim::sim_env::{SimConfig, SimEnv};
+
+/// 每轮 8 条指令的循环：ALU、load/store、比较与回跳
+const LOOP_PROGRAM: [u32; 8] = [
+    0x0010_0093, // addi x1, x0, 1
+    0x0010_8133, // add x2, x1, x1
+    0x1020_2223, // sw x2, 0x104(x0)
+    0x1040_2183, // lw x3, 0x104(x0)
+    0x0031_0233, // add x4, x2, x3
+    0x0012_0213, // addi x4, x4, 1
+    0x0000_0013, // nop
+    0xFE00_06E3, // beq x0, x0, -20（回到 add x2）
+];
+
+fn run_once(instructions: u64, decode_cache: bool) -> f64 {
+    let mut config = SimConfig::new()
+        .with_entry_pc(0)
+        .with_memory_size(4096)
+        .with_max_instructions(instructions);
+    if decode_cache {
+        config = config.with_decode_cache();
+    }
+    let mut env = SimEnv::from_config(config).expect("环境构建应成功");
+    for (i, word) in LOOP_PROGRAM.iter().enumerate() {
+        env.memory.store32(i as u32 * 4, *word).unwrap();
+    }
+
+    let start = Instant::now();
+    let (executed, state) = env.run_until_halt();
+    let elapsed = start.elapsed().as_secs_f64();
+    assert_eq!(state, CpuState::Running, "循环程序不应停机");
+    assert_eq!(executed, instructions);
+    executed as f64 / elapsed / 1e6
+}
+
+fn main() {
+    let instructions: u64 = std::env::args()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(20_000_000);
+
+    println!("指令数: {instructions}");
+    let plain = run_once(instructions, false);
+    println!("逐条解码:     {plain:8.1} MIPS");
+    let cached = run_once(instructions, true);
+    println!("解码缓存:     {cached:8.1} MIPS");
+    println!("加速比:       {:.2}x", cached / plain);
+}
@@ -4,19 +4,27 @@ use std::io;
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-use allude_sim::sim_env::{IsaExtensions, SimConfig, SimEnv, TestResult};
+use allude_sim::sim_env::{IsaExtensions, SimConfig, SimEnv, TestResult, TestResultCache};
 
 const PREFIX: &str = "rv32um-p-";
 
 fn main() {
-    let filter = env::args().nth(1);
-    if let Err(err) = run_suite(filter.as_deref()) {
+    let mut filter = None;
+    let mut force = false;
+    for arg in env::args().skip(1) {
+        if arg == "--force" {
+            force = true;
+        } else {
+            filter = Some(arg);
+        }
+    }
+    if let Err(err) = run_suite(filter.as_deref(), force) {
         eprintln!("error: {err}");
         std::process::exit(1);
     }
 }
 
-fn run_suite(filter: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+fn run_suite(filter: Option<&str>, force: bool) -> Result<(), Box<dyn std::error::Error>> {
     let root = Path::new("isa_test");
     if !root.exists() {
         return Err(format!("{} does not exist", root.display()).into());
@@ -42,16 +50,32 @@ fn run_suite(filter: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
         None => println!("Discovered {} {PREFIX}* tests", cases.len()),
     }
 
+    let mut cache = TestResultCache::open("target/isa-test-cache.txt");
     let mut pass = 0usize;
+    let mut skipped = 0usize;
     let mut fail = Vec::new();
 
     for case in &cases {
         let name = case.file_name().unwrap().to_string_lossy().into_owned();
+        // 缓存键基于 ELF 内容哈希，文件被重新编译后自动失效
+        let key = fs::read(case).ok().map(|data| TestResultCache::key(&data, "rv32g"));
+        if !force
+            && let Some(ref key) = key
+            && cache.is_cached_pass(key)
+        {
+            pass += 1;
+            skipped += 1;
+            println!("[RUN] {name} ... SKIP (cached PASS)");
+            continue;
+        }
         print!("[RUN] {name} ... ");
         let start = Instant::now();
         match run_case(case) {
             Ok((TestResult::Pass, executed)) => {
                 pass += 1;
+                if let Some(key) = key {
+                    cache.record_pass(key);
+                }
                 println!("PASS ({} instr, {:?})", executed, start.elapsed());
             }
             Ok((result, executed)) => {
@@ -65,7 +89,16 @@ fn run_suite(filter: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    println!("\nSummary: {} passed / {} failed", pass, fail.len());
+    if let Err(err) = cache.save() {
+        eprintln!("warning: failed to save test cache: {err}");
+    }
+
+    println!(
+        "\nSummary: {} passed ({} cached) / {} failed",
+        pass,
+        skipped,
+        fail.len()
+    );
     if !fail.is_empty() {
         println!("Failed cases:");
         for (name, result) in &fail {
@@ -105,8 +138,7 @@ fn run_case(path: &Path) -> Result<(TestResult, u64), Box<dyn std::error::Error>
     let config = SimConfig::new()
         .with_elf_path(path.to_string_lossy().into_owned())
         .with_memory("ram", 0x8000_0000, 512 * 1024)
-        .with_extensions(IsaExtensions::rv32g())
-        .with_verbose(false);
+        .with_extensions(IsaExtensions::rv32g());
 
     let mut env = SimEnv::from_config(config)?;
     let result = env.run_isa_test(2_000_000);
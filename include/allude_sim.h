@@ -0,0 +1,75 @@
+/*
+ * allude_sim C API — SystemC / Verilator 协同仿真接口
+ *
+ * 与 src/capi.rs 手工保持同步（接口有意保持小而稳定）。
+ * 构建：cargo build --release --features capi
+ * 链接：-L target/release -lallude_sim
+ */
+
+#ifndef ALLUDE_SIM_H
+#define ALLUDE_SIM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* 不透明仿真器句柄，线程间不可共享 */
+typedef struct AlludeSim AlludeSim;
+
+/*
+ * MMIO 回调：user 为注册时透传的指针，addr 是绝对地址，width 为
+ * 1/2/4，is_write 非零时 value 是写入值；读访问的返回值作为读出
+ * 数据，写访问的返回值被忽略。
+ */
+typedef uint32_t (*allude_mmio_callback)(void *user, uint32_t addr,
+                                         uint32_t width, int32_t is_write,
+                                         uint32_t value);
+
+/*
+ * CPU 状态码（allude_step / 句柄为空时各函数的错误返回见注释）：
+ *   0 运行中  1 WFI  2 停机  3 非法指令
+ *   4 监视点  5 单步停  6 已退出  -1 句柄为空
+ */
+
+/* 创建实例：RAM 为 [mem_base, mem_base+mem_size)，失败返回 NULL */
+AlludeSim *allude_create(uint32_t mem_base, size_t mem_size,
+                         uint32_t entry_pc);
+
+/* 销毁实例；NULL 时为空操作 */
+void allude_destroy(AlludeSim *sim);
+
+/* 单步一条指令，返回状态码 */
+int32_t allude_step(AlludeSim *sim);
+
+/* 执行至多 max_instructions 条指令，返回实际执行数 */
+uint64_t allude_run(AlludeSim *sim, uint64_t max_instructions);
+
+/* PC 读写 */
+uint32_t allude_get_pc(const AlludeSim *sim);
+void allude_set_pc(AlludeSim *sim, uint32_t pc);
+
+/* 整数寄存器 x0..x31 读写（x0 与越界写被忽略，越界读返回 0） */
+uint32_t allude_get_reg(const AlludeSim *sim, uint32_t reg);
+void allude_set_reg(AlludeSim *sim, uint32_t reg, uint32_t value);
+
+/* 客体内存读写，返回字节数；越界或句柄为空时返回 -1 */
+int32_t allude_mem_read(const AlludeSim *sim, uint32_t addr, uint8_t *buf,
+                        size_t len);
+int32_t allude_mem_write(AlludeSim *sim, uint32_t addr, const uint8_t *buf,
+                         size_t len);
+
+/*
+ * 把 [base, base+size) 映射到 MMIO 回调，返回设备编号（失败 -1）。
+ * callback 与 user 在实例销毁前必须保持有效。
+ */
+int32_t allude_register_mmio(AlludeSim *sim, uint32_t base, uint32_t size,
+                             allude_mmio_callback callback, void *user);
+
+#ifdef __cplusplus
+} /* extern "C" */
+#endif
+
+#endif /* ALLUDE_SIM_H */
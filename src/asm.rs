@@ -0,0 +1,859 @@
+//! 内置 RV32IMF 汇编器
+//!
+//! 把文本汇编（RV32I/M/F 子集，支持标签、分支和常用伪指令）
+//! 汇编成可直接写入 `FlatMemory` 的指令字，免去在演示程序和
+//! 测试里手工编码十六进制指令的麻烦。
+//!
+//! 支持的语法：
+//! - `#` 之后为注释；标签 `name:` 可与指令同行
+//! - 寄存器接受 ABI 名（`a0`、`sp`、`fa0`……）和编号（`x5`、`f3`）
+//! - 立即数接受十进制、`0x` 十六进制和 `0b` 二进制
+//! - 分支/跳转目标为标签或相对字节偏移
+//! - 伪指令：`nop`、`li`、`mv`、`not`、`neg`、`seqz`、`snez`、
+//!   `j`、`jr`、`ret`、`beqz`、`bnez`、`bltz`、`bgez`、`bgtz`、`blez`、
+//!   `call`（展开为 `auipc ra` + `jalr ra`）
+//! - `.word` 指示符直接放置数据字
+//!
+//! [`assemble_with`] 的 `allow_pseudo` 标志可以关闭伪指令
+//! （类似 `objdump -M no-aliases` 的严格模式），用于确保测试
+//! 程序只含规范编码。
+//!
+//! # 示例
+//!
+//! ```
+//! use allude_sim::asm::assemble;
+//!
+//! let words = assemble(
+//!     "
+//!     li   a0, 0          # 累加器
+//!     li   a1, 5          # 循环计数
+//! loop:
+//!     add  a0, a0, a1
+//!     addi a1, a1, -1
+//!     bnez a1, loop
+//!     ebreak
+//!     ",
+//! )
+//! .unwrap();
+//! assert_eq!(words.len(), 6);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::isa::{abi_reg_name, fp_abi_reg_name};
+
+/// 汇编错误：出错的源文件行号与描述
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// 源文本中的行号（从 1 开始）
+    pub line: usize,
+    /// 错误描述
+    pub message: String,
+}
+
+impl std::fmt::Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// 把汇编文本汇编为指令字序列（首条指令位于地址 0）
+pub fn assemble(source: &str) -> Result<Vec<u32>, AsmError> {
+    assemble_at(source, 0)
+}
+
+/// 把汇编文本汇编为指令字序列，首条指令位于 `base`
+///
+/// 本子集内只有相对寻址（分支、`jal`），`base` 仅影响标签地址的
+/// 记账，产出的指令字与加载地址无关。
+pub fn assemble_at(source: &str, base: u32) -> Result<Vec<u32>, AsmError> {
+    assemble_with(source, base, true)
+}
+
+/// 伪指令助记符（`allow_pseudo = false` 时整体拒绝）
+const PSEUDO_MNEMONICS: &[&str] = &[
+    "nop", "li", "mv", "not", "neg", "seqz", "snez", "j", "jr", "ret", "beqz", "bnez", "bltz",
+    "bgez", "bgtz", "blez", "call",
+];
+
+/// 把汇编文本汇编为指令字序列，可控制是否接受伪指令
+///
+/// `allow_pseudo = false` 时遇到伪指令助记符报错，
+/// 源文本必须只使用规范指令形式。
+pub fn assemble_with(source: &str, base: u32, allow_pseudo: bool) -> Result<Vec<u32>, AsmError> {
+    let mut labels: HashMap<&str, u32> = HashMap::new();
+    let mut items: Vec<Item<'_>> = Vec::new();
+    let mut addr = base;
+
+    // 第一遍：收集标签地址、确定每条指令的长度
+    for (idx, raw_line) in source.lines().enumerate() {
+        let line = idx + 1;
+        let mut text = raw_line;
+        if let Some(pos) = text.find('#') {
+            text = &text[..pos];
+        }
+        let mut text = text.trim();
+
+        // 标签（可与指令同行，可连续多个）
+        while let Some(pos) = text.find(':') {
+            let label = text[..pos].trim();
+            if label.is_empty() || !is_ident(label) {
+                return Err(err(line, format!("无效的标签 '{}'", label)));
+            }
+            if labels.insert(label, addr).is_some() {
+                return Err(err(line, format!("标签 '{}' 重复定义", label)));
+            }
+            text = text[pos + 1..].trim();
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = match text.find(char::is_whitespace) {
+            Some(pos) => (&text[..pos], text[pos..].trim()),
+            None => (text, ""),
+        };
+        if !allow_pseudo && PSEUDO_MNEMONICS.contains(&mnemonic) {
+            return Err(err(line, format!("严格模式下不接受伪指令 '{}'", mnemonic)));
+        }
+        let operands: Vec<&str> = if rest.is_empty() {
+            Vec::new()
+        } else {
+            rest.split(',').map(str::trim).collect()
+        };
+
+        let words = instr_words(line, mnemonic, &operands)?;
+        items.push(Item {
+            line,
+            addr,
+            mnemonic,
+            operands,
+        });
+        addr = addr.wrapping_add(words * 4);
+    }
+
+    // 第二遍：编码
+    let mut out = Vec::new();
+    for item in &items {
+        encode(item, &labels, &mut out)?;
+    }
+    Ok(out)
+}
+
+/// 第一遍收集到的一条指令
+struct Item<'a> {
+    line: usize,
+    addr: u32,
+    mnemonic: &'a str,
+    operands: Vec<&'a str>,
+}
+
+fn err(line: usize, message: String) -> AsmError {
+    AsmError { line, message }
+}
+
+fn is_ident(s: &str) -> bool {
+    s.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// 解析整数寄存器（ABI 名、`fp` 或 `xN`）
+fn parse_reg(s: &str) -> Option<u8> {
+    if s == "fp" {
+        return Some(8);
+    }
+    if let Some(num) = s.strip_prefix('x')
+        && let Ok(n) = num.parse::<u8>()
+        && n < 32
+    {
+        return Some(n);
+    }
+    (0..32u8).find(|&i| abi_reg_name(i) == s)
+}
+
+/// 解析浮点寄存器（ABI 名或 `fN`）
+fn parse_freg(s: &str) -> Option<u8> {
+    if let Some(num) = s.strip_prefix('f')
+        && let Ok(n) = num.parse::<u8>()
+        && n < 32
+    {
+        return Some(n);
+    }
+    (0..32u8).find(|&i| fp_abi_reg_name(i) == s)
+}
+
+/// 解析立即数（十进制 / 0x 十六进制 / 0b 二进制，可带符号）
+fn parse_imm(s: &str) -> Option<i64> {
+    let (neg, body) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let value = if let Some(hex) = body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = body.strip_prefix("0b").or_else(|| body.strip_prefix("0B")) {
+        i64::from_str_radix(bin, 2).ok()?
+    } else {
+        body.parse::<i64>().ok()?
+    };
+    Some(if neg { -value } else { value })
+}
+
+/// 解析内存操作数 `off(reg)`，返回 (偏移, 寄存器文本)
+fn parse_mem(s: &str) -> Option<(i64, &str)> {
+    let open = s.find('(')?;
+    let inner = s[open + 1..].strip_suffix(')')?;
+    let off_text = s[..open].trim();
+    let off = if off_text.is_empty() {
+        0
+    } else {
+        parse_imm(off_text)?
+    };
+    Some((off, inner.trim()))
+}
+
+/// `li` 展开后的指令数（两遍必须一致）
+fn li_words(imm: i64) -> u32 {
+    let v = imm as i32;
+    // 单条 addi 或单条 lui 就够的情况占一个字，否则 lui + addi
+    if (-2048..=2047).contains(&v) || v & 0xFFF == 0 {
+        1
+    } else {
+        2
+    }
+}
+
+/// 第一遍：计算一条指令占用的字数
+fn instr_words(line: usize, mnemonic: &str, operands: &[&str]) -> Result<u32, AsmError> {
+    match mnemonic {
+        ".word" => Ok(operands.len() as u32),
+        "li" => {
+            if operands.len() != 2 {
+                return Err(err(line, "li 需要 2 个操作数".into()));
+            }
+            let imm = parse_imm(operands[1])
+                .ok_or_else(|| err(line, format!("无效的立即数 '{}'", operands[1])))?;
+            Ok(li_words(imm))
+        }
+        "call" => {
+            if operands.len() != 1 {
+                return Err(err(line, "call 需要 1 个操作数".into()));
+            }
+            // 总是展开为 auipc + jalr 两个字
+            Ok(2)
+        }
+        _ => Ok(1),
+    }
+}
+
+/// R 型编码
+fn r_type(funct7: u32, rs2: u8, rs1: u8, funct3: u32, rd: u8, opcode: u32) -> u32 {
+    (funct7 << 25)
+        | ((rs2 as u32) << 20)
+        | ((rs1 as u32) << 15)
+        | (funct3 << 12)
+        | ((rd as u32) << 7)
+        | opcode
+}
+
+/// I 型编码
+fn i_type(imm: i32, rs1: u8, funct3: u32, rd: u8, opcode: u32) -> u32 {
+    ((imm as u32 & 0xFFF) << 20)
+        | ((rs1 as u32) << 15)
+        | (funct3 << 12)
+        | ((rd as u32) << 7)
+        | opcode
+}
+
+/// S 型编码
+fn s_type(imm: i32, rs2: u8, rs1: u8, funct3: u32, opcode: u32) -> u32 {
+    let imm = imm as u32;
+    ((imm >> 5 & 0x7F) << 25)
+        | ((rs2 as u32) << 20)
+        | ((rs1 as u32) << 15)
+        | (funct3 << 12)
+        | ((imm & 0x1F) << 7)
+        | opcode
+}
+
+/// B 型编码
+fn b_type(offset: i32, rs2: u8, rs1: u8, funct3: u32) -> u32 {
+    let imm = offset as u32;
+    ((imm >> 12 & 1) << 31)
+        | ((imm >> 5 & 0x3F) << 25)
+        | ((rs2 as u32) << 20)
+        | ((rs1 as u32) << 15)
+        | (funct3 << 12)
+        | ((imm >> 1 & 0xF) << 8)
+        | ((imm >> 11 & 1) << 7)
+        | 0x63
+}
+
+/// U 型编码
+fn u_type(imm20: u32, rd: u8, opcode: u32) -> u32 {
+    (imm20 << 12) | ((rd as u32) << 7) | opcode
+}
+
+/// J 型编码
+fn j_type(offset: i32, rd: u8) -> u32 {
+    let imm = offset as u32;
+    ((imm >> 20 & 1) << 31)
+        | ((imm >> 1 & 0x3FF) << 21)
+        | ((imm >> 11 & 1) << 20)
+        | ((imm >> 12 & 0xFF) << 12)
+        | ((rd as u32) << 7)
+        | 0x6F
+}
+
+/// 动态舍入模式（F 扩展指令的默认 rm 字段）
+const RM_DYN: u32 = 0b111;
+
+/// 第二遍：把一条指令编码进 `out`
+fn encode(item: &Item<'_>, labels: &HashMap<&str, u32>, out: &mut Vec<u32>) -> Result<(), AsmError> {
+    let line = item.line;
+    let ops = &item.operands;
+
+    let expect = |n: usize| -> Result<(), AsmError> {
+        if ops.len() == n {
+            Ok(())
+        } else {
+            Err(err(
+                line,
+                format!("{} 需要 {} 个操作数，实际 {}", item.mnemonic, n, ops.len()),
+            ))
+        }
+    };
+    let reg = |s: &str| -> Result<u8, AsmError> {
+        parse_reg(s).ok_or_else(|| err(line, format!("无效的整数寄存器 '{}'", s)))
+    };
+    let freg = |s: &str| -> Result<u8, AsmError> {
+        parse_freg(s).ok_or_else(|| err(line, format!("无效的浮点寄存器 '{}'", s)))
+    };
+    let imm = |s: &str| -> Result<i64, AsmError> {
+        parse_imm(s).ok_or_else(|| err(line, format!("无效的立即数 '{}'", s)))
+    };
+    let imm12 = |s: &str| -> Result<i32, AsmError> {
+        let v = imm(s)?;
+        if (-2048..=2047).contains(&v) {
+            Ok(v as i32)
+        } else {
+            Err(err(line, format!("立即数 {} 超出 12 位范围", v)))
+        }
+    };
+    let mem = |s: &str| -> Result<(i32, u8), AsmError> {
+        let (off, reg_text) = parse_mem(s)
+            .ok_or_else(|| err(line, format!("无效的内存操作数 '{}'", s)))?;
+        if !(-2048..=2047).contains(&off) {
+            return Err(err(line, format!("偏移 {} 超出 12 位范围", off)));
+        }
+        Ok((off as i32, reg(reg_text)?))
+    };
+    // 分支/跳转目标：标签或相对字节偏移
+    let target = |s: &str| -> Result<i32, AsmError> {
+        if let Some(&dest) = labels.get(s) {
+            Ok(dest.wrapping_sub(item.addr) as i32)
+        } else {
+            parse_imm(s)
+                .map(|v| v as i32)
+                .ok_or_else(|| err(line, format!("未定义的标签 '{}'", s)))
+        }
+    };
+    let branch_target = |s: &str| -> Result<i32, AsmError> {
+        let off = target(s)?;
+        if off % 2 != 0 || !(-4096..=4094).contains(&off) {
+            return Err(err(line, format!("分支偏移 {} 非法", off)));
+        }
+        Ok(off)
+    };
+    let jal_target = |s: &str| -> Result<i32, AsmError> {
+        let off = target(s)?;
+        if off % 2 != 0 || !(-(1 << 20)..(1 << 20)).contains(&off) {
+            return Err(err(line, format!("跳转偏移 {} 非法", off)));
+        }
+        Ok(off)
+    };
+
+    // RV32I/M 的 R 型指令：(funct7, funct3)
+    let r_op: Option<(u32, u32)> = match item.mnemonic {
+        "add" => Some((0x00, 0b000)),
+        "sub" => Some((0x20, 0b000)),
+        "sll" => Some((0x00, 0b001)),
+        "slt" => Some((0x00, 0b010)),
+        "sltu" => Some((0x00, 0b011)),
+        "xor" => Some((0x00, 0b100)),
+        "srl" => Some((0x00, 0b101)),
+        "sra" => Some((0x20, 0b101)),
+        "or" => Some((0x00, 0b110)),
+        "and" => Some((0x00, 0b111)),
+        "mul" => Some((0x01, 0b000)),
+        "mulh" => Some((0x01, 0b001)),
+        "mulhsu" => Some((0x01, 0b010)),
+        "mulhu" => Some((0x01, 0b011)),
+        "div" => Some((0x01, 0b100)),
+        "divu" => Some((0x01, 0b101)),
+        "rem" => Some((0x01, 0b110)),
+        "remu" => Some((0x01, 0b111)),
+        _ => None,
+    };
+    if let Some((funct7, funct3)) = r_op {
+        expect(3)?;
+        out.push(r_type(funct7, reg(ops[2])?, reg(ops[1])?, funct3, reg(ops[0])?, 0x33));
+        return Ok(());
+    }
+
+    // I 型算术指令
+    let i_op: Option<u32> = match item.mnemonic {
+        "addi" => Some(0b000),
+        "slti" => Some(0b010),
+        "sltiu" => Some(0b011),
+        "xori" => Some(0b100),
+        "ori" => Some(0b110),
+        "andi" => Some(0b111),
+        _ => None,
+    };
+    if let Some(funct3) = i_op {
+        expect(3)?;
+        out.push(i_type(imm12(ops[2])?, reg(ops[1])?, funct3, reg(ops[0])?, 0x13));
+        return Ok(());
+    }
+
+    // 移位立即数
+    let shift_op: Option<(u32, u32)> = match item.mnemonic {
+        "slli" => Some((0x00, 0b001)),
+        "srli" => Some((0x00, 0b101)),
+        "srai" => Some((0x20, 0b101)),
+        _ => None,
+    };
+    if let Some((funct7, funct3)) = shift_op {
+        expect(3)?;
+        let shamt = imm(ops[2])?;
+        if !(0..32).contains(&shamt) {
+            return Err(err(line, format!("移位量 {} 超出范围", shamt)));
+        }
+        out.push(r_type(funct7, shamt as u8, reg(ops[1])?, funct3, reg(ops[0])?, 0x13));
+        return Ok(());
+    }
+
+    // 加载
+    let load_op: Option<u32> = match item.mnemonic {
+        "lb" => Some(0b000),
+        "lh" => Some(0b001),
+        "lw" => Some(0b010),
+        "lbu" => Some(0b100),
+        "lhu" => Some(0b101),
+        _ => None,
+    };
+    if let Some(funct3) = load_op {
+        expect(2)?;
+        let (off, rs1) = mem(ops[1])?;
+        out.push(i_type(off, rs1, funct3, reg(ops[0])?, 0x03));
+        return Ok(());
+    }
+
+    // 存储
+    let store_op: Option<u32> = match item.mnemonic {
+        "sb" => Some(0b000),
+        "sh" => Some(0b001),
+        "sw" => Some(0b010),
+        _ => None,
+    };
+    if let Some(funct3) = store_op {
+        expect(2)?;
+        let (off, rs1) = mem(ops[1])?;
+        out.push(s_type(off, reg(ops[0])?, rs1, funct3, 0x23));
+        return Ok(());
+    }
+
+    // 条件分支
+    let branch_op: Option<u32> = match item.mnemonic {
+        "beq" => Some(0b000),
+        "bne" => Some(0b001),
+        "blt" => Some(0b100),
+        "bge" => Some(0b101),
+        "bltu" => Some(0b110),
+        "bgeu" => Some(0b111),
+        _ => None,
+    };
+    if let Some(funct3) = branch_op {
+        expect(3)?;
+        out.push(b_type(branch_target(ops[2])?, reg(ops[1])?, reg(ops[0])?, funct3));
+        return Ok(());
+    }
+
+    // 分支伪指令（与零比较）：(基础 funct3, rs 是否作为 rs2)
+    let branch_zero: Option<(u32, bool)> = match item.mnemonic {
+        "beqz" => Some((0b000, false)),
+        "bnez" => Some((0b001, false)),
+        "bltz" => Some((0b100, false)),
+        "bgez" => Some((0b101, false)),
+        "bgtz" => Some((0b100, true)),
+        "blez" => Some((0b101, true)),
+        _ => None,
+    };
+    if let Some((funct3, swapped)) = branch_zero {
+        expect(2)?;
+        let rs = reg(ops[0])?;
+        let off = branch_target(ops[1])?;
+        let (rs1, rs2) = if swapped { (0, rs) } else { (rs, 0) };
+        out.push(b_type(off, rs2, rs1, funct3));
+        return Ok(());
+    }
+
+    // F 扩展的 R 型运算：(funct7, funct3/rm, rd 是否整数寄存器)
+    let f_op: Option<(u32, u32, bool)> = match item.mnemonic {
+        "fadd.s" => Some((0x00, RM_DYN, false)),
+        "fsub.s" => Some((0x04, RM_DYN, false)),
+        "fmul.s" => Some((0x08, RM_DYN, false)),
+        "fdiv.s" => Some((0x0C, RM_DYN, false)),
+        "fsgnj.s" => Some((0x10, 0b000, false)),
+        "fsgnjn.s" => Some((0x10, 0b001, false)),
+        "fsgnjx.s" => Some((0x10, 0b010, false)),
+        "fmin.s" => Some((0x14, 0b000, false)),
+        "fmax.s" => Some((0x14, 0b001, false)),
+        "fle.s" => Some((0x50, 0b000, true)),
+        "flt.s" => Some((0x50, 0b001, true)),
+        "feq.s" => Some((0x50, 0b010, true)),
+        _ => None,
+    };
+    if let Some((funct7, funct3, int_rd)) = f_op {
+        expect(3)?;
+        let rd = if int_rd { reg(ops[0])? } else { freg(ops[0])? };
+        out.push(r_type(funct7, freg(ops[2])?, freg(ops[1])?, funct3, rd, 0x53));
+        return Ok(());
+    }
+
+    // F 扩展的单操作数运算：(funct7, rs2 字段, funct3/rm, rd 是否整数, rs1 是否整数)
+    let f_unary: Option<(u32, u8, u32, bool, bool)> = match item.mnemonic {
+        "fsqrt.s" => Some((0x2C, 0, RM_DYN, false, false)),
+        "fcvt.w.s" => Some((0x60, 0, RM_DYN, true, false)),
+        "fcvt.wu.s" => Some((0x60, 1, RM_DYN, true, false)),
+        "fcvt.s.w" => Some((0x68, 0, RM_DYN, false, true)),
+        "fcvt.s.wu" => Some((0x68, 1, RM_DYN, false, true)),
+        "fmv.x.w" => Some((0x70, 0, 0b000, true, false)),
+        "fmv.w.x" => Some((0x78, 0, 0b000, false, true)),
+        _ => None,
+    };
+    if let Some((funct7, rs2, funct3, int_rd, int_rs1)) = f_unary {
+        expect(2)?;
+        let rd = if int_rd { reg(ops[0])? } else { freg(ops[0])? };
+        let rs1 = if int_rs1 { reg(ops[1])? } else { freg(ops[1])? };
+        out.push(r_type(funct7, rs2, rs1, funct3, rd, 0x53));
+        return Ok(());
+    }
+
+    match item.mnemonic {
+        ".word" => {
+            for op in ops {
+                let v = imm(op)?;
+                out.push(v as u32);
+            }
+        }
+        "lui" | "auipc" => {
+            expect(2)?;
+            let opcode = if item.mnemonic == "lui" { 0x37 } else { 0x17 };
+            let v = imm(ops[1])?;
+            if !(0..=0xFFFFF).contains(&v) {
+                return Err(err(line, format!("立即数 {} 超出 20 位范围", v)));
+            }
+            out.push(u_type(v as u32, reg(ops[0])?, opcode));
+        }
+        "jal" => {
+            // `jal rd, target` 或伪指令形式 `jal target`（rd = ra）
+            let (rd, tgt) = match ops.len() {
+                1 => (1, ops[0]),
+                2 => (reg(ops[0])?, ops[1]),
+                n => return Err(err(line, format!("jal 需要 1-2 个操作数，实际 {}", n))),
+            };
+            out.push(j_type(jal_target(tgt)?, rd));
+        }
+        "jalr" => {
+            // `jalr rd, off(rs1)`、`jalr rd, rs1, off` 或 `jalr rs1`
+            let (rd, rs1, off) = match ops.len() {
+                1 => (1, reg(ops[0])?, 0),
+                2 => {
+                    let (off, rs1) = mem(ops[1])?;
+                    (reg(ops[0])?, rs1, off)
+                }
+                3 => (reg(ops[0])?, reg(ops[1])?, imm12(ops[2])?),
+                n => return Err(err(line, format!("jalr 需要 1-3 个操作数，实际 {}", n))),
+            };
+            out.push(i_type(off, rs1, 0b000, rd, 0x67));
+        }
+        "flw" | "fsw" => {
+            expect(2)?;
+            let (off, rs1) = mem(ops[1])?;
+            let f = freg(ops[0])?;
+            if item.mnemonic == "flw" {
+                out.push(i_type(off, rs1, 0b010, f, 0x07));
+            } else {
+                out.push(s_type(off, f, rs1, 0b010, 0x27));
+            }
+        }
+        "ecall" => {
+            expect(0)?;
+            out.push(0x00000073);
+        }
+        "ebreak" => {
+            expect(0)?;
+            out.push(0x00100073);
+        }
+        "fence" => {
+            expect(0)?;
+            out.push(0x0FF0000F); // fence iorw, iorw
+        }
+        // ===== 伪指令 =====
+        "nop" => {
+            expect(0)?;
+            out.push(i_type(0, 0, 0b000, 0, 0x13));
+        }
+        "li" => {
+            expect(2)?;
+            let rd = reg(ops[0])?;
+            let v = imm(ops[1])?;
+            if !(-(1i64 << 31)..(1i64 << 32)).contains(&v) {
+                return Err(err(line, format!("立即数 {} 超出 32 位范围", v)));
+            }
+            let v = v as i32;
+            if (-2048..=2047).contains(&v) {
+                out.push(i_type(v, 0, 0b000, rd, 0x13));
+            } else {
+                // 低 12 位按符号扩展参与 addi，高 20 位相应补偿
+                let lo = (v << 20) >> 20;
+                let hi = (v as u32).wrapping_sub(lo as u32) >> 12;
+                out.push(u_type(hi, rd, 0x37));
+                if lo != 0 {
+                    out.push(i_type(lo, rd, 0b000, rd, 0x13));
+                }
+            }
+        }
+        "mv" => {
+            expect(2)?;
+            out.push(i_type(0, reg(ops[1])?, 0b000, reg(ops[0])?, 0x13));
+        }
+        "not" => {
+            expect(2)?;
+            out.push(i_type(-1, reg(ops[1])?, 0b100, reg(ops[0])?, 0x13));
+        }
+        "neg" => {
+            expect(2)?;
+            out.push(r_type(0x20, reg(ops[1])?, 0, 0b000, reg(ops[0])?, 0x33));
+        }
+        "seqz" => {
+            expect(2)?;
+            out.push(i_type(1, reg(ops[1])?, 0b011, reg(ops[0])?, 0x13));
+        }
+        "snez" => {
+            expect(2)?;
+            out.push(r_type(0x00, reg(ops[1])?, 0, 0b011, reg(ops[0])?, 0x33));
+        }
+        "j" => {
+            expect(1)?;
+            out.push(j_type(jal_target(ops[0])?, 0));
+        }
+        "jr" => {
+            expect(1)?;
+            out.push(i_type(0, reg(ops[0])?, 0b000, 0, 0x67));
+        }
+        "ret" => {
+            expect(0)?;
+            out.push(i_type(0, 1, 0b000, 0, 0x67));
+        }
+        "call" => {
+            expect(1)?;
+            // auipc ra, hi; jalr ra, lo(ra)：低 12 位符号扩展需要补偿
+            let off = target(ops[0])?;
+            let lo = (off << 20) >> 20;
+            let hi = (off as u32).wrapping_sub(lo as u32) >> 12;
+            out.push(u_type(hi, 1, 0x17));
+            out.push(i_type(lo, 1, 0b000, 1, 0x67));
+        }
+        other => return Err(err(line, format!("不支持的指令 '{}'", other))),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CpuCore;
+    use crate::isa::{decode, RvInstr};
+    use crate::memory::{FlatMemory, Memory};
+
+    #[test]
+    fn test_assemble_matches_known_encodings() {
+        let words = assemble(
+            "
+            addi a0, zero, 42
+            add  x3, x1, x2
+            lw   ra, 4(sp)
+            sw   x1, 8(x2)
+            lui  x1, 0x12345
+            ecall
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            words,
+            vec![0x02A00513, 0x002081B3, 0x00412083, 0x00112423, 0x123450B7, 0x00000073]
+        );
+    }
+
+    #[test]
+    fn test_assemble_labels_and_branches() {
+        let words = assemble(
+            "
+            li   a1, 3
+        loop:
+            addi a1, a1, -1
+            bnez a1, loop
+            beq  zero, zero, done
+            nop
+        done:
+            j    loop
+            ",
+        )
+        .unwrap();
+
+        // bnez 向后 4 字节
+        assert_eq!(
+            decode(words[2]).instr,
+            RvInstr::Bne { rs1: 11, rs2: 0, offset: -4 }
+        );
+        // beq 跳过 nop（向前 8 字节）
+        assert_eq!(
+            decode(words[3]).instr,
+            RvInstr::Beq { rs1: 0, rs2: 0, offset: 8 }
+        );
+        // j 回到 loop（向后 16 字节）
+        assert_eq!(decode(words[5]).instr, RvInstr::Jal { rd: 0, offset: -16 });
+    }
+
+    #[test]
+    fn test_assemble_li_expansion() {
+        // 小立即数：单条 addi
+        assert_eq!(assemble("li a0, -1").unwrap(), vec![0xFFF00513]);
+        // 低 12 位为零：单条 lui
+        assert_eq!(assemble("li a0, 0x12345000").unwrap(), vec![0x12345537]);
+        // 一般情况：lui + addi，低 12 位符号扩展需要补偿
+        let words = assemble("li a0, 0xDEADBEEF").unwrap();
+        assert_eq!(words.len(), 2);
+        assert_eq!(decode(words[0]).instr, RvInstr::Lui { rd: 10, imm: 0xDEADC000_u32 as i32 });
+        assert_eq!(
+            decode(words[1]).instr,
+            RvInstr::Addi { rd: 10, rs1: 10, imm: -0x111 }
+        );
+    }
+
+    #[test]
+    fn test_assembled_program_executes() {
+        // 1 + 2 + ... + 5 = 15
+        let words = assemble(
+            "
+            li   a0, 0
+            li   a1, 5
+        loop:
+            add  a0, a0, a1
+            addi a1, a1, -1
+            bnez a1, loop
+            ",
+        )
+        .unwrap();
+
+        let mut cpu = CpuCore::new(0);
+        let mut mem = FlatMemory::new(4096, 0);
+        for (i, word) in words.iter().enumerate() {
+            mem.store32(i as u32 * 4, *word).unwrap();
+        }
+        for _ in 0..20 {
+            cpu.step(&mut mem);
+        }
+        assert_eq!(cpu.read_reg(10), 15);
+    }
+
+    #[test]
+    fn test_assemble_float_instrs() {
+        let words = assemble(
+            "
+            fmv.w.x fa0, a0
+            fadd.s  fa1, fa0, fa0
+            feq.s   a1, fa1, fa1
+            flw     ft0, 16(sp)
+            ",
+        )
+        .unwrap();
+        assert_eq!(decode_with_f(words[0]), RvInstr::FmvWX { frd: 10, rs1: 10 });
+        assert_eq!(
+            decode_with_f(words[1]),
+            RvInstr::FaddS { frd: 11, frs1: 10, frs2: 10, rm: 0b111 }
+        );
+        assert_eq!(
+            decode_with_f(words[2]),
+            RvInstr::FeqS { rd: 11, frs1: 11, frs2: 11 }
+        );
+        assert_eq!(
+            decode_with_f(words[3]),
+            RvInstr::Flw { frd: 0, rs1: 2, offset: 16 }
+        );
+    }
+
+    fn decode_with_f(raw: u32) -> RvInstr {
+        use crate::isa::InstrDecoder;
+        crate::isa::RV32F_DECODER
+            .decode(raw)
+            .expect("F 解码器应识别")
+            .instr
+    }
+
+    #[test]
+    fn test_assemble_call_expansion() {
+        let words = assemble(
+            "
+            call func
+            nop
+        func:
+            ret
+            ",
+        )
+        .unwrap();
+
+        // call 占两个字，func 位于 12（目标 = 0 + 12）
+        assert_eq!(words.len(), 4);
+        assert_eq!(decode(words[0]).instr, RvInstr::Auipc { rd: 1, imm: 0 });
+        assert_eq!(
+            decode(words[1]).instr,
+            RvInstr::Jalr { rd: 1, rs1: 1, offset: 12 }
+        );
+    }
+
+    #[test]
+    fn test_assemble_strict_rejects_pseudo() {
+        // 严格模式拒绝伪指令，规范形式不受影响
+        let e = assemble_with("addi a0, zero, 1\nnop", 0, false).unwrap_err();
+        assert_eq!(e.line, 2);
+        assert!(assemble_with("li a0, 1", 0, false).is_err());
+        assert!(assemble_with("call 8", 0, false).is_err());
+        assert_eq!(
+            assemble_with("addi a0, zero, 42", 0, false).unwrap(),
+            vec![0x02A00513]
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors_carry_line_numbers() {
+        // 未知指令
+        let e = assemble("nop\nfrobnicate a0").unwrap_err();
+        assert_eq!(e.line, 2);
+
+        // 未定义标签
+        let e = assemble("beq a0, a1, nowhere").unwrap_err();
+        assert_eq!(e.line, 1);
+
+        // 立即数超出范围
+        assert!(assemble("addi a0, a0, 4096").is_err());
+        // 重复标签
+        assert!(assemble("a:\na:").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! 批量 ISA 测试命令行入口
+//!
+//! ```text
+//! isa-test [--suite PREFIX]... [--filter PATTERN] [--jobs N]
+//!          [--root DIR] [--max-instructions N]
+//!          [--junit PATH] [--json PATH]
+//! ```
+//!
+//! 不带 `--suite` 时运行全部默认套件（rv32ui/um/uf/ua）；
+//! `--junit`/`--json` 把汇总写到文件供 CI 收集。
+
+use std::path::PathBuf;
+
+use allude_sim::sim_env::test_runner::{self, RunnerConfig};
+
+fn main() {
+    let mut config = RunnerConfig::default();
+    let mut suites: Vec<String> = Vec::new();
+    let mut junit_path: Option<PathBuf> = None;
+    let mut json_path: Option<PathBuf> = None;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--suite" => {
+                i += 1;
+                if let Some(suite) = args.get(i) {
+                    suites.push(suite.clone());
+                }
+            }
+            "--filter" => {
+                i += 1;
+                config.filter = args.get(i).cloned();
+            }
+            "--jobs" => {
+                i += 1;
+                config.jobs = parse_or_exit(args.get(i), "--jobs");
+            }
+            "--root" => {
+                i += 1;
+                if let Some(root) = args.get(i) {
+                    config.root = PathBuf::from(root);
+                }
+            }
+            "--max-instructions" => {
+                i += 1;
+                config.max_instructions = parse_or_exit(args.get(i), "--max-instructions");
+            }
+            "--junit" => {
+                i += 1;
+                junit_path = args.get(i).map(PathBuf::from);
+            }
+            "--json" => {
+                i += 1;
+                json_path = args.get(i).map(PathBuf::from);
+            }
+            other => {
+                eprintln!("unknown argument: {other}");
+                std::process::exit(2);
+            }
+        }
+        i += 1;
+    }
+    if !suites.is_empty() {
+        config.suites = suites;
+    }
+
+    let report = match test_runner::run(&config) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    for case in &report.cases {
+        match case.failure_message() {
+            None => println!(
+                "[PASS] {} ({} instr, {:?})",
+                case.name, case.executed, case.duration
+            ),
+            Some(msg) => println!("[FAIL] {}: {}", case.name, msg),
+        }
+    }
+    println!(
+        "\nSummary: {} passed / {} failed in {:?}",
+        report.passed(),
+        report.failed(),
+        report.wall_time
+    );
+
+    if let Some(path) = junit_path {
+        write_or_exit(&path, &report.to_junit_xml());
+    }
+    if let Some(path) = json_path {
+        write_or_exit(&path, &report.to_json());
+    }
+
+    if !report.all_passed() {
+        std::process::exit(1);
+    }
+}
+
+fn parse_or_exit<T: std::str::FromStr>(arg: Option<&String>, flag: &str) -> T {
+    match arg.and_then(|s| s.parse().ok()) {
+        Some(value) => value,
+        None => {
+            eprintln!("{flag} expects a number");
+            std::process::exit(2);
+        }
+    }
+}
+
+fn write_or_exit(path: &std::path::Path, content: &str) {
+    if let Err(err) = std::fs::write(path, content) {
+        eprintln!("failed to write {}: {err}", path.display());
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,318 @@
+//! 指令/数据 cache 仿真层
+//!
+//! [`CacheMemory`] 把任意 [`Memory`] 包装成带 cache 的视图：
+//! 大小/相联度/行大小/替换策略可配置，逐次访问统计命中/缺失，
+//! 并按命中/缺失延迟累计周期数，可与 [`crate::timing`] 的
+//! 指令周期合并成总开销。
+//!
+//! 这是 tag-only 仿真：数据始终直接来自被包装的内存（写直达、
+//! 写分配），cache 只维护 tag 状态用于统计，因此包装前后的功能
+//! 行为完全一致。指令侧和数据侧各包一层即可分别研究 I-cache 和
+//! D-cache 行为。
+//!
+//! `Memory` 的 load 族接口是 `&self`，tag 状态用 `RefCell` 做
+//! 内部可变。
+
+use std::cell::RefCell;
+
+use crate::memory::{MemResult, Memory};
+
+/// 替换策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplacementPolicy {
+    /// 最近最少使用：命中也更新时间戳
+    Lru,
+    /// 先进先出：只在填充时记录时间戳
+    Fifo,
+}
+
+/// cache 几何与延迟配置
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// 总容量（字节），必须是 2 的幂
+    pub size: usize,
+    /// 相联度（路数）
+    pub associativity: usize,
+    /// 行大小（字节），必须是 2 的幂
+    pub line_size: usize,
+    /// 替换策略
+    pub policy: ReplacementPolicy,
+    /// 命中延迟（周期）
+    pub hit_latency: u64,
+    /// 缺失延迟（周期，含填充）
+    pub miss_latency: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            size: 4 * 1024,
+            associativity: 2,
+            line_size: 32,
+            policy: ReplacementPolicy::Lru,
+            hit_latency: 1,
+            miss_latency: 20,
+        }
+    }
+}
+
+/// 命中/缺失统计
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    /// 命中次数
+    pub hits: u64,
+    /// 缺失次数
+    pub misses: u64,
+    /// 缺失中需要逐出有效行的次数
+    pub evictions: u64,
+    /// 按配置延迟累计的周期数
+    pub latency_cycles: u64,
+}
+
+impl CacheStats {
+    /// 命中率（没有访问过时为 None）
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 / total as f64)
+    }
+}
+
+/// 一行的 tag 状态
+#[derive(Debug, Clone, Copy)]
+struct Line {
+    valid: bool,
+    tag: u32,
+    /// LRU 下为最近使用时间，FIFO 下为填充时间
+    stamp: u64,
+}
+
+/// tag 阵列与统计（RefCell 内的可变部分）
+struct TagState {
+    /// `num_sets * associativity` 行，按组连续存放
+    lines: Vec<Line>,
+    /// 单调递增的访问序号，用作时间戳
+    tick: u64,
+    stats: CacheStats,
+}
+
+/// 带 cache 统计的内存包装器
+pub struct CacheMemory<M: Memory> {
+    inner: M,
+    config: CacheConfig,
+    num_sets: usize,
+    state: RefCell<TagState>,
+}
+
+impl<M: Memory> CacheMemory<M> {
+    /// 包装一个内存；配置非法（非 2 的幂、容量装不下一组）时 panic
+    pub fn new(inner: M, config: CacheConfig) -> Self {
+        assert!(
+            config.line_size.is_power_of_two(),
+            "line_size must be a power of two"
+        );
+        assert!(config.size.is_power_of_two(), "size must be a power of two");
+        assert!(config.associativity >= 1, "associativity must be >= 1");
+        let set_bytes = config.line_size * config.associativity;
+        assert!(
+            config.size >= set_bytes && config.size.is_multiple_of(set_bytes),
+            "size must hold a whole number of sets"
+        );
+        let num_sets = config.size / set_bytes;
+        let lines = vec![
+            Line { valid: false, tag: 0, stamp: 0 };
+            num_sets * config.associativity
+        ];
+        Self {
+            inner,
+            config,
+            num_sets,
+            state: RefCell::new(TagState {
+                lines,
+                tick: 0,
+                stats: CacheStats::default(),
+            }),
+        }
+    }
+
+    /// 当前统计快照
+    pub fn stats(&self) -> CacheStats {
+        self.state.borrow().stats
+    }
+
+    /// 清零统计（tag 状态保留，用于跳过预热段）
+    pub fn reset_stats(&self) {
+        self.state.borrow_mut().stats = CacheStats::default();
+    }
+
+    /// 被包装的内存
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// 拆掉包装，取回内存
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+
+    /// 在 tag 阵列上模拟一次访问，更新统计
+    fn touch(&self, addr: u32) {
+        let line_addr = addr / self.config.line_size as u32;
+        let set = (line_addr as usize) % self.num_sets;
+        let tag = line_addr / self.num_sets as u32;
+
+        let mut state = self.state.borrow_mut();
+        let TagState { lines, tick, stats } = &mut *state;
+        *tick += 1;
+        let tick = *tick;
+        let ways = self.config.associativity;
+        let base = set * ways;
+        let set_lines = &mut lines[base..base + ways];
+
+        if let Some(line) = set_lines.iter_mut().find(|l| l.valid && l.tag == tag) {
+            if self.config.policy == ReplacementPolicy::Lru {
+                line.stamp = tick;
+            }
+            stats.hits += 1;
+            stats.latency_cycles += self.config.hit_latency;
+            return;
+        }
+
+        // 缺失：优先填充无效行，否则按策略逐出时间戳最小的行
+        let victim = set_lines
+            .iter_mut()
+            .min_by_key(|l| (l.valid, l.stamp))
+            .expect("associativity >= 1");
+        if victim.valid {
+            stats.evictions += 1;
+        }
+        victim.valid = true;
+        victim.tag = tag;
+        victim.stamp = tick;
+        stats.misses += 1;
+        stats.latency_cycles += self.config.miss_latency;
+    }
+}
+
+impl<M: Memory> Memory for CacheMemory<M> {
+    fn load8(&self, addr: u32) -> MemResult<u8> {
+        let value = self.inner.load8(addr)?;
+        self.touch(addr);
+        Ok(value)
+    }
+
+    fn load16(&self, addr: u32) -> MemResult<u16> {
+        let value = self.inner.load16(addr)?;
+        self.touch(addr);
+        Ok(value)
+    }
+
+    fn load32(&self, addr: u32) -> MemResult<u32> {
+        let value = self.inner.load32(addr)?;
+        self.touch(addr);
+        Ok(value)
+    }
+
+    fn store8(&mut self, addr: u32, value: u8) -> MemResult<()> {
+        self.inner.store8(addr, value)?;
+        self.touch(addr);
+        Ok(())
+    }
+
+    fn store16(&mut self, addr: u32, value: u16) -> MemResult<()> {
+        self.inner.store16(addr, value)?;
+        self.touch(addr);
+        Ok(())
+    }
+
+    fn store32(&mut self, addr: u32, value: u32) -> MemResult<()> {
+        self.inner.store32(addr, value)?;
+        self.touch(addr);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::FlatMemory;
+
+    /// 2 路 1 组的微型 cache，便于构造冲突
+    fn tiny_cache(policy: ReplacementPolicy) -> CacheMemory<FlatMemory> {
+        CacheMemory::new(
+            FlatMemory::new(4096, 0),
+            CacheConfig {
+                size: 32,
+                associativity: 2,
+                line_size: 16,
+                policy,
+                hit_latency: 1,
+                miss_latency: 10,
+            },
+        )
+    }
+
+    #[test]
+    fn test_passthrough_semantics() {
+        let mut cache = CacheMemory::new(FlatMemory::new(1024, 0), CacheConfig::default());
+        cache.store32(0x100, 0xDEAD_BEEF).unwrap();
+        assert_eq!(cache.load32(0x100).unwrap(), 0xDEAD_BEEF);
+        // 越界访问原样报错，且不计入统计
+        assert!(cache.load32(0x10000).is_err());
+        assert_eq!(cache.stats().hits + cache.stats().misses, 2);
+    }
+
+    #[test]
+    fn test_hit_miss_counting() {
+        let cache = tiny_cache(ReplacementPolicy::Lru);
+        cache.load32(0x00).unwrap(); // miss（冷）
+        cache.load32(0x04).unwrap(); // hit（同一行）
+        cache.load32(0x08).unwrap(); // hit
+        cache.load32(0x40).unwrap(); // miss（另一行）
+
+        let stats = cache.stats();
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.evictions, 0);
+        assert_eq!(stats.hit_rate(), Some(0.5));
+        assert_eq!(stats.latency_cycles, 2 * 10 + 2);
+    }
+
+    #[test]
+    fn test_lru_keeps_recently_used_line() {
+        let cache = tiny_cache(ReplacementPolicy::Lru);
+        // 单组 2 路：A、B 填满，touch A 后 C 应逐出 B
+        cache.load32(0x000).unwrap(); // A miss
+        cache.load32(0x100).unwrap(); // B miss
+        cache.load32(0x000).unwrap(); // A hit（刷新 LRU）
+        cache.load32(0x200).unwrap(); // C miss，逐出 B
+
+        cache.load32(0x000).unwrap(); // A 仍在
+        assert_eq!(cache.stats().hits, 2);
+        assert_eq!(cache.stats().evictions, 1);
+    }
+
+    #[test]
+    fn test_fifo_evicts_oldest_fill() {
+        let cache = tiny_cache(ReplacementPolicy::Fifo);
+        cache.load32(0x000).unwrap(); // A miss
+        cache.load32(0x100).unwrap(); // B miss
+        cache.load32(0x000).unwrap(); // A hit（FIFO 不刷新时间戳）
+        cache.load32(0x200).unwrap(); // C miss，逐出最早填充的 A
+
+        cache.load32(0x100).unwrap(); // B 仍在
+        assert_eq!(cache.stats().hits, 2);
+        let before = cache.stats().misses;
+        cache.load32(0x000).unwrap(); // A 已被逐出
+        assert_eq!(cache.stats().misses, before + 1);
+    }
+
+    #[test]
+    fn test_reset_stats_keeps_tags() {
+        let cache = tiny_cache(ReplacementPolicy::Lru);
+        cache.load32(0x00).unwrap();
+        cache.reset_stats();
+        assert_eq!(cache.stats().misses, 0);
+        cache.load32(0x00).unwrap(); // 预热后的访问命中
+        assert_eq!(cache.stats().hits, 1);
+    }
+}
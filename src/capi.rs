@@ -0,0 +1,335 @@
+//! SystemC / Verilator 协同仿真用的稳定 C API（`capi` feature）
+//!
+//! 把 [`SimEnv`] 以不透明指针加扁平函数的形式导出，RTL 测试平台
+//! 可以把本 ISS 实例化为黄金参考模型：逐拍 `allude_step` 后比对
+//! 寄存器与访存，或经 MMIO 回调把总线事务转发回 RTL 侧。
+//!
+//! 配套头文件在 `include/allude_sim.h`，与本文件手工保持同步
+//! （依赖全部 vendored，没有引入 cbindgen；接口有意保持小而
+//! 稳定，改动时同步两处即可）。链接方式：
+//!
+//! ```text
+//! cargo build --release --features capi
+//! g++ tb.cpp -I include -L target/release -lallude_sim
+//! ```
+//!
+//! 所有函数以 `allude_` 为前缀；句柄是 `allude_create` 返回的
+//! 不透明指针，线程间不可共享（ISS 本身是单线程模型）。
+
+use std::ffi::c_void;
+
+use crate::cpu::CpuState;
+use crate::devices::MmioAccess;
+use crate::sim_env::{SimConfig, SimEnv};
+
+/// C 侧的不透明仿真器句柄
+///
+/// 只在本模块的导出函数间传递，内部就是一个 [`SimEnv`]。
+pub struct AlludeSim {
+    env: SimEnv,
+}
+
+/// MMIO 回调：`user` 为注册时透传的指针，`addr` 是绝对地址，
+/// `width` 为 1/2/4，`is_write` 非零时 `value` 是写入值；读访问
+/// 的返回值作为读出数据，写访问的返回值被忽略
+pub type AlludeMmioCallback = Option<
+    unsafe extern "C" fn(user: *mut c_void, addr: u32, width: u32, is_write: i32, value: u32) -> u32,
+>;
+
+/// CPU 状态到 C 侧整数码的映射（与快照格式的状态标签一致）
+fn state_code(state: CpuState) -> i32 {
+    match state {
+        CpuState::Running => 0,
+        CpuState::WaitForInterrupt => 1,
+        CpuState::Halted => 2,
+        CpuState::IllegalInstruction(_) => 3,
+        CpuState::WatchpointHit(_) => 4,
+        CpuState::DebugStep => 5,
+        CpuState::Exited(_) => 6,
+    }
+}
+
+/// 创建一个内存为 `[mem_base, mem_base + mem_size)`、入口 PC 为
+/// `entry_pc` 的仿真器实例；失败（如内存参数非法）时返回空指针
+#[unsafe(no_mangle)]
+pub extern "C" fn allude_create(mem_base: u32, mem_size: usize, entry_pc: u32) -> *mut AlludeSim {
+    let config = SimConfig::new()
+        .with_memory("ram", mem_base, mem_size)
+        .with_entry_pc(entry_pc);
+    match SimEnv::from_config(config) {
+        Ok(env) => Box::into_raw(Box::new(AlludeSim { env })),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// 销毁实例；空指针时为空操作
+///
+/// # Safety
+///
+/// `sim` 必须来自 [`allude_create`] 且只销毁一次。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_destroy(sim: *mut AlludeSim) {
+    if !sim.is_null() {
+        drop(unsafe { Box::from_raw(sim) });
+    }
+}
+
+/// 单步一条指令，返回状态码（0 运行中、1 WFI、2 停机、3 非法
+/// 指令、4 监视点、5 单步停、6 已退出；-1 句柄为空）
+///
+/// # Safety
+///
+/// `sim` 必须是有效句柄。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_step(sim: *mut AlludeSim) -> i32 {
+    let Some(sim) = (unsafe { sim.as_mut() }) else {
+        return -1;
+    };
+    state_code(sim.env.step())
+}
+
+/// 执行至多 `max_instructions` 条指令，返回实际执行数
+///
+/// # Safety
+///
+/// `sim` 必须是有效句柄。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_run(sim: *mut AlludeSim, max_instructions: u64) -> u64 {
+    let Some(sim) = (unsafe { sim.as_mut() }) else {
+        return 0;
+    };
+    sim.env.run(max_instructions).0
+}
+
+/// 当前 PC
+///
+/// # Safety
+///
+/// `sim` 必须是有效句柄。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_get_pc(sim: *const AlludeSim) -> u32 {
+    unsafe { sim.as_ref() }.map_or(0, |sim| sim.env.cpu().pc())
+}
+
+/// 设置 PC（用于复位向量或跳过引导段）
+///
+/// # Safety
+///
+/// `sim` 必须是有效句柄。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_set_pc(sim: *mut AlludeSim, pc: u32) {
+    if let Some(sim) = unsafe { sim.as_mut() } {
+        sim.env.cpu_mut().set_pc(pc);
+    }
+}
+
+/// 读整数寄存器 x0..x31（越界或句柄为空时返回 0）
+///
+/// # Safety
+///
+/// `sim` 必须是有效句柄。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_get_reg(sim: *const AlludeSim, reg: u32) -> u32 {
+    match unsafe { sim.as_ref() } {
+        Some(sim) if reg < 32 => sim.env.cpu().read_reg(reg as u8),
+        _ => 0,
+    }
+}
+
+/// 写整数寄存器（x0 与越界写被忽略）
+///
+/// # Safety
+///
+/// `sim` 必须是有效句柄。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_set_reg(sim: *mut AlludeSim, reg: u32, value: u32) {
+    if let Some(sim) = unsafe { sim.as_mut() }
+        && reg < 32
+    {
+        sim.env.cpu_mut().write_reg(reg as u8, value);
+    }
+}
+
+/// 把 `[addr, addr+len)` 的客体内存拷到 `buf`
+///
+/// 返回拷贝的字节数，越界或句柄为空时返回 -1。
+///
+/// # Safety
+///
+/// `sim` 必须是有效句柄，`buf` 起 `len` 字节可写。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_mem_read(
+    sim: *const AlludeSim,
+    addr: u32,
+    buf: *mut u8,
+    len: usize,
+) -> i32 {
+    let Some(sim) = (unsafe { sim.as_ref() }) else {
+        return -1;
+    };
+    match sim.env.memory.read_bytes(addr, len) {
+        Ok(bytes) => {
+            unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf, bytes.len()) };
+            bytes.len() as i32
+        }
+        Err(_) => -1,
+    }
+}
+
+/// 把 `buf` 起 `len` 字节写入客体内存（加载程序镜像用）
+///
+/// 返回写入的字节数，越界或句柄为空时返回 -1。
+///
+/// # Safety
+///
+/// `sim` 必须是有效句柄，`buf` 起 `len` 字节可读。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_mem_write(
+    sim: *mut AlludeSim,
+    addr: u32,
+    buf: *const u8,
+    len: usize,
+) -> i32 {
+    let Some(sim) = (unsafe { sim.as_mut() }) else {
+        return -1;
+    };
+    let data = unsafe { std::slice::from_raw_parts(buf, len) };
+    match sim.env.memory.write_bytes(addr, data) {
+        Ok(()) => len as i32,
+        Err(_) => -1,
+    }
+}
+
+/// 把 `[base, base+size)` 映射到 MMIO 回调，返回设备编号
+///
+/// 客体对该区间的 load/store 不再落到 RAM，而是调用 `callback`
+/// （见 [`AlludeMmioCallback`] 的参数约定），供测试平台把总线
+/// 事务转发给 RTL 或桩模型。`callback` 为空时返回 -1。
+///
+/// # Safety
+///
+/// `sim` 必须是有效句柄；`callback` 与 `user` 在实例销毁前必须
+/// 保持有效。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn allude_register_mmio(
+    sim: *mut AlludeSim,
+    base: u32,
+    size: u32,
+    callback: AlludeMmioCallback,
+    user: *mut c_void,
+) -> i32 {
+    let Some(sim) = (unsafe { sim.as_mut() }) else {
+        return -1;
+    };
+    let Some(callback) = callback else {
+        return -1;
+    };
+    // 裸指针进 'static 闭包：有效性由调用方担保（见 Safety）
+    let user = user as usize;
+    let id = sim.env.map_hook(base..base.wrapping_add(size), move |access: MmioAccess| {
+        let (is_write, value) = match access.write {
+            Some(value) => (1, value),
+            None => (0, 0),
+        };
+        unsafe {
+            callback(
+                user as *mut c_void,
+                base.wrapping_add(access.offset),
+                access.width,
+                is_write,
+                value,
+            )
+        }
+    });
+    id as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn test_create_step_and_registers() {
+        let sim = allude_create(0, 4096, 0);
+        assert!(!sim.is_null());
+
+        // addi x1, x0, 42; j .
+        let program = [0x93u8, 0x00, 0xA0, 0x02, 0x6F, 0x00, 0x00, 0x00];
+        assert_eq!(
+            unsafe { allude_mem_write(sim, 0, program.as_ptr(), program.len()) },
+            8
+        );
+        assert_eq!(unsafe { allude_step(sim) }, 0);
+        assert_eq!(unsafe { allude_get_reg(sim, 1) }, 42);
+        assert_eq!(unsafe { allude_get_pc(sim) }, 4);
+
+        unsafe { allude_set_reg(sim, 5, 0xDEAD_BEEF) };
+        assert_eq!(unsafe { allude_get_reg(sim, 5) }, 0xDEAD_BEEF);
+        assert_eq!(unsafe { allude_get_reg(sim, 0) }, 0, "x0 恒为 0");
+
+        let mut buf = [0u8; 4];
+        assert_eq!(unsafe { allude_mem_read(sim, 4, buf.as_mut_ptr(), 4) }, 4);
+        assert_eq!(u32::from_le_bytes(buf), 0x0000_006F);
+        assert_eq!(
+            unsafe { allude_mem_read(sim, 0x2000, buf.as_mut_ptr(), 4) },
+            -1,
+            "越界读应报错"
+        );
+
+        unsafe { allude_destroy(sim) };
+    }
+
+    #[test]
+    fn test_mmio_callback_sees_bus_transactions() {
+        // 低 32 位记最后一次写的值，高 32 位记访问次数
+        static LOG: AtomicU64 = AtomicU64::new(0);
+
+        unsafe extern "C" fn hook(
+            user: *mut c_void,
+            addr: u32,
+            width: u32,
+            is_write: i32,
+            value: u32,
+        ) -> u32 {
+            assert_eq!(user as usize, 0x1234);
+            assert_eq!(addr, 0x2000_0000);
+            assert_eq!(width, 4);
+            if is_write != 0 {
+                LOG.store((1 << 32) | u64::from(value), Ordering::Relaxed);
+                0
+            } else {
+                0xCAFE_F00D
+            }
+        }
+
+        let sim = allude_create(0, 4096, 0);
+        assert!(!sim.is_null());
+        let id = unsafe {
+            allude_register_mmio(sim, 0x2000_0000, 0x1000, Some(hook), 0x1234 as *mut c_void)
+        };
+        assert!(id >= 0);
+
+        // lui x2, 0x20000; addi x1, x0, 42; sw x1, 0(x2); lw x3, 0(x2); j .
+        let program: [u32; 5] = [
+            0x2000_0137,
+            0x02A0_0093,
+            0x0011_2023,
+            0x0001_2183,
+            0x0000_006F,
+        ];
+        for (i, word) in program.iter().enumerate() {
+            let bytes = word.to_le_bytes();
+            unsafe { allude_mem_write(sim, i as u32 * 4, bytes.as_ptr(), 4) };
+        }
+        unsafe { allude_run(sim, 4) };
+
+        assert_eq!(LOG.load(Ordering::Relaxed), (1 << 32) | 42, "写事务应到回调");
+        assert_eq!(
+            unsafe { allude_get_reg(sim, 3) },
+            0xCAFE_F00D,
+            "读事务应取回调返回值"
+        );
+        unsafe { allude_destroy(sim) };
+    }
+}
@@ -3,21 +3,27 @@
 //! 本模块定义了单线程 RV32I CPU 核心 `CpuCore`，
 //! 包含寄存器文件、程序计数器以及执行引擎。
 
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
 
-use crate::isa::{self, DecodedInstr, RvInstr, DecoderRegistry};
-use crate::memory::{Memory, MemError};
+use crate::isa::{self, CustomFields, DecodedInstr, RvInstr, DecoderRegistry};
+use crate::memory::{Memory, MemError, MemResult};
+use crate::stats::ExecStats;
+use crate::timing::{TimingModel, TimingReport};
+use crate::trace::{TraceRecord, TraceSink, TracingMemory};
 
 mod exu;
 pub mod csr_def;
+mod mmu;
 mod status;
 mod builder;
 pub mod trap;
 
 use status::Status;
-pub use status::{CsrEntry, StatusSnapshot};
-pub use builder::CpuBuilder;
-pub use trap::{TrapCause, PrivilegeMode};
+pub use status::{CsrEntry, CsrHook, FpRegs, FpWidth, RegFile, SnapshotDiff, StatusSnapshot};
+pub use builder::{CpuBuilder, CpuError};
+pub use trap::{TrapCause, PrivilegeMode, Mstatus, Mtvec, Mcause};
 
 /// CPU 执行状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -30,6 +36,76 @@ pub enum CpuState {
     WaitForInterrupt,
     /// CPU 已停机
     Halted,
+    /// 客体经受支持的退出机制（exit 系统调用、semihosting 退出、
+    /// HTIF tohost）报告退出码后停机
+    Exited(i32),
+    /// 数据访问命中监视点（携带命中地址，详情见 `last_watchpoint`）
+    ///
+    /// 触发的指令已经执行完毕；通过 `set_state(CpuState::Running)`
+    /// 可以继续执行
+    WatchpointHit(u32),
+    /// 单步模式下一条指令退休（对应调试规范的 dcsr.step）
+    ///
+    /// 指令已经执行完毕；通过 `set_state(CpuState::Running)` 可以
+    /// 继续单步或恢复连续执行
+    DebugStep,
+}
+
+/// [`CpuCore::steps`] 每步产出的信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepInfo {
+    /// 该条指令的 PC（执行前）
+    pub pc: u32,
+    /// 解码结果（取指失败时为 `Illegal`，随后的 state 会携带
+    /// 对应的访问异常）
+    pub instr: RvInstr,
+    /// 这一步执行后的 CPU 状态
+    pub state: CpuState,
+}
+
+/// [`CpuCore::run_budgeted`] 的结果：执行了多少、为何停止
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunOutcome {
+    /// 实际执行的指令数（保证不超过预算）
+    pub executed: u64,
+    /// 停止原因
+    pub reason: RunStopReason,
+}
+
+/// [`RunOutcome`] 的停止原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStopReason {
+    /// 预算内的指令全部执行完毕，CPU 仍为 Running
+    BudgetExhausted,
+    /// 最后一步触发了 trap（异常或中断）；CPU 已转向处理程序，
+    /// 状态仍是 Running
+    TrapTaken(TrapCause),
+    /// CPU 状态离开 Running（停机、非法指令、WFI 等）
+    StateChange(CpuState),
+}
+
+/// 逐条执行的流式迭代器，见 [`CpuCore::steps`]
+pub struct Steps<'a> {
+    cpu: &'a mut CpuCore,
+    mem: &'a mut dyn Memory,
+}
+
+impl Iterator for Steps<'_> {
+    type Item = StepInfo;
+
+    /// 状态为 `Running` 时执行一条指令并产出信息，否则结束
+    fn next(&mut self) -> Option<StepInfo> {
+        if self.cpu.state != CpuState::Running {
+            return None;
+        }
+        let pc = self.cpu.pc();
+        let instr = match self.mem.load32(pc) {
+            Ok(raw) => self.cpu.decoder.decode(raw).instr,
+            Err(_) => RvInstr::Illegal { raw: 0 },
+        };
+        let state = self.cpu.step(self.mem);
+        Some(StepInfo { pc, instr, state })
+    }
 }
 
 /// 单线程 CPU 核心
@@ -51,6 +127,100 @@ pub struct CpuCore {
     state: CpuState,
     /// 指令解码器
     decoder: Arc<DecoderRegistry>,
+    /// 最近一次 step 中触发的 trap（如果有）
+    ///
+    /// 每次 step 开始时清除，供上层（如 SimEnv 停止条件）查询
+    last_trap: Option<TrapCause>,
+    /// 被监视的 CSR 地址列表（通常很短，线性查找即可）
+    csr_watches: Vec<u16>,
+    /// 最近一次 step 中对被监视 CSR 的写入（如果有）
+    ///
+    /// 与 last_trap 语义一致：每次 step 开始时清除
+    last_csr_write: Option<CsrWriteEvent>,
+    /// 当前正在执行的指令 PC（供 csr_write 记录写入位置）
+    instr_pc: u32,
+    /// 已安装的指令跟踪 sink（如果有）
+    trace_sink: Option<Box<dyn TraceSink>>,
+    /// 当前指令正在收集中的跟踪记录（仅跟踪执行期间为 Some）
+    trace_record: Option<TraceRecord>,
+    /// LR/SC 保留集：LR.W 登记的地址（单 hart，最多一个保留）
+    reservation: Option<u32>,
+    /// 寄存器写入的影子历史（默认关闭，见 `enable_reg_history`）
+    reg_history: Option<RegHistory>,
+    /// 按 (扩展, 助记符) 统计的指令使用计数（默认关闭，见 `enable_instr_usage`）
+    instr_usage: Option<BTreeMap<(&'static str, &'static str), u64>>,
+    /// 指令表覆盖率跟踪器（默认关闭，见 `enable_coverage`）
+    coverage: Option<crate::isa::CoverageTracker>,
+    /// jal/jalr 调用对的影子栈（默认关闭，见 `enable_call_stack`）
+    call_stack: Option<Vec<CallFrame>>,
+    /// 逐指令执行统计（默认关闭，见 `enable_stats`）
+    stats: Option<ExecStats>,
+    /// 基本块/分支剖面器（默认关闭，见 `enable_profile`）
+    profiler: Option<crate::profile::BlockProfiler>,
+    /// 按 PC 直接映射的已解码指令缓存（默认关闭，见 `enable_decode_cache`）
+    decode_cache: Option<DecodeCache>,
+    /// 周期计时模型（默认关闭，见 `enable_timing`）
+    timing: Option<Box<dyn TimingModel>>,
+    /// 计时模型启用后累计的周期数
+    cycles: u64,
+    /// 计时模型启用后退休的指令数（用于计算 IPC）
+    timed_instrs: u64,
+    /// 已注册的内存监视点（见 `add_watchpoint`）
+    watchpoints: Vec<Watchpoint>,
+    /// 最近一次命中的监视点详情
+    ///
+    /// 与 last_trap 语义一致：每次 step 开始时清除
+    last_watchpoint: Option<WatchpointHit>,
+    /// Sdtrig 触发器堆（通过 tselect/tdata1/tdata2 编程）
+    triggers: [Trigger; NUM_TRIGGERS],
+    /// 当前选中的触发器索引（tselect，WARL 钳制到有效范围）
+    tselect: u32,
+    /// 撤销日志：最近 N 步的记录（默认关闭，见 `enable_recording`）
+    undo_log: Option<VecDeque<UndoRecord>>,
+    /// 撤销日志的最大深度
+    undo_depth: usize,
+    /// 按扩展名注册的自定义指令执行器（见 [`CustomExecutor`]）
+    custom_exus: HashMap<&'static str, Box<dyn CustomExecutor>>,
+    /// misa 复位值（由 CpuBuilder 按启用的扩展计算；0 表示未实现 misa）
+    pub(crate) misa_reset: u32,
+    /// 当前被 misa 写入关闭的扩展位（misa_reset 与当前值的差集）
+    ///
+    /// 缓存下来避免每条指令查一次 CSR 表；为 0 时 step 走快路径
+    misa_disabled: u32,
+    /// mcycle/mcycleh 计数器（功能模型按每条执行的指令推进一个周期）
+    mcycle: u64,
+    /// minstret/minstreth 计数器（只计真正退休的指令，异常指令不计）
+    minstret: u64,
+    /// mcountinhibit 的缓存值（与 misa_disabled 同理，避免每步查 CSR 表）
+    countinhibit: u32,
+    /// 本步内客体写过 mcycle / minstret：写入优先于本步的自增
+    /// （特权规范 3.1.11 节），与 last_trap 一样每步开始时清除
+    mcycle_written: bool,
+    minstret_written: bool,
+    /// 事件总线（如果已接线，见 [`CpuCore::set_event_bus`]）
+    event_bus: Option<crate::events::SharedEventBus>,
+    /// 单步模式：每退休一条指令就停在 `CpuState::DebugStep`
+    single_step: bool,
+    /// 大端数据模式：数据访问经字节交换层（取指不受影响）
+    big_endian: bool,
+    /// 非对齐数据访问的处理策略
+    misaligned_policy: MisalignedPolicy,
+    /// 复位向量：`reset()` 后 PC 从这里重新取指
+    reset_vector: u32,
+}
+
+/// 非对齐数据访问的处理策略
+///
+/// 默认把非对齐的半字/字访问拆成字节序列静默仿真；需要验证
+/// OS 的 trap 处理程序（如 rv32ui-p-ma_data）时可切换为按规范
+/// 触发地址非对齐异常。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MisalignedPolicy {
+    /// 拆成字节访问静默仿真（默认）
+    #[default]
+    Emulate,
+    /// 触发 LoadAddressMisaligned / StoreAddressMisaligned 异常
+    TrapMisaligned,
 }
 
 /// 内存访问类别（用于生成对应的 trap）
@@ -61,6 +231,440 @@ pub enum MemAccessType {
     Store,
 }
 
+/// 自定义指令执行器
+///
+/// 与 [`crate::isa::IsaConfig::with_custom_decoder`] 配对：解码器
+/// 产出 [`RvInstr::Custom`] 后，按扩展标识分发给注册的执行器
+/// （见 [`CpuBuilder::with_custom_executor`]），第三方无需 fork
+/// 本 crate 即可原型化 vendor 扩展。
+pub trait CustomExecutor {
+    /// 执行一条属于本扩展的指令
+    ///
+    /// 进入时 PC 已指向下一条指令，改变控制流用 `cpu.set_pc`；
+    /// 访存错误可通过 [`CpuCore::mem_result`] 族转成 trap。
+    /// 返回 `false` 表示编码不被识别，按非法指令处理。
+    fn execute(
+        &mut self,
+        cpu: &mut CpuCore,
+        mem: &mut dyn Memory,
+        opcode: u8,
+        raw: u32,
+        fields: &CustomFields,
+    ) -> bool;
+}
+
+/// 被监视 CSR 的一次写入记录
+///
+/// 由 `CpuCore::watch_csr` 布置监视点后，写入在 `last_csr_write` 中报告
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsrWriteEvent {
+    /// CSR 地址
+    pub addr: u16,
+    /// 执行写入的指令 PC
+    pub pc: u32,
+    /// 写入前的值
+    pub old: u32,
+    /// 写入后的值
+    pub new: u32,
+}
+
+/// 寄存器的一次写入记录（影子历史的条目）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegWriteRecord {
+    /// 执行写入的指令 PC
+    pub pc: u32,
+    /// 写入前的值
+    pub old: u32,
+    /// 写入后的值
+    pub new: u32,
+}
+
+/// 每个寄存器最近 N 次写入的影子历史
+///
+/// 回答调试时最常见的“谁最后写了 a5”一类问题。按寄存器保留
+/// 定长历史，写满后最旧的记录被挤出。
+#[derive(Debug)]
+struct RegHistory {
+    depth: usize,
+    int: Vec<Vec<RegWriteRecord>>,
+    fp: Vec<Vec<RegWriteRecord>>,
+}
+
+impl RegHistory {
+    fn new(depth: usize) -> Self {
+        RegHistory {
+            depth,
+            int: vec![Vec::new(); 32],
+            fp: vec![Vec::new(); 32],
+        }
+    }
+
+    fn record_int(&mut self, reg: u8, rec: RegWriteRecord) {
+        Self::push(&mut self.int[reg as usize & 31], self.depth, rec);
+    }
+
+    fn record_fp(&mut self, reg: u8, rec: RegWriteRecord) {
+        Self::push(&mut self.fp[reg as usize & 31], self.depth, rec);
+    }
+
+    fn push(buf: &mut Vec<RegWriteRecord>, depth: usize, rec: RegWriteRecord) {
+        if buf.len() == depth {
+            buf.remove(0);
+        }
+        buf.push(rec);
+    }
+}
+
+/// 一步执行的撤销记录（记录模式，见 `CpuCore::enable_recording`）
+///
+/// 架构状态（寄存器、CSR、PC、特权级）在 step 开始时整体快照——
+/// 状态本身很小，整体快照比逐项捕获更不容易漏掉 trap 进入等
+/// 旁路写入；内存只记录被覆写的字节，由 `UndoMemory` 在执行
+/// 期间收集。
+#[derive(Debug, Clone)]
+struct UndoRecord {
+    pc: u32,
+    state: CpuState,
+    privilege: PrivilegeMode,
+    status: StatusSnapshot,
+    reservation: Option<u32>,
+    triggers: [Trigger; NUM_TRIGGERS],
+    tselect: u32,
+    /// 被覆写的内存：(物理地址, 字节数, 旧值)，按写入顺序
+    mem_writes: Vec<(u32, u8, u32)>,
+}
+
+/// 包装一个 `Memory`，在每次存储前记下被覆写的旧值
+///
+/// 读取原样转发；Sv32 启用时包在地址翻译层之内，因此记录的是
+/// 物理地址，`step_back` 直接对物理内存回写，无需再次翻译。
+struct UndoMemory<'a> {
+    inner: &'a mut dyn Memory,
+    writes: Vec<(u32, u8, u32)>,
+}
+
+impl<'a> UndoMemory<'a> {
+    fn new(inner: &'a mut dyn Memory) -> Self {
+        Self {
+            inner,
+            writes: Vec::new(),
+        }
+    }
+
+    fn into_writes(self) -> Vec<(u32, u8, u32)> {
+        self.writes
+    }
+}
+
+impl Memory for UndoMemory<'_> {
+    fn load8(&self, addr: u32) -> MemResult<u8> {
+        self.inner.load8(addr)
+    }
+
+    fn load16(&self, addr: u32) -> MemResult<u16> {
+        self.inner.load16(addr)
+    }
+
+    fn load32(&self, addr: u32) -> MemResult<u32> {
+        self.inner.load32(addr)
+    }
+
+    fn store8(&mut self, addr: u32, value: u8) -> MemResult<()> {
+        let old = self.inner.load8(addr);
+        self.inner.store8(addr, value)?;
+        if let Ok(old) = old {
+            self.writes.push((addr, 1, old as u32));
+        }
+        Ok(())
+    }
+
+    fn store16(&mut self, addr: u32, value: u16) -> MemResult<()> {
+        let old = self.inner.load16(addr);
+        self.inner.store16(addr, value)?;
+        if let Ok(old) = old {
+            self.writes.push((addr, 2, old as u32));
+        }
+        Ok(())
+    }
+
+    fn store32(&mut self, addr: u32, value: u32) -> MemResult<()> {
+        let old = self.inner.load32(addr);
+        self.inner.store32(addr, value)?;
+        if let Ok(old) = old {
+            self.writes.push((addr, 4, old));
+        }
+        Ok(())
+    }
+}
+
+/// 触发器模块的触发器数量（tselect 的有效范围为 0..NUM_TRIGGERS）
+pub const NUM_TRIGGERS: usize = 4;
+
+/// Sdtrig 触发器模块中的一个 mcontrol 型触发器
+///
+/// 通过 tselect/tdata1/tdata2 CSR 编程（见 [`crate::cpu::csr_def`]）。
+/// 实现 mcontrol 的地址精确匹配子集：tdata2 为匹配地址，tdata1 的
+/// execute/store/load 位选择匹配的访问类型，命中时在指令执行前
+/// 触发 Breakpoint 异常（timing=before）。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Trigger {
+    /// tdata1 的可写位（execute/store/load），读取时补上类型字段
+    tdata1: u32,
+    /// 匹配地址
+    tdata2: u32,
+}
+
+impl Trigger {
+    /// tdata1 类型字段：2 = mcontrol（地址/数据匹配）
+    pub const TYPE_MCONTROL: u32 = 2 << 28;
+    /// tdata1.load：匹配加载地址
+    pub const LOAD: u32 = 1 << 0;
+    /// tdata1.store：匹配存储地址
+    pub const STORE: u32 = 1 << 1;
+    /// tdata1.execute：匹配取指地址
+    pub const EXECUTE: u32 = 1 << 2;
+    /// tdata1 中本实现支持的可写位（WARL，其余位写入丢弃）
+    const WRITE_MASK: u32 = Self::LOAD | Self::STORE | Self::EXECUTE;
+
+    /// tdata1 的架构视图（类型字段 + 已置的匹配位）
+    pub fn tdata1(&self) -> u32 {
+        Self::TYPE_MCONTROL | self.tdata1
+    }
+
+    /// 匹配地址（tdata2）
+    pub fn address(&self) -> u32 {
+        self.tdata2
+    }
+
+    /// 是否已武装（至少选择了一种访问类型）
+    pub fn is_armed(&self) -> bool {
+        self.tdata1 != 0
+    }
+
+    /// 是否匹配取指
+    pub fn on_execute(&self) -> bool {
+        self.tdata1 & Self::EXECUTE != 0
+    }
+
+    /// 是否匹配加载
+    pub fn on_load(&self) -> bool {
+        self.tdata1 & Self::LOAD != 0
+    }
+
+    /// 是否匹配存储
+    pub fn on_store(&self) -> bool {
+        self.tdata1 & Self::STORE != 0
+    }
+}
+
+/// 监视点关注的访问类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    /// 仅加载
+    Read,
+    /// 仅存储
+    Write,
+    /// 加载或存储
+    ReadWrite,
+}
+
+impl WatchKind {
+    /// 该类型是否覆盖一次给定方向的访问
+    fn matches(self, is_store: bool) -> bool {
+        match self {
+            WatchKind::Read => !is_store,
+            WatchKind::Write => is_store,
+            WatchKind::ReadWrite => true,
+        }
+    }
+}
+
+/// 一个地址区间上的内存监视点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Watchpoint {
+    /// 区间起始（含）
+    start: u32,
+    /// 区间结束（不含）
+    end: u32,
+    /// 关注的访问类型
+    kind: WatchKind,
+}
+
+/// 监视点的一次命中记录
+///
+/// 由 `CpuCore::last_watchpoint` 在 step 返回
+/// [`CpuState::WatchpointHit`] 后报告
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchpointHit {
+    /// 被访问的地址
+    pub addr: u32,
+    /// 触发访问的指令 PC
+    pub pc: u32,
+    /// 是否为存储（false 为加载）
+    pub is_store: bool,
+    /// 访问的值（加载时为读到的值，存储时为写入的值）
+    pub value: u32,
+}
+
+/// 已解码指令的直接映射缓存
+///
+/// 以 `(pc >> 2)` 的低位索引，命中要求 PC 和取到的指令字都一致。
+/// 自修改代码靠两层机制保证正确：执行存储指令时按有效地址剔除
+/// 对应缓存行（见 [`CpuCore::store_span`]），指令字比对再兜底
+/// 捕获绕过 CPU 的写入（宿主直接改内存、DMA 等）；FENCE.I 整体
+/// 清空，作为显式的同步点。解码是 step 中对指令表的线性扫描，
+/// 长时间运行的负载靠它省掉绝大部分扫描。
+struct DecodeCache {
+    entries: Vec<Option<DecodeCacheEntry>>,
+}
+
+#[derive(Clone, Copy)]
+struct DecodeCacheEntry {
+    pc: u32,
+    raw: u32,
+    instr: RvInstr,
+}
+
+/// 缓存项数（2 的幂，覆盖 16KB 代码的工作集）
+const DECODE_CACHE_ENTRIES: usize = 4096;
+
+impl DecodeCache {
+    fn new() -> Self {
+        DecodeCache {
+            entries: vec![None; DECODE_CACHE_ENTRIES],
+        }
+    }
+
+    fn index(pc: u32) -> usize {
+        (pc >> 2) as usize & (DECODE_CACHE_ENTRIES - 1)
+    }
+
+    /// PC 与指令字都一致时返回缓存的解码结果
+    fn lookup(&self, pc: u32, raw: u32) -> Option<RvInstr> {
+        self.entries[Self::index(pc)]
+            .filter(|e| e.pc == pc && e.raw == raw)
+            .map(|e| e.instr)
+    }
+
+    fn insert(&mut self, pc: u32, raw: u32, instr: RvInstr) {
+        self.entries[Self::index(pc)] = Some(DecodeCacheEntry { pc, raw, instr });
+    }
+
+    fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    /// 剔除与 `[addr, addr + len)` 重叠的全部代码字的缓存项
+    fn invalidate(&mut self, addr: u32, len: u32) {
+        let first = addr & !3;
+        let last = addr.wrapping_add(len.saturating_sub(1)) & !3;
+        let mut word = first;
+        loop {
+            let slot = &mut self.entries[Self::index(word)];
+            if slot.is_some_and(|e| e.pc == word) {
+                *slot = None;
+            }
+            if word == last {
+                break;
+            }
+            word = word.wrapping_add(4);
+        }
+    }
+}
+
+/// 影子调用栈中的一帧
+///
+/// 由启用了 [`CpuCore::enable_call_stack`] 的 CPU 在 jal/jalr
+/// 写 ra 时压入、在 `ret`（`jalr x0, 0(ra)`）落回对应返回地址时
+/// 弹出。ra 被客体改写后的返回按启发式向下匹配，匹配不到时保持
+/// 栈不动。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallFrame {
+    /// 被调用函数入口（跳转目标）
+    pub target: u32,
+    /// 调用指令所在 PC
+    pub call_site: u32,
+    /// 期望的返回地址（调用指令 + 4）
+    pub return_addr: u32,
+}
+
+/// 包装一个 `Memory`，检测命中监视点的数据访问
+///
+/// 与 [`TracingMemory`] 同理：load 接口是 `&self`，命中记录放在
+/// `RefCell` 中。只保留本条指令的第一次命中。
+struct WatchMemory<'a> {
+    inner: &'a mut dyn Memory,
+    watchpoints: &'a [Watchpoint],
+    hit: RefCell<Option<(u32, bool, u32)>>,
+}
+
+impl<'a> WatchMemory<'a> {
+    fn new(inner: &'a mut dyn Memory, watchpoints: &'a [Watchpoint]) -> Self {
+        Self {
+            inner,
+            watchpoints,
+            hit: RefCell::new(None),
+        }
+    }
+
+    /// 取出命中记录 (地址, 是否存储, 值)
+    fn into_hit(self) -> Option<(u32, bool, u32)> {
+        self.hit.into_inner()
+    }
+
+    fn check(&self, addr: u32, bytes: u32, value: u32, is_store: bool) {
+        let mut hit = self.hit.borrow_mut();
+        if hit.is_some() {
+            return;
+        }
+        for wp in self.watchpoints {
+            // 访问区间 [addr, addr+bytes) 与监视区间有交集即命中
+            if wp.kind.matches(is_store) && addr < wp.end && addr.wrapping_add(bytes) > wp.start {
+                *hit = Some((addr, is_store, value));
+                return;
+            }
+        }
+    }
+}
+
+impl Memory for WatchMemory<'_> {
+    fn load8(&self, addr: u32) -> MemResult<u8> {
+        let value = self.inner.load8(addr)?;
+        self.check(addr, 1, value as u32, false);
+        Ok(value)
+    }
+
+    fn load16(&self, addr: u32) -> MemResult<u16> {
+        let value = self.inner.load16(addr)?;
+        self.check(addr, 2, value as u32, false);
+        Ok(value)
+    }
+
+    fn load32(&self, addr: u32) -> MemResult<u32> {
+        let value = self.inner.load32(addr)?;
+        self.check(addr, 4, value, false);
+        Ok(value)
+    }
+
+    fn store8(&mut self, addr: u32, value: u8) -> MemResult<()> {
+        self.inner.store8(addr, value)?;
+        self.check(addr, 1, value as u32, true);
+        Ok(())
+    }
+
+    fn store16(&mut self, addr: u32, value: u16) -> MemResult<()> {
+        self.inner.store16(addr, value)?;
+        self.check(addr, 2, value as u32, true);
+        Ok(())
+    }
+
+    fn store32(&mut self, addr: u32, value: u32) -> MemResult<()> {
+        self.inner.store32(addr, value)?;
+        self.check(addr, 4, value, true);
+        Ok(())
+    }
+}
+
 impl CpuCore {
     /// 创建一个新的 CPU 核心
     ///
@@ -84,6 +688,42 @@ impl CpuCore {
             pc: entry_pc,
             state: CpuState::Running,
             decoder,
+            last_trap: None,
+            csr_watches: Vec::new(),
+            last_csr_write: None,
+            instr_pc: entry_pc,
+            trace_sink: None,
+            trace_record: None,
+            reservation: None,
+            reg_history: None,
+            instr_usage: None,
+            coverage: None,
+            call_stack: None,
+            stats: None,
+            profiler: None,
+            decode_cache: None,
+            timing: None,
+            cycles: 0,
+            timed_instrs: 0,
+            watchpoints: Vec::new(),
+            last_watchpoint: None,
+            triggers: [Trigger::default(); NUM_TRIGGERS],
+            tselect: 0,
+            undo_log: None,
+            undo_depth: 0,
+            custom_exus: HashMap::new(),
+            misa_reset: 0,
+            misa_disabled: 0,
+            mcycle: 0,
+            minstret: 0,
+            countinhibit: 0,
+            mcycle_written: false,
+            minstret_written: false,
+            event_bus: None,
+            single_step: false,
+            big_endian: false,
+            misaligned_policy: MisalignedPolicy::default(),
+            reset_vector: entry_pc,
         }
     }
 
@@ -94,6 +734,42 @@ impl CpuCore {
             pc: entry_pc,
             state: CpuState::Running,
             decoder,
+            last_trap: None,
+            csr_watches: Vec::new(),
+            last_csr_write: None,
+            instr_pc: entry_pc,
+            trace_sink: None,
+            trace_record: None,
+            reservation: None,
+            reg_history: None,
+            instr_usage: None,
+            coverage: None,
+            call_stack: None,
+            stats: None,
+            profiler: None,
+            decode_cache: None,
+            timing: None,
+            cycles: 0,
+            timed_instrs: 0,
+            watchpoints: Vec::new(),
+            last_watchpoint: None,
+            triggers: [Trigger::default(); NUM_TRIGGERS],
+            tselect: 0,
+            undo_log: None,
+            undo_depth: 0,
+            custom_exus: HashMap::new(),
+            misa_reset: 0,
+            misa_disabled: 0,
+            mcycle: 0,
+            minstret: 0,
+            countinhibit: 0,
+            mcycle_written: false,
+            minstret_written: false,
+            event_bus: None,
+            single_step: false,
+            big_endian: false,
+            misaligned_policy: MisalignedPolicy::default(),
+            reset_vector: entry_pc,
         }
     }
 
@@ -112,1283 +788,4092 @@ impl CpuCore {
         self.state
     }
 
-    /// 读取 x0 总是返回 0
-    pub fn read_reg(&self, reg: u8) -> u32 {
-        self.status.int_read(reg)
+    /// 获取最近一次 step 中触发的 trap 原因（如果有）
+    ///
+    /// 每次 step 开始时清除，因此只反映刚执行的那条指令
+    pub fn last_trap(&self) -> Option<TrapCause> {
+        self.last_trap
     }
 
-  
-    pub fn write_reg(&mut self, reg: u8, value: u32) {
-        self.status.int_write(reg, value)
+    /// 监视一个 CSR 的写入
+    ///
+    /// 此后对该 CSR 的每次写入（通过 `csr_write`）会记录到 `last_csr_write`
+    pub fn watch_csr(&mut self, addr: u16) {
+        if !self.csr_watches.contains(&addr) {
+            self.csr_watches.push(addr);
+        }
     }
 
-    pub fn read_fp(&self, reg: u8) -> u32 {
-        self.status.fp.as_ref().map(|fp| fp.read(reg)).unwrap_or(0)
+    /// 取消对一个 CSR 的监视
+    pub fn unwatch_csr(&mut self, addr: u16) {
+        self.csr_watches.retain(|&a| a != addr);
     }
 
-    /// 如果 F 扩展未启用，写入会被忽略
-    pub fn write_fp(&mut self, reg: u8, value: u32) {
-        if let Some(fp) = self.status.fp.as_mut() {
-            fp.write(reg, value);
-        }
+    /// 获取最近一次 step 中对被监视 CSR 的写入事件（如果有）
+    ///
+    /// 每次 step 开始时清除，因此只反映刚执行的那条指令
+    pub fn last_csr_write(&self) -> Option<CsrWriteEvent> {
+        self.last_csr_write
     }
 
-    pub fn read_fp_f32(&self, reg: u8) -> f32 {
-        f32::from_bits(self.read_fp(reg))
+    /// 启用寄存器写入的影子历史
+    ///
+    /// 此后每个整数/浮点寄存器保留最近 `depth` 次写入（PC、新旧值），
+    /// 可通过 `reg_history`/`fp_reg_history` 查询。默认关闭，启用后
+    /// 每次写入有少量额外开销。
+    pub fn enable_reg_history(&mut self, depth: usize) {
+        self.reg_history = Some(RegHistory::new(depth.max(1)));
     }
 
-    pub fn write_fp_f32(&mut self, reg: u8, value: f32) {
-        self.write_fp(reg, value.to_bits());
+    /// 整数寄存器的写入历史，最旧在前（未启用时为空）
+    pub fn reg_history(&self, reg: u8) -> &[RegWriteRecord] {
+        self.reg_history
+            .as_ref()
+            .map(|h| h.int[reg as usize & 31].as_slice())
+            .unwrap_or(&[])
     }
 
-    /// 检查是否启用了浮点扩展
-    pub fn has_fp(&self) -> bool {
-        self.status.fp.is_some()
+    /// 浮点寄存器的写入历史，最旧在前（未启用时为空）
+    pub fn fp_reg_history(&self, reg: u8) -> &[RegWriteRecord] {
+        self.reg_history
+            .as_ref()
+            .map(|h| h.fp[reg as usize & 31].as_slice())
+            .unwrap_or(&[])
     }
 
-    // CSR 地址常量 (浮点 CSR)
-    const CSR_FFLAGS: u16 = 0x001;
-    const CSR_FRM: u16 = 0x002;
-    const CSR_FCSR: u16 = 0x003;
+    /// 最近一次写入某整数寄存器的记录（“谁最后写了它”）
+    pub fn last_reg_write(&self, reg: u8) -> Option<RegWriteRecord> {
+        self.reg_history(reg).last().copied()
+    }
 
-    /// CSR 值，如果未注册则返回 0
-    /// 对 FCSR/FFLAGS/FRM 进行关联处理
-    pub fn csr_read(&self, csr: u16) -> u32 {
-        match csr {
-            Self::CSR_FFLAGS => {
-                // FFLAGS = FCSR[4:0]
-                self.status.csr_read(Self::CSR_FCSR) & 0x1F
-            }
-            Self::CSR_FRM => {
-                // FRM = FCSR[7:5]
-                (self.status.csr_read(Self::CSR_FCSR) >> 5) & 0x7
-            }
-            _ => self.status.csr_read(csr),
-        }
+    /// 启用指令使用统计
+    ///
+    /// 此后每条成功取指的指令都按 (扩展, 助记符) 计数，供运行结束后
+    /// 生成指令集使用证明报告。默认关闭。
+    pub fn enable_instr_usage(&mut self) {
+        self.instr_usage = Some(BTreeMap::new());
     }
 
-    /// CSR 写入，对 FCSR/FFLAGS/FRM 进行关联处理
-    pub fn csr_write(&mut self, csr: u16, value: u32) {
-        match csr {
-            Self::CSR_FFLAGS => {
-                // 写 FFLAGS 只更新 FCSR[4:0]
-                let old_fcsr = self.status.csr_read(Self::CSR_FCSR);
-                let new_fcsr = (old_fcsr & !0x1F) | (value & 0x1F);
-                self.status.csr_write(Self::CSR_FCSR, new_fcsr);
-            }
-            Self::CSR_FRM => {
-                // 写 FRM 只更新 FCSR[7:5]
-                let old_fcsr = self.status.csr_read(Self::CSR_FCSR);
-                let new_fcsr = (old_fcsr & !0xE0) | ((value & 0x7) << 5);
-                self.status.csr_write(Self::CSR_FCSR, new_fcsr);
-            }
-            Self::CSR_FCSR => {
-                // FCSR 只有低 8 位有效
-                self.status.csr_write(csr, value & 0xFF);
-            }
-            _ => self.status.csr_write(csr, value),
-        }
+    /// 指令使用计数表，键为 (扩展名, 助记符)（未启用时为 None）
+    pub fn instr_usage(&self) -> Option<&BTreeMap<(&'static str, &'static str), u64>> {
+        self.instr_usage.as_ref()
     }
 
-   
-    pub fn privilege(&self) -> PrivilegeMode {
-        self.status.privilege
+    /// 启用指令表覆盖率统计
+    ///
+    /// 此后每条成功取指的指令字都与 `tracker` 注册的指令表做
+    /// mask/match 匹配并计数，运行结束后可按扩展报告未覆盖的
+    /// 表项，见 [`crate::isa::CoverageTracker`]。默认关闭。
+    pub fn enable_coverage(&mut self, tracker: crate::isa::CoverageTracker) {
+        self.coverage = Some(tracker);
     }
 
-    pub fn set_privilege(&mut self, mode: PrivilegeMode) {
-        self.status.privilege = mode;
+    /// 覆盖率跟踪器（未启用时为 None）
+    pub fn coverage(&self) -> Option<&crate::isa::CoverageTracker> {
+        self.coverage.as_ref()
     }
 
-    /// 设置 CPU 状态
-    pub fn set_state(&mut self, state: CpuState) {
-        self.state = state;
+    /// 启用影子调用栈跟踪
+    ///
+    /// 此后每条写 ra 的 jal/jalr 压入一帧、每次 `ret` 弹出，
+    /// trap 或断点处可据此还原客体调用链（见
+    /// [`crate::sim_env::SimEnv::backtrace`]）。默认关闭。
+    pub fn enable_call_stack(&mut self) {
+        self.call_stack = Some(Vec::new());
     }
 
-    pub fn handle_memory_error(&mut self, err: MemError, access: MemAccessType, fault_pc: u32) {
-        use MemAccessType::*;
-        use TrapCause::*;
+    /// 影子调用栈，最外层调用在前（未启用时为 None）
+    pub fn call_stack(&self) -> Option<&[CallFrame]> {
+        self.call_stack.as_deref()
+    }
 
-        let (addr, cause) = match err {
-            MemError::Unaligned { addr, .. } => (
-                addr,
-                match access {
-                    Fetch => InstructionAddressMisaligned,
-                    Load => LoadAddressMisaligned,
-                    Store => StoreAddressMisaligned,
-                },
-            ),
-            MemError::OutOfRange { addr, .. } => (
-                addr,
-                match access {
-                    Fetch => InstructionAccessFault,
-                    Load => LoadAccessFault,
-                    Store => StoreAccessFault,
-                },
-            ),
-        };
+    /// 启用逐指令执行统计
+    ///
+    /// 此后每条执行的指令都按助记符和类别计数，并跟踪分支
+    /// taken/not-taken 与访存字节数，见 [`crate::stats::ExecStats`]。
+    /// 默认关闭。
+    pub fn enable_stats(&mut self) {
+        self.stats = Some(ExecStats::default());
+    }
 
-        self.take_trap_at(cause, addr, fault_pc);
+    /// 执行统计（未启用时为 None）
+    pub fn stats(&self) -> Option<&ExecStats> {
+        self.stats.as_ref()
     }
 
-    pub fn mem_result<T>(
-        &mut self,
-        result: Result<T, MemError>,
-        access: MemAccessType,
-        fault_pc: u32,
-    ) -> Option<T> {
-        match result {
-            Ok(v) => Some(v),
-            Err(err) => {
-                self.handle_memory_error(err, access, fault_pc);
-                None
-            }
-        }
+    /// 启用基本块执行计数与分支方向剖面
+    ///
+    /// 此后每条退休指令都送入 [`crate::profile::BlockProfiler`]，
+    /// 运行结束后可导出文本或 callgrind 格式的热块报告。默认关闭。
+    pub fn enable_profile(&mut self) {
+        self.profiler = Some(crate::profile::BlockProfiler::new());
     }
 
-    pub fn mem_result_unit(
-        &mut self,
-        result: Result<(), MemError>,
-        access: MemAccessType,
-        fault_pc: u32,
-    ) -> bool {
-        if let Err(err) = result {
-            self.handle_memory_error(err, access, fault_pc);
-            false
-        } else {
-            true
-        }
+    /// 基本块剖面器（未启用时为 None）
+    pub fn profile(&self) -> Option<&crate::profile::BlockProfiler> {
+        self.profiler.as_ref()
     }
 
-    /// 触发 trap（异常或中断）
-    ///
-    /// 执行 RISC-V 特权规范定义的 trap 处理流程：
-    /// 1. 保存当前 PC 到 mepc/sepc
-    /// 2. 保存异常原因到 mcause/scause
-    /// 3. 保存额外信息到 mtval/stval
-    /// 4. 更新 mstatus/sstatus（保存中断使能位等）
-    /// 5. 设置新特权级
-    /// 6. 跳转到 trap handler (mtvec/stvec)
-    ///
-    /// # 参数
+    /// 启用已解码指令缓存
     ///
-    /// * `cause` - trap 原因
-    /// * `tval` - 额外信息（如错误地址、非法指令编码等）
-    pub fn take_trap(&mut self, cause: TrapCause, tval: u32) {
-        // 使用当前 PC 作为异常 PC
-        self.take_trap_at(cause, tval, self.pc);
+    /// 命中时跳过解码器对指令表的线性扫描；PC + 指令字双重比对
+    /// 保证与逐条解码语义一致（自修改代码照常生效），FENCE.I
+    /// 清空缓存。长时间运行的负载建议开启。默认关闭。
+    pub fn enable_decode_cache(&mut self) {
+        self.decode_cache = Some(DecodeCache::new());
     }
 
-    /// 在指定 PC 处触发 trap（用于异常需要指向触发指令的情况）
+    /// 启用周期计时模型
     ///
-    /// # 参数
+    /// 此后每条退休的指令都向模型咨询一次周期数并累计，
+    /// 汇总通过 [`CpuCore::timing_report`] 读取。默认关闭。
+    pub fn enable_timing(&mut self, model: Box<dyn TimingModel>) {
+        self.timing = Some(model);
+        self.cycles = 0;
+        self.timed_instrs = 0;
+    }
+
+    /// 计时统计汇总（未启用计时模型时为 None）
+    pub fn timing_report(&self) -> Option<TimingReport> {
+        self.timing.as_ref().map(|_| TimingReport {
+            instructions: self.timed_instrs,
+            cycles: self.cycles,
+        })
+    }
+
+    /// 在地址区间 `[range.start, range.end)` 上注册监视点
     ///
-    /// * `cause` - trap 原因
-    /// * `tval` - 额外信息（如错误地址、非法指令编码等）
-    /// * `epc` - 异常 PC（保存到 mepc）
-    pub fn take_trap_at(&mut self, cause: TrapCause, tval: u32, epc: u32) {
-        use csr_def::*;
-        use trap::{mstatus, calculate_trap_pc};
+    /// 此后任何落在区间内且方向匹配 `kind` 的数据访问都会让 step
+    /// 返回 [`CpuState::WatchpointHit`]，命中详情（地址、PC、方向、
+    /// 值）记录在 [`CpuCore::last_watchpoint`]。触发的指令已执行
+    /// 完毕；要继续运行，将状态设回 `Running` 即可。取指不会触发
+    /// 监视点。
+    pub fn add_watchpoint(&mut self, range: std::ops::Range<u32>, kind: WatchKind) {
+        self.watchpoints.push(Watchpoint {
+            start: range.start,
+            end: range.end,
+            kind,
+        });
+    }
 
-        // 目前简化实现：所有 trap 都进入 M-mode
-        // TODO: 支持 trap 委托 (medeleg/mideleg)
-        let target_mode = PrivilegeMode::Machine;
+    /// 移除所有监视点
+    pub fn clear_watchpoints(&mut self) {
+        self.watchpoints.clear();
+    }
 
-        // 保存异常 PC 到 mepc
-        // 对于异常：mepc 指向触发异常的指令
-        // 对于中断：mepc 指向下一条要执行的指令
-        self.status.csr_write(CSR_MEPC, epc);
+    /// 当前监视点数量（与 [`Self::truncate_watchpoints`] 配对，
+    /// 供临时监视点成对保存/恢复）
+    pub(crate) fn watchpoint_len(&self) -> usize {
+        self.watchpoints.len()
+    }
 
-        // 保存异常原因到 mcause
-        self.status.csr_write(CSR_MCAUSE, cause.to_cause_value());
+    /// 丢弃第 `len` 个之后添加的监视点，保留更早的
+    pub(crate) fn truncate_watchpoints(&mut self, len: usize) {
+        self.watchpoints.truncate(len);
+    }
 
-        // 保存额外信息到 mtval
-        self.status.csr_write(CSR_MTVAL, tval);
+    /// 最近一次 step 中命中的监视点详情（如果有）
+    pub fn last_watchpoint(&self) -> Option<WatchpointHit> {
+        self.last_watchpoint
+    }
 
-        // 更新 mstatus
-        let mstatus = self.status.csr_read(CSR_MSTATUS);
-        
-        // 保存当前 MIE 到 MPIE
-        let mie = mstatus::read_mie(mstatus);
-        let mut new_mstatus = mstatus;
-        
-        // MPIE = MIE
-        if mie {
-            new_mstatus |= mstatus::MPIE_MASK;
-        } else {
-            new_mstatus &= !mstatus::MPIE_MASK;
+    /// 启用记录模式：每次 step 记下最小撤销信息，供
+    /// [`CpuCore::step_back`] 反向执行
+    ///
+    /// `depth` 为可回退的最大步数（至少为 1），更早的记录被挤出。
+    /// 回答"x5 是怎么变成这个值的"一类问题时，在出错点附近启用后
+    /// 逐步回退即可。默认关闭。
+    pub fn enable_recording(&mut self, depth: usize) {
+        self.undo_depth = depth.max(1);
+        self.undo_log = Some(VecDeque::new());
+    }
+
+    /// 当前可回退的步数
+    pub fn recorded_steps(&self) -> usize {
+        self.undo_log.as_ref().map_or(0, |log| log.len())
+    }
+
+    /// 回退最近执行的一步
+    ///
+    /// 恢复寄存器、CSR、PC、特权级和被覆写的内存字节。没有可用
+    /// 记录（未启用记录模式或日志已空）时返回 false。
+    pub fn step_back(&mut self, mem: &mut dyn Memory) -> bool {
+        let Some(rec) = self.undo_log.as_mut().and_then(|log| log.pop_back()) else {
+            return false;
+        };
+
+        // 逆序回写被覆写的内存字节
+        for &(addr, size, old) in rec.mem_writes.iter().rev() {
+            let _ = match size {
+                1 => mem.store8(addr, old as u8),
+                2 => mem.store16(addr, old as u16),
+                _ => mem.store32(addr, old),
+            };
         }
-        
-        // MIE = 0 (禁用中断)
-        new_mstatus &= !mstatus::MIE_MASK;
-        
-        // MPP = current privilege
-        new_mstatus = mstatus::write_mpp(new_mstatus, self.status.privilege.to_bits());
-        
-        self.status.csr_write(CSR_MSTATUS, new_mstatus);
 
-        // 设置新特权级
-        self.status.privilege = target_mode;
+        for (i, &v) in rec.status.int.iter().enumerate() {
+            self.status.int.write(i as u8, v);
+        }
+        if let (Some(fp_snap), Some(fp)) = (rec.status.fp.as_ref(), self.status.fp.as_mut()) {
+            for (i, &v) in fp_snap.iter().enumerate() {
+                fp.write(i as u8, v);
+            }
+        }
+        if let (Some(vec_snap), Some(vec)) = (rec.status.vec.as_ref(), self.status.vec.as_mut()) {
+            for (i, &lane) in vec_snap.iter().enumerate() {
+                vec.write(i as u8, lane);
+            }
+        }
+        self.status.csr.restore(rec.status.csr);
+        // misa 随 CSR 表一起恢复，禁用位缓存需要同步重算
+        self.misa_disabled = self.misa_reset
+            & csr_def::misa::TOGGLABLE
+            & !self.status.csr_read(csr_def::CSR_MISA);
+        self.status.privilege = rec.privilege;
+        self.pc = rec.pc;
+        self.state = rec.state;
+        self.reservation = rec.reservation;
+        self.triggers = rec.triggers;
+        self.tselect = rec.tselect;
+        self.last_trap = None;
+        self.last_csr_write = None;
+        self.last_watchpoint = None;
+        true
+    }
 
-        // 跳转到 trap handler
-        let mtvec = self.status.csr_read(CSR_MTVEC);
-        self.pc = calculate_trap_pc(mtvec, &cause);
+    /// 有界反向继续：最多回退 `max_steps` 步（受日志深度限制）
+    ///
+    /// 返回实际回退的步数。
+    pub fn reverse_continue(&mut self, mem: &mut dyn Memory, max_steps: u64) -> u64 {
+        let mut undone = 0;
+        while undone < max_steps && self.step_back(mem) {
+            undone += 1;
+        }
+        undone
     }
 
-    /// 获取所有寄存器的快照
-    pub fn regs(&self) -> &[u32; 32] {
-        self.status.int_snapshot()
+    /// Sdtrig 触发器堆（供调试器枚举，索引即 tselect 值）
+    pub fn triggers(&self) -> &[Trigger; NUM_TRIGGERS] {
+        &self.triggers
     }
 
-    /// 获取完整架构状态快照
-    pub fn snapshot(&self) -> StatusSnapshot {
-        self.status.snapshot()
+    /// 查询单个触发器（索引越界返回 None）
+    pub fn trigger(&self, index: usize) -> Option<Trigger> {
+        self.triggers.get(index).copied()
     }
 
-    /// 执行单步指令
-    ///
-    /// # 参数
-    ///
-    /// * `mem` - 内存接口
-    ///
-    /// # 返回
-    ///
-    /// 当前 CPU 状态
-    ///
-    /// # 流程
+    /// 用本核配置的解码器解码一个指令字（不执行，无副作用）
     ///
-    /// 1. 从 PC 处取指
-    /// 2. 解码指令
-    /// 3. 默认 PC += 4
-    /// 4. 执行指令（可能修改 PC）
-    pub fn step(&mut self, mem: &mut dyn Memory) -> CpuState {
-        if self.state != CpuState::Running {
-            return self.state;
-        }
+    /// 供静态分析（如加载镜像的预解码校验）复用运行时的 ISA 配置
+    pub fn decode(&self, raw: u32) -> DecodedInstr {
+        self.decoder.decode(raw)
+    }
 
-        // 保存当前 PC（用于计算返回地址等）
-        let current_pc = self.pc;
+    /// 登记 LR/SC 保留集（LR.W 调用）
+    pub(crate) fn set_reservation(&mut self, addr: u32) {
+        self.reservation = Some(addr);
+    }
 
-        // 取指
-        let instr_word = match mem.load32(current_pc) {
-            Ok(word) => word,
-            Err(err) => {
-                self.handle_memory_error(err, MemAccessType::Fetch, current_pc);
-                return self.state;
-            }
-        };
+    /// 检查保留集是否对给定地址有效，并无条件清除（SC.W 调用）
+    pub(crate) fn check_and_clear_reservation(&mut self, addr: u32) -> bool {
+        let valid = self.reservation == Some(addr);
+        self.reservation = None;
+        valid
+    }
 
-        // 使用配置的解码器解码
-        let decoded = self.decoder.decode(instr_word);
+    /// 安装指令跟踪 sink
+    ///
+    /// 此后每条指令执行完成时向 sink 推送一条 `TraceRecord`。
+    /// 需要事后读回内容的 sink（如环形缓冲区）可通过
+    /// `Rc<RefCell<...>>` 共享安装。
+    pub fn set_trace_sink(&mut self, sink: Box<dyn TraceSink>) {
+        self.trace_sink = Some(sink);
+    }
 
-        // 默认顺序执行
-        self.pc = self.pc.wrapping_add(4);
+    /// 接入事件总线：此后 trap、中断挂起和 CSR 写入都会发布
+    /// 类型化事件（见 [`crate::events`]）
+    pub fn set_event_bus(&mut self, bus: crate::events::SharedEventBus) {
+        self.event_bus = Some(bus);
+    }
 
-        // 执行指令
-        self.execute(mem, decoded, current_pc);
+    /// 向事件总线发布一条事件（未接线时为空操作）
+    fn publish_event(&self, event: crate::events::SimEvent) {
+        if let Some(bus) = &self.event_bus {
+            bus.borrow_mut().publish(&event);
+        }
+    }
 
-        self.state
+    /// 卸载并返回已安装的跟踪 sink（如果有）
+    pub fn take_trace_sink(&mut self) -> Option<Box<dyn TraceSink>> {
+        self.trace_sink.take()
     }
 
-    /// 运行多条指令
-    ///
-    /// # 参数
+    /// 注册自定义指令执行器
     ///
-    /// * `mem` - 内存接口
-    /// * `max_instructions` - 最大执行指令数
+    /// `extension` 必须与解码器产出的 [`RvInstr::Custom`] 的扩展
+    /// 标识一致；同名重复注册时后注册者生效。
+    pub fn register_custom_executor(
+        &mut self,
+        extension: &'static str,
+        executor: Box<dyn CustomExecutor>,
+    ) {
+        self.custom_exus.insert(extension, executor);
+    }
+
+    /// 给单个 CSR 地址安装回调后端
     ///
-    /// # 返回
+    /// 此后对该地址的读/写经由 [`CsrHook`] 转发，可实现读宿主
+    /// 时钟的 time、MMIO 风格的自定义 CSR 等有副作用的寄存器。
+    pub fn install_csr_hook(&mut self, addr: u16, hook: std::rc::Rc<dyn CsrHook>) {
+        self.status.csr.install_hook(addr, hook);
+    }
+
+    /// 开关单步模式（调试规范 dcsr.step 的模拟）
     ///
-    /// 执行的指令数量和最终 CPU 状态
+    /// 开启后每退休一条指令就停在 [`CpuState::DebugStep`]，供 REPL
+    /// 调试器与 GDB stub 实现 stepi；异常/中断转向也算一步。
+    pub fn set_single_step(&mut self, on: bool) {
+        self.single_step = on;
+    }
+
+    /// 单步模式是否开启
+    pub fn single_step(&self) -> bool {
+        self.single_step
+    }
+
+    /// 开关大端数据模式
     ///
-    /// # 停止条件
+    /// 开启后 16/32 位的数据 load/store 按大端字节序进行（对应
+    /// mstatus.MBE 一类的全局大端配置），取指保持小端。通常通过
+    /// [`CpuBuilder::with_big_endian`] 在构建时设定。
+    pub fn set_big_endian(&mut self, on: bool) {
+        self.big_endian = on;
+    }
+
+    /// 大端数据模式是否开启
+    pub fn big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// 设置非对齐数据访问的处理策略
     ///
-    /// - 达到最大指令数
-    /// - 遇到 ECALL/EBREAK
-    /// - 遇到非法指令
-    pub fn run(&mut self, mem: &mut dyn Memory, max_instructions: u64) -> (u64, CpuState) {
-        let mut executed = 0;
-        for _ in 0..max_instructions {
-            let state = self.step(mem);
-            executed += 1;
-            if state != CpuState::Running {
-                return (executed, state);
-            }
-        }
-        (executed, self.state)
+    /// 通常通过 [`CpuBuilder::with_misaligned_policy`] 在构建时设定。
+    pub fn set_misaligned_policy(&mut self, policy: MisalignedPolicy) {
+        self.misaligned_policy = policy;
     }
 
-    /// 执行已解码的指令，委托到分 ISA 的执行单元
-    fn execute(&mut self, mem: &mut dyn Memory, decoded: DecodedInstr, current_pc: u32) {
-        let instr = decoded.instr;
+    /// 当前的非对齐数据访问策略
+    pub fn misaligned_policy(&self) -> MisalignedPolicy {
+        self.misaligned_policy
+    }
 
-        if exu::rv32i::execute(self, mem, instr, current_pc) {
-            return;
-        }
+    /// 设置复位向量（见 [`Self::reset`]）
+    pub fn set_reset_vector(&mut self, addr: u32) {
+        self.reset_vector = addr;
+    }
 
-        if exu::rv32m::execute(self, instr) {
-            return;
+    /// 复位到规范定义的上电状态，不重建解码器
+    ///
+    /// - PC = 复位向量，特权级 = 实现的最高特权级
+    /// - mstatus 的 MIE/MPRV 清零（启用浮点时 FS 回到 Initial）
+    /// - mcause/mepc/mtval 清零，misa 回到复位值
+    /// - mcycle/minstret/mcountinhibit 清零
+    /// - 整数/浮点/向量寄存器与 fcsr 清零，LR/SC 保留集清除
+    ///
+    /// 调试配置（监视点、触发器、单步开关）与统计设施保留。
+    pub fn reset(&mut self) {
+        self.pc = self.reset_vector;
+        self.instr_pc = self.reset_vector;
+        self.state = CpuState::Running;
+        self.status.privilege = if self.status.has_m_mode {
+            PrivilegeMode::Machine
+        } else if self.status.has_s_mode {
+            PrivilegeMode::Supervisor
+        } else {
+            PrivilegeMode::User
+        };
+
+        self.status.int = RegFile::new();
+        if let Some(fp) = self.status.fp.as_mut() {
+            fp.reset();
+        }
+        if let Some(vec) = self.status.vec.as_mut() {
+            *vec = status::VecRegFile::new();
         }
 
-        if exu::rv32f::execute(self, mem, instr, current_pc) {
-            return;
+        if self.status.csr.contains(csr_def::CSR_MSTATUS) {
+            let fs = if self.status.fp.is_some() {
+                trap::mstatus::FS_INITIAL
+            } else {
+                trap::mstatus::FS_OFF
+            };
+            self.status.csr.write(csr_def::CSR_MSTATUS, trap::mstatus::write_fs(0, fs));
+            self.status.csr.write(csr_def::CSR_MCAUSE, 0);
+            self.status.csr.write(csr_def::CSR_MEPC, 0);
+            self.status.csr.write(csr_def::CSR_MTVAL, 0);
+            self.status.csr.write(csr_def::CSR_MISA, self.misa_reset);
+        }
+        if self.status.csr.contains(csr_def::CSR_FCSR) {
+            self.status.csr.write(csr_def::CSR_FCSR, 0);
         }
+        self.misa_disabled = 0;
+        self.mcycle = 0;
+        self.minstret = 0;
+        self.countinhibit = 0;
+
+        self.last_trap = None;
+        self.last_csr_write = None;
+        self.last_watchpoint = None;
+        self.reservation = None;
+        if let Some(log) = self.undo_log.as_mut() {
+            log.clear();
+        }
+    }
 
-        if exu::zicsr::execute(self, instr) {
-            return;
+    /// 读取 x0 总是返回 0
+    pub fn read_reg(&self, reg: u8) -> u32 {
+        self.status.int_read(reg)
+    }
+
+
+    pub fn write_reg(&mut self, reg: u8, value: u32) {
+        // 跟踪执行期间记录寄存器写入（x0 的写入没有架构效果，不记录）
+        if reg != 0
+            && let Some(rec) = self.trace_record.as_mut()
+        {
+            rec.reg_writes.push((reg, value));
         }
+        if reg != 0
+            && let Some(hist) = self.reg_history.as_mut()
+        {
+            hist.record_int(reg, RegWriteRecord {
+                pc: self.instr_pc,
+                old: self.status.int_read(reg),
+                new: value,
+            });
+        }
+        self.status.int_write(reg, value)
+    }
+
+    pub fn read_fp(&self, reg: u8) -> u32 {
+        self.status.fp.as_ref().map(|fp| fp.read(reg)).unwrap_or(0)
+    }
 
-        if exu::priv_instr::execute(self, instr) {
+    /// 如果 F 扩展未启用，写入会被忽略
+    pub fn write_fp(&mut self, reg: u8, value: u32) {
+        let Some(fp) = self.status.fp.as_mut() else {
             return;
+        };
+        if let Some(hist) = self.reg_history.as_mut() {
+            hist.record_fp(reg, RegWriteRecord {
+                pc: self.instr_pc,
+                old: fp.read(reg),
+                new: value,
+            });
         }
+        fp.write(reg, value);
+        self.mark_fs_dirty();
+    }
 
-        match instr {
-            RvInstr::Illegal { raw } => {
-                self.state = CpuState::IllegalInstruction(raw);
-            }
-            RvInstr::Custom { extension, opcode, raw, fields } => {
-                let _ = (extension, opcode, fields);
-                self.state = CpuState::IllegalInstruction(raw);
-            }
-            _ => {
-                self.state = CpuState::IllegalInstruction(decoded.raw);
-            }
-        }
+    pub fn read_fp_f32(&self, reg: u8) -> f32 {
+        f32::from_bits(self.read_fp(reg))
     }
 
-    /// 打印所有存在的状态（用于调试）
-    ///
-    /// 输出内容包括：
-    /// - PC 和 CPU 状态
-    /// - 特权级
-    /// - 整数寄存器 x0-x31
-    /// - 浮点寄存器 f0-f31（如果启用 F 扩展）
-    /// - 向量寄存器 v0-v31（如果启用 V 扩展）
-    /// - 所有已注册的 CSR
-    pub fn dump_regs(&self) {
-        println!("═══════════════════════════════════════════════════════════════════");
-        println!("CPU Status Dump");
-        println!("═══════════════════════════════════════════════════════════════════");
-        
-        // PC 和状态
-        println!("PC: 0x{:08x}  State: {:?}  Privilege: {:?}", 
-                 self.pc, self.state, self.status.privilege);
-        println!();
-        
-        // 整数寄存器
-        println!("─── Integer Registers (x0-x31) ───────────────────────────────────");
-        for i in 0..32 {
-            if i % 4 == 0 {
-                print!("  ");
+    pub fn write_fp_f32(&mut self, reg: u8, value: f32) {
+        self.write_fp(reg, value.to_bits());
+    }
+
+    /// 检查是否启用了浮点扩展
+    pub fn has_fp(&self) -> bool {
+        self.status.fp.is_some()
+    }
+
+    pub fn read_vec(&self, reg: u8) -> [u8; 16] {
+        self.status.vec.as_ref().map(|v| v.read(reg)).unwrap_or([0; 16])
+    }
+
+    /// 如果 V 扩展未启用，写入会被忽略
+    pub fn write_vec(&mut self, reg: u8, value: [u8; 16]) {
+        if let Some(v) = self.status.vec.as_mut() {
+            v.write(reg, value);
+        }
+    }
+
+    /// 检查是否启用了向量扩展
+    pub fn has_vec(&self) -> bool {
+        self.status.vec.is_some()
+    }
+
+    // CSR 地址常量 (浮点 CSR)
+    const CSR_FFLAGS: u16 = 0x001;
+    const CSR_FRM: u16 = 0x002;
+    const CSR_FCSR: u16 = 0x003;
+
+    /// CSR 值，如果未注册则返回 0
+    /// 对 FCSR/FFLAGS/FRM 进行关联处理
+    pub fn csr_read(&self, csr: u16) -> u32 {
+        match csr {
+            Self::CSR_FFLAGS => {
+                // FFLAGS = FCSR[4:0]
+                self.status.csr_read(Self::CSR_FCSR) & 0x1F
             }
-            print!("x{:02}: 0x{:08x}  ", i, self.read_reg(i as u8));
-            if i % 4 == 3 {
-                println!();
+            Self::CSR_FRM => {
+                // FRM = FCSR[7:5]
+                (self.status.csr_read(Self::CSR_FCSR) >> 5) & 0x7
             }
-        }
-        
-        // 浮点寄存器（如果存在）
-        if let Some(fp) = &self.status.fp {
-            println!();
-            println!("─── Floating-Point Registers (f0-f31) ────────────────────────────");
-            for i in 0..32 {
-                if i % 4 == 0 {
-                    print!("  ");
-                }
-                print!("f{:02}: 0x{:08x}  ", i, fp.read(i as u8));
-                if i % 4 == 3 {
-                    println!();
+            // SD 是只读推导位：FS 为 Dirty 时置位（XS 未实现，恒 0）
+            csr_def::CSR_MSTATUS => {
+                let value = self.status.csr_read(csr);
+                if trap::mstatus::read_fs(value) == trap::mstatus::FS_DIRTY {
+                    value | trap::mstatus::SD_MASK
+                } else {
+                    value
                 }
             }
+            // Sdtrig：tdata1/tdata2 是 tselect 选中触发器的窗口
+            csr_def::CSR_TSELECT => self.tselect,
+            csr_def::CSR_TDATA1 => self.triggers[self.tselect as usize].tdata1(),
+            csr_def::CSR_TDATA2 => self.triggers[self.tselect as usize].tdata2,
+            // 计数器：由 64 位字段承载，用户态 cycle/instret 是
+            // M 态计数器的只读别名
+            csr_def::CSR_MCYCLE | csr_def::CSR_CYCLE => self.mcycle as u32,
+            csr_def::CSR_MCYCLEH | csr_def::CSR_CYCLEH => (self.mcycle >> 32) as u32,
+            csr_def::CSR_MINSTRET | csr_def::CSR_INSTRET => self.minstret as u32,
+            csr_def::CSR_MINSTRETH | csr_def::CSR_INSTRETH => (self.minstret >> 32) as u32,
+            csr_def::CSR_MCOUNTINHIBIT => self.countinhibit,
+            _ => self.status.csr_read(csr),
         }
-        
-        // 向量寄存器（如果存在）
-        if let Some(vec) = &self.status.vec {
-            println!();
-            println!("─── Vector Registers (v0-v31, VLEN=128) ──────────────────────────");
-            for i in 0..32 {
-                let v = vec.read(i as u8);
-                print!("  v{:02}: ", i);
-                for b in v.iter().rev() {
-                    print!("{:02x}", b);
-                }
-                println!();
+    }
+
+    /// CSR 写入，对 FCSR/FFLAGS/FRM 进行关联处理
+    ///
+    /// 被监视的 CSR（见 `watch_csr`）会把写入的 PC 和新旧值记录到 `last_csr_write`
+    pub fn csr_write(&mut self, csr: u16, value: u32) {
+        let watched = self.csr_watches.contains(&csr);
+        let observed = watched || self.event_bus.is_some();
+        let old = if observed { self.csr_read(csr) } else { 0 };
+
+        match csr {
+            Self::CSR_FFLAGS => {
+                // 写 FFLAGS 只更新 FCSR[4:0]
+                let old_fcsr = self.status.csr_read(Self::CSR_FCSR);
+                let new_fcsr = (old_fcsr & !0x1F) | (value & 0x1F);
+                self.status.csr_write(Self::CSR_FCSR, new_fcsr);
+                self.mark_fs_dirty();
             }
-        }
-        
-        // CSR 寄存器（按地址排序）
-        let csr_snapshot = self.status.csr.snapshot();
-        if !csr_snapshot.is_empty() {
-            println!();
-            println!("─── Control and Status Registers (CSR) ───────────────────────────");
-            let mut csr_list: Vec<_> = csr_snapshot
-                .iter()
-                .map(|(&addr, &value)| (addr, value))
-                .collect();
-            csr_list.sort_by_key(|(addr, _)| *addr);
-            
-            for (i, &(addr, value)) in csr_list.iter().enumerate() {
-                if let Some(name) = csr_name(addr) {
-                    print!("  {:>12}: 0x{:08x}", name, value);
-                } else {
-                    print!("  0x{:03x}: 0x{:08x}", addr, value);
-                }
-                if i % 3 == 2 {
-                    println!();
-                } else {
-                    print!("  ");
-                }
+            Self::CSR_FRM => {
+                // 写 FRM 只更新 FCSR[7:5]
+                let old_fcsr = self.status.csr_read(Self::CSR_FCSR);
+                let new_fcsr = (old_fcsr & !0xE0) | ((value & 0x7) << 5);
+                self.status.csr_write(Self::CSR_FCSR, new_fcsr);
+                self.mark_fs_dirty();
             }
-            // 如果最后一行没有换行，补上
-            if csr_list.len() % 3 != 0 {
-                println!();
+            Self::CSR_FCSR => {
+                // FCSR 只有低 8 位有效
+                self.status.csr_write(csr, value & 0xFF);
+                self.mark_fs_dirty();
+            }
+            // mstatus 是 WARL：只有实现了的字段可写
+            csr_def::CSR_MSTATUS => {
+                self.status.csr_write(csr, value & trap::mstatus::WRITABLE_MASK);
+            }
+            // mtvec 是 WARL：mode 只支持 Direct(0)/Vectored(1)，
+            // 保留的 mode 值回落到 Direct
+            csr_def::CSR_MTVEC => {
+                let legal = if value & 0b11 > 1 { value & !0b11 } else { value };
+                self.status.csr_write(csr, legal);
             }
+            // mepc 是 WARL：按 IALIGN=32 对齐
+            csr_def::CSR_MEPC => {
+                self.status.csr_write(csr, value & !0b11);
+            }
+            // misa 是 WARL：MXL/I 与特权级位只读，只有复位时
+            // 置位的扩展位（misa_reset & TOGGLABLE）可以开关
+            csr_def::CSR_MISA => {
+                let togglable = self.misa_reset & csr_def::misa::TOGGLABLE;
+                let new = (self.misa_reset & !togglable) | (value & togglable);
+                self.status.csr_write(csr, new);
+                self.misa_disabled = togglable & !new;
+            }
+            // Sdtrig：tselect 是 WARL，越界写入钳制到最后一个触发器
+            csr_def::CSR_TSELECT => {
+                self.tselect = value.min(NUM_TRIGGERS as u32 - 1);
+            }
+            csr_def::CSR_TDATA1 => {
+                self.triggers[self.tselect as usize].tdata1 = value & Trigger::WRITE_MASK;
+            }
+            csr_def::CSR_TDATA2 => {
+                self.triggers[self.tselect as usize].tdata2 = value;
+            }
+            // 计数器写穿：写入任一半后从新值继续计数；写入优先于
+            // 本步的自增（OS 启动代码常清零计数器再读增量）
+            csr_def::CSR_MCYCLE => {
+                self.mcycle = (self.mcycle & !0xFFFF_FFFF) | value as u64;
+                self.mcycle_written = true;
+            }
+            csr_def::CSR_MCYCLEH => {
+                self.mcycle = (self.mcycle & 0xFFFF_FFFF) | ((value as u64) << 32);
+                self.mcycle_written = true;
+            }
+            csr_def::CSR_MINSTRET => {
+                self.minstret = (self.minstret & !0xFFFF_FFFF) | value as u64;
+                self.minstret_written = true;
+            }
+            csr_def::CSR_MINSTRETH => {
+                self.minstret = (self.minstret & 0xFFFF_FFFF) | ((value as u64) << 32);
+                self.minstret_written = true;
+            }
+            // mcountinhibit 是 WARL：只有 CY/IR 可写（TM 恒 0）
+            csr_def::CSR_MCOUNTINHIBIT => {
+                self.countinhibit = value & csr_def::mcountinhibit::WRITABLE_MASK;
+            }
+            _ => self.status.csr_write(csr, value),
         }
-        
-        println!("═══════════════════════════════════════════════════════════════════");
-    }
-}
 
-impl Default for CpuCore {
-    fn default() -> Self {
-        Self::new(0)
+        if watched {
+            self.last_csr_write = Some(CsrWriteEvent {
+                addr: csr,
+                pc: self.instr_pc,
+                old,
+                new: self.csr_read(csr),
+            });
+        }
+        if self.event_bus.is_some() {
+            self.publish_event(crate::events::SimEvent::CsrWritten {
+                pc: self.instr_pc,
+                addr: csr,
+                old,
+                new: self.csr_read(csr),
+            });
+        }
     }
-}
 
-fn csr_name(addr: u16) -> Option<&'static str> {
-    fn find(slice: &[CsrEntry], addr: u16) -> Option<&'static str> {
-        slice.iter().find(|entry| entry.addr == addr).map(|entry| entry.name)
+    /// 该 CSR 地址在当前配置下是否实现
+    ///
+    /// tselect/tdata1/tdata2 不进 CsrBank（见 [`csr_def`]），单独放行；
+    /// 其余地址查 CSR 注册表
+    pub fn csr_implemented(&self, csr: u16) -> bool {
+        match csr {
+            csr_def::CSR_TSELECT | csr_def::CSR_TDATA1 | csr_def::CSR_TDATA2 => true,
+            _ => self.status.csr.contains(csr),
+        }
     }
 
-    find(crate::cpu::csr_def::BASE_CSRS, addr)
-        .or_else(|| find(crate::cpu::csr_def::F_CSRS, addr))
-        .or_else(|| find(crate::cpu::csr_def::V_CSRS, addr))
-        .or_else(|| find(crate::cpu::csr_def::M_CSRS, addr))
-        .or_else(|| find(crate::cpu::csr_def::S_CSRS, addr))
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::memory::FlatMemory;
-
-    /// 将指令写入内存
-    fn write_instr(mem: &mut FlatMemory, addr: u32, instr: u32) {
-        mem.store32(addr, instr).unwrap();
+    /// CSR 访问合法性检查（特权规范 2.1 节）
+    ///
+    /// 以下情况返回 false，调用方应触发非法指令异常：
+    /// - CSR 未实现（未注册）
+    /// - 向只读地址段（addr[11:10] == 0b11）写入
+    /// - 当前特权级低于地址要求的最低特权级（addr[9:8]）
+    pub fn csr_access_legal(&self, csr: u16, writes: bool) -> bool {
+        if !self.csr_implemented(csr) {
+            return false;
+        }
+        if writes && csr_def::is_read_only(csr) {
+            return false;
+        }
+        // FS=Off 时浮点 CSR 不可访问
+        if (Self::CSR_FFLAGS..=Self::CSR_FCSR).contains(&csr) && !self.fs_allows_fp() {
+            return false;
+        }
+        csr_def::min_privilege_bits(csr) <= self.status.privilege.to_bits()
     }
 
-    #[test]
-    fn test_addi() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
-
-        // addi x1, x0, 42
-        write_instr(&mut mem, 0, 0x02A00093);
-        cpu.step(&mut mem);
-
-        assert_eq!(cpu.read_reg(1), 42);
-        assert_eq!(cpu.pc(), 4);
+    /// mstatus.FS 是否允许访问浮点状态
+    ///
+    /// FS=Off 时浮点指令与浮点 CSR 访问按非法指令处理，供 OS 做
+    /// 惰性浮点上下文切换。未实现 mstatus 的配置不做此检查。
+    fn fs_allows_fp(&self) -> bool {
+        !self.status.csr.contains(csr_def::CSR_MSTATUS)
+            || trap::mstatus::read_fs(self.status.csr_read(csr_def::CSR_MSTATUS))
+                != trap::mstatus::FS_OFF
     }
 
-    #[test]
-    fn test_add() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
-
-        // addi x1, x0, 10
-        write_instr(&mut mem, 0, 0x00A00093);
-        // addi x2, x0, 20
-        write_instr(&mut mem, 4, 0x01400113);
-        // add x3, x1, x2
-        write_instr(&mut mem, 8, 0x002081B3);
+    /// 浮点架构状态（f 寄存器或 fcsr）被写入后把 FS 置为 Dirty
+    ///
+    /// SD 位由 `csr_read` 从 FS 推导，这里只更新 FS 字段
+    fn mark_fs_dirty(&mut self) {
+        if self.status.csr.contains(csr_def::CSR_MSTATUS) {
+            let mstatus = self.status.csr_read(csr_def::CSR_MSTATUS);
+            self.status.csr_write(
+                csr_def::CSR_MSTATUS,
+                trap::mstatus::write_fs(mstatus, trap::mstatus::FS_DIRTY),
+            );
+        }
+    }
 
-        cpu.step(&mut mem);
-        cpu.step(&mut mem);
-        cpu.step(&mut mem);
+    pub fn privilege(&self) -> PrivilegeMode {
+        self.status.privilege
+    }
 
-        assert_eq!(cpu.read_reg(1), 10);
-        assert_eq!(cpu.read_reg(2), 20);
-        assert_eq!(cpu.read_reg(3), 30);
+    pub fn set_privilege(&mut self, mode: PrivilegeMode) {
+        self.status.privilege = mode;
     }
 
-    #[test]
-    fn test_sub() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+    /// 获取 mstatus 的类型化视图
+    pub fn mstatus(&self) -> Mstatus {
+        Mstatus(self.csr_read(csr_def::CSR_MSTATUS))
+    }
 
-        // addi x1, x0, 100
-        write_instr(&mut mem, 0, 0x06400093);
-        // addi x2, x0, 30
-        write_instr(&mut mem, 4, 0x01E00113);
-        // sub x3, x1, x2
-        write_instr(&mut mem, 8, 0x402081B3);
+    /// 写入 mstatus
+    pub fn set_mstatus(&mut self, value: Mstatus) {
+        self.csr_write(csr_def::CSR_MSTATUS, value.bits());
+    }
 
-        cpu.run(&mut mem, 3);
+    /// 获取 mtvec 的类型化视图
+    pub fn mtvec(&self) -> Mtvec {
+        Mtvec(self.csr_read(csr_def::CSR_MTVEC))
+    }
 
-        assert_eq!(cpu.read_reg(3), 70);
+    /// 写入 mtvec
+    pub fn set_mtvec(&mut self, value: Mtvec) {
+        self.csr_write(csr_def::CSR_MTVEC, value.bits());
     }
 
-    #[test]
-    fn test_lw_sw() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+    /// 获取 mcause 的类型化视图
+    pub fn mcause(&self) -> Mcause {
+        Mcause(self.csr_read(csr_def::CSR_MCAUSE))
+    }
 
-        // addi x1, x0, 0x42 (将 0x42 存入 x1)
-        write_instr(&mut mem, 0, 0x04200093);
-        // addi x2, x0, 100 (x2 = 100, 作为基地址)
-        write_instr(&mut mem, 4, 0x06400113);
-        // sw x1, 0(x2) (将 x1 存入 mem[100])
-        write_instr(&mut mem, 8, 0x00112023);
-        // lw x3, 0(x2) (从 mem[100] 加载到 x3)
-        write_instr(&mut mem, 12, 0x00012183);
+    /// 该 profile 是否包含 M-mode
+    pub fn has_m_mode(&self) -> bool {
+        self.status.has_m_mode
+    }
 
-        cpu.run(&mut mem, 4);
+    /// 该 profile 是否包含 S-mode
+    pub fn has_s_mode(&self) -> bool {
+        self.status.has_s_mode
+    }
 
-        assert_eq!(cpu.read_reg(3), 0x42);
-        assert_eq!(mem.load32(100).unwrap(), 0x42);
+    /// 设置 CPU 状态
+    pub fn set_state(&mut self, state: CpuState) {
+        self.state = state;
     }
 
-    #[test]
-    fn test_beq_taken() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+    pub fn handle_memory_error(&mut self, err: MemError, access: MemAccessType, fault_pc: u32) {
+        use MemAccessType::*;
+        use TrapCause::*;
 
-        // addi x1, x0, 5
-        write_instr(&mut mem, 0, 0x00500093);
-        // addi x2, x0, 5
-        write_instr(&mut mem, 4, 0x00500113);
-        // beq x1, x2, 8 (跳转到 PC+8=12)
-        write_instr(&mut mem, 8, 0x00208463);
-        // addi x3, x0, 1 (如果不跳转则执行)
-        write_instr(&mut mem, 12, 0x00100193);
-
-        cpu.run(&mut mem, 3);
+        let (addr, cause) = match err {
+            MemError::Unaligned { addr, .. } => (
+                addr,
+                match access {
+                    Fetch => InstructionAddressMisaligned,
+                    Load => LoadAddressMisaligned,
+                    Store => StoreAddressMisaligned,
+                },
+            ),
+            MemError::OutOfRange { addr, .. } => (
+                addr,
+                match access {
+                    Fetch => InstructionAccessFault,
+                    Load => LoadAccessFault,
+                    Store => StoreAccessFault,
+                },
+            ),
+            MemError::PageFault { addr } => (
+                addr,
+                match access {
+                    Fetch => InstructionPageFault,
+                    Load => LoadPageFault,
+                    Store => StorePageFault,
+                },
+            ),
+        };
 
-        // beq 应该跳转到地址 16 (8 + 8)
-        assert_eq!(cpu.pc(), 16);
-        // x3 不应该被修改（因为跳过了地址 12 的指令）
-        assert_eq!(cpu.read_reg(3), 0);
+        self.take_trap_at(cause, addr, fault_pc);
     }
 
-    #[test]
-    fn test_beq_not_taken() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+    pub fn mem_result<T>(
+        &mut self,
+        result: Result<T, MemError>,
+        access: MemAccessType,
+        fault_pc: u32,
+    ) -> Option<T> {
+        match result {
+            Ok(v) => Some(v),
+            Err(err) => {
+                self.handle_memory_error(err, access, fault_pc);
+                None
+            }
+        }
+    }
 
-        // addi x1, x0, 5
-        write_instr(&mut mem, 0, 0x00500093);
-        // addi x2, x0, 10
-        write_instr(&mut mem, 4, 0x00A00113);
-        // beq x1, x2, 8 (不跳转，因为 x1 != x2)
-        write_instr(&mut mem, 8, 0x00208463);
-        // addi x3, x0, 1 (应该执行)
-        write_instr(&mut mem, 12, 0x00100193);
+    pub fn mem_result_unit(
+        &mut self,
+        result: Result<(), MemError>,
+        access: MemAccessType,
+        fault_pc: u32,
+    ) -> bool {
+        if let Err(err) = result {
+            self.handle_memory_error(err, access, fault_pc);
+            false
+        } else {
+            true
+        }
+    }
 
-        cpu.run(&mut mem, 4);
+    /// 挂起一个异步中断：置位 mip 的对应位
+    ///
+    /// 实际注入发生在下一次 `step` 取指之前（见
+    /// `check_pending_interrupt`），届时根据 mstatus.MIE 和 mie
+    /// 决定是否进入 trap。`cause` 必须是中断。
+    pub fn raise_interrupt(&mut self, cause: TrapCause) {
+        debug_assert!(cause.is_interrupt(), "raise_interrupt 只接受中断原因");
+        let mip = self.status.csr_read(csr_def::CSR_MIP);
+        self.status.csr_write(csr_def::CSR_MIP, mip | (1 << cause.code()));
+        self.publish_event(crate::events::SimEvent::InterruptRaised { cause });
+    }
 
-        assert_eq!(cpu.read_reg(3), 1);
+    /// 撤销一个挂起的异步中断：清零 mip 的对应位
+    pub fn clear_interrupt(&mut self, cause: TrapCause) {
+        debug_assert!(cause.is_interrupt(), "clear_interrupt 只接受中断原因");
+        let mip = self.status.csr_read(csr_def::CSR_MIP);
+        self.status.csr_write(csr_def::CSR_MIP, mip & !(1 << cause.code()));
     }
 
-    #[test]
-    fn test_jal() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+    /// 取指前的异步中断评估
+    ///
+    /// 计算 mip & mie：有挂起且使能的中断时结束 WFI；mstatus.MIE
+    /// 全局使能时按优先级（MEI > MSI > MTI > SEI > SSI > STI）进入
+    /// 最高优先级中断的 trap。
+    fn check_pending_interrupt(&mut self) {
+        let pending =
+            self.status.csr_read(csr_def::CSR_MIP) & self.status.csr_read(csr_def::CSR_MIE);
+        if pending == 0 {
+            return;
+        }
 
-        // jal x1, 8 (跳转到 PC+8=8，x1 = PC+4=4)
-        write_instr(&mut mem, 0, 0x008000EF);
+        // 挂起且使能的中断结束 WFI（是否进入 trap 仍看全局使能）
+        if self.state == CpuState::WaitForInterrupt {
+            self.state = CpuState::Running;
+        }
 
-        cpu.step(&mut mem);
+        let mstatus = self.status.csr_read(csr_def::CSR_MSTATUS);
+        if (mstatus & trap::mstatus::MIE_MASK) == 0 {
+            return;
+        }
 
-        assert_eq!(cpu.read_reg(1), 4); // 返回地址
-        assert_eq!(cpu.pc(), 8); // 跳转目标
+        // 规范定义的中断优先级，从高到低
+        const PRIORITY: [TrapCause; 6] = [
+            TrapCause::MachineExternalInterrupt,
+            TrapCause::MachineSoftwareInterrupt,
+            TrapCause::MachineTimerInterrupt,
+            TrapCause::SupervisorExternalInterrupt,
+            TrapCause::SupervisorSoftwareInterrupt,
+            TrapCause::SupervisorTimerInterrupt,
+        ];
+        for cause in PRIORITY {
+            if pending & (1 << cause.code()) != 0 {
+                self.take_trap(cause, 0);
+                return;
+            }
+        }
     }
 
-    #[test]
-    fn test_lui() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
-
-        // lui x1, 0x12345
-        write_instr(&mut mem, 0, 0x123450B7);
+    /// 触发 trap（异常或中断）
+    ///
+    /// 执行 RISC-V 特权规范定义的 trap 处理流程：
+    /// 1. 保存当前 PC 到 mepc/sepc
+    /// 2. 保存异常原因到 mcause/scause
+    /// 3. 保存额外信息到 mtval/stval
+    /// 4. 更新 mstatus/sstatus（保存中断使能位等）
+    /// 5. 设置新特权级
+    /// 6. 跳转到 trap handler (mtvec/stvec)
+    ///
+    /// # 参数
+    ///
+    /// * `cause` - trap 原因
+    /// * `tval` - 额外信息（如错误地址、非法指令编码等）
+    pub fn take_trap(&mut self, cause: TrapCause, tval: u32) {
+        // 使用当前 PC 作为异常 PC
+        self.take_trap_at(cause, tval, self.pc);
+    }
 
-        cpu.step(&mut mem);
+    /// 在指定 PC 处触发 trap（用于异常需要指向触发指令的情况）
+    ///
+    /// # 参数
+    ///
+    /// * `cause` - trap 原因
+    /// * `tval` - 额外信息（如错误地址、非法指令编码等）
+    /// * `epc` - 异常 PC（保存到 mepc）
+    pub fn take_trap_at(&mut self, cause: TrapCause, tval: u32, epc: u32) {
+        self.last_trap = Some(cause);
+        self.publish_event(crate::events::SimEvent::TrapTaken { pc: epc, cause, tval });
 
-        assert_eq!(cpu.read_reg(1), 0x12345000);
+        // 按 profile 选择 trap 目标特权级：
+        // 有 M-mode 时进入 M-mode；supervisor-only profile 进入 S-mode；
+        // user-only profile 没有 trap handler，交由宿主处理
+        // TODO: 支持 trap 委托 (medeleg/mideleg)
+        if self.status.has_m_mode {
+            self.take_trap_to_m(cause, tval, epc);
+        } else if self.status.has_s_mode {
+            self.take_trap_to_s(cause, tval, epc);
+        } else {
+            // user-only profile：无处可去，停机并保留 last_trap 供宿主查询
+            self.state = CpuState::Halted;
+        }
     }
 
-    #[test]
-    fn test_auipc() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0x100);
-
-        // auipc x1, 0x12345 (x1 = PC + (0x12345 << 12))
-        write_instr(&mut mem, 0x100, 0x12345097);
+    /// 进入 M-mode trap handler
+    fn take_trap_to_m(&mut self, cause: TrapCause, tval: u32, epc: u32) {
+        use csr_def::*;
+        use trap::{mstatus, calculate_trap_pc};
 
-        cpu.step(&mut mem);
+        // 保存异常 PC 到 mepc
+        // 对于异常：mepc 指向触发异常的指令
+        // 对于中断：mepc 指向下一条要执行的指令
+        self.status.csr_write(CSR_MEPC, epc);
 
-        assert_eq!(cpu.read_reg(1), 0x100 + 0x12345000);
-    }
+        // 保存异常原因到 mcause
+        self.status.csr_write(CSR_MCAUSE, cause.to_cause_value());
 
-    #[test]
-    fn test_x0_always_zero() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+        // 保存额外信息到 mtval
+        self.status.csr_write(CSR_MTVAL, tval);
 
-        // addi x0, x0, 42 (尝试写入 x0)
-        write_instr(&mut mem, 0, 0x02A00013);
+        // 更新 mstatus
+        let mstatus = self.status.csr_read(CSR_MSTATUS);
 
-        cpu.step(&mut mem);
+        // 保存当前 MIE 到 MPIE
+        let mie = mstatus::read_mie(mstatus);
+        let mut new_mstatus = mstatus;
 
-        // x0 应该仍然是 0
-        assert_eq!(cpu.read_reg(0), 0);
-    }
+        // MPIE = MIE
+        if mie {
+            new_mstatus |= mstatus::MPIE_MASK;
+        } else {
+            new_mstatus &= !mstatus::MPIE_MASK;
+        }
 
-    #[test]
-    fn test_ecall() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+        // MIE = 0 (禁用中断)
+        new_mstatus &= !mstatus::MIE_MASK;
 
-        // 设置 trap handler 地址
-        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
+        // MPP = current privilege
+        new_mstatus = mstatus::write_mpp(new_mstatus, self.status.privilege.to_bits());
 
-        // ecall at PC=0
-        write_instr(&mut mem, 0, 0x00000073);
+        self.status.csr_write(CSR_MSTATUS, new_mstatus);
 
-        let state = cpu.step(&mut mem);
+        // 设置新特权级
+        self.status.privilege = PrivilegeMode::Machine;
 
-        assert_eq!(state, CpuState::Running); // trap 后继续运行
-        assert_eq!(cpu.pc(), 0x100); // 跳转到 mtvec
-        assert_eq!(cpu.csr_read(0x341), 0); // mepc = 原 PC
-        assert_eq!(cpu.csr_read(0x342), 11); // mcause = 11 (ecall from M-mode)
+        // 跳转到 trap handler
+        let mtvec = self.status.csr_read(CSR_MTVEC);
+        self.pc = calculate_trap_pc(mtvec, &cause);
     }
 
-    #[test]
-    fn test_ebreak() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+    /// 进入 S-mode trap handler（supervisor-only profile）
+    fn take_trap_to_s(&mut self, cause: TrapCause, tval: u32, epc: u32) {
+        use csr_def::*;
+        use trap::{mstatus, calculate_trap_pc};
 
-        // 设置 trap handler 地址
-        cpu.csr_write(0x305, 0x200); // mtvec = 0x200
+        self.status.csr_write(CSR_SEPC, epc);
+        self.status.csr_write(CSR_SCAUSE, cause.to_cause_value());
+        self.status.csr_write(CSR_STVAL, tval);
 
-        // ebreak at PC=0
-        write_instr(&mut mem, 0, 0x00100073);
+        // 更新 sstatus：SPIE = SIE, SIE = 0, SPP = 当前特权级
+        let sstatus = self.status.csr_read(CSR_SSTATUS);
+        let sie = (sstatus & mstatus::SIE_MASK) != 0;
+        let mut new_sstatus = sstatus;
 
-        let state = cpu.step(&mut mem);
+        if sie {
+            new_sstatus |= mstatus::SPIE_MASK;
+        } else {
+            new_sstatus &= !mstatus::SPIE_MASK;
+        }
 
-        assert_eq!(state, CpuState::Running); // trap 后继续运行
-        assert_eq!(cpu.pc(), 0x200); // 跳转到 mtvec
-        assert_eq!(cpu.csr_read(0x341), 0); // mepc = 原 PC
-        assert_eq!(cpu.csr_read(0x342), 3); // mcause = 3 (breakpoint)
-    }
+        new_sstatus &= !mstatus::SIE_MASK;
 
-    #[test]
-    fn test_shift_instructions() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+        if self.status.privilege == PrivilegeMode::Supervisor {
+            new_sstatus |= mstatus::SPP_MASK;
+        } else {
+            new_sstatus &= !mstatus::SPP_MASK;
+        }
 
-        // addi x1, x0, 0x10 (x1 = 16)
-        write_instr(&mut mem, 0, 0x01000093);
-        // slli x2, x1, 2 (x2 = 16 << 2 = 64)
-        write_instr(&mut mem, 4, 0x00209113);
-        // srli x3, x2, 1 (x3 = 64 >> 1 = 32)
-        write_instr(&mut mem, 8, 0x00115193);
+        self.status.csr_write(CSR_SSTATUS, new_sstatus);
 
-        cpu.run(&mut mem, 3);
+        self.status.privilege = PrivilegeMode::Supervisor;
 
-        assert_eq!(cpu.read_reg(1), 16);
-        assert_eq!(cpu.read_reg(2), 64);
-        assert_eq!(cpu.read_reg(3), 32);
+        let stvec = self.status.csr_read(CSR_STVEC);
+        self.pc = calculate_trap_pc(stvec, &cause);
     }
 
-    #[test]
-    fn test_slt() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
-
-        // addi x1, x0, -5 (x1 = -5)
-        write_instr(&mut mem, 0, 0xFFB00093);
-        // addi x2, x0, 10 (x2 = 10)
-        write_instr(&mut mem, 4, 0x00A00113);
-        // slt x3, x1, x2 (x3 = 1, 因为 -5 < 10)
-        write_instr(&mut mem, 8, 0x0020A1B3);
-
-        cpu.run(&mut mem, 3);
-
-        assert_eq!(cpu.read_reg(3), 1);
+    /// 获取所有寄存器的快照
+    pub fn regs(&self) -> &[u32; 32] {
+        self.status.int_snapshot()
     }
 
-    #[test]
-    fn test_simple_loop() {
-        let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuCore::new(0);
+    /// 获取完整架构状态快照
+    pub fn snapshot(&self) -> StatusSnapshot {
+        self.status.snapshot()
+    }
+
+    /// 执行单步指令
+    ///
+    /// # 参数
+    ///
+    /// * `mem` - 内存接口
+    ///
+    /// # 返回
+    ///
+    /// 当前 CPU 状态
+    ///
+    /// # 流程
+    ///
+    /// 1. 从 PC 处取指
+    /// 2. 解码指令
+    /// 3. 默认 PC += 4
+    /// 4. 执行指令（可能修改 PC）
+    pub fn step(&mut self, mem: &mut dyn Memory) -> CpuState {
+        // WFI 等待中仍需评估中断以便唤醒；其他非 Running 状态直接返回
+        if self.state != CpuState::Running && self.state != CpuState::WaitForInterrupt {
+            return self.state;
+        }
+
+        // 记录模式：先整体快照架构状态，内存写入稍后补充到记录里
+        if self.undo_log.is_some() {
+            let rec = UndoRecord {
+                pc: self.pc,
+                state: self.state,
+                privilege: self.status.privilege,
+                status: self.status.snapshot(),
+                reservation: self.reservation,
+                triggers: self.triggers,
+                tselect: self.tselect,
+                mem_writes: Vec::new(),
+            };
+            if let Some(log) = self.undo_log.as_mut() {
+                if log.len() == self.undo_depth {
+                    log.pop_front();
+                }
+                log.push_back(rec);
+            }
+        }
+
+        // 清除上一条指令的 trap / CSR 写入 / 监视点命中记录
+        self.last_trap = None;
+        self.last_csr_write = None;
+        self.last_watchpoint = None;
+        self.mcycle_written = false;
+        self.minstret_written = false;
+
+        // 取指前评估挂起的异步中断：可能唤醒 WFI 并转入处理程序
+        self.check_pending_interrupt();
+
+        if self.state != CpuState::Running {
+            return self.state;
+        }
+
+        // 中断已经转向处理程序时，本步只完成跳转，不再取指执行
+        if self.last_trap.is_some() {
+            return self.state;
+        }
+
+        // 保存当前 PC（用于计算返回地址等）
+        let current_pc = self.pc;
+        self.instr_pc = current_pc;
+
+        // satp 启用 Sv32 且特权级低于 M 时，本条指令的所有访存都要翻译
+        let sv32 = self.sv32_ctx();
+
+        // 取指（启用 Sv32 时先按 X 权限翻译指令地址）
+        let fetch_result = match sv32 {
+            Some(ctx) => ctx
+                .translate(mem, current_pc, MemAccessType::Fetch)
+                .and_then(|pa| mem.load32(pa)),
+            None => mem.load32(current_pc),
+        };
+        let instr_word = match fetch_result {
+            Ok(word) => word,
+            Err(err) => {
+                self.handle_memory_error(err, MemAccessType::Fetch, current_pc);
+                return self.state;
+            }
+        };
+
+        // 使用配置的解码器解码（缓存命中时跳过指令表扫描）
+        let decoded = match self.decode_cache.as_mut() {
+            Some(cache) => match cache.lookup(current_pc, instr_word) {
+                Some(instr) => DecodedInstr { raw: instr_word, instr },
+                None => {
+                    let decoded = self.decoder.decode(instr_word);
+                    cache.insert(current_pc, instr_word, decoded.instr);
+                    decoded
+                }
+            },
+            None => self.decoder.decode(instr_word),
+        };
+
+        // FENCE.I：显式同步点，丢弃全部已解码缓存
+        if matches!(decoded.instr, RvInstr::FenceI)
+            && let Some(cache) = self.decode_cache.as_mut()
+        {
+            cache.clear();
+        }
+
+        // misa 中被关掉的扩展，其指令按非法指令处理
+        let decoded = if self.misa_disabled != 0 && !self.misa_allows(&decoded.instr) {
+            DecodedInstr { raw: instr_word, instr: RvInstr::Illegal { raw: instr_word } }
+        } else {
+            decoded
+        };
+
+        // Sdtrig：已武装的触发器在执行前做地址匹配（timing=before）
+        if self.triggers.iter().any(Trigger::is_armed)
+            && let Some(tval) = self.trigger_match(&decoded.instr, current_pc)
+        {
+            self.take_trap_at(TrapCause::Breakpoint, tval, current_pc);
+            return self.state;
+        }
+
+        // 指令使用统计（按解码结果归类，非法指令也计入）
+        if let Some(usage) = self.instr_usage.as_mut() {
+            let key = (decoded.instr.extension_name(), decoded.instr.mnemonic());
+            *usage.entry(key).or_insert(0) += 1;
+        }
+
+        // 覆盖率统计（按指令字匹配表项，未匹配的编码不计）
+        if let Some(cov) = self.coverage.as_mut() {
+            cov.record(instr_word);
+        }
+
+        // 默认顺序执行
+        self.pc = self.pc.wrapping_add(4);
+
+        // 执行统计与计时模型都要在执行后对比 PC 判断分支走向，先留存指令
+        let post_instr = (self.stats.is_some() || self.timing.is_some() || self.profiler.is_some())
+            .then_some(decoded.instr);
+        let shadow_instr = self.call_stack.is_some().then_some(decoded.instr);
+
+        // 大端数据模式：数据访问经字节交换层（取指已经完成，
+        // 不受影响）
+        let mut be_mem;
+        let mem: &mut dyn Memory = if self.big_endian {
+            be_mem = crate::memory::BigEndianMemory::new(mem);
+            &mut be_mem
+        } else {
+            mem
+        };
+
+        // 自修改代码：存储指令覆盖已缓存的代码字时先剔除解码项，
+        // 后续取指走重新解码（宿主旁路写入由取指时的指令字比对兜底）
+        if self.decode_cache.is_some()
+            && let Some((addr, len)) = self.store_span(&decoded.instr)
+            && let Some(cache) = self.decode_cache.as_mut()
+        {
+            cache.invalidate(addr, len);
+        }
+
+        // 执行指令（记录模式最内层包撤销层，Sv32 再包地址翻译层，
+        // 监视点检测由 execute_watched 按需包装在最外层）
+        if self.undo_log.is_some() {
+            let mut undo_mem = UndoMemory::new(mem);
+            if let Some(ctx) = sv32 {
+                let mut mmu_mem = mmu::MmuMemory::new(&mut undo_mem, ctx);
+                self.execute_watched(&mut mmu_mem, decoded, current_pc, instr_word);
+            } else {
+                self.execute_watched(&mut undo_mem, decoded, current_pc, instr_word);
+            }
+            let writes = undo_mem.into_writes();
+            if let Some(rec) = self.undo_log.as_mut().and_then(|log| log.back_mut()) {
+                rec.mem_writes = writes;
+            }
+        } else if let Some(ctx) = sv32 {
+            let mut mmu_mem = mmu::MmuMemory::new(mem, ctx);
+            self.execute_watched(&mut mmu_mem, decoded, current_pc, instr_word);
+        } else {
+            self.execute_watched(mem, decoded, current_pc, instr_word);
+        }
+
+        // 影子调用栈：执行后 PC 已是跳转目标 / 返回地址
+        if let Some(instr) = shadow_instr
+            && self.state == CpuState::Running
+        {
+            self.track_call(&instr, current_pc);
+        }
+
+        if let Some(instr) = post_instr {
+            let taken = self.pc != current_pc.wrapping_add(4);
+            if let Some(stats) = self.stats.as_mut() {
+                stats.record(&instr, taken);
+            }
+            if let Some(profiler) = self.profiler.as_mut() {
+                profiler.record(current_pc, self.pc, &instr);
+            }
+            if let Some(model) = self.timing.as_ref() {
+                self.cycles += model.cycles(&instr, taken);
+                self.timed_instrs += 1;
+            }
+        }
+
+        // 计数器推进：mcycle 每执行一条指令记一个周期，minstret
+        // 只计真正退休的指令（触发异常的指令不算退休）；本步被
+        // 客体写过的计数器跳过自增，让写入的值精确生效
+        if self.countinhibit & csr_def::mcountinhibit::CY == 0 && !self.mcycle_written {
+            self.mcycle = self.mcycle.wrapping_add(1);
+        }
+        let retired = self.last_trap.is_none()
+            && !matches!(self.state, CpuState::IllegalInstruction(_));
+        if retired
+            && self.countinhibit & csr_def::mcountinhibit::IR == 0
+            && !self.minstret_written
+        {
+            self.minstret = self.minstret.wrapping_add(1);
+        }
+
+        // 单步模式：指令正常退休（含转入 trap 处理程序）后停步；
+        // 非法指令、监视点等停机状态保持原样上报
+        if self.single_step && self.state == CpuState::Running {
+            self.state = CpuState::DebugStep;
+        }
+
+        self.state
+    }
+
+    /// 按 jal/jalr 的调用/返回启发式维护影子栈
+    ///
+    /// 写 ra 视为调用；`jalr x0, 0(ra)` 视为返回，弹到返回地址
+    /// 匹配的那一帧为止（尾调用、longjmp 跳过的帧一并弹掉），
+    /// 匹配不到则不动。
+    fn track_call(&mut self, instr: &RvInstr, current_pc: u32) {
+        let Some(frames) = self.call_stack.as_mut() else {
+            return;
+        };
+        match *instr {
+            RvInstr::Jal { rd: 1, .. } | RvInstr::Jalr { rd: 1, .. } => {
+                frames.push(CallFrame {
+                    target: self.pc,
+                    call_site: current_pc,
+                    return_addr: current_pc.wrapping_add(4),
+                });
+            }
+            RvInstr::Jalr { rd: 0, rs1: 1, .. } => {
+                if let Some(pos) = frames.iter().rposition(|f| f.return_addr == self.pc) {
+                    frames.truncate(pos);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 当前指令是否需要 Sv32 地址翻译，需要时返回翻译上下文
+    fn sv32_ctx(&self) -> Option<mmu::Sv32Ctx> {
+        let satp = self.status.csr_read(csr_def::CSR_SATP);
+        if !mmu::satp_enables_sv32(satp) || self.status.privilege == PrivilegeMode::Machine {
+            return None;
+        }
+        let mstatus = self.status.csr_read(csr_def::CSR_MSTATUS);
+        let sum = mstatus & (1 << trap::mstatus::SUM) != 0;
+        let mxr = mstatus & (1 << trap::mstatus::MXR) != 0;
+        Some(mmu::Sv32Ctx::new(satp, self.status.privilege, sum, mxr))
+    }
+
+    /// 指令所属扩展当前是否仍在 misa 中启用
+    ///
+    /// 只有可开关的扩展（M/A/F/V）参与检查；基础指令集与
+    /// misa 未实现（misa_reset 为 0）的配置恒为真。
+    fn misa_allows(&self, instr: &RvInstr) -> bool {
+        let bit = match instr.extension_name() {
+            "M" => csr_def::misa::M,
+            "A" => csr_def::misa::A,
+            "F" => csr_def::misa::F,
+            "V" => csr_def::misa::V,
+            _ => return true,
+        };
+        self.misa_disabled & bit == 0
+    }
+
+    /// 返回第一个匹配当前指令的触发器的匹配地址（作为 mtval）
+    ///
+    /// execute 匹配取指地址；load/store 在执行前按译码出的
+    /// 基址 + 偏移计算有效地址做精确匹配（mcontrol match=0）。
+    /// AMO 同时按加载和存储参与匹配。
+    fn trigger_match(&self, instr: &RvInstr, pc: u32) -> Option<u32> {
+        let ea = |rs1: u8, offset: i32| self.read_reg(rs1).wrapping_add(offset as u32);
+        let (load_addr, store_addr) = match *instr {
+            RvInstr::Lb { rs1, offset, .. }
+            | RvInstr::Lh { rs1, offset, .. }
+            | RvInstr::Lw { rs1, offset, .. }
+            | RvInstr::Lbu { rs1, offset, .. }
+            | RvInstr::Lhu { rs1, offset, .. }
+            | RvInstr::Flw { rs1, offset, .. } => (Some(ea(rs1, offset)), None),
+            RvInstr::Sb { rs1, offset, .. }
+            | RvInstr::Sh { rs1, offset, .. }
+            | RvInstr::Sw { rs1, offset, .. }
+            | RvInstr::Fsw { rs1, offset, .. } => (None, Some(ea(rs1, offset))),
+            RvInstr::LrW { rs1, .. } => (Some(self.read_reg(rs1)), None),
+            RvInstr::ScW { rs1, .. } => (None, Some(self.read_reg(rs1))),
+            RvInstr::AmoswapW { rs1, .. }
+            | RvInstr::AmoaddW { rs1, .. }
+            | RvInstr::AmoxorW { rs1, .. }
+            | RvInstr::AmoandW { rs1, .. }
+            | RvInstr::AmoorW { rs1, .. }
+            | RvInstr::AmominW { rs1, .. }
+            | RvInstr::AmomaxW { rs1, .. }
+            | RvInstr::AmominuW { rs1, .. }
+            | RvInstr::AmomaxuW { rs1, .. } => {
+                let addr = self.read_reg(rs1);
+                (Some(addr), Some(addr))
+            }
+            _ => (None, None),
+        };
+
+        for trigger in &self.triggers {
+            if trigger.on_execute() && trigger.tdata2 == pc {
+                return Some(pc);
+            }
+            if trigger.on_load() && load_addr == Some(trigger.tdata2) {
+                return Some(trigger.tdata2);
+            }
+            if trigger.on_store() && store_addr == Some(trigger.tdata2) {
+                return Some(trigger.tdata2);
+            }
+        }
+        None
+    }
+
+    /// 译码出的存储指令的有效地址与字节数（用于解码缓存失效）
+    ///
+    /// 向量存储按 VLEN 上限（16 字节）保守估计。非存储指令返回
+    /// `None`。地址是翻译前的虚拟地址，与解码缓存的 PC 键一致。
+    fn store_span(&self, instr: &RvInstr) -> Option<(u32, u32)> {
+        let ea = |rs1: u8, offset: i32| self.read_reg(rs1).wrapping_add(offset as u32);
+        match *instr {
+            RvInstr::Sb { rs1, offset, .. } => Some((ea(rs1, offset), 1)),
+            RvInstr::Sh { rs1, offset, .. } => Some((ea(rs1, offset), 2)),
+            RvInstr::Sw { rs1, offset, .. } | RvInstr::Fsw { rs1, offset, .. } => {
+                Some((ea(rs1, offset), 4))
+            }
+            RvInstr::ScW { rs1, .. }
+            | RvInstr::AmoswapW { rs1, .. }
+            | RvInstr::AmoaddW { rs1, .. }
+            | RvInstr::AmoxorW { rs1, .. }
+            | RvInstr::AmoandW { rs1, .. }
+            | RvInstr::AmoorW { rs1, .. }
+            | RvInstr::AmominW { rs1, .. }
+            | RvInstr::AmomaxW { rs1, .. }
+            | RvInstr::AmominuW { rs1, .. }
+            | RvInstr::AmomaxuW { rs1, .. } => Some((self.read_reg(rs1), 4)),
+            RvInstr::VseV { rs1, .. } => Some((self.read_reg(rs1), 16)),
+            _ => None,
+        }
+    }
+
+    /// 按需包装监视点检测层后执行一条指令
+    fn execute_watched(
+        &mut self,
+        mem: &mut dyn Memory,
+        decoded: DecodedInstr,
+        current_pc: u32,
+        instr_word: u32,
+    ) {
+        if self.watchpoints.is_empty() {
+            self.execute_dispatch(mem, decoded, current_pc, instr_word);
+            return;
+        }
+
+        // 临时取出监视点列表，避免与 &mut self 的借用冲突
+        let watchpoints = std::mem::take(&mut self.watchpoints);
+        let mut wmem = WatchMemory::new(mem, &watchpoints);
+        self.execute_dispatch(&mut wmem, decoded, current_pc, instr_word);
+        let hit = wmem.into_hit();
+        self.watchpoints = watchpoints;
+
+        if let Some((addr, is_store, value)) = hit {
+            self.last_watchpoint = Some(WatchpointHit {
+                addr,
+                pc: current_pc,
+                is_store,
+                value,
+            });
+            // trap / WFI 等状态变化优先于监视点报告
+            if self.state == CpuState::Running {
+                self.state = CpuState::WatchpointHit(addr);
+            }
+        }
+    }
+
+    /// 执行一条指令（安装了 trace sink 时走跟踪路径）
+    fn execute_dispatch(
+        &mut self,
+        mem: &mut dyn Memory,
+        decoded: DecodedInstr,
+        current_pc: u32,
+        instr_word: u32,
+    ) {
+        if self.trace_sink.is_some() {
+            self.execute_traced(mem, decoded, current_pc, instr_word);
+        } else {
+            self.execute(mem, decoded, current_pc);
+        }
+    }
+
+    /// 带跟踪地执行一条指令：收集寄存器写入与内存访问，推送给 sink
+    fn execute_traced(
+        &mut self,
+        mem: &mut dyn Memory,
+        decoded: DecodedInstr,
+        current_pc: u32,
+        instr_word: u32,
+    ) {
+        self.trace_record = Some(TraceRecord::new(
+            current_pc,
+            instr_word,
+            decoded.instr,
+            self.status.privilege,
+        ));
+
+        let mut traced_mem = TracingMemory::new(mem);
+        self.execute(&mut traced_mem, decoded, current_pc);
+
+        let mut rec = self
+            .trace_record
+            .take()
+            .expect("trace record 在执行期间不应被移除");
+        rec.mem_accesses = traced_mem.into_accesses();
+
+        if let Some(sink) = self.trace_sink.as_mut() {
+            sink.record(&rec);
+        }
+    }
+
+    /// 运行多条指令
+    ///
+    /// # 参数
+    ///
+    /// * `mem` - 内存接口
+    /// * `max_instructions` - 最大执行指令数
+    ///
+    /// # 返回
+    ///
+    /// 执行的指令数量和最终 CPU 状态
+    ///
+    /// # 停止条件
+    ///
+    /// - 达到最大指令数
+    /// - 遇到 ECALL/EBREAK
+    /// - 遇到非法指令
+    pub fn run(&mut self, mem: &mut dyn Memory, max_instructions: u64) -> (u64, CpuState) {
+        let mut executed = 0;
+        for _ in 0..max_instructions {
+            let state = self.step(mem);
+            executed += 1;
+            if state != CpuState::Running {
+                return (executed, state);
+            }
+        }
+        (executed, self.state)
+    }
+
+    /// 以硬预算运行：保证执行不超过 `budget` 条指令
+    ///
+    /// 与 [`Self::run`] 的差别是停止原因的精确上报：除了状态离开
+    /// Running 之外，trap（转入处理程序后状态仍是 Running）也会
+    /// 立刻停止。事件驱动的外层循环由此可以按确定的指令数切片
+    /// 推进，在切片间穿插设备滴答或其他 hart，而不会错过任何
+    /// trap 边界。预算为 0 时不执行任何指令。
+    pub fn run_budgeted(&mut self, mem: &mut dyn Memory, budget: u64) -> RunOutcome {
+        let mut executed = 0;
+        while executed < budget {
+            // 已停机时 step 会原样返回，不计入执行数；WFI 照常
+            // 进入 step 评估中断，以便宿主注入的中断能够唤醒
+            if self.state != CpuState::Running && self.state != CpuState::WaitForInterrupt {
+                return RunOutcome {
+                    executed,
+                    reason: RunStopReason::StateChange(self.state),
+                };
+            }
+            let state = self.step(mem);
+            executed += 1;
+            if state != CpuState::Running {
+                return RunOutcome {
+                    executed,
+                    reason: RunStopReason::StateChange(state),
+                };
+            }
+            if let Some(cause) = self.last_trap {
+                return RunOutcome {
+                    executed,
+                    reason: RunStopReason::TrapTaken(cause),
+                };
+            }
+        }
+        RunOutcome {
+            executed,
+            reason: RunStopReason::BudgetExhausted,
+        }
+    }
+
+    /// 逐条执行的流式迭代器
+    ///
+    /// 每次迭代执行一条指令并产出 [`StepInfo`]（指令 PC、解码
+    /// 结果与执行后的状态），状态离开 `Running` 的那一步是最后
+    /// 一项。库用户可在迭代间穿插日志、故障注入或调度逻辑，
+    /// 无需自己重写取指-解码-执行循环：
+    ///
+    /// ```ignore
+    /// for info in cpu.steps(&mut mem) {
+    ///     println!("0x{:08x}: {}", info.pc, info.instr);
+    /// }
+    /// ```
+    pub fn steps<'a>(&'a mut self, mem: &'a mut dyn Memory) -> Steps<'a> {
+        Steps { cpu: self, mem }
+    }
+
+    /// 执行已解码的指令，委托到分 ISA 的执行单元
+    fn execute(&mut self, mem: &mut dyn Memory, decoded: DecodedInstr, current_pc: u32) {
+        let instr = decoded.instr;
+
+        if exu::rv32i::execute(self, mem, instr, current_pc) {
+            return;
+        }
+
+        if exu::rv32m::execute(self, instr) {
+            return;
+        }
+
+        if exu::rv32a::execute(self, mem, instr, current_pc) {
+            return;
+        }
+
+        // mstatus.FS=Off 时浮点指令触发非法指令异常
+        if instr.extension_name() == "F" && !self.fs_allows_fp() {
+            self.state = CpuState::IllegalInstruction(decoded.raw);
+            return;
+        }
+
+        if exu::rv32f::execute(self, mem, instr, current_pc) {
+            return;
+        }
+
+        if exu::rv32v::execute(self, mem, instr, current_pc) {
+            return;
+        }
+
+        if exu::zicsr::execute(self, instr, current_pc, decoded.raw) {
+            return;
+        }
+
+        if exu::zk::execute(self, instr) {
+            return;
+        }
+
+        if exu::zicond::execute(self, instr) {
+            return;
+        }
+
+        if exu::zicbo::execute(self, mem, instr, current_pc) {
+            return;
+        }
+
+        if exu::priv_instr::execute(self, instr, current_pc) {
+            return;
+        }
+
+        match instr {
+            RvInstr::Illegal { raw } => {
+                self.state = CpuState::IllegalInstruction(raw);
+            }
+            RvInstr::Custom { extension, opcode, raw, fields } => {
+                // 临时取出执行器，避免与 &mut self 的借用冲突
+                if let Some(mut exec) = self.custom_exus.remove(extension) {
+                    let handled = exec.execute(self, mem, opcode, raw, &fields);
+                    self.custom_exus.insert(extension, exec);
+                    if handled {
+                        return;
+                    }
+                }
+                self.state = CpuState::IllegalInstruction(raw);
+            }
+            _ => {
+                self.state = CpuState::IllegalInstruction(decoded.raw);
+            }
+        }
+    }
+
+    /// 打印所有存在的状态（用于调试）
+    ///
+    /// 输出内容包括：
+    /// - PC 和 CPU 状态
+    /// - 特权级
+    /// - 整数寄存器 x0-x31
+    /// - 浮点寄存器 f0-f31（如果启用 F 扩展）
+    /// - 向量寄存器 v0-v31（如果启用 V 扩展）
+    /// - 所有已注册的 CSR
+    pub fn dump_regs(&self) {
+        println!("═══════════════════════════════════════════════════════════════════");
+        println!("CPU Status Dump");
+        println!("═══════════════════════════════════════════════════════════════════");
+        
+        // PC 和状态
+        println!("PC: 0x{:08x}  State: {:?}  Privilege: {:?}", 
+                 self.pc, self.state, self.status.privilege);
+        println!();
+        
+        // 整数寄存器
+        println!("─── Integer Registers (x0-x31) ───────────────────────────────────");
+        for i in 0..32 {
+            if i % 4 == 0 {
+                print!("  ");
+            }
+            print!("x{:02}: 0x{:08x}  ", i, self.read_reg(i as u8));
+            if i % 4 == 3 {
+                println!();
+            }
+        }
+        
+        // 浮点寄存器（如果存在）
+        if let Some(fp) = &self.status.fp {
+            println!();
+            println!("─── Floating-Point Registers (f0-f31) ────────────────────────────");
+            for i in 0..32 {
+                if i % 4 == 0 {
+                    print!("  ");
+                }
+                print!("f{:02}: 0x{:08x}  ", i, fp.read(i as u8));
+                if i % 4 == 3 {
+                    println!();
+                }
+            }
+        }
+        
+        // 向量寄存器（如果存在）
+        if let Some(vec) = &self.status.vec {
+            println!();
+            println!("─── Vector Registers (v0-v31, VLEN=128) ──────────────────────────");
+            for i in 0..32 {
+                let v = vec.read(i as u8);
+                print!("  v{:02}: ", i);
+                for b in v.iter().rev() {
+                    print!("{:02x}", b);
+                }
+                println!();
+            }
+        }
+        
+        // CSR 寄存器（按地址排序）
+        let csr_snapshot = self.status.csr.snapshot();
+        if !csr_snapshot.is_empty() {
+            println!();
+            println!("─── Control and Status Registers (CSR) ───────────────────────────");
+            let mut csr_list: Vec<_> = csr_snapshot
+                .iter()
+                .map(|(&addr, &value)| (addr, value))
+                .collect();
+            csr_list.sort_by_key(|(addr, _)| *addr);
+            
+            for (i, &(addr, value)) in csr_list.iter().enumerate() {
+                if let Some(name) = csr_name(addr) {
+                    print!("  {:>12}: 0x{:08x}", name, value);
+                } else {
+                    print!("  0x{:03x}: 0x{:08x}", addr, value);
+                }
+                if i % 3 == 2 {
+                    println!();
+                } else {
+                    print!("  ");
+                }
+            }
+            // 如果最后一行没有换行，补上
+            if csr_list.len() % 3 != 0 {
+                println!();
+            }
+        }
+        
+        println!("═══════════════════════════════════════════════════════════════════");
+    }
+}
+
+impl Default for CpuCore {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+fn csr_name(addr: u16) -> Option<&'static str> {
+    fn find(slice: &[CsrEntry], addr: u16) -> Option<&'static str> {
+        slice.iter().find(|entry| entry.addr == addr).map(|entry| entry.name)
+    }
+
+    find(crate::cpu::csr_def::BASE_CSRS, addr)
+        .or_else(|| find(crate::cpu::csr_def::F_CSRS, addr))
+        .or_else(|| find(crate::cpu::csr_def::V_CSRS, addr))
+        .or_else(|| find(crate::cpu::csr_def::M_CSRS, addr))
+        .or_else(|| find(crate::cpu::csr_def::S_CSRS, addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::FlatMemory;
+
+    /// 将指令写入内存
+    fn write_instr(mem: &mut FlatMemory, addr: u32, instr: u32) {
+        mem.store32(addr, instr).unwrap();
+    }
+
+    #[test]
+    fn test_addi() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // addi x1, x0, 42
+        write_instr(&mut mem, 0, 0x02A00093);
+        cpu.step(&mut mem);
+
+        assert_eq!(cpu.read_reg(1), 42);
+        assert_eq!(cpu.pc(), 4);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // addi x1, x0, 10
+        write_instr(&mut mem, 0, 0x00A00093);
+        // addi x2, x0, 20
+        write_instr(&mut mem, 4, 0x01400113);
+        // add x3, x1, x2
+        write_instr(&mut mem, 8, 0x002081B3);
+
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+
+        assert_eq!(cpu.read_reg(1), 10);
+        assert_eq!(cpu.read_reg(2), 20);
+        assert_eq!(cpu.read_reg(3), 30);
+    }
+
+    #[test]
+    fn test_sub() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // addi x1, x0, 100
+        write_instr(&mut mem, 0, 0x06400093);
+        // addi x2, x0, 30
+        write_instr(&mut mem, 4, 0x01E00113);
+        // sub x3, x1, x2
+        write_instr(&mut mem, 8, 0x402081B3);
+
+        cpu.run(&mut mem, 3);
+
+        assert_eq!(cpu.read_reg(3), 70);
+    }
+
+    #[test]
+    fn test_lw_sw() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // addi x1, x0, 0x42 (将 0x42 存入 x1)
+        write_instr(&mut mem, 0, 0x04200093);
+        // addi x2, x0, 100 (x2 = 100, 作为基地址)
+        write_instr(&mut mem, 4, 0x06400113);
+        // sw x1, 0(x2) (将 x1 存入 mem[100])
+        write_instr(&mut mem, 8, 0x00112023);
+        // lw x3, 0(x2) (从 mem[100] 加载到 x3)
+        write_instr(&mut mem, 12, 0x00012183);
+
+        cpu.run(&mut mem, 4);
+
+        assert_eq!(cpu.read_reg(3), 0x42);
+        assert_eq!(mem.load32(100).unwrap(), 0x42);
+    }
+
+    #[test]
+    fn test_beq_taken() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // addi x1, x0, 5
+        write_instr(&mut mem, 0, 0x00500093);
+        // addi x2, x0, 5
+        write_instr(&mut mem, 4, 0x00500113);
+        // beq x1, x2, 8 (跳转到 PC+8=12)
+        write_instr(&mut mem, 8, 0x00208463);
+        // addi x3, x0, 1 (如果不跳转则执行)
+        write_instr(&mut mem, 12, 0x00100193);
+
+        cpu.run(&mut mem, 3);
+
+        // beq 应该跳转到地址 16 (8 + 8)
+        assert_eq!(cpu.pc(), 16);
+        // x3 不应该被修改（因为跳过了地址 12 的指令）
+        assert_eq!(cpu.read_reg(3), 0);
+    }
+
+    #[test]
+    fn test_beq_not_taken() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // addi x1, x0, 5
+        write_instr(&mut mem, 0, 0x00500093);
+        // addi x2, x0, 10
+        write_instr(&mut mem, 4, 0x00A00113);
+        // beq x1, x2, 8 (不跳转，因为 x1 != x2)
+        write_instr(&mut mem, 8, 0x00208463);
+        // addi x3, x0, 1 (应该执行)
+        write_instr(&mut mem, 12, 0x00100193);
+
+        cpu.run(&mut mem, 4);
+
+        assert_eq!(cpu.read_reg(3), 1);
+    }
+
+    #[test]
+    fn test_jal() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // jal x1, 8 (跳转到 PC+8=8，x1 = PC+4=4)
+        write_instr(&mut mem, 0, 0x008000EF);
+
+        cpu.step(&mut mem);
+
+        assert_eq!(cpu.read_reg(1), 4); // 返回地址
+        assert_eq!(cpu.pc(), 8); // 跳转目标
+    }
+
+    #[test]
+    fn test_lui() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // lui x1, 0x12345
+        write_instr(&mut mem, 0, 0x123450B7);
+
+        cpu.step(&mut mem);
+
+        assert_eq!(cpu.read_reg(1), 0x12345000);
+    }
+
+    #[test]
+    fn test_auipc() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0x100);
+
+        // auipc x1, 0x12345 (x1 = PC + (0x12345 << 12))
+        write_instr(&mut mem, 0x100, 0x12345097);
+
+        cpu.step(&mut mem);
+
+        assert_eq!(cpu.read_reg(1), 0x100 + 0x12345000);
+    }
+
+    #[test]
+    fn test_x0_always_zero() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // addi x0, x0, 42 (尝试写入 x0)
+        write_instr(&mut mem, 0, 0x02A00013);
+
+        cpu.step(&mut mem);
+
+        // x0 应该仍然是 0
+        assert_eq!(cpu.read_reg(0), 0);
+    }
+
+    #[test]
+    fn test_ecall() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // 设置 trap handler 地址
+        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
+
+        // ecall at PC=0
+        write_instr(&mut mem, 0, 0x00000073);
+
+        let state = cpu.step(&mut mem);
+
+        assert_eq!(state, CpuState::Running); // trap 后继续运行
+        assert_eq!(cpu.pc(), 0x100); // 跳转到 mtvec
+        assert_eq!(cpu.csr_read(0x341), 0); // mepc = 原 PC
+        assert_eq!(cpu.csr_read(0x342), 11); // mcause = 11 (ecall from M-mode)
+    }
+
+    #[test]
+    fn test_ebreak() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // 设置 trap handler 地址
+        cpu.csr_write(0x305, 0x200); // mtvec = 0x200
+
+        // ebreak at PC=0
+        write_instr(&mut mem, 0, 0x00100073);
+
+        let state = cpu.step(&mut mem);
+
+        assert_eq!(state, CpuState::Running); // trap 后继续运行
+        assert_eq!(cpu.pc(), 0x200); // 跳转到 mtvec
+        assert_eq!(cpu.csr_read(0x341), 0); // mepc = 原 PC
+        assert_eq!(cpu.csr_read(0x342), 3); // mcause = 3 (breakpoint)
+    }
+
+    #[test]
+    fn test_shift_instructions() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // addi x1, x0, 0x10 (x1 = 16)
+        write_instr(&mut mem, 0, 0x01000093);
+        // slli x2, x1, 2 (x2 = 16 << 2 = 64)
+        write_instr(&mut mem, 4, 0x00209113);
+        // srli x3, x2, 1 (x3 = 64 >> 1 = 32)
+        write_instr(&mut mem, 8, 0x00115193);
+
+        cpu.run(&mut mem, 3);
+
+        assert_eq!(cpu.read_reg(1), 16);
+        assert_eq!(cpu.read_reg(2), 64);
+        assert_eq!(cpu.read_reg(3), 32);
+    }
+
+    #[test]
+    fn test_slt() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // addi x1, x0, -5 (x1 = -5)
+        write_instr(&mut mem, 0, 0xFFB00093);
+        // addi x2, x0, 10 (x2 = 10)
+        write_instr(&mut mem, 4, 0x00A00113);
+        // slt x3, x1, x2 (x3 = 1, 因为 -5 < 10)
+        write_instr(&mut mem, 8, 0x0020A1B3);
+
+        cpu.run(&mut mem, 3);
+
+        assert_eq!(cpu.read_reg(3), 1);
+    }
+
+    #[test]
+    fn test_simple_loop() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
 
         // 设置 trap handler 地址
         cpu.csr_write(0x305, 0x100); // mtvec = 0x100
 
-        // 简单循环：计算 1+2+3 = 6
-        // addi x1, x0, 0     # x1 = sum = 0
-        write_instr(&mut mem, 0, 0x00000093);
-        // addi x2, x0, 1     # x2 = i = 1
-        write_instr(&mut mem, 4, 0x00100113);
-        // addi x3, x0, 4     # x3 = limit = 4
-        write_instr(&mut mem, 8, 0x00400193);
-        // loop:
-        // add x1, x1, x2     # sum += i
-        write_instr(&mut mem, 12, 0x002080B3);
-        // addi x2, x2, 1     # i++
-        write_instr(&mut mem, 16, 0x00110113);
-        // blt x2, x3, -8     # if i < limit goto loop (PC-8)
-        write_instr(&mut mem, 20, 0xFE314CE3);
-        // ecall              # 结束
-        write_instr(&mut mem, 24, 0x00000073);
+        // 简单循环：计算 1+2+3 = 6
+        // addi x1, x0, 0     # x1 = sum = 0
+        write_instr(&mut mem, 0, 0x00000093);
+        // addi x2, x0, 1     # x2 = i = 1
+        write_instr(&mut mem, 4, 0x00100113);
+        // addi x3, x0, 4     # x3 = limit = 4
+        write_instr(&mut mem, 8, 0x00400193);
+        // loop:
+        // add x1, x1, x2     # sum += i
+        write_instr(&mut mem, 12, 0x002080B3);
+        // addi x2, x2, 1     # i++
+        write_instr(&mut mem, 16, 0x00110113);
+        // blt x2, x3, -8     # if i < limit goto loop (PC-8)
+        write_instr(&mut mem, 20, 0xFE314CE3);
+        // ecall              # 结束
+        write_instr(&mut mem, 24, 0x00000073);
+
+        // 运行直到 PC 跳转到 trap handler
+        let mut executed = 0;
+        while cpu.pc() != 0x100 && executed < 100 {
+            cpu.step(&mut mem);
+            executed += 1;
+        }
+
+        assert_eq!(cpu.read_reg(1), 6); // 1 + 2 + 3 = 6
+        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
+        assert_eq!(cpu.csr_read(0x341), 24); // mepc = ecall 指令地址
+        assert_eq!(cpu.csr_read(0x342), 11); // mcause = ecall from M-mode
+        assert!(executed < 100);
+    }
+
+    #[test]
+    fn test_cpu_builder_basic() {
+        // 使用 CpuBuilder 创建带 M 扩展的 CPU
+        let cpu = CpuBuilder::new(0x1000)
+            .with_m_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        assert_eq!(cpu.pc(), 0x1000);
+        assert_eq!(cpu.state(), CpuState::Running);
+    }
+
+    #[test]
+    fn test_misa_reflects_configured_extensions() {
+        use csr_def::{misa, CSR_MISA};
+
+        let cpu = CpuBuilder::new(0)
+            .with_m_extension()
+            .with_f_extension()
+            .build()
+            .expect("配置无冲突");
+
+        let value = cpu.csr_read(CSR_MISA);
+        assert_eq!(value, misa::MXL32 | misa::I | misa::U | misa::M | misa::F);
+        assert_eq!(value & (misa::A | misa::V), 0, "未启用的扩展位不应置位");
+    }
+
+    #[test]
+    fn test_misa_warl_write_rules() {
+        use csr_def::{misa, CSR_MISA};
+
+        let mut cpu = CpuBuilder::new(0)
+            .with_m_extension()
+            .build()
+            .expect("配置无冲突");
+        let reset = cpu.csr_read(CSR_MISA);
+
+        // 写 0：可开关的扩展位（M）被清除，MXL/I/U 保持只读
+        cpu.csr_write(CSR_MISA, 0);
+        assert_eq!(cpu.csr_read(CSR_MISA), misa::MXL32 | misa::I | misa::U);
+
+        // 试图置位未实现的扩展（V）：该位写入被忽略，M 可以恢复
+        cpu.csr_write(CSR_MISA, reset | misa::V);
+        assert_eq!(cpu.csr_read(CSR_MISA), reset);
+    }
+
+    #[test]
+    fn test_misa_disable_m_makes_mul_illegal() {
+        use csr_def::{misa, CSR_MISA};
+
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_m_extension()
+            .build()
+            .expect("配置无冲突");
+
+        // addi x1, x0, 6
+        write_instr(&mut mem, 0, 0x00600093);
+        // addi x2, x0, 7
+        write_instr(&mut mem, 4, 0x00700113);
+        // mul x3, x1, x2
+        write_instr(&mut mem, 8, 0x022081B3);
+        // mul x4, x1, x2
+        write_instr(&mut mem, 12, 0x02208233);
+
+        cpu.run(&mut mem, 3);
+        assert_eq!(cpu.read_reg(3), 42, "misa.M 置位时 mul 正常执行");
+
+        // 关闭 M 扩展后，同样的 mul 按非法指令处理
+        let reset = cpu.csr_read(CSR_MISA);
+        cpu.csr_write(CSR_MISA, reset & !misa::M);
+        cpu.step(&mut mem);
+        assert_eq!(cpu.state(), CpuState::IllegalInstruction(0x02208233));
+
+        // 重新打开 M 扩展即可继续执行
+        cpu.csr_write(CSR_MISA, reset);
+        cpu.set_state(CpuState::Running);
+        cpu.set_pc(12);
+        cpu.step(&mut mem);
+        assert_eq!(cpu.read_reg(4), 42);
+    }
+
+    #[test]
+    fn test_run_budgeted_reports_stop_reason() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0).build().expect("配置无冲突");
+
+        // nop * 2
+        write_instr(&mut mem, 0, 0x00000013);
+        write_instr(&mut mem, 4, 0x00000013);
+        // ecall（trap 后转向 mtvec = 12）
+        write_instr(&mut mem, 8, 0x00000073);
+        // nop
+        write_instr(&mut mem, 12, 0x00000013);
+        // 非法指令字
+        write_instr(&mut mem, 16, 0xFFFFFFFF);
+        cpu.csr_write(csr_def::CSR_MTVEC, 12);
+
+        // 预算 0：一条也不执行
+        let outcome = cpu.run_budgeted(&mut mem, 0);
+        assert_eq!(outcome.executed, 0);
+        assert_eq!(outcome.reason, RunStopReason::BudgetExhausted);
+
+        // 精确的指令切片：正好 2 条 nop
+        let outcome = cpu.run_budgeted(&mut mem, 2);
+        assert_eq!(outcome.executed, 2);
+        assert_eq!(outcome.reason, RunStopReason::BudgetExhausted);
+        assert_eq!(cpu.pc(), 8);
+
+        // trap 边界立刻停止：CPU 已转向处理程序但仍在运行
+        let outcome = cpu.run_budgeted(&mut mem, 10);
+        assert_eq!(outcome.executed, 1);
+        assert_eq!(outcome.reason, RunStopReason::TrapTaken(TrapCause::EcallFromM));
+        assert_eq!(cpu.state(), CpuState::Running);
+        assert_eq!(cpu.pc(), 12);
+
+        // 状态离开 Running：携带新状态
+        let outcome = cpu.run_budgeted(&mut mem, 10);
+        assert_eq!(outcome.executed, 2);
+        assert_eq!(
+            outcome.reason,
+            RunStopReason::StateChange(CpuState::IllegalInstruction(0xFFFFFFFF))
+        );
+
+        // 已停机后再调用：不执行、原样上报
+        let outcome = cpu.run_budgeted(&mut mem, 10);
+        assert_eq!(outcome.executed, 0);
+        assert_eq!(
+            outcome.reason,
+            RunStopReason::StateChange(CpuState::IllegalInstruction(0xFFFFFFFF))
+        );
+    }
+
+    #[test]
+    fn test_counters_advance_and_write_through() {
+        use csr_def::{CSR_INSTRET, CSR_MCYCLE, CSR_MINSTRET, CSR_MINSTRETH};
+
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicsr_extension()
+            .build()
+            .expect("配置无冲突");
+
+        // nop * 3
+        write_instr(&mut mem, 0, 0x00000013);
+        write_instr(&mut mem, 4, 0x00000013);
+        write_instr(&mut mem, 8, 0x00000013);
+        // csrrw x0, minstret, x0  # 客体清零 minstret
+        write_instr(&mut mem, 12, 0xB0201073);
+        // nop
+        write_instr(&mut mem, 16, 0x00000013);
+        // ecall（触发异常，不算退休）
+        write_instr(&mut mem, 20, 0x00000073);
+
+        cpu.run(&mut mem, 3);
+        assert_eq!(cpu.csr_read(CSR_MINSTRET), 3);
+        assert_eq!(cpu.csr_read(CSR_MCYCLE), 3);
+        // 用户态 instret 是 M 态计数器的只读别名
+        assert_eq!(cpu.csr_read(CSR_INSTRET), 3);
+
+        // 写入优先于本步自增：清零后读回正好是 0，mcycle 照常推进
+        cpu.step(&mut mem);
+        assert_eq!(cpu.csr_read(CSR_MINSTRET), 0);
+        assert_eq!(cpu.csr_read(CSR_MCYCLE), 4);
+
+        // 之后从写入的值继续计数
+        cpu.step(&mut mem);
+        assert_eq!(cpu.csr_read(CSR_MINSTRET), 1);
+
+        // ecall 触发异常：该指令不退休，minstret 不变
+        cpu.step(&mut mem);
+        assert_eq!(cpu.csr_read(CSR_MINSTRET), 1);
+        assert_eq!(cpu.csr_read(CSR_MCYCLE), 6);
+
+        // 高半字写穿：宿主侧写入同样生效
+        cpu.csr_write(CSR_MINSTRETH, 2);
+        assert_eq!(cpu.csr_read(CSR_MINSTRETH), 2);
+        assert_eq!(cpu.csr_read(CSR_MINSTRET), 1, "低半字不受影响");
+    }
+
+    #[test]
+    fn test_mcountinhibit_freezes_counters() {
+        use csr_def::{mcountinhibit, CSR_MCOUNTINHIBIT, CSR_MCYCLE, CSR_MINSTRET};
+
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0).build().expect("配置无冲突");
+        for i in 0..6 {
+            write_instr(&mut mem, i * 4, 0x00000013); // nop
+        }
+
+        // WARL：只有 CY/IR 可写，TM 等保留位读回 0
+        cpu.csr_write(CSR_MCOUNTINHIBIT, 0xFFFF_FFFF);
+        assert_eq!(
+            cpu.csr_read(CSR_MCOUNTINHIBIT),
+            mcountinhibit::CY | mcountinhibit::IR
+        );
+
+        cpu.run(&mut mem, 3);
+        assert_eq!(cpu.csr_read(CSR_MINSTRET), 0, "IR 置位时 minstret 冻结");
+        assert_eq!(cpu.csr_read(CSR_MCYCLE), 0, "CY 置位时 mcycle 冻结");
+
+        // 解除抑制后从当前值继续计数
+        cpu.csr_write(CSR_MCOUNTINHIBIT, 0);
+        cpu.run(&mut mem, 3);
+        assert_eq!(cpu.csr_read(CSR_MINSTRET), 3);
+        assert_eq!(cpu.csr_read(CSR_MCYCLE), 3);
+    }
+
+    #[test]
+    fn test_amo_add_and_swap() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_a_extension()
+            .build()
+            .expect("配置无冲突");
+
+        mem.store32(256, 10).unwrap();
+        // addi x6, x0, 256 (基地址)
+        write_instr(&mut mem, 0, 0x10000313);
+        // addi x8, x0, 5
+        write_instr(&mut mem, 4, 0x00500413);
+        // amoadd.w x5, x8, (x6)
+        write_instr(&mut mem, 8, 0x008322AF);
+        // amoswap.w x7, x8, (x6)
+        write_instr(&mut mem, 12, 0x088323AF);
+
+        cpu.run(&mut mem, 4);
+
+        assert_eq!(cpu.read_reg(5), 10, "amoadd 应返回旧值");
+        assert_eq!(cpu.read_reg(7), 15, "amoswap 应返回 amoadd 后的值");
+        assert_eq!(mem.load32(256).unwrap(), 5, "amoswap 应写入 x8");
+    }
+
+    #[test]
+    fn test_lr_sc_pair() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_a_extension()
+            .build()
+            .expect("配置无冲突");
+
+        mem.store32(256, 0x1234).unwrap();
+        // addi x6, x0, 256
+        write_instr(&mut mem, 0, 0x10000313);
+        // addi x8, x0, 7
+        write_instr(&mut mem, 4, 0x00700413);
+        // lr.w x5, (x6)
+        write_instr(&mut mem, 8, 0x100322AF);
+        // sc.w x7, x8, (x6) — 保留有效，应成功
+        write_instr(&mut mem, 12, 0x188323AF);
+        // sc.w x9, x8, (x6) — 保留已清除，应失败
+        write_instr(&mut mem, 16, 0x188324AF);
+
+        cpu.run(&mut mem, 5);
+
+        assert_eq!(cpu.read_reg(5), 0x1234, "lr.w 应读出旧值");
+        assert_eq!(cpu.read_reg(7), 0, "首个 sc.w 应成功");
+        assert_eq!(cpu.read_reg(9), 1, "无保留的 sc.w 应失败");
+        assert_eq!(mem.load32(256).unwrap(), 7, "成功的 sc.w 应写入内存");
+    }
+
+    #[test]
+    fn test_zk_sha256_pack_aes() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zk_extension()
+            .build()
+            .expect("配置无冲突");
+
+        // lui x1, 0x12345 ; addi x1, x1, 0x678 → x1 = 0x12345678
+        write_instr(&mut mem, 0, 0x123450B7);
+        write_instr(&mut mem, 4, 0x67808093);
+        // sha256sum0 x5, x1
+        write_instr(&mut mem, 8, 0x10009293);
+        // brev8 x6, x1
+        write_instr(&mut mem, 12, 0x6870D313);
+        // pack x7, x1, x1
+        write_instr(&mut mem, 16, 0x0810C3B3);
+        // aes32esi x8, x0, x1, bs=0 → x8 = sbox[0x78]
+        write_instr(&mut mem, 20, 0x22100433);
+
+        cpu.run(&mut mem, 6);
+
+        assert_eq!(cpu.read_reg(5), 0x66146474, "sha256sum0 结果不符");
+        assert_eq!(cpu.read_reg(6), 0x482C6A1E, "brev8 应逐字节反转位序");
+        assert_eq!(cpu.read_reg(7), 0x56785678, "pack 应拼接低半字");
+        assert_eq!(cpu.read_reg(8), 0xBC, "aes32esi 应查 S-box 后异或 rs1");
+    }
+
+    #[test]
+    fn test_custom_executor_dispatch() {
+        use crate::isa::{InstrDecoder, IsaExtension, InstrSignature};
+
+        // MAC 指令：rd += rs1 * rs2，占用 custom-0 opcode (0b0001011)
+        struct MacDecoder;
+        impl InstrDecoder for MacDecoder {
+            fn name(&self) -> &str {
+                "Mac"
+            }
+            fn decode(&self, raw: u32) -> Option<DecodedInstr> {
+                if (raw & 0x7F) == 0b0001011 {
+                    Some(DecodedInstr {
+                        raw,
+                        instr: RvInstr::Custom {
+                            extension: "mac",
+                            opcode: 0b0001011,
+                            raw,
+                            fields: CustomFields::new()
+                                .with_rd(isa::rd(raw))
+                                .with_rs1(isa::rs1(raw))
+                                .with_rs2(isa::rs2(raw)),
+                        },
+                    })
+                } else {
+                    None
+                }
+            }
+            fn handled_opcodes(&self) -> Option<&[u32]> {
+                static OPS: [u32; 1] = [0b0001011];
+                Some(&OPS)
+            }
+        }
+
+        struct MacExecutor;
+        impl CustomExecutor for MacExecutor {
+            fn execute(
+                &mut self,
+                cpu: &mut CpuCore,
+                _mem: &mut dyn Memory,
+                _opcode: u8,
+                _raw: u32,
+                fields: &CustomFields,
+            ) -> bool {
+                let (Some(rd), Some(rs1), Some(rs2)) = (fields.rd, fields.rs1, fields.rs2)
+                else {
+                    return false;
+                };
+                let acc = cpu
+                    .read_reg(rd)
+                    .wrapping_add(cpu.read_reg(rs1).wrapping_mul(cpu.read_reg(rs2)));
+                cpu.write_reg(rd, acc);
+                true
+            }
+        }
+
+        let signatures = vec![InstrSignature::new(
+            IsaExtension::Custom("mac"),
+            "MAC",
+            0x7F,
+            0b0001011,
+        )];
+        let mut cpu = CpuBuilder::new(0)
+            .map_isa_config(|config| {
+                config.with_custom_decoder(
+                    IsaExtension::Custom("mac"),
+                    Arc::new(MacDecoder),
+                    signatures,
+                )
+            })
+            .with_custom_executor("mac", Box::new(MacExecutor))
+            .build()
+            .expect("配置无冲突");
+
+        let mut mem = FlatMemory::new(1024, 0);
+        // addi x1, x0, 6 ; addi x2, x0, 7 ; addi x3, x0, 100
+        write_instr(&mut mem, 0, 0x00600093);
+        write_instr(&mut mem, 4, 0x00700113);
+        write_instr(&mut mem, 8, 0x06400193);
+        // mac x3, x1, x2 — custom-0 编码
+        write_instr(&mut mem, 12, 0x0020818B);
+
+        cpu.run(&mut mem, 4);
+        assert_eq!(cpu.read_reg(3), 142, "mac 应计算 rd + rs1*rs2");
+
+        // 未注册执行器时，Custom 指令仍按非法指令处理
+        let mut cpu = CpuBuilder::new(0)
+            .map_isa_config(|config| {
+                config.with_custom_decoder(
+                    IsaExtension::Custom("mac"),
+                    Arc::new(MacDecoder),
+                    Vec::new(),
+                )
+            })
+            .build()
+            .expect("配置无冲突");
+        write_instr(&mut mem, 0, 0x0020818B);
+        let (_, state) = cpu.run(&mut mem, 1);
+        assert_eq!(state, CpuState::IllegalInstruction(0x0020818B));
+    }
+
+    #[test]
+    fn test_zicond_czero() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicond_extension()
+            .build()
+            .expect("配置无冲突");
+
+        // addi x1, x0, 42 ; addi x2, x0, 0
+        write_instr(&mut mem, 0, 0x02A00093);
+        write_instr(&mut mem, 4, 0x00000113);
+        // czero.eqz x3, x1, x2 — rs2 为零，rd 清零
+        write_instr(&mut mem, 8, 0x0E20D1B3);
+        // czero.nez x4, x1, x2 — rs2 为零，rd = rs1
+        write_instr(&mut mem, 12, 0x0E20F233);
+        // addi x2, x0, 1
+        write_instr(&mut mem, 16, 0x00100113);
+        // czero.eqz x5, x1, x2 — rs2 非零，rd = rs1
+        write_instr(&mut mem, 20, 0x0E20D2B3);
+        // czero.nez x6, x1, x2 — rs2 非零，rd 清零
+        write_instr(&mut mem, 24, 0x0E20F333);
+
+        cpu.run(&mut mem, 7);
+
+        assert_eq!(cpu.read_reg(3), 0, "czero.eqz 在 rs2==0 时应清零");
+        assert_eq!(cpu.read_reg(4), 42, "czero.nez 在 rs2==0 时应保留 rs1");
+        assert_eq!(cpu.read_reg(5), 42, "czero.eqz 在 rs2!=0 时应保留 rs1");
+        assert_eq!(cpu.read_reg(6), 0, "czero.nez 在 rs2!=0 时应清零");
+    }
+
+    #[test]
+    fn test_zicbo_zero_block() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicbo_extension()
+            .build()
+            .expect("配置无冲突");
+
+        // 把 [252, 324) 填上非零数据，块边界外的值应保持不变
+        for addr in (252..324).step_by(4) {
+            mem.store32(addr, 0xFFFF_FFFF).unwrap();
+        }
+
+        // addi x1, x0, 300 — 块内任意地址
+        write_instr(&mut mem, 0, 0x12C00093);
+        // cbo.zero (x1) — 清零 [256, 320)
+        write_instr(&mut mem, 4, 0x0040A00F);
+        // cbo.clean (x1) / cbo.inval (x1) — 空操作，但必须能解码执行
+        write_instr(&mut mem, 8, 0x0010A00F);
+        write_instr(&mut mem, 12, 0x0000A00F);
+
+        let (executed, state) = cpu.run(&mut mem, 4);
+        assert_eq!(executed, 4);
+        assert_eq!(state, CpuState::Running);
+
+        assert_eq!(mem.load32(252).unwrap(), 0xFFFF_FFFF, "块前的数据不应被动");
+        for addr in (256..320).step_by(4) {
+            assert_eq!(mem.load32(addr).unwrap(), 0, "块内应全部清零");
+        }
+        assert_eq!(mem.load32(320).unwrap(), 0xFFFF_FFFF, "块后的数据不应被动");
+    }
+
+    #[test]
+    fn test_vector_load_add_store() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_v_extension()
+            .build()
+            .expect("配置无冲突");
+
+        for i in 0..4u32 {
+            mem.store32(256 + i * 4, i + 1).unwrap();
+        }
+
+        // addi x6, x0, 4 (AVL = 4)
+        write_instr(&mut mem, 0, 0x00400313);
+        // vsetvli x1, x6, e32,m1
+        write_instr(&mut mem, 4, 0x010370D7);
+        // addi x5, x0, 256
+        write_instr(&mut mem, 8, 0x10000293);
+        // vle32.v v1, (x5)
+        write_instr(&mut mem, 12, 0x0202E087);
+        // vadd.vv v2, v1, v1
+        write_instr(&mut mem, 16, 0x02108157);
+        // addi x7, x0, 512
+        write_instr(&mut mem, 20, 0x20000393);
+        // vse32.v v2, (x7)
+        write_instr(&mut mem, 24, 0x0203E127);
+
+        cpu.run(&mut mem, 7);
+
+        assert_eq!(cpu.read_reg(1), 4, "vsetvli 应返回 vl=4");
+        for i in 0..4u32 {
+            assert_eq!(
+                mem.load32(512 + i * 4).unwrap(),
+                (i + 1) * 2,
+                "元素 {i} 应为加倍后的值"
+            );
+        }
+    }
+
+    #[test]
+    fn test_vsetvli_caps_at_vlmax() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_v_extension()
+            .build()
+            .expect("配置无冲突");
+
+        // addi x6, x0, 100 (AVL = 100，远超 VLMAX)
+        write_instr(&mut mem, 0, 0x06400313);
+        // vsetvli x1, x6, e32,m1 → VLMAX = 128/32 = 4
+        write_instr(&mut mem, 4, 0x010370D7);
+
+        cpu.run(&mut mem, 2);
+
+        assert_eq!(cpu.read_reg(1), 4, "vl 应被截断到 VLMAX");
+        assert_eq!(cpu.csr_read(0xC20), 4, "vl CSR 应为 4");
+        assert_eq!(cpu.csr_read(0xC21), 0x010, "vtype CSR 应为写入的 vtypei");
+    }
+
+    #[test]
+    fn test_cpu_builder_with_f_extension() {
+        // 使用 CpuBuilder 创建带 F 扩展的 CPU
+        let cpu = CpuBuilder::new(0)
+            .with_f_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 验证 F 扩展 CSR 已注册 (fflags=0x001, frm=0x002, fcsr=0x003)
+        // 通过 status snapshot 检查
+        let snapshot = cpu.snapshot();
+        // CSR 应该包含 F 扩展的 CSR
+        assert!(snapshot.csr.contains_key(&0x001), "fflags 应已注册");
+        assert!(snapshot.csr.contains_key(&0x002), "frm 应已注册");
+        assert!(snapshot.csr.contains_key(&0x003), "fcsr 应已注册");
+    }
+
+    #[test]
+    fn test_fp_regs_nan_boxing() {
+        // F32 宽度：32 位值原样读写，永远视为已装箱
+        let mut f32_regs = FpRegs::new();
+        assert_eq!(f32_regs.width(), FpWidth::F32);
+        f32_regs.write(3, 0x3F80_0000);
+        assert_eq!(f32_regs.read(3), 0x3F80_0000);
+        assert!(f32_regs.is_boxed(3));
+
+        // F64 宽度：32 位写入必须 NaN-box（高 32 位全 1）
+        let mut f64_regs = FpRegs::with_width(FpWidth::F64);
+        f64_regs.write(2, 0x3F80_0000);
+        assert_eq!(f64_regs.read64(2), 0xFFFF_FFFF_3F80_0000, "32 位写入应被装箱");
+        assert!(f64_regs.is_boxed(2));
+        assert_eq!(f64_regs.read(2), 0x3F80_0000, "装箱值按 32 位读回原值");
+
+        // 未装箱的 64 位值按 32 位读出时给出规范 NaN
+        f64_regs.write64(2, 0x0000_0001_3F80_0000);
+        assert!(!f64_regs.is_boxed(2));
+        assert_eq!(f64_regs.read(2), 0x7FC0_0000, "未装箱值应读出规范 NaN");
+
+        // reset 保持宽度不变
+        f64_regs.reset();
+        assert_eq!(f64_regs.width(), FpWidth::F64);
+        assert_eq!(f64_regs.read64(2), 0);
+    }
+
+    #[test]
+    fn test_cpu_builder_d_extension_widens_fp_regs() {
+        // D 扩展下浮点指令的 32 位结果读回时应保持装箱不变式
+        let mut cpu = CpuBuilder::new(0)
+            .with_d_extension()
+            .build()
+            .expect("配置无冲突");
+        let mut mem = FlatMemory::new(4096, 0);
+
+        cpu.write_fp(1, 0x3F80_0000); // f1 = 1.0f
+        cpu.write_fp(2, 0x4000_0000); // f2 = 2.0f
+        // fadd.s f3, f1, f2
+        write_instr(&mut mem, 0, 0x002081D3);
+        cpu.run(&mut mem, 1);
+
+        assert_eq!(cpu.read_fp(3), 0x4040_0000, "f3 应为 3.0f");
+        let fp = cpu.status.fp.as_ref().expect("FP 寄存器堆应已启用");
+        assert_eq!(fp.width(), FpWidth::F64);
+        assert!(fp.is_boxed(3), "32 位结果写入 64 位寄存器堆后应正确装箱");
+    }
+
+    #[test]
+    fn test_status_snapshot_json_roundtrip() {
+        let mut cpu = CpuBuilder::new(0)
+            .with_f_extension()
+            .build()
+            .expect("配置无冲突");
+        cpu.write_reg(1, 42);
+        cpu.write_reg(31, 0xDEAD_BEEF);
+
+        let snapshot = cpu.snapshot();
+        let json = snapshot.to_json();
+        let parsed = StatusSnapshot::from_json(&json).expect("自身输出应能解析");
+        assert_eq!(parsed, snapshot);
+        // 序列化是确定性的（CSR 按地址排序），可直接做黄金状态比对
+        assert_eq!(parsed.to_json(), json);
+
+        // 非法输入给出错误而不是 panic
+        assert!(StatusSnapshot::from_json("{\"int\":[1,2]}").is_err());
+        assert!(StatusSnapshot::from_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_status_snapshot_diff() {
+        let mut cpu = CpuBuilder::new(0)
+            .with_f_extension()
+            .build()
+            .expect("配置无冲突");
+        let before = cpu.snapshot();
+
+        // 相等的快照差异为空
+        assert!(before.diff(&before).is_empty());
+
+        cpu.write_reg(10, 42);
+        cpu.write_fp(2, 0x3F80_0000);
+        cpu.csr_write(csr_def::CSR_MSCRATCH, 0x55);
+        let after = cpu.snapshot();
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.int, vec![(10, 0, 42)]);
+        assert_eq!(diff.fp, vec![(2, Some(0), Some(0x3F80_0000))]);
+        // CSR 差异含 mscratch 与被 write_fp 置脏的 mstatus.FS
+        assert!(diff.csr.contains(&(csr_def::CSR_MSCRATCH, Some(0), Some(0x55))));
+        assert_eq!(diff.len(), 4, "{}", diff);
+
+        // 打印形式逐行列出差异，含 ABI 寄存器名
+        let report = diff.to_string();
+        assert!(report.contains("x10 (a0): 0x00000000 -> 0x0000002a"), "{}", report);
+        assert!(report.contains("f2: 0x00000000 -> 0x3f800000"), "{}", report);
+        assert!(report.contains("csr 0x340: 0x00000000 -> 0x00000055"), "{}", report);
+
+        // 扩展配置不同侧的寄存器堆标记为 <absent>
+        let plain = CpuBuilder::new(0).build().expect("配置无冲突").snapshot();
+        let cross = plain.diff(&after);
+        assert!(cross.fp.contains(&(2, None, Some(0x3F80_0000))));
+        assert!(cross.to_string().contains("<absent>"));
+    }
+
+    #[test]
+    fn test_cpu_builder_with_v_extension() {
+        // 使用 CpuBuilder 创建带 V 扩展的 CPU
+        let cpu = CpuBuilder::new(0)
+            .with_v_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        let snapshot = cpu.snapshot();
+        // V 扩展 CSR: vstart=0x008, vl=0xC20, vtype=0xC21
+        assert!(snapshot.csr.contains_key(&0x008), "vstart 应已注册");
+        assert!(snapshot.csr.contains_key(&0xC20), "vl 应已注册");
+        assert!(snapshot.csr.contains_key(&0xC21), "vtype 应已注册");
+        // vlenb 应该有默认值 16 (VLEN=128, vlenb=VLEN/8=16)
+        assert_eq!(snapshot.csr.get(&0xC22), Some(&16), "vlenb 应为 16");
+    }
+
+    #[test]
+    fn test_cpu_builder_m_mode_csrs() {
+        // 默认启用 M-mode
+        let cpu = CpuBuilder::new(0)
+            .build()
+            .expect("配置无冲突");
+        
+        let snapshot = cpu.snapshot();
+        // M-mode CSR: mstatus=0x300, mepc=0x341
+        assert!(snapshot.csr.contains_key(&0x300), "mstatus 应已注册");
+        assert!(snapshot.csr.contains_key(&0x341), "mepc 应已注册");
+    }
+
+    #[test]
+    fn test_cpu_builder_run_program() {
+        // 使用 CpuBuilder 创建 CPU 并运行简单程序
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_m_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 设置 trap handler
+        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
+        
+        // addi x1, x0, 42
+        write_instr(&mut mem, 0, 0x02A00093);
+        // addi x2, x1, 8
+        write_instr(&mut mem, 4, 0x00808113);
+        // ecall
+        write_instr(&mut mem, 8, 0x00000073);
+        
+        // 运行直到 PC 跳转到 trap handler
+        let mut executed = 0;
+        while cpu.pc() != 0x100 && executed < 10 {
+            cpu.step(&mut mem);
+            executed += 1;
+        }
+        
+        assert_eq!(cpu.read_reg(1), 42);
+        assert_eq!(cpu.read_reg(2), 50);
+        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
+        assert_eq!(cpu.csr_read(0x342), 11); // mcause = ecall from M-mode
+        assert_eq!(executed, 3);
+    }
+
+    #[test]
+    fn test_rv32im_full_program() {
+        // RV32IM 完整测试：计算阶乘 5! = 120
+        // 使用乘法指令 mul
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_m_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 设置 trap handler
+        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
+        
+        // 程序：计算 5! = 1*2*3*4*5 = 120
+        // x1 = result = 1
+        // x2 = i = 1
+        // x3 = limit = 6
+        // loop: result = result * i; i++; if i < limit goto loop
+        
+        // addi x1, x0, 1      # x1 = result = 1
+        write_instr(&mut mem, 0, 0x00100093);
+        // addi x2, x0, 1      # x2 = i = 1  
+        write_instr(&mut mem, 4, 0x00100113);
+        // addi x3, x0, 6      # x3 = limit = 6
+        write_instr(&mut mem, 8, 0x00600193);
+        
+        // loop (addr 12):
+        // mul x1, x1, x2      # result *= i (RV32M)
+        // opcode=0x33, funct3=0, funct7=0x01, rd=1, rs1=1, rs2=2
+        // 0000001 00010 00001 000 00001 0110011 = 0x022080B3
+        write_instr(&mut mem, 12, 0x022080B3);
+        
+        // addi x2, x2, 1      # i++
+        write_instr(&mut mem, 16, 0x00110113);
+        
+        // blt x2, x3, -8      # if i < limit goto loop (PC = 12)
+        // imm[12|10:5]=1111111, rs2=3, rs1=2, funct3=100, imm[4:1|11]=1100, opcode=1100011
+        // 要跳回 addr 12，当前 addr 20，offset = 12 - 20 = -8
+        write_instr(&mut mem, 20, 0xFE314CE3);
+        
+        // ecall               # 结束
+        write_instr(&mut mem, 24, 0x00000073);
+        
+        // 运行直到 PC 跳转到 trap handler
+        let mut executed = 0;
+        while cpu.pc() != 0x100 && executed < 50 {
+            cpu.step(&mut mem);
+            executed += 1;
+        }
+        
+        // 验证结果
+        assert_eq!(cpu.read_reg(1), 120, "5! = 120");
+        assert_eq!(cpu.read_reg(2), 6, "i 最终等于 6");
+        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
+        assert_eq!(cpu.csr_read(0x342), 11); // mcause = ecall from M-mode
+        println!("RV32IM 阶乘测试: 5! = {}, 执行了 {} 条指令", cpu.read_reg(1), executed);
+    }
+
+    #[test]
+    fn test_rv32im_div_rem() {
+        // RV32M 除法和取余测试
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_m_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 设置 trap handler
+        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
+        
+        // 计算 17 / 5 = 3 余 2
+        
+        // addi x1, x0, 17     # x1 = 17
+        write_instr(&mut mem, 0, 0x01100093);
+        // addi x2, x0, 5      # x2 = 5
+        write_instr(&mut mem, 4, 0x00500113);
+        
+        // div x3, x1, x2      # x3 = 17 / 5 = 3
+        // funct7=0x01, rs2=2, rs1=1, funct3=100, rd=3, opcode=0x33
+        // 0000001 00010 00001 100 00011 0110011 = 0x0220C1B3
+        write_instr(&mut mem, 8, 0x0220C1B3);
+        
+        // rem x4, x1, x2      # x4 = 17 % 5 = 2
+        // funct7=0x01, rs2=2, rs1=1, funct3=110, rd=4, opcode=0x33
+        // 0000001 00010 00001 110 00100 0110011 = 0x0220E233
+        write_instr(&mut mem, 12, 0x0220E233);
+        
+        // 验证: x3 * x2 + x4 == x1 (商*除数+余数=被除数)
+        // mul x5, x3, x2      # x5 = 3 * 5 = 15
+        write_instr(&mut mem, 16, 0x022181B3); // 这里 rd=3 改为 rd=5
+        // 更正: mul x5, x3, x2
+        // 0000001 00010 00011 000 00101 0110011 = 0x022182B3
+        write_instr(&mut mem, 16, 0x022182B3);
+        
+        // add x6, x5, x4      # x6 = 15 + 2 = 17
+        write_instr(&mut mem, 20, 0x00428333);
+        
+        // ecall
+        write_instr(&mut mem, 24, 0x00000073);
+        
+        // 运行直到 PC 跳转到 trap handler
+        let mut executed = 0;
+        while cpu.pc() != 0x100 && executed < 10 {
+            cpu.step(&mut mem);
+            executed += 1;
+        }
+        
+        assert_eq!(cpu.read_reg(1), 17, "被除数");
+        assert_eq!(cpu.read_reg(2), 5, "除数");
+        assert_eq!(cpu.read_reg(3), 3, "商 = 17 / 5 = 3");
+        assert_eq!(cpu.read_reg(4), 2, "余数 = 17 % 5 = 2");
+        assert_eq!(cpu.read_reg(5), 15, "商 * 除数 = 15");
+        assert_eq!(cpu.read_reg(6), 17, "商*除数+余数 = 被除数");
+        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
+        println!("RV32IM 除法测试: 17 / 5 = {} 余 {}", cpu.read_reg(3), cpu.read_reg(4));
+    }
+
+    #[test]
+    fn test_rv32im_fibonacci() {
+        // 计算斐波那契数列 F(10) = 55
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_m_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 设置 trap handler
+        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
+        
+        // F(1)=1, F(2)=1, F(3)=2, F(4)=3, F(5)=5, F(6)=8, F(7)=13, F(8)=21, F(9)=34, F(10)=55
+        // x1 = a = 1 (F1), x2 = b = 1 (F2), 循环8次得到 F(10)
+        // x3 = counter, x4 = limit, x5 = temp
+        
+        // addi x1, x0, 1      # a = 1 (F1)
+        write_instr(&mut mem, 0, 0x00100093);
+        // addi x2, x0, 1      # b = 1 (F2) 
+        write_instr(&mut mem, 4, 0x00100113);
+        // addi x3, x0, 2      # counter = 2 (已经计算到 F2)
+        write_instr(&mut mem, 8, 0x00200193);
+        // addi x4, x0, 10     # limit = 10 (计算到 F10 时停止)
+        write_instr(&mut mem, 12, 0x00A00213);
+        
+        // loop@16: (当 counter < limit 时执行)
+        // add x5, x1, x2      # temp = a + b
+        write_instr(&mut mem, 16, 0x002082B3);
+        // addi x1, x2, 0      # a = b
+        write_instr(&mut mem, 20, 0x00010093);
+        // addi x2, x5, 0      # b = temp (现在 b = F(counter+1))
+        write_instr(&mut mem, 24, 0x00028113);
+        // addi x3, x3, 1      # counter++
+        write_instr(&mut mem, 28, 0x00118193);
+        // blt x3, x4, -16     # if counter < limit goto loop
+        // 当前 PC=32, 目标 PC=16, offset = -16
+        write_instr(&mut mem, 32, 0xFE41C8E3);
+        
+        // ecall
+        write_instr(&mut mem, 36, 0x00000073);
+        
+        // 运行直到 PC 跳转到 trap handler
+        let mut executed = 0;
+        while cpu.pc() != 0x100 && executed < 100 {
+            cpu.step(&mut mem);
+            executed += 1;
+        }
+        
+        // 当循环结束时 counter=10, b = F(10) = 55
+        println!("斐波那契测试: F(10) = {}, 执行了 {} 条指令", cpu.read_reg(2), executed);
+        println!("x1={}, x2={}, x3={}, x4={}", cpu.read_reg(1), cpu.read_reg(2), cpu.read_reg(3), cpu.read_reg(4));
+        
+        assert_eq!(cpu.read_reg(2), 55, "F(10) = 55");
+        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
+    }
+
+    #[test]
+    fn test_zicsr_basic() {
+        // 测试 CSR 指令的基本功能
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicsr_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 测试程序：
+        // 1. 读写 mscratch CSR
+        // 2. 使用置位/清位操作
+        
+        // addi x1, x0, 0x55   # x1 = 0x55
+        write_instr(&mut mem, 0, 0x05500093);
+        
+        // csrrw x2, mscratch, x1  # x2 = old mscratch (0), mscratch = 0x55
+        // CSR mscratch = 0x340
+        // csrrw: csr=0x340, rs1=x1, rd=x2, funct3=001, opcode=0x73
+        // = 0x34009173
+        write_instr(&mut mem, 4, 0x34009173);
+        
+        // csrrs x3, mscratch, x0  # x3 = mscratch (0x55), 不修改 (rs1=x0)
+        // = 0x34002173 (rd=x3, rs1=x0, funct3=010)
+        write_instr(&mut mem, 8, 0x340021F3);
+        
+        // addi x4, x0, 0x0F   # x4 = 0x0F (用于置位)
+        write_instr(&mut mem, 12, 0x00F00213);
+        
+        // csrrs x5, mscratch, x4  # x5 = 0x55, mscratch = 0x55 | 0x0F = 0x5F
+        // = 0x34022173 (rd=x5, rs1=x4, funct3=010)
+        write_instr(&mut mem, 16, 0x340222F3);
+        
+        // csrrs x6, mscratch, x0  # x6 = mscratch (0x5F)
+        write_instr(&mut mem, 20, 0x34002373);
+        
+        // addi x7, x0, 0x50   # x7 = 0x50 (用于清位)
+        write_instr(&mut mem, 24, 0x05000393);
+        
+        // csrrc x8, mscratch, x7  # x8 = 0x5F, mscratch = 0x5F & ~0x50 = 0x0F
+        // csrrc: funct3=011
+        write_instr(&mut mem, 28, 0x3403B473);
+        
+        // csrrs x9, mscratch, x0  # x9 = mscratch (0x0F)
+        write_instr(&mut mem, 32, 0x340024F3);
+        
+        // csrrwi x10, mscratch, 0x1F  # x10 = 0x0F, mscratch = 0x1F
+        // csrrwi: zimm=0x1F, funct3=101
+        write_instr(&mut mem, 36, 0x340FD573);
+        
+        // csrrs x11, mscratch, x0  # x11 = mscratch (0x1F)
+        write_instr(&mut mem, 40, 0x340025F3);
+        
+        // ecall
+        write_instr(&mut mem, 44, 0x00000073);
+        
+        // 设置 trap handler
+        cpu.csr_write(0x305, 0x200); // mtvec = 0x200
+        
+        // 运行直到 PC 跳转到 trap handler
+        let mut executed = 0;
+        while cpu.pc() != 0x200 && executed < 20 {
+            cpu.step(&mut mem);
+            executed += 1;
+        }
+        
+        println!("Zicsr 测试: 执行了 {} 条指令", executed);
+        println!("x1={:#x}, x2={:#x}, x3={:#x}", cpu.read_reg(1), cpu.read_reg(2), cpu.read_reg(3));
+        println!("x4={:#x}, x5={:#x}, x6={:#x}", cpu.read_reg(4), cpu.read_reg(5), cpu.read_reg(6));
+        println!("x7={:#x}, x8={:#x}, x9={:#x}", cpu.read_reg(7), cpu.read_reg(8), cpu.read_reg(9));
+        println!("x10={:#x}, x11={:#x}", cpu.read_reg(10), cpu.read_reg(11));
+        
+        // 验证结果
+        assert_eq!(cpu.read_reg(1), 0x55, "x1 = 0x55");
+        assert_eq!(cpu.read_reg(2), 0, "x2 = old mscratch = 0");
+        assert_eq!(cpu.read_reg(3), 0x55, "x3 = mscratch after write = 0x55");
+        assert_eq!(cpu.read_reg(5), 0x55, "x5 = mscratch before set = 0x55");
+        assert_eq!(cpu.read_reg(6), 0x5F, "x6 = mscratch after set = 0x5F");
+        assert_eq!(cpu.read_reg(8), 0x5F, "x8 = mscratch before clear = 0x5F");
+        assert_eq!(cpu.read_reg(9), 0x0F, "x9 = mscratch after clear = 0x0F");
+        assert_eq!(cpu.read_reg(10), 0x0F, "x10 = mscratch before csrrwi = 0x0F");
+        assert_eq!(cpu.read_reg(11), 0x1F, "x11 = mscratch after csrrwi = 0x1F");
+        assert_eq!(cpu.pc(), 0x200); // 跳转到 trap handler
+    }
+
+    #[test]
+    fn test_csr_read_only_write_is_illegal() {
+        use crate::cpu::csr_def::*;
+
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicsr_extension()
+            .build()
+            .expect("配置无冲突");
+
+        cpu.csr_write(CSR_MTVEC, 0x200);
+
+        // csrrs x1, cycle, x0  # rs1=x0 是纯读取，对只读 CSR 合法
+        write_instr(&mut mem, 0, 0xC00020F3);
+        // csrrw x1, cycle, x2  # 向只读地址段 (0xC00) 写入，非法
+        write_instr(&mut mem, 4, 0xC00110F3);
+
+        cpu.step(&mut mem);
+        assert_eq!(cpu.pc(), 4, "纯读取只读 CSR 不应触发异常");
+
+        cpu.step(&mut mem);
+        assert_eq!(cpu.pc(), 0x200, "写只读 CSR 应进入 trap handler");
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 2, "mcause = IllegalInstruction");
+        assert_eq!(cpu.csr_read(CSR_MTVAL), 0xC00110F3);
+        assert_eq!(cpu.csr_read(CSR_MEPC), 4);
+    }
+
+    #[test]
+    fn test_csr_unimplemented_is_illegal() {
+        use crate::cpu::csr_def::*;
+
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicsr_extension()
+            .build()
+            .expect("配置无冲突");
+
+        cpu.csr_write(CSR_MTVEC, 0x200);
+
+        // csrrs x1, 0x800, x0  # 0x800 未注册，读取也非法（不再静默返回 0）
+        write_instr(&mut mem, 0, 0x800020F3);
+
+        cpu.step(&mut mem);
+        assert_eq!(cpu.pc(), 0x200);
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 2, "mcause = IllegalInstruction");
+    }
+
+    #[test]
+    fn test_csr_privilege_too_low_is_illegal() {
+        use crate::cpu::csr_def::*;
+
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicsr_extension()
+            .build()
+            .expect("配置无冲突");
+
+        cpu.csr_write(CSR_MTVEC, 0x200);
+        cpu.set_privilege(PrivilegeMode::User);
+
+        // csrrs x1, mscratch, x0  # U-mode 读 M 级 CSR (addr[9:8]=0b11)，非法
+        write_instr(&mut mem, 0, 0x340020F3);
+
+        cpu.step(&mut mem);
+        assert_eq!(cpu.pc(), 0x200);
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 2, "mcause = IllegalInstruction");
+        assert_eq!(cpu.privilege(), PrivilegeMode::Machine, "trap 后回到 M-mode");
+    }
+
+    #[test]
+    fn test_take_trap_basic() {
+        // 测试 take_trap 方法的基本功能
+        use crate::cpu::csr_def::*;
+        
+        let mut cpu = CpuBuilder::new(0x1000)
+            .with_zicsr_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 设置 mtvec = 0x8000_0000 (direct mode)
+        cpu.status.csr_write(CSR_MTVEC, 0x8000_0000);
+        
+        // 设置初始 mstatus 启用中断 (MIE=1)
+        cpu.status.csr_write(CSR_MSTATUS, 0x8); // MIE bit
+        
+        // 模拟 ecall 触发 trap
+        let cause = TrapCause::EcallFromM;
+        let tval = 0; // ecall 没有 tval
+        
+        let pc_before = cpu.pc;
+        cpu.take_trap(cause, tval);
+        
+        // 验证 mepc = PC before trap
+        assert_eq!(cpu.status.csr_read(CSR_MEPC), pc_before, "mepc should be saved PC");
+        
+        // 验证 mcause = 11 (ecall from M-mode)
+        assert_eq!(cpu.status.csr_read(CSR_MCAUSE), 11, "mcause should be 11");
+        
+        // 验证 mtval = 0
+        assert_eq!(cpu.status.csr_read(CSR_MTVAL), 0, "mtval should be 0");
+        
+        // 验证 mstatus: MPIE=old MIE (1), MIE=0, MPP=3 (Machine)
+        let mstatus = cpu.status.csr_read(CSR_MSTATUS);
+        assert_eq!((mstatus >> 7) & 1, 1, "MPIE should be 1 (old MIE)");
+        assert_eq!((mstatus >> 3) & 1, 0, "MIE should be 0 (disabled)");
+        assert_eq!((mstatus >> 11) & 3, 3, "MPP should be 3 (Machine)");
+        
+        // 验证 PC 跳转到 mtvec
+        assert_eq!(cpu.pc, 0x8000_0000, "PC should jump to mtvec");
+        
+        // 验证特权级仍然是 Machine
+        assert_eq!(cpu.status.privilege, PrivilegeMode::Machine);
+        
+        println!("take_trap 基本测试通过!");
+    }
+
+    #[test]
+    fn test_take_trap_vectored() {
+        // 测试向量模式中断
+        use crate::cpu::csr_def::*;
+        
+        let mut cpu = CpuBuilder::new(0x1000)
+            .with_zicsr_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 设置 mtvec = 0x8000_0001 (vectored mode, base=0x8000_0000)
+        cpu.status.csr_write(CSR_MTVEC, 0x8000_0001);
+        
+        // 测试异常 (IllegalInstruction, code=2) - 应该跳转到 base
+        cpu.pc = 0x1000;
+        cpu.take_trap(TrapCause::IllegalInstruction, 0xDEADBEEF);
+        assert_eq!(cpu.pc, 0x8000_0000, "Exception should go to base");
+        assert_eq!(cpu.status.csr_read(CSR_MTVAL), 0xDEADBEEF, "mtval should contain bad instruction");
+        
+        // 测试中断 (MachineTimerInterrupt, code=7) - 应该跳转到 base + 4*7
+        cpu.pc = 0x2000;
+        cpu.take_trap(TrapCause::MachineTimerInterrupt, 0);
+        assert_eq!(cpu.pc, 0x8000_0000 + 4 * 7, "Interrupt should go to base + 4*cause");
+        
+        println!("take_trap 向量模式测试通过!");
+    }
+
+    #[test]
+    fn test_mret_basic() {
+        // 测试 MRET 指令的基本功能
+        use crate::cpu::csr_def::*;
+        use crate::isa::MRET_ENCODING;
+        
+        let mut mem = FlatMemory::new(4096, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicsr_extension()
+            .with_priv_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 模拟 trap handler 准备返回的状态
+        // mepc = 0x1000 (返回地址)
+        cpu.status.csr_write(CSR_MEPC, 0x1000);
+        // mstatus: MPIE=1, MIE=0, MPP=0 (User mode)
+        // MPIE at bit 7, MPP at bits 11-12
+        let mstatus = 1 << 7; // MPIE=1, MIE=0, MPP=0
+        cpu.status.csr_write(CSR_MSTATUS, mstatus);
+        
+        // 放置 MRET 指令
+        write_instr(&mut mem, 0, MRET_ENCODING);
+        
+        // 执行 MRET
+        cpu.step(&mut mem);
+        
+        // 验证结果
+        // PC 应该跳转到 mepc (0x1000)
+        assert_eq!(cpu.pc(), 0x1000, "PC should be mepc");
+        
+        // 特权级应该变为 MPP 值 (User mode)
+        assert_eq!(cpu.privilege(), PrivilegeMode::User, "Should return to User mode");
+        
+        // mstatus: MIE 应该恢复为 MPIE (1), MPIE 应该为 1, MPP 应该为 0
+        let new_mstatus = cpu.status.csr_read(CSR_MSTATUS);
+        assert_eq!((new_mstatus >> 3) & 1, 1, "MIE should be restored to MPIE (1)");
+        assert_eq!((new_mstatus >> 7) & 1, 1, "MPIE should be 1");
+        assert_eq!((new_mstatus >> 11) & 3, 0, "MPP should be 0 (User)");
+        
+        println!("MRET 基本测试通过!");
+    }
+
+    #[test]
+    fn test_trap_and_return_cycle() {
+        // 测试完整的 trap -> handler -> mret 周期
+        use crate::cpu::csr_def::*;
+        use crate::isa::MRET_ENCODING;
+        
+        let mut mem = FlatMemory::new(0x10000, 0);
+        let mut cpu = CpuBuilder::new(0x1000)
+            .with_zicsr_extension()
+            .with_priv_extension()
+            .build()
+            .expect("配置无冲突");
+        
+        // 设置 mtvec 指向 trap handler
+        let handler_addr = 0x8000u32;
+        cpu.status.csr_write(CSR_MTVEC, handler_addr);
+        
+        // 设置初始 mstatus: MIE=1 (中断使能)
+        cpu.status.csr_write(CSR_MSTATUS, 1 << 3);
+        
+        // 在 handler 地址放置 MRET
+        write_instr(&mut mem, handler_addr, MRET_ENCODING);
+        
+        // 保存原始 PC
+        let original_pc = cpu.pc();
+        
+        // 触发 trap (模拟 ecall from M-mode)
+        cpu.take_trap(TrapCause::EcallFromM, 0);
+        
+        // 验证 trap 后状态
+        assert_eq!(cpu.pc(), handler_addr, "Should jump to handler");
+        assert_eq!(cpu.status.csr_read(CSR_MEPC), original_pc, "mepc should be saved PC");
+        assert_eq!(cpu.status.csr_read(CSR_MCAUSE), 11, "mcause should be 11 (EcallFromM)");
+        
+        // 执行 handler 中的 MRET
+        cpu.step(&mut mem);
+        
+        // 验证返回后状态
+        assert_eq!(cpu.pc(), original_pc, "Should return to original PC");
+        
+        // MIE 应该恢复 (因为 MPIE 是 1)
+        let mstatus = cpu.status.csr_read(CSR_MSTATUS);
+        assert_eq!((mstatus >> 3) & 1, 1, "MIE should be restored");
+        
+        println!("Trap/Return 周期测试通过!");
+    }
+
+    #[test]
+    fn test_s_mode_only_trap() {
+        // supervisor-only profile：trap 应使用 S CSR 组
+        use crate::cpu::csr_def::*;
+
+        let mut mem = FlatMemory::new(4096, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .without_m_mode()
+            .with_s_mode()
+            .with_zicsr_extension()
+            .with_priv_extension()
+            .build()
+            .expect("配置无冲突");
+
+        // 复位特权级应为 S-mode
+        assert_eq!(cpu.privilege(), PrivilegeMode::Supervisor);
 
-        // 运行直到 PC 跳转到 trap handler
-        let mut executed = 0;
-        while cpu.pc() != 0x100 && executed < 100 {
-            cpu.step(&mut mem);
-            executed += 1;
-        }
+        // 设置 stvec
+        cpu.csr_write(CSR_STVEC, 0x400);
 
-        assert_eq!(cpu.read_reg(1), 6); // 1 + 2 + 3 = 6
-        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
-        assert_eq!(cpu.csr_read(0x341), 24); // mepc = ecall 指令地址
-        assert_eq!(cpu.csr_read(0x342), 11); // mcause = ecall from M-mode
-        assert!(executed < 100);
+        // ecall at PC=0
+        write_instr(&mut mem, 0, 0x00000073);
+        cpu.step(&mut mem);
+
+        // trap 应进入 S-mode handler，写 S CSR 组
+        assert_eq!(cpu.pc(), 0x400, "应跳转到 stvec");
+        assert_eq!(cpu.csr_read(CSR_SEPC), 0, "sepc = 异常 PC");
+        assert_eq!(cpu.csr_read(CSR_SCAUSE), 9, "scause = ecall from S-mode");
+        // M CSR 不应被写（未注册，读为 0）
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 0);
+        // SPP 应记录之前的 S-mode
+        let sstatus = cpu.csr_read(CSR_SSTATUS);
+        assert_eq!((sstatus >> 8) & 1, 1, "SPP = 1 (Supervisor)");
     }
 
     #[test]
-    fn test_cpu_builder_basic() {
-        // 使用 CpuBuilder 创建带 M 扩展的 CPU
-        let cpu = CpuBuilder::new(0x1000)
-            .with_m_extension()
+    fn test_user_only_trap_halts() {
+        // user-only profile：没有 trap handler，trap 直接停机
+        let mut mem = FlatMemory::new(4096, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .without_m_mode()
             .build()
             .expect("配置无冲突");
-        
-        assert_eq!(cpu.pc(), 0x1000);
-        assert_eq!(cpu.state(), CpuState::Running);
+
+        assert_eq!(cpu.privilege(), PrivilegeMode::User);
+
+        // ecall at PC=0
+        write_instr(&mut mem, 0, 0x00000073);
+        let state = cpu.step(&mut mem);
+
+        assert_eq!(state, CpuState::Halted);
+        assert_eq!(cpu.last_trap(), Some(TrapCause::EcallFromU));
     }
 
     #[test]
-    fn test_cpu_builder_with_f_extension() {
-        // 使用 CpuBuilder 创建带 F 扩展的 CPU
-        let cpu = CpuBuilder::new(0)
-            .with_f_extension()
+    fn test_mret_without_m_mode_is_illegal() {
+        use crate::cpu::csr_def::*;
+        use crate::isa::MRET_ENCODING;
+
+        let mut mem = FlatMemory::new(4096, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .without_m_mode()
+            .with_s_mode()
+            .with_zicsr_extension()
+            .with_priv_extension()
             .build()
             .expect("配置无冲突");
-        
-        // 验证 F 扩展 CSR 已注册 (fflags=0x001, frm=0x002, fcsr=0x003)
-        // 通过 status snapshot 检查
-        let snapshot = cpu.snapshot();
-        // CSR 应该包含 F 扩展的 CSR
-        assert!(snapshot.csr.contains_key(&0x001), "fflags 应已注册");
-        assert!(snapshot.csr.contains_key(&0x002), "frm 应已注册");
-        assert!(snapshot.csr.contains_key(&0x003), "fcsr 应已注册");
+
+        cpu.csr_write(CSR_STVEC, 0x400);
+        write_instr(&mut mem, 0, MRET_ENCODING);
+
+        cpu.step(&mut mem);
+
+        // MRET 在没有 M-mode 的 profile 中应触发非法指令异常（进入 S handler）
+        assert_eq!(cpu.pc(), 0x400);
+        assert_eq!(cpu.csr_read(CSR_SCAUSE), 2, "scause = IllegalInstruction");
+        assert_eq!(cpu.csr_read(CSR_STVAL), MRET_ENCODING);
     }
 
     #[test]
-    fn test_cpu_builder_with_v_extension() {
-        // 使用 CpuBuilder 创建带 V 扩展的 CPU
-        let cpu = CpuBuilder::new(0)
-            .with_v_extension()
+    fn test_fetch_unmapped_pc_faults() {
+        use crate::cpu::csr_def::*;
+
+        // 内存只有 1KB，跳到 0x1000 后取指应报 InstructionAccessFault
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicsr_extension()
             .build()
             .expect("配置无冲突");
-        
-        let snapshot = cpu.snapshot();
-        // V 扩展 CSR: vstart=0x008, vl=0xC20, vtype=0xC21
-        assert!(snapshot.csr.contains_key(&0x008), "vstart 应已注册");
-        assert!(snapshot.csr.contains_key(&0xC20), "vl 应已注册");
-        assert!(snapshot.csr.contains_key(&0xC21), "vtype 应已注册");
-        // vlenb 应该有默认值 16 (VLEN=128, vlenb=VLEN/8=16)
-        assert_eq!(snapshot.csr.get(&0xC22), Some(&16), "vlenb 应为 16");
+
+        cpu.csr_write(CSR_MTVEC, 0x200);
+
+        // lui x1, 0x1      # x1 = 0x1000（超出内存范围）
+        write_instr(&mut mem, 0, 0x000010B7);
+        // jalr x0, x1, 0   # pc = 0x1000
+        write_instr(&mut mem, 4, 0x00008067);
+
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+        assert_eq!(cpu.pc(), 0x1000, "jalr 先正常跳转");
+
+        // 在坏 PC 上取指：进入 trap handler 而不是 panic
+        let state = cpu.step(&mut mem);
+        assert_eq!(state, CpuState::Running);
+        assert_eq!(cpu.pc(), 0x200);
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 1, "mcause = InstructionAccessFault");
+        assert_eq!(cpu.csr_read(CSR_MTVAL), 0x1000);
+        assert_eq!(cpu.csr_read(CSR_MEPC), 0x1000);
     }
 
     #[test]
-    fn test_cpu_builder_m_mode_csrs() {
-        // 默认启用 M-mode
-        let cpu = CpuBuilder::new(0)
+    fn test_fetch_misaligned_pc_faults() {
+        use crate::cpu::csr_def::*;
+
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_zicsr_extension()
             .build()
             .expect("配置无冲突");
-        
-        let snapshot = cpu.snapshot();
-        // M-mode CSR: mstatus=0x300, mepc=0x341
-        assert!(snapshot.csr.contains_key(&0x300), "mstatus 应已注册");
-        assert!(snapshot.csr.contains_key(&0x341), "mepc 应已注册");
+
+        cpu.csr_write(CSR_MTVEC, 0x200);
+
+        // addi x1, x0, 0x100
+        write_instr(&mut mem, 0, 0x10000093);
+        // jalr x0, x1, 2   # pc = 0x102（jalr 只清 bit 0，bit 1 保留）
+        write_instr(&mut mem, 4, 0x00208067);
+
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+        assert_eq!(cpu.pc(), 0x102);
+
+        cpu.step(&mut mem);
+        assert_eq!(cpu.pc(), 0x200);
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 0, "mcause = InstructionAddressMisaligned");
+        assert_eq!(cpu.csr_read(CSR_MTVAL), 0x102);
     }
 
     #[test]
-    fn test_cpu_builder_run_program() {
-        // 使用 CpuBuilder 创建 CPU 并运行简单程序
+    fn test_wfi() {
+        // 测试 WFI 指令
+        use crate::isa::WFI_ENCODING;
+        
         let mut mem = FlatMemory::new(1024, 0);
         let mut cpu = CpuBuilder::new(0)
-            .with_m_extension()
+            .with_priv_extension()
             .build()
             .expect("配置无冲突");
         
-        // 设置 trap handler
-        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
-        
-        // addi x1, x0, 42
-        write_instr(&mut mem, 0, 0x02A00093);
-        // addi x2, x1, 8
-        write_instr(&mut mem, 4, 0x00808113);
-        // ecall
-        write_instr(&mut mem, 8, 0x00000073);
+        // 放置 WFI 指令
+        write_instr(&mut mem, 0, WFI_ENCODING);
         
-        // 运行直到 PC 跳转到 trap handler
-        let mut executed = 0;
-        while cpu.pc() != 0x100 && executed < 10 {
-            cpu.step(&mut mem);
-            executed += 1;
-        }
+        // 执行 WFI
+        let state = cpu.step(&mut mem);
         
-        assert_eq!(cpu.read_reg(1), 42);
-        assert_eq!(cpu.read_reg(2), 50);
-        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
-        assert_eq!(cpu.csr_read(0x342), 11); // mcause = ecall from M-mode
+        // 应该进入 WaitForInterrupt 状态
+        assert_eq!(state, CpuState::WaitForInterrupt, "Should enter WaitForInterrupt");
+
+        println!("WFI 测试通过!");
+    }
+
+    #[test]
+    fn test_sv32_translation_and_page_fault() {
+        use csr_def::CSR_SATP;
+
+        let mut mem = FlatMemory::new(64 * 1024, 0);
+        // 页表：根在 PA 0x1000，VA 0x2000 -> PA 0x3000（V|R|W|X|A|D，内核页）
+        mem.store32(0x1000, (2 << 10) | 0x01).unwrap();
+        mem.store32(0x2000 + 2 * 4, (3 << 10) | 0xCF).unwrap();
+
+        // 物理 0x3000 起放指令
+        mem.store32(0x3000, 0x02A00093).unwrap(); // addi x1, x0, 42
+        mem.store32(0x3004, 0x00002103).unwrap(); // lw x2, 0(x0) — VA 0 未映射
+
+        let mut cpu = CpuBuilder::new(0x2000)
+            .with_s_mode()
+            .build()
+            .expect("配置无冲突");
+        cpu.set_privilege(PrivilegeMode::Supervisor);
+        cpu.status.csr_write(CSR_SATP, 0x8000_0001); // Sv32，根页表 ppn=1
+
+        // 取指经过翻译：VA 0x2000 -> PA 0x3000
+        let state = cpu.step(&mut mem);
+        assert_eq!(state, CpuState::Running);
+        assert_eq!(cpu.read_reg(1), 42, "翻译后的指令应正常执行");
+        assert_eq!(cpu.pc(), 0x2004, "PC 仍是虚拟地址");
+
+        // 数据访问未映射的虚拟地址：LoadPageFault
+        cpu.step(&mut mem);
+        assert_eq!(cpu.last_trap(), Some(TrapCause::LoadPageFault));
+    }
+
+    #[test]
+    fn test_reg_write_history() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+        cpu.enable_reg_history(2);
+
+        // 对 x5 连续写三次：历史只保留最近两次
+        write_instr(&mut mem, 0, 0x00100293); // addi x5, x0, 1
+        write_instr(&mut mem, 4, 0x00200293); // addi x5, x0, 2
+        write_instr(&mut mem, 8, 0x00300293); // addi x5, x0, 3
+
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+
+        let hist = cpu.reg_history(5);
+        assert_eq!(hist.len(), 2, "深度 2 应只保留两条记录");
+        assert_eq!(hist[0], RegWriteRecord { pc: 4, old: 1, new: 2 });
+        assert_eq!(hist[1], RegWriteRecord { pc: 8, old: 2, new: 3 });
+        assert_eq!(cpu.last_reg_write(5).unwrap().pc, 8, "最后写入来自 PC=8");
+        assert!(cpu.reg_history(6).is_empty(), "未写过的寄存器历史为空");
+    }
+
+    #[test]
+    fn test_raise_interrupt_wakes_wfi() {
+        // raise_interrupt 挂起的中断应在下一次 step 唤醒 WFI 并进入处理程序
+        use crate::isa::WFI_ENCODING;
+        use csr_def::{CSR_MCAUSE, CSR_MIE, CSR_MSTATUS, CSR_MTVEC};
+        use trap::interrupt;
+
+        let mut mem = FlatMemory::new(4096, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_priv_extension()
+            .build()
+            .expect("配置无冲突");
+
+        write_instr(&mut mem, 0, WFI_ENCODING);
+
+        cpu.status.csr_write(CSR_MTVEC, 0x200);
+        cpu.status.csr_write(CSR_MSTATUS, trap::mstatus::MIE_MASK);
+        cpu.status
+            .csr_write(CSR_MIE, interrupt::MTIP_MASK | interrupt::MEIP_MASK);
+
+        let state = cpu.step(&mut mem);
+        assert_eq!(state, CpuState::WaitForInterrupt);
+
+        // 同时挂起定时器和外部中断：应先取外部中断（优先级更高）
+        cpu.raise_interrupt(TrapCause::MachineTimerInterrupt);
+        cpu.raise_interrupt(TrapCause::MachineExternalInterrupt);
+
+        let state = cpu.step(&mut mem);
+        assert_eq!(state, CpuState::Running, "中断应唤醒 WFI");
+        assert_eq!(cpu.pc(), 0x200, "应跳转到 mtvec");
+        assert_eq!(
+            cpu.status.csr_read(CSR_MCAUSE),
+            0x8000_000B,
+            "mcause 应为机器外部中断"
+        );
+    }
+
+    #[test]
+    fn test_step_back_restores_register_and_memory() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+        cpu.enable_recording(16);
+
+        mem.store32(100, 0xDEAD_BEEF).unwrap(); // 将被 sw 覆写的旧值
+
+        write_instr(&mut mem, 0, 0x04200093); // addi x1, x0, 0x42
+        write_instr(&mut mem, 4, 0x06400113); // addi x2, x0, 100
+        write_instr(&mut mem, 8, 0x00112023); // sw x1, 0(x2)
+
+        cpu.run(&mut mem, 3);
+        assert_eq!(mem.load32(100).unwrap(), 0x42);
+        assert_eq!(cpu.recorded_steps(), 3);
+
+        // 回退 sw：被覆写的内存恢复
+        assert!(cpu.step_back(&mut mem));
+        assert_eq!(mem.load32(100).unwrap(), 0xDEAD_BEEF);
+        assert_eq!(cpu.pc(), 8);
+
+        // 回退两条 addi：寄存器恢复
+        assert!(cpu.step_back(&mut mem));
+        assert!(cpu.step_back(&mut mem));
+        assert_eq!(cpu.read_reg(1), 0);
+        assert_eq!(cpu.read_reg(2), 0);
+        assert_eq!(cpu.pc(), 0);
+        assert!(!cpu.step_back(&mut mem), "日志已空");
+
+        // 重放应得到与第一次相同的结果
+        cpu.run(&mut mem, 3);
+        assert_eq!(cpu.read_reg(1), 0x42);
+        assert_eq!(mem.load32(100).unwrap(), 0x42);
+    }
+
+    #[test]
+    fn test_step_back_restores_trap_state() {
+        use csr_def::{CSR_MCAUSE, CSR_MTVEC};
+
+        let mut mem = FlatMemory::new(4096, 0);
+        let mut cpu = CpuCore::new(0);
+        cpu.enable_recording(8);
+        cpu.csr_write(CSR_MTVEC, 0x200);
+
+        write_instr(&mut mem, 0, 0x00100073); // ebreak
+
+        cpu.step(&mut mem);
+        assert_eq!(cpu.pc(), 0x200);
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 3);
+
+        // 回退 trap 进入：PC 和 CSR 一并恢复
+        assert!(cpu.step_back(&mut mem));
+        assert_eq!(cpu.pc(), 0);
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 0);
+        assert_eq!(cpu.privilege(), PrivilegeMode::Machine);
+    }
+
+    #[test]
+    fn test_reverse_continue_bounded_by_depth() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+        cpu.enable_recording(3); // 深度小于执行步数
+
+        // x1 每步加 1，共 5 步
+        write_instr(&mut mem, 0, 0x00108093); // addi x1, x1, 1
+        write_instr(&mut mem, 4, 0x00108093);
+        write_instr(&mut mem, 8, 0x00108093);
+        write_instr(&mut mem, 12, 0x00108093);
+        write_instr(&mut mem, 16, 0x00108093);
+
+        cpu.run(&mut mem, 5);
+        assert_eq!(cpu.read_reg(1), 5);
+        assert_eq!(cpu.recorded_steps(), 3, "更早的记录被挤出");
+
+        let undone = cpu.reverse_continue(&mut mem, 10);
+        assert_eq!(undone, 3);
+        assert_eq!(cpu.read_reg(1), 2, "只能回退到日志深度允许的位置");
+        assert_eq!(cpu.pc(), 8);
+    }
+
+    #[test]
+    fn test_sdtrig_execute_trigger() {
+        use csr_def::{CSR_MCAUSE, CSR_MEPC, CSR_MTVAL, CSR_MTVEC, CSR_TDATA1, CSR_TDATA2, CSR_TSELECT};
+
+        let mut mem = FlatMemory::new(4096, 0);
+        let mut cpu = CpuCore::new(0);
+        cpu.csr_write(CSR_MTVEC, 0x200);
+
+        // 触发器 0：地址 8 上的执行断点
+        cpu.csr_write(CSR_TSELECT, 0);
+        cpu.csr_write(CSR_TDATA2, 8);
+        cpu.csr_write(CSR_TDATA1, Trigger::EXECUTE);
+
+        write_instr(&mut mem, 0, 0x00100093); // addi x1, x0, 1
+        write_instr(&mut mem, 4, 0x00200113); // addi x2, x0, 2
+        write_instr(&mut mem, 8, 0x00300193); // addi x3, x0, 3
+
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+        cpu.step(&mut mem); // 命中：地址 8 的指令不执行
+
+        assert_eq!(cpu.read_reg(3), 0, "断点处的指令不应执行");
+        assert_eq!(cpu.pc(), 0x200, "应跳转到 mtvec");
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 3, "mcause 应为 Breakpoint");
+        assert_eq!(cpu.csr_read(CSR_MEPC), 8);
+        assert_eq!(cpu.csr_read(CSR_MTVAL), 8);
+        assert_eq!(cpu.last_trap(), Some(TrapCause::Breakpoint));
+    }
+
+    #[test]
+    fn test_sdtrig_store_trigger_fires_before_access() {
+        use csr_def::{CSR_MCAUSE, CSR_TDATA1, CSR_TDATA2, CSR_TSELECT};
+
+        let mut mem = FlatMemory::new(4096, 0);
+        let mut cpu = CpuCore::new(0);
+        cpu.csr_write(csr_def::CSR_MTVEC, 0x200);
+
+        cpu.csr_write(CSR_TSELECT, 0);
+        cpu.csr_write(CSR_TDATA2, 100);
+        cpu.csr_write(CSR_TDATA1, Trigger::STORE);
+
+        write_instr(&mut mem, 0, 0x04200093); // addi x1, x0, 0x42
+        write_instr(&mut mem, 4, 0x06400113); // addi x2, x0, 100
+        write_instr(&mut mem, 8, 0x00112023); // sw x1, 0(x2)
+
+        cpu.run(&mut mem, 3);
+
+        assert_eq!(cpu.csr_read(CSR_MCAUSE), 3);
+        assert_eq!(mem.load32(100).unwrap(), 0, "timing=before：存储不应发生");
+
+        // 同一地址的 lw 不触发只写触发器
+        let mut cpu = CpuCore::new(0);
+        cpu.csr_write(CSR_TSELECT, 0);
+        cpu.csr_write(CSR_TDATA2, 100);
+        cpu.csr_write(CSR_TDATA1, Trigger::STORE);
+        write_instr(&mut mem, 8, 0x00012183); // lw x3, 0(x2)
+        let (executed, _) = cpu.run(&mut mem, 3);
         assert_eq!(executed, 3);
+        assert!(cpu.last_trap().is_none());
+    }
+
+    #[test]
+    fn test_sdtrig_tselect_banks_and_enumeration() {
+        use csr_def::{CSR_TDATA1, CSR_TDATA2, CSR_TSELECT};
+
+        let mut cpu = CpuCore::new(0);
+
+        cpu.csr_write(CSR_TSELECT, 0);
+        cpu.csr_write(CSR_TDATA2, 0x100);
+        cpu.csr_write(CSR_TDATA1, Trigger::EXECUTE);
+
+        cpu.csr_write(CSR_TSELECT, 1);
+        cpu.csr_write(CSR_TDATA2, 0x200);
+        cpu.csr_write(CSR_TDATA1, Trigger::LOAD | Trigger::STORE);
+
+        // 窗口按 tselect 切换
+        assert_eq!(cpu.csr_read(CSR_TDATA2), 0x200);
+        cpu.csr_write(CSR_TSELECT, 0);
+        assert_eq!(cpu.csr_read(CSR_TDATA2), 0x100);
+        assert_eq!(
+            cpu.csr_read(CSR_TDATA1),
+            Trigger::TYPE_MCONTROL | Trigger::EXECUTE,
+            "tdata1 读取应带 mcontrol 类型字段"
+        );
+
+        // tselect 是 WARL：越界写入钳制到最后一个触发器
+        cpu.csr_write(CSR_TSELECT, 100);
+        assert_eq!(cpu.csr_read(CSR_TSELECT), NUM_TRIGGERS as u32 - 1);
+
+        // 仿真侧枚举
+        let armed: Vec<usize> = cpu
+            .triggers()
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.is_armed())
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(armed, vec![0, 1]);
+        let t1 = cpu.trigger(1).unwrap();
+        assert!(t1.on_load() && t1.on_store() && !t1.on_execute());
+        assert_eq!(t1.address(), 0x200);
+        assert!(cpu.trigger(NUM_TRIGGERS).is_none());
+    }
+
+    #[test]
+    fn test_watchpoint_hits_on_store() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+        cpu.add_watchpoint(100..104, WatchKind::Write);
+
+        // addi x1, x0, 0x42; addi x2, x0, 100; sw x1, 0(x2)
+        write_instr(&mut mem, 0, 0x04200093);
+        write_instr(&mut mem, 4, 0x06400113);
+        write_instr(&mut mem, 8, 0x00112023);
+
+        let (executed, state) = cpu.run(&mut mem, 10);
+        assert_eq!(executed, 3, "命中监视点的 sw 应已执行完毕");
+        assert_eq!(state, CpuState::WatchpointHit(100));
+        assert_eq!(mem.load32(100).unwrap(), 0x42, "存储本身不被拦截");
+
+        let hit = cpu.last_watchpoint().expect("应有命中详情");
+        assert_eq!(hit.addr, 100);
+        assert_eq!(hit.pc, 8);
+        assert!(hit.is_store);
+        assert_eq!(hit.value, 0x42);
     }
 
     #[test]
-    fn test_rv32im_full_program() {
-        // RV32IM 完整测试：计算阶乘 5! = 120
-        // 使用乘法指令 mul
+    fn test_watchpoint_kind_and_resume() {
         let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuBuilder::new(0)
-            .with_m_extension()
-            .build()
-            .expect("配置无冲突");
-        
-        // 设置 trap handler
-        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
-        
-        // 程序：计算 5! = 1*2*3*4*5 = 120
-        // x1 = result = 1
-        // x2 = i = 1
-        // x3 = limit = 6
-        // loop: result = result * i; i++; if i < limit goto loop
-        
-        // addi x1, x0, 1      # x1 = result = 1
-        write_instr(&mut mem, 0, 0x00100093);
-        // addi x2, x0, 1      # x2 = i = 1  
-        write_instr(&mut mem, 4, 0x00100113);
-        // addi x3, x0, 6      # x3 = limit = 6
-        write_instr(&mut mem, 8, 0x00600193);
-        
-        // loop (addr 12):
-        // mul x1, x1, x2      # result *= i (RV32M)
-        // opcode=0x33, funct3=0, funct7=0x01, rd=1, rs1=1, rs2=2
-        // 0000001 00010 00001 000 00001 0110011 = 0x022080B3
-        write_instr(&mut mem, 12, 0x022080B3);
-        
-        // addi x2, x2, 1      # i++
-        write_instr(&mut mem, 16, 0x00110113);
-        
-        // blt x2, x3, -8      # if i < limit goto loop (PC = 12)
-        // imm[12|10:5]=1111111, rs2=3, rs1=2, funct3=100, imm[4:1|11]=1100, opcode=1100011
-        // 要跳回 addr 12，当前 addr 20，offset = 12 - 20 = -8
-        write_instr(&mut mem, 20, 0xFE314CE3);
-        
-        // ecall               # 结束
-        write_instr(&mut mem, 24, 0x00000073);
-        
-        // 运行直到 PC 跳转到 trap handler
-        let mut executed = 0;
-        while cpu.pc() != 0x100 && executed < 50 {
-            cpu.step(&mut mem);
-            executed += 1;
-        }
-        
-        // 验证结果
-        assert_eq!(cpu.read_reg(1), 120, "5! = 120");
-        assert_eq!(cpu.read_reg(2), 6, "i 最终等于 6");
-        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
-        assert_eq!(cpu.csr_read(0x342), 11); // mcause = ecall from M-mode
-        println!("RV32IM 阶乘测试: 5! = {}, 执行了 {} 条指令", cpu.read_reg(1), executed);
+        let mut cpu = CpuCore::new(0);
+        // 只监视读取：sw 不触发，lw 触发
+        cpu.add_watchpoint(100..104, WatchKind::Read);
+
+        write_instr(&mut mem, 0, 0x04200093); // addi x1, x0, 0x42
+        write_instr(&mut mem, 4, 0x06400113); // addi x2, x0, 100
+        write_instr(&mut mem, 8, 0x00112023); // sw x1, 0(x2)
+        write_instr(&mut mem, 12, 0x00012183); // lw x3, 0(x2)
+        write_instr(&mut mem, 16, 0x00000013); // nop
+
+        let (executed, state) = cpu.run(&mut mem, 10);
+        assert_eq!(executed, 4, "写监视点不应被只读监视触发");
+        assert_eq!(state, CpuState::WatchpointHit(100));
+        assert!(!cpu.last_watchpoint().unwrap().is_store);
+
+        // 设回 Running 继续执行
+        cpu.set_state(CpuState::Running);
+        let state = cpu.step(&mut mem);
+        assert_eq!(state, CpuState::Running);
+        assert_eq!(cpu.pc(), 20);
+
+        // 清除监视点后重新访问不再触发
+        cpu.clear_watchpoints();
+        cpu.set_pc(12);
+        assert_eq!(cpu.step(&mut mem), CpuState::Running);
     }
 
     #[test]
-    fn test_rv32im_div_rem() {
-        // RV32M 除法和取余测试
+    fn test_watchpoint_range_overlap() {
         let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+        // 单字节监视点：字访问与区间部分重叠也应命中
+        cpu.add_watchpoint(102..103, WatchKind::ReadWrite);
+
+        write_instr(&mut mem, 0, 0x06400113); // addi x2, x0, 100
+        write_instr(&mut mem, 4, 0x00012183); // lw x3, 0(x2)   ; [100,104) 覆盖 102
+
+        let (_, state) = cpu.run(&mut mem, 10);
+        assert_eq!(state, CpuState::WatchpointHit(100));
+
+        // 相邻但不重叠的访问不触发
+        let mut cpu = CpuCore::new(0);
+        cpu.add_watchpoint(104..108, WatchKind::ReadWrite);
+        let (executed, state) = cpu.run(&mut mem, 2);
+        assert_eq!(executed, 2);
+        assert_eq!(state, CpuState::Running);
+        assert!(cpu.last_watchpoint().is_none());
+    }
+
+    #[test]
+    fn test_fcsr_fflags_frm_linked() {
         let mut cpu = CpuBuilder::new(0)
-            .with_m_extension()
+            .with_f_extension()
+            .with_zicsr_extension()
             .build()
             .expect("配置无冲突");
-        
-        // 设置 trap handler
-        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
-        
-        // 计算 17 / 5 = 3 余 2
-        
-        // addi x1, x0, 17     # x1 = 17
-        write_instr(&mut mem, 0, 0x01100093);
-        // addi x2, x0, 5      # x2 = 5
-        write_instr(&mut mem, 4, 0x00500113);
-        
-        // div x3, x1, x2      # x3 = 17 / 5 = 3
-        // funct7=0x01, rs2=2, rs1=1, funct3=100, rd=3, opcode=0x33
-        // 0000001 00010 00001 100 00011 0110011 = 0x0220C1B3
-        write_instr(&mut mem, 8, 0x0220C1B3);
-        
-        // rem x4, x1, x2      # x4 = 17 % 5 = 2
-        // funct7=0x01, rs2=2, rs1=1, funct3=110, rd=4, opcode=0x33
-        // 0000001 00010 00001 110 00100 0110011 = 0x0220E233
-        write_instr(&mut mem, 12, 0x0220E233);
-        
-        // 验证: x3 * x2 + x4 == x1 (商*除数+余数=被除数)
-        // mul x5, x3, x2      # x5 = 3 * 5 = 15
-        write_instr(&mut mem, 16, 0x022181B3); // 这里 rd=3 改为 rd=5
-        // 更正: mul x5, x3, x2
-        // 0000001 00010 00011 000 00101 0110011 = 0x022182B3
-        write_instr(&mut mem, 16, 0x022182B3);
-        
-        // add x6, x5, x4      # x6 = 15 + 2 = 17
-        write_instr(&mut mem, 20, 0x00428333);
-        
-        // ecall
-        write_instr(&mut mem, 24, 0x00000073);
-        
-        // 运行直到 PC 跳转到 trap handler
-        let mut executed = 0;
-        while cpu.pc() != 0x100 && executed < 10 {
-            cpu.step(&mut mem);
-            executed += 1;
+
+        // 写 fcsr 同时反映到 fflags（低 5 位）与 frm（[7:5]）
+        cpu.csr_write(csr_def::CSR_FCSR, 0b101_10011);
+        assert_eq!(cpu.csr_read(csr_def::CSR_FFLAGS), 0b10011);
+        assert_eq!(cpu.csr_read(csr_def::CSR_FRM), 0b101);
+
+        // 写 fflags 只改低 5 位，frm 不受影响
+        cpu.csr_write(csr_def::CSR_FFLAGS, 0b00001);
+        assert_eq!(cpu.csr_read(csr_def::CSR_FCSR), 0b101_00001);
+        assert_eq!(cpu.csr_read(csr_def::CSR_FRM), 0b101);
+
+        // 写 frm 只改 [7:5]，越界位被截断
+        cpu.csr_write(csr_def::CSR_FRM, 0xFF);
+        assert_eq!(cpu.csr_read(csr_def::CSR_FCSR), 0b111_00001);
+    }
+
+    #[test]
+    fn test_csr_hook_custom_backend() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        // 读返回递增计数、写记录最后值的自定义 CSR
+        struct CounterCsr {
+            reads: Cell<u32>,
+            last_write: Cell<u32>,
         }
-        
-        assert_eq!(cpu.read_reg(1), 17, "被除数");
-        assert_eq!(cpu.read_reg(2), 5, "除数");
-        assert_eq!(cpu.read_reg(3), 3, "商 = 17 / 5 = 3");
-        assert_eq!(cpu.read_reg(4), 2, "余数 = 17 % 5 = 2");
-        assert_eq!(cpu.read_reg(5), 15, "商 * 除数 = 15");
-        assert_eq!(cpu.read_reg(6), 17, "商*除数+余数 = 被除数");
-        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
-        println!("RV32IM 除法测试: 17 / 5 = {} 余 {}", cpu.read_reg(3), cpu.read_reg(4));
+
+        impl CsrHook for CounterCsr {
+            fn read(&self) -> u32 {
+                let n = self.reads.get() + 1;
+                self.reads.set(n);
+                n
+            }
+
+            fn write(&self, value: u32) {
+                self.last_write.set(value);
+            }
+        }
+
+        let hook = Rc::new(CounterCsr {
+            reads: Cell::new(0),
+            last_write: Cell::new(0),
+        });
+        let mut cpu = CpuCore::new(0);
+        cpu.install_csr_hook(0x800, hook.clone());
+
+        assert_eq!(cpu.csr_read(0x800), 1);
+        assert_eq!(cpu.csr_read(0x800), 2);
+        cpu.csr_write(0x800, 0xDEAD);
+        assert_eq!(hook.last_write.get(), 0xDEAD);
     }
 
     #[test]
-    fn test_rv32im_fibonacci() {
-        // 计算斐波那契数列 F(10) = 55
+    fn test_misaligned_store_straddling_ram_end_is_precise() {
         let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuBuilder::new(0)
-            .with_m_extension()
-            .build()
-            .expect("配置无冲突");
-        
-        // 设置 trap handler
-        cpu.csr_write(0x305, 0x100); // mtvec = 0x100
-        
-        // F(1)=1, F(2)=1, F(3)=2, F(4)=3, F(5)=5, F(6)=8, F(7)=13, F(8)=21, F(9)=34, F(10)=55
-        // x1 = a = 1 (F1), x2 = b = 1 (F2), 循环8次得到 F(10)
-        // x3 = counter, x4 = limit, x5 = temp
-        
-        // addi x1, x0, 1      # a = 1 (F1)
-        write_instr(&mut mem, 0, 0x00100093);
-        // addi x2, x0, 1      # b = 1 (F2) 
-        write_instr(&mut mem, 4, 0x00100113);
-        // addi x3, x0, 2      # counter = 2 (已经计算到 F2)
-        write_instr(&mut mem, 8, 0x00200193);
-        // addi x4, x0, 10     # limit = 10 (计算到 F10 时停止)
-        write_instr(&mut mem, 12, 0x00A00213);
-        
-        // loop@16: (当 counter < limit 时执行)
-        // add x5, x1, x2      # temp = a + b
-        write_instr(&mut mem, 16, 0x002082B3);
-        // addi x1, x2, 0      # a = b
-        write_instr(&mut mem, 20, 0x00010093);
-        // addi x2, x5, 0      # b = temp (现在 b = F(counter+1))
-        write_instr(&mut mem, 24, 0x00028113);
-        // addi x3, x3, 1      # counter++
-        write_instr(&mut mem, 28, 0x00118193);
-        // blt x3, x4, -16     # if counter < limit goto loop
-        // 当前 PC=32, 目标 PC=16, offset = -16
-        write_instr(&mut mem, 32, 0xFE41C8E3);
-        
-        // ecall
-        write_instr(&mut mem, 36, 0x00000073);
-        
-        // 运行直到 PC 跳转到 trap handler
-        let mut executed = 0;
-        while cpu.pc() != 0x100 && executed < 100 {
-            cpu.step(&mut mem);
-            executed += 1;
+        let mut cpu = CpuCore::new(0);
+
+        // sw 跨越内存末尾：1022..1026 中后两个字节越界
+        write_instr(&mut mem, 0, 0x3FE00113); // addi x2, x0, 1022
+        write_instr(&mut mem, 4, 0xFFF00093); // addi x1, x0, -1
+        write_instr(&mut mem, 8, 0x00112023); // sw x1, 0(x2)
+        mem.store16(1020, 0).unwrap();
+
+        cpu.run(&mut mem, 3);
+        assert_eq!(cpu.last_trap(), Some(TrapCause::StoreAccessFault));
+        // 界内的两个字节也不应被部分写入
+        assert_eq!(mem.load8(1022).unwrap(), 0);
+        assert_eq!(mem.load8(1023).unwrap(), 0);
+
+        // 完全在界内的非对齐存储仍正常工作
+        let mut cpu = CpuCore::new(0);
+        write_instr(&mut mem, 8, 0xFE112DA3); // sw x1, -5(x2)  ; 地址 1017
+        cpu.run(&mut mem, 3);
+        for addr in 1017..1021 {
+            assert_eq!(mem.load8(addr).unwrap(), 0xFF);
         }
-        
-        // 当循环结束时 counter=10, b = F(10) = 55
-        println!("斐波那契测试: F(10) = {}, 执行了 {} 条指令", cpu.read_reg(2), executed);
-        println!("x1={}, x2={}, x3={}, x4={}", cpu.read_reg(1), cpu.read_reg(2), cpu.read_reg(3), cpu.read_reg(4));
-        
-        assert_eq!(cpu.read_reg(2), 55, "F(10) = 55");
-        assert_eq!(cpu.pc(), 0x100); // 跳转到 trap handler
     }
 
     #[test]
-    fn test_zicsr_basic() {
-        // 测试 CSR 指令的基本功能
+    fn test_reset_restores_arch_state() {
         let mut mem = FlatMemory::new(1024, 0);
-        let mut cpu = CpuBuilder::new(0)
-            .with_zicsr_extension()
-            .build()
-            .expect("配置无冲突");
-        
-        // 测试程序：
-        // 1. 读写 mscratch CSR
-        // 2. 使用置位/清位操作
-        
-        // addi x1, x0, 0x55   # x1 = 0x55
-        write_instr(&mut mem, 0, 0x05500093);
-        
-        // csrrw x2, mscratch, x1  # x2 = old mscratch (0), mscratch = 0x55
-        // CSR mscratch = 0x340
-        // csrrw: csr=0x340, rs1=x1, rd=x2, funct3=001, opcode=0x73
-        // = 0x34009173
-        write_instr(&mut mem, 4, 0x34009173);
-        
-        // csrrs x3, mscratch, x0  # x3 = mscratch (0x55), 不修改 (rs1=x0)
-        // = 0x34002173 (rd=x3, rs1=x0, funct3=010)
-        write_instr(&mut mem, 8, 0x340021F3);
-        
-        // addi x4, x0, 0x0F   # x4 = 0x0F (用于置位)
-        write_instr(&mut mem, 12, 0x00F00213);
-        
-        // csrrs x5, mscratch, x4  # x5 = 0x55, mscratch = 0x55 | 0x0F = 0x5F
-        // = 0x34022173 (rd=x5, rs1=x4, funct3=010)
-        write_instr(&mut mem, 16, 0x340222F3);
-        
-        // csrrs x6, mscratch, x0  # x6 = mscratch (0x5F)
-        write_instr(&mut mem, 20, 0x34002373);
-        
-        // addi x7, x0, 0x50   # x7 = 0x50 (用于清位)
-        write_instr(&mut mem, 24, 0x05000393);
-        
-        // csrrc x8, mscratch, x7  # x8 = 0x5F, mscratch = 0x5F & ~0x50 = 0x0F
-        // csrrc: funct3=011
-        write_instr(&mut mem, 28, 0x3403B473);
-        
-        // csrrs x9, mscratch, x0  # x9 = mscratch (0x0F)
-        write_instr(&mut mem, 32, 0x340024F3);
-        
-        // csrrwi x10, mscratch, 0x1F  # x10 = 0x0F, mscratch = 0x1F
-        // csrrwi: zimm=0x1F, funct3=101
-        write_instr(&mut mem, 36, 0x340FD573);
-        
-        // csrrs x11, mscratch, x0  # x11 = mscratch (0x1F)
-        write_instr(&mut mem, 40, 0x340025F3);
-        
-        // ecall
-        write_instr(&mut mem, 44, 0x00000073);
-        
-        // 设置 trap handler
-        cpu.csr_write(0x305, 0x200); // mtvec = 0x200
-        
-        // 运行直到 PC 跳转到 trap handler
-        let mut executed = 0;
-        while cpu.pc() != 0x200 && executed < 20 {
+        let mut cpu = CpuBuilder::new(0)
+            .with_f_extension()
+            .with_zicsr_extension()
+            .with_reset_vector(0x100)
+            .build()
+            .expect("配置无冲突");
+
+        // 弄脏各类架构状态
+        write_instr(&mut mem, 0, 0x02A00093); // addi x1, x0, 42
+        write_instr(&mut mem, 4, 0xF00080D3); // fmv.w.x f1, x1
+        cpu.run(&mut mem, 2);
+        cpu.csr_write(csr_def::CSR_MCAUSE, 0xDEAD);
+        cpu.csr_write(csr_def::CSR_MSTATUS, trap::mstatus::MIE_MASK);
+        cpu.set_privilege(PrivilegeMode::User);
+
+        cpu.reset();
+
+        assert_eq!(cpu.pc(), 0x100, "PC 应回到复位向量");
+        assert_eq!(cpu.privilege(), PrivilegeMode::Machine);
+        assert_eq!(cpu.read_reg(1), 0);
+        assert_eq!(cpu.read_fp(1), 0);
+        assert_eq!(cpu.csr_read(csr_def::CSR_MCAUSE), 0);
+        let mstatus = cpu.csr_read(csr_def::CSR_MSTATUS);
+        assert_eq!(mstatus & trap::mstatus::MIE_MASK, 0, "MIE 复位应为 0");
+        assert_eq!(trap::mstatus::read_fs(mstatus), trap::mstatus::FS_INITIAL);
+    }
+
+    #[test]
+    fn test_single_step_mode() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+        cpu.set_single_step(true);
+
+        write_instr(&mut mem, 0, 0x02A00093); // addi x1, x0, 42
+        write_instr(&mut mem, 4, 0x00108113); // addi x2, x1, 1
+
+        // 每条指令退休后停在 DebugStep，寄存器效果已生效
+        assert_eq!(cpu.step(&mut mem), CpuState::DebugStep);
+        assert_eq!(cpu.read_reg(1), 42);
+        assert_eq!(cpu.pc(), 4);
+
+        // 继续单步前需要恢复 Running
+        cpu.set_state(CpuState::Running);
+        assert_eq!(cpu.step(&mut mem), CpuState::DebugStep);
+        assert_eq!(cpu.read_reg(2), 43);
+
+        // 关闭单步后恢复连续执行
+        write_instr(&mut mem, 8, 0x00000013); // nop
+        write_instr(&mut mem, 12, 0x00000013); // nop
+        cpu.set_single_step(false);
+        cpu.set_state(CpuState::Running);
+        let (executed, state) = cpu.run(&mut mem, 2);
+        assert_eq!(executed, 2);
+        assert_eq!(state, CpuState::Running);
+    }
+
+    #[test]
+    fn test_steps_iterator_yields_per_instruction_info() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        write_instr(&mut mem, 0, 0x02A00093); // addi x1, x0, 42
+        write_instr(&mut mem, 4, 0x00108113); // addi x2, x1, 1
+        write_instr(&mut mem, 8, 0xFFFF_FFFF); // 非法编码
+
+        let infos: Vec<StepInfo> = cpu.steps(&mut mem).collect();
+
+        // 状态离开 Running 的那一步是最后一项
+        assert_eq!(infos.len(), 3);
+        assert_eq!(infos[0].pc, 0);
+        assert_eq!(infos[0].instr.mnemonic(), "addi");
+        assert_eq!(infos[0].state, CpuState::Running);
+        assert_eq!(infos[1].pc, 4);
+        assert_eq!(infos[2].pc, 8);
+        assert_eq!(infos[2].state, CpuState::IllegalInstruction(0xFFFF_FFFF));
+
+        // 状态未恢复前迭代器保持耗尽
+        assert_eq!(cpu.steps(&mut mem).next(), None);
+        assert_eq!(cpu.read_reg(2), 43, "前两条指令的效果已生效");
+    }
+
+    #[test]
+    fn test_decode_cache_matches_plain_decode_and_self_modify() {
+        let mut cpu = CpuCore::new(0);
+        cpu.enable_decode_cache();
+        let mut mem = FlatMemory::new(1024, 0);
+        mem.store32(0x00, 0x0030_0093).unwrap(); // addi x1, x0, 3
+        mem.store32(0x04, 0xFFF0_8093).unwrap(); // loop: addi x1, x1, -1
+        mem.store32(0x08, 0xFE00_9EE3).unwrap(); // bne x1, x0, loop
+
+        // 循环让 0x04/0x08 反复命中缓存
+        for _ in 0..7 {
             cpu.step(&mut mem);
-            executed += 1;
         }
-        
-        println!("Zicsr 测试: 执行了 {} 条指令", executed);
-        println!("x1={:#x}, x2={:#x}, x3={:#x}", cpu.read_reg(1), cpu.read_reg(2), cpu.read_reg(3));
-        println!("x4={:#x}, x5={:#x}, x6={:#x}", cpu.read_reg(4), cpu.read_reg(5), cpu.read_reg(6));
-        println!("x7={:#x}, x8={:#x}, x9={:#x}", cpu.read_reg(7), cpu.read_reg(8), cpu.read_reg(9));
-        println!("x10={:#x}, x11={:#x}", cpu.read_reg(10), cpu.read_reg(11));
-        
-        // 验证结果
-        assert_eq!(cpu.read_reg(1), 0x55, "x1 = 0x55");
-        assert_eq!(cpu.read_reg(2), 0, "x2 = old mscratch = 0");
-        assert_eq!(cpu.read_reg(3), 0x55, "x3 = mscratch after write = 0x55");
-        assert_eq!(cpu.read_reg(5), 0x55, "x5 = mscratch before set = 0x55");
-        assert_eq!(cpu.read_reg(6), 0x5F, "x6 = mscratch after set = 0x5F");
-        assert_eq!(cpu.read_reg(8), 0x5F, "x8 = mscratch before clear = 0x5F");
-        assert_eq!(cpu.read_reg(9), 0x0F, "x9 = mscratch after clear = 0x0F");
-        assert_eq!(cpu.read_reg(10), 0x0F, "x10 = mscratch before csrrwi = 0x0F");
-        assert_eq!(cpu.read_reg(11), 0x1F, "x11 = mscratch after csrrwi = 0x1F");
-        assert_eq!(cpu.pc(), 0x200); // 跳转到 trap handler
+        assert_eq!(cpu.read_reg(1), 0);
+        assert_eq!(cpu.pc(), 0xC);
+
+        // 自修改：覆盖循环体后重跑，指令字比对应让旧缓存失效
+        mem.store32(0x04, 0x0150_8093).unwrap(); // addi x1, x1, 21
+        cpu.set_pc(0x04);
+        cpu.write_reg(1, 21);
+        cpu.step(&mut mem);
+        assert_eq!(cpu.read_reg(1), 42, "改写后的指令按新编码执行");
     }
 
     #[test]
-    fn test_take_trap_basic() {
-        // 测试 take_trap 方法的基本功能
-        use crate::cpu::csr_def::*;
-        
-        let mut cpu = CpuBuilder::new(0x1000)
-            .with_zicsr_extension()
+    fn test_decode_cache_guest_store_invalidates_code() {
+        let mut cpu = CpuCore::new(0);
+        cpu.enable_decode_cache();
+        let mut mem = FlatMemory::new(1024, 0);
+        mem.store32(0x00, 0x0042A023).unwrap(); // sw x4, 0(x5)
+        mem.store32(0x20, 0x00500113).unwrap(); // addi x2, x0, 5
+
+        // 先执行 0x20 填充解码缓存
+        cpu.set_pc(0x20);
+        cpu.step(&mut mem);
+        assert_eq!(cpu.read_reg(2), 5);
+        let idx = DecodeCache::index(0x20);
+        assert!(
+            cpu.decode_cache.as_ref().unwrap().entries[idx].is_some(),
+            "0x20 应已缓存"
+        );
+
+        // JIT 式改写：客体通过 sw 把新指令写进 0x20
+        cpu.write_reg(4, 0x00900113); // addi x2, x0, 9
+        cpu.write_reg(5, 0x20);
+        cpu.set_pc(0x00);
+        cpu.step(&mut mem);
+        assert!(
+            cpu.decode_cache.as_ref().unwrap().entries[idx].is_none(),
+            "写入代码字应剔除对应缓存行"
+        );
+
+        // 重跑改写后的代码：按新编码执行
+        cpu.set_pc(0x20);
+        cpu.step(&mut mem);
+        assert_eq!(cpu.read_reg(2), 9, "自修改后的指令应重新解码执行");
+    }
+
+    #[test]
+    fn test_shadow_call_stack_tracks_calls_and_returns() {
+        let mut cpu = CpuCore::new(0);
+        cpu.enable_call_stack();
+        let mut mem = FlatMemory::new(1024, 0);
+        mem.store32(0x00, 0x020000EF).unwrap(); // jal ra, +0x20（调用 0x20）
+        mem.store32(0x20, 0x020000EF).unwrap(); // jal ra, +0x20（调用 0x40）
+        mem.store32(0x40, 0x00008067).unwrap(); // ret
+
+        cpu.step(&mut mem);
+        assert_eq!(
+            cpu.call_stack().unwrap(),
+            &[CallFrame { target: 0x20, call_site: 0, return_addr: 4 }]
+        );
+
+        cpu.step(&mut mem);
+        let frames = cpu.call_stack().unwrap();
+        assert_eq!(frames.len(), 2, "嵌套调用再压一帧");
+        assert_eq!(frames[1].call_site, 0x20);
+        assert_eq!(frames[1].return_addr, 0x24);
+
+        cpu.step(&mut mem); // ret 回到 0x24
+        assert_eq!(cpu.pc(), 0x24);
+        assert_eq!(cpu.call_stack().unwrap().len(), 1, "返回弹掉内层帧");
+    }
+
+    #[test]
+    fn test_misaligned_policy_trap() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_misaligned_policy(MisalignedPolicy::TrapMisaligned)
             .build()
             .expect("配置无冲突");
-        
-        // 设置 mtvec = 0x8000_0000 (direct mode)
-        cpu.status.csr_write(CSR_MTVEC, 0x8000_0000);
-        
-        // 设置初始 mstatus 启用中断 (MIE=1)
-        cpu.status.csr_write(CSR_MSTATUS, 0x8); // MIE bit
-        
-        // 模拟 ecall 触发 trap
-        let cause = TrapCause::EcallFromM;
-        let tval = 0; // ecall 没有 tval
-        
-        let pc_before = cpu.pc;
-        cpu.take_trap(cause, tval);
-        
-        // 验证 mepc = PC before trap
-        assert_eq!(cpu.status.csr_read(CSR_MEPC), pc_before, "mepc should be saved PC");
-        
-        // 验证 mcause = 11 (ecall from M-mode)
-        assert_eq!(cpu.status.csr_read(CSR_MCAUSE), 11, "mcause should be 11");
-        
-        // 验证 mtval = 0
-        assert_eq!(cpu.status.csr_read(CSR_MTVAL), 0, "mtval should be 0");
-        
-        // 验证 mstatus: MPIE=old MIE (1), MIE=0, MPP=3 (Machine)
-        let mstatus = cpu.status.csr_read(CSR_MSTATUS);
-        assert_eq!((mstatus >> 7) & 1, 1, "MPIE should be 1 (old MIE)");
-        assert_eq!((mstatus >> 3) & 1, 0, "MIE should be 0 (disabled)");
-        assert_eq!((mstatus >> 11) & 3, 3, "MPP should be 3 (Machine)");
-        
-        // 验证 PC 跳转到 mtvec
-        assert_eq!(cpu.pc, 0x8000_0000, "PC should jump to mtvec");
-        
-        // 验证特权级仍然是 Machine
-        assert_eq!(cpu.status.privilege, PrivilegeMode::Machine);
-        
-        println!("take_trap 基本测试通过!");
+
+        write_instr(&mut mem, 0, 0x10100113); // addi x2, x0, 0x101
+        write_instr(&mut mem, 4, 0x00012083); // lw   x1, 0(x2)
+
+        cpu.run(&mut mem, 2);
+
+        // 非对齐 load 不再拆字节仿真，而是按规范触发异常
+        assert_eq!(cpu.last_trap(), Some(TrapCause::LoadAddressMisaligned));
+        assert_eq!(cpu.csr_read(csr_def::CSR_MTVAL), 0x101);
+        assert_eq!(cpu.csr_read(csr_def::CSR_MEPC), 4);
+        assert_eq!(cpu.read_reg(1), 0, "目标寄存器不应被写入");
+
+        // 非对齐 store 同理，且内存不被改动
+        let mut cpu = CpuBuilder::new(0)
+            .with_misaligned_policy(MisalignedPolicy::TrapMisaligned)
+            .build()
+            .expect("配置无冲突");
+        let mut mem = FlatMemory::new(1024, 0);
+        write_instr(&mut mem, 0, 0x10100113); // addi x2, x0, 0x101
+        write_instr(&mut mem, 4, 0x00111023); // sh   x1, 0(x2)
+        cpu.write_reg(1, 0xBEEF);
+        cpu.run(&mut mem, 2);
+
+        assert_eq!(cpu.last_trap(), Some(TrapCause::StoreAddressMisaligned));
+        assert_eq!(mem.read_bytes(0x101, 2).unwrap(), [0, 0]);
     }
 
     #[test]
-    fn test_take_trap_vectored() {
-        // 测试向量模式中断
-        use crate::cpu::csr_def::*;
-        
-        let mut cpu = CpuBuilder::new(0x1000)
-            .with_zicsr_extension()
+    fn test_big_endian_data_mode() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_big_endian()
             .build()
             .expect("配置无冲突");
-        
-        // 设置 mtvec = 0x8000_0001 (vectored mode, base=0x8000_0000)
-        cpu.status.csr_write(CSR_MTVEC, 0x8000_0001);
-        
-        // 测试异常 (IllegalInstruction, code=2) - 应该跳转到 base
-        cpu.pc = 0x1000;
-        cpu.take_trap(TrapCause::IllegalInstruction, 0xDEADBEEF);
-        assert_eq!(cpu.pc, 0x8000_0000, "Exception should go to base");
-        assert_eq!(cpu.status.csr_read(CSR_MTVAL), 0xDEADBEEF, "mtval should contain bad instruction");
-        
-        // 测试中断 (MachineTimerInterrupt, code=7) - 应该跳转到 base + 4*7
-        cpu.pc = 0x2000;
-        cpu.take_trap(TrapCause::MachineTimerInterrupt, 0);
-        assert_eq!(cpu.pc, 0x8000_0000 + 4 * 7, "Interrupt should go to base + 4*cause");
-        
-        println!("take_trap 向量模式测试通过!");
+
+        write_instr(&mut mem, 0, 0x123450B7); // lui  x1, 0x12345
+        write_instr(&mut mem, 4, 0x67808093); // addi x1, x1, 0x678
+        write_instr(&mut mem, 8, 0x10102023); // sw   x1, 0x100(x0)
+        write_instr(&mut mem, 12, 0x10002103); // lw  x2, 0x100(x0)
+        write_instr(&mut mem, 16, 0x10000183); // lb  x3, 0x100(x0)
+        let (executed, _) = cpu.run(&mut mem, 5);
+
+        // 取指保持小端：程序照常执行
+        assert_eq!(executed, 5);
+        assert_eq!(cpu.read_reg(1), 0x1234_5678);
+        // 物理字节序为大端，读回往返一致，lb 读到最高位字节
+        assert_eq!(mem.read_bytes(0x100, 4).unwrap(), [0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(cpu.read_reg(2), 0x1234_5678);
+        assert_eq!(cpu.read_reg(3), 0x12);
     }
 
     #[test]
-    fn test_mret_basic() {
-        // 测试 MRET 指令的基本功能
-        use crate::cpu::csr_def::*;
-        use crate::isa::MRET_ENCODING;
-        
-        let mut mem = FlatMemory::new(4096, 0);
+    fn test_mstatus_fs_dirty_and_sd() {
+        let mut mem = FlatMemory::new(1024, 0);
         let mut cpu = CpuBuilder::new(0)
+            .with_f_extension()
             .with_zicsr_extension()
-            .with_priv_extension()
             .build()
             .expect("配置无冲突");
-        
-        // 模拟 trap handler 准备返回的状态
-        // mepc = 0x1000 (返回地址)
-        cpu.status.csr_write(CSR_MEPC, 0x1000);
-        // mstatus: MPIE=1, MIE=0, MPP=0 (User mode)
-        // MPIE at bit 7, MPP at bits 11-12
-        let mstatus = 1 << 7; // MPIE=1, MIE=0, MPP=0
-        cpu.status.csr_write(CSR_MSTATUS, mstatus);
-        
-        // 放置 MRET 指令
-        write_instr(&mut mem, 0, MRET_ENCODING);
-        
-        // 执行 MRET
-        cpu.step(&mut mem);
-        
-        // 验证结果
-        // PC 应该跳转到 mepc (0x1000)
-        assert_eq!(cpu.pc(), 0x1000, "PC should be mepc");
-        
-        // 特权级应该变为 MPP 值 (User mode)
-        assert_eq!(cpu.privilege(), PrivilegeMode::User, "Should return to User mode");
-        
-        // mstatus: MIE 应该恢复为 MPIE (1), MPIE 应该为 1, MPP 应该为 0
-        let new_mstatus = cpu.status.csr_read(CSR_MSTATUS);
-        assert_eq!((new_mstatus >> 3) & 1, 1, "MIE should be restored to MPIE (1)");
-        assert_eq!((new_mstatus >> 7) & 1, 1, "MPIE should be 1");
-        assert_eq!((new_mstatus >> 11) & 3, 0, "MPP should be 0 (User)");
-        
-        println!("MRET 基本测试通过!");
+
+        // 复位时 FS=Initial，SD 清零
+        let mstatus = cpu.csr_read(csr_def::CSR_MSTATUS);
+        assert_eq!(trap::mstatus::read_fs(mstatus), trap::mstatus::FS_INITIAL);
+        assert_eq!(mstatus & trap::mstatus::SD_MASK, 0);
+
+        // 写浮点寄存器后 FS=Dirty，SD（bit 31）随之置位
+        write_instr(&mut mem, 0, 0x04200093); // addi x1, x0, 0x42
+        write_instr(&mut mem, 4, 0xF00080D3); // fmv.w.x f1, x1
+        cpu.run(&mut mem, 2);
+        let mstatus = cpu.csr_read(csr_def::CSR_MSTATUS);
+        assert_eq!(trap::mstatus::read_fs(mstatus), trap::mstatus::FS_DIRTY);
+        assert_ne!(mstatus & trap::mstatus::SD_MASK, 0);
+
+        // SD 是推导位，不能直接写入
+        cpu.csr_write(csr_def::CSR_MSTATUS, 0);
+        assert_eq!(cpu.csr_read(csr_def::CSR_MSTATUS) & trap::mstatus::SD_MASK, 0);
     }
 
     #[test]
-    fn test_trap_and_return_cycle() {
-        // 测试完整的 trap -> handler -> mret 周期
-        use crate::cpu::csr_def::*;
-        use crate::isa::MRET_ENCODING;
-        
-        let mut mem = FlatMemory::new(0x10000, 0);
-        let mut cpu = CpuBuilder::new(0x1000)
+    fn test_fp_traps_when_fs_off() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuBuilder::new(0)
+            .with_f_extension()
             .with_zicsr_extension()
-            .with_priv_extension()
             .build()
             .expect("配置无冲突");
-        
-        // 设置 mtvec 指向 trap handler
-        let handler_addr = 0x8000u32;
-        cpu.status.csr_write(CSR_MTVEC, handler_addr);
-        
-        // 设置初始 mstatus: MIE=1 (中断使能)
-        cpu.status.csr_write(CSR_MSTATUS, 1 << 3);
-        
-        // 在 handler 地址放置 MRET
-        write_instr(&mut mem, handler_addr, MRET_ENCODING);
-        
-        // 保存原始 PC
-        let original_pc = cpu.pc();
-        
-        // 触发 trap (模拟 ecall from M-mode)
-        cpu.take_trap(TrapCause::EcallFromM, 0);
-        
-        // 验证 trap 后状态
-        assert_eq!(cpu.pc(), handler_addr, "Should jump to handler");
-        assert_eq!(cpu.status.csr_read(CSR_MEPC), original_pc, "mepc should be saved PC");
-        assert_eq!(cpu.status.csr_read(CSR_MCAUSE), 11, "mcause should be 11 (EcallFromM)");
-        
-        // 执行 handler 中的 MRET
-        cpu.step(&mut mem);
-        
-        // 验证返回后状态
-        assert_eq!(cpu.pc(), original_pc, "Should return to original PC");
-        
-        // MIE 应该恢复 (因为 MPIE 是 1)
-        let mstatus = cpu.status.csr_read(CSR_MSTATUS);
-        assert_eq!((mstatus >> 3) & 1, 1, "MIE should be restored");
-        
-        println!("Trap/Return 周期测试通过!");
+
+        // FS=Off 时浮点指令与浮点 CSR 访问都按非法指令处理
+        cpu.csr_write(csr_def::CSR_MSTATUS, 0);
+        write_instr(&mut mem, 0, 0xF00080D3); // fmv.w.x f1, x1
+        let state = cpu.step(&mut mem);
+        assert_eq!(state, CpuState::IllegalInstruction(0xF00080D3));
+        assert!(!cpu.csr_access_legal(csr_def::CSR_FFLAGS, false));
+
+        // 重新打开 FS 后同一条指令正常执行
+        cpu.set_state(CpuState::Running);
+        cpu.csr_write(
+            csr_def::CSR_MSTATUS,
+            trap::mstatus::write_fs(0, trap::mstatus::FS_INITIAL),
+        );
+        cpu.set_pc(0);
+        assert_eq!(cpu.step(&mut mem), CpuState::Running);
+        assert!(cpu.csr_access_legal(csr_def::CSR_FFLAGS, false));
     }
 
     #[test]
-    fn test_wfi() {
-        // 测试 WFI 指令
-        use crate::isa::WFI_ENCODING;
-        
+    fn test_csrrw_fflags_aliases_fcsr() {
         let mut mem = FlatMemory::new(1024, 0);
         let mut cpu = CpuBuilder::new(0)
-            .with_priv_extension()
+            .with_f_extension()
+            .with_zicsr_extension()
             .build()
             .expect("配置无冲突");
-        
-        // 放置 WFI 指令
-        write_instr(&mut mem, 0, WFI_ENCODING);
-        
-        // 执行 WFI
-        let state = cpu.step(&mut mem);
-        
-        // 应该进入 WaitForInterrupt 状态
-        assert_eq!(state, CpuState::WaitForInterrupt, "Should enter WaitForInterrupt");
-        
-        println!("WFI 测试通过!");
+
+        // 预置 frm=0b011，随后经指令写 fflags 不应扰动舍入模式
+        cpu.csr_write(csr_def::CSR_FRM, 0b011);
+
+        write_instr(&mut mem, 0, 0x01300093); // addi x1, x0, 0x13
+        write_instr(&mut mem, 4, 0x00109073); // csrrw x0, fflags, x1
+        write_instr(&mut mem, 8, 0x00302173); // csrrs x2, fcsr, x0
+
+        let (executed, state) = cpu.run(&mut mem, 3);
+        assert_eq!(executed, 3);
+        assert_eq!(state, CpuState::Running);
+        // fcsr = frm(0b011) << 5 | fflags(0b10011)
+        assert_eq!(cpu.read_reg(2), 0b011_10011);
+        assert_eq!(cpu.csr_read(csr_def::CSR_FFLAGS), 0b10011);
+        assert_eq!(cpu.csr_read(csr_def::CSR_FRM), 0b011);
     }
 }
@@ -18,8 +18,42 @@ use std::sync::Arc;
 
 use super::csr_def;
 use super::status::Status;
-use super::CpuCore;
-use crate::isa::{IsaConfig, ConflictInfo};
+use super::trap::{mstatus, PrivilegeMode};
+use super::{CpuCore, CustomExecutor, MisalignedPolicy};
+use crate::isa::{ConflictInfo, IsaConfig, IsaError, IsaExtension};
+
+/// CPU 构建/配置错误
+///
+/// 带结构化载荷的分层错误：ISA 侧的冲突细节保留在
+/// [`IsaError`] 里，库用户可以逐层下钻匹配原因。
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub enum CpuError {
+    /// ISA / 解码器配置错误（指令冲突、opcode 占用）
+    Isa(IsaError),
+}
+
+impl std::fmt::Display for CpuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CpuError::Isa(e) => write!(f, "ISA config error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CpuError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CpuError::Isa(e) => Some(e),
+        }
+    }
+}
+
+impl From<IsaError> for CpuError {
+    fn from(e: IsaError) -> Self {
+        CpuError::Isa(e)
+    }
+}
 
 /// CPU 构建器
 ///
@@ -30,6 +64,10 @@ use crate::isa::{IsaConfig, ConflictInfo};
 pub struct CpuBuilder {
     entry_pc: u32,
     isa_config: IsaConfig,
+    custom_executors: Vec<(&'static str, Box<dyn CustomExecutor>)>,
+    reset_vector: Option<u32>,
+    big_endian: bool,
+    misaligned_policy: MisalignedPolicy,
     enable_f: bool,
     enable_d: bool,
     enable_v: bool,
@@ -45,6 +83,10 @@ impl CpuBuilder {
         Self {
             entry_pc,
             isa_config: IsaConfig::new(),
+            custom_executors: Vec::new(),
+            reset_vector: None,
+            big_endian: false,
+            misaligned_policy: MisalignedPolicy::default(),
             enable_f: false,
             enable_d: false,
             enable_v: false,
@@ -59,6 +101,12 @@ impl CpuBuilder {
         self
     }
 
+    /// 启用 A 扩展（原子操作）
+    pub fn with_a_extension(mut self) -> Self {
+        self.isa_config = self.isa_config.with_a_extension();
+        self
+    }
+
     /// 启用 Zicsr 扩展（CSR 操作指令）
     pub fn with_zicsr_extension(mut self) -> Self {
         self.isa_config = self.isa_config.with_zicsr_extension();
@@ -71,6 +119,24 @@ impl CpuBuilder {
         self
     }
 
+    /// 启用 Zk 扩展（标量密码学）
+    pub fn with_zk_extension(mut self) -> Self {
+        self.isa_config = self.isa_config.with_zk_extension();
+        self
+    }
+
+    /// 启用 Zicond 扩展（整数条件操作）
+    pub fn with_zicond_extension(mut self) -> Self {
+        self.isa_config = self.isa_config.with_zicond_extension();
+        self
+    }
+
+    /// 启用 Zicbo 扩展（缓存块操作）
+    pub fn with_zicbo_extension(mut self) -> Self {
+        self.isa_config = self.isa_config.with_zicbo_extension();
+        self
+    }
+
     /// 启用 F 扩展（单精度浮点）
     pub fn with_f_extension(mut self) -> Self {
         self.enable_f = true;
@@ -82,6 +148,8 @@ impl CpuBuilder {
     pub fn with_d_extension(mut self) -> Self {
         self.enable_f = true;
         self.enable_d = true;
+        // D 隐含 F：先注册单精度指令集，D 指令待后续补充
+        self.isa_config = self.isa_config.with_f_extension();
         // TODO: self.isa_config = self.isa_config.with_d_extension();
         self
     }
@@ -89,7 +157,55 @@ impl CpuBuilder {
     /// 启用 V 扩展（向量）
     pub fn with_v_extension(mut self) -> Self {
         self.enable_v = true;
-        // TODO: self.isa_config = self.isa_config.with_v_extension();
+        self.isa_config = self.isa_config.with_v_extension();
+        self
+    }
+
+    /// 注册自定义解码器与配套的执行器
+    ///
+    /// 解码侧走 [`IsaConfig::with_custom_decoder`]（可单独使用，
+    /// 未配执行器的 Custom 指令按非法指令处理）；执行侧按扩展
+    /// 标识分发到 `executor`，见 [`CustomExecutor`]。
+    pub fn with_custom_executor(
+        mut self,
+        extension: &'static str,
+        executor: Box<dyn CustomExecutor>,
+    ) -> Self {
+        self.custom_executors.push((extension, executor));
+        self
+    }
+
+    /// 访问内部的 ISA 配置（注册自定义解码器等）
+    pub fn map_isa_config(mut self, f: impl FnOnce(IsaConfig) -> IsaConfig) -> Self {
+        self.isa_config = f(self.isa_config);
+        self
+    }
+
+    /// 启用大端数据模式
+    ///
+    /// 数据 load/store 按大端字节序进行，取指保持小端；用于假定
+    /// BE 数据布局的遗留固件镜像与测试向量。
+    pub fn with_big_endian(mut self) -> Self {
+        self.big_endian = true;
+        self
+    }
+
+    /// 设置非对齐数据访问的处理策略
+    ///
+    /// 默认为 [`MisalignedPolicy::Emulate`]（拆字节静默仿真）；
+    /// [`MisalignedPolicy::TrapMisaligned`] 按规范触发地址非对齐
+    /// 异常，用于演练 OS 的 trap 处理程序。
+    pub fn with_misaligned_policy(mut self, policy: MisalignedPolicy) -> Self {
+        self.misaligned_policy = policy;
+        self
+    }
+
+    /// 设置复位向量（[`CpuCore::reset`] 的目标地址）
+    ///
+    /// 默认与入口 PC 相同；固件在非入口地址提供复位处理程序时
+    /// 可单独指定。
+    pub fn with_reset_vector(mut self, addr: u32) -> Self {
+        self.reset_vector = Some(addr);
         self
     }
 
@@ -134,18 +250,39 @@ impl CpuBuilder {
 
     /// 构建 CPU 核心
     ///
-    /// 返回 `Err` 如果检测到指令冲突
-    pub fn build(self) -> Result<CpuCore, Vec<ConflictInfo>> {
+    /// 检测到指令冲突时返回 [`CpuError::Isa`]
+    pub fn build(self) -> Result<CpuCore, CpuError> {
         // 1. 检测指令冲突
         let conflicts = self.isa_config.detect_conflicts();
         if !conflicts.is_empty() {
-            return Err(conflicts);
+            return Err(IsaError::Conflicts(conflicts).into());
+        }
+
+        // 2. 计算 misa 复位值：MXL=1 (RV32)，扩展字母位按配置置位
+        let mut misa_reset = csr_def::misa::MXL32 | csr_def::misa::I | csr_def::misa::U;
+        if self.isa_config.has_extension(IsaExtension::RV32M) {
+            misa_reset |= csr_def::misa::M;
+        }
+        if self.isa_config.has_extension(IsaExtension::RV32A) {
+            misa_reset |= csr_def::misa::A;
+        }
+        if self.enable_f {
+            misa_reset |= csr_def::misa::F;
+        }
+        if self.enable_d {
+            misa_reset |= csr_def::misa::D;
+        }
+        if self.enable_v {
+            misa_reset |= csr_def::misa::V;
+        }
+        if self.enable_s_mode {
+            misa_reset |= csr_def::misa::S;
         }
 
-        // 2. 构建解码器
+        // 3. 构建解码器
         let decoder = Arc::new(self.isa_config.build()?);
 
-        // 3. 构建架构状态
+        // 4. 构建架构状态
         let mut status = Status::new();
         
         // 注册基础 CSR
@@ -153,8 +290,16 @@ impl CpuBuilder {
 
         // 根据扩展配置状态
         if self.enable_f || self.enable_d {
-            status.enable_fp();
+            // D 扩展下寄存器堆加宽到 64 位并启用 NaN-boxing 检查
+            if self.enable_d {
+                status.enable_fp_width(super::status::FpWidth::F64);
+            } else {
+                status.enable_fp();
+            }
             status.csr.register(csr_def::F_CSRS);
+            // fflags/frm 是 fcsr 的位段视图：用联动钩子共享状态，
+            // 避免三个独立单元悄悄失去同步
+            status.csr.install_fcsr();
         }
 
         if self.enable_v {
@@ -163,16 +308,50 @@ impl CpuBuilder {
         }
 
         // 特权级 CSR
+        status.has_m_mode = self.enable_m_mode;
+        status.has_s_mode = self.enable_s_mode;
+
         if self.enable_m_mode {
             status.csr.register(csr_def::M_CSRS);
+            // misa 报告实际启用的扩展，供客户软件探测
+            status.csr.write(csr_def::CSR_MISA, misa_reset);
+            if self.enable_f || self.enable_d {
+                // FS 复位为 Initial：裸机程序无需先写 mstatus 即可用浮点
+                let mstatus = mstatus::write_fs(0, mstatus::FS_INITIAL);
+                status.csr.write(csr_def::CSR_MSTATUS, mstatus);
+            }
         }
 
         if self.enable_s_mode {
             status.csr.register(csr_def::S_CSRS);
         }
 
-        // 4. 创建 CPU 核心
-        Ok(CpuCore::with_config(self.entry_pc, status, decoder))
+        // 复位特权级为 profile 中的最高特权级
+        status.privilege = if self.enable_m_mode {
+            PrivilegeMode::Machine
+        } else if self.enable_s_mode {
+            PrivilegeMode::Supervisor
+        } else {
+            PrivilegeMode::User
+        };
+
+        // 5. 创建 CPU 核心
+        let mut cpu = CpuCore::with_config(self.entry_pc, status, decoder);
+        if let Some(addr) = self.reset_vector {
+            cpu.set_reset_vector(addr);
+        }
+        if self.big_endian {
+            cpu.set_big_endian(true);
+        }
+        cpu.set_misaligned_policy(self.misaligned_policy);
+        for (extension, executor) in self.custom_executors {
+            cpu.register_custom_executor(extension, executor);
+        }
+        if self.enable_m_mode {
+            // WARL 写 misa 时以复位值为上限：只有复位时置位的扩展位可开关
+            cpu.misa_reset = misa_reset;
+        }
+        Ok(cpu)
     }
 }
 
@@ -10,6 +10,24 @@
 
 use super::status::CsrEntry;
 
+// ============================================================================
+// CSR 地址编码属性（特权规范 2.1 节）
+// ============================================================================
+// CSR 地址本身编码访问属性：addr[11:10] == 0b11 表示只读，
+// addr[9:8] 是访问所需的最低特权级（00=U，01=S，11=M）
+
+/// 该 CSR 地址是否只读（向其写入应触发非法指令异常）
+#[inline]
+pub fn is_read_only(addr: u16) -> bool {
+    (addr >> 10) & 0b11 == 0b11
+}
+
+/// 该 CSR 地址要求的最低特权级（与 `PrivilegeMode::to_bits` 同编码）
+#[inline]
+pub fn min_privilege_bits(addr: u16) -> u8 {
+    ((addr >> 8) & 0b11) as u8
+}
+
 // ============================================================================
 // Base Unprivileged CSR Addresses
 // ============================================================================
@@ -86,13 +104,53 @@ pub const CSR_MCONFIGPTR: u16 = 0xF15;
 // Machine Trap Setup
 pub const CSR_MSTATUS: u16 = 0x300;
 pub const CSR_MISA: u16 = 0x301;
+
+/// misa 的字段布局：MXL[31:30] 表示基础 XLEN，低 26 位是扩展字母位
+/// （bit 0 = A，bit 1 = B，……，bit 25 = Z）
+pub mod misa {
+    /// MXL = 1：RV32
+    pub const MXL32: u32 = 1 << 30;
+    pub const A: u32 = 1 << 0;
+    pub const D: u32 = 1 << 3;
+    pub const F: u32 = 1 << 5;
+    pub const I: u32 = 1 << 8;
+    pub const M: u32 = 1 << 12;
+    pub const S: u32 = 1 << 18;
+    pub const U: u32 = 1 << 20;
+    pub const V: u32 = 1 << 21;
+    /// 允许运行时通过写 misa 开关的扩展位（WARL）；
+    /// MXL/I 以及特权级位（S/U）只读
+    pub const TOGGLABLE: u32 = A | D | F | M | V;
+}
+
 pub const CSR_MEDELEG: u16 = 0x302;
+pub const CSR_MCOUNTINHIBIT: u16 = 0x320;
+
+/// mcountinhibit 的字段布局：置位表示对应计数器停止计数。
+/// bit 1（TM）在规范中恒为只读 0。
+pub mod mcountinhibit {
+    /// 停止 mcycle 计数
+    pub const CY: u32 = 1 << 0;
+    /// 停止 minstret 计数
+    pub const IR: u32 = 1 << 2;
+    /// 可写位集合（WARL：其余位读回 0）
+    pub const WRITABLE_MASK: u32 = CY | IR;
+}
+
 pub const CSR_MIDELEG: u16 = 0x303;
 pub const CSR_MIE: u16 = 0x304;
 pub const CSR_MTVEC: u16 = 0x305;
 pub const CSR_MCOUNTEREN: u16 = 0x306;
 pub const CSR_MSTATUSH: u16 = 0x310;
 
+// Machine Counters
+// mcycle/minstret 的值由 CpuCore 的 64 位计数器字段承载，CsrBank
+// 中的存储仅用于注册表查询；读写语义见 CpuCore::csr_read / csr_write
+pub const CSR_MCYCLE: u16 = 0xB00;
+pub const CSR_MINSTRET: u16 = 0xB02;
+pub const CSR_MCYCLEH: u16 = 0xB80;
+pub const CSR_MINSTRETH: u16 = 0xB82;
+
 // Machine Trap Handling
 pub const CSR_MSCRATCH: u16 = 0x340;
 pub const CSR_MEPC: u16 = 0x341;
@@ -119,7 +177,13 @@ pub const M_CSRS: &[CsrEntry] = &[
     CsrEntry { name: "mie",        addr: CSR_MIE,        reset: 0 },
     CsrEntry { name: "mtvec",      addr: CSR_MTVEC,      reset: 0 },
     CsrEntry { name: "mcounteren", addr: CSR_MCOUNTEREN, reset: 0 },
+    CsrEntry { name: "mcountinhibit", addr: CSR_MCOUNTINHIBIT, reset: 0 },
     CsrEntry { name: "mstatush",   addr: CSR_MSTATUSH,   reset: 0 },
+    // Machine Counters
+    CsrEntry { name: "mcycle",     addr: CSR_MCYCLE,     reset: 0 },
+    CsrEntry { name: "minstret",   addr: CSR_MINSTRET,   reset: 0 },
+    CsrEntry { name: "mcycleh",    addr: CSR_MCYCLEH,    reset: 0 },
+    CsrEntry { name: "minstreth",  addr: CSR_MINSTRETH,  reset: 0 },
     // Machine Trap Handling
     CsrEntry { name: "mscratch",   addr: CSR_MSCRATCH,   reset: 0 },
     CsrEntry { name: "mepc",       addr: CSR_MEPC,       reset: 0 },
@@ -130,6 +194,16 @@ pub const M_CSRS: &[CsrEntry] = &[
     CsrEntry { name: "mtval2",     addr: CSR_MTVAL2,     reset: 0 },
 ];
 
+// ============================================================================
+// Sdtrig (Trigger Module) CSR Addresses
+// ============================================================================
+// tselect/tdata1/tdata2 不进 CsrBank：它们是触发器堆的窗口寄存器，
+// 读写语义由 CpuCore::csr_read / csr_write 按 tselect 转发
+
+pub const CSR_TSELECT: u16 = 0x7A0;
+pub const CSR_TDATA1: u16 = 0x7A1;
+pub const CSR_TDATA2: u16 = 0x7A2;
+
 // ============================================================================
 // Supervisor-level CSR Addresses (S-mode)
 // ============================================================================
@@ -1,6 +1,11 @@
 //! Execution units split by ISA modules
 pub mod rv32i;
 pub mod rv32m;
+pub mod rv32a;
 pub mod rv32f;
+pub mod rv32v;
+pub mod zicbo;
+pub mod zicond;
 pub mod zicsr;
+pub mod zk;
 pub mod priv_instr;
@@ -4,19 +4,29 @@
 
 use super::super::CpuCore;
 use super::super::csr_def::{CSR_MEPC, CSR_MSTATUS, CSR_SEPC, CSR_SSTATUS};
-use super::super::trap::{mstatus, PrivilegeMode};
+use super::super::trap::{mstatus, PrivilegeMode, TrapCause};
 use super::super::CpuState;
-use crate::isa::RvInstr;
+use crate::isa::{RvInstr, MRET_ENCODING, SRET_ENCODING};
 
 /// 执行特权指令。返回 true 如果处理了该指令。
-pub fn execute(cpu: &mut CpuCore, instr: RvInstr) -> bool {
+pub fn execute(cpu: &mut CpuCore, instr: RvInstr, current_pc: u32) -> bool {
     match instr {
         RvInstr::Mret => {
-            execute_mret(cpu);
+            // profile 中没有 M-mode 时 MRET 是非法指令
+            if cpu.has_m_mode() {
+                execute_mret(cpu);
+            } else {
+                cpu.take_trap_at(TrapCause::IllegalInstruction, MRET_ENCODING, current_pc);
+            }
             true
         }
         RvInstr::Sret => {
-            execute_sret(cpu);
+            // profile 中没有 S-mode 时 SRET 是非法指令
+            if cpu.has_s_mode() {
+                execute_sret(cpu);
+            } else {
+                cpu.take_trap_at(TrapCause::IllegalInstruction, SRET_ENCODING, current_pc);
+            }
             true
         }
         RvInstr::Wfi => {
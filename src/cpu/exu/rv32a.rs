@@ -0,0 +1,110 @@
+use super::super::{CpuCore, MemAccessType};
+use super::super::trap::TrapCause;
+use crate::isa::RvInstr;
+use crate::memory::Memory;
+
+/// Execute RV32A atomic instructions. Returns true if handled.
+///
+/// 单 hart 仿真中原子性天然成立：每条指令的 load-modify-store
+/// 之间不会有其他访问插入。LR/SC 通过 `CpuCore` 的保留集
+/// （reservation）模拟：LR.W 登记地址，SC.W 仅在保留仍有效且
+/// 地址匹配时写入并返回 0，否则返回 1。任何 SC.W 都会清除保留。
+pub fn execute(cpu: &mut CpuCore, mem: &mut dyn Memory, instr: RvInstr, current_pc: u32) -> bool {
+    match instr {
+        RvInstr::LrW { rd, rs1 } => {
+            let addr = cpu.read_reg(rs1);
+            if addr & 0x3 != 0 {
+                cpu.take_trap_at(TrapCause::LoadAddressMisaligned, addr, current_pc);
+                return true;
+            }
+            let value = match cpu.mem_result(mem.load32(addr), MemAccessType::Load, current_pc) {
+                Some(v) => v,
+                None => return true,
+            };
+            cpu.set_reservation(addr);
+            cpu.write_reg(rd, value);
+        }
+        RvInstr::ScW { rd, rs1, rs2 } => {
+            let addr = cpu.read_reg(rs1);
+            if addr & 0x3 != 0 {
+                cpu.take_trap_at(TrapCause::StoreAddressMisaligned, addr, current_pc);
+                return true;
+            }
+            if cpu.check_and_clear_reservation(addr) {
+                let value = cpu.read_reg(rs2);
+                if !cpu.mem_result_unit(mem.store32(addr, value), MemAccessType::Store, current_pc)
+                {
+                    return true;
+                }
+                cpu.write_reg(rd, 0);
+            } else {
+                cpu.write_reg(rd, 1);
+            }
+        }
+
+        // ========== AMO 指令 ==========
+        RvInstr::AmoswapW { rd, rs1, rs2 } => {
+            amo(cpu, mem, rd, rs1, rs2, current_pc, |_, src| src);
+        }
+        RvInstr::AmoaddW { rd, rs1, rs2 } => {
+            amo(cpu, mem, rd, rs1, rs2, current_pc, u32::wrapping_add);
+        }
+        RvInstr::AmoxorW { rd, rs1, rs2 } => {
+            amo(cpu, mem, rd, rs1, rs2, current_pc, |old, src| old ^ src);
+        }
+        RvInstr::AmoandW { rd, rs1, rs2 } => {
+            amo(cpu, mem, rd, rs1, rs2, current_pc, |old, src| old & src);
+        }
+        RvInstr::AmoorW { rd, rs1, rs2 } => {
+            amo(cpu, mem, rd, rs1, rs2, current_pc, |old, src| old | src);
+        }
+        RvInstr::AmominW { rd, rs1, rs2 } => {
+            amo(cpu, mem, rd, rs1, rs2, current_pc, |old, src| {
+                (old as i32).min(src as i32) as u32
+            });
+        }
+        RvInstr::AmomaxW { rd, rs1, rs2 } => {
+            amo(cpu, mem, rd, rs1, rs2, current_pc, |old, src| {
+                (old as i32).max(src as i32) as u32
+            });
+        }
+        RvInstr::AmominuW { rd, rs1, rs2 } => {
+            amo(cpu, mem, rd, rs1, rs2, current_pc, u32::min);
+        }
+        RvInstr::AmomaxuW { rd, rs1, rs2 } => {
+            amo(cpu, mem, rd, rs1, rs2, current_pc, u32::max);
+        }
+
+        _ => return false,
+    }
+
+    true
+}
+
+/// AMO 公共骨架：load 旧值 → 计算新值 → store → 旧值写回 rd
+///
+/// AMO 要求自然对齐；misaligned 按规范报 store/AMO 地址未对齐异常。
+fn amo(
+    cpu: &mut CpuCore,
+    mem: &mut dyn Memory,
+    rd: u8,
+    rs1: u8,
+    rs2: u8,
+    current_pc: u32,
+    op: impl FnOnce(u32, u32) -> u32,
+) {
+    let addr = cpu.read_reg(rs1);
+    if addr & 0x3 != 0 {
+        cpu.take_trap_at(TrapCause::StoreAddressMisaligned, addr, current_pc);
+        return;
+    }
+    let old = match cpu.mem_result(mem.load32(addr), MemAccessType::Store, current_pc) {
+        Some(v) => v,
+        None => return,
+    };
+    let new = op(old, cpu.read_reg(rs2));
+    if !cpu.mem_result_unit(mem.store32(addr, new), MemAccessType::Store, current_pc) {
+        return;
+    }
+    cpu.write_reg(rd, old);
+}
@@ -1,4 +1,4 @@
-use super::super::{CpuCore, MemAccessType};
+use super::super::{CpuCore, MemAccessType, MisalignedPolicy};
 use super::super::trap::TrapCause;
 use crate::isa::RvInstr;
 use crate::memory::Memory;
@@ -244,6 +244,9 @@ fn load_halfword(
         let raw = cpu.mem_result(mem.load16(addr), MemAccessType::Load, current_pc)?;
         return Some(if signed { raw as i16 as i32 as u32 } else { raw as u32 });
     }
+    if !misaligned_allowed(cpu, addr, MemAccessType::Load, current_pc) {
+        return None;
+    }
 
     let b0 = cpu.mem_result(mem.load8(addr), MemAccessType::Load, current_pc)?;
     let b1 = cpu.mem_result(mem.load8(addr.wrapping_add(1)), MemAccessType::Load, current_pc)?;
@@ -260,6 +263,9 @@ fn load_word(
     if addr & 0x3 == 0 {
         return cpu.mem_result(mem.load32(addr), MemAccessType::Load, current_pc);
     }
+    if !misaligned_allowed(cpu, addr, MemAccessType::Load, current_pc) {
+        return None;
+    }
 
     let mut bytes = [0u8; 4];
     for i in 0..4 {
@@ -282,7 +288,13 @@ fn store_halfword(
     if addr & 0x1 == 0 {
         return cpu.mem_result_unit(mem.store16(addr, value), MemAccessType::Store, current_pc);
     }
+    if !misaligned_allowed(cpu, addr, MemAccessType::Store, current_pc) {
+        return false;
+    }
 
+    if !probe_store_range(cpu, mem, addr, 2, current_pc) {
+        return false;
+    }
     let bytes = value.to_le_bytes();
     if !cpu.mem_result_unit(mem.store8(addr, bytes[0]), MemAccessType::Store, current_pc) {
         return false;
@@ -307,7 +319,13 @@ fn store_word(
     if addr & 0x3 == 0 {
         return cpu.mem_result_unit(mem.store32(addr, value), MemAccessType::Store, current_pc);
     }
+    if !misaligned_allowed(cpu, addr, MemAccessType::Store, current_pc) {
+        return false;
+    }
 
+    if !probe_store_range(cpu, mem, addr, 4, current_pc) {
+        return false;
+    }
     let bytes = value.to_le_bytes();
     for i in 0..4 {
         if !cpu.mem_result_unit(
@@ -320,3 +338,43 @@ fn store_word(
     }
     true
 }
+
+/// 按配置的策略处理非对齐访问：Emulate 放行字节仿真，
+/// TrapMisaligned 触发对应的地址非对齐异常并返回 false
+fn misaligned_allowed(
+    cpu: &mut CpuCore,
+    addr: u32,
+    access: MemAccessType,
+    current_pc: u32,
+) -> bool {
+    if cpu.misaligned_policy() == MisalignedPolicy::Emulate {
+        return true;
+    }
+    let cause = match access {
+        MemAccessType::Load => TrapCause::LoadAddressMisaligned,
+        _ => TrapCause::StoreAddressMisaligned,
+    };
+    cpu.take_trap_at(cause, addr, current_pc);
+    false
+}
+
+/// 事务性检查：逐字节写入前先确认整个区间可访问
+///
+/// 非对齐存储拆成多次 store8，若中途才发现越界，前面的字节已经
+/// 落盘，异常就不精确了。先探测完整区间，任何一个字节不可访问
+/// 都按存储访问异常上报且不改动内存。
+fn probe_store_range(
+    cpu: &mut CpuCore,
+    mem: &mut dyn Memory,
+    addr: u32,
+    len: u32,
+    current_pc: u32,
+) -> bool {
+    for i in 0..len {
+        let probe = mem.load8(addr.wrapping_add(i)).map(|_| ());
+        if !cpu.mem_result_unit(probe, MemAccessType::Store, current_pc) {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,190 @@
+//! RV32V (vector) execution unit
+//!
+//! 初始子集：vsetvli、单位步长 load/store 和 OPIVV 整数算术。
+//!
+//! 执行语义遵守 vl/vtype：
+//! - 元素宽度取 vtype.SEW（算术）或指令编码的 EEW（load/store）
+//! - 寄存器组按 LMUL 布局：元素 i 落在 `v(base + i*esize/16)` 的
+//!   第 `i*esize%16` 字节（VLEN=128）
+//! - 只处理前 vl 个元素，vl 之后的元素保持不变（尾部不打扰）
+//!
+//! 简化：掩码位 vm 忽略（全部按无掩码执行），vstart 视为 0。
+
+use super::super::{CpuCore, MemAccessType};
+use crate::cpu::csr_def::{CSR_VL, CSR_VTYPE};
+use crate::isa::RvInstr;
+use crate::memory::Memory;
+
+/// VLEN（位）：与 Status 中 128-bit VecRegFile 一致
+const VLEN: u32 = 128;
+
+/// vtype 的解码视图
+struct Vtype {
+    /// 元素宽度（位）
+    sew: u32,
+    /// LMUL 分子/分母（1/8..8 编码为分数）
+    lmul_num: u32,
+    lmul_den: u32,
+    /// 非法 vtype
+    vill: bool,
+}
+
+impl Vtype {
+    fn decode(vtype: u32) -> Vtype {
+        let vill = (vtype >> 31) != 0;
+        let vsew = (vtype >> 3) & 0x7;
+        let vlmul = vtype & 0x7;
+
+        let sew = 8u32 << vsew.min(3);
+        let (lmul_num, lmul_den) = match vlmul {
+            0b000 => (1, 1),
+            0b001 => (2, 1),
+            0b010 => (4, 1),
+            0b011 => (8, 1),
+            0b101 => (1, 8),
+            0b110 => (1, 4),
+            0b111 => (1, 2),
+            _ => (0, 1), // 0b100 保留
+        };
+
+        // ELEN=32：SEW>32、保留的 LMUL 编码都视为非法
+        let vill = vill || vsew > 2 || lmul_num == 0;
+        Vtype { sew, lmul_num, lmul_den, vill }
+    }
+
+    /// VLMAX = LMUL * VLEN / SEW
+    fn vlmax(&self) -> u32 {
+        VLEN * self.lmul_num / (self.sew * self.lmul_den)
+    }
+}
+
+/// Execute RV32V vector instructions. Returns true if handled.
+pub fn execute(cpu: &mut CpuCore, mem: &mut dyn Memory, instr: RvInstr, current_pc: u32) -> bool {
+    // V 扩展未启用时交给非法指令处理
+    if !cpu.has_vec() {
+        return false;
+    }
+
+    match instr {
+        RvInstr::Vsetvli { rd, rs1, vtypei } => {
+            let vtype = Vtype::decode(vtypei as u32);
+            if vtype.vill {
+                cpu.csr_write(CSR_VTYPE, 1 << 31);
+                cpu.csr_write(CSR_VL, 0);
+                cpu.write_reg(rd, 0);
+                return true;
+            }
+
+            let vlmax = vtype.vlmax();
+            let avl = if rs1 != 0 {
+                cpu.read_reg(rs1)
+            } else if rd != 0 {
+                u32::MAX // rs1=x0, rd!=x0：请求最大向量长度
+            } else {
+                cpu.csr_read(CSR_VL) // rs1=rd=x0：仅更新 vtype，保持 vl
+            };
+            let vl = avl.min(vlmax);
+
+            cpu.csr_write(CSR_VTYPE, vtypei as u32);
+            cpu.csr_write(CSR_VL, vl);
+            cpu.write_reg(rd, vl);
+        }
+        RvInstr::VleV { vd, rs1, eew } => {
+            let vl = cpu.csr_read(CSR_VL);
+            let base = cpu.read_reg(rs1);
+            let esize = (eew / 8) as u32;
+            for i in 0..vl {
+                for b in 0..esize {
+                    let addr = base.wrapping_add(i * esize + b);
+                    let byte = match cpu.mem_result(mem.load8(addr), MemAccessType::Load, current_pc)
+                    {
+                        Some(v) => v,
+                        None => return true,
+                    };
+                    vreg_byte_write(cpu, vd, (i * esize + b) as usize, byte);
+                }
+            }
+        }
+        RvInstr::VseV { vs3, rs1, eew } => {
+            let vl = cpu.csr_read(CSR_VL);
+            let base = cpu.read_reg(rs1);
+            let esize = (eew / 8) as u32;
+            for i in 0..vl {
+                for b in 0..esize {
+                    let addr = base.wrapping_add(i * esize + b);
+                    let byte = vreg_byte_read(cpu, vs3, (i * esize + b) as usize);
+                    if !cpu.mem_result_unit(mem.store8(addr, byte), MemAccessType::Store, current_pc)
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+        RvInstr::VaddVV { vd, vs2, vs1 } => {
+            arith_vv(cpu, vd, vs2, vs1, u32::wrapping_add);
+        }
+        RvInstr::VsubVV { vd, vs2, vs1 } => {
+            arith_vv(cpu, vd, vs2, vs1, u32::wrapping_sub);
+        }
+        RvInstr::VandVV { vd, vs2, vs1 } => {
+            arith_vv(cpu, vd, vs2, vs1, |a, b| a & b);
+        }
+        RvInstr::VorVV { vd, vs2, vs1 } => {
+            arith_vv(cpu, vd, vs2, vs1, |a, b| a | b);
+        }
+        RvInstr::VxorVV { vd, vs2, vs1 } => {
+            arith_vv(cpu, vd, vs2, vs1, |a, b| a ^ b);
+        }
+
+        _ => return false,
+    }
+
+    true
+}
+
+/// OPIVV 算术骨架：按 vtype.SEW 对前 vl 个元素逐一计算 vs2 op vs1
+fn arith_vv(cpu: &mut CpuCore, vd: u8, vs2: u8, vs1: u8, op: impl Fn(u32, u32) -> u32) {
+    let vtype = Vtype::decode(cpu.csr_read(CSR_VTYPE));
+    if vtype.vill {
+        return;
+    }
+    let vl = cpu.csr_read(CSR_VL);
+    let esize = (vtype.sew / 8) as usize;
+    let mask = if vtype.sew == 32 { u32::MAX } else { (1u32 << vtype.sew) - 1 };
+
+    for i in 0..vl as usize {
+        let a = elem_read(cpu, vs2, i, esize);
+        let b = elem_read(cpu, vs1, i, esize);
+        elem_write(cpu, vd, i, esize, op(a, b) & mask);
+    }
+}
+
+/// 读寄存器组中的一个字节（跨越 LMUL>1 的寄存器边界）
+fn vreg_byte_read(cpu: &CpuCore, base: u8, byte_idx: usize) -> u8 {
+    let reg = base.wrapping_add((byte_idx / 16) as u8);
+    cpu.read_vec(reg)[byte_idx % 16]
+}
+
+/// 写寄存器组中的一个字节
+fn vreg_byte_write(cpu: &mut CpuCore, base: u8, byte_idx: usize, value: u8) {
+    let reg = base.wrapping_add((byte_idx / 16) as u8);
+    let mut data = cpu.read_vec(reg);
+    data[byte_idx % 16] = value;
+    cpu.write_vec(reg, data);
+}
+
+/// 读元素（小端，esize 字节）
+fn elem_read(cpu: &CpuCore, base: u8, idx: usize, esize: usize) -> u32 {
+    let mut value = 0u32;
+    for b in 0..esize {
+        value |= (vreg_byte_read(cpu, base, idx * esize + b) as u32) << (8 * b);
+    }
+    value
+}
+
+/// 写元素（小端，esize 字节）
+fn elem_write(cpu: &mut CpuCore, base: u8, idx: usize, esize: usize, value: u32) {
+    for b in 0..esize {
+        vreg_byte_write(cpu, base, idx * esize + b, (value >> (8 * b)) as u8);
+    }
+}
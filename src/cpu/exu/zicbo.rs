@@ -0,0 +1,45 @@
+//! Zicbo 扩展执行单元（缓存块操作）
+//!
+//! CBO.ZERO 按架构语义把整个块写零；CBO.CLEAN/FLUSH/INVAL 在
+//! 本仿真中是空操作——cache 层（[`crate::cache`]）只做 tag 统计，
+//! 数据始终与内存一致，写回/失效没有可观察效果。
+
+use super::super::{CpuCore, MemAccessType};
+use crate::isa::RvInstr;
+use crate::memory::Memory;
+
+/// CBO 指令操作的缓存块大小（字节）
+///
+/// 与 Linux/QEMU 的常见取值一致；客户软件应从设备树或
+/// 环境中获知，而不是假设其他值。
+pub const CBO_BLOCK_SIZE: u32 = 64;
+
+/// 执行 Zicbo 指令，如果指令不属于 Zicbo 返回 false
+pub fn execute(
+    cpu: &mut CpuCore,
+    mem: &mut dyn Memory,
+    instr: RvInstr,
+    current_pc: u32,
+) -> bool {
+    match instr {
+        RvInstr::CboZero { rs1 } => {
+            let base = cpu.read_reg(rs1) & !(CBO_BLOCK_SIZE - 1);
+            for offset in (0..CBO_BLOCK_SIZE).step_by(4) {
+                if !cpu.mem_result_unit(
+                    mem.store32(base + offset, 0),
+                    MemAccessType::Store,
+                    current_pc,
+                ) {
+                    return true;
+                }
+            }
+        }
+        RvInstr::CboClean { rs1 }
+        | RvInstr::CboFlush { rs1 }
+        | RvInstr::CboInval { rs1 } => {
+            let _ = rs1; // tag-only cache 模型下无可观察效果
+        }
+        _ => return false,
+    }
+    true
+}
@@ -0,0 +1,20 @@
+//! Zicond 扩展执行单元（整数条件操作）
+
+use super::super::CpuCore;
+use crate::isa::RvInstr;
+
+/// 执行 Zicond 指令，如果指令不属于 Zicond 返回 false
+pub fn execute(cpu: &mut CpuCore, instr: RvInstr) -> bool {
+    match instr {
+        RvInstr::CzeroEqz { rd, rs1, rs2 } => {
+            let value = if cpu.read_reg(rs2) == 0 { 0 } else { cpu.read_reg(rs1) };
+            cpu.write_reg(rd, value);
+        }
+        RvInstr::CzeroNez { rd, rs1, rs2 } => {
+            let value = if cpu.read_reg(rs2) != 0 { 0 } else { cpu.read_reg(rs1) };
+            cpu.write_reg(rd, value);
+        }
+        _ => return false,
+    }
+    true
+}
@@ -2,11 +2,29 @@
 //!
 //! 实现 CSR 操作指令的执行逻辑
 
+use super::super::trap::TrapCause;
 use super::super::CpuCore;
 use crate::isa::RvInstr;
 
 /// 执行 Zicsr 指令。返回 true 如果处理了该指令。
-pub fn execute(cpu: &mut CpuCore, instr: RvInstr) -> bool {
+///
+/// 执行前按特权规范 2.1 节检查访问合法性（CSR 是否实现、只读地址段、
+/// 特权级），不合法的访问触发非法指令异常而不是静默读 0 / 丢弃写入。
+pub fn execute(cpu: &mut CpuCore, instr: RvInstr, current_pc: u32, raw: u32) -> bool {
+    // CSRRS/CSRRC 在 rs1 = x0、CSRRSI/CSRRCI 在 zimm = 0 时是纯读取，
+    // 对只读 CSR 不构成写入
+    let (csr, writes) = match instr {
+        RvInstr::Csrrw { csr, .. } | RvInstr::Csrrwi { csr, .. } => (csr, true),
+        RvInstr::Csrrs { rs1, csr, .. } | RvInstr::Csrrc { rs1, csr, .. } => (csr, rs1 != 0),
+        RvInstr::Csrrsi { zimm, csr, .. } | RvInstr::Csrrci { zimm, csr, .. } => (csr, zimm != 0),
+        _ => return false,
+    };
+
+    if !cpu.csr_access_legal(csr, writes) {
+        cpu.take_trap_at(TrapCause::IllegalInstruction, raw, current_pc);
+        return true;
+    }
+
     match instr {
         // CSRRW: t = CSR[csr]; CSR[csr] = rs1; rd = t
         // 特例：当 rd = x0 时，不读取 CSR（可能有副作用的 CSR 不会被读取）
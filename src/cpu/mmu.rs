@@ -0,0 +1,284 @@
+//! Sv32 虚拟内存（MMU）
+//!
+//! 在 CPU 核心与物理内存之间插入一层地址翻译：satp 启用 Sv32 且
+//! 当前特权级低于 M-mode 时，取指和数据访问都要经过两级页表遍历，
+//! 并按当前特权级与 mstatus.SUM/MXR 检查 PTE 的 U/R/W/X/A/D 位。
+//!
+//! 翻译失败以 [`MemError::PageFault`] 上报，由 `CpuCore` 按访问
+//! 类别映射到 `InstructionPageFault`/`LoadPageFault`/`StorePageFault`。
+//! A/D 位按软件维护的方案处理：A 未置位（或写入时 D 未置位）视为
+//! 页缺失，交由客体的缺页处理程序更新。
+
+use super::trap::PrivilegeMode;
+use super::MemAccessType;
+use crate::memory::{MemError, MemResult, Memory};
+
+/// 页内偏移位数（4 KiB 页）
+const PAGE_SHIFT: u32 = 12;
+
+// PTE 标志位
+const PTE_V: u32 = 1 << 0;
+const PTE_R: u32 = 1 << 1;
+const PTE_W: u32 = 1 << 2;
+const PTE_X: u32 = 1 << 3;
+const PTE_U: u32 = 1 << 4;
+const PTE_A: u32 = 1 << 6;
+const PTE_D: u32 = 1 << 7;
+
+/// satp 是否启用 Sv32（RV32 下 MODE 是 bit 31）
+pub(crate) fn satp_enables_sv32(satp: u32) -> bool {
+    satp & 0x8000_0000 != 0
+}
+
+/// 一次地址翻译所需的上下文（satp 根页表 + 特权状态快照）
+///
+/// 在每条指令开始时从 CSR 抓取；satp/mstatus 的修改从下一条指令
+/// 开始生效，与真实硬件的粒度一致。
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Sv32Ctx {
+    root_ppn: u32,
+    privilege: PrivilegeMode,
+    sum: bool,
+    mxr: bool,
+}
+
+impl Sv32Ctx {
+    pub(crate) fn new(satp: u32, privilege: PrivilegeMode, sum: bool, mxr: bool) -> Self {
+        Sv32Ctx {
+            root_ppn: satp & 0x003F_FFFF,
+            privilege,
+            sum,
+            mxr,
+        }
+    }
+
+    /// 两级页表遍历，返回物理地址
+    pub(crate) fn translate(
+        &self,
+        mem: &dyn Memory,
+        vaddr: u32,
+        access: MemAccessType,
+    ) -> MemResult<u32> {
+        let fault = MemError::PageFault { addr: vaddr };
+
+        let vpn = [(vaddr >> PAGE_SHIFT) & 0x3FF, vaddr >> 22];
+        let mut table_base = self.root_ppn << PAGE_SHIFT;
+
+        for level in (0..2usize).rev() {
+            let pte_addr = table_base.wrapping_add(vpn[level] * 4);
+            // 页表本身不可访问同样是页缺失
+            let pte = mem.load32(pte_addr).map_err(|_| fault)?;
+
+            // 无效或保留编码（W 置位但 R 未置位）
+            if pte & PTE_V == 0 || (pte & PTE_R == 0 && pte & PTE_W != 0) {
+                return Err(fault);
+            }
+
+            if pte & (PTE_R | PTE_X) != 0 {
+                // 叶子 PTE：权限与 A/D 检查
+                self.check_leaf(pte, access, vaddr)?;
+
+                let ppn = pte >> 10;
+                if level == 1 {
+                    // 4 MiB 超页：PPN 低 10 位必须为 0（对齐）
+                    if ppn & 0x3FF != 0 {
+                        return Err(fault);
+                    }
+                    return Ok(((ppn & !0x3FF) << PAGE_SHIFT) | (vaddr & 0x003F_FFFF));
+                }
+                return Ok((ppn << PAGE_SHIFT) | (vaddr & 0xFFF));
+            }
+
+            // 非叶子：下降到下一级页表
+            table_base = (pte >> 10) << PAGE_SHIFT;
+        }
+
+        // 第 0 级仍是非叶子编码
+        Err(fault)
+    }
+
+    /// 叶子 PTE 的权限检查
+    fn check_leaf(&self, pte: u32, access: MemAccessType, vaddr: u32) -> MemResult<()> {
+        let fault = MemError::PageFault { addr: vaddr };
+
+        // U 位与当前特权级
+        match self.privilege {
+            PrivilegeMode::User => {
+                if pte & PTE_U == 0 {
+                    return Err(fault);
+                }
+            }
+            PrivilegeMode::Supervisor => {
+                if pte & PTE_U != 0 {
+                    // S-mode 永远不能执行用户页；数据访问需要 SUM
+                    if access == MemAccessType::Fetch || !self.sum {
+                        return Err(fault);
+                    }
+                }
+            }
+            // M-mode 不经过翻译，这里不会出现
+            PrivilegeMode::Machine | PrivilegeMode::_Reserved => {}
+        }
+
+        // R/W/X 权限
+        let permitted = match access {
+            MemAccessType::Fetch => pte & PTE_X != 0,
+            MemAccessType::Load => pte & PTE_R != 0 || (self.mxr && pte & PTE_X != 0),
+            MemAccessType::Store => pte & PTE_W != 0,
+        };
+        if !permitted {
+            return Err(fault);
+        }
+
+        // A/D 位由软件维护：未置位视为页缺失
+        if pte & PTE_A == 0 || (access == MemAccessType::Store && pte & PTE_D == 0) {
+            return Err(fault);
+        }
+
+        Ok(())
+    }
+}
+
+/// 带 Sv32 翻译的内存视图
+///
+/// 数据访问（exu 中的 load/store）经此视图时逐次翻译后再下发到
+/// 物理内存；取指的翻译由 `CpuCore::step` 单独完成（权限按 X 位
+/// 检查，不能走这里的 Load 路径）。
+pub(crate) struct MmuMemory<'a> {
+    mem: &'a mut dyn Memory,
+    ctx: Sv32Ctx,
+}
+
+impl<'a> MmuMemory<'a> {
+    pub(crate) fn new(mem: &'a mut dyn Memory, ctx: Sv32Ctx) -> Self {
+        MmuMemory { mem, ctx }
+    }
+}
+
+impl Memory for MmuMemory<'_> {
+    fn load8(&self, addr: u32) -> MemResult<u8> {
+        let pa = self.ctx.translate(&*self.mem, addr, MemAccessType::Load)?;
+        self.mem.load8(pa)
+    }
+
+    fn load16(&self, addr: u32) -> MemResult<u16> {
+        let pa = self.ctx.translate(&*self.mem, addr, MemAccessType::Load)?;
+        self.mem.load16(pa)
+    }
+
+    fn load32(&self, addr: u32) -> MemResult<u32> {
+        let pa = self.ctx.translate(&*self.mem, addr, MemAccessType::Load)?;
+        self.mem.load32(pa)
+    }
+
+    fn store8(&mut self, addr: u32, value: u8) -> MemResult<()> {
+        let pa = self.ctx.translate(&*self.mem, addr, MemAccessType::Store)?;
+        self.mem.store8(pa, value)
+    }
+
+    fn store16(&mut self, addr: u32, value: u16) -> MemResult<()> {
+        let pa = self.ctx.translate(&*self.mem, addr, MemAccessType::Store)?;
+        self.mem.store16(pa, value)
+    }
+
+    fn store32(&mut self, addr: u32, value: u32) -> MemResult<()> {
+        let pa = self.ctx.translate(&*self.mem, addr, MemAccessType::Store)?;
+        self.mem.store32(pa, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::FlatMemory;
+
+    /// 搭建两级页表：VA 0x2000 -> PA 0x3000，标志由 `flags` 指定
+    fn build_page_table(mem: &mut FlatMemory, flags: u32) -> Sv32Ctx {
+        // 根页表在 PA 0x1000（ppn=1），二级页表在 PA 0x2000
+        mem.store32(0x1000, (2 << 10) | PTE_V).unwrap();
+        mem.store32(0x2000 + 2 * 4, (3 << 10) | flags).unwrap();
+        Sv32Ctx::new(0x8000_0001, PrivilegeMode::User, false, false)
+    }
+
+    const ALL_FLAGS: u32 = PTE_V | PTE_R | PTE_W | PTE_X | PTE_U | PTE_A | PTE_D;
+
+    #[test]
+    fn test_translate_two_level() {
+        let mut mem = FlatMemory::new(64 * 1024, 0);
+        let ctx = build_page_table(&mut mem, ALL_FLAGS);
+
+        assert_eq!(ctx.translate(&mem, 0x2ABC, MemAccessType::Load).unwrap(), 0x3ABC);
+        assert_eq!(ctx.translate(&mem, 0x2000, MemAccessType::Store).unwrap(), 0x3000);
+        assert_eq!(ctx.translate(&mem, 0x2004, MemAccessType::Fetch).unwrap(), 0x3004);
+
+        // 未映射的 VPN 应报页缺失
+        assert_eq!(
+            ctx.translate(&mem, 0x5000, MemAccessType::Load),
+            Err(MemError::PageFault { addr: 0x5000 })
+        );
+    }
+
+    #[test]
+    fn test_translate_superpage() {
+        let mut mem = FlatMemory::new(64 * 1024, 0);
+        // 根页表叶子：VA 0x0040_0000 起 4 MiB 映射到 PA 0x0080_0000
+        mem.store32(0x1000 + 4, (0x800 << 10) | ALL_FLAGS).unwrap();
+        let ctx = Sv32Ctx::new(0x8000_0001, PrivilegeMode::User, false, false);
+
+        assert_eq!(
+            ctx.translate(&mem, 0x0040_1234, MemAccessType::Load).unwrap(),
+            0x0080_1234
+        );
+
+        // 未对齐的超页（PPN 低 10 位非零）应报页缺失
+        mem.store32(0x1000 + 4, ((0x800 | 1) << 10) | ALL_FLAGS).unwrap();
+        assert!(ctx.translate(&mem, 0x0040_1234, MemAccessType::Load).is_err());
+    }
+
+    #[test]
+    fn test_permission_checks() {
+        let mut mem = FlatMemory::new(64 * 1024, 0);
+
+        // 只读页：写应失败，MXR 关闭时可读
+        let ctx = build_page_table(&mut mem, PTE_V | PTE_R | PTE_U | PTE_A);
+        assert!(ctx.translate(&mem, 0x2000, MemAccessType::Load).is_ok());
+        assert!(ctx.translate(&mem, 0x2000, MemAccessType::Store).is_err());
+        assert!(ctx.translate(&mem, 0x2000, MemAccessType::Fetch).is_err());
+
+        // 只执行页：MXR 置位时允许读
+        let _ = build_page_table(&mut mem, PTE_V | PTE_X | PTE_U | PTE_A);
+        let no_mxr = Sv32Ctx::new(0x8000_0001, PrivilegeMode::User, false, false);
+        let mxr = Sv32Ctx::new(0x8000_0001, PrivilegeMode::User, false, true);
+        assert!(no_mxr.translate(&mem, 0x2000, MemAccessType::Load).is_err());
+        assert!(mxr.translate(&mem, 0x2000, MemAccessType::Load).is_ok());
+
+        // A 位未置：任何访问都是页缺失
+        let ctx = build_page_table(&mut mem, PTE_V | PTE_R | PTE_W | PTE_U | PTE_D);
+        assert!(ctx.translate(&mem, 0x2000, MemAccessType::Load).is_err());
+
+        // D 位未置：读可以，写是页缺失
+        let ctx = build_page_table(&mut mem, PTE_V | PTE_R | PTE_W | PTE_U | PTE_A);
+        assert!(ctx.translate(&mem, 0x2000, MemAccessType::Load).is_ok());
+        assert!(ctx.translate(&mem, 0x2000, MemAccessType::Store).is_err());
+    }
+
+    #[test]
+    fn test_user_bit_and_sum() {
+        let mut mem = FlatMemory::new(64 * 1024, 0);
+        let _ = build_page_table(&mut mem, ALL_FLAGS); // 用户页
+
+        // S-mode 访问用户页：默认拒绝，SUM 置位后允许数据访问
+        let s = Sv32Ctx::new(0x8000_0001, PrivilegeMode::Supervisor, false, false);
+        let s_sum = Sv32Ctx::new(0x8000_0001, PrivilegeMode::Supervisor, true, false);
+        assert!(s.translate(&mem, 0x2000, MemAccessType::Load).is_err());
+        assert!(s_sum.translate(&mem, 0x2000, MemAccessType::Load).is_ok());
+        // SUM 不影响取指：S-mode 永远不能执行用户页
+        assert!(s_sum.translate(&mem, 0x2000, MemAccessType::Fetch).is_err());
+
+        // U 位未置的页：U-mode 拒绝，S-mode 允许
+        let _ = build_page_table(&mut mem, ALL_FLAGS & !PTE_U);
+        let u = Sv32Ctx::new(0x8000_0001, PrivilegeMode::User, false, false);
+        assert!(u.translate(&mem, 0x2000, MemAccessType::Load).is_err());
+        assert!(s.translate(&mem, 0x2000, MemAccessType::Load).is_ok());
+    }
+}
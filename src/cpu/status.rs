@@ -1,6 +1,10 @@
 //! CPU architectural state components: register file(s) and CSR bank.
 
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::csr_def;
 use super::trap::PrivilegeMode;
 
 /// Generic register file with configurable count, element type, and zero-hardwire behavior.
@@ -55,8 +59,115 @@ where
 /// Integer register file x0..x31. x0 is hard-wired to zero.
 pub type RegFile = GenericRegFile<32, u32, true>;
 
-/// Floating-point register file f0..f31. No zero-hardwire.
-pub type FpRegFile = GenericRegFile<32, u32, false>;
+/// FP register width (FLEN).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FpWidth {
+    /// FLEN=32: F extension only.
+    F32,
+    /// FLEN=64: D extension (F values are NaN-boxed).
+    F64,
+}
+
+/// Floating-point register file f0..f31 with configurable register
+/// width. No zero-hardwire.
+///
+/// Storage is always 64-bit so call sites stay width-agnostic: with
+/// [`FpWidth::F32`] only the low halves are architecturally visible,
+/// while with [`FpWidth::F64`] 32-bit writes are NaN-boxed (upper
+/// half all-ones) and 32-bit reads validate the boxing, substituting
+/// the canonical NaN for improperly boxed values as the F-on-D rules
+/// require. Full-width access for future D instructions goes through
+/// [`Self::read64`]/[`Self::write64`].
+#[derive(Clone)]
+pub struct FpRegs {
+    regs: [u64; 32],
+    width: FpWidth,
+}
+
+/// Upper-half pattern of a properly NaN-boxed 32-bit value.
+const NAN_BOX_HIGH: u64 = 0xFFFF_FFFF;
+/// Canonical single-precision NaN, substituted on unboxed 32-bit reads.
+const F32_CANONICAL_NAN: u32 = 0x7FC0_0000;
+
+impl FpRegs {
+    /// FLEN=32 register file (plain F extension).
+    pub fn new() -> Self {
+        Self::with_width(FpWidth::F32)
+    }
+
+    /// Register file with an explicit width.
+    pub fn with_width(width: FpWidth) -> Self {
+        Self { regs: [0; 32], width }
+    }
+
+    /// Configured register width.
+    pub fn width(&self) -> FpWidth {
+        self.width
+    }
+
+    /// Zero all registers, keeping the configured width.
+    pub fn reset(&mut self) {
+        self.regs = [0; 32];
+    }
+
+    /// Read the single-precision view of a register.
+    ///
+    /// With FLEN=64, an improperly boxed value reads back as the
+    /// canonical NaN instead of its raw low half.
+    #[inline]
+    pub fn read(&self, reg: u8) -> u32 {
+        let raw = self.regs[reg as usize];
+        match self.width {
+            FpWidth::F32 => raw as u32,
+            FpWidth::F64 if raw >> 32 == NAN_BOX_HIGH => raw as u32,
+            FpWidth::F64 => F32_CANONICAL_NAN,
+        }
+    }
+
+    /// Write a single-precision value (NaN-boxed when FLEN=64).
+    #[inline]
+    pub fn write(&mut self, reg: u8, value: u32) {
+        self.regs[reg as usize] = match self.width {
+            FpWidth::F32 => value as u64,
+            FpWidth::F64 => (NAN_BOX_HIGH << 32) | value as u64,
+        };
+    }
+
+    /// Read the full register contents (for FLEN=64 instructions).
+    #[inline]
+    #[allow(dead_code)]
+    pub fn read64(&self, reg: u8) -> u64 {
+        self.regs[reg as usize]
+    }
+
+    /// Write the full register contents without boxing.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn write64(&mut self, reg: u8, value: u64) {
+        self.regs[reg as usize] = value;
+    }
+
+    /// Whether the register holds a properly NaN-boxed 32-bit value.
+    /// Always true with FLEN=32 (there is nothing to box).
+    #[allow(dead_code)]
+    pub fn is_boxed(&self, reg: u8) -> bool {
+        match self.width {
+            FpWidth::F32 => true,
+            FpWidth::F64 => self.regs[reg as usize] >> 32 == NAN_BOX_HIGH,
+        }
+    }
+
+    /// Single-precision view of all registers (snapshot format).
+    pub fn snapshot(&self) -> [u32; 32] {
+        std::array::from_fn(|i| self.read(i as u8))
+    }
+}
+
+impl Default for FpRegs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Vector register file v0..v31. Each element is 128-bit (VLEN=128 default).
 /// Stored as [u8; 16] per register for flexibility.
@@ -71,11 +182,32 @@ pub struct CsrEntry {
     pub reset: u32,
 }
 
+/// 有副作用 CSR 的后端：读/写转发给回调而不是普通存储单元
+///
+/// 用于联动寄存器（fcsr 与 fflags/frm 共享状态）、读宿主时钟的
+/// time，以及 MMIO 风格的自定义 CSR。钩子以 `&self` 接口加内部
+/// 可变性实现（与 [`crate::memory::Memory`] 的 load 族一致）。
+pub trait CsrHook {
+    /// 读取当前值
+    fn read(&self) -> u32;
+    /// 写入新值（WARL 行为由钩子自行实现）
+    fn write(&self, value: u32);
+}
+
+/// CSR 存储单元：普通值或回调后端
+#[derive(Clone)]
+enum CsrCell {
+    Value(u32),
+    Hook(Rc<dyn CsrHook>),
+}
+
 /// Simple CSR bank: a hash table indexed by address.
+///
+/// 克隆 bank 时钩子单元共享同一个后端（`Rc`），普通单元按值复制。
 #[derive(Clone, Default)]
 #[allow(dead_code)]
 pub struct CsrBank {
-    table: HashMap<u16, u32>,
+    table: HashMap<u16, CsrCell>,
 }
 
 impl CsrBank {
@@ -89,26 +221,115 @@ impl CsrBank {
     #[allow(dead_code)]
     pub fn register(&mut self, entries: &[CsrEntry]) {
         for e in entries {
-            self.table.insert(e.addr, e.reset);
+            self.table.insert(e.addr, CsrCell::Value(e.reset));
         }
     }
 
+    /// 给单个地址安装回调后端（覆盖已有的普通单元或旧钩子）
+    #[allow(dead_code)]
+    pub fn install_hook(&mut self, addr: u16, hook: Rc<dyn CsrHook>) {
+        self.table.insert(addr, CsrCell::Hook(hook));
+    }
+
+    /// 安装联动的 fflags/frm/fcsr：三个地址共享一个 8 位状态
+    /// （fcsr[4:0] = fflags，fcsr[7:5] = frm），写任何一个立即反映
+    /// 到其余两个
+    #[allow(dead_code)]
+    pub fn install_fcsr(&mut self) {
+        let bits = Rc::new(Cell::new(0u32));
+        self.install_hook(
+            csr_def::CSR_FFLAGS,
+            Rc::new(FcsrView { bits: bits.clone(), shift: 0, mask: 0x1F }),
+        );
+        self.install_hook(
+            csr_def::CSR_FRM,
+            Rc::new(FcsrView { bits: bits.clone(), shift: 5, mask: 0x7 }),
+        );
+        self.install_hook(
+            csr_def::CSR_FCSR,
+            Rc::new(FcsrView { bits, shift: 0, mask: 0xFF }),
+        );
+    }
+
+    /// 该地址是否已注册（用于判断 CSR 在当前配置中是否实现）
+    #[inline]
+    #[allow(dead_code)]
+    pub fn contains(&self, addr: u16) -> bool {
+        self.table.contains_key(&addr)
+    }
+
     #[inline]
     #[allow(dead_code)]
     pub fn read(&self, addr: u16) -> u32 {
-        *self.table.get(&addr).unwrap_or(&0)
+        match self.table.get(&addr) {
+            Some(CsrCell::Value(v)) => *v,
+            Some(CsrCell::Hook(hook)) => hook.read(),
+            None => 0,
+        }
     }
 
     #[inline]
     #[allow(dead_code)]
     pub fn write(&mut self, addr: u16, value: u32) {
-        self.table.insert(addr, value);
+        match self.table.get_mut(&addr) {
+            Some(CsrCell::Hook(hook)) => hook.write(value),
+            _ => {
+                self.table.insert(addr, CsrCell::Value(value));
+            }
+        }
     }
 
+    /// 按地址物化所有单元的当前值（钩子单元经由回调读出）
     #[inline]
     #[allow(dead_code)]
-    pub fn snapshot(&self) -> &HashMap<u16, u32> {
-        &self.table
+    pub fn snapshot(&self) -> HashMap<u16, u32> {
+        self.table
+            .iter()
+            .map(|(&addr, _)| (addr, self.read(addr)))
+            .collect()
+    }
+
+    /// Replace the whole table at once (used by record/replay undo).
+    ///
+    /// 普通单元整体替换；钩子单元保留后端，值经由回调写回。
+    pub fn restore(&mut self, values: HashMap<u16, u32>) {
+        let hooks: Vec<(u16, Rc<dyn CsrHook>)> = self
+            .table
+            .iter()
+            .filter_map(|(&addr, cell)| match cell {
+                CsrCell::Hook(hook) => Some((addr, hook.clone())),
+                CsrCell::Value(_) => None,
+            })
+            .collect();
+        self.table = values
+            .into_iter()
+            .map(|(addr, v)| (addr, CsrCell::Value(v)))
+            .collect();
+        for (addr, hook) in hooks {
+            if let Some(CsrCell::Value(v)) = self.table.get(&addr) {
+                hook.write(*v);
+            }
+            self.table.insert(addr, CsrCell::Hook(hook));
+        }
+    }
+}
+
+/// fcsr 位段视图：fflags/frm/fcsr 共享的 [`CsrHook`] 实现
+struct FcsrView {
+    bits: Rc<Cell<u32>>,
+    shift: u32,
+    mask: u32,
+}
+
+impl CsrHook for FcsrView {
+    fn read(&self) -> u32 {
+        (self.bits.get() >> self.shift) & self.mask
+    }
+
+    fn write(&self, value: u32) {
+        let cur = self.bits.get();
+        self.bits
+            .set((cur & !(self.mask << self.shift)) | ((value & self.mask) << self.shift));
     }
 }
 
@@ -117,13 +338,17 @@ impl CsrBank {
 pub struct Status {
     pub int: RegFile,
     #[allow(dead_code)]
-    pub fp: Option<FpRegFile>,
+    pub fp: Option<FpRegs>,
     #[allow(dead_code)]
     pub vec: Option<VecRegFile>,
     #[allow(dead_code)]
     pub csr: CsrBank,
     /// Current privilege mode
     pub privilege: PrivilegeMode,
+    /// Whether M-mode (and its CSRs) exists in this profile
+    pub has_m_mode: bool,
+    /// Whether S-mode (and its CSRs) exists in this profile
+    pub has_s_mode: bool,
 }
 
 impl Default for Status {
@@ -140,14 +365,25 @@ impl Status {
             vec: None,
             csr: CsrBank::new(),
             privilege: PrivilegeMode::Machine, // 启动时为 M-mode
+            has_m_mode: true,
+            has_s_mode: false,
         }
     }
 
     /// Enable floating-point state (F extension) on demand.
     #[allow(dead_code)]
     pub fn enable_fp(&mut self) {
-        if self.fp.is_none() {
-            self.fp = Some(FpRegFile::new());
+        self.enable_fp_width(FpWidth::F32);
+    }
+
+    /// Enable floating-point state with an explicit register width
+    /// (FLEN=64 for the D extension). Widening an already-enabled
+    /// file keeps nothing: registers reset to zero.
+    #[allow(dead_code)]
+    pub fn enable_fp_width(&mut self, width: FpWidth) {
+        match &self.fp {
+            Some(fp) if fp.width() == width => {}
+            _ => self.fp = Some(FpRegs::with_width(width)),
         }
     }
 
@@ -195,7 +431,7 @@ impl Status {
 
     #[inline]
     #[allow(dead_code)]
-    pub fn fp_snapshot(&self) -> Option<&[u32; 32]> {
+    pub fn fp_snapshot(&self) -> Option<[u32; 32]> {
         self.fp.as_ref().map(|f| f.snapshot())
     }
 
@@ -238,7 +474,7 @@ impl Status {
 
     #[inline]
     #[allow(dead_code)]
-    pub fn csr_snapshot(&self) -> &HashMap<u16, u32> {
+    pub fn csr_snapshot(&self) -> HashMap<u16, u32> {
         self.csr.snapshot()
     }
 
@@ -247,15 +483,15 @@ impl Status {
     pub fn snapshot(&self) -> StatusSnapshot {
         StatusSnapshot {
             int: self.int.snapshot().clone(),
-            fp: self.fp.as_ref().map(|f| f.snapshot().clone()),
+            fp: self.fp.as_ref().map(|f| f.snapshot()),
             vec: self.vec.as_ref().map(|v| v.snapshot().clone()),
-            csr: self.csr.table.clone(),
+            csr: self.csr.snapshot(),
         }
     }
 }
 
 /// Snapshot of all architectural state.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[allow(dead_code)]
 pub struct StatusSnapshot {
     pub int: [u32; 32],
@@ -263,3 +499,343 @@ pub struct StatusSnapshot {
     pub vec: Option<[[u8; 16]; 32]>,
     pub csr: HashMap<u16, u32>,
 }
+
+impl StatusSnapshot {
+    /// Serialize to a single JSON object with deterministic field and
+    /// CSR ordering, suitable for golden-state regression tests and
+    /// cross-run diffing. `fp`/`vec` are `null` when the extension is
+    /// not enabled; CSR addresses are encoded as decimal string keys.
+    #[allow(dead_code)]
+    pub fn to_json(&self) -> String {
+        fn u32_array(out: &mut String, values: &[u32]) {
+            out.push('[');
+            for (i, v) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&v.to_string());
+            }
+            out.push(']');
+        }
+
+        let mut out = String::from("{\"int\":");
+        u32_array(&mut out, &self.int);
+        out.push_str(",\"fp\":");
+        match &self.fp {
+            Some(fp) => u32_array(&mut out, fp),
+            None => out.push_str("null"),
+        }
+        out.push_str(",\"vec\":");
+        match &self.vec {
+            Some(vec) => {
+                out.push('[');
+                for (i, lane) in vec.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push('[');
+                    for (j, byte) in lane.iter().enumerate() {
+                        if j > 0 {
+                            out.push(',');
+                        }
+                        out.push_str(&byte.to_string());
+                    }
+                    out.push(']');
+                }
+                out.push(']');
+            }
+            None => out.push_str("null"),
+        }
+        out.push_str(",\"csr\":{");
+        let mut addrs: Vec<_> = self.csr.keys().copied().collect();
+        addrs.sort_unstable();
+        for (i, addr) in addrs.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str(&format!("\"{}\":{}", addr, self.csr[addr]));
+        }
+        out.push_str("}}");
+        out
+    }
+
+    /// Parse a snapshot previously produced by [`Self::to_json`].
+    ///
+    /// Accepts any key order and insignificant whitespace; rejects
+    /// wrong array lengths or out-of-range values with a short
+    /// description of the offending position.
+    #[allow(dead_code)]
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let mut cur = JsonCursor::new(text);
+        let mut int = None;
+        let mut fp = None;
+        let mut vec = None;
+        let mut csr = None;
+
+        cur.expect(b'{')?;
+        loop {
+            let key = cur.string()?;
+            cur.expect(b':')?;
+            match key.as_str() {
+                "int" => int = Some(cur.u32_array::<32>()?),
+                "fp" => fp = Some(if cur.eat_null() { None } else { Some(cur.u32_array::<32>()?) }),
+                "vec" => {
+                    vec = Some(if cur.eat_null() {
+                        None
+                    } else {
+                        let mut lanes = [[0u8; 16]; 32];
+                        cur.expect(b'[')?;
+                        for (i, lane) in lanes.iter_mut().enumerate() {
+                            if i > 0 {
+                                cur.expect(b',')?;
+                            }
+                            let bytes = cur.u32_array::<16>()?;
+                            for (dst, src) in lane.iter_mut().zip(bytes) {
+                                *dst = u8::try_from(src)
+                                    .map_err(|_| format!("vec byte {} out of range", src))?;
+                            }
+                        }
+                        cur.expect(b']')?;
+                        Some(lanes)
+                    })
+                }
+                "csr" => {
+                    let mut map = HashMap::new();
+                    cur.expect(b'{')?;
+                    while !cur.eat(b'}') {
+                        if !map.is_empty() {
+                            cur.expect(b',')?;
+                        }
+                        let addr: u16 = cur
+                            .string()?
+                            .parse()
+                            .map_err(|_| "invalid CSR address key".to_string())?;
+                        cur.expect(b':')?;
+                        map.insert(addr, cur.u32()?);
+                    }
+                    csr = Some(map);
+                }
+                other => return Err(format!("unknown snapshot field '{}'", other)),
+            }
+            if !cur.eat(b',') {
+                break;
+            }
+        }
+        cur.expect(b'}')?;
+
+        Ok(Self {
+            int: int.ok_or("missing field 'int'")?,
+            fp: fp.ok_or("missing field 'fp'")?,
+            vec: vec.ok_or("missing field 'vec'")?,
+            csr: csr.ok_or("missing field 'csr'")?,
+        })
+    }
+
+    /// List every architectural difference against `other`, with
+    /// `self` as the "old" side. Entries are sorted by register index
+    /// / CSR address; the result is empty iff the snapshots are equal.
+    #[allow(dead_code)]
+    pub fn diff(&self, other: &StatusSnapshot) -> SnapshotDiff {
+        let mut diff = SnapshotDiff::default();
+
+        for i in 0..32u8 {
+            let (old, new) = (self.int[i as usize], other.int[i as usize]);
+            if old != new {
+                diff.int.push((i, old, new));
+            }
+        }
+
+        for i in 0..32u8 {
+            let old = self.fp.map(|fp| fp[i as usize]);
+            let new = other.fp.map(|fp| fp[i as usize]);
+            if old != new {
+                diff.fp.push((i, old, new));
+            }
+        }
+
+        for i in 0..32u8 {
+            let old = self.vec.map(|v| v[i as usize]);
+            let new = other.vec.map(|v| v[i as usize]);
+            if old != new {
+                diff.vec.push((i, old, new));
+            }
+        }
+
+        let mut addrs: Vec<u16> = self.csr.keys().chain(other.csr.keys()).copied().collect();
+        addrs.sort_unstable();
+        addrs.dedup();
+        for addr in addrs {
+            let (old, new) = (self.csr.get(&addr).copied(), other.csr.get(&addr).copied());
+            if old != new {
+                diff.csr.push((addr, old, new));
+            }
+        }
+
+        diff
+    }
+}
+
+/// Architectural difference between two snapshots (see
+/// [`StatusSnapshot::diff`]).
+///
+/// Entries hold `(index_or_addr, old, new)`. An `Option` side is
+/// `None` when the register file or CSR only exists in one snapshot
+/// (e.g. comparing across configurations with different extensions).
+/// The `Display` impl renders one line per difference for test
+/// failure output and co-simulation mismatch reports.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[allow(dead_code)]
+pub struct SnapshotDiff {
+    /// Changed integer registers.
+    pub int: Vec<(u8, u32, u32)>,
+    /// Changed FP registers.
+    pub fp: Vec<(u8, Option<u32>, Option<u32>)>,
+    /// Changed vector registers.
+    pub vec: Vec<(u8, Option<[u8; 16]>, Option<[u8; 16]>)>,
+    /// Changed CSRs.
+    pub csr: Vec<(u16, Option<u32>, Option<u32>)>,
+}
+
+impl SnapshotDiff {
+    /// True when the compared snapshots were architecturally equal.
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.int.is_empty() && self.fp.is_empty() && self.vec.is_empty() && self.csr.is_empty()
+    }
+
+    /// Total number of differing entries across all state classes.
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.int.len() + self.fp.len() + self.vec.len() + self.csr.len()
+    }
+}
+
+impl std::fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn word(v: Option<u32>) -> String {
+            v.map_or_else(|| "<absent>".to_string(), |v| format!("0x{:08x}", v))
+        }
+        fn lanes(v: Option<[u8; 16]>) -> String {
+            v.map_or_else(
+                || "<absent>".to_string(),
+                |lane| lane.iter().map(|b| format!("{:02x}", b)).collect(),
+            )
+        }
+
+        for &(reg, old, new) in &self.int {
+            writeln!(
+                f,
+                "x{} ({}): 0x{:08x} -> 0x{:08x}",
+                reg,
+                crate::isa::abi_reg_name(reg),
+                old,
+                new
+            )?;
+        }
+        for &(reg, old, new) in &self.fp {
+            writeln!(f, "f{}: {} -> {}", reg, word(old), word(new))?;
+        }
+        for &(reg, old, new) in &self.vec {
+            writeln!(f, "v{}: {} -> {}", reg, lanes(old), lanes(new))?;
+        }
+        for &(addr, old, new) in &self.csr {
+            writeln!(f, "csr 0x{:03x}: {} -> {}", addr, word(old), word(new))?;
+        }
+        Ok(())
+    }
+}
+
+/// Minimal cursor over the fixed JSON shape emitted by
+/// [`StatusSnapshot::to_json`] (numbers, plain-ASCII string keys,
+/// `null`, arrays, objects — no escapes or floats needed).
+struct JsonCursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> JsonCursor<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume `ch` if it is the next non-whitespace byte.
+    fn eat(&mut self, ch: u8) -> bool {
+        self.skip_ws();
+        if self.bytes.get(self.pos) == Some(&ch) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, ch: u8) -> Result<(), String> {
+        if self.eat(ch) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at byte {}", ch as char, self.pos))
+        }
+    }
+
+    fn eat_null(&mut self) -> bool {
+        self.skip_ws();
+        if self.bytes[self.pos..].starts_with(b"null") {
+            self.pos += 4;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        while let Some(&b) = self.bytes.get(self.pos) {
+            if b == b'"' {
+                let s = std::str::from_utf8(&self.bytes[start..self.pos])
+                    .map_err(|_| "invalid UTF-8 in string".to_string())?;
+                self.pos += 1;
+                return Ok(s.to_string());
+            }
+            self.pos += 1;
+        }
+        Err("unterminated string".to_string())
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.skip_ws();
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(format!("expected number at byte {}", start));
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .unwrap()
+            .parse()
+            .map_err(|_| format!("number out of range at byte {}", start))
+    }
+
+    fn u32_array<const N: usize>(&mut self) -> Result<[u32; N], String> {
+        let mut values = [0u32; N];
+        self.expect(b'[')?;
+        for (i, slot) in values.iter_mut().enumerate() {
+            if i > 0 {
+                self.expect(b',')?;
+            }
+            *slot = self.u32()?;
+        }
+        self.expect(b']')?;
+        Ok(values)
+    }
+}
@@ -180,6 +180,24 @@ impl TrapCause {
     }
 }
 
+// ========== 中断位常量 ==========
+
+/// mie/mip 寄存器的中断位（两个寄存器位布局相同）
+pub mod interrupt {
+    // 位位置
+    pub const SSIP: u32 = 1;     // Supervisor Software Interrupt
+    pub const MSIP: u32 = 3;     // Machine Software Interrupt
+    pub const STIP: u32 = 5;     // Supervisor Timer Interrupt
+    pub const MTIP: u32 = 7;     // Machine Timer Interrupt
+    pub const SEIP: u32 = 9;     // Supervisor External Interrupt
+    pub const MEIP: u32 = 11;    // Machine External Interrupt
+
+    // 位掩码
+    pub const MSIP_MASK: u32 = 1 << MSIP;
+    pub const MTIP_MASK: u32 = 1 << MTIP;
+    pub const MEIP_MASK: u32 = 1 << MEIP;
+}
+
 // ========== mstatus 字段位置常量 ==========
 
 /// mstatus 寄存器字段
@@ -210,6 +228,43 @@ pub mod mstatus {
     pub const SIE_MASK: u32 = 1 << SIE;
     pub const SPIE_MASK: u32 = 1 << SPIE;
     pub const SPP_MASK: u32 = 1 << SPP;
+    pub const FS_MASK: u32 = 0x3 << FS;
+    pub const SD_MASK: u32 = 1 << SD;
+
+    // FS 字段编码（特权规范 3.1.6.6）
+    pub const FS_OFF: u32 = 0;
+    pub const FS_INITIAL: u32 = 1;
+    #[allow(dead_code)]
+    pub const FS_CLEAN: u32 = 2;
+    pub const FS_DIRTY: u32 = 3;
+
+    /// 可写字段掩码（WARL）：UIE/UPIE（N 扩展已废除）与只读的
+    /// XS/SD 不可写，其余实现的字段按原值保留
+    pub const WRITABLE_MASK: u32 = SIE_MASK
+        | MIE_MASK
+        | SPIE_MASK
+        | MPIE_MASK
+        | SPP_MASK
+        | MPP_MASK
+        | (0x3 << FS)
+        | (1 << MPRV)
+        | (1 << SUM)
+        | (1 << MXR)
+        | (1 << TVM)
+        | (1 << TW)
+        | (1 << TSR);
+
+    /// 从 mstatus 值读取 FS 字段
+    #[inline]
+    pub fn read_fs(mstatus: u32) -> u32 {
+        (mstatus >> FS) & 0x3
+    }
+
+    /// 向 mstatus 值写入 FS 字段
+    #[inline]
+    pub fn write_fs(mstatus: u32, fs: u32) -> u32 {
+        (mstatus & !FS_MASK) | ((fs & 0x3) << FS)
+    }
 
     /// 从 mstatus 值读取 MPP 字段
     #[inline]
@@ -281,6 +336,131 @@ pub fn calculate_trap_pc(tvec: u32, cause: &TrapCause) -> u32 {
     }
 }
 
+// ========== 类型化 CSR 视图 ==========
+
+/// mstatus 寄存器的类型化视图
+///
+/// 包装原始 32-bit 值并提供字段访问方法，避免在宿主侧代码和测试中
+/// 散落魔法掩码。`with_*` 方法返回新值，便于链式修改。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Mstatus(pub u32);
+
+impl Mstatus {
+    /// 获取原始值
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// MIE: 机器模式中断使能
+    pub fn mie(self) -> bool {
+        (self.0 & mstatus::MIE_MASK) != 0
+    }
+
+    /// MPIE: trap 前的 MIE
+    pub fn mpie(self) -> bool {
+        (self.0 & mstatus::MPIE_MASK) != 0
+    }
+
+    /// MPP: trap 前的特权级
+    pub fn mpp(self) -> PrivilegeMode {
+        PrivilegeMode::from_bits(mstatus::read_mpp(self.0))
+    }
+
+    /// SIE: 监管者模式中断使能
+    pub fn sie(self) -> bool {
+        (self.0 & mstatus::SIE_MASK) != 0
+    }
+
+    /// SPIE: trap 前的 SIE
+    pub fn spie(self) -> bool {
+        (self.0 & mstatus::SPIE_MASK) != 0
+    }
+
+    /// SPP: trap 前的特权级（0=U, 1=S）
+    pub fn spp(self) -> PrivilegeMode {
+        if (self.0 & mstatus::SPP_MASK) != 0 {
+            PrivilegeMode::Supervisor
+        } else {
+            PrivilegeMode::User
+        }
+    }
+
+    /// 返回修改 MIE 后的新值
+    pub fn with_mie(self, mie: bool) -> Self {
+        if mie {
+            Self(self.0 | mstatus::MIE_MASK)
+        } else {
+            Self(self.0 & !mstatus::MIE_MASK)
+        }
+    }
+
+    /// 返回修改 MPIE 后的新值
+    pub fn with_mpie(self, mpie: bool) -> Self {
+        if mpie {
+            Self(self.0 | mstatus::MPIE_MASK)
+        } else {
+            Self(self.0 & !mstatus::MPIE_MASK)
+        }
+    }
+
+    /// 返回修改 MPP 后的新值
+    pub fn with_mpp(self, mode: PrivilegeMode) -> Self {
+        Self(mstatus::write_mpp(self.0, mode.to_bits()))
+    }
+}
+
+/// mtvec 寄存器的类型化视图
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Mtvec(pub u32);
+
+impl Mtvec {
+    /// 从 base 和 mode 构造
+    pub fn new(base: u32, mode: TvecMode) -> Self {
+        Self((base & !0x3) | mode as u32)
+    }
+
+    /// 获取原始值
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// trap handler 基地址（4 字节对齐）
+    pub fn base(self) -> u32 {
+        self.0 & !0x3
+    }
+
+    /// trap 模式（Direct/Vectored）
+    pub fn mode(self) -> TvecMode {
+        TvecMode::from_bits(self.0)
+    }
+
+    /// 计算指定 trap 原因对应的 handler 地址
+    pub fn trap_pc(self, cause: &TrapCause) -> u32 {
+        calculate_trap_pc(self.0, cause)
+    }
+}
+
+/// mcause 寄存器的类型化视图
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Mcause(pub u32);
+
+impl Mcause {
+    /// 获取原始值
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// 是否为中断（最高位）
+    pub fn is_interrupt(self) -> bool {
+        (self.0 >> 31) != 0
+    }
+
+    /// 原因码（低 31 位）
+    pub fn code(self) -> u32 {
+        self.0 & 0x7FFF_FFFF
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -340,6 +520,39 @@ mod tests {
         assert_eq!(mode, TvecMode::Vectored);
     }
 
+    #[test]
+    fn test_mstatus_view() {
+        let m = Mstatus(0x00001888); // MPP=3, MPIE=1, MIE=1
+        assert!(m.mie());
+        assert!(m.mpie());
+        assert_eq!(m.mpp(), PrivilegeMode::Machine);
+
+        let m2 = m.with_mie(false).with_mpp(PrivilegeMode::User);
+        assert!(!m2.mie());
+        assert_eq!(m2.mpp(), PrivilegeMode::User);
+        assert!(m2.mpie(), "未修改的字段应保持");
+    }
+
+    #[test]
+    fn test_mtvec_view() {
+        let t = Mtvec::new(0x8000_0000, TvecMode::Vectored);
+        assert_eq!(t.base(), 0x8000_0000);
+        assert_eq!(t.mode(), TvecMode::Vectored);
+        assert_eq!(t.trap_pc(&TrapCause::MachineTimerInterrupt), 0x8000_001C);
+        assert_eq!(t.trap_pc(&TrapCause::IllegalInstruction), 0x8000_0000);
+    }
+
+    #[test]
+    fn test_mcause_view() {
+        let c = Mcause(TrapCause::MachineTimerInterrupt.to_cause_value());
+        assert!(c.is_interrupt());
+        assert_eq!(c.code(), 7);
+
+        let c = Mcause(TrapCause::IllegalInstruction.to_cause_value());
+        assert!(!c.is_interrupt());
+        assert_eq!(c.code(), 2);
+    }
+
     #[test]
     fn test_trap_pc_calculation() {
         // Direct mode
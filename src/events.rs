@@ -0,0 +1,133 @@
+//! 仿真事件总线
+//!
+//! 组件（CPU、内存总线、设备）把类型化事件发布到总线，订阅者
+//! （跟踪、统计、调试器、GUI 前端）以观察者身份接收，无需在各
+//! 组件上逐个开凿钩子。总线是单线程的：发布即同步回调全部
+//! 订阅者，按订阅顺序依次调用。
+//!
+//! 发布方各持一份 [`SharedEventBus`]（`Rc<RefCell<EventBus>>`），
+//! 通过 [`crate::sim_env::SimEnv::subscribe_events`] 一次接线。
+//! 没有订阅者时各发布点只付一次 `Option` 检查的代价。
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::cpu::TrapCause;
+
+/// 类型化的仿真事件
+///
+/// 新的事件类别会随功能增加，下游请为未知变体保留分支。
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimEvent {
+    /// CPU 进入 trap（异常或中断），已转向处理程序
+    TrapTaken {
+        /// 异常 PC（写入 mepc 的值）
+        pc: u32,
+        /// trap 原因
+        cause: TrapCause,
+        /// 附加信息（mtval：错误地址、非法指令编码等）
+        tval: u32,
+    },
+    /// 异步中断被挂起（mip 置位；是否注入还要看使能位）
+    InterruptRaised {
+        /// 中断原因
+        cause: TrapCause,
+    },
+    /// CSR 被写入（客体指令或宿主 API）
+    CsrWritten {
+        /// 写入发生时的指令 PC
+        pc: u32,
+        /// CSR 地址
+        addr: u16,
+        /// 写入前的值
+        old: u32,
+        /// 写入后的值（WARL 约束生效后）
+        new: u32,
+    },
+    /// 设备窗口内的 MMIO 访问
+    MmioAccess {
+        /// 访问的绝对地址
+        addr: u32,
+        /// 访问宽度（1、2 或 4 字节）
+        width: u32,
+        /// 写访问时为写入值，读访问为 None
+        write: Option<u32>,
+    },
+}
+
+/// 事件订阅者
+///
+/// 闭包 `FnMut(&SimEvent)` 自动实现本 trait，简单场景无需
+/// 定义类型。
+pub trait EventObserver {
+    /// 收到一条事件（发布方同步调用）
+    fn on_event(&mut self, event: &SimEvent);
+}
+
+impl<F: FnMut(&SimEvent)> EventObserver for F {
+    fn on_event(&mut self, event: &SimEvent) {
+        self(event)
+    }
+}
+
+/// 事件总线：登记订阅者、向全部订阅者同步分发
+#[derive(Default)]
+pub struct EventBus {
+    observers: Vec<Box<dyn EventObserver>>,
+}
+
+impl EventBus {
+    /// 创建空总线
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一个订阅者（此后所有事件都会送达）
+    pub fn subscribe(&mut self, observer: Box<dyn EventObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// 是否有订阅者（发布方据此跳过事件构造）
+    pub fn has_observers(&self) -> bool {
+        !self.observers.is_empty()
+    }
+
+    /// 向全部订阅者分发一条事件
+    pub fn publish(&mut self, event: &SimEvent) {
+        for observer in &mut self.observers {
+            observer.on_event(event);
+        }
+    }
+}
+
+/// 总线的共享句柄：各发布方持有克隆（单线程，内部可变）
+pub type SharedEventBus = Rc<RefCell<EventBus>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bus_dispatches_in_subscription_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = EventBus::new();
+        assert!(!bus.has_observers());
+
+        let first = Rc::clone(&log);
+        bus.subscribe(Box::new(move |e: &SimEvent| {
+            first.borrow_mut().push((1u8, *e));
+        }));
+        let second = Rc::clone(&log);
+        bus.subscribe(Box::new(move |e: &SimEvent| {
+            second.borrow_mut().push((2u8, *e));
+        }));
+        assert!(bus.has_observers());
+
+        let event = SimEvent::InterruptRaised {
+            cause: TrapCause::MachineTimerInterrupt,
+        };
+        bus.publish(&event);
+        assert_eq!(log.borrow().as_slice(), &[(1, event), (2, event)]);
+    }
+}
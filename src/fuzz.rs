@@ -0,0 +1,218 @@
+//! 确定性指令流模糊测试
+//!
+//! 解码器与执行单元分离后，"解码出的指令执行侧没接"这类静默
+//! 穿透缺陷很容易引入。本模块生成随机的合法/非法 32 位指令字，
+//! 逐条喂给 [`crate::cpu::CpuCore`] 并检查架构不变量：
+//!
+//! - x0 恒为零
+//! - PC 对齐契约：非对齐的跳转目标在下一次取指以
+//!   InstructionAddressMisaligned 收场，trap 后 PC 重新对齐
+//! - 单步不 panic
+//! - 发生 trap 时 mcause/mepc 成形（mcause 与原因码一致，
+//!   mepc 指向肇事指令）
+//!
+//! 随机流基于 splitmix64（与 [`crate::devices::EntropySource`]
+//! 同源）：相同种子产生相同的指令序列，违例报告携带种子与
+//! 迭代号，可直接复现。
+
+use crate::cpu::{csr_def, CpuBuilder, TrapCause};
+use crate::isa::{RV32I_INSTRS, RV32I_OPCODES};
+use crate::memory::{FlatMemory, Memory};
+
+/// 指令流模糊测试器
+///
+/// 持有种子与当前随机流状态；[`InstrFuzzer::run`] 每次迭代用
+/// 新建的 CPU 执行一个随机指令字，避免前一条指令的状态污染
+/// 掩盖违例。
+pub struct InstrFuzzer {
+    seed: u64,
+    state: u64,
+}
+
+/// 一轮模糊测试的结果
+#[derive(Debug, Default)]
+pub struct FuzzReport {
+    /// 执行的指令字总数
+    pub executed: u64,
+    /// 其中触发 trap 的条数（非法编码、ECALL 等）
+    pub trapped: u64,
+    /// 不变量违例描述（含种子、迭代号和指令字，便于复现）
+    pub violations: Vec<String>,
+}
+
+impl FuzzReport {
+    /// 是否没有发现任何违例
+    pub fn ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl InstrFuzzer {
+    /// 创建以 `seed` 初始化的模糊测试器
+    pub fn new(seed: u64) -> Self {
+        InstrFuzzer { seed, state: seed }
+    }
+
+    /// 初始化种子
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 随机流的下一个 64 位值（splitmix64）
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// 生成下一个指令字
+    ///
+    /// 三种策略轮流出现，保证合法与非法编码都有覆盖：
+    /// 完全随机的字、随机字嵌入合法 RV32I opcode、从指令表取
+    /// 定义后随机化其可变位（寄存器号、立即数）。
+    pub fn next_word(&mut self) -> u32 {
+        let r = self.next_u64();
+        let raw = (r >> 32) as u32;
+        match r % 3 {
+            0 => raw,
+            1 => {
+                let opcode = RV32I_OPCODES[raw as usize % RV32I_OPCODES.len()];
+                (raw & !0x7F) | opcode
+            }
+            _ => {
+                let def = &RV32I_INSTRS[raw as usize % RV32I_INSTRS.len()];
+                def.match_val | (raw & !def.mask)
+            }
+        }
+    }
+
+    /// 执行 `iterations` 个随机指令字并检查不变量
+    pub fn run(&mut self, iterations: u64) -> FuzzReport {
+        let mut report = FuzzReport::default();
+        for iteration in 0..iterations {
+            let word = self.next_word();
+            self.run_one(iteration, word, &mut report);
+        }
+        report
+    }
+
+    /// 在新建的 CPU 上执行单个指令字并检查不变量
+    fn run_one(&mut self, iteration: u64, word: u32, report: &mut FuzzReport) {
+        let mut cpu = CpuBuilder::new(0)
+            .with_m_extension()
+            .with_a_extension()
+            .with_zicsr_extension()
+            .with_priv_extension()
+            .build()
+            .expect("模糊测试用的 CPU 配置无冲突");
+        let mut mem = FlatMemory::new(4096, 0);
+
+        // 寄存器预置随机值，让数据通路上的缺陷更容易显形
+        for reg in 1..32 {
+            cpu.write_reg(reg, (self.next_u64() >> 16) as u32);
+        }
+        mem.store32(0, word).expect("指令字写入内存");
+
+        let stepped = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            cpu.step(&mut mem);
+        }));
+        let mut violate = |what: &str| {
+            report.violations.push(format!(
+                "seed=0x{:x} iteration={} word=0x{:08x}: {}",
+                self.seed, iteration, word, what
+            ));
+        };
+
+        if stepped.is_err() {
+            violate("step panic");
+            return;
+        }
+        report.executed += 1;
+
+        if cpu.read_reg(0) != 0 {
+            violate("x0 被写成非零");
+        }
+
+        // 跳转允许瞬时落在非对齐目标；契约是下一次取指必须以
+        // InstructionAddressMisaligned 收场并回到对齐的处理程序
+        let mut trap_pc = 0;
+        if !cpu.pc().is_multiple_of(4) {
+            trap_pc = cpu.pc();
+            let refetched = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                cpu.step(&mut mem);
+            }));
+            if refetched.is_err() {
+                violate("非对齐目标的取指 panic");
+                return;
+            }
+            if cpu.last_trap() != Some(TrapCause::InstructionAddressMisaligned) {
+                violate("非对齐目标的取指未触发 InstructionAddressMisaligned");
+            }
+            if !cpu.pc().is_multiple_of(4) {
+                violate("trap 之后 PC 仍未对齐");
+            }
+        }
+
+        if let Some(cause) = cpu.last_trap() {
+            report.trapped += 1;
+            let expected = if cause.is_interrupt() {
+                cause.code() | 0x8000_0000
+            } else {
+                cause.code()
+            };
+            if cpu.csr_read(csr_def::CSR_MCAUSE) != expected {
+                violate("mcause 与 trap 原因码不一致");
+            }
+            if cpu.csr_read(csr_def::CSR_MEPC) != trap_pc {
+                violate("mepc 未指向肇事指令");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isa::{InstrDecoder, RvInstr, RV32I_DECODER};
+
+    #[test]
+    fn test_same_seed_same_stream() {
+        let mut a = InstrFuzzer::new(0xDEAD_BEEF);
+        let mut b = InstrFuzzer::new(0xDEAD_BEEF);
+        let words_a: Vec<u32> = (0..64).map(|_| a.next_word()).collect();
+        let words_b: Vec<u32> = (0..64).map(|_| b.next_word()).collect();
+        assert_eq!(words_a, words_b, "相同种子应产生相同指令序列");
+
+        let mut c = InstrFuzzer::new(1);
+        let words_c: Vec<u32> = (0..64).map(|_| c.next_word()).collect();
+        assert_ne!(words_a, words_c, "不同种子应产生不同指令序列");
+    }
+
+    #[test]
+    fn test_strategies_cover_legal_and_illegal() {
+        let mut fuzzer = InstrFuzzer::new(42);
+        let mut legal = 0;
+        let mut illegal = 0;
+        for _ in 0..512 {
+            let word = fuzzer.next_word();
+            match RV32I_DECODER.decode(word) {
+                Some(d) if !matches!(d.instr, RvInstr::Illegal { .. }) => legal += 1,
+                _ => illegal += 1,
+            }
+        }
+        assert!(legal > 0, "指令流应包含合法编码");
+        assert!(illegal > 0, "指令流应包含非法编码");
+    }
+
+    #[test]
+    fn test_invariants_hold_over_random_stream() {
+        let mut fuzzer = InstrFuzzer::new(2026);
+        let report = fuzzer.run(2000);
+        assert!(report.ok(), "不变量违例：{:?}", report.violations);
+        assert_eq!(report.executed, 2000);
+        assert!(report.trapped > 0, "非法编码应触发 trap");
+        assert!(report.trapped < report.executed, "合法编码应正常退休");
+    }
+}
@@ -0,0 +1,548 @@
+//! Warp/SIMT 执行原型
+//!
+//! 本 crate 的长期目标是演化到 GPGPU，这里提供第一块实验性表面：
+//! 一个 [`Warp`] 把同一条解码后的指令在 K 条 lane 上同时执行，
+//! 每条 lane 有独立的整数寄存器堆，PC 由整个 warp 共享。
+//!
+//! 分支分歧用重汇合栈（SIMT stack）处理：分歧时先执行一侧路径，
+//! 另一侧（PC 和 lane 掩码）压栈，重汇合点取立即后支配点的简化
+//! 近似——前向分支取跳转目标、后向分支取顺序下一条，即两个路径
+//! PC 的较大者。执行路径到达重汇合点时切换到挂起路径，两侧都到达
+//! 后恢复合并掩码继续。
+//!
+//! 支持 RV32I 整数子集（算术/逻辑/访存/分支/跳转）；CSR、特权
+//! 指令等标量概念不在 warp 语义内，遇到时报告 Unsupported。
+
+use crate::cpu::RegFile;
+use crate::isa::RvInstr;
+use crate::memory::{MemError, Memory};
+
+/// lane 活跃掩码（bit i 对应 lane i），上限 32 条 lane
+pub type LaneMask = u32;
+
+/// warp 支持的最大 lane 数（掩码位宽决定）
+pub const MAX_LANES: usize = 32;
+
+/// 重汇合栈帧：一次分歧中"另一侧"路径与重汇合信息
+#[derive(Debug, Clone, Copy)]
+struct ReconvFrame {
+    /// 重汇合点 PC（立即后支配点的近似）
+    reconv_pc: u32,
+    /// 挂起路径的入口 PC
+    pending_pc: u32,
+    /// 挂起路径的 lane 掩码，切换执行后清零
+    pending_mask: LaneMask,
+    /// 分歧前的活跃掩码，两侧都到达重汇合点后恢复
+    merged_mask: LaneMask,
+}
+
+/// 一条指令在 warp 上执行的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarpExec {
+    /// 正常执行完成
+    Executed,
+    /// 指令不在 warp 支持的子集内，未执行
+    Unsupported,
+    /// 某条 lane 的访存出错（lane 号与错误）
+    MemFault { lane: usize, err: MemError },
+}
+
+/// 一组以锁步方式执行同一指令流的 lane
+pub struct Warp {
+    pc: u32,
+    lanes: Vec<RegFile>,
+    active: LaneMask,
+    reconv_stack: Vec<ReconvFrame>,
+}
+
+impl Warp {
+    /// 创建 warp：`num_lanes` 条 lane（1..=32），全部活跃，共享入口 PC
+    pub fn new(num_lanes: usize, entry_pc: u32) -> Self {
+        assert!(
+            (1..=MAX_LANES).contains(&num_lanes),
+            "num_lanes must be in 1..={}",
+            MAX_LANES
+        );
+        Self {
+            pc: entry_pc,
+            lanes: (0..num_lanes).map(|_| RegFile::new()).collect(),
+            active: full_mask(num_lanes),
+            reconv_stack: Vec::new(),
+        }
+    }
+
+    /// lane 数
+    pub fn num_lanes(&self) -> usize {
+        self.lanes.len()
+    }
+
+    /// warp 共享 PC
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    /// 设置 warp PC（清空重汇合栈，视为新的统一入口）
+    pub fn set_pc(&mut self, pc: u32) {
+        self.pc = pc;
+        self.active = full_mask(self.lanes.len());
+        self.reconv_stack.clear();
+    }
+
+    /// 当前活跃 lane 掩码
+    pub fn active_mask(&self) -> LaneMask {
+        self.active
+    }
+
+    /// 是否存在尚未重汇合的分歧
+    pub fn is_diverged(&self) -> bool {
+        !self.reconv_stack.is_empty()
+    }
+
+    /// 读 lane 的整数寄存器
+    pub fn lane_reg(&self, lane: usize, reg: u8) -> u32 {
+        self.lanes[lane].read(reg)
+    }
+
+    /// 写 lane 的整数寄存器（x0 仍然硬连线为 0）
+    pub fn set_lane_reg(&mut self, lane: usize, reg: u8, value: u32) {
+        self.lanes[lane].write(reg, value);
+    }
+
+    /// 取指前同步：当前 PC 到达栈顶重汇合点时切换/合并路径
+    ///
+    /// 调用方在每次取指前调用（[`Warp::execute`] 的约定），可能连续
+    /// 弹出多层嵌套分歧
+    pub fn reconverge(&mut self) {
+        while let Some(top) = self.reconv_stack.last_mut() {
+            if self.pc != top.reconv_pc {
+                return;
+            }
+            if top.pending_mask != 0 {
+                // 第一条路径到达重汇合点：切到挂起路径。挂起路径的
+                // 入口可能就是重汇合点（空的 then 块），继续循环判定
+                self.pc = top.pending_pc;
+                self.active = top.pending_mask;
+                top.pending_mask = 0;
+                continue;
+            }
+            // 两侧都已到达：恢复合并掩码，继续从重汇合点执行
+            self.active = top.merged_mask;
+            self.reconv_stack.pop();
+        }
+    }
+
+    /// 在所有活跃 lane 上执行一条解码后的指令
+    ///
+    /// 调用方负责在 `pc()` 处取指解码，并在取指前调用
+    /// [`Warp::reconverge`]。执行后 PC 按顺序/分支语义推进
+    pub fn execute(&mut self, mem: &mut dyn Memory, instr: RvInstr) -> WarpExec {
+        let current_pc = self.pc;
+        let next_pc = current_pc.wrapping_add(4);
+
+        // 分支：逐 lane 判定，统一则整体跳转，分歧则压栈
+        if let Some(results) = self.branch_taken_mask(instr) {
+            let taken = results & self.active;
+            let fallthrough = self.active & !results;
+            let target = branch_target(current_pc, instr);
+
+            if fallthrough == 0 {
+                self.pc = target;
+            } else if taken == 0 {
+                self.pc = next_pc;
+            } else {
+                // 立即后支配点的近似：两个路径 PC 的较大者
+                // （前向分支 = 跳转目标，后向循环分支 = 顺序下一条）
+                let reconv_pc = target.max(next_pc);
+                let (first_pc, first_mask, pending_pc, pending_mask) = if target < next_pc {
+                    // 后向分支先执行跳转侧（循环体），挂起顺序侧
+                    (target, taken, next_pc, fallthrough)
+                } else {
+                    (next_pc, fallthrough, target, taken)
+                };
+                self.reconv_stack.push(ReconvFrame {
+                    reconv_pc,
+                    pending_pc,
+                    pending_mask,
+                    merged_mask: self.active,
+                });
+                self.pc = first_pc;
+                self.active = first_mask;
+            }
+            return WarpExec::Executed;
+        }
+
+        // 跳转：warp 共享 PC，所有活跃 lane 统一转移
+        match instr {
+            RvInstr::Jal { rd, offset } => {
+                for lane in 0..self.lanes.len() {
+                    if self.active & (1 << lane) != 0 {
+                        self.lanes[lane].write(rd, next_pc);
+                    }
+                }
+                self.pc = current_pc.wrapping_add(offset as u32);
+                return WarpExec::Executed;
+            }
+            RvInstr::Jalr { rd, rs1, offset } => {
+                // 间接跳转的目标取自 lane 寄存器，原型要求各活跃 lane
+                // 一致（发散的间接跳转不在本模型内），以最低活跃 lane 为准
+                let Some(first) = self.active_lanes().next() else {
+                    self.pc = next_pc;
+                    return WarpExec::Executed;
+                };
+                let target = self.lanes[first].read(rs1).wrapping_add(offset as u32) & !1;
+                for lane in 0..self.lanes.len() {
+                    if self.active & (1 << lane) != 0 {
+                        self.lanes[lane].write(rd, next_pc);
+                    }
+                }
+                self.pc = target;
+                return WarpExec::Executed;
+            }
+            _ => {}
+        }
+
+        // 其余指令逐 lane 执行，PC 顺序推进
+        for lane in 0..self.lanes.len() {
+            if self.active & (1 << lane) == 0 {
+                continue;
+            }
+            match lane_execute(&mut self.lanes[lane], mem, instr, current_pc) {
+                LaneExec::Executed => {}
+                LaneExec::Unsupported => return WarpExec::Unsupported,
+                LaneExec::MemFault(err) => return WarpExec::MemFault { lane, err },
+            }
+        }
+        self.pc = next_pc;
+        WarpExec::Executed
+    }
+
+    /// 活跃 lane 的下标迭代器（低位 lane 在前）
+    fn active_lanes(&self) -> impl Iterator<Item = usize> + '_ {
+        let mask = self.active;
+        (0..self.lanes.len()).filter(move |lane| mask & (1 << lane) != 0)
+    }
+
+    /// 分支指令逐 lane 的 taken 掩码；非分支指令返回 None
+    fn branch_taken_mask(&self, instr: RvInstr) -> Option<LaneMask> {
+        let cond: fn(u32, u32) -> bool = match instr {
+            RvInstr::Beq { .. } => |a, b| a == b,
+            RvInstr::Bne { .. } => |a, b| a != b,
+            RvInstr::Blt { .. } => |a, b| (a as i32) < (b as i32),
+            RvInstr::Bge { .. } => |a, b| (a as i32) >= (b as i32),
+            RvInstr::Bltu { .. } => |a, b| a < b,
+            RvInstr::Bgeu { .. } => |a, b| a >= b,
+            _ => return None,
+        };
+        let (rs1, rs2) = match instr {
+            RvInstr::Beq { rs1, rs2, .. }
+            | RvInstr::Bne { rs1, rs2, .. }
+            | RvInstr::Blt { rs1, rs2, .. }
+            | RvInstr::Bge { rs1, rs2, .. }
+            | RvInstr::Bltu { rs1, rs2, .. }
+            | RvInstr::Bgeu { rs1, rs2, .. } => (rs1, rs2),
+            _ => unreachable!(),
+        };
+        let mut mask = 0;
+        for (lane, regs) in self.lanes.iter().enumerate() {
+            if cond(regs.read(rs1), regs.read(rs2)) {
+                mask |= 1 << lane;
+            }
+        }
+        Some(mask)
+    }
+}
+
+/// `num_lanes` 条 lane 全活跃的掩码
+fn full_mask(num_lanes: usize) -> LaneMask {
+    if num_lanes == MAX_LANES {
+        LaneMask::MAX
+    } else {
+        (1 << num_lanes) - 1
+    }
+}
+
+/// 分支指令的跳转目标
+fn branch_target(pc: u32, instr: RvInstr) -> u32 {
+    let offset = match instr {
+        RvInstr::Beq { offset, .. }
+        | RvInstr::Bne { offset, .. }
+        | RvInstr::Blt { offset, .. }
+        | RvInstr::Bge { offset, .. }
+        | RvInstr::Bltu { offset, .. }
+        | RvInstr::Bgeu { offset, .. } => offset,
+        _ => 0,
+    };
+    pc.wrapping_add(offset as u32)
+}
+
+/// 单 lane 执行结果
+enum LaneExec {
+    Executed,
+    Unsupported,
+    MemFault(MemError),
+}
+
+/// 在单条 lane 的寄存器堆上执行一条非分支/跳转指令
+///
+/// 语义与 `cpu::exu::rv32i` 对应，但 lane 没有 trap 机制，
+/// 访存错误原样上报给 warp
+fn lane_execute(
+    regs: &mut RegFile,
+    mem: &mut dyn Memory,
+    instr: RvInstr,
+    current_pc: u32,
+) -> LaneExec {
+    match instr {
+        // R-type 算术/逻辑
+        RvInstr::Add { rd, rs1, rs2 } => {
+            regs.write(rd, regs.read(rs1).wrapping_add(regs.read(rs2)));
+        }
+        RvInstr::Sub { rd, rs1, rs2 } => {
+            regs.write(rd, regs.read(rs1).wrapping_sub(regs.read(rs2)));
+        }
+        RvInstr::And { rd, rs1, rs2 } => {
+            regs.write(rd, regs.read(rs1) & regs.read(rs2));
+        }
+        RvInstr::Or { rd, rs1, rs2 } => {
+            regs.write(rd, regs.read(rs1) | regs.read(rs2));
+        }
+        RvInstr::Xor { rd, rs1, rs2 } => {
+            regs.write(rd, regs.read(rs1) ^ regs.read(rs2));
+        }
+        RvInstr::Slt { rd, rs1, rs2 } => {
+            let result = ((regs.read(rs1) as i32) < (regs.read(rs2) as i32)) as u32;
+            regs.write(rd, result);
+        }
+        RvInstr::Sltu { rd, rs1, rs2 } => {
+            regs.write(rd, (regs.read(rs1) < regs.read(rs2)) as u32);
+        }
+        RvInstr::Sll { rd, rs1, rs2 } => {
+            regs.write(rd, regs.read(rs1) << (regs.read(rs2) & 0x1F));
+        }
+        RvInstr::Srl { rd, rs1, rs2 } => {
+            regs.write(rd, regs.read(rs1) >> (regs.read(rs2) & 0x1F));
+        }
+        RvInstr::Sra { rd, rs1, rs2 } => {
+            regs.write(rd, ((regs.read(rs1) as i32) >> (regs.read(rs2) & 0x1F)) as u32);
+        }
+
+        // I-type 立即数
+        RvInstr::Addi { rd, rs1, imm } => {
+            regs.write(rd, regs.read(rs1).wrapping_add(imm as u32));
+        }
+        RvInstr::Andi { rd, rs1, imm } => {
+            regs.write(rd, regs.read(rs1) & (imm as u32));
+        }
+        RvInstr::Ori { rd, rs1, imm } => {
+            regs.write(rd, regs.read(rs1) | (imm as u32));
+        }
+        RvInstr::Xori { rd, rs1, imm } => {
+            regs.write(rd, regs.read(rs1) ^ (imm as u32));
+        }
+        RvInstr::Slti { rd, rs1, imm } => {
+            regs.write(rd, ((regs.read(rs1) as i32) < imm) as u32);
+        }
+        RvInstr::Sltiu { rd, rs1, imm } => {
+            regs.write(rd, (regs.read(rs1) < (imm as u32)) as u32);
+        }
+        RvInstr::Slli { rd, rs1, shamt } => {
+            regs.write(rd, regs.read(rs1) << shamt);
+        }
+        RvInstr::Srli { rd, rs1, shamt } => {
+            regs.write(rd, regs.read(rs1) >> shamt);
+        }
+        RvInstr::Srai { rd, rs1, shamt } => {
+            regs.write(rd, ((regs.read(rs1) as i32) >> shamt) as u32);
+        }
+
+        // U-type 与 PC 相关
+        RvInstr::Lui { rd, imm } => {
+            regs.write(rd, imm as u32);
+        }
+        RvInstr::Auipc { rd, imm } => {
+            regs.write(rd, current_pc.wrapping_add(imm as u32));
+        }
+
+        // 访存：每条 lane 独立计算地址
+        RvInstr::Lb { rd, rs1, offset } => {
+            let addr = regs.read(rs1).wrapping_add(offset as u32);
+            match mem.load8(addr) {
+                Ok(v) => regs.write(rd, v as i8 as i32 as u32),
+                Err(err) => return LaneExec::MemFault(err),
+            }
+        }
+        RvInstr::Lbu { rd, rs1, offset } => {
+            let addr = regs.read(rs1).wrapping_add(offset as u32);
+            match mem.load8(addr) {
+                Ok(v) => regs.write(rd, v as u32),
+                Err(err) => return LaneExec::MemFault(err),
+            }
+        }
+        RvInstr::Lh { rd, rs1, offset } => {
+            let addr = regs.read(rs1).wrapping_add(offset as u32);
+            match mem.load16(addr) {
+                Ok(v) => regs.write(rd, v as i16 as i32 as u32),
+                Err(err) => return LaneExec::MemFault(err),
+            }
+        }
+        RvInstr::Lhu { rd, rs1, offset } => {
+            let addr = regs.read(rs1).wrapping_add(offset as u32);
+            match mem.load16(addr) {
+                Ok(v) => regs.write(rd, v as u32),
+                Err(err) => return LaneExec::MemFault(err),
+            }
+        }
+        RvInstr::Lw { rd, rs1, offset } => {
+            let addr = regs.read(rs1).wrapping_add(offset as u32);
+            match mem.load32(addr) {
+                Ok(v) => regs.write(rd, v),
+                Err(err) => return LaneExec::MemFault(err),
+            }
+        }
+        RvInstr::Sb { rs1, rs2, offset } => {
+            let addr = regs.read(rs1).wrapping_add(offset as u32);
+            if let Err(err) = mem.store8(addr, regs.read(rs2) as u8) {
+                return LaneExec::MemFault(err);
+            }
+        }
+        RvInstr::Sh { rs1, rs2, offset } => {
+            let addr = regs.read(rs1).wrapping_add(offset as u32);
+            if let Err(err) = mem.store16(addr, regs.read(rs2) as u16) {
+                return LaneExec::MemFault(err);
+            }
+        }
+        RvInstr::Sw { rs1, rs2, offset } => {
+            let addr = regs.read(rs1).wrapping_add(offset as u32);
+            if let Err(err) = mem.store32(addr, regs.read(rs2)) {
+                return LaneExec::MemFault(err);
+            }
+        }
+
+        // 内存序指令在单 warp 模型里是 no-op
+        RvInstr::Fence { .. } | RvInstr::FenceI => {}
+
+        _ => return LaneExec::Unsupported,
+    }
+    LaneExec::Executed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isa::{DecoderRegistry, IsaConfig};
+    use crate::memory::FlatMemory;
+
+    /// 循环：reconverge → 取指 → 解码 → 执行，直到 warp 到达 stop_pc
+    fn run_warp(warp: &mut Warp, mem: &mut FlatMemory, stop_pc: u32, max_steps: usize) {
+        let decoder: DecoderRegistry = IsaConfig::new().build().expect("RV32I should not conflict");
+        for _ in 0..max_steps {
+            warp.reconverge();
+            if warp.pc() == stop_pc && !warp.is_diverged() {
+                return;
+            }
+            let word = mem.load32(warp.pc()).expect("fetch");
+            let decoded = decoder.decode(word);
+            assert_eq!(
+                warp.execute(mem, decoded.instr),
+                WarpExec::Executed,
+                "pc=0x{:x}",
+                warp.pc()
+            );
+        }
+        panic!("warp did not reach 0x{:x} in {} steps", stop_pc, max_steps);
+    }
+
+    #[test]
+    fn test_lanes_execute_in_lockstep() {
+        let mut mem = FlatMemory::new(1024, 0);
+        // addi x2, x1, 10
+        mem.store32(0, 0x00A08113).unwrap();
+
+        let mut warp = Warp::new(4, 0);
+        for lane in 0..4 {
+            warp.set_lane_reg(lane, 1, lane as u32 * 100);
+        }
+        let decoder: DecoderRegistry = IsaConfig::new().build().unwrap();
+        let decoded = decoder.decode(mem.load32(0).unwrap());
+        assert_eq!(warp.execute(&mut mem, decoded.instr), WarpExec::Executed);
+
+        assert_eq!(warp.pc(), 4);
+        for lane in 0..4 {
+            assert_eq!(warp.lane_reg(lane, 2), lane as u32 * 100 + 10);
+        }
+    }
+
+    #[test]
+    fn test_divergent_branch_reconverges() {
+        let mut mem = FlatMemory::new(1024, 0);
+        // 0:  beq x1, x0, +8   # x1 == 0 的 lane 跳到 8
+        // 4:  addi x2, x0, 1   # 仅 x1 != 0 的 lane 执行
+        // 8:  addi x3, x0, 7   # 重汇合点，所有 lane 执行
+        mem.store32(0, 0x00008463).unwrap();
+        mem.store32(4, 0x00100113).unwrap();
+        mem.store32(8, 0x00700193).unwrap();
+
+        let mut warp = Warp::new(4, 0);
+        warp.set_lane_reg(1, 1, 5);
+        warp.set_lane_reg(3, 1, 5);
+
+        run_warp(&mut warp, &mut mem, 12, 16);
+
+        assert_eq!(warp.active_mask(), 0b1111, "重汇合后全部 lane 活跃");
+        // then 路径只在 lane 1/3 上执行
+        assert_eq!(warp.lane_reg(0, 2), 0);
+        assert_eq!(warp.lane_reg(1, 2), 1);
+        assert_eq!(warp.lane_reg(2, 2), 0);
+        assert_eq!(warp.lane_reg(3, 2), 1);
+        // 重汇合点之后所有 lane 都执行
+        for lane in 0..4 {
+            assert_eq!(warp.lane_reg(lane, 3), 7);
+        }
+    }
+
+    #[test]
+    fn test_uniform_branch_no_divergence() {
+        let mut mem = FlatMemory::new(1024, 0);
+        // beq x0, x0, +8：所有 lane 同向，不应压栈
+        mem.store32(0, 0x00000463).unwrap();
+        mem.store32(8, 0x00700193).unwrap();
+
+        let mut warp = Warp::new(2, 0);
+        let decoder: DecoderRegistry = IsaConfig::new().build().unwrap();
+        let decoded = decoder.decode(mem.load32(0).unwrap());
+        assert_eq!(warp.execute(&mut mem, decoded.instr), WarpExec::Executed);
+
+        assert_eq!(warp.pc(), 8);
+        assert!(!warp.is_diverged());
+        assert_eq!(warp.active_mask(), 0b11);
+    }
+
+    #[test]
+    fn test_per_lane_memory_access() {
+        let mut mem = FlatMemory::new(1024, 0);
+        // sw x2, 0(x1)：每条 lane 往自己的地址写自己的值
+        mem.store32(0, 0x0020A023).unwrap();
+
+        let mut warp = Warp::new(4, 0);
+        for lane in 0..4 {
+            warp.set_lane_reg(lane, 1, 0x100 + lane as u32 * 4);
+            warp.set_lane_reg(lane, 2, 0xA0 + lane as u32);
+        }
+        let decoder: DecoderRegistry = IsaConfig::new().build().unwrap();
+        let decoded = decoder.decode(mem.load32(0).unwrap());
+        assert_eq!(warp.execute(&mut mem, decoded.instr), WarpExec::Executed);
+
+        for lane in 0..4 {
+            assert_eq!(mem.load32(0x100 + lane as u32 * 4).unwrap(), 0xA0 + lane as u32);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_instruction_reported() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut warp = Warp::new(2, 0);
+        // CSR 指令是标量概念，warp 不支持
+        let instr = RvInstr::Csrrw { rd: 1, rs1: 2, csr: 0x340 };
+        assert_eq!(warp.execute(&mut mem, instr), WarpExec::Unsupported);
+    }
+}
@@ -0,0 +1,300 @@
+//! 客户机 I/O 辅助：在宿主侧解码客户机的 printf 调用
+//!
+//! 不少裸机固件没有 semihosting，日志只能走自带的 printf。
+//! 本模块按 RV32 调用约定（ilp32）读取格式串与可变参数，
+//! 在宿主侧渲染出格式化结果，配合 `SimEnv` 的 host stub
+//! 机制即可把固件日志截获成可读文本。
+//!
+//! 参数定位规则（printf 的第一个参数是格式串，位于 a0）：
+//! - 可变参数依次占用 a1..a7，用完后从 sp+0 开始按 4 字节读栈
+//! - 64-bit 参数（`%lld` 等）占用偶数对齐的寄存器对或 8 字节
+//!   对齐的栈槽，低位字在前（小端）
+//!
+//! 简化实现：不支持浮点转换（`%f` 等原样输出）。
+
+use crate::cpu::CpuCore;
+use crate::memory::Memory;
+
+/// C 字符串读取的长度上限，防止失控指针把宿主拖死
+const MAX_C_STRING: usize = 4096;
+
+/// 从客户机内存读取以 NUL 结尾的 C 字符串
+///
+/// 读取失败（地址越界）或达到 `max_len` 时截断返回已读部分。
+/// 非 UTF-8 字节以 `U+FFFD` 替换。
+pub fn read_c_string(mem: &dyn Memory, addr: u32, max_len: usize) -> String {
+    let mut bytes = Vec::new();
+    for i in 0..max_len {
+        match mem.load8(addr.wrapping_add(i as u32)) {
+            Ok(0) | Err(_) => break,
+            Ok(b) => bytes.push(b),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// 渲染客户机的 printf 调用（格式串地址取自 a0）
+///
+/// 在 PC 停在 printf 入口时调用（例如 host stub 内），
+/// 返回格式化后的文本。
+pub fn format_printf(cpu: &CpuCore, mem: &dyn Memory) -> String {
+    let fmt = read_c_string(mem, cpu.read_reg(10), MAX_C_STRING);
+    format_with_args(cpu, mem, &fmt)
+}
+
+/// 按 RV32 ABI 从 a1..a7 / 栈上取可变参数，渲染格式串
+pub fn format_with_args(cpu: &CpuCore, mem: &dyn Memory, fmt: &str) -> String {
+    let mut args = VarArgs::new(cpu, mem);
+    let mut out = String::new();
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        // 收集整个转换说明，遇到不支持的转换时原样输出
+        let mut spec = String::from("%");
+
+        // 标志
+        let mut zero_pad = false;
+        let mut left_align = false;
+        while let Some(&f) = chars.peek() {
+            match f {
+                '0' => zero_pad = true,
+                '-' => left_align = true,
+                _ => break,
+            }
+            spec.push(f);
+            chars.next();
+        }
+
+        // 最小宽度
+        let mut width = 0usize;
+        while let Some(&d) = chars.peek() {
+            if let Some(v) = d.to_digit(10) {
+                width = width * 10 + v as usize;
+                spec.push(d);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        // 长度修饰符（RV32 上 long 即 32-bit，只有 ll 需要区分）
+        let mut long_long = false;
+        while let Some(&'l') = chars.peek() {
+            if spec.ends_with('l') {
+                long_long = true;
+            }
+            spec.push('l');
+            chars.next();
+        }
+
+        let conv = match chars.next() {
+            Some(c) => c,
+            None => {
+                out.push_str(&spec);
+                break;
+            }
+        };
+
+        let rendered = match conv {
+            '%' => Some("%".to_string()),
+            'd' | 'i' => Some(if long_long {
+                (args.next_u64() as i64).to_string()
+            } else {
+                (args.next_u32() as i32).to_string()
+            }),
+            'u' => Some(if long_long {
+                args.next_u64().to_string()
+            } else {
+                args.next_u32().to_string()
+            }),
+            'x' => Some(if long_long {
+                format!("{:x}", args.next_u64())
+            } else {
+                format!("{:x}", args.next_u32())
+            }),
+            'X' => Some(if long_long {
+                format!("{:X}", args.next_u64())
+            } else {
+                format!("{:X}", args.next_u32())
+            }),
+            'o' => Some(if long_long {
+                format!("{:o}", args.next_u64())
+            } else {
+                format!("{:o}", args.next_u32())
+            }),
+            'p' => Some(format!("0x{:08x}", args.next_u32())),
+            'c' => Some(
+                char::from_u32(args.next_u32() & 0xFF)
+                    .unwrap_or('\u{FFFD}')
+                    .to_string(),
+            ),
+            's' => Some(read_c_string(args.mem, args.next_u32(), MAX_C_STRING)),
+            _ => None,
+        };
+
+        match rendered {
+            Some(s) => out.push_str(&pad(&s, width, zero_pad, left_align)),
+            None => {
+                // 不支持的转换（如 %f）：原样输出
+                spec.push(conv);
+                out.push_str(&spec);
+            }
+        }
+    }
+
+    out
+}
+
+/// 应用最小宽度填充
+fn pad(s: &str, width: usize, zero_pad: bool, left_align: bool) -> String {
+    if s.len() >= width {
+        return s.to_string();
+    }
+    let fill_len = width - s.len();
+    if left_align {
+        format!("{}{}", s, " ".repeat(fill_len))
+    } else if zero_pad {
+        // 符号要留在填充 0 之前
+        if let Some(rest) = s.strip_prefix('-') {
+            format!("-{}{}", "0".repeat(fill_len), rest)
+        } else {
+            format!("{}{}", "0".repeat(fill_len), s)
+        }
+    } else {
+        format!("{}{}", " ".repeat(fill_len), s)
+    }
+}
+
+/// RV32 ABI 可变参数游标：先 a1..a7，再从 sp+0 读栈
+struct VarArgs<'a> {
+    cpu: &'a CpuCore,
+    mem: &'a dyn Memory,
+    /// 下一个参数寄存器编号（11 = a1；>17 表示已转入栈）
+    next_reg: u8,
+    /// 下一个栈槽相对 sp 的偏移
+    stack_off: u32,
+}
+
+impl<'a> VarArgs<'a> {
+    fn new(cpu: &'a CpuCore, mem: &'a dyn Memory) -> Self {
+        VarArgs {
+            cpu,
+            mem,
+            next_reg: 11,
+            stack_off: 0,
+        }
+    }
+
+    /// 取下一个 32-bit 参数
+    fn next_u32(&mut self) -> u32 {
+        if self.next_reg <= 17 {
+            let value = self.cpu.read_reg(self.next_reg);
+            self.next_reg += 1;
+            value
+        } else {
+            let sp = self.cpu.read_reg(2);
+            let value = self.mem.load32(sp.wrapping_add(self.stack_off)).unwrap_or(0);
+            self.stack_off += 4;
+            value
+        }
+    }
+
+    /// 取下一个 64-bit 参数（偶数寄存器对 / 8 字节栈槽对齐）
+    fn next_u64(&mut self) -> u64 {
+        if self.next_reg <= 17 {
+            if !self.next_reg.is_multiple_of(2) {
+                self.next_reg += 1; // 对齐到偶数寄存器（a2/a4/a6）
+            }
+            if self.next_reg <= 16 {
+                let lo = self.cpu.read_reg(self.next_reg) as u64;
+                let hi = self.cpu.read_reg(self.next_reg + 1) as u64;
+                self.next_reg += 2;
+                return lo | (hi << 32);
+            }
+            // 寄存器只剩一个，整个 64-bit 参数转入栈
+            self.next_reg = 18;
+        }
+        self.stack_off = (self.stack_off + 7) & !7;
+        let lo = self.next_u32() as u64;
+        let hi = self.next_u32() as u64;
+        lo | (hi << 32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::FlatMemory;
+
+    /// 把字符串（含 NUL）写入内存
+    fn write_str(mem: &mut FlatMemory, addr: u32, s: &str) {
+        for (i, b) in s.bytes().enumerate() {
+            mem.store8(addr + i as u32, b).unwrap();
+        }
+        mem.store8(addr + s.len() as u32, 0).unwrap();
+    }
+
+    #[test]
+    fn test_basic_specifiers() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        write_str(&mut mem, 100, "x=%d hex=%08x s=%s %c%%");
+        write_str(&mut mem, 200, "world");
+        cpu.write_reg(10, 100); // a0 = fmt
+        cpu.write_reg(11, (-5i32) as u32); // a1 = %d
+        cpu.write_reg(12, 0xBEEF); // a2 = %08x
+        cpu.write_reg(13, 200); // a3 = %s
+        cpu.write_reg(14, b'!' as u32); // a4 = %c
+
+        assert_eq!(format_printf(&cpu, &mem), "x=-5 hex=0000beef s=world !%");
+    }
+
+    #[test]
+    fn test_stack_spill() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // 8 个可变参数：前 7 个在 a1..a7，第 8 个在 sp+0
+        write_str(&mut mem, 100, "%d %d %d %d %d %d %d %d");
+        cpu.write_reg(10, 100);
+        for i in 0..7u8 {
+            cpu.write_reg(11 + i, (i + 1) as u32);
+        }
+        cpu.write_reg(2, 512); // sp
+        mem.store32(512, 8).unwrap();
+
+        assert_eq!(format_printf(&cpu, &mem), "1 2 3 4 5 6 7 8");
+    }
+
+    #[test]
+    fn test_long_long_pair_alignment() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // %lld 跳过 a1，占用 a2/a3 寄存器对
+        write_str(&mut mem, 100, "%lld");
+        cpu.write_reg(10, 100);
+        cpu.write_reg(12, 0x89ABCDEF); // lo
+        cpu.write_reg(13, 0x01234567); // hi
+
+        assert_eq!(format_printf(&cpu, &mem), "81985529216486895");
+    }
+
+    #[test]
+    fn test_unsupported_conversion_passthrough() {
+        let mut mem = FlatMemory::new(1024, 0);
+        let mut cpu = CpuCore::new(0);
+
+        write_str(&mut mem, 100, "pi=%f d=%d");
+        cpu.write_reg(10, 100);
+        cpu.write_reg(11, 7);
+
+        assert_eq!(format_printf(&cpu, &mem), "pi=%f d=7");
+    }
+}
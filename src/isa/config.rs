@@ -5,12 +5,17 @@
 use std::collections::HashSet;
 use std::sync::Arc;
 
-use super::decoder::{DecoderRegistry, InstrDecoder};
+use super::decoder::{DecoderRegistry, InstrDecoder, IsaError};
 use super::instr_def::InstrDef;
 use super::rv32i::{RV32I_DECODER, RV32I_INSTRS};
 use super::rv32m::{RV32M_DECODER, RV32M_INSTRS};
+use super::rv32a::{RV32A_DECODER, RV32A_INSTRS};
 use super::rv32f::{RV32F_DECODER, RV32F_INSTRS};
+use super::rv32v::{RV32V_DECODER, RV32V_INSTRS};
 use super::zicsr::{ZICSR_DECODER, ZICSR_INSTRS};
+use super::zicbo::{ZICBO_DECODER, ZICBO_INSTRS};
+use super::zicond::{ZICOND_DECODER, ZICOND_INSTRS};
+use super::zk::{ZK_DECODER, ZK_INSTRS};
 use super::priv_instr::{PRIV_DECODER, PRIV_INSTRS};
 
 /// 支持的 ISA 扩展
@@ -20,7 +25,7 @@ pub enum IsaExtension {
     RV32I,
     /// M 扩展：乘除法
     RV32M,
-    /// A 扩展：原子操作（未实现）
+    /// A 扩展：原子操作
     RV32A,
     /// F 扩展：单精度浮点（未实现）
     RV32F,
@@ -28,8 +33,16 @@ pub enum IsaExtension {
     RV32D,
     /// C 扩展：压缩指令（未实现）
     RV32C,
+    /// V 扩展：向量
+    RV32V,
     /// Zicsr 扩展：CSR 操作指令
     Zicsr,
+    /// Zk 扩展：标量密码学（Zbkb/Zknd/Zkne/Zknh 子集）
+    Zk,
+    /// Zicond 扩展：整数条件操作（czero.eqz/czero.nez）
+    Zicond,
+    /// Zicbo 扩展：缓存块操作（Zicboz 的 cbo.zero 与 Zicbom）
+    Zicbo,
     /// 特权指令：MRET, SRET, WFI 等
     Priv,
     /// 自定义扩展
@@ -45,7 +58,11 @@ impl std::fmt::Display for IsaExtension {
             IsaExtension::RV32F => write!(f, "F"),
             IsaExtension::RV32D => write!(f, "D"),
             IsaExtension::RV32C => write!(f, "C"),
+            IsaExtension::RV32V => write!(f, "V"),
             IsaExtension::Zicsr => write!(f, "_Zicsr"),
+            IsaExtension::Zk => write!(f, "_Zk"),
+            IsaExtension::Zicond => write!(f, "_Zicond"),
+            IsaExtension::Zicbo => write!(f, "_Zicbo"),
             IsaExtension::Priv => write!(f, "_Priv"),
             IsaExtension::Custom(name) => write!(f, "X{}", name),
         }
@@ -168,6 +185,14 @@ impl IsaConfig {
         self
     }
 
+    /// 启用 A 扩展（原子操作）
+    pub fn with_a_extension(mut self) -> Self {
+        if self.extensions.insert(IsaExtension::RV32A) {
+            self.signatures.extend(rv32a_signatures());
+        }
+        self
+    }
+
     /// 启用 F 扩展（单精度浮点）
     pub fn with_f_extension(mut self) -> Self {
         if self.extensions.insert(IsaExtension::RV32F) {
@@ -176,6 +201,14 @@ impl IsaConfig {
         self
     }
 
+    /// 启用 V 扩展（向量）
+    pub fn with_v_extension(mut self) -> Self {
+        if self.extensions.insert(IsaExtension::RV32V) {
+            self.signatures.extend(rv32v_signatures());
+        }
+        self
+    }
+
     /// 启用 Zicsr 扩展（CSR 操作指令）
     pub fn with_zicsr_extension(mut self) -> Self {
         if self.extensions.insert(IsaExtension::Zicsr) {
@@ -184,6 +217,30 @@ impl IsaConfig {
         self
     }
 
+    /// 启用 Zk 扩展（标量密码学）
+    pub fn with_zk_extension(mut self) -> Self {
+        if self.extensions.insert(IsaExtension::Zk) {
+            self.signatures.extend(zk_signatures());
+        }
+        self
+    }
+
+    /// 启用 Zicond 扩展（整数条件操作）
+    pub fn with_zicond_extension(mut self) -> Self {
+        if self.extensions.insert(IsaExtension::Zicond) {
+            self.signatures.extend(zicond_signatures());
+        }
+        self
+    }
+
+    /// 启用 Zicbo 扩展（缓存块操作）
+    pub fn with_zicbo_extension(mut self) -> Self {
+        if self.extensions.insert(IsaExtension::Zicbo) {
+            self.signatures.extend(zicbo_signatures());
+        }
+        self
+    }
+
     /// 启用特权指令扩展（MRET, SRET, WFI）
     pub fn with_priv_extension(mut self) -> Self {
         if self.extensions.insert(IsaExtension::Priv) {
@@ -192,8 +249,13 @@ impl IsaConfig {
         self
     }
 
+    /// 查询某个扩展是否已启用
+    pub fn has_extension(&self, ext: IsaExtension) -> bool {
+        self.extensions.contains(&ext)
+    }
+
     /// 添加自定义解码器
-    /// 
+    ///
     /// # 参数
     /// 
     /// * `extension` - 扩展标识
@@ -253,6 +315,7 @@ impl IsaConfig {
             IsaExtension::RV32F,
             IsaExtension::RV32D,
             IsaExtension::RV32C,
+            IsaExtension::RV32V,
         ];
         
         for ext in &order {
@@ -279,12 +342,12 @@ impl IsaConfig {
     }
 
     /// 构建解码器注册表
-    /// 
-    /// 如果存在冲突，返回错误
-    pub fn build(self) -> Result<DecoderRegistry, Vec<ConflictInfo>> {
+    ///
+    /// 如果存在冲突，返回 [`IsaError::Conflicts`]
+    pub fn build(self) -> Result<DecoderRegistry, IsaError> {
         let conflicts = self.detect_conflicts();
         if !conflicts.is_empty() {
-            return Err(conflicts);
+            return Err(IsaError::Conflicts(conflicts));
         }
         
         let mut registry = DecoderRegistry::new();
@@ -299,13 +362,27 @@ impl IsaConfig {
                 .expect("RV32M decoder must register");
         }
         
+        // 添加 A 扩展
+        if self.extensions.contains(&IsaExtension::RV32A) {
+            registry
+                .register(Arc::new(RV32A_DECODER))
+                .expect("RV32A decoder must register");
+        }
+
         // 添加 F 扩展
         if self.extensions.contains(&IsaExtension::RV32F) {
             registry
                 .register(Arc::new(RV32F_DECODER))
                 .expect("RV32F decoder must register");
         }
-        
+
+        // 添加 V 扩展
+        if self.extensions.contains(&IsaExtension::RV32V) {
+            registry
+                .register(Arc::new(RV32V_DECODER))
+                .expect("RV32V decoder must register");
+        }
+
         // 添加 Zicsr 扩展
         if self.extensions.contains(&IsaExtension::Zicsr) {
             registry
@@ -313,6 +390,27 @@ impl IsaConfig {
                 .expect("Zicsr decoder must register");
         }
         
+        // 添加 Zk 扩展
+        if self.extensions.contains(&IsaExtension::Zk) {
+            registry
+                .register(Arc::new(ZK_DECODER))
+                .expect("Zk decoder must register");
+        }
+
+        // 添加 Zicond 扩展
+        if self.extensions.contains(&IsaExtension::Zicond) {
+            registry
+                .register(Arc::new(ZICOND_DECODER))
+                .expect("Zicond decoder must register");
+        }
+
+        // 添加 Zicbo 扩展
+        if self.extensions.contains(&IsaExtension::Zicbo) {
+            registry
+                .register(Arc::new(ZICBO_DECODER))
+                .expect("Zicbo decoder must register");
+        }
+
         // 添加特权指令扩展
         if self.extensions.contains(&IsaExtension::Priv) {
             registry
@@ -337,15 +435,35 @@ impl IsaConfig {
         if self.extensions.contains(&IsaExtension::RV32M) {
             let _ = registry.register(Arc::new(RV32M_DECODER));
         }
-        
+
+        if self.extensions.contains(&IsaExtension::RV32A) {
+            let _ = registry.register(Arc::new(RV32A_DECODER));
+        }
+
         if self.extensions.contains(&IsaExtension::RV32F) {
             let _ = registry.register(Arc::new(RV32F_DECODER));
         }
-        
+
+        if self.extensions.contains(&IsaExtension::RV32V) {
+            let _ = registry.register(Arc::new(RV32V_DECODER));
+        }
+
         if self.extensions.contains(&IsaExtension::Zicsr) {
             let _ = registry.register(Arc::new(ZICSR_DECODER));
         }
-        
+
+        if self.extensions.contains(&IsaExtension::Zk) {
+            let _ = registry.register(Arc::new(ZK_DECODER));
+        }
+
+        if self.extensions.contains(&IsaExtension::Zicond) {
+            let _ = registry.register(Arc::new(ZICOND_DECODER));
+        }
+
+        if self.extensions.contains(&IsaExtension::Zicbo) {
+            let _ = registry.register(Arc::new(ZICBO_DECODER));
+        }
+
         for (_, decoder, _) in self.custom_decoders {
             let _ = registry.register(decoder);
         }
@@ -358,6 +476,13 @@ impl IsaConfig {
         &self.extensions
     }
 
+    /// 获取全部指令签名（按注册顺序）
+    ///
+    /// 供 [`crate::isa::all_instructions`] 等自省工具使用
+    pub fn signatures(&self) -> &[InstrSignature] {
+        &self.signatures
+    }
+
     /// 打印配置摘要
     pub fn summary(&self) -> String {
         let mut s = format!("ISA: {}\n", self.isa_string());
@@ -402,6 +527,22 @@ fn rv32m_signatures() -> Vec<InstrSignature> {
         .collect()
 }
 
+/// RV32A 指令签名（从 RV32A_INSTRS 派生）
+fn rv32a_signatures() -> Vec<InstrSignature> {
+    RV32A_INSTRS
+        .iter()
+        .map(|def| InstrSignature::from_def(def, IsaExtension::RV32A))
+        .collect()
+}
+
+/// RV32V 指令签名（从 RV32V_INSTRS 派生）
+fn rv32v_signatures() -> Vec<InstrSignature> {
+    RV32V_INSTRS
+        .iter()
+        .map(|def| InstrSignature::from_def(def, IsaExtension::RV32V))
+        .collect()
+}
+
 /// RV32F 指令签名（从 RV32F_INSTRS 派生）
 fn rv32f_signatures() -> Vec<InstrSignature> {
     RV32F_INSTRS
@@ -418,6 +559,30 @@ fn zicsr_signatures() -> Vec<InstrSignature> {
         .collect()
 }
 
+/// Zk 指令签名（从 ZK_INSTRS 派生）
+fn zk_signatures() -> Vec<InstrSignature> {
+    ZK_INSTRS
+        .iter()
+        .map(|def| InstrSignature::from_def(def, IsaExtension::Zk))
+        .collect()
+}
+
+/// Zicond 指令签名（从 ZICOND_INSTRS 派生）
+fn zicond_signatures() -> Vec<InstrSignature> {
+    ZICOND_INSTRS
+        .iter()
+        .map(|def| InstrSignature::from_def(def, IsaExtension::Zicond))
+        .collect()
+}
+
+/// Zicbo 指令签名（从 ZICBO_INSTRS 派生）
+fn zicbo_signatures() -> Vec<InstrSignature> {
+    ZICBO_INSTRS
+        .iter()
+        .map(|def| InstrSignature::from_def(def, IsaExtension::Zicbo))
+        .collect()
+}
+
 /// 特权指令签名（从 PRIV_INSTRS 派生）
 fn priv_signatures() -> Vec<InstrSignature> {
     PRIV_INSTRS
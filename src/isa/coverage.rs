@@ -0,0 +1,193 @@
+//! 指令表覆盖率统计
+//!
+//! 记录运行期间每个 [`InstrDef`] 表项被匹配的次数，并能按扩展
+//! 报告未覆盖的指令。解码器重构后，用它检查 rv32ui/um/uf 等
+//! 测试套件是否真的打到了每个表项——没被任何用例执行过的
+//! 指令正是静默穿透缺陷最容易藏身的地方。
+
+use super::instr_def::InstrDef;
+
+/// 单个指令表的覆盖计数
+struct TableCoverage {
+    /// 扩展名（与解码器名一致，如 "RV32I"）
+    extension: &'static str,
+    defs: &'static [InstrDef],
+    /// 与 `defs` 一一对应的命中计数
+    counts: Vec<u64>,
+}
+
+/// 指令表覆盖率跟踪器
+///
+/// 按注册顺序用 mask/match 匹配指令字，首个命中的表项计数
+/// +1（与 [`super::TableDrivenDecoder`] 的首中即停语义一致）。
+/// 通过 [`crate::cpu::CpuCore::enable_coverage`] 挂到 CPU 上
+/// 后，每条成功取指的指令自动记录。
+pub struct CoverageTracker {
+    tables: Vec<TableCoverage>,
+}
+
+impl CoverageTracker {
+    /// 创建空的跟踪器（用 [`CoverageTracker::add_table`] 注册表）
+    pub fn new() -> Self {
+        CoverageTracker { tables: Vec::new() }
+    }
+
+    /// 创建注册了全部内置指令表的跟踪器
+    pub fn with_all_tables() -> Self {
+        let mut tracker = Self::new();
+        tracker.add_table("RV32I", super::RV32I_INSTRS);
+        tracker.add_table("RV32M", super::RV32M_INSTRS);
+        tracker.add_table("RV32A", super::RV32A_INSTRS);
+        tracker.add_table("RV32F", super::RV32F_INSTRS);
+        tracker.add_table("RV32V", super::RV32V_INSTRS);
+        tracker.add_table("Zicbo", super::ZICBO_INSTRS);
+        tracker.add_table("Zicond", super::ZICOND_INSTRS);
+        tracker.add_table("Zicsr", super::ZICSR_INSTRS);
+        tracker.add_table("Zk", super::ZK_INSTRS);
+        tracker.add_table("Priv", super::PRIV_INSTRS);
+        tracker
+    }
+
+    /// 注册一个指令表，初始计数全零
+    pub fn add_table(&mut self, extension: &'static str, defs: &'static [InstrDef]) {
+        self.tables.push(TableCoverage {
+            extension,
+            defs,
+            counts: vec![0; defs.len()],
+        });
+    }
+
+    /// 记录一个指令字：首个匹配的表项计数 +1
+    pub fn record(&mut self, raw: u32) {
+        for table in &mut self.tables {
+            if let Some(i) = table.defs.iter().position(|def| def.matches(raw)) {
+                table.counts[i] += 1;
+                return;
+            }
+        }
+    }
+
+    /// 指定扩展中某条指令的命中次数（表或指令不存在时为 None）
+    pub fn hits(&self, extension: &str, name: &str) -> Option<u64> {
+        let table = self.tables.iter().find(|t| t.extension == extension)?;
+        let i = table.defs.iter().position(|def| def.name == name)?;
+        Some(table.counts[i])
+    }
+
+    /// 指定扩展中从未命中的指令名（表不存在时为空）
+    pub fn untested(&self, extension: &str) -> Vec<&'static str> {
+        self.tables
+            .iter()
+            .filter(|t| t.extension == extension)
+            .flat_map(|t| {
+                t.defs
+                    .iter()
+                    .zip(&t.counts)
+                    .filter(|&(_, &count)| count == 0)
+                    .map(|(def, _)| def.name)
+            })
+            .collect()
+    }
+
+    /// 全部 (扩展, 指令名) 形式的未命中表项
+    pub fn all_untested(&self) -> Vec<(&'static str, &'static str)> {
+        self.tables
+            .iter()
+            .flat_map(|t| {
+                t.defs
+                    .iter()
+                    .zip(&t.counts)
+                    .filter(|&(_, &count)| count == 0)
+                    .map(move |(def, _)| (t.extension, def.name))
+            })
+            .collect()
+    }
+
+    /// 是否每个注册表项都至少命中一次
+    pub fn complete(&self) -> bool {
+        self.tables
+            .iter()
+            .all(|t| t.counts.iter().all(|&count| count > 0))
+    }
+}
+
+impl Default for CoverageTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for CoverageTracker {
+    /// 按扩展一行的覆盖摘要，未命中的指令名列在行尾
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "=== 指令表覆盖率 ===")?;
+        for table in &self.tables {
+            let covered = table.counts.iter().filter(|&&count| count > 0).count();
+            write!(f, "{:8} {:3}/{:3}", table.extension, covered, table.defs.len())?;
+            if covered < table.defs.len() {
+                let missing: Vec<&str> = table
+                    .defs
+                    .iter()
+                    .zip(&table.counts)
+                    .filter(|&(_, &count)| count == 0)
+                    .map(|(def, _)| def.name)
+                    .collect();
+                write!(f, "  未覆盖: {}", missing.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_counts_and_untested() {
+        let mut tracker = CoverageTracker::new();
+        tracker.add_table("RV32I", crate::isa::RV32I_INSTRS);
+
+        tracker.record(0x0000_0013); // addi x0, x0, 0
+        tracker.record(0x0000_0013);
+        tracker.record(0x0001_2083); // lw x1, 0(x2)
+
+        assert_eq!(tracker.hits("RV32I", "ADDI"), Some(2));
+        assert_eq!(tracker.hits("RV32I", "LW"), Some(1));
+        assert_eq!(tracker.hits("RV32I", "LUI"), Some(0));
+        assert_eq!(tracker.hits("RV32I", "MUL"), None, "不在表中的指令名");
+
+        let untested = tracker.untested("RV32I");
+        assert!(untested.contains(&"LUI"));
+        assert!(!untested.contains(&"ADDI"));
+        assert!(!tracker.complete());
+    }
+
+    #[test]
+    fn test_unmatched_words_are_not_counted() {
+        let mut tracker = CoverageTracker::with_all_tables();
+        let before = tracker.all_untested().len();
+        tracker.record(0xFFFF_FFFF); // 非法编码
+        assert_eq!(tracker.all_untested().len(), before);
+    }
+
+    #[test]
+    fn test_cpu_records_executed_instructions() {
+        use crate::cpu::CpuCore;
+        use crate::memory::{FlatMemory, Memory};
+
+        let mut cpu = CpuCore::new(0);
+        cpu.enable_coverage(CoverageTracker::with_all_tables());
+        let mut mem = FlatMemory::new(1024, 0);
+        mem.store32(0, 0x0000_0013).unwrap(); // addi x0, x0, 0
+        mem.store32(4, 0x2000_0137).unwrap(); // lui x2, 0x20000
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+
+        let cov = cpu.coverage().expect("启用后应有跟踪器");
+        assert_eq!(cov.hits("RV32I", "ADDI"), Some(1));
+        assert_eq!(cov.hits("RV32I", "LUI"), Some(1));
+        assert!(cov.untested("RV32M").contains(&"MUL"));
+    }
+}
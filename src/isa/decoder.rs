@@ -2,7 +2,9 @@
 //!
 //! 提供可扩展的指令解码系统
 
+use super::instr_def::InstrDef;
 use crate::isa::{DecodedInstr, RvInstr};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// 指令解码器 trait
@@ -28,6 +30,163 @@ pub trait InstrDecoder: Send + Sync {
         fn allow_opcode_overlap(&self) -> bool {
             false
         }
+
+    /// 暴露底层的指令定义表（若有）
+    ///
+    /// 表驱动解码器返回自己的 `InstrDef` 表，注册表据此在注册时
+    /// 构建按 opcode/funct3/funct7 分层的解码树；返回 `None` 的
+    /// 解码器退化为整体参与匹配
+    fn instr_defs(&self) -> Option<&'static [InstrDef]> {
+        None
+    }
+}
+
+/// ISA / 解码器配置错误
+///
+/// 带结构化载荷，库用户可以按原因编程匹配（如把
+/// [`IsaError::Conflicts`] 逐条报给用户）。
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub enum IsaError {
+    /// opcode 已被既有解码器占用且双方不允许重叠
+    OpcodeOccupied {
+        /// 被占用的 opcode（低 7 位）
+        opcode: u32,
+        /// 被拒绝的解码器名称
+        decoder: String,
+    },
+    /// 全 opcode 覆盖的解码器与既有解码器重叠
+    WildcardOverlap {
+        /// 被拒绝的解码器名称
+        decoder: String,
+    },
+    /// 指令定义两两冲突（见 [`super::config::IsaConfig::detect_conflicts`]）
+    Conflicts(Vec<super::config::ConflictInfo>),
+}
+
+impl std::fmt::Display for IsaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IsaError::OpcodeOccupied { opcode, decoder } => write!(
+                f,
+                "opcode 0x{:02X} already handled; rejecting decoder {}",
+                opcode, decoder
+            ),
+            IsaError::WildcardOverlap { decoder } => {
+                write!(f, "wildcard decoder {} cannot register due to overlap", decoder)
+            }
+            IsaError::Conflicts(conflicts) => {
+                write!(f, "{} instruction conflict(s)", conflicts.len())?;
+                for c in conflicts {
+                    write!(f, "; {}", c)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for IsaError {}
+
+/// funct3 字段在指令字中的掩码（位 14:12）
+const FUNCT3_MASK: u32 = 0x0000_7000;
+/// funct7 字段在指令字中的掩码（位 31:25）
+const FUNCT7_MASK: u32 = 0xFE00_0000;
+/// opcode 字段掩码（位 6:0）
+const OPCODE_MASK: u32 = 0x0000_007F;
+
+/// 解码树中的一个定义条目
+///
+/// `order` 是全局注册顺序（解码器序号在高位、表内序号在低位），
+/// 解码时各桶按 `order` 归并，保证与逐解码器线性扫描相同的
+/// 先注册先匹配语义
+struct TreeEntry {
+    order: u32,
+    def: InstrDef,
+}
+
+/// 解码树中单个 opcode 的节点
+///
+/// 定义按其 mask 覆盖的字段分到三层：funct3 与 funct7 都确定的
+/// 进 `exact` 精确桶，只确定 funct3 的进 `by_funct3`，其余（如
+/// ECALL 之外还检查 rs1/rd 为零的编码、rm 字段自由的浮点指令）
+/// 留在 `linear` 线性匹配。各桶内部按注册顺序有序。
+#[derive(Default)]
+struct DecodeNode {
+    /// (funct3, funct7) 精确分桶
+    exact: HashMap<(u8, u8), Vec<TreeEntry>>,
+    /// 按 funct3 分桶（funct7 不完整覆盖）
+    by_funct3: [Vec<TreeEntry>; 8],
+    /// mask 未完整覆盖 funct3 的定义
+    linear: Vec<TreeEntry>,
+    /// 没有定义表的解码器 (order, 解码器索引)，整体参与匹配
+    fallback: Vec<(u32, usize)>,
+}
+
+impl DecodeNode {
+    fn insert(&mut self, order: u32, def: InstrDef) {
+        let entry = TreeEntry { order, def };
+        if entry.def.mask & FUNCT3_MASK == FUNCT3_MASK {
+            let f3 = ((entry.def.match_val >> 12) & 0x7) as u8;
+            if entry.def.mask & FUNCT7_MASK == FUNCT7_MASK {
+                let f7 = ((entry.def.match_val >> 25) & 0x7F) as u8;
+                self.exact.entry((f3, f7)).or_default().push(entry);
+            } else {
+                self.by_funct3[f3 as usize].push(entry);
+            }
+        } else {
+            self.linear.push(entry);
+        }
+    }
+
+    /// 在本节点内解码：三个定义桶与 fallback 解码器按 order 归并，
+    /// 命中即返回
+    fn decode(&self, raw: u32, decoders: &[Arc<dyn InstrDecoder>]) -> Option<DecodedInstr> {
+        let f3 = ((raw >> 12) & 0x7) as usize;
+        let f7 = ((raw >> 25) & 0x7F) as u8;
+        let exact = self
+            .exact
+            .get(&(f3 as u8, f7))
+            .map_or(&[][..], Vec::as_slice);
+        let lists: [&[TreeEntry]; 3] = [exact, &self.by_funct3[f3], &self.linear];
+        let mut cursor = [0usize; 3];
+        let mut fb = 0usize;
+
+        loop {
+            // 取当前 order 最小的候选；3 表示 fallback 解码器
+            let mut best: Option<(u32, usize)> = None;
+            for (k, list) in lists.iter().enumerate() {
+                if let Some(entry) = list.get(cursor[k])
+                    && best.is_none_or(|(order, _)| entry.order < order)
+                {
+                    best = Some((entry.order, k));
+                }
+            }
+            if let Some(&(order, _)) = self.fallback.get(fb)
+                && best.is_none_or(|(o, _)| order < o)
+            {
+                best = Some((order, 3));
+            }
+
+            match best {
+                None => return None,
+                Some((_, 3)) => {
+                    let (_, idx) = self.fallback[fb];
+                    fb += 1;
+                    if let Some(decoded) = decoders[idx].decode(raw) {
+                        return Some(decoded);
+                    }
+                }
+                Some((_, k)) => {
+                    let entry = &lists[k][cursor[k]];
+                    cursor[k] += 1;
+                    if entry.def.matches(raw) {
+                        return Some(entry.def.decode_instr(raw));
+                    }
+                }
+            }
+        }
+    }
 }
 
     /// 解码器注册表
@@ -36,8 +195,10 @@ pub trait InstrDecoder: Send + Sync {
 pub struct DecoderRegistry {
     /// 注册的解码器列表（按注册顺序）
     decoders: Vec<Arc<dyn InstrDecoder>>,
-    /// 按 opcode 分桶的解码器索引
+    /// 按 opcode 分桶的解码器索引（用于注册时的冲突检测）
     opcode_map: [Vec<usize>; 128],
+    /// 按 opcode/funct3/funct7 分层的解码树，注册时自动构建
+    tree: [DecodeNode; 128],
 }
 
 impl DecoderRegistry {
@@ -46,6 +207,7 @@ impl DecoderRegistry {
         Self {
             decoders: Vec::new(),
             opcode_map: std::array::from_fn(|_| Vec::new()),
+            tree: std::array::from_fn(|_| DecodeNode::default()),
         }
     }
     
@@ -57,7 +219,7 @@ impl DecoderRegistry {
     }
     
     /// 注册一个解码器；若声明的 opcode 已被占用则返回 Err
-    pub fn register(&mut self, decoder: Arc<dyn InstrDecoder>) -> Result<(), String> {
+    pub fn register(&mut self, decoder: Arc<dyn InstrDecoder>) -> Result<(), IsaError> {
         let idx = self.decoders.len();
 
         // 先做冲突检测，避免错误时污染注册表
@@ -69,11 +231,10 @@ impl DecoderRegistry {
                             .iter()
                             .any(|&i| !self.decoders[i].allow_opcode_overlap());
                         if existing_conflict || !decoder.allow_opcode_overlap() {
-                            return Err(format!(
-                                "opcode 0x{:02X} already handled; rejecting decoder {}",
-                                op,
-                                decoder.name()
-                            ));
+                            return Err(IsaError::OpcodeOccupied {
+                                opcode: op,
+                                decoder: decoder.name().to_string(),
+                            });
                         }
                     }
                 }
@@ -85,7 +246,9 @@ impl DecoderRegistry {
                 .iter()
                 .any(|bucket| bucket.iter().any(|&i| !self.decoders[i].allow_opcode_overlap()));
             if has_blocking || !decoder.allow_opcode_overlap() {
-                return Err(format!("wildcard decoder {} cannot register due to overlap", decoder.name()));
+                return Err(IsaError::WildcardOverlap {
+                    decoder: decoder.name().to_string(),
+                });
             }
         }
 
@@ -104,21 +267,54 @@ impl DecoderRegistry {
             }
         }
 
+        self.index_decoder(idx);
+
         Ok(())
     }
-    
+
+    /// 把一个已注册的解码器并入解码树
+    ///
+    /// 有定义表且每条定义都确定 opcode 时，逐条按 opcode 插入
+    /// 对应节点；否则整个解码器作为 fallback 挂到它声明的
+    /// opcode（未声明则挂到全部 128 个节点）
+    fn index_decoder(&mut self, idx: usize) {
+        // order 高 16 位是解码器序号、低 16 位是表内序号，
+        // 注册越早越小
+        let base = (idx as u32) << 16;
+        let decoder = Arc::clone(&self.decoders[idx]);
+
+        match decoder.instr_defs() {
+            Some(defs) if defs.iter().all(|d| d.mask & OPCODE_MASK == OPCODE_MASK) => {
+                for (j, def) in defs.iter().enumerate() {
+                    let opcode = (def.match_val & OPCODE_MASK) as usize;
+                    self.tree[opcode].insert(base | j as u32, def.clone());
+                }
+            }
+            _ => match decoder.handled_opcodes() {
+                Some(opcodes) => {
+                    for &op in opcodes {
+                        if (op as usize) < 128 {
+                            self.tree[op as usize].fallback.push((base, idx));
+                        }
+                    }
+                }
+                None => {
+                    for node in &mut self.tree {
+                        node.fallback.push((base, idx));
+                    }
+                }
+            },
+        }
+    }
+
     /// 解码指令
     ///
-    /// 仅按 opcode 分桶的解码器尝试，命中即返回
+    /// 走按 opcode/funct3/funct7 分层的解码树，命中即返回
     pub fn decode(&self, raw: u32) -> DecodedInstr {
         let opcode = raw & 0x7F;
 
-        // 按 opcode 分桶解码
-        for &idx in &self.opcode_map[opcode as usize] {
-            let decoder = &self.decoders[idx];
-            if let Some(decoded) = decoder.decode(raw) {
-                return decoded;
-            }
+        if let Some(decoded) = self.tree[opcode as usize].decode(raw, &self.decoders) {
+            return decoded;
         }
 
         DecodedInstr {
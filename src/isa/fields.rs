@@ -116,6 +116,7 @@ pub const OP_MISC_MEM: u32 = 0b0001111;
 pub const OP_IMM: u32 = 0b0010011;
 pub const OP_REG: u32 = 0b0110011;
 pub const OP_SYSTEM: u32 = 0b1110011;
+pub const OP_AMO: u32 = 0b0101111;
 
 // RISC-V 预留的自定义 opcode 空间
 pub const OP_CUSTOM_0: u32 = 0b0001011;
@@ -139,6 +139,31 @@ pub enum RvInstr {
     /// REMU: rd = rs1 % rs2 (unsigned)
     Remu { rd: u8, rs1: u8, rs2: u8 },
 
+    // ========== A 扩展（原子操作）==========
+    // 单 hart 仿真：aq/rl 位在解码时忽略（无可观察效果）
+    /// LR.W: rd = mem[rs1]，并在 rs1 地址上登记保留集
+    LrW { rd: u8, rs1: u8 },
+    /// SC.W: 如果保留集仍然有效则 mem[rs1] = rs2 且 rd = 0，否则 rd = 1
+    ScW { rd: u8, rs1: u8, rs2: u8 },
+    /// AMOSWAP.W: rd = mem[rs1]; mem[rs1] = rs2
+    AmoswapW { rd: u8, rs1: u8, rs2: u8 },
+    /// AMOADD.W: rd = mem[rs1]; mem[rs1] = rd + rs2
+    AmoaddW { rd: u8, rs1: u8, rs2: u8 },
+    /// AMOXOR.W: rd = mem[rs1]; mem[rs1] = rd ^ rs2
+    AmoxorW { rd: u8, rs1: u8, rs2: u8 },
+    /// AMOAND.W: rd = mem[rs1]; mem[rs1] = rd & rs2
+    AmoandW { rd: u8, rs1: u8, rs2: u8 },
+    /// AMOOR.W: rd = mem[rs1]; mem[rs1] = rd | rs2
+    AmoorW { rd: u8, rs1: u8, rs2: u8 },
+    /// AMOMIN.W: rd = mem[rs1]; mem[rs1] = min(rd, rs2) (有符号)
+    AmominW { rd: u8, rs1: u8, rs2: u8 },
+    /// AMOMAX.W: rd = mem[rs1]; mem[rs1] = max(rd, rs2) (有符号)
+    AmomaxW { rd: u8, rs1: u8, rs2: u8 },
+    /// AMOMINU.W: rd = mem[rs1]; mem[rs1] = min(rd, rs2) (无符号)
+    AmominuW { rd: u8, rs1: u8, rs2: u8 },
+    /// AMOMAXU.W: rd = mem[rs1]; mem[rs1] = max(rd, rs2) (无符号)
+    AmomaxuW { rd: u8, rs1: u8, rs2: u8 },
+
     // ========== Zicsr 扩展（CSR 操作）==========
     /// CSRRW: t = CSR[csr]; CSR[csr] = rs1; rd = t
     /// 原子读写 CSR
@@ -243,6 +268,72 @@ pub enum RvInstr {
     /// FCLASS.S: 浮点分类
     FclassS { rd: u8, frs1: u8 },
 
+    // ========== V 扩展（向量）==========
+    // 初始子集：vsetvli、单位步长 load/store 和 OPIVV 整数算术。
+    // 掩码位 vm 在解码时忽略（所有操作按无掩码执行）。
+
+    /// VSETVLI: 设置向量长度与类型（vl/vtype）
+    Vsetvli { rd: u8, rs1: u8, vtypei: u16 },
+    /// VLE{8,16,32}.V: 单位步长向量加载（eew 为元素位宽）
+    VleV { vd: u8, rs1: u8, eew: u16 },
+    /// VSE{8,16,32}.V: 单位步长向量存储（eew 为元素位宽）
+    VseV { vs3: u8, rs1: u8, eew: u16 },
+    /// VADD.VV: 向量-向量加法
+    VaddVV { vd: u8, vs2: u8, vs1: u8 },
+    /// VSUB.VV: 向量-向量减法（vs2 - vs1）
+    VsubVV { vd: u8, vs2: u8, vs1: u8 },
+    /// VAND.VV: 向量-向量按位与
+    VandVV { vd: u8, vs2: u8, vs1: u8 },
+    /// VOR.VV: 向量-向量按位或
+    VorVV { vd: u8, vs2: u8, vs1: u8 },
+    /// VXOR.VV: 向量-向量按位异或
+    VxorVV { vd: u8, vs2: u8, vs1: u8 },
+
+    // ========== Zk 扩展（标量密码学）==========
+    // 子集：Zbkb 位操作、Zknh SHA-256、Zkne/Zknd AES32 轮函数。
+
+    /// PACK: rd = rs2[15:0] ## rs1[15:0]
+    Pack { rd: u8, rs1: u8, rs2: u8 },
+    /// PACKH: rd = zero_extend(rs2[7:0] ## rs1[7:0])
+    Packh { rd: u8, rs1: u8, rs2: u8 },
+    /// BREV8: 逐字节位反转
+    Brev8 { rd: u8, rs1: u8 },
+    /// SHA256SIG0: rd = ror(rs1,7) ^ ror(rs1,18) ^ (rs1 >> 3)
+    Sha256Sig0 { rd: u8, rs1: u8 },
+    /// SHA256SIG1: rd = ror(rs1,17) ^ ror(rs1,19) ^ (rs1 >> 10)
+    Sha256Sig1 { rd: u8, rs1: u8 },
+    /// SHA256SUM0: rd = ror(rs1,2) ^ ror(rs1,13) ^ ror(rs1,22)
+    Sha256Sum0 { rd: u8, rs1: u8 },
+    /// SHA256SUM1: rd = ror(rs1,6) ^ ror(rs1,11) ^ ror(rs1,25)
+    Sha256Sum1 { rd: u8, rs1: u8 },
+    /// AES32ESI: AES 末轮加密，S-box 处理 rs2 的第 bs 字节后异或进 rs1
+    Aes32Esi { rd: u8, rs1: u8, rs2: u8, bs: u8 },
+    /// AES32ESMI: AES 中间轮加密（S-box + MixColumns）
+    Aes32Esmi { rd: u8, rs1: u8, rs2: u8, bs: u8 },
+    /// AES32DSI: AES 末轮解密（逆 S-box）
+    Aes32Dsi { rd: u8, rs1: u8, rs2: u8, bs: u8 },
+    /// AES32DSMI: AES 中间轮解密（逆 S-box + 逆 MixColumns）
+    Aes32Dsmi { rd: u8, rs1: u8, rs2: u8, bs: u8 },
+
+    // ========== Zicond 扩展（整数条件操作）==========
+
+    /// CZERO.EQZ: rd = (rs2 == 0) ? 0 : rs1
+    CzeroEqz { rd: u8, rs1: u8, rs2: u8 },
+    /// CZERO.NEZ: rd = (rs2 != 0) ? 0 : rs1
+    CzeroNez { rd: u8, rs1: u8, rs2: u8 },
+
+    // ========== Zicbo 扩展（缓存块操作）==========
+    // Zicboz 的 cbo.zero 与 Zicbom 的 clean/flush/inval。
+
+    /// CBO.ZERO: 把 rs1 所在的缓存块清零
+    CboZero { rs1: u8 },
+    /// CBO.CLEAN: 写回 rs1 所在的缓存块
+    CboClean { rs1: u8 },
+    /// CBO.FLUSH: 写回并失效 rs1 所在的缓存块
+    CboFlush { rs1: u8 },
+    /// CBO.INVAL: 失效 rs1 所在的缓存块
+    CboInval { rs1: u8 },
+
     // ========== 特殊 ==========
     /// 非法指令
     Illegal { raw: u32 },
@@ -262,6 +353,589 @@ pub enum RvInstr {
     },
 }
 
+impl RvInstr {
+    /// 指令所属扩展的名称
+    ///
+    /// 用于指令使用统计与 ISA 合规性检查。基础指令集返回 "I"，
+    /// 自定义指令返回其扩展标识符。
+    pub fn extension_name(&self) -> &'static str {
+        match self {
+            RvInstr::Add { .. }
+            | RvInstr::Sub { .. }
+            | RvInstr::And { .. }
+            | RvInstr::Or { .. }
+            | RvInstr::Xor { .. }
+            | RvInstr::Slt { .. }
+            | RvInstr::Sltu { .. }
+            | RvInstr::Sll { .. }
+            | RvInstr::Srl { .. }
+            | RvInstr::Sra { .. }
+            | RvInstr::Addi { .. }
+            | RvInstr::Andi { .. }
+            | RvInstr::Ori { .. }
+            | RvInstr::Xori { .. }
+            | RvInstr::Slti { .. }
+            | RvInstr::Sltiu { .. }
+            | RvInstr::Slli { .. }
+            | RvInstr::Srli { .. }
+            | RvInstr::Srai { .. }
+            | RvInstr::Lb { .. }
+            | RvInstr::Lh { .. }
+            | RvInstr::Lw { .. }
+            | RvInstr::Lbu { .. }
+            | RvInstr::Lhu { .. }
+            | RvInstr::Sb { .. }
+            | RvInstr::Sh { .. }
+            | RvInstr::Sw { .. }
+            | RvInstr::Lui { .. }
+            | RvInstr::Auipc { .. }
+            | RvInstr::Jal { .. }
+            | RvInstr::Jalr { .. }
+            | RvInstr::Beq { .. }
+            | RvInstr::Bne { .. }
+            | RvInstr::Blt { .. }
+            | RvInstr::Bge { .. }
+            | RvInstr::Bltu { .. }
+            | RvInstr::Bgeu { .. }
+            | RvInstr::Ecall
+            | RvInstr::Ebreak
+            | RvInstr::Fence { .. }
+            | RvInstr::FenceI => "I",
+
+            RvInstr::Mul { .. }
+            | RvInstr::Mulh { .. }
+            | RvInstr::Mulhsu { .. }
+            | RvInstr::Mulhu { .. }
+            | RvInstr::Div { .. }
+            | RvInstr::Divu { .. }
+            | RvInstr::Rem { .. }
+            | RvInstr::Remu { .. } => "M",
+
+            RvInstr::LrW { .. }
+            | RvInstr::ScW { .. }
+            | RvInstr::AmoswapW { .. }
+            | RvInstr::AmoaddW { .. }
+            | RvInstr::AmoxorW { .. }
+            | RvInstr::AmoandW { .. }
+            | RvInstr::AmoorW { .. }
+            | RvInstr::AmominW { .. }
+            | RvInstr::AmomaxW { .. }
+            | RvInstr::AmominuW { .. }
+            | RvInstr::AmomaxuW { .. } => "A",
+
+            RvInstr::Csrrw { .. }
+            | RvInstr::Csrrs { .. }
+            | RvInstr::Csrrc { .. }
+            | RvInstr::Csrrwi { .. }
+            | RvInstr::Csrrsi { .. }
+            | RvInstr::Csrrci { .. } => "Zicsr",
+
+            RvInstr::Mret | RvInstr::Sret | RvInstr::Wfi => "Priv",
+
+            RvInstr::Flw { .. }
+            | RvInstr::Fsw { .. }
+            | RvInstr::FaddS { .. }
+            | RvInstr::FsubS { .. }
+            | RvInstr::FmulS { .. }
+            | RvInstr::FdivS { .. }
+            | RvInstr::FsqrtS { .. }
+            | RvInstr::FmaddS { .. }
+            | RvInstr::FmsubS { .. }
+            | RvInstr::FnmaddS { .. }
+            | RvInstr::FnmsubS { .. }
+            | RvInstr::FsgnjS { .. }
+            | RvInstr::FsgnjnS { .. }
+            | RvInstr::FsgnjxS { .. }
+            | RvInstr::FminS { .. }
+            | RvInstr::FmaxS { .. }
+            | RvInstr::FeqS { .. }
+            | RvInstr::FltS { .. }
+            | RvInstr::FleS { .. }
+            | RvInstr::FcvtWS { .. }
+            | RvInstr::FcvtWuS { .. }
+            | RvInstr::FcvtSW { .. }
+            | RvInstr::FcvtSWu { .. }
+            | RvInstr::FmvXW { .. }
+            | RvInstr::FmvWX { .. }
+            | RvInstr::FclassS { .. } => "F",
+
+            RvInstr::Vsetvli { .. }
+            | RvInstr::VleV { .. }
+            | RvInstr::VseV { .. }
+            | RvInstr::VaddVV { .. }
+            | RvInstr::VsubVV { .. }
+            | RvInstr::VandVV { .. }
+            | RvInstr::VorVV { .. }
+            | RvInstr::VxorVV { .. } => "V",
+
+            RvInstr::Pack { .. }
+            | RvInstr::Packh { .. }
+            | RvInstr::Brev8 { .. }
+            | RvInstr::Sha256Sig0 { .. }
+            | RvInstr::Sha256Sig1 { .. }
+            | RvInstr::Sha256Sum0 { .. }
+            | RvInstr::Sha256Sum1 { .. }
+            | RvInstr::Aes32Esi { .. }
+            | RvInstr::Aes32Esmi { .. }
+            | RvInstr::Aes32Dsi { .. }
+            | RvInstr::Aes32Dsmi { .. } => "Zk",
+
+            RvInstr::CzeroEqz { .. } | RvInstr::CzeroNez { .. } => "Zicond",
+
+            RvInstr::CboZero { .. }
+            | RvInstr::CboClean { .. }
+            | RvInstr::CboFlush { .. }
+            | RvInstr::CboInval { .. } => "Zicbo",
+
+            RvInstr::Illegal { .. } => "Illegal",
+            RvInstr::Custom { extension, .. } => extension,
+        }
+    }
+
+    /// 指令的汇编助记符（小写）
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            RvInstr::Add { .. } => "add",
+            RvInstr::Sub { .. } => "sub",
+            RvInstr::And { .. } => "and",
+            RvInstr::Or { .. } => "or",
+            RvInstr::Xor { .. } => "xor",
+            RvInstr::Slt { .. } => "slt",
+            RvInstr::Sltu { .. } => "sltu",
+            RvInstr::Sll { .. } => "sll",
+            RvInstr::Srl { .. } => "srl",
+            RvInstr::Sra { .. } => "sra",
+            RvInstr::Addi { .. } => "addi",
+            RvInstr::Andi { .. } => "andi",
+            RvInstr::Ori { .. } => "ori",
+            RvInstr::Xori { .. } => "xori",
+            RvInstr::Slti { .. } => "slti",
+            RvInstr::Sltiu { .. } => "sltiu",
+            RvInstr::Slli { .. } => "slli",
+            RvInstr::Srli { .. } => "srli",
+            RvInstr::Srai { .. } => "srai",
+            RvInstr::Lb { .. } => "lb",
+            RvInstr::Lh { .. } => "lh",
+            RvInstr::Lw { .. } => "lw",
+            RvInstr::Lbu { .. } => "lbu",
+            RvInstr::Lhu { .. } => "lhu",
+            RvInstr::Sb { .. } => "sb",
+            RvInstr::Sh { .. } => "sh",
+            RvInstr::Sw { .. } => "sw",
+            RvInstr::Lui { .. } => "lui",
+            RvInstr::Auipc { .. } => "auipc",
+            RvInstr::Jal { .. } => "jal",
+            RvInstr::Jalr { .. } => "jalr",
+            RvInstr::Beq { .. } => "beq",
+            RvInstr::Bne { .. } => "bne",
+            RvInstr::Blt { .. } => "blt",
+            RvInstr::Bge { .. } => "bge",
+            RvInstr::Bltu { .. } => "bltu",
+            RvInstr::Bgeu { .. } => "bgeu",
+            RvInstr::Ecall => "ecall",
+            RvInstr::Ebreak => "ebreak",
+            RvInstr::Fence { .. } => "fence",
+            RvInstr::FenceI => "fence.i",
+            RvInstr::Mul { .. } => "mul",
+            RvInstr::Mulh { .. } => "mulh",
+            RvInstr::Mulhsu { .. } => "mulhsu",
+            RvInstr::Mulhu { .. } => "mulhu",
+            RvInstr::Div { .. } => "div",
+            RvInstr::Divu { .. } => "divu",
+            RvInstr::Rem { .. } => "rem",
+            RvInstr::Remu { .. } => "remu",
+            RvInstr::LrW { .. } => "lr.w",
+            RvInstr::ScW { .. } => "sc.w",
+            RvInstr::AmoswapW { .. } => "amoswap.w",
+            RvInstr::AmoaddW { .. } => "amoadd.w",
+            RvInstr::AmoxorW { .. } => "amoxor.w",
+            RvInstr::AmoandW { .. } => "amoand.w",
+            RvInstr::AmoorW { .. } => "amoor.w",
+            RvInstr::AmominW { .. } => "amomin.w",
+            RvInstr::AmomaxW { .. } => "amomax.w",
+            RvInstr::AmominuW { .. } => "amominu.w",
+            RvInstr::AmomaxuW { .. } => "amomaxu.w",
+            RvInstr::Csrrw { .. } => "csrrw",
+            RvInstr::Csrrs { .. } => "csrrs",
+            RvInstr::Csrrc { .. } => "csrrc",
+            RvInstr::Csrrwi { .. } => "csrrwi",
+            RvInstr::Csrrsi { .. } => "csrrsi",
+            RvInstr::Csrrci { .. } => "csrrci",
+            RvInstr::Mret => "mret",
+            RvInstr::Sret => "sret",
+            RvInstr::Wfi => "wfi",
+            RvInstr::Flw { .. } => "flw",
+            RvInstr::Fsw { .. } => "fsw",
+            RvInstr::FaddS { .. } => "fadd.s",
+            RvInstr::FsubS { .. } => "fsub.s",
+            RvInstr::FmulS { .. } => "fmul.s",
+            RvInstr::FdivS { .. } => "fdiv.s",
+            RvInstr::FsqrtS { .. } => "fsqrt.s",
+            RvInstr::FmaddS { .. } => "fmadd.s",
+            RvInstr::FmsubS { .. } => "fmsub.s",
+            RvInstr::FnmaddS { .. } => "fnmadd.s",
+            RvInstr::FnmsubS { .. } => "fnmsub.s",
+            RvInstr::FsgnjS { .. } => "fsgnj.s",
+            RvInstr::FsgnjnS { .. } => "fsgnjn.s",
+            RvInstr::FsgnjxS { .. } => "fsgnjx.s",
+            RvInstr::FminS { .. } => "fmin.s",
+            RvInstr::FmaxS { .. } => "fmax.s",
+            RvInstr::FeqS { .. } => "feq.s",
+            RvInstr::FltS { .. } => "flt.s",
+            RvInstr::FleS { .. } => "fle.s",
+            RvInstr::FcvtWS { .. } => "fcvt.w.s",
+            RvInstr::FcvtWuS { .. } => "fcvt.wu.s",
+            RvInstr::FcvtSW { .. } => "fcvt.s.w",
+            RvInstr::FcvtSWu { .. } => "fcvt.s.wu",
+            RvInstr::FmvXW { .. } => "fmv.x.w",
+            RvInstr::FmvWX { .. } => "fmv.w.x",
+            RvInstr::FclassS { .. } => "fclass.s",
+            RvInstr::Vsetvli { .. } => "vsetvli",
+            RvInstr::VleV { eew: 8, .. } => "vle8.v",
+            RvInstr::VleV { eew: 16, .. } => "vle16.v",
+            RvInstr::VleV { .. } => "vle32.v",
+            RvInstr::VseV { eew: 8, .. } => "vse8.v",
+            RvInstr::VseV { eew: 16, .. } => "vse16.v",
+            RvInstr::VseV { .. } => "vse32.v",
+            RvInstr::VaddVV { .. } => "vadd.vv",
+            RvInstr::VsubVV { .. } => "vsub.vv",
+            RvInstr::VandVV { .. } => "vand.vv",
+            RvInstr::VorVV { .. } => "vor.vv",
+            RvInstr::VxorVV { .. } => "vxor.vv",
+            RvInstr::Pack { .. } => "pack",
+            RvInstr::Packh { .. } => "packh",
+            RvInstr::Brev8 { .. } => "brev8",
+            RvInstr::Sha256Sig0 { .. } => "sha256sig0",
+            RvInstr::Sha256Sig1 { .. } => "sha256sig1",
+            RvInstr::Sha256Sum0 { .. } => "sha256sum0",
+            RvInstr::Sha256Sum1 { .. } => "sha256sum1",
+            RvInstr::Aes32Esi { .. } => "aes32esi",
+            RvInstr::Aes32Esmi { .. } => "aes32esmi",
+            RvInstr::Aes32Dsi { .. } => "aes32dsi",
+            RvInstr::Aes32Dsmi { .. } => "aes32dsmi",
+            RvInstr::CzeroEqz { .. } => "czero.eqz",
+            RvInstr::CzeroNez { .. } => "czero.nez",
+            RvInstr::CboZero { .. } => "cbo.zero",
+            RvInstr::CboClean { .. } => "cbo.clean",
+            RvInstr::CboFlush { .. } => "cbo.flush",
+            RvInstr::CboInval { .. } => "cbo.inval",
+            RvInstr::Illegal { .. } => "<illegal>",
+            RvInstr::Custom { .. } => "<custom>",
+        }
+    }
+
+    /// 识别标准伪指令并渲染别名形式（objdump 默认输出风格）
+    ///
+    /// 覆盖与汇编器对称的伪指令集合：`nop`、`li`、`mv`、`not`、
+    /// `neg`、`seqz`、`snez`、`j`、`jr`、`ret`、`beqz`/`bnez` 及
+    /// 与零比较的分支别名。不构成伪指令的编码返回 `None`，
+    /// 调用方回退到 [`std::fmt::Display`] 的规范形式
+    /// （等价于 `objdump -M no-aliases`）。
+    pub fn pseudo_alias(&self) -> Option<String> {
+        let r = abi_reg_name;
+        match *self {
+            RvInstr::Addi { rd: 0, rs1: 0, imm: 0 } => Some("nop".to_string()),
+            RvInstr::Addi { rd, rs1: 0, imm } => Some(format!("li {}, {}", r(rd), imm)),
+            RvInstr::Addi { rd, rs1, imm: 0 } => Some(format!("mv {}, {}", r(rd), r(rs1))),
+            RvInstr::Xori { rd, rs1, imm: -1 } => Some(format!("not {}, {}", r(rd), r(rs1))),
+            RvInstr::Sub { rd, rs1: 0, rs2 } => Some(format!("neg {}, {}", r(rd), r(rs2))),
+            RvInstr::Sltiu { rd, rs1, imm: 1 } => Some(format!("seqz {}, {}", r(rd), r(rs1))),
+            RvInstr::Sltu { rd, rs1: 0, rs2 } => Some(format!("snez {}, {}", r(rd), r(rs2))),
+            RvInstr::Jal { rd: 0, offset } => Some(format!("j {}", offset)),
+            RvInstr::Jalr { rd: 0, rs1: 1, offset: 0 } => Some("ret".to_string()),
+            RvInstr::Jalr { rd: 0, rs1, offset: 0 } => Some(format!("jr {}", r(rs1))),
+            RvInstr::Beq { rs1, rs2: 0, offset } => Some(format!("beqz {}, {}", r(rs1), offset)),
+            RvInstr::Bne { rs1, rs2: 0, offset } => Some(format!("bnez {}, {}", r(rs1), offset)),
+            RvInstr::Blt { rs1, rs2: 0, offset } => Some(format!("bltz {}, {}", r(rs1), offset)),
+            RvInstr::Bge { rs1, rs2: 0, offset } => Some(format!("bgez {}, {}", r(rs1), offset)),
+            RvInstr::Blt { rs1: 0, rs2, offset } => Some(format!("bgtz {}, {}", r(rs2), offset)),
+            RvInstr::Bge { rs1: 0, rs2, offset } => Some(format!("blez {}, {}", r(rs2), offset)),
+            _ => None,
+        }
+    }
+}
+
+/// 整数寄存器的 ABI 名称（x0 → zero、x10 → a0 等）
+pub fn abi_reg_name(reg: u8) -> &'static str {
+    const NAMES: [&str; 32] = [
+        "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+        "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+        "t5", "t6",
+    ];
+    NAMES[reg as usize & 31]
+}
+
+/// 浮点寄存器的 ABI 名称（f10 → fa0 等）
+pub fn fp_abi_reg_name(reg: u8) -> &'static str {
+    const NAMES: [&str; 32] = [
+        "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+        "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+        "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+    ];
+    NAMES[reg as usize & 31]
+}
+
+/// 常见 CSR 的标准名称（反汇编用，未收录的按十六进制地址渲染）
+fn csr_name(csr: u16) -> Option<&'static str> {
+    Some(match csr {
+        0x001 => "fflags",
+        0x002 => "frm",
+        0x003 => "fcsr",
+        0x100 => "sstatus",
+        0x104 => "sie",
+        0x105 => "stvec",
+        0x140 => "sscratch",
+        0x141 => "sepc",
+        0x142 => "scause",
+        0x143 => "stval",
+        0x144 => "sip",
+        0x180 => "satp",
+        0x300 => "mstatus",
+        0x301 => "misa",
+        0x304 => "mie",
+        0x305 => "mtvec",
+        0x340 => "mscratch",
+        0x341 => "mepc",
+        0x342 => "mcause",
+        0x343 => "mtval",
+        0x344 => "mip",
+        0xC00 => "cycle",
+        0xC01 => "time",
+        0xC02 => "instret",
+        0xF14 => "mhartid",
+        _ => return None,
+    })
+}
+
+/// 渲染 fence 的访问域位（i=8, o=4, r=2, w=1）
+fn fence_set(bits: u8) -> String {
+    let mut s = String::new();
+    for (mask, c) in [(8, 'i'), (4, 'o'), (2, 'r'), (1, 'w')] {
+        if bits & mask != 0 {
+            s.push(c);
+        }
+    }
+    s
+}
+
+/// 按标准汇编文本渲染指令（ABI 寄存器名，如 `addi a0, zero, 42`）
+///
+/// 跳转/分支的目标渲染为相对当前指令的字节偏移（Display 无 PC 上下文）。
+/// 非法编码渲染为 `.word 0x...`。
+impl fmt::Display for RvInstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let m = self.mnemonic();
+        let r = abi_reg_name;
+        let fr = fp_abi_reg_name;
+        match *self {
+            // R-type 整数（含 M 与 Zbkb pack/packh）
+            RvInstr::Add { rd, rs1, rs2 }
+            | RvInstr::Sub { rd, rs1, rs2 }
+            | RvInstr::And { rd, rs1, rs2 }
+            | RvInstr::Or { rd, rs1, rs2 }
+            | RvInstr::Xor { rd, rs1, rs2 }
+            | RvInstr::Slt { rd, rs1, rs2 }
+            | RvInstr::Sltu { rd, rs1, rs2 }
+            | RvInstr::Sll { rd, rs1, rs2 }
+            | RvInstr::Srl { rd, rs1, rs2 }
+            | RvInstr::Sra { rd, rs1, rs2 }
+            | RvInstr::Mul { rd, rs1, rs2 }
+            | RvInstr::Mulh { rd, rs1, rs2 }
+            | RvInstr::Mulhsu { rd, rs1, rs2 }
+            | RvInstr::Mulhu { rd, rs1, rs2 }
+            | RvInstr::Div { rd, rs1, rs2 }
+            | RvInstr::Divu { rd, rs1, rs2 }
+            | RvInstr::Rem { rd, rs1, rs2 }
+            | RvInstr::Remu { rd, rs1, rs2 }
+            | RvInstr::Pack { rd, rs1, rs2 }
+            | RvInstr::Packh { rd, rs1, rs2 }
+            | RvInstr::CzeroEqz { rd, rs1, rs2 }
+            | RvInstr::CzeroNez { rd, rs1, rs2 } => {
+                write!(f, "{} {}, {}, {}", m, r(rd), r(rs1), r(rs2))
+            }
+
+            // Zicbo 缓存块操作（基址寻址，无目的寄存器）
+            RvInstr::CboZero { rs1 }
+            | RvInstr::CboClean { rs1 }
+            | RvInstr::CboFlush { rs1 }
+            | RvInstr::CboInval { rs1 } => {
+                write!(f, "{} ({})", m, r(rs1))
+            }
+
+            // I-type 立即数
+            RvInstr::Addi { rd, rs1, imm }
+            | RvInstr::Andi { rd, rs1, imm }
+            | RvInstr::Ori { rd, rs1, imm }
+            | RvInstr::Xori { rd, rs1, imm }
+            | RvInstr::Slti { rd, rs1, imm }
+            | RvInstr::Sltiu { rd, rs1, imm } => {
+                write!(f, "{} {}, {}, {}", m, r(rd), r(rs1), imm)
+            }
+
+            // 移位立即数
+            RvInstr::Slli { rd, rs1, shamt }
+            | RvInstr::Srli { rd, rs1, shamt }
+            | RvInstr::Srai { rd, rs1, shamt } => {
+                write!(f, "{} {}, {}, {}", m, r(rd), r(rs1), shamt)
+            }
+
+            // Load / Store
+            RvInstr::Lb { rd, rs1, offset }
+            | RvInstr::Lh { rd, rs1, offset }
+            | RvInstr::Lw { rd, rs1, offset }
+            | RvInstr::Lbu { rd, rs1, offset }
+            | RvInstr::Lhu { rd, rs1, offset } => {
+                write!(f, "{} {}, {}({})", m, r(rd), offset, r(rs1))
+            }
+            RvInstr::Sb { rs1, rs2, offset }
+            | RvInstr::Sh { rs1, rs2, offset }
+            | RvInstr::Sw { rs1, rs2, offset } => {
+                write!(f, "{} {}, {}({})", m, r(rs2), offset, r(rs1))
+            }
+
+            // U-type：渲染 20 位立即数（objdump 风格）
+            RvInstr::Lui { rd, imm } | RvInstr::Auipc { rd, imm } => {
+                write!(f, "{} {}, 0x{:x}", m, r(rd), (imm as u32) >> 12)
+            }
+
+            // 控制流：目标为相对偏移
+            RvInstr::Jal { rd, offset } => write!(f, "{} {}, {}", m, r(rd), offset),
+            RvInstr::Jalr { rd, rs1, offset } => {
+                write!(f, "{} {}, {}({})", m, r(rd), offset, r(rs1))
+            }
+            RvInstr::Beq { rs1, rs2, offset }
+            | RvInstr::Bne { rs1, rs2, offset }
+            | RvInstr::Blt { rs1, rs2, offset }
+            | RvInstr::Bge { rs1, rs2, offset }
+            | RvInstr::Bltu { rs1, rs2, offset }
+            | RvInstr::Bgeu { rs1, rs2, offset } => {
+                write!(f, "{} {}, {}, {}", m, r(rs1), r(rs2), offset)
+            }
+
+            // 无操作数系统指令
+            RvInstr::Ecall
+            | RvInstr::Ebreak
+            | RvInstr::FenceI
+            | RvInstr::Mret
+            | RvInstr::Sret
+            | RvInstr::Wfi => write!(f, "{}", m),
+
+            RvInstr::Fence { pred, succ, .. } => {
+                if pred == 0xF && succ == 0xF {
+                    write!(f, "fence")
+                } else {
+                    write!(f, "fence {},{}", fence_set(pred), fence_set(succ))
+                }
+            }
+
+            // A 扩展
+            RvInstr::LrW { rd, rs1 } => write!(f, "{} {}, ({})", m, r(rd), r(rs1)),
+            RvInstr::ScW { rd, rs1, rs2 }
+            | RvInstr::AmoswapW { rd, rs1, rs2 }
+            | RvInstr::AmoaddW { rd, rs1, rs2 }
+            | RvInstr::AmoxorW { rd, rs1, rs2 }
+            | RvInstr::AmoandW { rd, rs1, rs2 }
+            | RvInstr::AmoorW { rd, rs1, rs2 }
+            | RvInstr::AmominW { rd, rs1, rs2 }
+            | RvInstr::AmomaxW { rd, rs1, rs2 }
+            | RvInstr::AmominuW { rd, rs1, rs2 }
+            | RvInstr::AmomaxuW { rd, rs1, rs2 } => {
+                write!(f, "{} {}, {}, ({})", m, r(rd), r(rs2), r(rs1))
+            }
+
+            // Zicsr
+            RvInstr::Csrrw { rd, rs1, csr }
+            | RvInstr::Csrrs { rd, rs1, csr }
+            | RvInstr::Csrrc { rd, rs1, csr } => match csr_name(csr) {
+                Some(name) => write!(f, "{} {}, {}, {}", m, r(rd), name, r(rs1)),
+                None => write!(f, "{} {}, 0x{:x}, {}", m, r(rd), csr, r(rs1)),
+            },
+            RvInstr::Csrrwi { rd, zimm, csr }
+            | RvInstr::Csrrsi { rd, zimm, csr }
+            | RvInstr::Csrrci { rd, zimm, csr } => match csr_name(csr) {
+                Some(name) => write!(f, "{} {}, {}, {}", m, r(rd), name, zimm),
+                None => write!(f, "{} {}, 0x{:x}, {}", m, r(rd), csr, zimm),
+            },
+
+            // F 扩展
+            RvInstr::Flw { frd, rs1, offset } => {
+                write!(f, "{} {}, {}({})", m, fr(frd), offset, r(rs1))
+            }
+            RvInstr::Fsw { frs2, rs1, offset } => {
+                write!(f, "{} {}, {}({})", m, fr(frs2), offset, r(rs1))
+            }
+            RvInstr::FaddS { frd, frs1, frs2, .. }
+            | RvInstr::FsubS { frd, frs1, frs2, .. }
+            | RvInstr::FmulS { frd, frs1, frs2, .. }
+            | RvInstr::FdivS { frd, frs1, frs2, .. } => {
+                write!(f, "{} {}, {}, {}", m, fr(frd), fr(frs1), fr(frs2))
+            }
+            RvInstr::FsqrtS { frd, frs1, .. } => write!(f, "{} {}, {}", m, fr(frd), fr(frs1)),
+            RvInstr::FmaddS { frd, frs1, frs2, frs3, .. }
+            | RvInstr::FmsubS { frd, frs1, frs2, frs3, .. }
+            | RvInstr::FnmaddS { frd, frs1, frs2, frs3, .. }
+            | RvInstr::FnmsubS { frd, frs1, frs2, frs3, .. } => {
+                write!(f, "{} {}, {}, {}, {}", m, fr(frd), fr(frs1), fr(frs2), fr(frs3))
+            }
+            RvInstr::FsgnjS { frd, frs1, frs2 }
+            | RvInstr::FsgnjnS { frd, frs1, frs2 }
+            | RvInstr::FsgnjxS { frd, frs1, frs2 }
+            | RvInstr::FminS { frd, frs1, frs2 }
+            | RvInstr::FmaxS { frd, frs1, frs2 } => {
+                write!(f, "{} {}, {}, {}", m, fr(frd), fr(frs1), fr(frs2))
+            }
+            RvInstr::FeqS { rd, frs1, frs2 }
+            | RvInstr::FltS { rd, frs1, frs2 }
+            | RvInstr::FleS { rd, frs1, frs2 } => {
+                write!(f, "{} {}, {}, {}", m, r(rd), fr(frs1), fr(frs2))
+            }
+            RvInstr::FcvtWS { rd, frs1, .. } | RvInstr::FcvtWuS { rd, frs1, .. } => {
+                write!(f, "{} {}, {}", m, r(rd), fr(frs1))
+            }
+            RvInstr::FcvtSW { frd, rs1, .. } | RvInstr::FcvtSWu { frd, rs1, .. } => {
+                write!(f, "{} {}, {}", m, fr(frd), r(rs1))
+            }
+            RvInstr::FmvXW { rd, frs1 } | RvInstr::FclassS { rd, frs1 } => {
+                write!(f, "{} {}, {}", m, r(rd), fr(frs1))
+            }
+            RvInstr::FmvWX { frd, rs1 } => write!(f, "{} {}, {}", m, fr(frd), r(rs1)),
+
+            // V 扩展
+            RvInstr::Vsetvli { rd, rs1, vtypei } => {
+                write!(f, "{} {}, {}, 0x{:x}", m, r(rd), r(rs1), vtypei)
+            }
+            RvInstr::VleV { vd, rs1, .. } => write!(f, "{} v{}, ({})", m, vd, r(rs1)),
+            RvInstr::VseV { vs3, rs1, .. } => write!(f, "{} v{}, ({})", m, vs3, r(rs1)),
+            RvInstr::VaddVV { vd, vs2, vs1 }
+            | RvInstr::VsubVV { vd, vs2, vs1 }
+            | RvInstr::VandVV { vd, vs2, vs1 }
+            | RvInstr::VorVV { vd, vs2, vs1 }
+            | RvInstr::VxorVV { vd, vs2, vs1 } => {
+                write!(f, "{} v{}, v{}, v{}", m, vd, vs2, vs1)
+            }
+
+            // Zk 扩展
+            RvInstr::Brev8 { rd, rs1 }
+            | RvInstr::Sha256Sig0 { rd, rs1 }
+            | RvInstr::Sha256Sig1 { rd, rs1 }
+            | RvInstr::Sha256Sum0 { rd, rs1 }
+            | RvInstr::Sha256Sum1 { rd, rs1 } => write!(f, "{} {}, {}", m, r(rd), r(rs1)),
+            RvInstr::Aes32Esi { rd, rs1, rs2, bs }
+            | RvInstr::Aes32Esmi { rd, rs1, rs2, bs }
+            | RvInstr::Aes32Dsi { rd, rs1, rs2, bs }
+            | RvInstr::Aes32Dsmi { rd, rs1, rs2, bs } => {
+                write!(f, "{} {}, {}, {}, {}", m, r(rd), r(rs1), r(rs2), bs)
+            }
+
+            // 特殊
+            RvInstr::Illegal { raw } => write!(f, ".word 0x{:08x}", raw),
+            RvInstr::Custom { extension, raw, .. } => {
+                write!(f, "custom.{} 0x{:08x}", extension, raw)
+            }
+        }
+    }
+}
+
 /// 自定义指令的字段
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Default)]
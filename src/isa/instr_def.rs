@@ -173,6 +173,10 @@ impl InstrDecoder for TableDrivenDecoder {
     fn allow_opcode_overlap(&self) -> bool {
         self.allow_overlap
     }
+
+    fn instr_defs(&self) -> Option<&'static [InstrDef]> {
+        Some(self.instrs)
+    }
 }
 
 #[cfg(test)]
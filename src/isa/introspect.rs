@@ -0,0 +1,303 @@
+//! 指令表自省 API
+//!
+//! 把 `InstrDef` 表以结构化形式暴露给外部工具，
+//! 使解码器文档、opcode 映射表和汇编器表可以直接从
+//! 唯一事实来源（指令定义表）自动生成，而不必手工维护副本。
+//!
+//! 操作数种类按编码槽位机械推导：mask 未固定的字段即为
+//! 可变操作数槽位，立即数种类由 opcode 所属的指令格式决定。
+
+use super::config::{IsaConfig, IsaExtension};
+
+/// 指令操作数种类（按编码槽位划分）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    /// 目的寄存器（bits 11:7）
+    Rd,
+    /// 源寄存器 1（bits 19:15）
+    Rs1,
+    /// 源寄存器 2（bits 24:20）
+    Rs2,
+    /// 源寄存器 3（bits 31:27，R4-type）
+    Rs3,
+    /// I-type 立即数（bits 31:20）
+    ImmI,
+    /// S-type 立即数（bits 31:25 + 11:7）
+    ImmS,
+    /// B-type 分支偏移
+    ImmB,
+    /// U-type 高 20 位立即数
+    ImmU,
+    /// J-type 跳转偏移
+    ImmJ,
+    /// 移位量（bits 24:20）
+    Shamt,
+    /// CSR 地址（bits 31:20）
+    Csr,
+    /// CSR 立即数形式的 5 位零扩展立即数（rs1 槽位）
+    Uimm5,
+    /// 浮点舍入模式（funct3 槽位）
+    Rm,
+    /// 原子指令的 aq/rl 位（bits 26:25）
+    AqRl,
+}
+
+impl std::fmt::Display for OperandKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            OperandKind::Rd => "rd",
+            OperandKind::Rs1 => "rs1",
+            OperandKind::Rs2 => "rs2",
+            OperandKind::Rs3 => "rs3",
+            OperandKind::ImmI => "imm[I]",
+            OperandKind::ImmS => "imm[S]",
+            OperandKind::ImmB => "imm[B]",
+            OperandKind::ImmU => "imm[U]",
+            OperandKind::ImmJ => "imm[J]",
+            OperandKind::Shamt => "shamt",
+            OperandKind::Csr => "csr",
+            OperandKind::Uimm5 => "uimm5",
+            OperandKind::Rm => "rm",
+            OperandKind::AqRl => "aq/rl",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// 单条指令的自省信息
+#[derive(Debug, Clone)]
+pub struct InstrInfo {
+    /// 指令名称（与 `InstrDef::name` 相同）
+    pub name: &'static str,
+    /// 所属扩展
+    pub extension: IsaExtension,
+    /// 匹配掩码
+    pub mask: u32,
+    /// 匹配值
+    pub match_val: u32,
+    /// 操作数槽位（按惯例顺序）
+    pub operands: Vec<OperandKind>,
+}
+
+impl std::fmt::Display for InstrInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<12} [{}] mask=0x{:08X} match=0x{:08X}",
+            self.name, self.extension, self.mask, self.match_val
+        )?;
+        if !self.operands.is_empty() {
+            let ops: Vec<String> = self.operands.iter().map(|o| o.to_string()).collect();
+            write!(f, "  {}", ops.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// 各编码槽位的位域
+const RD_BITS: u32 = 0x0000_0F80;
+const RS1_BITS: u32 = 0x000F_8000;
+const RS2_BITS: u32 = 0x01F0_0000;
+const RS3_BITS: u32 = 0xF800_0000;
+const RM_BITS: u32 = 0x0000_7000;
+const IMM_I_BITS: u32 = 0xFFF0_0000;
+const IMM_SB_BITS: u32 = 0xFE00_0F80;
+const IMM_UJ_BITS: u32 = 0xFFFF_F000;
+const AQRL_BITS: u32 = 0x0600_0000;
+
+/// 从 mask/match 推导操作数槽位
+///
+/// 原则：mask 中整段未固定的标准字段视为操作数；
+/// 立即数种类由 opcode 所属的指令格式给出。推导是近似的
+/// （如 vsetvli 的 zimm 会报告为 rs2 槽位），但对文档生成
+/// 足够，且无需为每条指令手工标注。
+pub fn operand_kinds(mask: u32, match_val: u32) -> Vec<OperandKind> {
+    let free = |bits: u32| mask & bits == 0;
+    let opcode = match_val & 0x7F;
+    let mut kinds = Vec::new();
+
+    match opcode {
+        // LUI / AUIPC / JAL
+        0x37 | 0x17 | 0x6F => {
+            if free(RD_BITS) {
+                kinds.push(OperandKind::Rd);
+            }
+            if free(IMM_UJ_BITS) {
+                kinds.push(if opcode == 0x6F {
+                    OperandKind::ImmJ
+                } else {
+                    OperandKind::ImmU
+                });
+            }
+        }
+        // BRANCH：rd 槽位属于立即数
+        0x63 => {
+            if free(RS1_BITS) {
+                kinds.push(OperandKind::Rs1);
+            }
+            if free(RS2_BITS) {
+                kinds.push(OperandKind::Rs2);
+            }
+            if free(IMM_SB_BITS) {
+                kinds.push(OperandKind::ImmB);
+            }
+        }
+        // STORE / STORE-FP（标量布局；向量 store 落到 default 分支）
+        0x23 => {
+            if free(RS1_BITS) {
+                kinds.push(OperandKind::Rs1);
+            }
+            if free(RS2_BITS) {
+                kinds.push(OperandKind::Rs2);
+            }
+            if free(IMM_SB_BITS) {
+                kinds.push(OperandKind::ImmS);
+            }
+        }
+        0x27 if free(IMM_SB_BITS) => {
+            kinds.push(OperandKind::Rs1);
+            kinds.push(OperandKind::Rs2);
+            kinds.push(OperandKind::ImmS);
+        }
+        // LOAD / LOAD-FP / OP-IMM / JALR / MISC-MEM（标量 I-type 布局）
+        0x03 | 0x13 | 0x67 | 0x0F if free(IMM_I_BITS) || free(RS2_BITS) => {
+            if free(RD_BITS) {
+                kinds.push(OperandKind::Rd);
+            }
+            if free(RS1_BITS) {
+                kinds.push(OperandKind::Rs1);
+            }
+            if free(IMM_I_BITS) {
+                kinds.push(OperandKind::ImmI);
+            } else {
+                // 高位被 funct6 固定：移位立即数
+                kinds.push(OperandKind::Shamt);
+            }
+        }
+        0x07 if free(IMM_I_BITS) => {
+            kinds.push(OperandKind::Rd);
+            kinds.push(OperandKind::Rs1);
+            kinds.push(OperandKind::ImmI);
+        }
+        // SYSTEM：ECALL 等精确匹配无操作数；CSR 指令的 rs1 槽位
+        // 在立即数形式（funct3 高位为 1）下是 uimm5
+        0x73 => {
+            if free(IMM_I_BITS) {
+                let funct3 = (match_val >> 12) & 0x7;
+                kinds.push(OperandKind::Rd);
+                kinds.push(if funct3 & 0b100 != 0 {
+                    OperandKind::Uimm5
+                } else {
+                    OperandKind::Rs1
+                });
+                kinds.push(OperandKind::Csr);
+            }
+        }
+        // 寄存器-寄存器格式（OP/OP-FP/AMO/R4/向量等）：按空闲槽位推导
+        _ => {
+            if free(RD_BITS) {
+                kinds.push(OperandKind::Rd);
+            }
+            if free(RS1_BITS) {
+                kinds.push(OperandKind::Rs1);
+            }
+            if free(RS2_BITS) {
+                kinds.push(OperandKind::Rs2);
+            }
+            if free(RS3_BITS) {
+                kinds.push(OperandKind::Rs3);
+            }
+            if free(RM_BITS) {
+                kinds.push(OperandKind::Rm);
+            }
+            if opcode == 0x2F && free(AQRL_BITS) {
+                kinds.push(OperandKind::AqRl);
+            }
+        }
+    }
+
+    kinds
+}
+
+/// 枚举配置中所有已启用扩展的指令
+///
+/// 按注册顺序返回（RV32I 在前，随后是各扩展与自定义解码器），
+/// 顺序确定，可直接用于生成稳定的文档输出。
+pub fn all_instructions(config: &IsaConfig) -> Vec<InstrInfo> {
+    config
+        .signatures()
+        .iter()
+        .map(|sig| InstrInfo {
+            name: sig.name,
+            extension: sig.extension,
+            mask: sig.mask,
+            match_val: sig.match_val,
+            operands: operand_kinds(sig.mask, sig.match_val),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isa::instr_def::{I_TYPE_MASK, R_TYPE_MASK, SHIFT_IMM_MASK};
+
+    #[test]
+    fn test_operand_kinds_basic_formats() {
+        use OperandKind::*;
+
+        // ADD（R-type）
+        assert_eq!(operand_kinds(R_TYPE_MASK, 0x33), vec![Rd, Rs1, Rs2]);
+        // ADDI（I-type）
+        assert_eq!(operand_kinds(I_TYPE_MASK, 0x13), vec![Rd, Rs1, ImmI]);
+        // SLLI（移位立即数：高位被 funct6 固定）
+        assert_eq!(
+            operand_kinds(SHIFT_IMM_MASK, (0b001 << 12) | 0x13),
+            vec![Rd, Rs1, Shamt]
+        );
+        // SW（S-type）
+        assert_eq!(
+            operand_kinds(I_TYPE_MASK, (0b010 << 12) | 0x23),
+            vec![Rs1, Rs2, ImmS]
+        );
+        // BEQ（B-type：rd 槽位不是操作数）
+        assert_eq!(operand_kinds(I_TYPE_MASK, 0x63), vec![Rs1, Rs2, ImmB]);
+        // LUI（U-type）
+        assert_eq!(operand_kinds(0x7F, 0x37), vec![Rd, ImmU]);
+        // ECALL（精确匹配无操作数）
+        assert_eq!(operand_kinds(0xFFFF_FFFF, 0x73), vec![]);
+        // CSRRWI（立即数形式：rs1 槽位是 uimm5）
+        assert_eq!(
+            operand_kinds(I_TYPE_MASK, (0b101 << 12) | 0x73),
+            vec![Rd, Uimm5, Csr]
+        );
+        // FMADD.S（R4-type 带动态舍入模式）
+        assert_eq!(
+            operand_kinds(0x0600_007F, 0x43),
+            vec![Rd, Rs1, Rs2, Rs3, Rm]
+        );
+        // AMOADD.W（带 aq/rl 位）
+        assert_eq!(
+            operand_kinds(0xF800_707F, (0b010 << 12) | 0x2F),
+            vec![Rd, Rs1, Rs2, AqRl]
+        );
+    }
+
+    #[test]
+    fn test_all_instructions_covers_enabled_extensions() {
+        let base = all_instructions(&IsaConfig::new());
+        assert!(base.iter().all(|i| i.extension == IsaExtension::RV32I));
+        assert!(base.iter().any(|i| i.name == "ADD"));
+
+        let with_m = all_instructions(&IsaConfig::new().with_m_extension());
+        assert_eq!(with_m.len(), base.len() + 8, "M 扩展应贡献 8 条指令");
+        let mul = with_m.iter().find(|i| i.name == "MUL").expect("应包含 MUL");
+        assert_eq!(mul.extension, IsaExtension::RV32M);
+        assert_eq!(mul.mask, 0xFE00707F);
+
+        // Display 输出可直接用作文档行
+        let line = mul.to_string();
+        assert!(line.contains("MUL"));
+        assert!(line.contains("rd, rs1, rs2"));
+    }
+}
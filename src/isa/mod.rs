@@ -6,6 +6,8 @@
 //! - `DecoderRegistry`: 解码器注册表，支持运行时注册
 //! - `InstrDef`: 统一的指令定义，同时用于解码和冲突检测
 //! - `IsaConfig`: ISA 配置构建器，支持冲突检测
+//! - `CoverageTracker`: 指令表覆盖率统计（按扩展报告未覆盖项）
+//! - `all_instructions`: 指令表自省（自动生成解码器文档与 opcode 映射表）
 
 mod decoder;
 mod instr;
@@ -13,24 +15,38 @@ mod fields;
 mod instr_def;
 mod rv32i;
 mod rv32m;
+mod rv32a;
 mod rv32f;
+mod rv32v;
+mod zicbo;
+mod zicond;
 mod zicsr;
+mod zk;
 mod config;
+pub mod coverage;
+mod introspect;
 mod priv_instr;
 
-pub use decoder::{InstrDecoder, DecoderRegistry};
-pub use instr::{RvInstr, DecodedInstr, CustomInstr, CustomFields};
+pub use decoder::{InstrDecoder, DecoderRegistry, IsaError};
+pub use instr::{RvInstr, DecodedInstr, CustomInstr, CustomFields, abi_reg_name, fp_abi_reg_name};
 pub use fields::*;
 pub use instr_def::{InstrDef, TableDrivenDecoder};
 pub use rv32i::{RV32I_DECODER, RV32I_INSTRS, RV32I_OPCODES, Rv32iDecoder};
 pub use rv32m::{RV32M_DECODER, RV32M_INSTRS, RV32M_OPCODES, Rv32mDecoder};
+pub use rv32a::{RV32A_DECODER, RV32A_INSTRS, RV32A_OPCODES, Rv32aDecoder};
 pub use rv32f::{RV32F_DECODER, RV32F_INSTRS, RV32F_OPCODES, Rv32fDecoder, RoundingMode};
+pub use rv32v::{RV32V_DECODER, RV32V_INSTRS, RV32V_OPCODES, Rv32vDecoder};
+pub use zicbo::{ZICBO_DECODER, ZICBO_INSTRS, ZICBO_OPCODES};
+pub use zicond::{ZICOND_DECODER, ZICOND_INSTRS, ZICOND_OPCODES};
 pub use zicsr::{ZICSR_DECODER, ZICSR_INSTRS, ZICSR_OPCODES, ZicsrDecoder};
+pub use zk::{ZK_DECODER, ZK_INSTRS, ZK_OPCODES, ZkDecoder};
 pub use priv_instr::{PRIV_DECODER, PRIV_INSTRS, PRIV_OPCODES, MRET_ENCODING, SRET_ENCODING, WFI_ENCODING};
-pub use config::{IsaConfig, IsaExtension, ConflictInfo};
+pub use config::{IsaConfig, IsaExtension, ConflictInfo, InstrSignature};
+pub use coverage::CoverageTracker;
+pub use introspect::{all_instructions, operand_kinds, InstrInfo, OperandKind};
 
 /// 便捷函数：使用默认 RV32I 解码器解码指令
-/// 
+///
 /// 这保持了与旧 API 的兼容性
 pub fn decode(raw: u32) -> DecodedInstr {
     RV32I_DECODER.decode(raw).unwrap_or(DecodedInstr {
@@ -39,5 +55,69 @@ pub fn decode(raw: u32) -> DecodedInstr {
     })
 }
 
+/// 反汇编内存区间 `[start, end)` 内的指令字
+///
+/// 每个对齐的 4 字节字用给定解码器解码为一行
+/// `地址: 编码  汇编文本`；读不到的地址跳过。供跟踪输出、
+/// 调试 REPL 和测试失败诊断使用。
+pub fn disassemble_range(
+    registry: &DecoderRegistry,
+    mem: &dyn crate::memory::Memory,
+    start: u32,
+    end: u32,
+) -> Vec<String> {
+    disassemble_range_with(registry, mem, start, end, false)
+}
+
+/// 同 [`disassemble_range`]，`aliases` 控制伪指令识别
+///
+/// `aliases = false` 输出规范形式（`objdump -M no-aliases`）；
+/// `aliases = true` 把标准伪指令渲染为别名（`nop`、`li`、`mv`、
+/// `ret`、`j`、`beqz` 等，objdump 默认风格），并把
+/// `auipc t, hi` + `jalr ra, lo(t)` 对折叠为 `call 0x目标地址`。
+pub fn disassemble_range_with(
+    registry: &DecoderRegistry,
+    mem: &dyn crate::memory::Memory,
+    start: u32,
+    end: u32,
+    aliases: bool,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut addr = (start + 3) & !3;
+    // call 折叠需要回看前一条指令（auipc 的地址与高 20 位）
+    let mut prev: Option<(u32, RvInstr)> = None;
+    while addr < end {
+        if let Ok(raw) = mem.load32(addr) {
+            let decoded = registry.decode(raw);
+            let text = if aliases {
+                match (prev, decoded.instr) {
+                    // auipc + jalr ra 组成函数调用：渲染绝对目标地址
+                    (
+                        Some((auipc_addr, RvInstr::Auipc { rd: t, imm })),
+                        RvInstr::Jalr { rd: 1, rs1, offset },
+                    ) if rs1 == t && addr == auipc_addr.wrapping_add(4) => {
+                        let target = auipc_addr
+                            .wrapping_add(imm as u32)
+                            .wrapping_add(offset as u32);
+                        format!("call 0x{:x}", target)
+                    }
+                    _ => decoded
+                        .instr
+                        .pseudo_alias()
+                        .unwrap_or_else(|| decoded.instr.to_string()),
+                }
+            } else {
+                decoded.instr.to_string()
+            };
+            lines.push(format!("0x{:08x}: {:08x}  {}", addr, raw, text));
+            prev = Some((addr, decoded.instr));
+        } else {
+            prev = None;
+        }
+        addr += 4;
+    }
+    lines
+}
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,192 @@
+//! RV32A 扩展（原子操作）解码器
+//!
+//! 基于表驱动的解码实现
+//!
+//! AMO 指令编码格式（R-type 变体）：
+//! ```text
+//! 31    27 26 25 24  20 19  15 14  12 11   7 6      0
+//! ┌───────┬──┬──┬──────┬──────┬──────┬──────┬────────┐
+//! │funct5 │aq│rl│ rs2  │ rs1  │funct3│  rd  │ opcode │
+//! └───────┴──┴──┴──────┴──────┴──────┴──────┴────────┘
+//!              AMO (opcode = 0b0101111, funct3 = 010)
+//! ```
+//!
+//! aq/rl 位只约束多 hart 的内存顺序，在单 hart 仿真中没有可观察
+//! 效果，解码时直接忽略（mask 不检查 bit 26:25）。
+
+use crate::isa::fields::*;
+use crate::isa::instr::RvInstr;
+use crate::isa::instr_def::{InstrDef, TableDrivenDecoder};
+
+/// AMO 指令的 mask（检查 opcode + funct3 + funct5，忽略 aq/rl）
+pub const AMO_MASK: u32 = 0xF800707F;
+
+/// LR.W 的 mask（在 AMO_MASK 基础上额外要求 rs2 = 0）
+pub const LR_MASK: u32 = 0xF9F0707F;
+
+/// 构造 AMO 的 match 值
+#[inline]
+pub const fn amo_match(funct5: u32) -> u32 {
+    (funct5 << 27) | (0b010 << 12) | OP_AMO
+}
+
+// ========== RV32A 指令定义表 ==========
+
+/// RV32A 指令定义表
+pub static RV32A_INSTRS: &[InstrDef] = &[
+    InstrDef::new("LR.W", LR_MASK, amo_match(0b00010), |raw| RvInstr::LrW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+    }),
+    InstrDef::new("SC.W", AMO_MASK, amo_match(0b00011), |raw| RvInstr::ScW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("AMOSWAP.W", AMO_MASK, amo_match(0b00001), |raw| RvInstr::AmoswapW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("AMOADD.W", AMO_MASK, amo_match(0b00000), |raw| RvInstr::AmoaddW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("AMOXOR.W", AMO_MASK, amo_match(0b00100), |raw| RvInstr::AmoxorW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("AMOAND.W", AMO_MASK, amo_match(0b01100), |raw| RvInstr::AmoandW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("AMOOR.W", AMO_MASK, amo_match(0b01000), |raw| RvInstr::AmoorW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("AMOMIN.W", AMO_MASK, amo_match(0b10000), |raw| RvInstr::AmominW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("AMOMAX.W", AMO_MASK, amo_match(0b10100), |raw| RvInstr::AmomaxW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("AMOMINU.W", AMO_MASK, amo_match(0b11000), |raw| RvInstr::AmominuW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("AMOMAXU.W", AMO_MASK, amo_match(0b11100), |raw| RvInstr::AmomaxuW {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+];
+
+/// RV32A 扩展的 opcode 列表
+pub static RV32A_OPCODES: [u32; 1] = [OP_AMO];
+
+// ========== 解码器实例 ==========
+
+/// RV32A 解码器（基于 TableDrivenDecoder）
+///
+/// A 扩展独占 AMO (0b0101111) opcode 空间
+pub static RV32A_DECODER: TableDrivenDecoder = TableDrivenDecoder::new(
+    "RV32A",
+    RV32A_INSTRS,
+    Some(&RV32A_OPCODES),
+    false,
+);
+
+/// 兼容性别名
+pub type Rv32aDecoder = TableDrivenDecoder;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isa::{DecodedInstr, InstrDecoder};
+
+    #[test]
+    fn test_decode_lr_w() {
+        // lr.w x5, (x6)
+        // funct5=00010, rs2=0, rs1=6, funct3=010, rd=5, opcode=0x2F
+        let raw = 0x100322AF;
+        let decoded = RV32A_DECODER.decode(raw);
+        assert!(decoded.is_some());
+        let DecodedInstr { instr, .. } = decoded.unwrap();
+        match instr {
+            RvInstr::LrW { rd, rs1 } => {
+                assert_eq!(rd, 5);
+                assert_eq!(rs1, 6);
+            }
+            _ => panic!("Expected LrW"),
+        }
+    }
+
+    #[test]
+    fn test_decode_lr_w_nonzero_rs2_rejected() {
+        // LR.W 要求 rs2=0；rs2=1 的编码不属于任何 RV32A 指令
+        let raw = 0x100322AF | (1 << 20);
+        assert!(RV32A_DECODER.decode(raw).is_none());
+    }
+
+    #[test]
+    fn test_decode_sc_w() {
+        // sc.w x7, x8, (x6)
+        // funct5=00011, rs2=8, rs1=6, funct3=010, rd=7, opcode=0x2F
+        let raw = 0x188323AF;
+        let decoded = RV32A_DECODER.decode(raw);
+        assert!(decoded.is_some());
+        let DecodedInstr { instr, .. } = decoded.unwrap();
+        match instr {
+            RvInstr::ScW { rd, rs1, rs2 } => {
+                assert_eq!(rd, 7);
+                assert_eq!(rs1, 6);
+                assert_eq!(rs2, 8);
+            }
+            _ => panic!("Expected ScW"),
+        }
+    }
+
+    #[test]
+    fn test_decode_amoadd_w() {
+        // amoadd.w x5, x8, (x6)
+        // funct5=00000, rs2=8, rs1=6, funct3=010, rd=5, opcode=0x2F
+        let raw = 0x008322AF;
+        let decoded = RV32A_DECODER.decode(raw);
+        assert!(decoded.is_some());
+        let DecodedInstr { instr, .. } = decoded.unwrap();
+        match instr {
+            RvInstr::AmoaddW { rd, rs1, rs2 } => {
+                assert_eq!(rd, 5);
+                assert_eq!(rs1, 6);
+                assert_eq!(rs2, 8);
+            }
+            _ => panic!("Expected AmoaddW"),
+        }
+    }
+
+    #[test]
+    fn test_decode_ignores_aq_rl() {
+        // amoswap.w.aqrl x5, x8, (x6)：aq/rl 位不影响解码
+        let raw = 0x0E8322AF;
+        let decoded = RV32A_DECODER.decode(raw);
+        assert!(decoded.is_some());
+        let DecodedInstr { instr, .. } = decoded.unwrap();
+        match instr {
+            RvInstr::AmoswapW { rd, rs1, rs2 } => {
+                assert_eq!(rd, 5);
+                assert_eq!(rs1, 6);
+                assert_eq!(rs2, 8);
+            }
+            _ => panic!("Expected AmoswapW"),
+        }
+    }
+}
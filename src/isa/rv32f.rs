@@ -320,7 +320,7 @@ pub static RV32F_DECODER: TableDrivenDecoder = TableDrivenDecoder::new(
     "RV32F",
     RV32F_INSTRS,
     Some(&RV32F_OPCODES),
-    false,
+    true, // 允许与 RV32V 共享 LOAD-FP/STORE-FP opcode
 );
 
 /// 兼容性别名
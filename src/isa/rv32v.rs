@@ -0,0 +1,182 @@
+//! RV32V 扩展（向量）解码器
+//!
+//! 基于表驱动的解码实现。初始子集面向 GPGPU 演化目标：
+//! - `vsetvli`：设置 vl/vtype
+//! - `vle{8,16,32}.v` / `vse{8,16,32}.v`：单位步长 load/store
+//! - OPIVV 整数算术：`vadd/vsub/vand/vor/vxor.vv`
+//!
+//! OP-V 指令编码（opcode = 0b1010111）：
+//! ```text
+//! 31    26 25 24  20 19  15 14  12 11   7 6      0
+//! ┌───────┬──┬──────┬──────┬──────┬──────┬────────┐
+//! │funct6 │vm│ vs2  │ vs1  │funct3│  vd  │ opcode │
+//! └───────┴──┴──────┴──────┴──────┴──────┴────────┘
+//! ```
+//!
+//! 掩码位 vm 在解码时忽略（执行单元按无掩码处理）；
+//! 单位步长 load/store 复用 LOAD-FP/STORE-FP opcode 空间，
+//! 通过 width（funct3）与 FLW/FSW 区分。
+
+use crate::isa::fields::*;
+use crate::isa::instr::RvInstr;
+use crate::isa::instr_def::{InstrDef, TableDrivenDecoder};
+use crate::isa::rv32f::{OP_LOAD_FP, OP_STORE_FP};
+
+/// OP-V opcode
+pub const OP_V: u32 = 0b1010111;
+
+/// vsetvli 的 mask（bit31 = 0 区分 vsetvl/vsetivli）
+pub const VSETVLI_MASK: u32 = 0x8000707F;
+
+/// 单位步长 load/store 的 mask
+/// （nf/mew/mop + lumop + width + opcode，忽略 vm）
+pub const VMEM_UNIT_MASK: u32 = 0xFDF0707F;
+
+/// OPIVV 算术的 mask（funct6 + funct3 + opcode，忽略 vm）
+pub const OPIVV_MASK: u32 = 0xFC00707F;
+
+/// 构造单位步长 load/store 的 match 值（width 为 funct3 编码）
+const fn vmem_match(width: u32, opcode: u32) -> u32 {
+    (width << 12) | opcode
+}
+
+/// 构造 OPIVV 的 match 值
+const fn opivv_match(funct6: u32) -> u32 {
+    (funct6 << 26) | OP_V
+}
+
+/// 提取 vsetvli 的 vtype 立即数 zimm[10:0]
+#[inline]
+fn vtypei(raw: u32) -> u16 {
+    ((raw >> 20) & 0x7FF) as u16
+}
+
+// ========== RV32V 指令定义表 ==========
+
+/// RV32V 指令定义表
+pub static RV32V_INSTRS: &[InstrDef] = &[
+    InstrDef::new("VSETVLI", VSETVLI_MASK, (0b111 << 12) | OP_V, |raw| {
+        RvInstr::Vsetvli {
+            rd: rd(raw),
+            rs1: rs1(raw),
+            vtypei: vtypei(raw),
+        }
+    }),
+    // 单位步长 load：width 000/101/110 对应 EEW 8/16/32
+    InstrDef::new("VLE8.V", VMEM_UNIT_MASK, vmem_match(0b000, OP_LOAD_FP), |raw| {
+        RvInstr::VleV { vd: rd(raw), rs1: rs1(raw), eew: 8 }
+    }),
+    InstrDef::new("VLE16.V", VMEM_UNIT_MASK, vmem_match(0b101, OP_LOAD_FP), |raw| {
+        RvInstr::VleV { vd: rd(raw), rs1: rs1(raw), eew: 16 }
+    }),
+    InstrDef::new("VLE32.V", VMEM_UNIT_MASK, vmem_match(0b110, OP_LOAD_FP), |raw| {
+        RvInstr::VleV { vd: rd(raw), rs1: rs1(raw), eew: 32 }
+    }),
+    // 单位步长 store
+    InstrDef::new("VSE8.V", VMEM_UNIT_MASK, vmem_match(0b000, OP_STORE_FP), |raw| {
+        RvInstr::VseV { vs3: rd(raw), rs1: rs1(raw), eew: 8 }
+    }),
+    InstrDef::new("VSE16.V", VMEM_UNIT_MASK, vmem_match(0b101, OP_STORE_FP), |raw| {
+        RvInstr::VseV { vs3: rd(raw), rs1: rs1(raw), eew: 16 }
+    }),
+    InstrDef::new("VSE32.V", VMEM_UNIT_MASK, vmem_match(0b110, OP_STORE_FP), |raw| {
+        RvInstr::VseV { vs3: rd(raw), rs1: rs1(raw), eew: 32 }
+    }),
+    // OPIVV 整数算术
+    InstrDef::new("VADD.VV", OPIVV_MASK, opivv_match(0b000000), |raw| {
+        RvInstr::VaddVV { vd: rd(raw), vs2: rs2(raw), vs1: rs1(raw) }
+    }),
+    InstrDef::new("VSUB.VV", OPIVV_MASK, opivv_match(0b000010), |raw| {
+        RvInstr::VsubVV { vd: rd(raw), vs2: rs2(raw), vs1: rs1(raw) }
+    }),
+    InstrDef::new("VAND.VV", OPIVV_MASK, opivv_match(0b001001), |raw| {
+        RvInstr::VandVV { vd: rd(raw), vs2: rs2(raw), vs1: rs1(raw) }
+    }),
+    InstrDef::new("VOR.VV", OPIVV_MASK, opivv_match(0b001010), |raw| {
+        RvInstr::VorVV { vd: rd(raw), vs2: rs2(raw), vs1: rs1(raw) }
+    }),
+    InstrDef::new("VXOR.VV", OPIVV_MASK, opivv_match(0b001011), |raw| {
+        RvInstr::VxorVV { vd: rd(raw), vs2: rs2(raw), vs1: rs1(raw) }
+    }),
+];
+
+/// RV32V 扩展的 opcode 列表
+pub static RV32V_OPCODES: [u32; 3] = [OP_V, OP_LOAD_FP, OP_STORE_FP];
+
+// ========== 解码器实例 ==========
+
+/// RV32V 解码器（基于 TableDrivenDecoder）
+pub static RV32V_DECODER: TableDrivenDecoder = TableDrivenDecoder::new(
+    "RV32V",
+    RV32V_INSTRS,
+    Some(&RV32V_OPCODES),
+    true, // 允许与 RV32F 共享 LOAD-FP/STORE-FP opcode
+);
+
+/// 兼容性别名
+pub type Rv32vDecoder = TableDrivenDecoder;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isa::{DecodedInstr, InstrDecoder};
+
+    #[test]
+    fn test_decode_vsetvli() {
+        // vsetvli x1, x2, e32,m1 (vtypei = 0b0_010_000 = 0x10)
+        // zimm=0x010, rs1=2, funct3=111, rd=1, opcode=0x57
+        let raw = (0x010 << 20) | (2 << 15) | (0b111 << 12) | (1 << 7) | OP_V;
+        let decoded = RV32V_DECODER.decode(raw);
+        assert!(decoded.is_some());
+        let DecodedInstr { instr, .. } = decoded.unwrap();
+        match instr {
+            RvInstr::Vsetvli { rd, rs1, vtypei } => {
+                assert_eq!(rd, 1);
+                assert_eq!(rs1, 2);
+                assert_eq!(vtypei, 0x010);
+            }
+            _ => panic!("Expected Vsetvli"),
+        }
+    }
+
+    #[test]
+    fn test_decode_vle32() {
+        // vle32.v v3, (x5)，vm=1（无掩码）
+        let raw = (1 << 25) | (5 << 15) | (0b110 << 12) | (3 << 7) | OP_LOAD_FP;
+        let decoded = RV32V_DECODER.decode(raw);
+        assert!(decoded.is_some());
+        let DecodedInstr { instr, .. } = decoded.unwrap();
+        match instr {
+            RvInstr::VleV { vd, rs1, eew } => {
+                assert_eq!(vd, 3);
+                assert_eq!(rs1, 5);
+                assert_eq!(eew, 32);
+            }
+            _ => panic!("Expected VleV"),
+        }
+    }
+
+    #[test]
+    fn test_decode_vadd_vv() {
+        // vadd.vv v1, v2, v3
+        let raw = (1 << 25) | (2 << 20) | (3 << 15) | (1 << 7) | OP_V;
+        let decoded = RV32V_DECODER.decode(raw);
+        assert!(decoded.is_some());
+        let DecodedInstr { instr, .. } = decoded.unwrap();
+        match instr {
+            RvInstr::VaddVV { vd, vs2, vs1 } => {
+                assert_eq!(vd, 1);
+                assert_eq!(vs2, 2);
+                assert_eq!(vs1, 3);
+            }
+            _ => panic!("Expected VaddVV"),
+        }
+    }
+
+    #[test]
+    fn test_flw_not_claimed() {
+        // FLW（width=010）不属于向量 load，应由 RV32F 解码
+        let raw = 0x0002A007; // flw f0, 0(x5)
+        assert!(RV32V_DECODER.decode(raw).is_none());
+    }
+}
@@ -144,6 +144,85 @@ fn test_decode_illegal() {
     }
 }
 
+#[test]
+fn test_decode_zk_sha256sig0() {
+    let raw = 0x10231293; // sha256sig0 x5, x6
+    let decoded = ZK_DECODER.decode(raw).expect("Zk 解码器应识别 sha256sig0");
+    assert_eq!(decoded.instr, RvInstr::Sha256Sig0 { rd: 5, rs1: 6 });
+}
+
+#[test]
+fn test_decode_zk_pack() {
+    let raw = 0x0820C1B3; // pack x3, x1, x2
+    let decoded = ZK_DECODER.decode(raw).expect("Zk 解码器应识别 pack");
+    assert_eq!(decoded.instr, RvInstr::Pack { rd: 3, rs1: 1, rs2: 2 });
+}
+
+#[test]
+fn test_decode_zk_aes32esmi_bs_field() {
+    let raw = 0xA6C58533; // aes32esmi x10, x11, x12, bs=2
+    let decoded = ZK_DECODER.decode(raw).expect("Zk 解码器应识别 aes32esmi");
+    assert_eq!(
+        decoded.instr,
+        RvInstr::Aes32Esmi {
+            rd: 10,
+            rs1: 11,
+            rs2: 12,
+            bs: 2
+        }
+    );
+}
+
+#[test]
+fn test_zk_no_conflict_with_rv32im() {
+    let config = IsaConfig::new().with_m_extension().with_zk_extension();
+    let conflicts = config.detect_conflicts();
+    assert!(conflicts.is_empty(), "Zk 不应与 RV32IM 冲突: {:?}", conflicts);
+}
+
+#[test]
+fn test_structured_errors_match_on_cause() {
+    use std::sync::Arc;
+
+    static CLASH: [InstrDef; 1] = [InstrDef::new(
+        "CLASH",
+        crate::isa::instr_def::R_TYPE_MASK,
+        crate::isa::instr_def::r_match(0, 0, 0x33),
+        |raw| RvInstr::Illegal { raw },
+    )];
+    static CLASH_DECODER: TableDrivenDecoder =
+        TableDrivenDecoder::new("Clash", &CLASH, Some(&[0x33]), false);
+
+    // opcode 占用：载荷带具体 opcode 与被拒解码器名
+    let mut registry = DecoderRegistry::with_rv32i();
+    let err = registry
+        .register(Arc::new(CLASH_DECODER))
+        .expect_err("不允许重叠的解码器抢占 0x33 应失败");
+    assert!(
+        matches!(err, IsaError::OpcodeOccupied { opcode: 0x33, decoder } if decoder == "Clash"),
+    );
+
+    // 指令冲突：与 RV32I ADD 同编码的自定义定义经 build 报
+    // IsaError::Conflicts，冲突对可编程遍历
+    let err = IsaConfig::new()
+        .with_custom_decoder(
+            IsaExtension::Custom("clash"),
+            Arc::new(CLASH_DECODER),
+            vec![InstrSignature {
+                extension: IsaExtension::Custom("clash"),
+                name: "CLASH",
+                mask: crate::isa::instr_def::R_TYPE_MASK,
+                match_val: crate::isa::instr_def::r_match(0, 0, 0x33),
+            }],
+        )
+        .build()
+        .expect_err("与 ADD 冲突应失败");
+    let IsaError::Conflicts(conflicts) = err else {
+        panic!("应为 Conflicts: {err:?}");
+    };
+    assert!(conflicts.iter().any(|c| c.instr1.name == "ADD" || c.instr2.name == "ADD"));
+}
+
 #[test]
 fn test_decoder_registry() {
     let registry = DecoderRegistry::with_rv32i();
@@ -218,6 +297,154 @@ fn test_decoder_registry_multiple_decoders() {
     assert!(matches!(decoded.instr, RvInstr::Addi { .. }));
 }
 
+#[test]
+fn test_decode_tree_matches_table_scan() {
+    use std::sync::Arc;
+
+    let mut registry = DecoderRegistry::with_rv32i();
+    registry.register(Arc::new(RV32M_DECODER)).unwrap();
+    registry.register(Arc::new(RV32A_DECODER)).unwrap();
+    registry.register(Arc::new(ZICSR_DECODER)).unwrap();
+    registry.register(Arc::new(PRIV_DECODER)).unwrap();
+
+    // 每个定义的 match_val 本身就是匹配该定义的指令字，解码树
+    // 必须给出与各表线性扫描一致的结果
+    let tables = [
+        &RV32I_DECODER,
+        &RV32M_DECODER,
+        &RV32A_DECODER,
+        &ZICSR_DECODER,
+        &PRIV_DECODER,
+    ];
+    for table in tables {
+        for def in table.instrs() {
+            let linear = table.decode(def.match_val).expect("表内定义应能自解码");
+            assert_eq!(
+                registry.decode(def.match_val).instr,
+                linear.instr,
+                "{}",
+                def.name
+            );
+        }
+    }
+
+    // 再用一批伪随机指令字做全路径等价：解码树 == 按注册顺序
+    // 逐解码器线性扫描
+    let scan = |raw: u32| -> RvInstr {
+        for table in tables {
+            if let Some(decoded) = table.decode(raw) {
+                return decoded.instr;
+            }
+        }
+        RvInstr::Illegal { raw }
+    };
+    let mut raw = 0x2A00_0093u32;
+    for _ in 0..65536 {
+        raw = raw.wrapping_mul(0x0001_9660D).wrapping_add(0x3C6E_F35F);
+        assert_eq!(registry.decode(raw).instr, scan(raw), "raw=0x{raw:08X}");
+    }
+
+    // 已占用 opcode 下不存在的 funct7 组合仍然是非法指令
+    let bogus = 0xFE00_0033; // opcode=OP, funct3=0, funct7=0x7F
+    assert!(matches!(registry.decode(bogus).instr, RvInstr::Illegal { raw } if raw == bogus));
+}
+
+#[test]
+fn test_display_abi_names() {
+    // addi a0, zero, 42
+    assert_eq!(decode(0x02A00513).instr.to_string(), "addi a0, zero, 42");
+    // add s0, a1, t0
+    assert_eq!(decode(0x00558433).instr.to_string(), "add s0, a1, t0");
+    // lw ra, 4(sp)
+    assert_eq!(decode(0x00412083).instr.to_string(), "lw ra, 4(sp)");
+    // sw a0, -8(s0)
+    assert_eq!(decode(0xFEA42C23).instr.to_string(), "sw a0, -8(s0)");
+    // lui t0, 0x12345
+    assert_eq!(decode(0x123452B7).instr.to_string(), "lui t0, 0x12345");
+    // beq a0, a1, 8（相对偏移）
+    assert_eq!(decode(0x00B50463).instr.to_string(), "beq a0, a1, 8");
+    // 非法编码渲染为数据
+    assert_eq!(decode(0x00000000).instr.to_string(), ".word 0x00000000");
+}
+
+#[test]
+fn test_display_csr_and_zk() {
+    let registry = IsaConfig::new()
+        .with_zicsr_extension()
+        .with_zk_extension()
+        .build()
+        .unwrap();
+    // csrrw a0, mstatus, a1
+    assert_eq!(registry.decode(0x30059573).instr.to_string(), "csrrw a0, mstatus, a1");
+    // sha256sig0 t0, t1
+    assert_eq!(registry.decode(0x10231293).instr.to_string(), "sha256sig0 t0, t1");
+}
+
+#[test]
+fn test_decode_zicond_and_zicbo() {
+    let registry = IsaConfig::new()
+        .with_zicond_extension()
+        .with_zicbo_extension()
+        .build()
+        .unwrap();
+    // czero.eqz gp, ra, sp
+    assert_eq!(registry.decode(0x0E20D1B3).instr.to_string(), "czero.eqz gp, ra, sp");
+    // cbo.zero (ra)
+    assert_eq!(registry.decode(0x0040A00F).instr.to_string(), "cbo.zero (ra)");
+    // cbo.flush (ra)
+    assert_eq!(registry.decode(0x0020A00F).instr.to_string(), "cbo.flush (ra)");
+    // rd != x0 的 CBO 编码不合法
+    assert!(matches!(registry.decode(0x0040A08F).instr, RvInstr::Illegal { .. }));
+}
+
+#[test]
+fn test_disassemble_range() {
+    use crate::memory::{FlatMemory, Memory};
+
+    let mut mem = FlatMemory::new(1024, 0);
+    mem.store32(0, 0x02A00513).unwrap(); // addi a0, zero, 42
+    mem.store32(4, 0x00A00533).unwrap(); // add a0, zero, a0
+
+    let registry = DecoderRegistry::with_rv32i();
+    let lines = disassemble_range(&registry, &mem, 0, 8);
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], "0x00000000: 02a00513  addi a0, zero, 42");
+    assert_eq!(lines[1], "0x00000004: 00a00533  add a0, zero, a0");
+}
+
+#[test]
+fn test_disassemble_range_with_aliases() {
+    use crate::memory::{FlatMemory, Memory};
+
+    let mut mem = FlatMemory::new(1024, 0);
+    mem.store32(0, 0x00000013).unwrap(); // addi zero, zero, 0
+    mem.store32(4, 0x02A00513).unwrap(); // addi a0, zero, 42
+    mem.store32(8, 0x00050593).unwrap(); // addi a1, a0, 0
+    mem.store32(12, 0x00008067).unwrap(); // jalr zero, 0(ra)
+    mem.store32(16, 0x00050463).unwrap(); // beq a0, zero, +8
+    mem.store32(20, 0x00001097).unwrap(); // auipc ra, 0x1
+    mem.store32(24, 0x008080E7).unwrap(); // jalr ra, 8(ra)
+
+    let registry = DecoderRegistry::with_rv32i();
+
+    // 别名模式：伪指令形式 + call 折叠
+    let lines = disassemble_range_with(&registry, &mem, 0, 28, true);
+    assert_eq!(lines[0], "0x00000000: 00000013  nop");
+    assert_eq!(lines[1], "0x00000004: 02a00513  li a0, 42");
+    assert_eq!(lines[2], "0x00000008: 00050593  mv a1, a0");
+    assert_eq!(lines[3], "0x0000000c: 00008067  ret");
+    assert_eq!(lines[4], "0x00000010: 00050463  beqz a0, 8");
+    // auipc@0x14 + jalr ra：目标 = 0x14 + 0x1000 + 8
+    assert_eq!(lines[6], "0x00000018: 008080e7  call 0x101c");
+
+    // 关闭别名等价于现有 disassemble_range（no-aliases）
+    let plain = disassemble_range_with(&registry, &mem, 0, 28, false);
+    assert_eq!(plain[0], "0x00000000: 00000013  addi zero, zero, 0");
+    assert_eq!(plain[3], "0x0000000c: 00008067  jalr zero, 0(ra)");
+    assert_eq!(plain[6], "0x00000018: 008080e7  jalr ra, 8(ra)");
+}
+
 #[test]
 fn test_custom_fields() {
     let fields = CustomFields::new()
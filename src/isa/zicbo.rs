@@ -0,0 +1,47 @@
+//! Zicbo 扩展（缓存块操作）解码器
+//!
+//! 覆盖 Zicboz 的 CBO.ZERO 与 Zicbom 的 CBO.CLEAN/FLUSH/INVAL。
+//! 四条指令共用 MISC-MEM opcode、funct3 = 0b010、rd = x0，
+//! 由 funct12 区分具体操作，rs1 给出块内任意地址。
+
+use crate::isa::fields::*;
+use crate::isa::instr::RvInstr;
+use crate::isa::instr_def::{InstrDef, TableDrivenDecoder};
+
+/// CBO 指令的 mask（检查 opcode + funct3 + rd + funct12）
+const CBO_MASK: u32 = 0xFFF0_7FFF;
+
+/// 构造 CBO 指令的 match 值
+const fn cbo_match(funct12: u32) -> u32 {
+    (funct12 << 20) | (0b010 << 12) | OP_MISC_MEM
+}
+
+/// Zicbo 指令定义表
+pub static ZICBO_INSTRS: &[InstrDef] = &[
+    InstrDef::new("CBO.INVAL", CBO_MASK, cbo_match(0b000000000000), |raw| {
+        RvInstr::CboInval { rs1: rs1(raw) }
+    }),
+    InstrDef::new("CBO.CLEAN", CBO_MASK, cbo_match(0b000000000001), |raw| {
+        RvInstr::CboClean { rs1: rs1(raw) }
+    }),
+    InstrDef::new("CBO.FLUSH", CBO_MASK, cbo_match(0b000000000010), |raw| {
+        RvInstr::CboFlush { rs1: rs1(raw) }
+    }),
+    InstrDef::new("CBO.ZERO", CBO_MASK, cbo_match(0b000000000100), |raw| {
+        RvInstr::CboZero { rs1: rs1(raw) }
+    }),
+];
+
+/// Zicbo 扩展的 opcode 列表
+pub static ZICBO_OPCODES: [u32; 1] = [OP_MISC_MEM];
+
+/// Zicbo 解码器（基于 TableDrivenDecoder）
+///
+/// CBO 指令与 FENCE/FENCE.I 共享 MISC-MEM opcode，通过
+/// funct3 = 0b010 区分
+pub static ZICBO_DECODER: TableDrivenDecoder = TableDrivenDecoder::new(
+    "Zicbo",
+    ZICBO_INSTRS,
+    Some(&ZICBO_OPCODES),
+    true,
+);
@@ -0,0 +1,30 @@
+//! Zicond 扩展（整数条件操作）解码器
+//!
+//! CZERO.EQZ / CZERO.NEZ：按条件把 rd 清零的无分支选择原语，
+//! 新版工具链在 -O2 下会默认生成。两条指令都落在 OP opcode
+//! 空间内，funct7 = 0b0000111。
+
+use crate::isa::fields::*;
+use crate::isa::instr::RvInstr;
+use crate::isa::instr_def::{InstrDef, TableDrivenDecoder, R_TYPE_MASK, r_match};
+
+/// Zicond 指令定义表
+pub static ZICOND_INSTRS: &[InstrDef] = &[
+    InstrDef::new("CZERO.EQZ", R_TYPE_MASK, r_match(0b0000111, 0b101, OP_REG), |raw| {
+        RvInstr::CzeroEqz { rd: rd(raw), rs1: rs1(raw), rs2: rs2(raw) }
+    }),
+    InstrDef::new("CZERO.NEZ", R_TYPE_MASK, r_match(0b0000111, 0b111, OP_REG), |raw| {
+        RvInstr::CzeroNez { rd: rd(raw), rs1: rs1(raw), rs2: rs2(raw) }
+    }),
+];
+
+/// Zicond 扩展的 opcode 列表
+pub static ZICOND_OPCODES: [u32; 1] = [OP_REG];
+
+/// Zicond 解码器（基于 TableDrivenDecoder）
+pub static ZICOND_DECODER: TableDrivenDecoder = TableDrivenDecoder::new(
+    "Zicond",
+    ZICOND_INSTRS,
+    Some(&ZICOND_OPCODES),
+    true,
+);
@@ -0,0 +1,113 @@
+//! Zk 扩展（标量密码学）解码器
+//!
+//! 覆盖嵌入式密码固件常用的子集：
+//! - Zbkb: PACK / PACKH / BREV8（位操作）
+//! - Zknh: SHA256SIG0/SIG1/SUM0/SUM1（SHA-256 sigma/sum 函数）
+//! - Zkne: AES32ESI / AES32ESMI（AES 加密轮）
+//! - Zknd: AES32DSI / AES32DSMI（AES 解密轮）
+//!
+//! AES 指令的 bs 字段占据 [31:30]，因此匹配掩码只检查 funct5 [29:25]。
+
+use crate::isa::fields::*;
+use crate::isa::instr::RvInstr;
+use crate::isa::instr_def::{InstrDef, TableDrivenDecoder, R_TYPE_MASK, r_match};
+
+/// 一元指令的 mask（检查 opcode + funct3 + funct12）
+const UNARY_MASK: u32 = 0xFFF0707F;
+
+/// 构造一元指令（rs2 位置为 funct12 低 5 位）的 match 值
+const fn unary_match(funct12: u32, funct3: u32, opcode: u32) -> u32 {
+    (funct12 << 20) | (funct3 << 12) | opcode
+}
+
+/// AES32 指令的 mask（检查 opcode + funct3 + funct5，bs [31:30] 不参与匹配）
+const AES32_MASK: u32 = 0x3E00707F;
+
+/// 构造 AES32 指令的 match 值
+const fn aes32_match(funct5: u32) -> u32 {
+    (funct5 << 25) | OP_REG
+}
+
+/// 提取 AES32 指令的字节选择字段 bs [31:30]
+#[inline]
+fn aes_bs(raw: u32) -> u8 {
+    (raw >> 30) as u8
+}
+
+// ========== Zk 指令定义表 ==========
+
+/// Zk 指令定义表
+pub static ZK_INSTRS: &[InstrDef] = &[
+    // Zbkb
+    InstrDef::new("PACK", R_TYPE_MASK, r_match(0b0000100, 0b100, OP_REG), |raw| RvInstr::Pack {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("PACKH", R_TYPE_MASK, r_match(0b0000100, 0b111, OP_REG), |raw| RvInstr::Packh {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+    }),
+    InstrDef::new("BREV8", UNARY_MASK, unary_match(0b011010000111, 0b101, OP_IMM), |raw| {
+        RvInstr::Brev8 { rd: rd(raw), rs1: rs1(raw) }
+    }),
+    // Zknh
+    InstrDef::new("SHA256SUM0", UNARY_MASK, unary_match(0b000100000000, 0b001, OP_IMM), |raw| {
+        RvInstr::Sha256Sum0 { rd: rd(raw), rs1: rs1(raw) }
+    }),
+    InstrDef::new("SHA256SUM1", UNARY_MASK, unary_match(0b000100000001, 0b001, OP_IMM), |raw| {
+        RvInstr::Sha256Sum1 { rd: rd(raw), rs1: rs1(raw) }
+    }),
+    InstrDef::new("SHA256SIG0", UNARY_MASK, unary_match(0b000100000010, 0b001, OP_IMM), |raw| {
+        RvInstr::Sha256Sig0 { rd: rd(raw), rs1: rs1(raw) }
+    }),
+    InstrDef::new("SHA256SIG1", UNARY_MASK, unary_match(0b000100000011, 0b001, OP_IMM), |raw| {
+        RvInstr::Sha256Sig1 { rd: rd(raw), rs1: rs1(raw) }
+    }),
+    // Zkne
+    InstrDef::new("AES32ESI", AES32_MASK, aes32_match(0b10001), |raw| RvInstr::Aes32Esi {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+        bs: aes_bs(raw),
+    }),
+    InstrDef::new("AES32ESMI", AES32_MASK, aes32_match(0b10011), |raw| RvInstr::Aes32Esmi {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+        bs: aes_bs(raw),
+    }),
+    // Zknd
+    InstrDef::new("AES32DSI", AES32_MASK, aes32_match(0b10101), |raw| RvInstr::Aes32Dsi {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+        bs: aes_bs(raw),
+    }),
+    InstrDef::new("AES32DSMI", AES32_MASK, aes32_match(0b10111), |raw| RvInstr::Aes32Dsmi {
+        rd: rd(raw),
+        rs1: rs1(raw),
+        rs2: rs2(raw),
+        bs: aes_bs(raw),
+    }),
+];
+
+/// Zk 扩展的 opcode 列表
+pub static ZK_OPCODES: [u32; 2] = [OP_REG, OP_IMM];
+
+// ========== 解码器实例 ==========
+
+/// Zk 解码器（基于 TableDrivenDecoder）
+///
+/// Zk 指令落在 OP (0b0110011) 与 OP-IMM (0b0010011) opcode 空间内，
+/// 通过 funct7/funct12 与基础指令集区分
+pub static ZK_DECODER: TableDrivenDecoder = TableDrivenDecoder::new(
+    "Zk",
+    ZK_INSTRS,
+    Some(&ZK_OPCODES),
+    true,
+);
+
+/// 兼容性别名
+pub type ZkDecoder = TableDrivenDecoder;
@@ -6,11 +6,42 @@
 //! # 模块结构
 //!
 //! - `isa`: RISC-V ISA 抽象与解码
+//! - `asm`: 内置 RV32IMF 汇编器（测试程序无需手工编码）
 //! - `cpu`: CPU 核心与执行引擎
 //! - `memory`: 内存抽象层
+//! - `cache`: 指令/数据 cache 仿真层（命中/缺失统计）
 //! - `sim_env`: 仿真环境（配置、ELF 加载、初始化）
+//! - `trace`: 指令跟踪子系统
+//! - `guest_io`: 客户机 I/O 辅助（printf 解码等）
+//! - `gpgpu`: Warp/SIMT 执行原型（实验性）
+//! - `syscalls`: ECALL 系统调用仿真（newlib semihosting）
+//! - `semihosting`: RISC-V semihosting 仿真（ebreak 序列）
+//! - `stats`: 逐指令执行统计与直方图报告
+//! - `profile`: 基本块执行计数与分支剖面（callgrind 导出）
+//! - `timing`: 可插拔的周期计时模型（周期数/IPC 报告）
+//! - `devices`: 内存映射外设（UART 等）
+//! - `fuzz`: 确定性指令流模糊测试（架构不变量检查）
+//! - `wasm`: 浏览器侧 JS API（`wasm` feature，C ABI 导出）
+//! - `capi`: SystemC/Verilator 嵌入用的稳定 C API（`capi` feature）
 
+pub mod asm;
+pub mod cache;
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod cpu;
+pub mod devices;
+pub mod events;
+pub mod fuzz;
+pub mod gpgpu;
+pub mod guest_io;
 pub mod isa;
 pub mod memory;
+pub mod profile;
+pub mod semihosting;
 pub mod sim_env;
+pub mod stats;
+pub mod syscalls;
+pub mod timing;
+pub mod trace;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,177 +1,253 @@
-//! allude_sim CLI 示例入口
+//! allude_sim CLI 入口
 //!
-//! 本文件演示如何使用 allude_sim 库进行 RV32I 仿真。
+//! ```text
+//! allude_sim run <program.elf> [选项]
+//!
+//! 选项:
+//!   --isa SPEC         ISA 扩展（如 rv32imf），默认 rv32g
+//!   --mem BASE:SIZE    内存区域（如 0x80000000:1M），默认按 ELF 段推导
+//!   --max-instr N      指令数上限（支持 1e7 这样的科学记数），默认 1e7
+//!   --trace PATH       把指令跟踪写到文件
+//!   --entry SYM        按 ELF 符号名覆盖入口
+//!   --break SYM        按 ELF 符号名设置断点（可重复）
+//!   --verbose SPEC     级别（如 2）或逐子系统规格（如 loader=2,htif=1）
+//!   --signature PATH   运行结束后按 RISCOF 格式写签名区间
+//! ```
+//!
+//! 为兼容旧用法，省略 `run` 子命令、直接给 ELF 路径也可以。
+
+use allude_sim::sim_env::{ElfInfo, IsaExtensions, SimConfig, SimEnv};
+use allude_sim::trace::FileSink;
 
-use allude_sim::cpu::{CpuCore, CpuState};
-use allude_sim::memory::{FlatMemory, Memory};
+const USAGE: &str = "用法: allude_sim run <program.elf> [--isa SPEC] [--mem BASE:SIZE] \
+[--max-instr N] [--trace PATH] [--entry SYM] [--break SYM]... [--verbose SPEC] [--signature PATH]";
 
 fn main() {
-    println!("=== allude_sim: RV32I 单线程 ISA 仿真器 ===\n");
-
-    // 创建 64KB 内存，起始地址为 0
-    let mut mem = FlatMemory::new(64 * 1024, 0);
-
-    // 示例程序：计算 1 + 2 + 3 + ... + 10 = 55
-    // 程序逻辑：
-    //   x1 = sum = 0
-    //   x2 = i = 1
-    //   x3 = limit = 11
-    // loop:
-    //   sum += i
-    //   i++
-    //   if i < limit goto loop
-    //   ecall (结束)
-
-    let program: &[u32] = &[
-        0x00000093, // addi x1, x0, 0      # x1 = sum = 0
-        0x00100113, // addi x2, x0, 1      # x2 = i = 1
-        0x00B00193, // addi x3, x0, 11     # x3 = limit = 11
-        // loop (地址 12):
-        0x002080B3, // add x1, x1, x2      # sum += i
-        0x00110113, // addi x2, x2, 1      # i++
-        0xFE314CE3, // blt x2, x3, -8      # if i < limit goto loop
-        0x00000073, // ecall               # 结束
-    ];
-
-    // 将程序写入内存
-    for (i, &instr) in program.iter().enumerate() {
-        mem
-            .store32((i * 4) as u32, instr)
-            .expect("failed to write demo program into memory");
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("run") {
+        args.remove(0);
     }
+    if args.is_empty() {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+    run_elf_cli(&args);
+}
 
-    println!("程序已加载到内存，计算 1 + 2 + ... + 10");
-    println!();
-
-    // 初始化 CPU，PC 从 0 开始
-    let mut cpu = CpuCore::new(0);
-
-    println!("初始状态:");
-    cpu.dump_regs();
-    println!();
-
-    // 运行程序
-    let (executed, final_state) = cpu.run(&mut mem, 1000);
+/// 解析好的命令行选项
+struct CliOptions {
+    elf_path: String,
+    isa: Option<String>,
+    mem: Option<(u32, usize)>,
+    max_instr: u64,
+    trace_path: Option<String>,
+    entry_symbol: Option<String>,
+    break_symbols: Vec<String>,
+    verbosity_spec: Option<String>,
+    signature_path: Option<String>,
+}
 
-    println!("执行完毕!");
-    println!("执行指令数: {}", executed);
-    println!(
-        "最终状态: {:?}",
-        match final_state {
-            CpuState::Running => "运行中",
-            CpuState::IllegalInstruction(_) => "非法指令",
-            CpuState::WaitForInterrupt => "等待中断 (WFI)",
-            CpuState::Halted => "已停机",
+/// 解析命令行；出错时返回给用户看的错误信息
+fn parse_args(args: &[String]) -> Result<CliOptions, String> {
+    let mut elf_path: Option<String> = None;
+    let mut isa = None;
+    let mut mem = None;
+    let mut max_instr = 10_000_000u64;
+    let mut trace_path = None;
+    let mut entry_symbol = None;
+    let mut break_symbols = Vec::new();
+    let mut verbosity_spec = None;
+    let mut signature_path = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        // 带值选项统一从下一个参数取值
+        let mut value = || {
+            i += 1;
+            args.get(i)
+                .cloned()
+                .ok_or_else(|| format!("选项 {} 缺少参数", arg))
+        };
+        match arg {
+            "--isa" => isa = Some(value()?),
+            "--mem" => mem = Some(parse_mem_spec(&value()?)?),
+            "--max-instr" => max_instr = parse_count(&value()?)?,
+            "--trace" => trace_path = Some(value()?),
+            "--entry" => entry_symbol = Some(value()?),
+            "--break" => break_symbols.push(value()?),
+            "--verbose" => verbosity_spec = Some(value()?),
+            "--signature" => signature_path = Some(value()?),
+            other if other.starts_with("--") => {
+                return Err(format!("未知选项: {}", other));
+            }
+            other => {
+                if elf_path.is_some() {
+                    return Err(format!("多余的位置参数: {}", other));
+                }
+                elf_path = Some(other.to_string());
+            }
         }
-    );
-    println!();
+        i += 1;
+    }
 
-    println!("最终寄存器状态:");
-    cpu.dump_regs();
-    println!();
+    let elf_path = elf_path.ok_or_else(|| "缺少 ELF 路径".to_string())?;
+    Ok(CliOptions {
+        elf_path,
+        isa,
+        mem,
+        max_instr,
+        trace_path,
+        entry_symbol,
+        break_symbols,
+        verbosity_spec,
+        signature_path,
+    })
+}
 
-    // 验证结果
-    let sum = cpu.read_reg(1);
-    let expected = 55; // 1 + 2 + ... + 10 = 55
-    println!("计算结果: x1 = {}", sum);
-    println!("预期结果: {}", expected);
+/// 解析 `BASE:SIZE` 内存规格，BASE 支持 0x 前缀，SIZE 支持 K/M/G 后缀
+fn parse_mem_spec(spec: &str) -> Result<(u32, usize), String> {
+    let (base, size) = spec
+        .split_once(':')
+        .ok_or_else(|| format!("内存规格应为 BASE:SIZE，得到: {}", spec))?;
+    Ok((parse_u32(base)?, parse_size(size)?))
+}
 
-    if sum == expected {
-        println!("✓ 验证通过!");
+/// 解析 32 位整数（0x 前缀为十六进制）
+fn parse_u32(s: &str) -> Result<u32, String> {
+    let result = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16)
     } else {
-        println!("✗ 验证失败!");
-    }
+        s.parse()
+    };
+    result.map_err(|_| format!("无效的地址: {}", s))
+}
 
-    println!();
-    println!("=== 演示 2：斐波那契数列 ===\n");
-    demo_fibonacci();
+/// 解析带 K/M/G 后缀的大小
+fn parse_size(s: &str) -> Result<usize, String> {
+    let (digits, shift) = match s.as_bytes().last() {
+        Some(b'K' | b'k') => (&s[..s.len() - 1], 10),
+        Some(b'M' | b'm') => (&s[..s.len() - 1], 20),
+        Some(b'G' | b'g') => (&s[..s.len() - 1], 30),
+        _ => (s, 0),
+    };
+    let value: usize = digits.parse().map_err(|_| format!("无效的大小: {}", s))?;
+    value
+        .checked_shl(shift)
+        .ok_or_else(|| format!("大小溢出: {}", s))
 }
 
-/// 演示计算斐波那契数列
-fn demo_fibonacci() {
-    let mut mem = FlatMemory::new(64 * 1024, 0);
-
-    // 计算第 10 个斐波那契数 (F(10) = 55)
-    // F(0)=0, F(1)=1, F(2)=1, F(3)=2, F(4)=3, F(5)=5, F(6)=8, F(7)=13, F(8)=21, F(9)=34, F(10)=55
-    //
-    // 程序逻辑：
-    //   x1 = a = 0      (F(0))
-    //   x2 = b = 1      (F(1))
-    //   x3 = i = 1      (从 1 开始计数)
-    //   x4 = n = 10     (目标索引)
-    // loop:
-    //   if i >= n goto done   # 当 i=10 时退出，此时 x2 = F(10)
-    //   x5 = a + b            # F(i+1)
-    //   a = b                 # a = F(i)
-    //   b = x5                # b = F(i+1)
-    //   i++
-    //   goto loop
-    // done:
-    //   ecall (x2 包含 F(n))
-
-    let program: &[u32] = &[
-        0x00000093, // 0:  addi x1, x0, 0      # a = F(0) = 0
-        0x00100113, // 4:  addi x2, x0, 1      # b = F(1) = 1
-        0x00100193, // 8:  addi x3, x0, 1      # i = 1
-        0x00A00213, // 12: addi x4, x0, 10     # n = 10
-        // loop (地址 16):
-        0x0041DC63, // 16: bge x3, x4, 24      # if i >= n goto done (PC + 24 = 40)
-        0x002082B3, // 20: add x5, x1, x2      # temp = a + b
-        0x00010093, // 24: addi x1, x2, 0      # a = b
-        0x00028113, // 28: addi x2, x5, 0      # b = temp
-        0x00118193, // 32: addi x3, x3, 1      # i++
-        0xFEC006E3, // 36: beq x0, x0, -20     # goto loop (PC - 20 = 16)
-        // done (地址 40):
-        0x00000073, // 40: ecall
-    ];
-
-    // 将程序写入内存
-    for (i, &instr) in program.iter().enumerate() {
-        mem
-            .store32((i * 4) as u32, instr)
-            .expect("failed to write fibonacci program into memory");
+/// 解析指令数：普通整数或科学记数（如 1e7）
+fn parse_count(s: &str) -> Result<u64, String> {
+    if let Ok(n) = s.parse::<u64>() {
+        return Ok(n);
     }
+    match s.parse::<f64>() {
+        Ok(f) if f >= 0.0 && f.fract() == 0.0 && f <= u64::MAX as f64 => Ok(f as u64),
+        _ => Err(format!("无效的指令数: {}", s)),
+    }
+}
 
-    let mut cpu = CpuCore::new(0);
-
-    println!("程序已加载到内存，计算 F(10) (斐波那契数列)");
-    println!();
-
-    let (executed, final_state) = cpu.run(&mut mem, 1000);
+/// ELF 运行模式：加载 ELF 并运行
+fn run_elf_cli(args: &[String]) {
+    let opts = match parse_args(args) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // 未显式给 --mem 时按 ELF 段推导内存布局
+    let (mem_base, mem_size) = match opts.mem {
+        Some(region) => region,
+        None => {
+            let elf = match ElfInfo::parse(&opts.elf_path) {
+                Ok(elf) => elf,
+                Err(e) => {
+                    eprintln!("无法解析 {}: {}", opts.elf_path, e);
+                    std::process::exit(1);
+                }
+            };
+            let Some((min_addr, max_addr)) = elf.address_range() else {
+                eprintln!("{} 没有可加载的程序段", opts.elf_path);
+                std::process::exit(1);
+            };
+            let size = (((max_addr - min_addr + 0xFFF) & !0xFFF) as usize).max(64 * 1024);
+            (min_addr, size)
+        }
+    };
+
+    let extensions = match &opts.isa {
+        Some(spec) => match IsaExtensions::from_str(spec) {
+            Ok(ext) => ext,
+            Err(e) => {
+                eprintln!("无效的 --isa 规格: {}", e);
+                std::process::exit(2);
+            }
+        },
+        None => IsaExtensions::rv32g(),
+    };
+
+    let mut config = SimConfig::new()
+        .with_elf_path(&opts.elf_path)
+        .with_memory("ram", mem_base, mem_size)
+        .with_extensions(extensions)
+        .with_max_instructions(opts.max_instr);
+
+    if let Some(sym) = opts.entry_symbol {
+        config = config.with_entry_symbol(sym);
+    }
+    for sym in opts.break_symbols {
+        config = config.with_break_symbol(sym);
+    }
+    if let Some(spec) = &opts.verbosity_spec {
+        config = match config.with_verbosity_spec(spec) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("无效的 --verbose 规格: {}", e);
+                std::process::exit(2);
+            }
+        };
+    }
 
-    println!("执行完毕!");
-    println!("执行指令数: {}", executed);
-    println!(
-        "最终状态: {:?}",
-        match final_state {
-            CpuState::Running => "运行中",
-            CpuState::IllegalInstruction(raw) => {
-                println!("非法指令: 0x{:08x}", raw);
-                "非法指令"
+    let mut env = match SimEnv::from_config(config) {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("创建仿真环境失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(path) = &opts.trace_path {
+        match FileSink::create(path) {
+            Ok(sink) => env.cpu.set_trace_sink(Box::new(sink)),
+            Err(e) => {
+                eprintln!("无法创建跟踪文件 {}: {}", path, e);
+                std::process::exit(1);
             }
-            CpuState::WaitForInterrupt => "等待中断 (WFI)",
-            CpuState::Halted => "已停机",
         }
-    );
-    println!();
+    }
 
-    println!("最终寄存器状态:");
-    cpu.dump_regs();
+    let (executed, state) = env.run_until_halt();
 
-    // F(10) = 55 应该在 x2 (b) 中
-    let result = cpu.read_reg(2);
-    let expected = 55;
-    println!();
-    println!("计算结果: x2 = {} (F(10))", result);
-    println!("预期结果: {}", expected);
+    println!("执行指令数: {}", executed);
+    println!("最终状态: {:?}  PC: 0x{:08x}", state, env.cpu.pc());
+    if let Some(reason) = env.stop_reason {
+        println!("停止原因: {:?}", reason);
+    }
 
-    if result == expected {
-        println!("✓ 验证通过!");
-    } else {
-        println!("✗ 验证失败!");
-        // 调试信息
-        println!("调试信息: a(x1)={}, b(x2)={}, i(x3)={}, n(x4)={}", 
-                 cpu.read_reg(1), cpu.read_reg(2), cpu.read_reg(3), cpu.read_reg(4));
+    if let Some(path) = opts.signature_path {
+        if let Err(e) = env.write_signature(&path) {
+            eprintln!("写签名文件 {} 失败: {}", path, e);
+            std::process::exit(1);
+        }
+        println!("签名已写入: {}", path);
     }
-}
 
+    // 把客体的退出状态带回 shell
+    if let Some(code) = env.exit_code() {
+        std::process::exit(code);
+    }
+}
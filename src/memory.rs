@@ -1,7 +1,12 @@
 //! 内存抽象层
 //!
-//! 本模块定义了内存访问的统一接口 `Memory` trait，
-//! 以及用于功能验证的简单线性内存实现 `FlatMemory`。
+//! 本模块定义了内存访问的统一接口 `Memory` trait，用于功能验证
+//! 的简单线性内存实现 `FlatMemory`，以及按 4KB 页懒分配的
+//! `SparseMemory`（大地址空间用），以及可在多 hart / 宿主线程
+//! 间共享的 `SharedMemory`。仿真环境通过 `GuestMemory` 在平坦
+//! 与稀疏后端间选择。
+
+use std::collections::HashMap;
 
 /// 访存粒度
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -28,6 +33,8 @@ pub enum MemError {
     Unaligned { addr: u32, access: AccessSize },
     /// 地址越界（未映射到当前内存区域）
     OutOfRange { addr: u32, access: AccessSize, base: u32, size: usize },
+    /// 虚拟地址翻译失败（Sv32 页表遍历未命中或权限不符）
+    PageFault { addr: u32 },
 }
 
 impl std::fmt::Display for MemError {
@@ -46,6 +53,9 @@ impl std::fmt::Display for MemError {
                     base.wrapping_add(*size as u32)
                 )
             }
+            MemError::PageFault { addr } => {
+                write!(f, "Page fault at 0x{:08x}", addr)
+            }
         }
     }
 }
@@ -126,7 +136,7 @@ impl FlatMemory {
         self.data.len()
     }
 
-    fn ensure_aligned(addr: u32, access: AccessSize) -> MemResult<()> {
+    pub(crate) fn ensure_aligned(addr: u32, access: AccessSize) -> MemResult<()> {
         match access {
             AccessSize::Byte => Ok(()),
             AccessSize::Half if addr.is_multiple_of(2) => Ok(()),
@@ -267,10 +277,551 @@ impl Memory for FlatMemory {
     }
 }
 
+/// 稀疏内存的页大小（4 KB）
+const SPARSE_PAGE_SIZE: usize = 4096;
+
+/// 按 4KB 页懒分配的稀疏内存
+///
+/// 语义与 [`FlatMemory`] 一致：同样的区间 bounds 检查和对齐
+/// 错误，未写过的地址读出 0。区别只在宿主侧的占用——仅真正
+/// 被写入的页才分配，适合仿真 GB 级地址空间而不用一次性
+/// `Vec<u8>` 拉满。
+pub struct SparseMemory {
+    /// 页号（区间内相对地址 / 页大小）到页数据的映射
+    pages: HashMap<u32, Box<[u8; SPARSE_PAGE_SIZE]>>,
+    base_addr: u32,
+    size: usize,
+}
+
+impl SparseMemory {
+    /// 创建一个指定大小的稀疏内存区域（不做任何预分配）
+    pub fn new(size: usize, base_addr: u32) -> Self {
+        SparseMemory {
+            pages: HashMap::new(),
+            base_addr,
+            size,
+        }
+    }
+
+    /// 获取内存的基地址
+    pub fn base_addr(&self) -> u32 {
+        self.base_addr
+    }
+
+    /// 获取内存的大小（可寻址区间，非宿主占用）
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// 已分配的页数（宿主占用约为页数 × 4KB）
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn bounds_check(&self, addr: u32, len: usize, access: AccessSize) -> MemResult<usize> {
+        let relative = addr
+            .checked_sub(self.base_addr)
+            .ok_or(MemError::OutOfRange {
+                addr,
+                access,
+                base: self.base_addr,
+                size: self.size,
+            })? as usize;
+
+        let end = relative.checked_add(len).ok_or(MemError::OutOfRange {
+            addr,
+            access,
+            base: self.base_addr,
+            size: self.size,
+        })?;
+
+        if end > self.size {
+            return Err(MemError::OutOfRange {
+                addr,
+                access,
+                base: self.base_addr,
+                size: self.size,
+            });
+        }
+
+        Ok(relative)
+    }
+
+    /// 读区间内相对地址处的字节（页未分配则为 0）
+    fn read_rel(&self, rel: usize) -> u8 {
+        let page = (rel / SPARSE_PAGE_SIZE) as u32;
+        self.pages
+            .get(&page)
+            .map_or(0, |p| p[rel % SPARSE_PAGE_SIZE])
+    }
+
+    /// 写区间内相对地址处的字节（按需分配页）
+    fn write_rel(&mut self, rel: usize, value: u8) {
+        let page = (rel / SPARSE_PAGE_SIZE) as u32;
+        let data = self
+            .pages
+            .entry(page)
+            .or_insert_with(|| Box::new([0; SPARSE_PAGE_SIZE]));
+        data[rel % SPARSE_PAGE_SIZE] = value;
+    }
+
+    /// 批量写入数据到内存
+    pub fn write_bytes(&mut self, addr: u32, data: &[u8]) -> MemResult<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let start = self.bounds_check(addr, data.len(), AccessSize::Byte)?;
+        for (i, &byte) in data.iter().enumerate() {
+            self.write_rel(start + i, byte);
+        }
+        Ok(())
+    }
+
+    /// 批量读取数据
+    pub fn read_bytes(&self, addr: u32, len: usize) -> MemResult<Vec<u8>> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let start = self.bounds_check(addr, len, AccessSize::Byte)?;
+        Ok((0..len).map(|i| self.read_rel(start + i)).collect())
+    }
+
+    /// 将指定范围填充为固定字节
+    ///
+    /// 填 0 且覆盖整页的部分直接释放页，保持稀疏性（复位大区间
+    /// 的开销与已分配页数成正比，而非区间长度）。
+    pub fn fill(&mut self, addr: u32, len: usize, value: u8) -> MemResult<()> {
+        if len == 0 {
+            return Ok(());
+        }
+        let start = self.bounds_check(addr, len, AccessSize::Byte)?;
+        let end = start + len;
+        let first_page = start / SPARSE_PAGE_SIZE;
+        let last_page = (end - 1) / SPARSE_PAGE_SIZE;
+        for page in first_page..=last_page {
+            let page_start = page * SPARSE_PAGE_SIZE;
+            let lo = start.max(page_start) - page_start;
+            let hi = end.min(page_start + SPARSE_PAGE_SIZE) - page_start;
+            if value == 0 && lo == 0 && hi == SPARSE_PAGE_SIZE {
+                self.pages.remove(&(page as u32));
+            } else if let Some(data) = self.pages.get_mut(&(page as u32)) {
+                data[lo..hi].fill(value);
+            } else if value != 0 {
+                let data = self
+                    .pages
+                    .entry(page as u32)
+                    .or_insert_with(|| Box::new([0; SPARSE_PAGE_SIZE]));
+                data[lo..hi].fill(value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Memory for SparseMemory {
+    fn load8(&self, addr: u32) -> MemResult<u8> {
+        let rel = self.bounds_check(addr, 1, AccessSize::Byte)?;
+        Ok(self.read_rel(rel))
+    }
+
+    fn load16(&self, addr: u32) -> MemResult<u16> {
+        FlatMemory::ensure_aligned(addr, AccessSize::Half)?;
+        let rel = self.bounds_check(addr, 2, AccessSize::Half)?;
+        Ok(u16::from_le_bytes([self.read_rel(rel), self.read_rel(rel + 1)]))
+    }
+
+    fn load32(&self, addr: u32) -> MemResult<u32> {
+        FlatMemory::ensure_aligned(addr, AccessSize::Word)?;
+        let rel = self.bounds_check(addr, 4, AccessSize::Word)?;
+        Ok(u32::from_le_bytes([
+            self.read_rel(rel),
+            self.read_rel(rel + 1),
+            self.read_rel(rel + 2),
+            self.read_rel(rel + 3),
+        ]))
+    }
+
+    fn store8(&mut self, addr: u32, value: u8) -> MemResult<()> {
+        let rel = self.bounds_check(addr, 1, AccessSize::Byte)?;
+        self.write_rel(rel, value);
+        Ok(())
+    }
+
+    fn store16(&mut self, addr: u32, value: u16) -> MemResult<()> {
+        FlatMemory::ensure_aligned(addr, AccessSize::Half)?;
+        let rel = self.bounds_check(addr, 2, AccessSize::Half)?;
+        for (i, byte) in value.to_le_bytes().into_iter().enumerate() {
+            self.write_rel(rel + i, byte);
+        }
+        Ok(())
+    }
+
+    fn store32(&mut self, addr: u32, value: u32) -> MemResult<()> {
+        FlatMemory::ensure_aligned(addr, AccessSize::Word)?;
+        let rel = self.bounds_check(addr, 4, AccessSize::Word)?;
+        for (i, byte) in value.to_le_bytes().into_iter().enumerate() {
+            self.write_rel(rel + i, byte);
+        }
+        Ok(())
+    }
+}
+
+/// 可在多个持有者（多 hart 或宿主线程）之间共享的内存
+///
+/// 内部用 `Arc<RwLock<FlatMemory>>` 包装：克隆出的每个句柄指向
+/// 同一块存储，单次 8/16/32 位访问持锁完成，因此天然具备字粒度
+/// 原子性——并发读写不会观察到半新半旧的字。这是多 hart 子系统
+/// 和 A 扩展 AMO 的地基；AMO 的读改写序列仍需调用方额外串行化
+/// （如通过 [`SharedMemory::with_locked`]）。
+#[derive(Clone)]
+pub struct SharedMemory {
+    inner: std::sync::Arc<std::sync::RwLock<FlatMemory>>,
+}
+
+impl SharedMemory {
+    /// 创建一个指定大小的共享内存区域
+    pub fn new(size: usize, base_addr: u32) -> Self {
+        SharedMemory {
+            inner: std::sync::Arc::new(std::sync::RwLock::new(FlatMemory::new(size, base_addr))),
+        }
+    }
+
+    /// 获取内存的基地址
+    pub fn base_addr(&self) -> u32 {
+        self.inner.read().unwrap().base_addr()
+    }
+
+    /// 获取内存的大小
+    pub fn size(&self) -> usize {
+        self.inner.read().unwrap().size()
+    }
+
+    /// 持写锁执行一段读改写序列（AMO、LR/SC 等需要的粒度）
+    ///
+    /// 闭包运行期间其它句柄的访问被阻塞，整段序列对外原子。
+    pub fn with_locked<R>(&self, f: impl FnOnce(&mut FlatMemory) -> R) -> R {
+        f(&mut self.inner.write().unwrap())
+    }
+
+    /// 批量写入数据到内存
+    pub fn write_bytes(&self, addr: u32, data: &[u8]) -> MemResult<()> {
+        self.inner.write().unwrap().write_bytes(addr, data)
+    }
+
+    /// 批量读取数据
+    pub fn read_bytes(&self, addr: u32, len: usize) -> MemResult<Vec<u8>> {
+        self.inner.read().unwrap().read_bytes(addr, len)
+    }
+
+    /// 将指定范围填充为固定字节
+    pub fn fill(&self, addr: u32, len: usize, value: u8) -> MemResult<()> {
+        self.inner.write().unwrap().fill(addr, len, value)
+    }
+}
+
+impl Memory for SharedMemory {
+    fn load8(&self, addr: u32) -> MemResult<u8> {
+        self.inner.read().unwrap().load8(addr)
+    }
+
+    fn load16(&self, addr: u32) -> MemResult<u16> {
+        self.inner.read().unwrap().load16(addr)
+    }
+
+    fn load32(&self, addr: u32) -> MemResult<u32> {
+        self.inner.read().unwrap().load32(addr)
+    }
+
+    fn store8(&mut self, addr: u32, value: u8) -> MemResult<()> {
+        self.inner.write().unwrap().store8(addr, value)
+    }
+
+    fn store16(&mut self, addr: u32, value: u16) -> MemResult<()> {
+        self.inner.write().unwrap().store16(addr, value)
+    }
+
+    fn store32(&mut self, addr: u32, value: u32) -> MemResult<()> {
+        self.inner.write().unwrap().store32(addr, value)
+    }
+}
+
+/// 客体 RAM：平坦或稀疏后端，访问语义一致
+///
+/// [`crate::sim_env::SimEnv`] 按配置在两种后端间选择，本枚举把
+/// `Memory` 接口和批量访问接口统一转发到所选后端。
+pub enum GuestMemory {
+    Flat(FlatMemory),
+    Sparse(SparseMemory),
+}
+
+impl GuestMemory {
+    /// 创建平坦后端（整个区间预分配）
+    pub fn flat(size: usize, base_addr: u32) -> Self {
+        GuestMemory::Flat(FlatMemory::new(size, base_addr))
+    }
+
+    /// 创建稀疏后端（4KB 页懒分配）
+    pub fn sparse(size: usize, base_addr: u32) -> Self {
+        GuestMemory::Sparse(SparseMemory::new(size, base_addr))
+    }
+
+    /// 获取内存的基地址
+    pub fn base_addr(&self) -> u32 {
+        match self {
+            GuestMemory::Flat(m) => m.base_addr(),
+            GuestMemory::Sparse(m) => m.base_addr(),
+        }
+    }
+
+    /// 获取内存的大小
+    pub fn size(&self) -> usize {
+        match self {
+            GuestMemory::Flat(m) => m.size(),
+            GuestMemory::Sparse(m) => m.size(),
+        }
+    }
+
+    /// 批量写入数据到内存
+    pub fn write_bytes(&mut self, addr: u32, data: &[u8]) -> MemResult<()> {
+        match self {
+            GuestMemory::Flat(m) => m.write_bytes(addr, data),
+            GuestMemory::Sparse(m) => m.write_bytes(addr, data),
+        }
+    }
+
+    /// 批量读取数据
+    pub fn read_bytes(&self, addr: u32, len: usize) -> MemResult<Vec<u8>> {
+        match self {
+            GuestMemory::Flat(m) => m.read_bytes(addr, len),
+            GuestMemory::Sparse(m) => m.read_bytes(addr, len),
+        }
+    }
+
+    /// 将指定范围填充为固定字节
+    pub fn fill(&mut self, addr: u32, len: usize, value: u8) -> MemResult<()> {
+        match self {
+            GuestMemory::Flat(m) => m.fill(addr, len, value),
+            GuestMemory::Sparse(m) => m.fill(addr, len, value),
+        }
+    }
+}
+
+impl Memory for GuestMemory {
+    fn load8(&self, addr: u32) -> MemResult<u8> {
+        match self {
+            GuestMemory::Flat(m) => m.load8(addr),
+            GuestMemory::Sparse(m) => m.load8(addr),
+        }
+    }
+
+    fn load16(&self, addr: u32) -> MemResult<u16> {
+        match self {
+            GuestMemory::Flat(m) => m.load16(addr),
+            GuestMemory::Sparse(m) => m.load16(addr),
+        }
+    }
+
+    fn load32(&self, addr: u32) -> MemResult<u32> {
+        match self {
+            GuestMemory::Flat(m) => m.load32(addr),
+            GuestMemory::Sparse(m) => m.load32(addr),
+        }
+    }
+
+    fn store8(&mut self, addr: u32, value: u8) -> MemResult<()> {
+        match self {
+            GuestMemory::Flat(m) => m.store8(addr, value),
+            GuestMemory::Sparse(m) => m.store8(addr, value),
+        }
+    }
+
+    fn store16(&mut self, addr: u32, value: u16) -> MemResult<()> {
+        match self {
+            GuestMemory::Flat(m) => m.store16(addr, value),
+            GuestMemory::Sparse(m) => m.store16(addr, value),
+        }
+    }
+
+    fn store32(&mut self, addr: u32, value: u32) -> MemResult<()> {
+        match self {
+            GuestMemory::Flat(m) => m.store32(addr, value),
+            GuestMemory::Sparse(m) => m.store32(addr, value),
+        }
+    }
+}
+
+/// 把 16/32 位数据访问按字节交换的包装层
+///
+/// 包在任意 `Memory` 外面即得到大端数据视图：load 读出后交换
+/// 字节序，store 写入前交换，单字节访问不受影响。CPU 的取指
+/// 不经过该层（RISC-V 的指令编码始终是小端，mstatus.MBE 一类
+/// 的大端模式只作用于数据访问），见 [`crate::cpu::CpuBuilder::with_big_endian`]。
+pub struct BigEndianMemory<'a> {
+    inner: &'a mut dyn Memory,
+}
+
+impl<'a> BigEndianMemory<'a> {
+    /// 包装 `inner`，数据访问呈现大端字节序
+    pub fn new(inner: &'a mut dyn Memory) -> Self {
+        Self { inner }
+    }
+}
+
+impl Memory for BigEndianMemory<'_> {
+    fn load8(&self, addr: u32) -> MemResult<u8> {
+        self.inner.load8(addr)
+    }
+
+    fn load16(&self, addr: u32) -> MemResult<u16> {
+        Ok(self.inner.load16(addr)?.swap_bytes())
+    }
+
+    fn load32(&self, addr: u32) -> MemResult<u32> {
+        Ok(self.inner.load32(addr)?.swap_bytes())
+    }
+
+    fn store8(&mut self, addr: u32, value: u8) -> MemResult<()> {
+        self.inner.store8(addr, value)
+    }
+
+    fn store16(&mut self, addr: u32, value: u16) -> MemResult<()> {
+        self.inner.store16(addr, value.swap_bytes())
+    }
+
+    fn store32(&mut self, addr: u32, value: u32) -> MemResult<()> {
+        self.inner.store32(addr, value.swap_bytes())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_big_endian_wrapper_swaps_data() {
+        let mut flat = FlatMemory::new(64, 0);
+        {
+            let mut be = BigEndianMemory::new(&mut flat);
+            be.store32(0, 0x1122_3344).unwrap();
+            be.store16(4, 0xAABB).unwrap();
+            // 经包装层读回往返一致，单字节访问直通
+            assert_eq!(be.load32(0).unwrap(), 0x1122_3344);
+            assert_eq!(be.load16(4).unwrap(), 0xAABB);
+            assert_eq!(be.load8(0).unwrap(), 0x11);
+        }
+        // 底层字节序为大端
+        assert_eq!(flat.load32(0).unwrap(), 0x4433_2211);
+        assert_eq!(flat.load16(4).unwrap(), 0xBBAA);
+    }
+
+    #[test]
+    fn test_shared_memory_clones_see_same_storage() {
+        let shared = SharedMemory::new(1024, 0);
+        let mut writer = shared.clone();
+        let reader = shared.clone();
+
+        writer.store32(0x10, 0xDEAD_BEEF).unwrap();
+        assert_eq!(reader.load32(0x10).unwrap(), 0xDEAD_BEEF);
+
+        // 两个 CPU 执行各自的程序段，经同一句柄的存储互相可见
+        shared.with_locked(|mem| {
+            mem.store8(0x20, 0x7F).unwrap();
+        });
+        assert_eq!(writer.load8(0x20).unwrap(), 0x7F);
+    }
+
+    #[test]
+    fn test_shared_memory_word_atomicity_across_threads() {
+        let shared = SharedMemory::new(64, 0);
+        let mut a = shared.clone();
+        let mut b = shared.clone();
+
+        // 两个线程反复写互补的 32 位模式；字粒度原子性保证读者
+        // 永远看不到混合字节
+        let t1 = std::thread::spawn(move || {
+            for _ in 0..2000 {
+                a.store32(0, 0xAAAA_AAAA).unwrap();
+            }
+        });
+        let t2 = std::thread::spawn(move || {
+            for _ in 0..2000 {
+                b.store32(0, 0x5555_5555).unwrap();
+            }
+        });
+        for _ in 0..2000 {
+            let word = shared.load32(0).unwrap();
+            assert!(
+                word == 0 || word == 0xAAAA_AAAA || word == 0x5555_5555,
+                "观察到撕裂的字: {word:#010x}"
+            );
+        }
+        t1.join().unwrap();
+        t2.join().unwrap();
+    }
+
+    #[test]
+    fn test_sparse_memory_lazy_allocation() {
+        // 2 GB 地址空间，创建时不分配任何页
+        let mut mem = SparseMemory::new(0x8000_0000, 0);
+        assert_eq!(mem.page_count(), 0);
+
+        // 未写过的地址读出 0
+        assert_eq!(mem.load32(0x4000_0000).unwrap(), 0);
+        assert_eq!(mem.load8(0x7FFF_FFFF).unwrap(), 0);
+        assert_eq!(mem.page_count(), 0, "只读不触发分配");
+
+        // 写入才按页分配；同页多次写不增加页数
+        mem.store32(0x1000, 0x1234_5678).unwrap();
+        mem.store8(0x1FFF, 0xAA).unwrap();
+        assert_eq!(mem.page_count(), 1);
+        mem.store16(0x4000_0000, 0xBEEF).unwrap();
+        assert_eq!(mem.page_count(), 2);
+
+        assert_eq!(mem.load32(0x1000).unwrap(), 0x1234_5678);
+        assert_eq!(mem.load16(0x4000_0000).unwrap(), 0xBEEF);
+    }
+
+    #[test]
+    fn test_sparse_memory_same_errors_as_flat() {
+        let mut sparse = SparseMemory::new(1024, 0x100);
+        let mut flat = FlatMemory::new(1024, 0x100);
+
+        // 越界与非对齐错误和平坦后端逐项一致
+        assert_eq!(sparse.load32(0x00).unwrap_err(), flat.load32(0x00).unwrap_err());
+        assert_eq!(sparse.load32(0x500).unwrap_err(), flat.load32(0x500).unwrap_err());
+        assert_eq!(sparse.load32(0x102).unwrap_err(), flat.load32(0x102).unwrap_err());
+        assert_eq!(sparse.load16(0x101).unwrap_err(), flat.load16(0x101).unwrap_err());
+        assert_eq!(
+            sparse.store32(0x4FE, 0).unwrap_err(),
+            flat.store32(0x4FE, 0).unwrap_err()
+        );
+        assert_eq!(
+            sparse.read_bytes(0x4F0, 32).unwrap_err(),
+            flat.read_bytes(0x4F0, 32).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_sparse_memory_bulk_and_fill() {
+        let mut mem = SparseMemory::new(0x10_0000, 0);
+
+        // 跨页批量写读
+        let data: Vec<u8> = (0..=255).cycle().take(8192 + 16).collect();
+        mem.write_bytes(0x0FF8, &data).unwrap();
+        assert_eq!(mem.read_bytes(0x0FF8, data.len()).unwrap(), data);
+        assert_eq!(mem.page_count(), 4);
+
+        // 填 0 覆盖整页的部分直接释放页
+        mem.fill(0, 0x10_0000, 0).unwrap();
+        assert_eq!(mem.page_count(), 0);
+        assert_eq!(mem.load32(0x1000).unwrap(), 0);
+
+        // 非零填充正常生效
+        mem.fill(0x2000, 8, 0xCC).unwrap();
+        assert_eq!(mem.load8(0x2007).unwrap(), 0xCC);
+        assert_eq!(mem.load8(0x2008).unwrap(), 0);
+    }
+
     #[test]
     fn test_flat_memory_basic() {
         let mut mem = FlatMemory::new(1024, 0);
@@ -0,0 +1,199 @@
+//! 基本块执行计数与分支剖面
+//!
+//! 在线检测基本块边界（任何控制转移的目标开启新块）并统计每块的
+//! 执行次数，同时按分支 PC 记录 taken/not-taken 方向。通过
+//! `SimConfig::with_profile` 启用，运行结束后由 `SimEnv::profile`
+//! 读取；[`BlockProfiler::write_text`] 输出可读报告，
+//! [`BlockProfiler::write_callgrind`] 输出 callgrind 兼容格式，
+//! 可直接喂给 kcachegrind 等工具可视化——客体内核的优化从热块
+//! 开始。
+//!
+//! 与 [`crate::stats`]（按指令类别聚合）互补，本模块回答的是
+//! "时间花在哪段代码上"。
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::isa::RvInstr;
+use crate::stats::InstrClass;
+
+/// 单个基本块的剖面数据
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// 块内最后一条指令的 PC（含）
+    pub end: u32,
+    /// 完整执行次数
+    pub count: u64,
+}
+
+/// 单个分支指令的方向统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BranchStat {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+/// 基本块与分支方向剖面器
+///
+/// 块按首条指令的 PC（leader）标识：执行从某个 leader 进入、到
+/// 下一次控制转移（分支/跳转/trap 改写 PC）为止算一次完整执行。
+/// 同一段代码被不同目标切入时会以不同 leader 分别计数，这与
+/// 在线检测的精度一致，足以定位热块。
+#[derive(Debug, Default)]
+pub struct BlockProfiler {
+    /// 正在执行的块的 leader（控制转移后重置为新目标）
+    current_start: Option<u32>,
+    /// leader -> 块信息
+    blocks: BTreeMap<u32, BlockInfo>,
+    /// 分支 PC -> 方向统计
+    branches: BTreeMap<u32, BranchStat>,
+}
+
+impl BlockProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一条已退休的指令
+    ///
+    /// `pc` 为指令地址，`next_pc` 为执行后的 PC（跳转目标或
+    /// pc + 4），由 CPU 在每条指令退休后调用。
+    pub fn record(&mut self, pc: u32, next_pc: u32, instr: &RvInstr) {
+        let start = *self.current_start.get_or_insert(pc);
+        let class = InstrClass::of(instr);
+        let transferred = next_pc != pc.wrapping_add(4);
+
+        if class == InstrClass::Branch {
+            let stat = self.branches.entry(pc).or_default();
+            if transferred {
+                stat.taken += 1;
+            } else {
+                stat.not_taken += 1;
+            }
+        }
+
+        // 分支/跳转即便 fall-through 也终结当前块（目标是潜在
+        // leader）；trap 等非显式转移按实际 PC 变化判断
+        if transferred || matches!(class, InstrClass::Branch | InstrClass::Jump) {
+            let info = self.blocks.entry(start).or_default();
+            info.end = pc;
+            info.count += 1;
+            self.current_start = Some(next_pc);
+        }
+    }
+
+    /// 指定 leader 的块信息（没执行过时为 None）
+    pub fn block(&self, start: u32) -> Option<BlockInfo> {
+        self.blocks.get(&start).copied()
+    }
+
+    /// 指定分支 PC 的方向统计
+    pub fn branch(&self, pc: u32) -> Option<BranchStat> {
+        self.branches.get(&pc).copied()
+    }
+
+    /// 所有块，按执行次数降序
+    pub fn hottest_blocks(&self) -> Vec<(u32, BlockInfo)> {
+        let mut blocks: Vec<_> = self.blocks.iter().map(|(&s, &i)| (s, i)).collect();
+        blocks.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(&b.0)));
+        blocks
+    }
+
+    /// 写可读的文本报告：热块表 + 分支方向表
+    pub fn write_text(&self, w: &mut dyn Write) -> io::Result<()> {
+        writeln!(w, "=== 基本块剖面 ===")?;
+        writeln!(w, "{:<22} {:>12}", "块 [起-止]", "次数")?;
+        for (start, info) in self.hottest_blocks() {
+            writeln!(
+                w,
+                "0x{:08x}-0x{:08x} {:>12}",
+                start, info.end, info.count
+            )?;
+        }
+        writeln!(w)?;
+        writeln!(w, "=== 分支方向 ===")?;
+        writeln!(w, "{:<12} {:>10} {:>10}", "PC", "taken", "not-taken")?;
+        for (pc, stat) in &self.branches {
+            writeln!(w, "0x{:08x} {:>10} {:>10}", pc, stat.taken, stat.not_taken)?;
+        }
+        Ok(())
+    }
+
+    /// 写 callgrind 兼容格式（每块一个 fn，代价为块执行次数乘
+    /// 块内指令数），kcachegrind 等工具可直接打开
+    pub fn write_callgrind(&self, w: &mut dyn Write) -> io::Result<()> {
+        writeln!(w, "# callgrind format")?;
+        writeln!(w, "version: 1")?;
+        writeln!(w, "positions: instr")?;
+        writeln!(w, "events: Instructions")?;
+        writeln!(w)?;
+        for (&start, info) in &self.blocks {
+            let instrs = u64::from(info.end.wrapping_sub(start) / 4 + 1);
+            writeln!(w, "fn=block_0x{:08x}", start)?;
+            writeln!(w, "0x{:x} {}", start, instrs * info.count)?;
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CpuCore;
+    use crate::memory::{FlatMemory, Memory};
+
+    #[test]
+    fn test_blocks_and_branch_directions() {
+        // 3 轮循环：入口块 [0, 8] 一次，回跳块 [4, 8] 两次，bne
+        // 落空后进入 [0xC] 的死循环块
+        let mut cpu = CpuCore::new(0);
+        cpu.enable_profile();
+        let mut mem = FlatMemory::new(1024, 0);
+        mem.store32(0x00, 0x0030_0093).unwrap(); // addi x1, x0, 3
+        mem.store32(0x04, 0xFFF0_8093).unwrap(); // loop: addi x1, x1, -1
+        mem.store32(0x08, 0xFE00_9EE3).unwrap(); // bne x1, x0, loop
+        mem.store32(0x0C, 0x0000_006F).unwrap(); // j .
+        for _ in 0..8 {
+            cpu.step(&mut mem);
+        }
+
+        let profile = cpu.profile().expect("启用后应有剖面器");
+        assert_eq!(
+            profile.block(0),
+            Some(BlockInfo { end: 8, count: 1 }),
+            "入口进入的 [0, 8] 块执行一次"
+        );
+        assert_eq!(
+            profile.block(4),
+            Some(BlockInfo { end: 8, count: 2 }),
+            "回跳目标开启的循环体块再执行 2 次"
+        );
+        assert_eq!(profile.block(0xC).map(|b| b.count), Some(1), "落空后的出口块");
+        let branch = profile.branch(8).expect("bne 应有方向统计");
+        assert_eq!((branch.taken, branch.not_taken), (2, 1));
+    }
+
+    #[test]
+    fn test_text_and_callgrind_export() {
+        let mut profiler = BlockProfiler::new();
+        let bne = RvInstr::Bne { rs1: 1, rs2: 0, offset: -4 };
+        profiler.record(0x100, 0x104, &RvInstr::Addi { rd: 1, rs1: 1, imm: -1 });
+        profiler.record(0x104, 0x100, &bne);
+        profiler.record(0x100, 0x104, &RvInstr::Addi { rd: 1, rs1: 1, imm: -1 });
+        profiler.record(0x104, 0x108, &bne);
+
+        let mut text = Vec::new();
+        profiler.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("0x00000100-0x00000104            2"), "{text}");
+        assert!(text.contains("0x00000104          1          1"), "{text}");
+
+        let mut cg = Vec::new();
+        profiler.write_callgrind(&mut cg).unwrap();
+        let cg = String::from_utf8(cg).unwrap();
+        assert!(cg.starts_with("# callgrind format"));
+        assert!(cg.contains("fn=block_0x00000100"), "{cg}");
+        assert!(cg.contains("0x100 4"), "两条指令的块执行两次: {cg}");
+    }
+}
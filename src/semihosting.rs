@@ -0,0 +1,385 @@
+//! RISC-V semihosting 仿真
+//!
+//! 嵌入式测试套件与 Zephyr 镜像用 semihosting 借宿主完成 I/O 和
+//! 退出：调用点是固定的三条指令序列
+//!
+//! ```text
+//! slli x0, x0, 0x1f
+//! ebreak
+//! srai x0, x0, 7
+//! ```
+//!
+//! `SimEnv` 在取指前识别该序列（启用了
+//! `SimConfig::with_semihosting` 时），按规范从 a0 取操作号、a1 取
+//! 参数（或参数块指针），在宿主侧完成操作后把返回值写回 a0 并跳
+//! 过 ebreak。未识别的操作号交还正常的 trap 路径。
+//!
+//! 实现的操作：SYS_OPEN / SYS_CLOSE / SYS_WRITEC / SYS_WRITE0 /
+//! SYS_WRITE / SYS_READ / SYS_EXIT。
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+
+use crate::cpu::CpuCore;
+use crate::memory::{GuestMemory, Memory};
+
+/// 序列第一条：`slli x0, x0, 0x1f`
+pub const SEMIHOST_PRE: u32 = 0x01F0_1013;
+/// 序列第二条：`ebreak`
+pub const EBREAK_ENCODING: u32 = 0x0010_0073;
+/// 序列第三条：`srai x0, x0, 7`
+pub const SEMIHOST_POST: u32 = 0x4070_5013;
+
+/// semihosting 操作号
+pub mod op {
+    pub const SYS_OPEN: u32 = 0x01;
+    pub const SYS_CLOSE: u32 = 0x02;
+    pub const SYS_WRITEC: u32 = 0x03;
+    pub const SYS_WRITE0: u32 = 0x04;
+    pub const SYS_WRITE: u32 = 0x05;
+    pub const SYS_READ: u32 = 0x06;
+    pub const SYS_EXIT: u32 = 0x18;
+}
+
+/// SYS_EXIT 的"正常退出"原因码（ADP_Stopped_ApplicationExit）
+pub const ADP_STOPPED_APPLICATION_EXIT: u32 = 0x20026;
+
+/// 控制台句柄：SYS_OPEN 打开 `:tt` 时返回，写入走 stdout sink
+const CONSOLE_FD: u32 = 1;
+/// 宿主文件句柄从这里开始分配（0-2 保留给标准流语义）
+const FIRST_FILE_FD: u32 = 3;
+
+/// 操作失败时写回 a0 的值（规范定义为 -1）
+const FAIL: u32 = u32::MAX;
+
+/// 一次 semihosting 调用的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemihostOutcome {
+    /// 已处理：返回值应写入 a0，PC 跳过 ebreak
+    Handled(u32),
+    /// SYS_EXIT：携带退出码，仿真应停机
+    Exit(i32),
+    /// 未识别的操作号：交还正常的 trap 路径执行这条 ebreak
+    Unhandled,
+}
+
+/// semihosting 仿真器
+///
+/// 持有宿主侧的控制台输出与打开的文件句柄。stdout 可替换（测试
+/// 用 [`crate::devices::SharedBuffer`] 捕获输出）。
+pub struct SemihostEmulator {
+    stdout: Box<dyn Write>,
+    files: HashMap<u32, File>,
+    next_fd: u32,
+}
+
+impl Default for SemihostEmulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SemihostEmulator {
+    /// 创建控制台直通宿主 stdout 的仿真器
+    pub fn new() -> Self {
+        SemihostEmulator {
+            stdout: Box::new(io::stdout()),
+            files: HashMap::new(),
+            next_fd: FIRST_FILE_FD,
+        }
+    }
+
+    /// 重定向控制台输出
+    pub fn set_stdout(&mut self, sink: Box<dyn Write>) {
+        self.stdout = sink;
+    }
+
+    /// 处理 PC 停在序列的 ebreak 上的一次 semihosting 调用
+    ///
+    /// 只读取寄存器，不推进 PC——写回 a0 和前进由调用方
+    /// （`SimEnv::step`）根据返回的 [`SemihostOutcome`] 完成。
+    pub fn handle(&mut self, cpu: &CpuCore, mem: &mut GuestMemory) -> SemihostOutcome {
+        let a1 = cpu.read_reg(11);
+        match cpu.read_reg(10) {
+            op::SYS_OPEN => SemihostOutcome::Handled(self.sys_open(mem, a1)),
+            op::SYS_CLOSE => SemihostOutcome::Handled(self.sys_close(mem, a1)),
+            op::SYS_WRITEC => SemihostOutcome::Handled(self.sys_writec(mem, a1)),
+            op::SYS_WRITE0 => SemihostOutcome::Handled(self.sys_write0(mem, a1)),
+            op::SYS_WRITE => SemihostOutcome::Handled(self.sys_write(mem, a1)),
+            op::SYS_READ => SemihostOutcome::Handled(self.sys_read(mem, a1)),
+            op::SYS_EXIT => {
+                // a1 是停止原因：应用正常退出映射为 0，其余为 1
+                let code = if a1 == ADP_STOPPED_APPLICATION_EXIT { 0 } else { 1 };
+                SemihostOutcome::Exit(code)
+            }
+            _ => SemihostOutcome::Unhandled,
+        }
+    }
+
+    /// SYS_OPEN：参数块 [路径指针, 模式, 路径长度]
+    ///
+    /// 路径 `:tt` 返回控制台句柄；模式 0-3 只读，4-7 写（截断），
+    /// 8-11 追加（与规范的 fopen 模式表同序）。
+    fn sys_open(&mut self, mem: &GuestMemory, block: u32) -> u32 {
+        let Some([path_ptr, mode, path_len]) = read_block::<3>(mem, block) else {
+            return FAIL;
+        };
+        let Ok(path_bytes) = mem.read_bytes(path_ptr, path_len as usize) else {
+            return FAIL;
+        };
+        let Ok(path) = String::from_utf8(path_bytes) else {
+            return FAIL;
+        };
+
+        if path == ":tt" {
+            return CONSOLE_FD;
+        }
+
+        let mut options = OpenOptions::new();
+        let options = match mode {
+            0..=3 => options.read(true),
+            4..=7 => options.write(true).create(true).truncate(true),
+            8..=11 => options.append(true).create(true),
+            _ => return FAIL,
+        };
+        match options.open(&path) {
+            Ok(file) => {
+                let fd = self.next_fd;
+                self.next_fd += 1;
+                self.files.insert(fd, file);
+                fd
+            }
+            Err(_) => FAIL,
+        }
+    }
+
+    /// SYS_CLOSE：参数块 [句柄]
+    fn sys_close(&mut self, mem: &GuestMemory, block: u32) -> u32 {
+        let Some([fd]) = read_block::<1>(mem, block) else {
+            return FAIL;
+        };
+        if fd == CONSOLE_FD || self.files.remove(&fd).is_some() {
+            0
+        } else {
+            FAIL
+        }
+    }
+
+    /// SYS_WRITEC：a1 直接指向要输出的单个字符
+    fn sys_writec(&mut self, mem: &GuestMemory, ptr: u32) -> u32 {
+        if let Ok(byte) = mem.load8(ptr) {
+            let _ = self.stdout.write_all(&[byte]);
+            let _ = self.stdout.flush();
+        }
+        0
+    }
+
+    /// SYS_WRITE0：a1 指向 NUL 结尾的字符串
+    fn sys_write0(&mut self, mem: &GuestMemory, mut ptr: u32) -> u32 {
+        let mut bytes = Vec::new();
+        while let Ok(byte) = mem.load8(ptr) {
+            if byte == 0 {
+                break;
+            }
+            bytes.push(byte);
+            ptr = ptr.wrapping_add(1);
+        }
+        let _ = self.stdout.write_all(&bytes);
+        let _ = self.stdout.flush();
+        0
+    }
+
+    /// SYS_WRITE：参数块 [句柄, 缓冲区, 长度]，返回未写出的字节数
+    fn sys_write(&mut self, mem: &GuestMemory, block: u32) -> u32 {
+        let Some([fd, buf, len]) = read_block::<3>(mem, block) else {
+            return FAIL;
+        };
+        let Ok(bytes) = mem.read_bytes(buf, len as usize) else {
+            return FAIL;
+        };
+        let sink: &mut dyn Write = if fd == CONSOLE_FD || fd == 2 {
+            &mut self.stdout
+        } else if let Some(file) = self.files.get_mut(&fd) {
+            file
+        } else {
+            return len;
+        };
+        match sink.write_all(&bytes) {
+            Ok(()) => {
+                let _ = sink.flush();
+                0
+            }
+            Err(_) => len,
+        }
+    }
+
+    /// SYS_READ：参数块 [句柄, 缓冲区, 长度]，返回未读到的字节数
+    /// （0 = 全部读到，len = 已到文件尾）
+    fn sys_read(&mut self, mem: &mut GuestMemory, block: u32) -> u32 {
+        let Some([fd, buf, len]) = read_block::<3>(mem, block) else {
+            return FAIL;
+        };
+        let Some(file) = self.files.get_mut(&fd) else {
+            return len;
+        };
+        let mut bytes = vec![0u8; len as usize];
+        let n = file.read(&mut bytes).unwrap_or(0);
+        if mem.write_bytes(buf, &bytes[..n]).is_err() {
+            return FAIL;
+        }
+        len - n as u32
+    }
+}
+
+/// 从客体内存读一个 N 字（32 位）参数块
+fn read_block<const N: usize>(mem: &GuestMemory, addr: u32) -> Option<[u32; N]> {
+    let mut words = [0u32; N];
+    for (i, word) in words.iter_mut().enumerate() {
+        *word = mem.load32(addr.wrapping_add(4 * i as u32)).ok()?;
+    }
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::cpu::{CpuState, TrapCause};
+    use crate::devices::SharedBuffer;
+    use crate::memory::Memory;
+    use crate::sim_env::{SimConfig, SimEnv};
+
+    fn env_with_program(source: &str) -> SimEnv {
+        let config = SimConfig::new()
+            .with_memory_size(64 * 1024)
+            .with_entry_pc(0)
+            .with_semihosting(true)
+            .with_stop_on_trap(true)
+            .with_max_instructions(1000);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        for (i, word) in assemble(source).unwrap().iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+        env
+    }
+
+    #[test]
+    fn test_sequence_write0_then_exit() {
+        let mut env = env_with_program(
+            "
+            li a0, 4            # SYS_WRITE0
+            li a1, 0x200
+            slli x0, x0, 0x1f
+            ebreak
+            srai x0, x0, 7
+            li a0, 0x18         # SYS_EXIT
+            li a1, 0x20026      # ADP_Stopped_ApplicationExit
+            slli x0, x0, 0x1f
+            ebreak
+            srai x0, x0, 7
+            ",
+        );
+        env.memory.write_bytes(0x200, b"semi\0").unwrap();
+
+        let out = SharedBuffer::new();
+        env.semihost_mut().unwrap().set_stdout(Box::new(out.clone()));
+
+        let (_, state) = env.run_until_halt();
+
+        assert_eq!(state, CpuState::Exited(0));
+        assert_eq!(env.exit_code, Some(0));
+        assert_eq!(out.contents(), "semi");
+    }
+
+    #[test]
+    fn test_plain_ebreak_still_traps() {
+        // 没有前后标记的 ebreak 不是 semihosting 调用，照常陷入
+        let mut env = env_with_program("ebreak");
+        env.run_until_halt();
+        assert_eq!(env.cpu.last_trap(), Some(TrapCause::Breakpoint));
+    }
+
+    #[test]
+    fn test_write0_and_writec() {
+        let buf = SharedBuffer::new();
+        let mut emu = SemihostEmulator::new();
+        emu.set_stdout(Box::new(buf.clone()));
+
+        let mut mem = GuestMemory::flat(1024, 0);
+        mem.write_bytes(0x100, b"hello\0").unwrap();
+        let mut cpu = CpuCore::new(0);
+        cpu.write_reg(10, op::SYS_WRITE0);
+        cpu.write_reg(11, 0x100);
+        assert_eq!(emu.handle(&cpu, &mut mem), SemihostOutcome::Handled(0));
+
+        cpu.write_reg(10, op::SYS_WRITEC);
+        cpu.write_reg(11, 0x104); // 'o'
+        assert_eq!(emu.handle(&cpu, &mut mem), SemihostOutcome::Handled(0));
+
+        assert_eq!(buf.contents(), "helloo");
+    }
+
+    #[test]
+    fn test_open_write_read_close_roundtrip() {
+        let path = std::env::temp_dir().join("allude_sim_semihost_test.txt");
+        let path_str = path.to_str().unwrap();
+        let mut emu = SemihostEmulator::new();
+        let mut mem = GuestMemory::flat(4096, 0);
+        let mut cpu = CpuCore::new(0);
+
+        // SYS_OPEN 写模式
+        mem.write_bytes(0x200, path_str.as_bytes()).unwrap();
+        mem.store32(0x100, 0x200).unwrap();
+        mem.store32(0x104, 4).unwrap(); // "w"
+        mem.store32(0x108, path_str.len() as u32).unwrap();
+        cpu.write_reg(10, op::SYS_OPEN);
+        cpu.write_reg(11, 0x100);
+        let SemihostOutcome::Handled(fd) = emu.handle(&cpu, &mut mem) else {
+            panic!("SYS_OPEN 应被处理");
+        };
+        assert_ne!(fd, FAIL);
+
+        // SYS_WRITE：返回 0 表示全部写出
+        mem.write_bytes(0x300, b"data!").unwrap();
+        mem.store32(0x100, fd).unwrap();
+        mem.store32(0x104, 0x300).unwrap();
+        mem.store32(0x108, 5).unwrap();
+        cpu.write_reg(10, op::SYS_WRITE);
+        assert_eq!(emu.handle(&cpu, &mut mem), SemihostOutcome::Handled(0));
+
+        // SYS_CLOSE 后重开读回
+        cpu.write_reg(10, op::SYS_CLOSE);
+        mem.store32(0x100, fd).unwrap();
+        assert_eq!(emu.handle(&cpu, &mut mem), SemihostOutcome::Handled(0));
+
+        mem.store32(0x100, 0x200).unwrap();
+        mem.store32(0x104, 0).unwrap(); // "r"
+        mem.store32(0x108, path_str.len() as u32).unwrap();
+        cpu.write_reg(10, op::SYS_OPEN);
+        let SemihostOutcome::Handled(fd) = emu.handle(&cpu, &mut mem) else {
+            panic!("SYS_OPEN 应被处理");
+        };
+
+        mem.store32(0x100, fd).unwrap();
+        mem.store32(0x104, 0x400).unwrap();
+        mem.store32(0x108, 5).unwrap();
+        cpu.write_reg(10, op::SYS_READ);
+        assert_eq!(emu.handle(&cpu, &mut mem), SemihostOutcome::Handled(0));
+        assert_eq!(mem.read_bytes(0x400, 5).unwrap(), b"data!");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_exit_reason_mapping() {
+        let mut emu = SemihostEmulator::new();
+        let mut mem = GuestMemory::flat(64, 0);
+        let mut cpu = CpuCore::new(0);
+        cpu.write_reg(10, op::SYS_EXIT);
+        cpu.write_reg(11, ADP_STOPPED_APPLICATION_EXIT);
+        assert_eq!(emu.handle(&cpu, &mut mem), SemihostOutcome::Exit(0));
+        cpu.write_reg(11, 0);
+        assert_eq!(emu.handle(&cpu, &mut mem), SemihostOutcome::Exit(1));
+    }
+}
@@ -20,30 +20,51 @@
 //! env.run(1000);
 //! ```
 
+use std::cell::RefCell;
 use std::fs::File;
-use std::io::{self, Read, BufReader};
+use std::io::{self, Read, Write, BufReader};
+use std::rc::Rc;
 use std::path::Path;
 
 use elf::abi::{EM_RISCV, PT_LOAD, PF_X, PF_W};
 use elf::endian::AnyEndian;
 use elf::ElfBytes;
 
-use crate::cpu::{CpuCore, CpuBuilder, CpuState};
-use crate::memory::{FlatMemory, Memory, MemError};
+use crate::cpu::{CpuCore, CpuBuilder, CpuError, CpuState, PrivilegeMode};
+use crate::isa::IsaError;
+use crate::devices::{Clint, Device, EntropySource, IrqAggregator, MmioBus, Plic, Uart, VirtioBlk};
+use crate::events::{EventBus, EventObserver, SharedEventBus};
+use crate::isa::RvInstr;
+use crate::memory::{GuestMemory, Memory, MemError};
+use crate::stats::ExecStats;
+use crate::semihosting::{
+    SemihostEmulator, SemihostOutcome, EBREAK_ENCODING, SEMIHOST_POST, SEMIHOST_PRE,
+};
+use crate::syscalls::{SyscallEmulator, SyscallOutcome, ECALL_ENCODING};
+use crate::timing::{SimpleTimingModel, TimingReport};
+
+pub mod config_file;
+pub mod loaders;
+pub mod test_runner;
 
 /// 仿真配置错误
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum SimError {
     /// IO 错误
     Io(io::Error),
     /// ELF 解析错误
     ElfParse(String),
+    /// 非 ELF 程序镜像（Intel HEX / Verilog hex）解析错误
+    ImageParse(String),
     /// 配置错误
     Config(String),
-    /// 内存错误
-    Memory(String),
-    /// CPU 配置错误
-    CpuConfig(String),
+    /// 内存访问错误（保留 [`MemError`] 的结构化载荷）
+    Memory(MemError),
+    /// 地址区间不合法（长度超出 32 位空间或放不进目标内存区域）
+    AddressRange { start: u32, len: usize },
+    /// CPU 构建/配置错误（冲突细节见 [`CpuError`]）
+    Cpu(CpuError),
 }
 
 impl std::fmt::Display for SimError {
@@ -51,14 +72,29 @@ impl std::fmt::Display for SimError {
         match self {
             SimError::Io(e) => write!(f, "IO error: {}", e),
             SimError::ElfParse(s) => write!(f, "ELF parse error: {}", s),
+            SimError::ImageParse(s) => write!(f, "Image parse error: {}", s),
             SimError::Config(s) => write!(f, "Config error: {}", s),
-            SimError::Memory(s) => write!(f, "Memory error: {}", s),
-            SimError::CpuConfig(s) => write!(f, "CPU config error: {}", s),
+            SimError::Memory(e) => write!(f, "Memory error: {}", e),
+            SimError::AddressRange { start, len } => write!(
+                f,
+                "Invalid address range: start=0x{:08x}, len=0x{:x}",
+                start, len
+            ),
+            SimError::Cpu(e) => write!(f, "CPU error: {}", e),
         }
     }
 }
 
-impl std::error::Error for SimError {}
+impl std::error::Error for SimError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SimError::Io(e) => Some(e),
+            SimError::Memory(e) => Some(e),
+            SimError::Cpu(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl From<io::Error> for SimError {
     fn from(e: io::Error) -> Self {
@@ -68,7 +104,19 @@ impl From<io::Error> for SimError {
 
 impl From<MemError> for SimError {
     fn from(e: MemError) -> Self {
-        SimError::Memory(e.to_string())
+        SimError::Memory(e)
+    }
+}
+
+impl From<CpuError> for SimError {
+    fn from(e: CpuError) -> Self {
+        SimError::Cpu(e)
+    }
+}
+
+impl From<IsaError> for SimError {
+    fn from(e: IsaError) -> Self {
+        SimError::Cpu(CpuError::Isa(e))
     }
 }
 
@@ -77,6 +125,8 @@ impl From<MemError> for SimError {
 pub struct IsaExtensions {
     /// 启用 M 扩展（乘除法）
     pub m: bool,
+    /// 启用 A 扩展（原子操作）
+    pub a: bool,
     /// 启用 F 扩展（单精度浮点）
     pub f: bool,
     /// 启用 D 扩展（双精度浮点）
@@ -85,6 +135,12 @@ pub struct IsaExtensions {
     pub v: bool,
     /// 启用 Zicsr 扩展（CSR 操作）
     pub zicsr: bool,
+    /// 启用 Zk 扩展（标量密码学）
+    pub zk: bool,
+    /// 启用 Zicond 扩展（整数条件操作）
+    pub zicond: bool,
+    /// 启用 Zicbo 扩展（缓存块操作，Zicboz + Zicbom）
+    pub zicbo: bool,
     /// 启用特权指令
     pub priv_instr: bool,
 }
@@ -118,6 +174,7 @@ impl IsaExtensions {
     pub fn rv32g() -> Self {
         Self {
             m: true,
+            a: true,
             f: true,
             d: true,
             zicsr: true,
@@ -128,19 +185,33 @@ impl IsaExtensions {
 
     /// 从字符串解析扩展配置
     ///
-    /// 格式示例: "rv32imf", "rv32gc", "imfv"
+    /// 格式示例: "rv32imf", "rv32gc", "imfv"。下划线分隔的多字符
+    /// 扩展按词匹配（如 "rv32im_zicond_zicbom"）。
     pub fn from_str(s: &str) -> Result<Self, SimError> {
         let s = s.to_lowercase();
         let s = s.strip_prefix("rv32").unwrap_or(&s);
         let s = s.strip_prefix("rv64").unwrap_or(s);
-        
+
         let mut ext = Self::default();
-        
-        for c in s.chars() {
+
+        let mut parts = s.split('_');
+        let letters = parts.next().unwrap_or("");
+        for token in parts {
+            match token {
+                "zicsr" => ext.zicsr = true,
+                "zicond" => ext.zicond = true,
+                // Zicboz/Zicbom 共用一个解码器
+                "zicbo" | "zicbom" | "zicboz" => ext.zicbo = true,
+                "zk" => ext.zk = true,
+                _ => {} // 忽略未知扩展，与单字母路径一致
+            }
+        }
+
+        for c in letters.chars() {
             match c {
                 'i' => {} // 基础指令集，总是启用
                 'm' => ext.m = true,
-                'a' => {} // TODO: A 扩展（原子操作）
+                'a' => ext.a = true,
                 'f' => {
                     ext.f = true;
                     ext.zicsr = true; // F 扩展需要 Zicsr
@@ -151,10 +222,12 @@ impl IsaExtensions {
                     ext.zicsr = true;
                 }
                 'c' => {} // TODO: C 扩展（压缩指令）
+                'k' => ext.zk = true,
                 'v' => ext.v = true,
                 'g' => {
                     // G = IMAFD + Zicsr + Zifencei
                     ext.m = true;
+                    ext.a = true;
                     ext.f = true;
                     ext.d = true;
                     ext.zicsr = true;
@@ -169,6 +242,119 @@ impl IsaExtensions {
         
         Ok(ext)
     }
+
+    /// 渲染为 ISA 字符串（[`Self::from_str`] 的逆操作）
+    ///
+    /// Zicsr/特权指令没有单字母，完整的 IMAFD+Zicsr+Priv 组合折叠
+    /// 为 `g`，其余情况随 F/D 隐含。
+    pub fn isa_string(&self) -> String {
+        let mut s = String::from("rv32");
+        if self.m && self.a && self.f && self.d && self.zicsr && self.priv_instr {
+            s.push('g');
+        } else {
+            s.push('i');
+            if self.m {
+                s.push('m');
+            }
+            if self.a {
+                s.push('a');
+            }
+            if self.f {
+                s.push('f');
+            }
+            if self.d {
+                s.push('d');
+            }
+        }
+        if self.zk {
+            s.push('k');
+        }
+        if self.v {
+            s.push('v');
+        }
+        if self.zicond {
+            s.push_str("_zicond");
+        }
+        if self.zicbo {
+            s.push_str("_zicbo");
+        }
+        s
+    }
+
+    /// 判断某扩展（按 [`crate::isa::RvInstr::extension_name`] 的命名）是否在本配置内
+    ///
+    /// 基础指令集 "I" 总是允许；未知扩展（包括自定义与非法指令）
+    /// 一律视为不允许。
+    pub fn allows(&self, extension: &str) -> bool {
+        match extension {
+            "I" => true,
+            "M" => self.m,
+            "A" => self.a,
+            "F" => self.f,
+            "V" => self.v,
+            "Zicsr" => self.zicsr,
+            "Zk" => self.zk,
+            "Zicond" => self.zicond,
+            "Zicbo" => self.zicbo,
+            "Priv" => self.priv_instr,
+            _ => false,
+        }
+    }
+}
+
+/// 仿真停止条件
+///
+/// 由 `run_until_halt` 在每条指令执行后评估，命中任意一个即停止。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopCondition {
+    /// 任意 trap（异常或中断）发生时停止
+    OnTrap,
+    /// EBREAK 断点异常时停止
+    OnEbreak,
+    /// 指定地址的内容被改写时停止（按 32-bit 值变化检测）
+    OnStoreTo(u32),
+    /// tohost 被写入指定值时停止（需要 ELF 中存在 tohost 符号）
+    OnTohostValue(u32),
+    /// 执行到达指定 PC 时停止（断点，在该地址的指令执行前停止）
+    OnPc(u32),
+    /// 指定地址的 CSR 被写入时停止（如 mtvec = 0x305）
+    OnCsrWrite(u16),
+}
+
+/// [`SimEnv::run_until`] 的一次性停止条件
+///
+/// 与配置级的 [`StopCondition`]（随 [`SimConfig`] 常驻、每次
+/// `run_until_halt` 都生效）不同，这些条件只对单次调用生效，
+/// 并额外支持指令数预算、内存区间写与自定义谓词——以前要在
+/// 调用方手写 step 循环的场景都能用它表达。
+pub enum RunCondition {
+    /// 到达指定 PC（在该地址的指令执行前停止）
+    PcEquals(u32),
+    /// 本次调用内已执行指定条数的指令
+    InstructionCount(u64),
+    /// tohost 被写入任意非零值（需要 tohost 地址已知）
+    TohostWrite,
+    /// 任意 trap（异常或中断）发生
+    AnyTrap,
+    /// `[start, end)` 区间内的内存被写（触发的指令已执行完毕）
+    MemWrite(std::ops::Range<u32>),
+    /// 自定义谓词，每条指令执行后调用
+    Predicate(Box<dyn FnMut(&SimEnv) -> bool>),
+}
+
+impl std::fmt::Debug for RunCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunCondition::PcEquals(pc) => write!(f, "PcEquals(0x{:08x})", pc),
+            RunCondition::InstructionCount(n) => write!(f, "InstructionCount({})", n),
+            RunCondition::TohostWrite => write!(f, "TohostWrite"),
+            RunCondition::AnyTrap => write!(f, "AnyTrap"),
+            RunCondition::MemWrite(range) => {
+                write!(f, "MemWrite(0x{:08x}..0x{:08x})", range.start, range.end)
+            }
+            RunCondition::Predicate(_) => write!(f, "Predicate(..)"),
+        }
+    }
 }
 
 /// 内存区域配置
@@ -192,41 +378,225 @@ impl Default for MemoryRegion {
     }
 }
 
+/// 各子系统独立的输出级别
+///
+/// 级别含义：0 = 静默，1 = 概要，2 = 详细。拆分子系统后可以只看
+/// 加载器细节而不被指令级跟踪刷屏。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Verbosity {
+    /// 加载器（ELF/二进制加载、内存布局、CPU 初始化）
+    pub loader: u8,
+    /// HTIF（tohost/fromhost 交互）
+    pub htif: u8,
+    /// Trap（异常与中断的进入）
+    pub traps: u8,
+    /// MMIO（内存映射设备访问）
+    pub mmio: u8,
+    /// 指令级跟踪
+    pub trace: u8,
+}
+
+impl Verbosity {
+    /// 所有子系统统一设为同一级别
+    pub fn all(level: u8) -> Self {
+        Verbosity {
+            loader: level,
+            htif: level,
+            traps: level,
+            mmio: level,
+            trace: level,
+        }
+    }
+
+    /// 解析命令行/配置文件风格的规格串
+    ///
+    /// 两种形式：
+    /// - `"2"`：所有子系统设为级别 2
+    /// - `"loader=2,htif=1"`：逗号分隔的 `子系统=级别` 列表，
+    ///   未提及的子系统保持 0
+    pub fn from_spec(spec: &str) -> Result<Self, SimError> {
+        if let Ok(level) = spec.trim().parse::<u8>() {
+            return Ok(Verbosity::all(level));
+        }
+
+        let mut v = Verbosity::default();
+        for part in spec.split(',') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            let (name, level) = part.split_once('=').ok_or_else(|| {
+                SimError::Config(format!("Invalid verbosity spec '{}'", part))
+            })?;
+            let level: u8 = level.trim().parse().map_err(|_| {
+                SimError::Config(format!("Invalid verbosity level '{}'", level))
+            })?;
+            match name.trim() {
+                "loader" => v.loader = level,
+                "htif" => v.htif = level,
+                "traps" => v.traps = level,
+                "mmio" => v.mmio = level,
+                "trace" => v.trace = level,
+                other => {
+                    return Err(SimError::Config(format!(
+                        "Unknown verbosity subsystem '{}'",
+                        other
+                    )));
+                }
+            }
+        }
+        Ok(v)
+    }
+
+    /// 是否存在逐指令级别的输出（影响批量执行快速路径）
+    fn per_instruction(&self) -> bool {
+        self.traps > 0 || self.trace > 0
+    }
+}
+
 /// 仿真配置
 #[derive(Debug, Clone)]
 pub struct SimConfig {
     /// ELF 文件路径（可选，也可以直接提供二进制）
     pub elf_path: Option<String>,
+    /// 内存中的 ELF 镜像（可选，`elf_path` 优先）。用于运行生成或
+    /// 内嵌的程序而无需落盘
+    pub elf_bytes: Option<Vec<u8>>,
+    /// 追加加载到同一地址空间的 ELF 文件（固件 + 载荷等场景）。
+    /// 入口点、tohost 等仍取自主 ELF，这里只搬段并合并符号表
+    pub extra_elf_paths: Vec<String>,
+    /// 设备树 blob：`(路径, 放置地址)`。按 RISC-V 启动约定，
+    /// 复位时把放置地址传入 a1
+    pub dtb: Option<(String, u32)>,
     /// 二进制文件路径（可选）
     pub bin_path: Option<String>,
     /// 二进制加载地址（用于 bin_path）
     pub bin_load_addr: u32,
     /// 入口点 PC（如果不从 ELF 获取）
     pub entry_pc: Option<u32>,
+    /// 入口点符号名（从 ELF 符号表解析，优先于 ELF 头中的入口点）
+    pub entry_symbol: Option<String>,
+    /// 断点符号名列表（从 ELF 符号表解析为 `StopCondition::OnPc`）
+    pub break_symbols: Vec<String>,
     /// 内存配置
     pub memory: MemoryRegion,
     /// ISA 扩展
     pub extensions: IsaExtensions,
     /// 最大执行指令数（0 表示无限制）
     pub max_instructions: u64,
-    /// 是否在 trap 时停止
+    /// 是否在 trap 时停止（等价于附加一个 `StopCondition::OnTrap`）
     pub stop_on_trap: bool,
-    /// 是否启用调试输出
-    pub verbose: bool,
+    /// HTIF tohost 轮询间隔（指令数）。仅在 ELF 中存在 tohost 符号时生效
+    pub htif_poll_interval: u64,
+    /// 附加的停止条件列表
+    pub stop_conditions: Vec<StopCondition>,
+    /// 需要跟踪写入的 CSR 地址列表（每次写入打印 PC 和新旧值）
+    pub trace_csrs: Vec<u16>,
+    /// 各子系统的输出级别
+    pub verbosity: Verbosity,
+    /// 宿主内存用量上限（字节）。超过时 `from_config` 返回错误而
+    /// 不是放任分配把宿主 OOM 掉。`None` 表示不限制
+    pub host_memory_cap: Option<usize>,
+    /// UART 基地址。`Some` 时在该地址映射一个只发送的 16550 风格
+    /// UART（默认输出到 stdout，可用 `SimEnv::set_uart_sink` 重定向）
+    pub uart_base: Option<u32>,
+    /// CLINT 基地址。`Some` 时在该地址映射 mtime/mtimecmp 定时器，
+    /// 每条指令 mtime 加 1，到期时注入机器定时器中断
+    pub clint_base: Option<u32>,
+    /// 寄存器写入历史深度。`Some(n)` 时每个寄存器保留最近 n 次
+    /// 写入（见 [`CpuCore::enable_reg_history`]）
+    pub reg_history_depth: Option<usize>,
+    /// 是否在跳转到入口前运行 ELF `.init_array` 中的构造函数
+    /// （crt-less 裸机程序的启动便利）
+    pub run_init_array: bool,
+    /// 熵设备基地址。`Some` 时在该地址映射一个可播种的熵源
+    /// （见 [`crate::devices::EntropySource`]）
+    pub rng_base: Option<u32>,
+    /// 块设备后备文件与基地址。`Some` 时映射一个 virtio 风格的
+    /// 块设备（见 [`crate::devices::VirtioBlk`]）
+    pub block_device: Option<(String, u32)>,
+    /// 全局仿真种子，熵设备的初始状态由它决定
+    pub seed: u64,
+    /// 是否统计每条指令的使用情况，供运行结束后生成指令集使用
+    /// 证明报告（见 [`SimEnv::instr_usage_report`]）
+    pub track_instr_usage: bool,
+    /// 客体堆区间 (起始地址, 字节数)。`Some` 时启用程序 break 管理，
+    /// 配合 [`SimEnv::register_sbrk_stub`] 仿真 brk/sbrk
+    pub heap_region: Option<(u32, usize)>,
+    /// 初始栈区间：(栈顶, 大小)。加载后 sp 指向 16 字节对齐的
+    /// 栈顶，栈底下方的保护区以监视点形式拦截溢出
+    pub stack_region: Option<(u32, usize)>,
+    /// 注入给客体 `main` 的命令行参数（argv[0] 起）
+    pub args: Vec<String>,
+    /// 是否启用影子调用栈（trap/断点处可取客体回溯）
+    pub track_call_stack: bool,
+    /// 设备/中断评估的指令配额：每执行 N 条指令才推进一次 CLINT
+    /// 并重新评估挂起的中断。默认 1（每条指令评估一次，中断延迟
+    /// 最精确）；调大可提高解释器吞吐，代价是中断交付最多推迟
+    /// N-1 条指令。mtime 按实际执行的指令数批量推进，不会丢失
+    /// 时间，相同配额下的重复运行仍是确定性的
+    pub device_quantum: u64,
+    /// 是否拦截 ECALL 并仿真 newlib 系统调用（见 [`crate::syscalls`]）。
+    /// 未识别的调用号仍走正常的 trap 路径
+    pub emulate_syscalls: bool,
+    /// 是否拦截 RISC-V semihosting 序列（见 [`crate::semihosting`]）。
+    /// 未识别的操作号仍走正常的 ebreak trap 路径
+    pub semihosting: bool,
+    /// 是否使用按 4KB 页懒分配的稀疏内存后端（默认平坦预分配）
+    pub sparse_memory: bool,
+    /// 是否收集逐指令执行统计（按助记符/类别计数、分支走向、
+    /// 访存总量），供负载特征分析（见 [`crate::stats::ExecStats`]）
+    pub collect_stats: bool,
+    /// 是否启用基本块/分支剖面（见 [`SimEnv::profile`]）
+    pub collect_profile: bool,
+    /// 是否启用已解码指令缓存（长时间运行的负载建议开启）
+    pub decode_cache: bool,
+    /// 是否启用周期计时模型（默认的 [`SimpleTimingModel`]），
+    /// 周期数与 IPC 通过 [`SimEnv::timing`] 读取
+    pub collect_timing: bool,
 }
 
 impl Default for SimConfig {
     fn default() -> Self {
         Self {
             elf_path: None,
+            elf_bytes: None,
+            extra_elf_paths: Vec::new(),
+            dtb: None,
             bin_path: None,
             bin_load_addr: 0,
             entry_pc: None,
+            entry_symbol: None,
+            break_symbols: Vec::new(),
             memory: MemoryRegion::default(),
             extensions: IsaExtensions::rv32im(),
             max_instructions: 0,
             stop_on_trap: false,
-            verbose: false,
+            htif_poll_interval: 64,
+            stop_conditions: Vec::new(),
+            trace_csrs: Vec::new(),
+            verbosity: Verbosity::default(),
+            host_memory_cap: None,
+            uart_base: None,
+            clint_base: None,
+            reg_history_depth: None,
+            run_init_array: false,
+            rng_base: None,
+            block_device: None,
+            seed: 0,
+            track_instr_usage: false,
+            heap_region: None,
+            stack_region: None,
+            args: Vec::new(),
+            track_call_stack: false,
+            device_quantum: 1,
+            emulate_syscalls: false,
+            semihosting: false,
+            sparse_memory: false,
+            collect_stats: false,
+            collect_profile: false,
+            decode_cache: false,
+            collect_timing: false,
         }
     }
 }
@@ -237,9 +607,63 @@ impl SimConfig {
         Self::default()
     }
 
+    /// 从 TOML 配置文件读取（格式见 [`config_file`]）
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, SimError> {
+        let text = std::fs::read_to_string(path)?;
+        config_file::parse(&text)
+    }
+
+    /// 把配置写成 TOML 文件，可被 [`Self::from_file`] 读回
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SimError> {
+        std::fs::write(path, config_file::render(self)).map_err(SimError::Io)
+    }
+
+    /// 渲染为 TOML 文本（[`Self::to_file`] 的免文件版，便于在
+    /// 进程间或网络上交换配置）
+    pub fn to_toml(&self) -> String {
+        config_file::render(self)
+    }
+
+    /// 从 TOML 文本解析（[`Self::to_toml`] 的逆操作）
+    pub fn from_toml(text: &str) -> Result<Self, SimError> {
+        config_file::parse(text)
+    }
+
     /// 设置 ELF 文件路径
+    ///
+    /// 重复调用时，后续文件作为追加镜像加载到同一地址空间
+    /// （段不应重叠，入口点取自第一个 ELF）
     pub fn with_elf_path(mut self, path: impl Into<String>) -> Self {
-        self.elf_path = Some(path.into());
+        let path = path.into();
+        if self.elf_path.is_none() {
+            self.elf_path = Some(path);
+        } else {
+            self.extra_elf_paths.push(path);
+        }
+        self
+    }
+
+    /// 放置设备树 blob 并按启动约定在复位时把地址传入 a1
+    pub fn with_dtb(mut self, path: impl Into<String>, addr: u32) -> Self {
+        self.dtb = Some((path.into(), addr));
+        self
+    }
+
+    /// 映射一个由宿主文件提供存储的块设备（默认基地址 0x1000_1000）
+    pub fn with_block_device(mut self, path: impl Into<String>) -> Self {
+        self.block_device = Some((path.into(), 0x1000_1000));
+        self
+    }
+
+    /// 映射块设备到指定基地址
+    pub fn with_block_device_at(mut self, path: impl Into<String>, base: u32) -> Self {
+        self.block_device = Some((path.into(), base));
+        self
+    }
+
+    /// 设置内存中的 ELF 镜像
+    pub fn with_elf_bytes(mut self, bytes: Vec<u8>) -> Self {
+        self.elf_bytes = Some(bytes);
         self
     }
 
@@ -256,6 +680,18 @@ impl SimConfig {
         self
     }
 
+    /// 按符号名设置入口点（需要 ELF 文件，符号不存在时构建失败）
+    pub fn with_entry_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.entry_symbol = Some(symbol.into());
+        self
+    }
+
+    /// 按符号名附加一个断点（需要 ELF 文件，可多次调用）
+    pub fn with_break_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.break_symbols.push(symbol.into());
+        self
+    }
+
     /// 设置内存大小
     pub fn with_memory_size(mut self, size: usize) -> Self {
         self.memory.size = size;
@@ -296,688 +732,4982 @@ impl SimConfig {
         self
     }
 
-    /// 启用详细输出
-    pub fn with_verbose(mut self, verbose: bool) -> Self {
-        self.verbose = verbose;
+    /// 启用 trap 时停止
+    pub fn with_stop_on_trap(mut self, stop: bool) -> Self {
+        self.stop_on_trap = stop;
         self
     }
-}
-
-/// ELF 程序段信息
-#[derive(Debug, Clone)]
-pub struct ElfSegment {
-    /// 虚拟地址
-    pub vaddr: u32,
-    /// 物理地址
-    pub paddr: u32,
-    /// 文件中的大小
-    pub file_size: usize,
-    /// 内存中的大小
-    pub mem_size: usize,
-    /// 段数据
-    pub data: Vec<u8>,
-    /// 是否可执行
-    pub executable: bool,
-    /// 是否可写
-    pub writable: bool,
-}
-
-/// ELF 符号信息
-#[derive(Debug, Clone)]
-pub struct ElfSymbol {
-    /// 符号名称
-    pub name: String,
-    /// 符号地址
-    pub addr: u32,
-    /// 符号大小
-    pub size: u32,
-}
 
-/// ELF 文件解析结果
-#[derive(Debug, Clone)]
-pub struct ElfInfo {
-    /// 入口点地址
-    pub entry: u32,
-    /// 程序段
-    pub segments: Vec<ElfSegment>,
-    /// 符号表（仅保留需要的符号）
-    pub symbols: Vec<ElfSymbol>,
-    /// 是否为 32 位 ELF
-    pub is_32bit: bool,
-    /// 是否为小端序
-    pub is_little_endian: bool,
-    /// 机器类型（应为 RISC-V = 0xF3）
-    pub machine: u16,
-}
+    /// 设置 HTIF tohost 轮询间隔（指令数，至少为 1）
+    pub fn with_htif_poll_interval(mut self, interval: u64) -> Self {
+        self.htif_poll_interval = interval.max(1);
+        self
+    }
 
-impl ElfInfo {
-    /// 解析 ELF 文件
-    pub fn parse<P: AsRef<Path>>(path: P) -> Result<Self, SimError> {
-        let file = File::open(path.as_ref())?;
-        let mut reader = BufReader::new(file);
-        let mut data = Vec::new();
-        reader.read_to_end(&mut data)?;
-        
-        Self::parse_bytes(&data)
+    /// 附加一个停止条件（可多次调用）
+    pub fn with_stop_condition(mut self, cond: StopCondition) -> Self {
+        self.stop_conditions.push(cond);
+        self
     }
 
-    /// 从字节数组解析 ELF（使用 elf crate）
-    pub fn parse_bytes(data: &[u8]) -> Result<Self, SimError> {
-        // 使用 elf crate 解析
-        let elf_file = ElfBytes::<AnyEndian>::minimal_parse(data)
-            .map_err(|e| SimError::ElfParse(format!("Failed to parse ELF: {}", e)))?;
+    /// 跟踪一个 CSR 的写入（可多次调用）
+    pub fn with_csr_trace(mut self, addr: u16) -> Self {
+        self.trace_csrs.push(addr);
+        self
+    }
 
-        // 获取 ELF 头信息
-        let header = &elf_file.ehdr;
-        
-        // 检查机器类型
-        if header.e_machine != EM_RISCV {
-            return Err(SimError::ElfParse(format!(
-                "Not a RISC-V ELF (machine type: 0x{:x}, expected 0x{:x})",
-                header.e_machine, EM_RISCV
-            )));
-        }
+    /// 设置各子系统的输出级别
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
 
-        // 检查是否为 32 位
-        let is_32bit = header.class == elf::file::Class::ELF32;
-        if !is_32bit {
-            return Err(SimError::ElfParse("Only 32-bit ELF is supported".into()));
-        }
+    /// 从规格串设置输出级别（见 [`Verbosity::from_spec`]）
+    pub fn with_verbosity_spec(self, spec: &str) -> Result<Self, SimError> {
+        let verbosity = Verbosity::from_spec(spec)?;
+        Ok(self.with_verbosity(verbosity))
+    }
 
-        // 检查字节序
-        let is_little_endian = header.endianness == elf::endian::AnyEndian::Little;
+    /// 设置宿主内存用量上限（字节）
+    pub fn with_host_memory_cap(mut self, bytes: usize) -> Self {
+        self.host_memory_cap = Some(bytes);
+        self
+    }
 
-        // 获取入口点
-        let entry = header.e_entry as u32;
+    /// 在指定基地址映射一个 UART（见 [`crate::devices::Uart`]）
+    pub fn with_uart(mut self, base: u32) -> Self {
+        self.uart_base = Some(base);
+        self
+    }
 
-        // 解析程序段
-        let mut segments = Vec::new();
+    /// 在指定基地址映射一个 CLINT 定时器（见 [`crate::devices::Clint`]）
+    pub fn with_clint(mut self, base: u32) -> Self {
+        self.clint_base = Some(base);
+        self
+    }
 
-        if let Some(phdrs) = elf_file.segments() {
-            for phdr in phdrs {
-                // 只处理 PT_LOAD 类型的段
-                if phdr.p_type != PT_LOAD {
-                    continue;
-                }
+    /// 启用寄存器写入历史，每个寄存器保留最近 `depth` 次写入
+    pub fn with_reg_history(mut self, depth: usize) -> Self {
+        self.reg_history_depth = Some(depth);
+        self
+    }
 
-                let vaddr = phdr.p_vaddr as u32;
-                let paddr = phdr.p_paddr as u32;
-                let file_size = phdr.p_filesz as usize;
-                let mem_size = phdr.p_memsz as usize;
-                let flags = phdr.p_flags;
-                
-                let executable = (flags & PF_X) != 0;
-                let writable = (flags & PF_W) != 0;
+    /// 在跳转到入口前运行 `.init_array` 中的构造函数
+    pub fn with_init_array(mut self) -> Self {
+        self.run_init_array = true;
+        self
+    }
 
-                // 获取段数据
-                let segment_data = elf_file.segment_data(&phdr)
-                    .map_err(|e| SimError::ElfParse(format!("Failed to read segment data: {}", e)))?
-                    .to_vec();
+    /// 在指定基地址映射一个熵设备（见 [`crate::devices::EntropySource`]）
+    pub fn with_entropy(mut self, base: u32) -> Self {
+        self.rng_base = Some(base);
+        self
+    }
 
-                segments.push(ElfSegment {
-                    vaddr,
-                    paddr,
-                    file_size,
-                    mem_size,
-                    data: segment_data,
-                    executable,
-                    writable,
-                });
-            }
-        }
+    /// 设置全局仿真种子
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
 
-        // 解析符号表（查找 tohost/fromhost 等特殊符号）
-        let mut symbols = Vec::new();
-        
-        if let Ok(Some((symtab, strtab))) = elf_file.symbol_table() {
-            for sym in symtab {
-                // 只保留有名字且有地址的符号
-                if sym.st_value != 0 {
-                    if let Ok(name) = strtab.get(sym.st_name as usize) {
-                        // 只保留我们关心的符号
-                        if name == "tohost" || name == "fromhost" {
-                            symbols.push(ElfSymbol {
-                                name: name.to_string(),
-                                addr: sym.st_value as u32,
-                                size: sym.st_size as u32,
-                            });
-                        }
-                    }
-                }
-            }
-        }
+    /// 启用指令使用统计（见 [`SimEnv::instr_usage_report`]）
+    pub fn with_instr_usage(mut self) -> Self {
+        self.track_instr_usage = true;
+        self
+    }
 
-        Ok(ElfInfo {
-            entry,
-            segments,
-            symbols,
-            is_32bit,
-            is_little_endian,
-            machine: header.e_machine,
-        })
+    /// 配置客体堆区间 `[base, base + size)`，启用程序 break 管理
+    pub fn with_heap(mut self, base: u32, size: usize) -> Self {
+        self.heap_region = Some((base, size));
+        self
     }
 
-    /// 查找符号地址
-    pub fn find_symbol(&self, name: &str) -> Option<u32> {
-        self.symbols.iter()
-            .find(|s| s.name == name)
-            .map(|s| s.addr)
+    /// 配置初始栈 `[top - size, top)`
+    ///
+    /// 加载后 sp (x2) 指向 16 字节对齐的 `top`，栈底下方一页
+    /// （不足一页时到内存区间底为止）作为保护区注册读写监视点，
+    /// 溢出以 [`CpuState::WatchpointHit`] 浮出而不是静默踩坏数据。
+    /// 配合 [`Self::with_heap`]，freestanding C 程序无需自带启动
+    /// 代码即可运行。
+    pub fn with_stack(mut self, top: u32, size: usize) -> Self {
+        self.stack_region = Some((top, size));
+        self
     }
 
-    /// 获取程序使用的最小和最大地址
-    pub fn address_range(&self) -> Option<(u32, u32)> {
-        if self.segments.is_empty() {
-            return None;
-        }
+    /// 为客体 `main` 注入命令行参数（`args[0]` 即 argv[0]）
+    ///
+    /// 加载后在模拟栈上搭出标准的 argc/argv/envp 布局（envp 为空
+    /// 表），a0 = argc、a1 = argv、a2 = envp，sp 指向 argc 所在的
+    /// 16 字节对齐地址。配合 [`Self::with_syscalls`] 可以直接运行
+    /// 真实的命令行程序。
+    pub fn with_args(mut self, args: &[&str]) -> Self {
+        self.args = args.iter().map(|s| s.to_string()).collect();
+        self
+    }
 
-        let min_addr = self.segments.iter().map(|s| s.vaddr).min().unwrap();
-        let max_addr = self.segments
-            .iter()
-            .map(|s| s.vaddr + s.mem_size as u32)
-            .max()
-            .unwrap();
+    /// 跟踪 jal/jalr 调用对的影子栈，trap 或断点处经
+    /// [`SimEnv::backtrace`] 取符号化的客体调用链
+    pub fn with_call_stack(mut self) -> Self {
+        self.track_call_stack = true;
+        self
+    }
 
-        Some((min_addr, max_addr))
+    /// 设置设备/中断评估的指令配额（0 视为 1）
+    pub fn with_device_quantum(mut self, quantum: u64) -> Self {
+        self.device_quantum = quantum.max(1);
+        self
     }
-}
 
-fn len_to_u32(len: usize) -> Result<u32, SimError> {
-    len.try_into().map_err(|_| SimError::Memory(format!("Size {} exceeds 32-bit address space", len)))
-}
+    /// 拦截 ECALL 并仿真 newlib 系统调用（见 [`crate::syscalls`]）
+    pub fn with_syscalls(mut self) -> Self {
+        self.emulate_syscalls = true;
+        self
+    }
 
-fn range_end(addr: u32, len: usize) -> Result<u32, SimError> {
-    let len_u32 = len_to_u32(len)?;
-    addr.checked_add(len_u32).ok_or_else(|| {
-        SimError::Memory(format!("Address range overflow: start=0x{:08x}, len=0x{:x}", addr, len))
-    })
-}
+    /// 拦截 RISC-V semihosting 序列并在宿主侧完成 I/O 与退出
+    /// （见 [`crate::semihosting`]）
+    pub fn with_semihosting(mut self, enable: bool) -> Self {
+        self.semihosting = enable;
+        self
+    }
 
-fn ensure_range(region: &MemoryRegion, addr: u32, len: usize) -> Result<(), SimError> {
-    let region_end = range_end(region.base, region.size)?;
-    let target_end = range_end(addr, len)?;
-    if addr < region.base || target_end > region_end {
-        return Err(SimError::Memory(format!(
-            "Memory region '{}' (0x{:08x}..0x{:08x}) cannot fit range 0x{:08x}..0x{:08x}",
-            region.name,
-            region.base,
-            region_end,
-            addr,
-            target_end,
-        )));
+    /// 使用稀疏内存后端（大地址空间时避免一次性分配整个区间）
+    pub fn with_sparse_memory(mut self) -> Self {
+        self.sparse_memory = true;
+        self
     }
-    Ok(())
-}
 
-fn load_segments_into_memory(
-    memory: &mut FlatMemory,
-    region: &MemoryRegion,
-    segments: &[ElfSegment],
-) -> Result<(), SimError> {
-    for (i, seg) in segments.iter().enumerate() {
-        ensure_range(region, seg.vaddr, seg.mem_size)?;
-        if seg.mem_size == 0 {
-            continue;
-        }
+    /// 启用逐指令执行统计（见 [`SimEnv::stats`]）
+    pub fn with_stats(mut self) -> Self {
+        self.collect_stats = true;
+        self
+    }
 
-        memory
-            .write_bytes(seg.vaddr, &seg.data)
-            .map_err(SimError::from)?;
+    /// 启用基本块执行计数与分支方向剖面（见 [`SimEnv::profile`]）
+    pub fn with_profile(mut self) -> Self {
+        self.collect_profile = true;
+        self
+    }
 
-        if seg.mem_size > seg.file_size {
-            let bss_start = range_end(seg.vaddr, seg.file_size)?;
-            let bss_size = seg.mem_size - seg.file_size;
-            memory.fill(bss_start, bss_size, 0).map_err(SimError::from)?;
-        }
+    /// 启用已解码指令缓存（见 [`CpuCore::enable_decode_cache`]）
+    pub fn with_decode_cache(mut self) -> Self {
+        self.decode_cache = true;
+        self
+    }
 
-        if cfg!(debug_assertions) {
-            let end = range_end(seg.vaddr, seg.mem_size)?;
-            if end <= seg.vaddr {
-                return Err(SimError::Memory(format!(
-                    "Segment {} has invalid range (wraparound)",
-                    i
-                )));
-            }
-        }
+    /// 启用默认周期计时模型（见 [`SimEnv::timing`]）
+    ///
+    /// 需要非默认参数时，改为在构建后调用
+    /// `env.cpu.enable_timing(...)` 安装自定义模型
+    pub fn with_timing(mut self) -> Self {
+        self.collect_timing = true;
+        self
     }
-    Ok(())
 }
 
-/// ISA 测试结果
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TestResult {
-    /// 测试通过
-    Pass,
-    /// 测试失败，包含失败的测试编号
-    Fail(u32),
-    /// 测试超时或未完成
-    Timeout,
+/// 多次重复运行的聚合结果（见 [`SimEnv::run_replicated`]）
+#[derive(Debug, Clone, Copy)]
+pub struct ReplicationReport {
+    /// 运行次数
+    pub runs: usize,
+    /// 每次运行执行的指令数（各次相同）
+    pub instructions: u64,
+    /// 最终 PC（各次相同）
+    pub final_pc: u32,
+    /// 最终 CPU 状态（各次相同）
+    pub final_state: CpuState,
+    /// 墙钟耗时均值（秒）
+    pub mean_seconds: f64,
+    /// 墙钟耗时标准差（秒）
+    pub stddev_seconds: f64,
 }
 
-impl TestResult {
-    /// 从 tohost 值解析测试结果
-    pub fn from_tohost(value: u32) -> Self {
-        if value == 1 {
-            TestResult::Pass
-        } else if value != 0 {
-            // value = (test_num << 1) | 1
-            TestResult::Fail(value >> 1)
-        } else {
-            TestResult::Timeout
+/// 内存断言的逐字节差异报告（见 [`SimEnv::expect_mem`]）
+///
+/// `Display` 渲染为可读的 expected/got 列表，测试失败输出里
+/// 可以直接定位出错的字节。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemDiff {
+    /// 断言的起始地址
+    pub addr: u32,
+    /// 断言覆盖的总字节数
+    pub len: usize,
+    /// 不匹配的字节：(地址, 期望值, 实际值)；实际值为 None
+    /// 表示该地址读不到（未映射）
+    pub mismatches: Vec<(u32, u8, Option<u8>)>,
+}
+
+impl std::fmt::Display for MemDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // 只列出前若干处，避免大段差异淹没测试输出
+        const MAX_LISTED: usize = 16;
+        writeln!(
+            f,
+            "0x{:08x} 起 {} 字节中有 {} 字节不符:",
+            self.addr,
+            self.len,
+            self.mismatches.len()
+        )?;
+        for &(addr, want, got) in self.mismatches.iter().take(MAX_LISTED) {
+            match got {
+                Some(got) => writeln!(f, "  0x{:08x}: 期望 0x{:02x}, 实际 0x{:02x}", addr, want, got)?,
+                None => writeln!(f, "  0x{:08x}: 期望 0x{:02x}, 实际 <未映射>", addr, want)?,
+            }
         }
+        if self.mismatches.len() > MAX_LISTED {
+            writeln!(f, "  ... 另有 {} 处不符", self.mismatches.len() - MAX_LISTED)?;
+        }
+        Ok(())
     }
 }
 
-/// 仿真环境
-///
-/// 封装了 CPU、内存和仿真配置，提供统一的仿真接口
-pub struct SimEnv {
-    /// CPU 核心
-    pub cpu: CpuCore,
-    /// 主内存
-    pub memory: FlatMemory,
-    /// 配置
-    pub config: SimConfig,
-    /// 已执行的指令数
-    pub instructions_executed: u64,
-    /// HTIF tohost 地址（用于 ISA 测试）
-    pub tohost_addr: Option<u32>,
-    /// HTIF fromhost 地址
-    pub fromhost_addr: Option<u32>,
+/// 指令使用报告中的一项：某条指令被执行的次数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstrUsageEntry {
+    /// 所属扩展（"I"、"M"、"Zk" 等，见 [`crate::isa::RvInstr::extension_name`]）
+    pub extension: &'static str,
+    /// 汇编助记符
+    pub mnemonic: &'static str,
+    /// 执行次数
+    pub count: u64,
 }
 
-impl SimEnv {
-    /// 从配置创建仿真环境
-    pub fn from_config(config: SimConfig) -> Result<Self, SimError> {
-        // 1. 创建内存
-        let mut memory = FlatMemory::new(config.memory.size, config.memory.base);
+/// 指令集使用证明报告
+///
+/// 由 [`SimEnv::instr_usage_report`] 在运行结束后生成，记录客体程序
+/// 实际用到的扩展与单条指令。配合 [`IsaExtensions`] 声明的允许集，
+/// 可在部署到受限硬件前验证二进制确实只用了声明的指令。
+#[derive(Debug, Clone, Default)]
+pub struct InstrUsageReport {
+    /// 各指令的使用计数，按 (扩展, 助记符) 排序
+    pub entries: Vec<InstrUsageEntry>,
+}
 
-        // 2. 确定入口 PC
-        let mut entry_pc = config.entry_pc.unwrap_or(config.memory.base);
+impl InstrUsageReport {
+    /// 实际用到的扩展名列表（去重，按名称排序）
+    pub fn used_extensions(&self) -> Vec<&'static str> {
+        let mut exts: Vec<&'static str> = self.entries.iter().map(|e| e.extension).collect();
+        exts.sort_unstable();
+        exts.dedup();
+        exts
+    }
 
-        // 3. 加载程序
-        let mut tohost_addr = None;
-        let mut fromhost_addr = None;
-        
-        if let Some(ref elf_path) = config.elf_path {
-            let elf = ElfInfo::parse(elf_path)?;
-            
-            // 查找 tohost/fromhost 符号
-            tohost_addr = elf.find_symbol("tohost");
-            fromhost_addr = elf.find_symbol("fromhost");
-            
-            if config.verbose {
-                println!("Loaded ELF: {}", elf_path);
-                println!("  Entry point: 0x{:08x}", elf.entry);
-                println!("  Segments: {}", elf.segments.len());
-                if let Some(addr) = tohost_addr {
-                    println!("  tohost: 0x{:08x}", addr);
-                }
-                if let Some(addr) = fromhost_addr {
-                    println!("  fromhost: 0x{:08x}", addr);
-                }
-            }
+    /// 允许集之外的指令（违规项）
+    pub fn violations(&self, allowed: &IsaExtensions) -> Vec<InstrUsageEntry> {
+        self.entries
+            .iter()
+            .filter(|e| !allowed.allows(e.extension))
+            .copied()
+            .collect()
+    }
 
-            if config.verbose {
-                for (i, seg) in elf.segments.iter().enumerate() {
-                    println!(
-                        "  Segment {}: vaddr=0x{:08x}, size=0x{:x}, flags={}{}",
-                        i,
-                        seg.vaddr,
-                        seg.mem_size,
-                        if seg.executable { "X" } else { "-" },
-                        if seg.writable { "W" } else { "R" },
-                    );
-                }
-            }
+    /// 客体是否只使用了允许集内的指令
+    pub fn is_compliant(&self, allowed: &IsaExtensions) -> bool {
+        self.violations(allowed).is_empty()
+    }
+}
 
-            load_segments_into_memory(&mut memory, &config.memory, &elf.segments)?;
+impl std::fmt::Display for InstrUsageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "使用的扩展: {}", self.used_extensions().join(", "))?;
+        for entry in &self.entries {
+            writeln!(f, "  [{}] {:<12} {}", entry.extension, entry.mnemonic, entry.count)?;
+        }
+        Ok(())
+    }
+}
 
-            // 使用 ELF 入口点（除非配置明确指定了入口）
-            if config.entry_pc.is_none() {
-                entry_pc = elf.entry;
-            }
-        } else if let Some(ref bin_path) = config.bin_path {
-            // 加载原始二进制文件
-            let data = std::fs::read(bin_path)?;
-            ensure_range(&config.memory, config.bin_load_addr, data.len())?;
-            
-            if config.verbose {
-                println!("Loaded binary: {}", bin_path);
-                println!("  Load address: 0x{:08x}", config.bin_load_addr);
-                println!("  Size: {} bytes", data.len());
-            }
+/// 客体堆的分配统计（brk/sbrk 仿真）
+///
+/// 由 [`SimEnv::heap_stats`] 返回
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    /// 堆区起始（初始 break）
+    pub heap_base: u32,
+    /// 当前程序 break
+    pub program_break: u32,
+    /// 历史最高 break
+    pub peak_break: u32,
+    /// 成功的增长调用次数
+    pub grow_calls: u64,
+    /// 成功的收缩调用次数
+    pub shrink_calls: u64,
+    /// 被拒绝的调用次数（越过堆区上限或撞上栈）
+    pub failed_calls: u64,
+}
 
-            memory
-                .write_bytes(config.bin_load_addr, &data)
-                .map_err(SimError::from)?;
+impl HeapStats {
+    /// 当前堆占用（字节）
+    pub fn current_usage(&self) -> u32 {
+        self.program_break - self.heap_base
+    }
 
-            // 使用二进制加载地址作为入口点
-            if config.entry_pc.is_none() {
-                entry_pc = config.bin_load_addr;
-            }
-        }
+    /// 峰值堆占用（字节）
+    pub fn peak_usage(&self) -> u32 {
+        self.peak_break - self.heap_base
+    }
+}
 
-        // 4. 创建 CPU
-        let cpu = Self::build_cpu(&config.extensions, entry_pc)?;
+impl std::fmt::Display for HeapStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "brk=0x{:08x} used={} peak={} (grow={}, shrink={}, failed={})",
+            self.program_break,
+            self.current_usage(),
+            self.peak_usage(),
+            self.grow_calls,
+            self.shrink_calls,
+            self.failed_calls
+        )
+    }
+}
 
-        if config.verbose {
-            println!("CPU initialized at PC=0x{:08x}", entry_pc);
-        }
+/// 客体堆的程序 break 管理（brk/sbrk 仿真）
+///
+/// 在一段可配置的堆区间内维护程序 break：增长不会越过堆区上限，
+/// 也不会闯入栈（与当前 sp 保持保护间隙），越界的调用失败返回
+/// 而不是悄悄覆盖栈。所有调用都计入统计。
+#[derive(Debug)]
+pub struct GuestHeap {
+    /// 堆区起始（初始 break）
+    base: u32,
+    /// 堆区上限（不含）
+    limit: u32,
+    /// 当前程序 break
+    brk: u32,
+    /// 历史最高 break
+    peak: u32,
+    /// break 与栈指针之间保留的最小间隙（字节）
+    guard_gap: u32,
+    grow_calls: u64,
+    shrink_calls: u64,
+    failed_calls: u64,
+}
 
-        let mut env = SimEnv {
-            cpu,
-            memory,
-            config,
-            instructions_executed: 0,
-            tohost_addr,
-            fromhost_addr,
-        };
+impl GuestHeap {
+    /// 默认的堆/栈保护间隙（字节）
+    pub const DEFAULT_GUARD_GAP: u32 = 256;
 
-        env.clear_htif_mailboxes();
+    /// 创建堆区间 `[base, base + size)`，初始 break 在 `base`
+    pub fn new(base: u32, size: usize) -> Self {
+        GuestHeap {
+            base,
+            limit: base.saturating_add(size as u32),
+            brk: base,
+            peak: base,
+            guard_gap: Self::DEFAULT_GUARD_GAP,
+            grow_calls: 0,
+            shrink_calls: 0,
+            failed_calls: 0,
+        }
+    }
 
-        Ok(env)
+    /// 设置堆/栈保护间隙
+    pub fn set_guard_gap(&mut self, gap: u32) {
+        self.guard_gap = gap;
     }
 
-    /// 根据扩展配置构建 CPU
-    fn build_cpu(ext: &IsaExtensions, entry_pc: u32) -> Result<CpuCore, SimError> {
-        let mut builder = CpuBuilder::new(entry_pc);
+    /// 当前程序 break
+    pub fn program_break(&self) -> u32 {
+        self.brk
+    }
 
-        if ext.m {
-            builder = builder.with_m_extension();
-        }
-        if ext.f {
-            builder = builder.with_f_extension();
+    /// 按 sbrk 语义调整 break：成功返回旧 break
+    ///
+    /// `sp` 为调用时的栈指针，用于堆/栈碰撞检测
+    pub fn sbrk(&mut self, delta: i32, sp: u32) -> Option<u32> {
+        let old = self.brk;
+        let new_brk = old.wrapping_add(delta as u32);
+        if !self.try_set(new_brk, sp) {
+            self.failed_calls += 1;
+            return None;
         }
-        // D 扩展目前隐含在 F 中处理
-        if ext.v {
-            builder = builder.with_v_extension();
+        if delta >= 0 {
+            self.grow_calls += 1;
+        } else {
+            self.shrink_calls += 1;
         }
-        if ext.zicsr {
-            builder = builder.with_zicsr_extension();
+        Some(old)
+    }
+
+    /// 按 brk 语义设置 break：成功返回 true（失败时 break 不变）
+    pub fn brk(&mut self, new_brk: u32, sp: u32) -> bool {
+        let old = self.brk;
+        if !self.try_set(new_brk, sp) {
+            self.failed_calls += 1;
+            return false;
         }
-        if ext.priv_instr {
-            builder = builder.with_priv_extension();
+        if new_brk >= old {
+            self.grow_calls += 1;
+        } else {
+            self.shrink_calls += 1;
         }
+        true
+    }
 
-        builder
-            .build()
-            .map_err(|conflicts| {
-                SimError::CpuConfig(format!(
-                    "ISA conflicts detected: {:?}",
-                    conflicts
-                ))
-            })
+    /// 校验并落实新的 break 值
+    fn try_set(&mut self, new_brk: u32, sp: u32) -> bool {
+        if new_brk < self.base || new_brk > self.limit {
+            return false;
+        }
+        // 栈在堆区起始之上时，break 不得进入 sp 下方的保护间隙
+        if sp >= self.base && new_brk > sp.saturating_sub(self.guard_gap) {
+            return false;
+        }
+        self.brk = new_brk;
+        self.peak = self.peak.max(new_brk);
+        true
     }
 
-    /// 从 ELF 文件创建仿真环境（便捷方法）
-    pub fn from_elf<P: AsRef<Path>>(path: P) -> Result<Self, SimError> {
-        let elf = ElfInfo::parse(&path)?;
-        
-        // 计算所需内存大小
-        let (min_addr, max_addr) = elf.address_range()
-            .ok_or_else(|| SimError::ElfParse("No loadable segments".into()))?;
-        
-        // 分配足够大的内存（对齐到 4KB）
-        let mem_size = ((max_addr - min_addr + 0xFFF) & !0xFFF) as usize;
-        let mem_size = mem_size.max(64 * 1024); // 至少 64KB
+    /// 当前分配统计
+    pub fn stats(&self) -> HeapStats {
+        HeapStats {
+            heap_base: self.base,
+            program_break: self.brk,
+            peak_break: self.peak,
+            grow_calls: self.grow_calls,
+            shrink_calls: self.shrink_calls,
+            failed_calls: self.failed_calls,
+        }
+    }
+}
 
-        let config = SimConfig::new()
-            .with_elf_path(path.as_ref().to_string_lossy().to_string())
-            .with_memory("ram".to_string(), min_addr, mem_size);
+/// 预解码发现的一处未知编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownEncoding {
+    /// 指令字所在地址
+    pub addr: u32,
+    /// 原始编码
+    pub raw: u32,
+}
 
-        Self::from_config(config)
-    }
+/// 加载镜像的预解码（dry decode）报告
+///
+/// 由 [`SimEnv::dry_decode`] 生成：对可执行段做一遍静态反汇编，
+/// 把配置的 ISA 无法解码的指令字连同地址列出，在运行前暴露
+/// ISA 与镜像不匹配的问题。
+#[derive(Debug, Clone, Default)]
+pub struct DryDecodeReport {
+    /// 检查过的指令字数量
+    pub words_checked: u64,
+    /// 无法解码的指令字（按地址升序）
+    pub unknown: Vec<UnknownEncoding>,
+}
 
-    /// 执行单步
-    pub fn step(&mut self) -> CpuState {
-        let state = self.cpu.step(&mut self.memory);
-        self.instructions_executed += 1;
-        state
+impl DryDecodeReport {
+    /// 镜像是否完全可由配置的 ISA 解码
+    pub fn is_clean(&self) -> bool {
+        self.unknown.is_empty()
     }
+}
 
-    /// 运行指定数量的指令
-    pub fn run(&mut self, max_instructions: u64) -> (u64, CpuState) {
-        let (executed, state) = self.cpu.run(&mut self.memory, max_instructions);
-        self.instructions_executed += executed;
-        (executed, state)
+impl std::fmt::Display for DryDecodeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "预解码: {} 字已检查, {} 个未知编码",
+            self.words_checked,
+            self.unknown.len()
+        )?;
+        for u in &self.unknown {
+            writeln!(f, "  0x{:08x}: 0x{:08x}", u.addr, u.raw)?;
+        }
+        Ok(())
     }
+}
 
-    /// 运行直到停止条件
+/// 仿真占用的宿主内存估算（字节）
+///
+/// 由 [`SimEnv::host_memory_usage`] 返回。数值是保守估算：
+/// 客体 RAM 是精确值，其余按容器元素大小折算。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HostMemoryUsage {
+    /// 客体 RAM 后备存储
+    pub guest_ram: usize,
+    /// ELF 符号表（含符号名字符串）
+    pub symbol_table: usize,
+    /// 已调度事件队列
+    pub events: usize,
+    /// 已注册的宿主桩
+    pub host_stubs: usize,
+}
+
+impl HostMemoryUsage {
+    /// 各分项之和
+    pub fn total(&self) -> usize {
+        self.guest_ram + self.symbol_table + self.events + self.host_stubs
+    }
+}
+
+impl std::fmt::Display for HostMemoryUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "total={} (guest_ram={}, symbols={}, events={}, stubs={})",
+            self.total(),
+            self.guest_ram,
+            self.symbol_table,
+            self.events,
+            self.host_stubs
+        )
+    }
+}
+
+/// ELF 程序段信息
+#[derive(Debug, Clone)]
+pub struct ElfSegment {
+    /// 虚拟地址
+    pub vaddr: u32,
+    /// 物理地址
+    pub paddr: u32,
+    /// 文件中的大小
+    pub file_size: usize,
+    /// 内存中的大小
+    pub mem_size: usize,
+    /// 段数据
+    pub data: Vec<u8>,
+    /// 是否可执行
+    pub executable: bool,
+    /// 是否可写
+    pub writable: bool,
+}
+
+/// ELF 符号信息
+#[derive(Debug, Clone)]
+pub struct ElfSymbol {
+    /// 符号名称
+    pub name: String,
+    /// 符号地址
+    pub addr: u32,
+    /// 符号大小
+    pub size: u32,
+}
+
+/// ELF 文件解析结果
+#[derive(Debug, Clone)]
+pub struct ElfInfo {
+    /// 入口点地址
+    pub entry: u32,
+    /// 程序段
+    pub segments: Vec<ElfSegment>,
+    /// 符号表（仅保留需要的符号）
+    pub symbols: Vec<ElfSymbol>,
+    /// .init_array 中的构造函数地址（按出现顺序）
+    pub init_array: Vec<u32>,
+    /// 是否为 32 位 ELF
+    pub is_32bit: bool,
+    /// 是否为小端序
+    pub is_little_endian: bool,
+    /// 机器类型（应为 RISC-V = 0xF3）
+    pub machine: u16,
+}
+
+impl ElfInfo {
+    /// 解析 ELF 文件
+    pub fn parse<P: AsRef<Path>>(path: P) -> Result<Self, SimError> {
+        let file = File::open(path.as_ref())?;
+        let mut reader = BufReader::new(file);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        
+        Self::parse_bytes(&data)
+    }
+
+    /// 从字节数组解析 ELF（使用 elf crate）
+    pub fn parse_bytes(data: &[u8]) -> Result<Self, SimError> {
+        // 使用 elf crate 解析
+        let elf_file = ElfBytes::<AnyEndian>::minimal_parse(data)
+            .map_err(|e| SimError::ElfParse(format!("Failed to parse ELF: {}", e)))?;
+
+        // 获取 ELF 头信息
+        let header = &elf_file.ehdr;
+        
+        // 检查机器类型
+        if header.e_machine != EM_RISCV {
+            return Err(SimError::ElfParse(format!(
+                "Not a RISC-V ELF (machine type: 0x{:x}, expected 0x{:x})",
+                header.e_machine, EM_RISCV
+            )));
+        }
+
+        // 检查是否为 32 位
+        let is_32bit = header.class == elf::file::Class::ELF32;
+        if !is_32bit {
+            return Err(SimError::ElfParse("Only 32-bit ELF is supported".into()));
+        }
+
+        // 检查字节序：内存模型和取指都按小端实现，大端客户机
+        // 加载后会静默错乱，这里直接拒绝
+        let is_little_endian = header.endianness == elf::endian::AnyEndian::Little;
+        if !is_little_endian {
+            return Err(SimError::ElfParse(
+                "Only little-endian ELF is supported (big-endian guests are not implemented)"
+                    .into(),
+            ));
+        }
+
+        // 获取入口点
+        let entry = header.e_entry as u32;
+
+        // 解析程序段
+        let mut segments = Vec::new();
+
+        if let Some(phdrs) = elf_file.segments() {
+            for phdr in phdrs {
+                // 只处理 PT_LOAD 类型的段
+                if phdr.p_type != PT_LOAD {
+                    continue;
+                }
+
+                let vaddr = phdr.p_vaddr as u32;
+                let paddr = phdr.p_paddr as u32;
+                let file_size = phdr.p_filesz as usize;
+                let mem_size = phdr.p_memsz as usize;
+                let flags = phdr.p_flags;
+                
+                let executable = (flags & PF_X) != 0;
+                let writable = (flags & PF_W) != 0;
+
+                // 获取段数据
+                let segment_data = elf_file.segment_data(&phdr)
+                    .map_err(|e| SimError::ElfParse(format!("Failed to read segment data: {}", e)))?
+                    .to_vec();
+
+                segments.push(ElfSegment {
+                    vaddr,
+                    paddr,
+                    file_size,
+                    mem_size,
+                    data: segment_data,
+                    executable,
+                    writable,
+                });
+            }
+        }
+
+        // 解析完整符号表（HTIF 邮箱、按符号名的断点和入口覆盖都从这里解析）
+        let mut symbols = Vec::new();
+
+        if let Ok(Some((symtab, strtab))) = elf_file.symbol_table() {
+            for sym in symtab {
+                // 只保留有名字且有地址的符号
+                if sym.st_value != 0
+                    && let Ok(name) = strtab.get(sym.st_name as usize)
+                    && !name.is_empty()
+                {
+                    symbols.push(ElfSymbol {
+                        name: name.to_string(),
+                        addr: sym.st_value as u32,
+                        size: sym.st_size as u32,
+                    });
+                }
+            }
+        }
+
+        // 解析 .init_array（crt-less 启动时可由加载器代跑构造函数）
+        let mut init_array = Vec::new();
+        if let Ok(Some(shdr)) = elf_file.section_header_by_name(".init_array")
+            && let Ok((section_data, _)) = elf_file.section_data(&shdr)
+        {
+            for chunk in section_data.chunks_exact(4) {
+                let ptr = u32::from_le_bytes(chunk.try_into().unwrap());
+                // 跳过链接器的占位值（0 和 -1）
+                if ptr != 0 && ptr != 0xFFFF_FFFF {
+                    init_array.push(ptr);
+                }
+            }
+        }
+
+        Ok(ElfInfo {
+            entry,
+            segments,
+            symbols,
+            init_array,
+            is_32bit,
+            is_little_endian,
+            machine: header.e_machine,
+        })
+    }
+
+    /// 查找符号地址
+    pub fn find_symbol(&self, name: &str) -> Option<u32> {
+        self.symbols.iter()
+            .find(|s| s.name == name)
+            .map(|s| s.addr)
+    }
+
+    /// 反查地址所在的符号，返回符号名与地址在符号内的偏移
     ///
-    /// 停止条件：
-    /// - 达到最大指令数
-    /// - CPU 状态变为非 Running
-    /// - 遇到 ECALL/EBREAK（如果 stop_on_trap 为 true）
-    pub fn run_until_halt(&mut self) -> (u64, CpuState) {
-        let max = if self.config.max_instructions > 0 {
-            self.config.max_instructions
-        } else {
-            u64::MAX
+    /// 优先匹配大小覆盖该地址的符号；退而取地址之前最近的
+    /// 无大小符号（汇编标号常见 size 为 0）。
+    pub fn symbolize(&self, addr: u32) -> Option<(&str, u32)> {
+        symbolize_in(&self.symbols, addr)
+    }
+
+    /// 获取程序使用的最小和最大地址
+    pub fn address_range(&self) -> Option<(u32, u32)> {
+        if self.segments.is_empty() {
+            return None;
+        }
+
+        let min_addr = self.segments.iter().map(|s| s.vaddr).min().unwrap();
+        let max_addr = self.segments
+            .iter()
+            .map(|s| s.vaddr + s.mem_size as u32)
+            .max()
+            .unwrap();
+
+        Some((min_addr, max_addr))
+    }
+}
+
+/// 在符号表中反查包含 `addr` 的符号（[`ElfInfo::symbolize`] 的实现）
+fn symbolize_in(symbols: &[ElfSymbol], addr: u32) -> Option<(&str, u32)> {
+    let sym = symbols
+        .iter()
+        .filter(|s| s.addr <= addr && (s.size == 0 || addr - s.addr < s.size))
+        // 同地址时优先有大小的符号；否则取最近的前驱
+        .max_by_key(|s| (s.addr, s.size > 0))?;
+    Some((sym.name.as_str(), addr - sym.addr))
+}
+
+fn len_to_u32(len: usize) -> Result<u32, SimError> {
+    len.try_into()
+        .map_err(|_| SimError::AddressRange { start: 0, len })
+}
+
+fn range_end(addr: u32, len: usize) -> Result<u32, SimError> {
+    let len_u32 = len_to_u32(len)?;
+    addr.checked_add(len_u32)
+        .ok_or(SimError::AddressRange { start: addr, len })
+}
+
+fn ensure_range(region: &MemoryRegion, addr: u32, len: usize) -> Result<(), SimError> {
+    let region_end = range_end(region.base, region.size)?;
+    let target_end = range_end(addr, len)?;
+    if addr < region.base || target_end > region_end {
+        return Err(SimError::AddressRange { start: addr, len });
+    }
+    Ok(())
+}
+
+fn load_segments_into_memory(
+    memory: &mut GuestMemory,
+    region: &MemoryRegion,
+    segments: &[ElfSegment],
+) -> Result<(), SimError> {
+    for seg in segments {
+        ensure_range(region, seg.vaddr, seg.mem_size)?;
+        if seg.mem_size == 0 {
+            continue;
+        }
+
+        memory
+            .write_bytes(seg.vaddr, &seg.data)
+            .map_err(SimError::from)?;
+
+        if seg.mem_size > seg.file_size {
+            let bss_start = range_end(seg.vaddr, seg.file_size)?;
+            let bss_size = seg.mem_size - seg.file_size;
+            memory.fill(bss_start, bss_size, 0).map_err(SimError::from)?;
+        }
+
+        if cfg!(debug_assertions) {
+            let end = range_end(seg.vaddr, seg.mem_size)?;
+            if end <= seg.vaddr {
+                return Err(SimError::AddressRange {
+                    start: seg.vaddr,
+                    len: seg.mem_size,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// OnStoreTo 监视点记录的内存值（越界时为 None）
+type MemResult32 = Option<u32>;
+
+/// 事件回调
+///
+/// 在调度的指令数到达时调用，可访问 CPU 和内存。
+/// 返回 `Some(delta)` 表示在 delta 条指令后重新调度（用于周期性事件，
+/// 如定时器），返回 `None` 表示一次性事件。
+pub type EventCallback = Box<dyn FnMut(&mut CpuCore, &mut GuestMemory) -> Option<u64>>;
+
+/// 宿主桩函数
+///
+/// 客体 PC 命中注册地址时调用，替代该客体函数的执行。
+/// 返回 `Some(v)` 会把 v 写入 a0 作为返回值，返回 `None` 保持 a0
+/// 不变（void 桩）。调用后执行从 ra 恢复。
+pub type HostStub = Box<dyn FnMut(&mut CpuCore, &mut GuestMemory) -> Option<u32>>;
+
+/// [`SimEnv::call_function`] 的调用目标：符号名或绝对地址
+#[derive(Debug, Clone, Copy)]
+pub enum CallTarget<'a> {
+    /// ELF 符号名
+    Symbol(&'a str),
+    /// 绝对地址
+    Addr(u32),
+}
+
+impl<'a> From<&'a str> for CallTarget<'a> {
+    fn from(name: &'a str) -> Self {
+        CallTarget::Symbol(name)
+    }
+}
+
+impl From<u32> for CallTarget<'_> {
+    fn from(addr: u32) -> Self {
+        CallTarget::Addr(addr)
+    }
+}
+
+/// 已调度的事件：在绝对已执行指令数 `at` 处触发
+struct ScheduledEvent {
+    at: u64,
+    callback: EventCallback,
+}
+
+/// HTIF 阻塞式控制台设备（设备号 1）
+///
+/// tohost 命令 1 为 putchar：低字节写到输出 sink（默认宿主
+/// stdout）；命令 0 为 getchar：从可选输入源读一个字节，按
+/// fesvr 约定经 fromhost 回复 `ch + 1`（无输入时为 0）。
+/// riscv-tests 基准和 pk 风格二进制的控制台输出走这条路径。
+pub struct HtifConsole {
+    sink: Box<dyn Write>,
+    input: Option<Box<dyn Read>>,
+}
+
+impl Default for HtifConsole {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HtifConsole {
+    /// 创建输出到宿主 stdout、无输入源的控制台
+    pub fn new() -> Self {
+        HtifConsole {
+            sink: Box::new(io::stdout()),
+            input: None,
+        }
+    }
+
+    /// 替换输出 sink
+    pub fn set_sink(&mut self, sink: Box<dyn Write>) {
+        self.sink = sink;
+    }
+
+    /// 设置 getchar 的输入源
+    pub fn set_input(&mut self, input: Box<dyn Read>) {
+        self.input = Some(input);
+    }
+
+    /// 输出一个字符
+    fn putchar(&mut self, byte: u8) {
+        let _ = self.sink.write_all(&[byte]);
+        let _ = self.sink.flush();
+    }
+
+    /// 读取一个字符（无输入源或输入耗尽时为 None）
+    fn getchar(&mut self) -> Option<u8> {
+        let input = self.input.as_mut()?;
+        let mut buf = [0u8; 1];
+        match input.read(&mut buf) {
+            Ok(1) => Some(buf[0]),
+            _ => None,
+        }
+    }
+}
+
+/// ISA 测试结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestResult {
+    /// 测试通过
+    Pass,
+    /// 测试失败，包含失败的测试编号
+    Fail(u32),
+    /// 测试超时或未完成
+    Timeout,
+}
+
+impl TestResult {
+    /// 从 tohost 值解析测试结果
+    pub fn from_tohost(value: u32) -> Self {
+        if value == 1 {
+            TestResult::Pass
+        } else if value != 0 {
+            // value = (test_num << 1) | 1
+            TestResult::Fail(value >> 1)
+        } else {
+            TestResult::Timeout
+        }
+    }
+
+    /// 序列化为 JSON 对象（`{"status":"fail","case":3}` 形式）
+    pub fn to_json(&self) -> String {
+        match self {
+            TestResult::Pass => "{\"status\":\"pass\"}".to_string(),
+            TestResult::Fail(n) => format!("{{\"status\":\"fail\",\"case\":{}}}", n),
+            TestResult::Timeout => "{\"status\":\"timeout\"}".to_string(),
+        }
+    }
+
+    /// 解析 [`Self::to_json`] 的输出
+    pub fn from_json(text: &str) -> Result<Self, SimError> {
+        let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        match text.as_str() {
+            "{\"status\":\"pass\"}" => Ok(TestResult::Pass),
+            "{\"status\":\"timeout\"}" => Ok(TestResult::Timeout),
+            _ => text
+                .strip_prefix("{\"status\":\"fail\",\"case\":")
+                .and_then(|rest| rest.strip_suffix('}'))
+                .and_then(|n| n.parse().ok())
+                .map(TestResult::Fail)
+                .ok_or_else(|| SimError::Config(format!("invalid TestResult JSON: {}", text))),
+        }
+    }
+}
+
+/// ISA 测试结果缓存
+///
+/// 将已通过的测试按 (ELF 内容哈希, ISA 配置, 仿真器版本) 记录到磁盘，
+/// 重跑大型 rv32 套件时可以跳过未变化的测试。只缓存 Pass 结果，
+/// 失败和超时的测试总是重跑。
+///
+/// 缓存文件为简单文本格式，每行一个键。
+pub struct TestResultCache {
+    path: std::path::PathBuf,
+    passed: std::collections::HashSet<String>,
+}
+
+impl TestResultCache {
+    /// 打开（或新建）缓存文件
+    ///
+    /// 文件不存在或无法解析时从空缓存开始
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let passed = std::fs::read_to_string(&path)
+            .map(|s| s.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+            .unwrap_or_default();
+        Self { path, passed }
+    }
+
+    /// 计算缓存键：ELF 内容哈希 + ISA 字符串 + 仿真器版本
+    pub fn key(elf_data: &[u8], isa: &str) -> String {
+        format!("{:016x}-{}-{}", fnv1a64(elf_data), isa, env!("CARGO_PKG_VERSION"))
+    }
+
+    /// 该键对应的测试是否已缓存为通过
+    pub fn is_cached_pass(&self, key: &str) -> bool {
+        self.passed.contains(key)
+    }
+
+    /// 记录一个通过的测试
+    pub fn record_pass(&mut self, key: String) {
+        self.passed.insert(key);
+    }
+
+    /// 将缓存写回磁盘
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut lines: Vec<&str> = self.passed.iter().map(|s| s.as_str()).collect();
+        lines.sort_unstable();
+        std::fs::write(&self.path, lines.join("\n") + "\n")
+    }
+}
+
+/// FNV-1a 64-bit 哈希（用于 ELF 内容指纹，无需外部依赖）
+fn fnv1a64(data: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// 状态快照 blob 的魔数（见 [`SimEnv::export_state`]）
+const SNAPSHOT_MAGIC: &[u8; 8] = b"ALSIMENV";
+/// 状态快照格式版本
+const SNAPSHOT_VERSION: u32 = 1;
+
+fn write_u32(w: &mut dyn Write, v: u32) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn write_u64(w: &mut dyn Write, v: u64) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn read_u8(r: &mut dyn Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(r: &mut dyn Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(r: &mut dyn Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// 完整仿真状态的快照
+///
+/// 由 [`SimEnv::save_snapshot`] 捕获，[`SimEnv::restore_snapshot`]
+/// 恢复，覆盖全部体系结构状态（PC、整数/浮点/向量寄存器、CSR、
+/// 运行状态、特权级、指令计数）和客体内存镜像。快照留在内存中
+/// 可以零拷贝地反复回滚；[`SimSnapshot::save_to_file`] /
+/// [`SimSnapshot::load_from_file`] 则把它落盘，供跨进程或跨会话
+/// 使用（格式即 [`SimEnv::export_state`] 的二进制 blob）。
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimSnapshot {
+    /// 程序计数器
+    pub pc: u32,
+    /// CPU 运行状态
+    pub state: CpuState,
+    /// 当前特权级
+    pub privilege: PrivilegeMode,
+    /// 已执行的指令数
+    pub instructions: u64,
+    /// 整数寄存器（x0 固定为 0，恢复时跳过）
+    pub int_regs: [u32; 32],
+    /// 浮点寄存器（仅启用 F 扩展时存在）
+    pub fp_regs: Option<[u32; 32]>,
+    /// 向量寄存器（仅启用 V 扩展时存在）
+    pub vec_regs: Option<[[u8; 16]; 32]>,
+    /// CSR 内容，按地址升序
+    pub csrs: Vec<(u16, u32)>,
+    /// 内存区间起始地址
+    pub mem_base: u32,
+    /// 客体内存镜像
+    pub memory: Vec<u8>,
+}
+
+impl SimSnapshot {
+    /// 把快照序列化为可移植的二进制 blob
+    pub fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        w.write_all(SNAPSHOT_MAGIC)?;
+        write_u32(w, SNAPSHOT_VERSION)?;
+
+        write_u32(w, self.pc)?;
+        match self.state {
+            CpuState::Running => w.write_all(&[0])?,
+            CpuState::WaitForInterrupt => w.write_all(&[1])?,
+            CpuState::Halted => w.write_all(&[2])?,
+            CpuState::IllegalInstruction(raw) => {
+                w.write_all(&[3])?;
+                write_u32(w, raw)?;
+            }
+            CpuState::WatchpointHit(addr) => {
+                w.write_all(&[4])?;
+                write_u32(w, addr)?;
+            }
+            CpuState::DebugStep => w.write_all(&[5])?,
+            CpuState::Exited(code) => {
+                w.write_all(&[6])?;
+                write_u32(w, code as u32)?;
+            }
+        }
+        w.write_all(&[self.privilege as u8])?;
+        write_u64(w, self.instructions)?;
+
+        for v in self.int_regs {
+            write_u32(w, v)?;
+        }
+        match self.fp_regs {
+            Some(fp) => {
+                w.write_all(&[1])?;
+                for v in fp {
+                    write_u32(w, v)?;
+                }
+            }
+            None => w.write_all(&[0])?,
+        }
+        match self.vec_regs {
+            Some(vec) => {
+                w.write_all(&[1])?;
+                for lane in vec {
+                    w.write_all(&lane)?;
+                }
+            }
+            None => w.write_all(&[0])?,
+        }
+
+        write_u32(w, self.csrs.len() as u32)?;
+        for &(addr, value) in &self.csrs {
+            write_u32(w, addr as u32)?;
+            write_u32(w, value)?;
+        }
+
+        write_u32(w, self.mem_base)?;
+        write_u64(w, self.memory.len() as u64)?;
+        w.write_all(&self.memory)?;
+        w.flush()
+    }
+
+    /// 从二进制 blob 反序列化快照
+    pub fn read_from(r: &mut dyn Read) -> Result<Self, SimError> {
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(SimError::Config("Snapshot magic mismatch".into()));
+        }
+        let version = read_u32(r)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SimError::Config(format!(
+                "Unsupported snapshot version {}",
+                version
+            )));
+        }
+
+        let pc = read_u32(r)?;
+        let state = match read_u8(r)? {
+            0 => CpuState::Running,
+            1 => CpuState::WaitForInterrupt,
+            2 => CpuState::Halted,
+            3 => CpuState::IllegalInstruction(read_u32(r)?),
+            4 => CpuState::WatchpointHit(read_u32(r)?),
+            5 => CpuState::DebugStep,
+            6 => CpuState::Exited(read_u32(r)? as i32),
+            n => {
+                return Err(SimError::Config(format!("Invalid CPU state code {}", n)));
+            }
+        };
+        let privilege = match read_u8(r)? {
+            0 => PrivilegeMode::User,
+            1 => PrivilegeMode::Supervisor,
+            3 => PrivilegeMode::Machine,
+            n => {
+                return Err(SimError::Config(format!("Invalid privilege mode {}", n)));
+            }
+        };
+        let instructions = read_u64(r)?;
+
+        let mut int_regs = [0u32; 32];
+        for v in int_regs.iter_mut() {
+            *v = read_u32(r)?;
+        }
+        let fp_regs = if read_u8(r)? != 0 {
+            let mut fp = [0u32; 32];
+            for v in fp.iter_mut() {
+                *v = read_u32(r)?;
+            }
+            Some(fp)
+        } else {
+            None
+        };
+        let vec_regs = if read_u8(r)? != 0 {
+            let mut vec = [[0u8; 16]; 32];
+            for lane in vec.iter_mut() {
+                r.read_exact(lane)?;
+            }
+            Some(vec)
+        } else {
+            None
+        };
+
+        let csr_count = read_u32(r)?;
+        let mut csrs = Vec::with_capacity(csr_count as usize);
+        for _ in 0..csr_count {
+            let addr = read_u32(r)?;
+            let value = read_u32(r)?;
+            csrs.push((addr as u16, value));
+        }
+
+        let mem_base = read_u32(r)?;
+        let mem_size = read_u64(r)? as usize;
+        let mut memory = vec![0u8; mem_size];
+        r.read_exact(&mut memory)?;
+
+        Ok(SimSnapshot {
+            pc,
+            state,
+            privilege,
+            instructions,
+            int_regs,
+            fp_regs,
+            vec_regs,
+            csrs,
+            mem_base,
+            memory,
+        })
+    }
+
+    /// 把快照写入文件
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SimError> {
+        let mut file = File::create(path)?;
+        self.write_to(&mut file)?;
+        Ok(())
+    }
+
+    /// 从文件加载快照
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, SimError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        Self::read_from(&mut reader)
+    }
+}
+
+/// 仿真环境
+///
+/// 封装了 CPU、内存和仿真配置，提供统一的仿真接口
+pub struct SimEnv {
+    /// CPU 核心
+    pub cpu: CpuCore,
+    /// 主内存
+    pub memory: GuestMemory,
+    /// 配置
+    pub config: SimConfig,
+    /// 已执行的指令数
+    pub instructions_executed: u64,
+    /// HTIF tohost 地址（用于 ISA 测试）
+    pub tohost_addr: Option<u32>,
+    /// HTIF fromhost 地址
+    pub fromhost_addr: Option<u32>,
+    /// 最近一次 run_until_halt 停止时命中的条件（如果有）
+    pub stop_reason: Option<StopCondition>,
+    /// 最近一次通过 HTIF 轮询检测到的 tohost 值（非零写入）
+    pub last_tohost: Option<u32>,
+    /// 已加载 ELF 的符号表（无 ELF 时为空）
+    pub symbols: Vec<ElfSymbol>,
+    /// 事件队列（按触发指令数升序）
+    events: Vec<ScheduledEvent>,
+    /// 按地址注册的宿主桩函数
+    host_stubs: std::collections::HashMap<u32, HostStub>,
+    /// 内存映射的 UART（配置了 `uart_base` 时存在）
+    uart: Option<Uart>,
+    /// 内存映射的 CLINT 定时器（配置了 `clint_base` 时存在）
+    clint: Option<Clint>,
+    /// 内存映射的熵源（配置了 `rng_base` 时存在）
+    rng: Option<EntropySource>,
+    /// 已加载镜像中可执行区域的 (起始地址, 字节数)（用于 `dry_decode`）
+    exec_ranges: Vec<(u32, usize)>,
+    /// 客体堆的程序 break 管理（配置了 `heap_region` 时存在）。
+    /// 共享引用：sbrk/brk 桩闭包持有同一个堆
+    heap: Option<Rc<RefCell<GuestHeap>>>,
+    /// 自上次设备评估以来执行的指令数（见 `SimConfig::device_quantum`）
+    quantum_credit: u64,
+    /// HTIF 控制台设备（tohost 设备号 1 的 putchar/getchar）
+    htif_console: HtifConsole,
+    /// ECALL 系统调用仿真器（配置了 `emulate_syscalls` 时存在）
+    syscalls: Option<SyscallEmulator>,
+    /// semihosting 仿真器（配置了 `semihosting` 时存在）
+    semihost: Option<SemihostEmulator>,
+    /// 经 [`Self::add_device`] 注册的自定义外设，按注册顺序占用
+    /// 中断源线 1、2、……
+    devices: Vec<Box<dyn Device>>,
+    /// 自定义外设的中断汇集器（未安装 PLIC 时的扁平路径）
+    plic: IrqAggregator,
+    /// 平台级中断控制器（经 [`Self::install_plic`] 安装时存在）。
+    /// 存在时自定义外设的中断线接到它的源输入上
+    plic_ctrl: Option<Plic>,
+    /// 事件总线（有订阅者时存在，见 [`Self::subscribe_events`]）
+    event_bus: Option<SharedEventBus>,
+    /// 客体通过 exit 系统调用报告的退出码
+    pub exit_code: Option<i32>,
+}
+
+impl SimEnv {
+    /// 从配置创建仿真环境
+    pub fn from_config(mut config: SimConfig) -> Result<Self, SimError> {
+        // 按符号名的入口覆盖和断点依赖 ELF 符号表
+        if config.elf_path.is_none()
+            && config.elf_bytes.is_none()
+            && (config.entry_symbol.is_some() || !config.break_symbols.is_empty())
+        {
+            return Err(SimError::Config(
+                "Symbol-based entry/breakpoints require an ELF image".into(),
+            ));
+        }
+
+        // 1. 创建内存（先做上限检查，避免分配把宿主 OOM 掉）
+        if let Some(cap) = config.host_memory_cap
+            && config.memory.size > cap
+        {
+            return Err(SimError::Config(format!(
+                "Guest memory {} bytes exceeds host memory cap {} bytes",
+                config.memory.size, cap
+            )));
+        }
+        let mut memory = if config.sparse_memory {
+            GuestMemory::sparse(config.memory.size, config.memory.base)
+        } else {
+            GuestMemory::flat(config.memory.size, config.memory.base)
+        };
+
+        // 2. 确定入口 PC
+        let mut entry_pc = config.entry_pc.unwrap_or(config.memory.base);
+
+        // 3. 加载程序
+        let mut tohost_addr = None;
+        let mut fromhost_addr = None;
+        let mut symbols = Vec::new();
+        let mut init_array = Vec::new();
+        let mut exec_ranges = Vec::new();
+
+        // ELF 来源：文件路径优先，其次内存镜像
+        let loaded_elf = if let Some(ref elf_path) = config.elf_path {
+            Some((ElfInfo::parse(elf_path)?, elf_path.clone()))
+        } else if let Some(ref bytes) = config.elf_bytes {
+            Some((ElfInfo::parse_bytes(bytes)?, "<memory>".to_string()))
+        } else {
+            None
+        };
+
+        if let Some((elf, elf_source)) = loaded_elf {
+            // 查找 tohost/fromhost 符号
+            tohost_addr = elf.find_symbol("tohost");
+            fromhost_addr = elf.find_symbol("fromhost");
+
+            if config.verbosity.loader >= 1 {
+                println!("Loaded ELF: {}", elf_source);
+                println!("  Entry point: 0x{:08x}", elf.entry);
+                println!("  Segments: {}", elf.segments.len());
+                if let Some(addr) = tohost_addr {
+                    println!("  tohost: 0x{:08x}", addr);
+                }
+                if let Some(addr) = fromhost_addr {
+                    println!("  fromhost: 0x{:08x}", addr);
+                }
+            }
+
+            if config.verbosity.loader >= 2 {
+                for (i, seg) in elf.segments.iter().enumerate() {
+                    println!(
+                        "  Segment {}: vaddr=0x{:08x}, size=0x{:x}, flags={}{}",
+                        i,
+                        seg.vaddr,
+                        seg.mem_size,
+                        if seg.executable { "X" } else { "-" },
+                        if seg.writable { "W" } else { "R" },
+                    );
+                }
+            }
+
+            load_segments_into_memory(&mut memory, &config.memory, &elf.segments)?;
+
+            // 入口优先级：符号名 > 配置指定的 PC > ELF 头入口点
+            if let Some(ref sym) = config.entry_symbol {
+                let addr = elf.find_symbol(sym).ok_or_else(|| {
+                    SimError::Config(format!("Entry symbol '{}' not found in ELF", sym))
+                })?;
+                entry_pc = addr;
+                // 记录到 entry_pc，使 reset 也从该符号重启
+                config.entry_pc = Some(addr);
+            } else if config.entry_pc.is_none() {
+                entry_pc = elf.entry;
+            }
+
+            // 解析符号断点为 PC 断点
+            for sym in &config.break_symbols {
+                let addr = elf.find_symbol(sym).ok_or_else(|| {
+                    SimError::Config(format!("Break symbol '{}' not found in ELF", sym))
+                })?;
+                config.stop_conditions.push(StopCondition::OnPc(addr));
+            }
+
+            // 可执行段中文件提供的部分才包含代码（BSS 填零不算）
+            exec_ranges = elf
+                .segments
+                .iter()
+                .filter(|seg| seg.executable && seg.file_size > 0)
+                .map(|seg| (seg.vaddr, seg.file_size))
+                .collect();
+
+            // 保留符号表供运行时查询（断点、宿主桩等）
+            symbols = elf.symbols;
+            init_array = elf.init_array;
+        } else if let Some(ref bin_path) = config.bin_path {
+            // 非 ELF 程序文件：按扩展名识别格式（见 loaders 模块）
+            match loaders::detect_format(bin_path) {
+                loaders::BinFormat::Raw => {
+                    let data = std::fs::read(bin_path)?;
+                    ensure_range(&config.memory, config.bin_load_addr, data.len())?;
+
+                    if config.verbosity.loader >= 1 {
+                        println!("Loaded binary: {}", bin_path);
+                        println!("  Load address: 0x{:08x}", config.bin_load_addr);
+                        println!("  Size: {} bytes", data.len());
+                    }
+
+                    memory
+                        .write_bytes(config.bin_load_addr, &data)
+                        .map_err(SimError::from)?;
+
+                    // 原始二进制没有段信息：整个镜像视为可执行
+                    exec_ranges.push((config.bin_load_addr, data.len()));
+
+                    // 使用二进制加载地址作为入口点
+                    if config.entry_pc.is_none() {
+                        entry_pc = config.bin_load_addr;
+                    }
+                }
+                format => {
+                    let text = std::fs::read_to_string(bin_path)?;
+                    let image = match format {
+                        loaders::BinFormat::IntelHex => loaders::parse_intel_hex(&text)?,
+                        _ => loaders::parse_verilog_hex(&text, config.bin_load_addr)?,
+                    };
+
+                    if config.verbosity.loader >= 1 {
+                        println!("Loaded {:?} image: {}", format, bin_path);
+                        println!("  Chunks: {}", image.chunks.len());
+                        println!("  Size: {} bytes", image.total_bytes());
+                        if let Some(entry) = image.entry {
+                            println!("  Entry point: 0x{:08x}", entry);
+                        }
+                    }
+
+                    for (addr, data) in &image.chunks {
+                        ensure_range(&config.memory, *addr, data.len())?;
+                        if config.verbosity.loader >= 2 {
+                            println!("  Chunk: addr=0x{:08x}, size=0x{:x}", addr, data.len());
+                        }
+                        memory.write_bytes(*addr, data).map_err(SimError::from)?;
+                        // 文本镜像同样没有段信息：所有数据块视为可执行
+                        exec_ranges.push((*addr, data.len()));
+                    }
+
+                    // 入口优先级：配置指定的 PC > 文件内入口记录 > 最低地址
+                    if config.entry_pc.is_none()
+                        && let Some(entry) = image.entry.or_else(|| image.min_addr())
+                    {
+                        entry_pc = entry;
+                    }
+                }
+            }
+        }
+
+        // 追加镜像：在同一地址空间加载其余 ELF，合并符号与可执行区域
+        for path in &config.extra_elf_paths {
+            let elf = ElfInfo::parse(path)?;
+            if config.verbosity.loader >= 1 {
+                println!("Loaded extra ELF: {}", path);
+                println!("  Segments: {}", elf.segments.len());
+            }
+            load_segments_into_memory(&mut memory, &config.memory, &elf.segments)?;
+            exec_ranges.extend(
+                elf.segments
+                    .iter()
+                    .filter(|seg| seg.executable && seg.file_size > 0)
+                    .map(|seg| (seg.vaddr, seg.file_size)),
+            );
+            symbols.extend(elf.symbols);
+        }
+
+        // 设备树 blob 放入客体内存，复位约定见 `dtb` 字段文档
+        let dtb_addr = if let Some((ref path, addr)) = config.dtb {
+            let blob = std::fs::read(path)?;
+            ensure_range(&config.memory, addr, blob.len())?;
+            memory.write_bytes(addr, &blob).map_err(SimError::from)?;
+            if config.verbosity.loader >= 1 {
+                println!("Loaded DTB: {} at 0x{:08x} ({} bytes)", path, addr, blob.len());
+            }
+            Some(addr)
+        } else {
+            None
+        };
+
+        // 4. 创建外设与 CPU
+        let env_uart = config.uart_base.map(Uart::new);
+        let env_clint = config.clint_base.map(Clint::new);
+        let env_rng = config.rng_base.map(|base| EntropySource::new(base, config.seed));
+        let env_heap = config
+            .heap_region
+            .map(|(base, size)| Rc::new(RefCell::new(GuestHeap::new(base, size))));
+        let config_syscalls = config.emulate_syscalls.then(SyscallEmulator::new);
+        let config_semihost = config.semihosting.then(SemihostEmulator::new);
+
+        let mut cpu = Self::build_cpu(&config.extensions, entry_pc)?;
+        if let Some(depth) = config.reg_history_depth {
+            cpu.enable_reg_history(depth);
+        }
+
+        if config.track_instr_usage {
+            cpu.enable_instr_usage();
+        }
+
+        if config.collect_stats {
+            cpu.enable_stats();
+        }
+
+        if config.collect_profile {
+            cpu.enable_profile();
+        }
+
+        if config.decode_cache {
+            cpu.enable_decode_cache();
+        }
+
+        if config.track_call_stack {
+            cpu.enable_call_stack();
+        }
+
+        if config.collect_timing {
+            cpu.enable_timing(Box::new(SimpleTimingModel::default()));
+        }
+
+        // 启动约定：a0 = hart id（恒 0），a1 = DTB 地址
+        if let Some(addr) = dtb_addr {
+            cpu.write_reg(11, addr);
+        }
+
+        if config.verbosity.loader >= 1 {
+            println!("CPU initialized at PC=0x{:08x}", entry_pc);
+        }
+
+        let mut env = SimEnv {
+            cpu,
+            memory,
+            config,
+            instructions_executed: 0,
+            tohost_addr,
+            fromhost_addr,
+            stop_reason: None,
+            last_tohost: None,
+            symbols,
+            events: Vec::new(),
+            host_stubs: std::collections::HashMap::new(),
+            uart: env_uart,
+            clint: env_clint,
+            rng: env_rng,
+            exec_ranges,
+            heap: env_heap,
+            quantum_credit: 0,
+            htif_console: HtifConsole::new(),
+            syscalls: config_syscalls,
+            semihost: config_semihost,
+            devices: Vec::new(),
+            plic: IrqAggregator::new(),
+            plic_ctrl: None,
+            event_bus: None,
+            exit_code: None,
+        };
+
+        env.clear_htif_mailboxes();
+        env.arm_csr_watches();
+        env.init_stack(true);
+        env.inject_args()?;
+
+        if let Some((ref path, base)) = env.config.block_device {
+            let blk = VirtioBlk::open(path, base).map_err(SimError::Io)?;
+            if env.config.verbosity.loader >= 1 {
+                println!(
+                    "Block device: {} at 0x{:08x} ({} sectors)",
+                    path,
+                    base,
+                    blk.capacity_sectors()
+                );
+            }
+            env.add_device(Box::new(blk));
+        }
+
+        if env.config.run_init_array && !init_array.is_empty() {
+            if env.config.verbosity.loader >= 1 {
+                println!("Running {} .init_array constructor(s)", init_array.len());
+            }
+            env.run_init_array(&init_array)?;
+        }
+
+        Ok(env)
+    }
+
+    /// 运行 `.init_array` 中的构造函数（crt-less 启动便利）
+    ///
+    /// 为每个构造函数合成一次调用：ra 指向哨兵地址，单步执行到
+    /// 返回为止，全部跑完后把 PC 恢复到程序入口。crt-less 程序
+    /// 通常没有设置栈，sp 为 0 时指向内存顶端。
+    fn run_init_array(&mut self, ctors: &[u32]) -> Result<(), SimError> {
+        // 返回地址哨兵：对齐且不会与已加载代码重合
+        const RETURN_SENTINEL: u32 = 0xFFFF_FFF0;
+        const MAX_CTOR_INSTRUCTIONS: u64 = 1_000_000;
+
+        if self.cpu.read_reg(2) == 0 {
+            let top = self
+                .config
+                .memory
+                .base
+                .wrapping_add(self.config.memory.size as u32)
+                & !0xF;
+            self.cpu.write_reg(2, top); // sp
+        }
+
+        let entry_pc = self.cpu.pc();
+        for &ctor in ctors {
+            self.cpu.set_pc(ctor);
+            self.cpu.write_reg(1, RETURN_SENTINEL); // ra
+            let mut executed = 0;
+            while self.cpu.pc() != RETURN_SENTINEL {
+                if executed >= MAX_CTOR_INSTRUCTIONS {
+                    return Err(SimError::Config(format!(
+                        "init_array constructor at 0x{:08x} did not return within {} instructions",
+                        ctor, MAX_CTOR_INSTRUCTIONS
+                    )));
+                }
+                let state = self.cpu.step(&mut self.memory);
+                executed += 1;
+                if state != CpuState::Running {
+                    return Err(SimError::Config(format!(
+                        "init_array constructor at 0x{:08x} stopped unexpectedly: {:?}",
+                        ctor, state
+                    )));
+                }
+            }
+        }
+        self.cpu.set_pc(entry_pc);
+        Ok(())
+    }
+
+    /// 按 `stack_region` 初始化 sp，`arm_guard` 时一并注册栈底
+    /// 下方的保护区监视点（监视点在 CPU 复位后仍存续，reset 只
+    /// 需重设 sp）
+    fn init_stack(&mut self, arm_guard: bool) {
+        const STACK_GUARD_SIZE: u32 = 4096;
+
+        let Some((top, size)) = self.config.stack_region else {
+            return;
+        };
+        self.cpu.write_reg(2, top & !0xF); // sp
+        if !arm_guard {
+            return;
+        }
+        let bottom = top.wrapping_sub(size as u32);
+        let guard_start = bottom
+            .saturating_sub(STACK_GUARD_SIZE)
+            .max(self.config.memory.base);
+        if guard_start < bottom {
+            self.cpu
+                .add_watchpoint(guard_start..bottom, crate::cpu::WatchKind::ReadWrite);
+        }
+    }
+
+    /// 在模拟栈上搭出 argc/argv/envp 布局并设置 a0/a1/a2
+    ///
+    /// 布局自当前 sp 向下：参数字符串（NUL 结尾）在高处，其下是
+    /// 16 字节对齐的指针块 `[argc][argv..][NULL][NULL(envp)]`；
+    /// 完成后 sp 指向 argc。sp 为 0（没配栈）时从内存顶部开始。
+    fn inject_args(&mut self) -> Result<(), SimError> {
+        if self.config.args.is_empty() {
+            return Ok(());
+        }
+
+        let mut sp = self.cpu.read_reg(2);
+        if sp == 0 {
+            sp = self
+                .config
+                .memory
+                .base
+                .wrapping_add(self.config.memory.size as u32)
+                & !0xF;
+        }
+
+        // 字符串区：从高到低逐个拷贝
+        let mut arg_ptrs = Vec::with_capacity(self.config.args.len());
+        for arg in self.config.args.iter().rev() {
+            sp = sp.wrapping_sub(arg.len() as u32 + 1);
+            self.memory.write_bytes(sp, arg.as_bytes())?;
+            self.memory.store8(sp.wrapping_add(arg.len() as u32), 0)?;
+            arg_ptrs.push(sp);
+        }
+        arg_ptrs.reverse();
+
+        // 指针块：argc + argv 指针 + argv 终结 NULL + envp 终结 NULL
+        let words = 1 + arg_ptrs.len() as u32 + 2;
+        sp = sp.wrapping_sub(words * 4) & !0xF;
+        let argv = sp.wrapping_add(4);
+        let envp = argv.wrapping_add((arg_ptrs.len() as u32 + 1) * 4);
+        self.memory.store32(sp, arg_ptrs.len() as u32)?; // argc
+        for (i, &ptr) in arg_ptrs.iter().enumerate() {
+            self.memory.store32(argv.wrapping_add(i as u32 * 4), ptr)?;
+        }
+        self.memory
+            .store32(argv.wrapping_add(arg_ptrs.len() as u32 * 4), 0)?;
+        self.memory.store32(envp, 0)?;
+
+        self.cpu.write_reg(2, sp);
+        self.cpu.write_reg(10, arg_ptrs.len() as u32); // a0 = argc
+        self.cpu.write_reg(11, argv); // a1 = argv
+        self.cpu.write_reg(12, envp); // a2 = envp
+        Ok(())
+    }
+
+    /// 根据配置为 CPU 布置 CSR 监视点（跟踪列表 + OnCsrWrite 停止条件）
+    fn arm_csr_watches(&mut self) {
+        for &addr in &self.config.trace_csrs {
+            self.cpu.watch_csr(addr);
+        }
+        for cond in &self.config.stop_conditions {
+            if let StopCondition::OnCsrWrite(addr) = cond {
+                self.cpu.watch_csr(*addr);
+            }
+        }
+    }
+
+    /// 注册一个自定义外设（见 [`Device`]）
+    ///
+    /// 设备窗口内的访存由内存总线路由到设备；每个设备配额
+    /// （`SimConfig::device_quantum`）推进一次 `tick` 并采样中断线，
+    /// 任何设备拉高中断时向 CPU 注入机器外部中断。返回设备占用的
+    /// 中断源线编号（从 1 开始）。
+    pub fn add_device(&mut self, device: Box<dyn Device>) -> u32 {
+        self.devices.push(device);
+        self.devices.len() as u32
+    }
+
+    /// 在地址窗口上注册一个 MMIO 钩子（见 [`crate::devices::MmioHook`]）
+    ///
+    /// 等价于 `add_device` 一个只转发访问的设备：窗口内的读写交给
+    /// 闭包，读访问的返回值即读出数据。适合 tohost 式信箱和临时
+    /// 设备；返回占用的中断源线编号（钩子自身不拉中断线）。
+    pub fn map_hook(
+        &mut self,
+        range: std::ops::Range<u32>,
+        hook: impl FnMut(crate::devices::MmioAccess) -> u32 + 'static,
+    ) -> u32 {
+        self.add_device(Box::new(crate::devices::MmioHook::new(range, Box::new(hook))))
+    }
+
+    /// 订阅仿真事件（见 [`crate::events`]）
+    ///
+    /// 首次订阅时创建总线并接入 CPU 与内存总线；此后 trap、
+    /// 中断挂起、CSR 写入与设备窗口内的 MMIO 访问都会以
+    /// [`crate::events::SimEvent`] 的形式送达全部订阅者。
+    pub fn subscribe_events(&mut self, observer: Box<dyn EventObserver>) {
+        let bus = self
+            .event_bus
+            .get_or_insert_with(|| Rc::new(std::cell::RefCell::new(EventBus::new())));
+        bus.borrow_mut().subscribe(observer);
+        self.cpu.set_event_bus(Rc::clone(bus));
+    }
+
+    /// 编号最小的挂起中断源（供外部中断处理程序认领）
+    pub fn pending_irq_source(&self) -> Option<u32> {
+        self.plic.highest_pending()
+    }
+
+    /// 安装 PLIC（见 [`Plic`]）
+    ///
+    /// 安装后自定义外设的中断线不再直接驱动机器外部中断，而是
+    /// 作为 PLIC 的源输入；MEIP/SEIP 分别由 PLIC 的上下文 0/1
+    /// 经优先级与阈值仲裁后驱动，客户软件走标准认领/完成握手。
+    pub fn install_plic(&mut self, base: u32, num_sources: u32) {
+        self.plic_ctrl = Some(Plic::new(base, num_sources));
+    }
+
+    /// 访问已安装的 PLIC（宿主侧直接拉源线、查状态）
+    pub fn plic_mut(&mut self) -> Option<&mut Plic> {
+        self.plic_ctrl.as_mut()
+    }
+
+    /// 重定向 UART 输出（未配置 UART 时无效果）
+    ///
+    /// 默认输出到宿主 stdout；测试可用
+    /// [`crate::devices::SharedBuffer`] 捕获输出。
+    pub fn set_uart_sink(&mut self, sink: Box<dyn std::io::Write>) {
+        if let Some(ref mut uart) = self.uart {
+            uart.set_sink(sink);
+        }
+    }
+
+    /// 对已加载镜像的可执行区域做一遍静态预解码
+    ///
+    /// 逐字用 CPU 配置的解码器解码（不执行），报告所有无法识别的
+    /// 编码及其地址。注意这是线性扫描而非控制流跟踪：字面量池或
+    /// 对齐填充也会被当作指令检查，未知编码不一定真的可达。
+    pub fn dry_decode(&self) -> DryDecodeReport {
+        let mut report = DryDecodeReport::default();
+        for &(start, len) in &self.exec_ranges {
+            // 只检查完整且对齐的指令字
+            let first = (start + 3) & !3;
+            let end = start.wrapping_add(len as u32) & !3;
+            let mut addr = first;
+            while addr < end {
+                if let Ok(raw) = self.memory.load32(addr) {
+                    report.words_checked += 1;
+                    if matches!(self.cpu.decode(raw).instr, RvInstr::Illegal { .. }) {
+                        report.unknown.push(UnknownEncoding { addr, raw });
+                    }
+                }
+                addr += 4;
+            }
+        }
+        report
+    }
+
+    /// 生成指令集使用证明报告
+    ///
+    /// 需要通过 [`SimConfig::with_instr_usage`] 启用统计；未启用时
+    /// 返回空报告。条目按 (扩展, 助记符) 排序，输出稳定。
+    pub fn instr_usage_report(&self) -> InstrUsageReport {
+        let entries = self
+            .cpu
+            .instr_usage()
+            .map(|usage| {
+                usage
+                    .iter()
+                    .map(|(&(extension, mnemonic), &count)| InstrUsageEntry {
+                        extension,
+                        mnemonic,
+                        count,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        InstrUsageReport { entries }
+    }
+
+    /// 逐指令执行统计（需要通过 [`SimConfig::with_stats`] 启用，
+    /// 未启用时返回 None）
+    ///
+    /// [`ExecStats`] 的 `Display` 实现可直接打印为运行结束报告：
+    /// 按执行次数排序的助记符直方图、类别分布、分支 taken 比例
+    /// 和访存总量。
+    pub fn stats(&self) -> Option<&ExecStats> {
+        self.cpu.stats()
+    }
+
+    /// 基本块/分支剖面（启用了 `with_profile` 时存在）
+    pub fn profile(&self) -> Option<&crate::profile::BlockProfiler> {
+        self.cpu.profile()
+    }
+
+    /// 计时统计汇总（需要通过 [`SimConfig::with_timing`] 启用，
+    /// 未启用时返回 None）
+    pub fn timing(&self) -> Option<TimingReport> {
+        self.cpu.timing_report()
+    }
+
+    /// 根据扩展配置构建 CPU
+    fn build_cpu(ext: &IsaExtensions, entry_pc: u32) -> Result<CpuCore, SimError> {
+        let mut builder = CpuBuilder::new(entry_pc);
+
+        if ext.m {
+            builder = builder.with_m_extension();
+        }
+        if ext.a {
+            builder = builder.with_a_extension();
+        }
+        if ext.f {
+            builder = builder.with_f_extension();
+        }
+        // D 扩展目前隐含在 F 中处理
+        if ext.v {
+            builder = builder.with_v_extension();
+        }
+        if ext.zicsr {
+            builder = builder.with_zicsr_extension();
+        }
+        if ext.zk {
+            builder = builder.with_zk_extension();
+        }
+        if ext.zicond {
+            builder = builder.with_zicond_extension();
+        }
+        if ext.zicbo {
+            builder = builder.with_zicbo_extension();
+        }
+        if ext.priv_instr {
+            builder = builder.with_priv_extension();
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// 从 ELF 文件创建仿真环境（便捷方法）
+    pub fn from_elf<P: AsRef<Path>>(path: P) -> Result<Self, SimError> {
+        let elf = ElfInfo::parse(&path)?;
+        
+        // 计算所需内存大小
+        let (min_addr, max_addr) = elf.address_range()
+            .ok_or_else(|| SimError::ElfParse("No loadable segments".into()))?;
+        
+        // 分配足够大的内存（对齐到 4KB）
+        let mem_size = ((max_addr - min_addr + 0xFFF) & !0xFFF) as usize;
+        let mem_size = mem_size.max(64 * 1024); // 至少 64KB
+
+        let config = SimConfig::new()
+            .with_elf_path(path.as_ref().to_string_lossy().to_string())
+            .with_memory("ram".to_string(), min_addr, mem_size);
+
+        Self::from_config(config)
+    }
+
+    /// 热重载客体程序
+    ///
+    /// 按当前配置重新读取（可能已重新编译的）ELF 或二进制，重建
+    /// 内存与 CPU，并按新的符号表重新解析入口符号和符号断点，
+    /// 省去固件调试时重建整个仿真环境的样板代码。
+    ///
+    /// 配置（停止条件、CSR 跟踪、外设映射等）保持不变；运行时
+    /// 注册的事件和宿主桩会被清除，因为其地址在新镜像中可能已
+    /// 失效。
+    pub fn reload(&mut self) -> Result<(), SimError> {
+        let mut config = self.config.clone();
+
+        // from_config 会把符号断点的解析结果追加到 stop_conditions，
+        // 先去掉上一次追加的部分，避免留下指向旧地址的 PC 断点
+        let appended = config.break_symbols.len();
+        let keep = config.stop_conditions.len().saturating_sub(appended);
+        config.stop_conditions.truncate(keep);
+
+        *self = Self::from_config(config)?;
+        Ok(())
+    }
+
+    /// 捕获完整仿真状态的内存快照
+    ///
+    /// 快照包含全部体系结构状态（PC、整数/浮点/向量寄存器、CSR、
+    /// 运行状态、特权级、指令计数）和客体内存镜像。适合长时间
+    /// 负载的定期保存、二分差异定位，以及快进到感兴趣区域后反复
+    /// 从同一状态重放。
+    pub fn save_snapshot(&self) -> SimSnapshot {
+        let snap = self.cpu.snapshot();
+        // CSR 按地址排序，保证快照可复现
+        let mut csrs: Vec<(u16, u32)> = snap.csr.iter().map(|(&a, &v)| (a, v)).collect();
+        csrs.sort_unstable();
+
+        SimSnapshot {
+            pc: self.cpu.pc(),
+            state: self.cpu.state(),
+            privilege: self.cpu.privilege(),
+            instructions: self.instructions_executed,
+            int_regs: snap.int,
+            fp_regs: snap.fp,
+            vec_regs: snap.vec,
+            csrs,
+            mem_base: self.memory.base_addr(),
+            memory: self
+                .memory
+                .read_bytes(self.memory.base_addr(), self.memory.size())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// 从快照恢复仿真状态
+    ///
+    /// 本环境必须与快照兼容：内存区间一致、浮点/向量寄存器堆的
+    /// 有无一致，否则返回配置错误且不做任何修改。
+    pub fn restore_snapshot(&mut self, snap: &SimSnapshot) -> Result<(), SimError> {
+        if snap.mem_base != self.memory.base_addr() || snap.memory.len() != self.memory.size() {
+            return Err(SimError::Config(format!(
+                "Snapshot memory region 0x{:08x}+0x{:x} does not match target 0x{:08x}+0x{:x}",
+                snap.mem_base,
+                snap.memory.len(),
+                self.memory.base_addr(),
+                self.memory.size()
+            )));
+        }
+        if snap.fp_regs.is_some() != self.cpu.has_fp() {
+            return Err(SimError::Config(
+                "Snapshot FP register state does not match target CPU".into(),
+            ));
+        }
+        if snap.vec_regs.is_some() != self.cpu.has_vec() {
+            return Err(SimError::Config(
+                "Snapshot vector register state does not match target CPU".into(),
+            ));
+        }
+
+        self.memory.write_bytes(snap.mem_base, &snap.memory)?;
+        for (i, &v) in snap.int_regs.iter().enumerate().skip(1) {
+            self.cpu.write_reg(i as u8, v);
+        }
+        if let Some(ref fp) = snap.fp_regs {
+            for (i, &v) in fp.iter().enumerate() {
+                self.cpu.write_fp(i as u8, v);
+            }
+        }
+        if let Some(ref vec) = snap.vec_regs {
+            for (i, &lane) in vec.iter().enumerate() {
+                self.cpu.write_vec(i as u8, lane);
+            }
+        }
+        for &(addr, value) in &snap.csrs {
+            self.cpu.csr_write(addr, value);
+        }
+        self.cpu.set_pc(snap.pc);
+        self.cpu.set_state(snap.state);
+        self.cpu.set_privilege(snap.privilege);
+        self.instructions_executed = snap.instructions;
+        self.stop_reason = None;
+        Ok(())
+    }
+
+    /// 把正在运行的仿真状态导出为可移植的二进制 blob
+    ///
+    /// 等价于 `save_snapshot()` 加 [`SimSnapshot::write_to`]：blob 可
+    /// 写入文件或 TCP socket，在另一个进程里用
+    /// [`SimEnv::import_state`] 恢复——长时间仿真得以跨宿主维护窗口
+    /// 存活。
+    ///
+    /// 跟踪 sink、宿主桩和事件回调是宿主侧资源（任意闭包），不随
+    /// blob 迁移；接收端在导入前照常安装自己的 sink 和桩即可重新
+    /// 挂接，导入不会动它们。
+    pub fn export_state(&self, w: &mut dyn Write) -> Result<(), SimError> {
+        self.save_snapshot().write_to(w)?;
+        Ok(())
+    }
+
+    /// 从 [`SimEnv::export_state`] 导出的 blob 恢复仿真状态
+    ///
+    /// 等价于 [`SimSnapshot::read_from`] 加 `restore_snapshot()`。
+    pub fn import_state(&mut self, r: &mut dyn Read) -> Result<(), SimError> {
+        let snap = SimSnapshot::read_from(r)?;
+        self.restore_snapshot(&snap)
+    }
+
+    /// 从内存中的 ELF 镜像创建仿真环境（便捷方法）
+    ///
+    /// 与 [`SimEnv::from_elf`] 等价，但直接消费字节而不经过文件系统，
+    /// 便于运行汇编器或 fuzzer 生成的程序。
+    pub fn from_elf_bytes(bytes: Vec<u8>) -> Result<Self, SimError> {
+        let elf = ElfInfo::parse_bytes(&bytes)?;
+
+        // 计算所需内存大小
+        let (min_addr, max_addr) = elf.address_range()
+            .ok_or_else(|| SimError::ElfParse("No loadable segments".into()))?;
+
+        // 分配足够大的内存（对齐到 4KB）
+        let mem_size = ((max_addr - min_addr + 0xFFF) & !0xFFF) as usize;
+        let mem_size = mem_size.max(64 * 1024); // 至少 64KB
+
+        let config = SimConfig::new()
+            .with_elf_bytes(bytes)
+            .with_memory("ram".to_string(), min_addr, mem_size);
+
+        Self::from_config(config)
+    }
+
+    /// 在已加载 ELF 的符号表中查找符号地址
+    pub fn find_symbol(&self, name: &str) -> Option<u32> {
+        self.symbols.iter().find(|s| s.name == name).map(|s| s.addr)
+    }
+
+    /// 按符号名注册一个 PC 断点（等价于 `StopCondition::OnPc`）
+    ///
+    /// 运行期版的 [`SimConfig::with_break_symbol`]：不必交叉比对
+    /// objdump 输出找地址。符号不在已加载的符号表中时报
+    /// [`SimError::Config`]。
+    pub fn add_breakpoint(&mut self, symbol: &str) -> Result<u32, SimError> {
+        let addr = self.find_symbol(symbol).ok_or_else(|| {
+            SimError::Config(format!("Break symbol '{}' not found in ELF", symbol))
+        })?;
+        self.config.stop_conditions.push(StopCondition::OnPc(addr));
+        Ok(addr)
+    }
+
+    /// 运行到 PC 第一次落在指定符号上
+    ///
+    /// 临时挂一个 `OnPc` 停止条件跑 [`Self::run_until_halt`]，返回
+    /// 前撤掉，不影响已配置的断点。到达符号时 `stop_reason` 为
+    /// 该 `OnPc` 条件；先触发其它停止条件或停机时按原语义返回。
+    pub fn run_until_symbol(&mut self, symbol: &str) -> Result<(u64, CpuState), SimError> {
+        let addr = self.add_breakpoint(symbol)?;
+        let result = self.run_until_halt();
+        let cond = StopCondition::OnPc(addr);
+        if let Some(i) = self.config.stop_conditions.iter().rposition(|c| *c == cond) {
+            self.config.stop_conditions.remove(i);
+        }
+        Ok(result)
+    }
+
+    /// 反查地址所在符号（语义同 [`ElfInfo::symbolize`]）
+    pub fn symbolize(&self, addr: u32) -> Option<(&str, u32)> {
+        symbolize_in(&self.symbols, addr)
+    }
+
+    /// 按 RV32 调用约定调用客体中的一个函数，返回其 a0
+    ///
+    /// 参数依次放入 a0-a7（最多 8 个，更多属于栈传参，不支持），
+    /// sp 指向内存区间顶部附近的 16 字节对齐地址，ra 指向顶部
+    /// 预留的返回跳板（一条 `ebreak`）；从目标地址运行到 PC 落回
+    /// 跳板即视为返回。让模拟器可以直接当作 Rust 测试里 C /
+    /// 汇编例程的执行目标：
+    ///
+    /// ```ignore
+    /// let sum = env.call_function("add_u32", &[2, 40])?;
+    /// assert_eq!(sum, 42);
+    /// ```
+    ///
+    /// 受 `SimConfig::max_instructions`（非零时）约束；函数在返回
+    /// 前停机或超出预算时报 [`SimError::Config`]。
+    pub fn call_function<'a>(
+        &mut self,
+        target: impl Into<CallTarget<'a>>,
+        args: &[u32],
+    ) -> Result<u32, SimError> {
+        let addr = match target.into() {
+            CallTarget::Addr(addr) => addr,
+            CallTarget::Symbol(name) => self.find_symbol(name).ok_or_else(|| {
+                SimError::Config(format!("Function symbol '{}' not found in ELF", name))
+            })?,
+        };
+        if args.len() > 8 {
+            return Err(SimError::Config(format!(
+                "call_function supports at most 8 register args, got {}",
+                args.len()
+            )));
+        }
+
+        // 内存顶部：跳板占最后 8 字节，栈顶在其下 16 字节对齐
+        let end = self
+            .memory
+            .base_addr()
+            .wrapping_add(self.memory.size() as u32);
+        let trampoline = end.wrapping_sub(8) & !0x3;
+        let sp = end.wrapping_sub(16) & !0xF;
+        self.memory.store32(trampoline, 0x0010_0073)?; // ebreak
+
+        for (i, &arg) in args.iter().enumerate() {
+            self.cpu.write_reg(10 + i as u8, arg); // a0-a7
+        }
+        self.cpu.write_reg(2, sp); // sp
+        self.cpu.write_reg(1, trampoline); // ra
+        self.cpu.set_pc(addr);
+
+        let max = if self.config.max_instructions > 0 {
+            self.config.max_instructions
+        } else {
+            u64::MAX
+        };
+        for _ in 0..max {
+            let state = self.step();
+            if self.cpu.pc() == trampoline {
+                return Ok(self.cpu.read_reg(10));
+            }
+            if state != CpuState::Running {
+                return Err(SimError::Config(format!(
+                    "Function at 0x{:08x} stopped ({:?}) before returning",
+                    addr, state
+                )));
+            }
+        }
+        Err(SimError::Config(format!(
+            "Function at 0x{:08x} did not return within {} instructions",
+            addr, max
+        )))
+    }
+
+    /// 还原客体当前的调用链，内层帧在前、符号化后逐帧一行
+    ///
+    /// 第 0 帧是当前 PC，其余取自影子调用栈的调用点（见
+    /// [`CpuCore::enable_call_stack`]）。未启用调用栈跟踪时只有
+    /// 第 0 帧。测试在库代码深处失败时，配合 `with_call_stack`
+    /// 能直接看到是谁把执行带进去的。
+    pub fn backtrace(&self) -> Vec<String> {
+        let pc = self.cpu.pc();
+        let mut lines = vec![format!("#0 0x{:08x}{}", pc, self.symbol_annotation(pc))];
+        if let Some(frames) = self.cpu.call_stack() {
+            for (i, frame) in frames.iter().rev().enumerate() {
+                lines.push(format!(
+                    "#{} 0x{:08x}{}",
+                    i + 1,
+                    frame.call_site,
+                    self.symbol_annotation(frame.call_site)
+                ));
+            }
+        }
+        lines
+    }
+
+    /// 把签名区间按 RISCOF 要求的格式写到文件
+    ///
+    /// 区间由 ELF 中的 `begin_signature`/`end_signature` 符号界定，
+    /// 每行一个 32 位字的小写十六进制。用于 riscv-arch-test 的
+    /// 合规性比对流程。
+    pub fn write_signature<P: AsRef<Path>>(&self, path: P) -> Result<(), SimError> {
+        let begin = self.find_symbol("begin_signature").ok_or_else(|| {
+            SimError::Config("begin_signature symbol not found in ELF".into())
+        })?;
+        let end = self.find_symbol("end_signature").ok_or_else(|| {
+            SimError::Config("end_signature symbol not found in ELF".into())
+        })?;
+        if end < begin || !begin.is_multiple_of(4) || !end.is_multiple_of(4) {
+            return Err(SimError::Config(format!(
+                "Invalid signature range: 0x{:08x}..0x{:08x}",
+                begin, end
+            )));
+        }
+
+        let mut out = String::with_capacity(((end - begin) / 4) as usize * 9);
+        let mut addr = begin;
+        while addr < end {
+            let word = self.memory.load32(addr)?;
+            out.push_str(&format!("{:08x}\n", word));
+            addr += 4;
+        }
+        std::fs::write(path, out).map_err(SimError::Io)
+    }
+
+    /// 生成跟踪/trap 输出用的符号后缀（如 ` <main+0x14>`），无符号表或
+    /// 查不到时为空串
+    fn symbol_annotation(&self, addr: u32) -> String {
+        match self.symbolize(addr) {
+            Some((name, 0)) => format!(" <{}>", name),
+            Some((name, off)) => format!(" <{}+0x{:x}>", name, off),
+            None => String::new(),
+        }
+    }
+
+    /// 按地址注册宿主桩函数
+    ///
+    /// PC 命中 `addr` 时调用 `stub` 替代客体函数：返回值写入 a0，
+    /// 执行从 ra 恢复。适合把 printf、memcpy 或未实现的驱动换成
+    /// 宿主侧的快速实现。
+    pub fn register_host_stub(&mut self, addr: u32, stub: HostStub) {
+        self.host_stubs.insert(addr, stub);
+    }
+
+    /// 按符号名注册宿主桩函数（需要 ELF 符号表）
+    pub fn register_host_stub_by_symbol(
+        &mut self,
+        symbol: &str,
+        stub: HostStub,
+    ) -> Result<(), SimError> {
+        let addr = self.find_symbol(symbol).ok_or_else(|| {
+            SimError::Config(format!("Stub symbol '{}' not found in ELF", symbol))
+        })?;
+        self.register_host_stub(addr, stub);
+        Ok(())
+    }
+
+    /// 在 `addr` 处注册 printf 桩：按 RV32 ABI 解码格式串与参数，
+    /// 渲染结果打印到宿主 stdout，a0 返回输出的字节数
+    ///
+    /// 典型用法是把固件自带的 printf 换成宿主实现：
+    /// `env.register_printf_stub(env.find_symbol("printf").unwrap())`
+    pub fn register_printf_stub(&mut self, addr: u32) {
+        self.register_host_stub(
+            addr,
+            Box::new(|cpu, mem| {
+                let text = crate::guest_io::format_printf(cpu, mem);
+                print!("{}", text);
+                Some(text.len() as u32)
+            }),
+        );
+    }
+
+    /// 在 `addr` 处注册 sbrk 桩（newlib `_sbrk` 语义）
+    ///
+    /// a0 为有符号增量，成功时返回旧的程序 break，越过堆区上限或
+    /// 撞上栈（与当前 sp 的保护间隙内）时返回 `(void*)-1` 且 break
+    /// 不变。需要先用 [`SimConfig::with_heap`] 配置堆区间。
+    pub fn register_sbrk_stub(&mut self, addr: u32) -> Result<(), SimError> {
+        let heap = self.shared_heap()?;
+        self.register_host_stub(
+            addr,
+            Box::new(move |cpu, _mem| {
+                let delta = cpu.read_reg(10) as i32;
+                let sp = cpu.read_reg(2);
+                Some(heap.borrow_mut().sbrk(delta, sp).unwrap_or(u32::MAX))
+            }),
+        );
+        Ok(())
+    }
+
+    /// 在 `addr` 处注册 brk 桩
+    ///
+    /// a0 为新的 break 地址（0 表示查询），返回调用后的 break——
+    /// 设置失败时即为原值，客体可据此判断调用是否成功。
+    pub fn register_brk_stub(&mut self, addr: u32) -> Result<(), SimError> {
+        let heap = self.shared_heap()?;
+        self.register_host_stub(
+            addr,
+            Box::new(move |cpu, _mem| {
+                let request = cpu.read_reg(10);
+                let sp = cpu.read_reg(2);
+                let mut heap = heap.borrow_mut();
+                if request != 0 {
+                    heap.brk(request, sp);
+                }
+                Some(heap.program_break())
+            }),
+        );
+        Ok(())
+    }
+
+    /// 客体堆的当前分配统计（未配置堆区间时为 `None`）
+    pub fn heap_stats(&self) -> Option<HeapStats> {
+        self.heap.as_ref().map(|h| h.borrow().stats())
+    }
+
+    /// 取出供桩闭包共享的堆引用
+    fn shared_heap(&self) -> Result<Rc<RefCell<GuestHeap>>, SimError> {
+        self.heap.clone().ok_or_else(|| {
+            SimError::Config("brk/sbrk stubs require a heap region (with_heap)".into())
+        })
+    }
+
+    /// 估算仿真当前占用的宿主内存
+    ///
+    /// 客体 RAM 为精确值，容器类按元素大小折算（不含分配器开销）。
+    pub fn host_memory_usage(&self) -> HostMemoryUsage {
+        HostMemoryUsage {
+            guest_ram: self.memory.size(),
+            symbol_table: self
+                .symbols
+                .iter()
+                .map(|s| std::mem::size_of::<ElfSymbol>() + s.name.capacity())
+                .sum(),
+            events: self.events.len() * std::mem::size_of::<ScheduledEvent>(),
+            host_stubs: self.host_stubs.len()
+                * std::mem::size_of::<(u32, HostStub)>(),
+        }
+    }
+
+    /// 在绝对已执行指令数 `at` 处调度一个事件
+    ///
+    /// 如果 `at` 已经过去，事件将在下一条指令执行后立即触发
+    pub fn schedule_at(&mut self, at: u64, callback: EventCallback) {
+        let pos = self.events.partition_point(|e| e.at <= at);
+        self.events.insert(pos, ScheduledEvent { at, callback });
+    }
+
+    /// 在 `delta` 条指令之后调度一个事件
+    ///
+    /// # 示例
+    ///
+    /// "1000 条指令后注入定时器中断" 只需：
+    ///
+    /// ```no_run
+    /// # use allude_sim::sim_env::{SimEnv, SimConfig};
+    /// # use allude_sim::cpu::TrapCause;
+    /// # let mut env = SimEnv::from_config(SimConfig::default()).unwrap();
+    /// env.schedule_after(1000, Box::new(|cpu, _mem| {
+    ///     cpu.take_trap(TrapCause::MachineTimerInterrupt, 0);
+    ///     None
+    /// }));
+    /// ```
+    pub fn schedule_after(&mut self, delta: u64, callback: EventCallback) {
+        self.schedule_at(self.instructions_executed + delta, callback);
+    }
+
+    /// 触发所有已到期的事件
+    fn dispatch_events(&mut self) {
+        while self
+            .events
+            .first()
+            .is_some_and(|e| e.at <= self.instructions_executed)
+        {
+            let mut event = self.events.remove(0);
+            if let Some(delta) = (event.callback)(&mut self.cpu, &mut self.memory) {
+                // 周期性事件：按返回的间隔重新调度（至少前进 1，避免死循环）
+                let next = self.instructions_executed + delta.max(1);
+                let pos = self.events.partition_point(|e| e.at <= next);
+                self.events.insert(pos, ScheduledEvent { at: next, callback: event.callback });
+            }
+        }
+    }
+
+    /// 执行单步
+    ///
+    /// PC 命中已注册的宿主桩时调用桩函数代替客体指令（计为一条指令）；
+    /// 启用系统调用仿真时，可识别的 ECALL 同样在宿主侧完成
+    pub fn step(&mut self) -> CpuState {
+        if self.syscalls.is_some()
+            && self.memory.load32(self.cpu.pc()) == Ok(ECALL_ENCODING)
+            && let Some(state) = self.try_emulate_ecall()
+        {
+            return state;
+        }
+
+        if self.semihost.is_some()
+            && self.memory.load32(self.cpu.pc()) == Ok(EBREAK_ENCODING)
+            && let Some(state) = self.try_emulate_semihost()
+        {
+            return state;
+        }
+
+        if !self.host_stubs.is_empty()
+            && let Some(stub) = self.host_stubs.get_mut(&self.cpu.pc())
+        {
+            if let Some(ret) = stub(&mut self.cpu, &mut self.memory) {
+                self.cpu.write_reg(10, ret); // a0 = 返回值
+            }
+            let ra = self.cpu.read_reg(1);
+            self.cpu.set_pc(ra);
+            self.instructions_executed += 1;
+            if !self.events.is_empty() {
+                self.dispatch_events();
+            }
+            return self.cpu.state();
+        }
+
+        let instr_pc = self.cpu.pc();
+        let mut state = if self.uart.is_some()
+            || self.clint.is_some()
+            || self.rng.is_some()
+            || self.plic_ctrl.is_some()
+            || !self.devices.is_empty()
+        {
+            let mut bus = MmioBus {
+                ram: &mut self.memory,
+                uart: self.uart.as_mut(),
+                clint: self.clint.as_mut(),
+                rng: self.rng.as_mut(),
+                plic: self.plic_ctrl.as_mut(),
+                devices: &mut self.devices,
+                events: self.event_bus.as_ref(),
+            };
+            self.cpu.step(&mut bus)
+        } else {
+            self.cpu.step(&mut self.memory)
+        };
+        self.instructions_executed += 1;
+
+        if self.clint.is_some() || self.plic_ctrl.is_some() || !self.devices.is_empty() {
+            self.quantum_credit += 1;
+            if self.quantum_credit >= self.config.device_quantum {
+                let elapsed = self.quantum_credit;
+                self.quantum_credit = 0;
+                self.tick_clint(elapsed);
+                self.tick_devices(elapsed);
+                state = self.cpu.state();
+            }
+        }
+
+        if self.config.verbosity.trace >= 1 {
+            let sym = self.symbol_annotation(instr_pc);
+            if self.config.verbosity.trace >= 2 {
+                let raw = self.memory.load32(instr_pc).unwrap_or(0);
+                println!("[trace] pc=0x{:08x} instr=0x{:08x}{}", instr_pc, raw, sym);
+            } else {
+                println!("[trace] pc=0x{:08x}{}", instr_pc, sym);
+            }
+        }
+
+        if self.config.verbosity.traps >= 1
+            && let Some(cause) = self.cpu.last_trap()
+        {
+            println!(
+                "[trap] pc=0x{:08x}{} cause={:?}",
+                instr_pc,
+                self.symbol_annotation(instr_pc),
+                cause
+            );
+            if self.config.verbosity.traps >= 2 && self.cpu.call_stack().is_some() {
+                for line in self.backtrace() {
+                    println!("[trap]   {}", line);
+                }
+            }
+        }
+
+        // CSR 写入跟踪：打印 PC 和新旧值
+        if !self.config.trace_csrs.is_empty()
+            && let Some(ev) = self.cpu.last_csr_write()
+            && self.config.trace_csrs.contains(&ev.addr)
+        {
+            println!(
+                "[csr] pc=0x{:08x} csr=0x{:03x}: 0x{:08x} -> 0x{:08x}",
+                ev.pc, ev.addr, ev.old, ev.new
+            );
+        }
+
+        if !self.events.is_empty() {
+            self.dispatch_events();
+        }
+        state
+    }
+
+    /// 尝试在宿主侧仿真 PC 所指的 ECALL
+    ///
+    /// 识别的调用号按 [`crate::syscalls`] 处理并计为一条指令；
+    /// 返回 `None` 表示调用号未识别，调用方应正常执行这条 ECALL
+    /// 让它陷入 mtvec。
+    fn try_emulate_ecall(&mut self) -> Option<CpuState> {
+        let emu = self.syscalls.as_mut()?;
+        let mut heap = self.heap.as_ref().map(|h| h.borrow_mut());
+        let outcome = emu.handle(&self.cpu, &mut self.memory, heap.as_deref_mut());
+        drop(heap);
+
+        match outcome {
+            SyscallOutcome::Handled(ret) => {
+                self.cpu.write_reg(10, ret);
+                let pc = self.cpu.pc();
+                self.cpu.set_pc(pc.wrapping_add(4));
+            }
+            SyscallOutcome::Exit(code) => {
+                self.exit_code = Some(code);
+                self.cpu.set_state(CpuState::Exited(code));
+            }
+            SyscallOutcome::Unhandled => return None,
+        }
+        self.instructions_executed += 1;
+        if !self.events.is_empty() {
+            self.dispatch_events();
+        }
+        Some(self.cpu.state())
+    }
+
+    /// 按 HTIF 退出编码把 tohost 值转为最终状态并记录退出码
+    ///
+    /// 低位为 1 表示退出请求，`value >> 1` 即退出码（0 为 pass）；
+    /// 其余写入维持原有的 Halted 语义。
+    fn htif_exit_state(&mut self, value: u32) -> CpuState {
+        let state = if value & 1 == 1 {
+            let code = (value >> 1) as i32;
+            self.exit_code = Some(code);
+            CpuState::Exited(code)
+        } else {
+            CpuState::Halted
+        };
+        self.cpu.set_state(state);
+        state
+    }
+
+    /// 系统调用仿真器（启用了 `with_syscalls` 时存在），用于重定向
+    /// 客体的标准流
+    pub fn syscalls_mut(&mut self) -> Option<&mut SyscallEmulator> {
+        self.syscalls.as_mut()
+    }
+
+    /// 尝试在宿主侧仿真 PC 所指的 semihosting ebreak
+    ///
+    /// 只有 ebreak 前后分别是 `slli x0,x0,0x1f` / `srai x0,x0,7`
+    /// 标记时才是 semihosting 调用；识别的操作按
+    /// [`crate::semihosting`] 处理并计为一条指令。返回 `None` 表示
+    /// 不是 semihosting 序列或操作号未识别，调用方应正常执行这条
+    /// ebreak 让它陷入 mtvec。
+    fn try_emulate_semihost(&mut self) -> Option<CpuState> {
+        let pc = self.cpu.pc();
+        if self.memory.load32(pc.wrapping_sub(4)) != Ok(SEMIHOST_PRE)
+            || self.memory.load32(pc.wrapping_add(4)) != Ok(SEMIHOST_POST)
+        {
+            return None;
+        }
+
+        let emu = self.semihost.as_mut()?;
+        match emu.handle(&self.cpu, &mut self.memory) {
+            SemihostOutcome::Handled(ret) => {
+                self.cpu.write_reg(10, ret);
+                self.cpu.set_pc(pc.wrapping_add(4));
+            }
+            SemihostOutcome::Exit(code) => {
+                self.exit_code = Some(code);
+                self.cpu.set_state(CpuState::Exited(code));
+            }
+            SemihostOutcome::Unhandled => return None,
+        }
+        self.instructions_executed += 1;
+        if !self.events.is_empty() {
+            self.dispatch_events();
+        }
+        Some(self.cpu.state())
+    }
+
+    /// semihosting 仿真器（启用了 `with_semihosting` 时存在），用于
+    /// 重定向客体的控制台输出
+    pub fn semihost_mut(&mut self) -> Option<&mut SemihostEmulator> {
+        self.semihost.as_mut()
+    }
+
+    /// 客体报告的退出码（exit 系统调用、semihosting 退出或 HTIF
+    /// tohost），尚未退出时为 None。CLI 据此把客体状态带回 shell
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// 推进 CLINT 定时器 `elapsed` 条指令并同步 mip.MTIP
+    ///
+    /// 中断的评估（检查 mie/mstatus.MIE、唤醒 WFI、进入 trap）由
+    /// `CpuCore::step` 在下一次取指前完成。
+    fn tick_clint(&mut self, elapsed: u64) {
+        use crate::cpu::TrapCause;
+
+        let Some(ref mut clint) = self.clint else {
+            return;
+        };
+        clint.tick(elapsed);
+        if clint.timer_pending() {
+            self.cpu.raise_interrupt(TrapCause::MachineTimerInterrupt);
+        } else {
+            self.cpu.clear_interrupt(TrapCause::MachineTimerInterrupt);
+        }
+    }
+
+    /// 推进自定义外设并把它们的中断线汇集成外部中断
+    ///
+    /// 安装了 PLIC 时设备电平作为 PLIC 源输入，MEIP/SEIP 由上下文
+    /// 0/1 仲裁驱动；否则走扁平的 [`IrqAggregator`] 直连 MEIP。
+    fn tick_devices(&mut self, elapsed: u64) {
+        use crate::cpu::TrapCause;
+
+        if self.devices.is_empty() && self.plic_ctrl.is_none() {
+            return;
+        }
+        if let Some(ref mut plic) = self.plic_ctrl {
+            for (i, dev) in self.devices.iter_mut().enumerate() {
+                dev.tick(elapsed);
+                plic.set_source(i as u32 + 1, dev.pending_irq());
+            }
+            if plic.ext_interrupt(0) {
+                self.cpu.raise_interrupt(TrapCause::MachineExternalInterrupt);
+            } else {
+                self.cpu.clear_interrupt(TrapCause::MachineExternalInterrupt);
+            }
+            if plic.ext_interrupt(1) {
+                self.cpu.raise_interrupt(TrapCause::SupervisorExternalInterrupt);
+            } else {
+                self.cpu.clear_interrupt(TrapCause::SupervisorExternalInterrupt);
+            }
+            return;
+        }
+        for (i, dev) in self.devices.iter_mut().enumerate() {
+            dev.tick(elapsed);
+            self.plic.set_level(i as u32 + 1, dev.pending_irq());
+        }
+        if self.plic.any_pending() {
+            self.cpu.raise_interrupt(TrapCause::MachineExternalInterrupt);
+        } else {
+            self.cpu.clear_interrupt(TrapCause::MachineExternalInterrupt);
+        }
+    }
+
+    /// 运行指定数量的指令
+    ///
+    /// 如果 ELF 中存在 tohost 符号，会按 `htif_poll_interval` 分块执行并轮询
+    /// tohost：检测到非零写入时执行 ACK、记录到 `last_tohost` 并停机，使得
+    /// 使用 HTIF 退出的程序在通用 run 接口下也能及时结束。
+    pub fn run(&mut self, max_instructions: u64) -> (u64, CpuState) {
+        if self.tohost_addr.is_some() {
+            let interval = self.config.htif_poll_interval.max(1);
+            let mut executed = 0;
+            while executed < max_instructions {
+                let chunk = interval.min(max_instructions - executed);
+                let (n, state) = self.run_chunk(chunk);
+                executed += n;
+
+                if let Some(value) = self.check_tohost() {
+                    self.last_tohost = Some(value);
+                    let state = self.htif_exit_state(value);
+                    return (executed, state);
+                }
+
+                if state != CpuState::Running {
+                    return (executed, state);
+                }
+            }
+            return (executed, self.cpu.state());
+        }
+
+        self.run_chunk(max_instructions)
+    }
+
+    /// 不带 HTIF 轮询地运行一段指令
+    fn run_chunk(&mut self, max_instructions: u64) -> (u64, CpuState) {
+        // 没有事件、CSR 跟踪、宿主桩、定时器、系统调用仿真和
+        // 逐指令输出时走批量快速路径
+        if self.events.is_empty()
+            && self.config.trace_csrs.is_empty()
+            && self.host_stubs.is_empty()
+            && self.clint.is_none()
+            && self.plic_ctrl.is_none()
+            && self.devices.is_empty()
+            && self.syscalls.is_none()
+            && self.semihost.is_none()
+            && !self.config.verbosity.per_instruction()
+        {
+            let (executed, state) = if self.uart.is_some() || self.rng.is_some() {
+                let mut bus = MmioBus {
+                    ram: &mut self.memory,
+                    uart: self.uart.as_mut(),
+                    clint: None,
+                    rng: self.rng.as_mut(),
+                    plic: None,
+                    devices: &mut [],
+                    events: self.event_bus.as_ref(),
+                };
+                self.cpu.run(&mut bus, max_instructions)
+            } else {
+                self.cpu.run(&mut self.memory, max_instructions)
+            };
+            self.instructions_executed += executed;
+            return (executed, state);
+        }
+
+        let mut executed = 0;
+        for _ in 0..max_instructions {
+            let state = self.step();
+            executed += 1;
+            // 有定时器或外设时 WFI 不是终态：继续走时钟直到中断唤醒
+            if state == CpuState::WaitForInterrupt
+                && (self.clint.is_some() || self.plic_ctrl.is_some() || !self.devices.is_empty())
+            {
+                continue;
+            }
+            if state != CpuState::Running {
+                return (executed, state);
+            }
+        }
+        (executed, self.cpu.state())
+    }
+
+    /// 以接近真实时间的节奏运行（配速到 `target_mhz` 百万条指令每秒）
+    ///
+    /// 把执行切成约 1 毫秒模拟时间的片，每片结束后若模拟时间
+    /// 领先墙钟就 sleep 补齐，使 mtime 的推进与墙钟大致成比例。
+    /// 与 UART 控制台或外部 GDB 交互时，全速仿真会让依赖定时的
+    /// 客体代码错乱，用配速换取正确的交互节奏；落后于墙钟时
+    /// 不会加速追赶。停止语义与 [`SimEnv::run`] 一致，指令数上限
+    /// 取自配置的 `max_instructions`（0 表示不限）。
+    pub fn run_realtime(&mut self, target_mhz: f64) -> (u64, CpuState) {
+        use std::time::{Duration, Instant};
+
+        let hz = target_mhz.max(0.000_001) * 1_000_000.0;
+        // 每个配速片约 1ms 的模拟时间，至少 1 条指令
+        let slice = ((hz / 1_000.0) as u64).max(1);
+        let max = if self.config.max_instructions > 0 {
+            self.config.max_instructions
+        } else {
+            u64::MAX
+        };
+
+        let start = Instant::now();
+        let mut total = 0u64;
+        while total < max {
+            let budget = slice.min(max - total);
+            let (executed, state) = self.run(budget);
+            total += executed;
+            if state != CpuState::Running {
+                return (total, state);
+            }
+            let simulated = Duration::from_secs_f64(total as f64 / hz);
+            let elapsed = start.elapsed();
+            if simulated > elapsed {
+                std::thread::sleep(simulated - elapsed);
+            }
+        }
+        (total, self.cpu.state())
+    }
+
+    /// 运行直到停止条件
+    ///
+    /// 停止条件：
+    /// - 达到最大指令数
+    /// - CPU 状态变为非 Running
+    /// - 任意 trap（如果 stop_on_trap 为 true）
+    /// - 命中 `stop_conditions` 中的任意条件
+    ///
+    /// 命中的条件记录在 `stop_reason` 中（未命中则为 None）
+    pub fn run_until_halt(&mut self) -> (u64, CpuState) {
+        let max = if self.config.max_instructions > 0 {
+            self.config.max_instructions
+        } else {
+            u64::MAX
+        };
+
+        self.stop_reason = None;
+
+        // 没有停止条件时走快速路径
+        if !self.config.stop_on_trap && self.config.stop_conditions.is_empty() {
+            return self.run(max);
+        }
+
+        // OnStoreTo 条件通过值变化检测：记录初始值
+        let mut watched: Vec<(u32, MemResult32)> = self
+            .config
+            .stop_conditions
+            .iter()
+            .filter_map(|cond| match cond {
+                StopCondition::OnStoreTo(addr) => Some((*addr, self.memory.load32(*addr).ok())),
+                _ => None,
+            })
+            .collect();
+
+        let interval = self.config.htif_poll_interval.max(1);
+        let mut executed = 0;
+        for _ in 0..max {
+            let state = self.step();
+            executed += 1;
+
+            if let Some(reason) = self.evaluate_stop_conditions(&mut watched) {
+                self.stop_reason = Some(reason);
+                return (executed, state);
+            }
+
+            // 与 run() 一致：按间隔轮询 tohost，及时响应 HTIF 退出请求
+            if self.tohost_addr.is_some()
+                && executed % interval == 0
+                && let Some(value) = self.check_tohost()
+            {
+                self.last_tohost = Some(value);
+                let state = self.htif_exit_state(value);
+                return (executed, state);
+            }
+
+            if state != CpuState::Running {
+                return (executed, state);
+            }
+        }
+
+        (executed, self.cpu.state())
+    }
+
+    /// 运行直到任意一个一次性条件命中
+    ///
+    /// 返回 `(已执行指令数, 命中的条件)`；命中的条件按值返回，
+    /// 未命中而停止（CPU 状态变为非 Running 或达到
+    /// `max_instructions` 预算）时为 `None`。条件按传入顺序评估，
+    /// 多个同时满足时返回最靠前的一个。
+    ///
+    /// [`RunCondition::MemWrite`] 通过临时监视点实现，调用结束后
+    /// 自动移除，不影响已有的用户监视点；命中时 CPU 状态恢复为
+    /// Running，停止原因只体现在返回值中。配置级的
+    /// [`StopCondition`] 与 `stop_reason` 不受本方法影响。
+    pub fn run_until(&mut self, mut conditions: Vec<RunCondition>) -> (u64, Option<RunCondition>) {
+        let max = if self.config.max_instructions > 0 {
+            self.config.max_instructions
+        } else {
+            u64::MAX
+        };
+
+        // MemWrite 条件挂临时写监视点，结束时成对恢复
+        let saved_watchpoints = self.cpu.watchpoint_len();
+        for cond in &conditions {
+            if let RunCondition::MemWrite(range) = cond {
+                self.cpu
+                    .add_watchpoint(range.clone(), crate::cpu::WatchKind::Write);
+            }
+        }
+
+        let interval = self.config.htif_poll_interval.max(1);
+        let mut executed = 0u64;
+        let mut fired: Option<usize> = None;
+
+        'run: while executed < max {
+            let state = self.step();
+            executed += 1;
+
+            // 命中监视点先判明是否属于某个 MemWrite 条件；是则恢复
+            // Running（停止通过返回值传达），否则按用户监视点停止
+            let write_hit = match state {
+                CpuState::WatchpointHit(addr) => self
+                    .cpu
+                    .last_watchpoint()
+                    .filter(|hit| hit.is_store)
+                    .map(|_| addr),
+                _ => None,
+            };
+
+            for (idx, cond) in conditions.iter_mut().enumerate() {
+                let hit = match cond {
+                    RunCondition::PcEquals(addr) => self.cpu.pc() == *addr,
+                    RunCondition::InstructionCount(n) => executed >= *n,
+                    RunCondition::TohostWrite => {
+                        self.tohost_addr.is_some_and(|addr| {
+                            self.memory.load32(addr).is_ok_and(|v| v != 0)
+                        })
+                    }
+                    RunCondition::AnyTrap => self.cpu.last_trap().is_some(),
+                    RunCondition::MemWrite(range) => {
+                        write_hit.is_some_and(|addr| range.contains(&addr))
+                    }
+                    RunCondition::Predicate(f) => f(self),
+                };
+                if hit {
+                    if matches!(cond, RunCondition::MemWrite(_)) {
+                        self.cpu.set_state(CpuState::Running);
+                    }
+                    fired = Some(idx);
+                    break 'run;
+                }
+            }
+
+            // 与 run() 一致：按间隔轮询 tohost，及时响应 HTIF 退出请求
+            if self.tohost_addr.is_some()
+                && executed.is_multiple_of(interval)
+                && let Some(value) = self.check_tohost()
+            {
+                self.last_tohost = Some(value);
+                self.htif_exit_state(value);
+                break;
+            }
+
+            if self.cpu.state() != CpuState::Running {
+                break;
+            }
+        }
+
+        self.cpu.truncate_watchpoints(saved_watchpoints);
+        let fired = fired.and_then(|idx| conditions.into_iter().nth(idx));
+        (executed, fired)
+    }
+
+    /// 检查是否命中任意停止条件，返回第一个命中的条件
+    fn evaluate_stop_conditions(
+        &self,
+        watched: &mut [(u32, MemResult32)],
+    ) -> Option<StopCondition> {
+        let last_trap = self.cpu.last_trap();
+
+        if self.config.stop_on_trap && last_trap.is_some() {
+            return Some(StopCondition::OnTrap);
+        }
+
+        for cond in &self.config.stop_conditions {
+            match cond {
+                StopCondition::OnTrap => {
+                    if last_trap.is_some() {
+                        return Some(*cond);
+                    }
+                }
+                StopCondition::OnEbreak => {
+                    if last_trap == Some(crate::cpu::TrapCause::Breakpoint) {
+                        return Some(*cond);
+                    }
+                }
+                StopCondition::OnStoreTo(addr) => {
+                    let current = self.memory.load32(*addr).ok();
+                    if let Some(entry) = watched.iter_mut().find(|(a, _)| a == addr)
+                        && entry.1 != current
+                    {
+                        entry.1 = current;
+                        return Some(*cond);
+                    }
+                }
+                StopCondition::OnPc(addr) => {
+                    if self.cpu.pc() == *addr {
+                        return Some(*cond);
+                    }
+                }
+                StopCondition::OnCsrWrite(addr) => {
+                    if self.cpu.last_csr_write().is_some_and(|ev| ev.addr == *addr) {
+                        return Some(*cond);
+                    }
+                }
+                StopCondition::OnTohostValue(value) => {
+                    if let Some(addr) = self.tohost_addr
+                        && self.memory.load32(addr) == Ok(*value)
+                    {
+                        return Some(*cond);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// 获取 CPU 引用
+    pub fn cpu(&self) -> &CpuCore {
+        &self.cpu
+    }
+
+    /// 获取 CPU 可变引用
+    pub fn cpu_mut(&mut self) -> &mut CpuCore {
+        &mut self.cpu
+    }
+
+    /// 获取内存引用
+    pub fn memory(&self) -> &GuestMemory {
+        &self.memory
+    }
+
+    /// 获取内存可变引用
+    pub fn memory_mut(&mut self) -> &mut GuestMemory {
+        &mut self.memory
+    }
+
+    /// 断言 `addr` 起的内存与 `expected` 逐字节一致
+    ///
+    /// 运行前后均可调用：运行前验证镜像装载结果，运行后做黄金
+    /// 内存比对。不一致（含地址读不到）时返回 [`MemDiff`]，其
+    /// `Display` 是可读的逐字节差异，自检固件测试可以直接
+    /// `.map_err(|d| d.to_string())` 或在 `expect` 消息里打印。
+    pub fn expect_mem(&self, addr: u32, expected: &[u8]) -> Result<(), MemDiff> {
+        let mut mismatches = Vec::new();
+        for (i, &want) in expected.iter().enumerate() {
+            let byte_addr = addr.wrapping_add(i as u32);
+            let got = self.memory.load8(byte_addr).ok();
+            if got != Some(want) {
+                mismatches.push((byte_addr, want, got));
+            }
+        }
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(MemDiff { addr, len: expected.len(), mismatches })
+        }
+    }
+
+    /// [`Self::expect_mem`] 的 32 位字便捷版（按小端字节序展开）
+    pub fn expect_mem_words(&self, addr: u32, expected: &[u32]) -> Result<(), MemDiff> {
+        let bytes: Vec<u8> = expected.iter().flat_map(|w| w.to_le_bytes()).collect();
+        self.expect_mem(addr, &bytes)
+    }
+
+    /// 以同一配置重复运行 N 次并聚合统计
+    ///
+    /// 每次运行都从头创建仿真环境并 `run_until_halt`。运行间比较
+    /// 架构结果（最终 PC、整数寄存器、执行指令数、CPU 状态），
+    /// 任何差异都报错——这是防止非确定性混进核心的防线（未来引入
+    /// 随机调度后尤其重要）。耗时统计按各次运行的墙钟时间给出
+    /// 均值和标准差。
+    pub fn run_replicated(
+        config: &SimConfig,
+        runs: usize,
+    ) -> Result<ReplicationReport, SimError> {
+        if runs == 0 {
+            return Err(SimError::Config("Replication requires at least one run".into()));
+        }
+
+        let mut reference: Option<(u32, [u32; 32], u64, CpuState)> = None;
+        let mut seconds = Vec::with_capacity(runs);
+
+        for run in 0..runs {
+            let mut env = SimEnv::from_config(config.clone())?;
+            let start = std::time::Instant::now();
+            env.run_until_halt();
+            seconds.push(start.elapsed().as_secs_f64());
+
+            let result = (
+                env.cpu.pc(),
+                env.cpu.snapshot().int,
+                env.instructions_executed,
+                env.cpu.state(),
+            );
+
+            match &reference {
+                None => reference = Some(result),
+                Some(expected) => {
+                    if *expected != result {
+                        return Err(SimError::Config(format!(
+                            "Nondeterministic result in run {}: \
+                             pc=0x{:08x}/0x{:08x}, instructions={}/{}",
+                            run, expected.0, result.0, expected.2, result.2
+                        )));
+                    }
+                }
+            }
+        }
+
+        let (final_pc, _, instructions, final_state) = reference.unwrap();
+        let mean = seconds.iter().sum::<f64>() / runs as f64;
+        let variance =
+            seconds.iter().map(|s| (s - mean) * (s - mean)).sum::<f64>() / runs as f64;
+
+        Ok(ReplicationReport {
+            runs,
+            instructions,
+            final_pc,
+            final_state,
+            mean_seconds: mean,
+            stddev_seconds: variance.sqrt(),
+        })
+    }
+
+    /// 打印仿真状态
+    pub fn dump(&self) {
+        println!("=== SimEnv Status ===");
+        println!("Instructions executed: {}", self.instructions_executed);
+        self.cpu.dump_regs();
+    }
+
+    /// 重定向 HTIF 控制台的输出
+    pub fn set_htif_console_sink(&mut self, sink: Box<dyn Write>) {
+        self.htif_console.set_sink(sink);
+    }
+
+    /// 设置 HTIF 控制台 getchar 的输入源
+    pub fn set_htif_console_input(&mut self, input: Box<dyn Read>) {
+        self.htif_console.set_input(input);
+    }
+
+    /// 检查 tohost 并处理 HTIF 命令
+    ///
+    /// tohost 为 64 位（低字在 `tohost_addr`，高字随后）：设备号在
+    /// 位 63:56，命令在 55:48，载荷在 47:0。控制台命令（设备 1）在
+    /// 宿主侧完成、经 fromhost 应答后继续执行，返回 `None`；设备 0
+    /// （syscall/exit）及未知设备的写入返回 `Some(低 32 位)`，调用方
+    /// 照旧据此停机并解析 pass/fail 编码。
+    pub fn check_tohost(&mut self) -> Option<u32> {
+        let addr = self.tohost_addr?;
+        let lo = self.memory.load32(addr).ok()?;
+        let hi = self.memory.load32(addr.wrapping_add(4)).unwrap_or(0);
+        if lo == 0 && hi == 0 {
+            return None;
+        }
+
+        let device = (hi >> 24) as u8;
+        let command = (hi >> 16) as u8;
+        if self.config.verbosity.htif >= 1 {
+            println!(
+                "[htif] tohost=0x{:08x}{:08x} dev={} cmd={}",
+                hi, lo, device, command
+            );
+        }
+
+        match (device, command) {
+            // 控制台 putchar：低字节送往 sink
+            (1, 1) => {
+                self.htif_console.putchar(lo as u8);
+                self.reply_fromhost(device, command, 0);
+                self.clear_tohost();
+                None
+            }
+            // 控制台 getchar：有字符回 ch + 1，否则 0
+            (1, 0) => {
+                let payload = self.htif_console.getchar().map_or(0, |ch| ch as u64 + 1);
+                self.reply_fromhost(device, command, payload);
+                self.clear_tohost();
+                None
+            }
+            // 设备 0（syscall/exit）与未知设备：按测试退出约定处理
+            _ => {
+                self.acknowledge_tohost(lo);
+                Some(lo)
+            }
+        }
+    }
+
+    fn clear_htif_mailboxes(&mut self) {
+        self.clear_tohost();
+        if let Some(addr) = self.fromhost_addr {
+            let _ = self.memory.store32(addr, 0);
+            let _ = self.memory.store32(addr.wrapping_add(4), 0);
+        }
+    }
+
+    /// 清空 64 位 tohost 信箱
+    fn clear_tohost(&mut self) {
+        if let Some(addr) = self.tohost_addr {
+            let _ = self.memory.store32(addr, 0);
+            let _ = self.memory.store32(addr.wrapping_add(4), 0);
+        }
+    }
+
+    /// 写 fromhost 应答包：`(dev << 56) | (cmd << 48) | payload`
+    fn reply_fromhost(&mut self, device: u8, command: u8, payload: u64) {
+        if let Some(addr) = self.fromhost_addr {
+            let hi = ((device as u32) << 24)
+                | ((command as u32) << 16)
+                | ((payload >> 32) as u32 & 0xFFFF);
+            let _ = self.memory.store32(addr, payload as u32);
+            let _ = self.memory.store32(addr.wrapping_add(4), hi);
+        }
+    }
+
+    fn acknowledge_tohost(&mut self, value: u32) {
+        self.clear_tohost();
+        if let Some(addr) = self.fromhost_addr {
+            let _ = self.memory.store32(addr, value);
+        }
+    }
+
+    /// 运行 ISA 测试
+    ///
+    /// 执行程序直到 tohost 被写入，或达到最大指令数
+    ///
+    /// # 参数
+    ///
+    /// * `max_instructions` - 最大执行指令数（0 表示使用默认值 1000000）
+    ///
+    /// # 返回
+    ///
+    /// * `TestResult` - 测试结果（Pass/Fail/Timeout）
+    /// * `u64` - 执行的指令数
+    pub fn run_isa_test(&mut self, max_instructions: u64) -> (TestResult, u64) {
+        let max = if max_instructions > 0 {
+            max_instructions
+        } else {
+            1_000_000 // 默认最大 100 万条指令
+        };
+
+        // 如果没有 tohost 地址，直接运行到停止
+        if self.tohost_addr.is_none() {
+            let start = self.instructions_executed;
+            let (executed, _state) = self.run(max);
+            let delta = self.instructions_executed - start;
+            let reported = if delta == 0 { executed } else { delta };
+            return (TestResult::Timeout, reported);
+        }
+
+        self.clear_htif_mailboxes();
+        let start = self.instructions_executed;
+
+        for _ in 0..max {
+            let state = self.step();
+            
+            // 检查 tohost
+            if let Some(value) = self.check_tohost() {
+                let delta = self.instructions_executed - start;
+                return (TestResult::from_tohost(value), delta);
+            }
+            
+            // 检查 CPU 状态（非法指令等）
+            if state != CpuState::Running {
+                // 可能是 trap，继续检查 tohost
+                if let Some(value) = self.check_tohost() {
+                    let delta = self.instructions_executed - start;
+                    return (TestResult::from_tohost(value), delta);
+                }
+                // CPU 停止但 tohost 未写入
+                break;
+            }
+        }
+
+        // 超时或 CPU 异常停止
+        let delta = self.instructions_executed - start;
+        (TestResult::Timeout, delta)
+    }
+
+    /// 重置仿真环境
+    pub fn reset(&mut self) -> Result<(), SimError> {
+        // 复位 CPU 架构状态（解码器与调试配置保留）
+        let entry_pc = self.config.entry_pc.unwrap_or(self.config.memory.base);
+        self.cpu.set_reset_vector(entry_pc);
+        self.cpu.reset();
+        self.instructions_executed = 0;
+        self.stop_reason = None;
+        self.last_tohost = None;
+        self.events.clear();
+
+        // 整块内存清零后再重载镜像：段外区域（BSS 之外的堆栈、
+        // 上次运行的写入）不能残留
+        self.memory
+            .fill(self.config.memory.base, self.config.memory.size, 0)
+            .map_err(SimError::from)?;
+
+        // 如果有 ELF，重新加载
+        if let Some(ref elf_path) = self.config.elf_path {
+            let elf = ElfInfo::parse(elf_path)?;
+            self.tohost_addr = elf.find_symbol("tohost");
+            self.fromhost_addr = elf.find_symbol("fromhost");
+            load_segments_into_memory(&mut self.memory, &self.config.memory, &elf.segments)?;
+            self.symbols = elf.symbols;
+            // 设置入口点
+            if self.config.entry_pc.is_none() {
+                self.cpu.set_pc(elf.entry);
+            }
+        } else if let Some(ref bin_path) = self.config.bin_path {
+            let data = std::fs::read(bin_path)?;
+            ensure_range(&self.config.memory, self.config.bin_load_addr, data.len())?;
+            self.memory
+                .write_bytes(self.config.bin_load_addr, &data)
+                .map_err(SimError::from)?;
+            if self.config.entry_pc.is_none() {
+                self.cpu.set_pc(self.config.bin_load_addr);
+            }
+        }
+
+        // 追加镜像与设备树同样要恢复到加载时的状态
+        for path in &self.config.extra_elf_paths {
+            let elf = ElfInfo::parse(path)?;
+            load_segments_into_memory(&mut self.memory, &self.config.memory, &elf.segments)?;
+            self.symbols.extend(elf.symbols);
+        }
+        if let Some((ref path, addr)) = self.config.dtb {
+            let blob = std::fs::read(path)?;
+            ensure_range(&self.config.memory, addr, blob.len())?;
+            self.memory.write_bytes(addr, &blob).map_err(SimError::from)?;
+            self.cpu.write_reg(11, addr); // a1
+        }
+
+        self.clear_htif_mailboxes();
+        self.arm_csr_watches();
+        self.init_stack(false);
+        self.inject_args()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+
+    #[test]
+    fn test_run_replicated_deterministic() {
+        // 把演示程序（1+2+...+10 后 ecall）写成临时 bin 文件，
+        // 让每次运行都能从配置完整重建
+        let program: [u32; 7] = [
+            0x00000093, 0x00100113, 0x00B00193, 0x002080B3, 0x00110113, 0xFE314CE3, 0x00000073,
+        ];
+        let bytes: Vec<u8> = program.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let path = std::env::temp_dir().join("allude_sim_replication_test.bin");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let config = SimConfig::new()
+            .with_bin_path(path.to_str().unwrap(), 0)
+            .with_entry_pc(0)
+            .with_memory_size(4096)
+            .with_max_instructions(1000)
+            .with_stop_on_trap(true);
+
+        let report = SimEnv::run_replicated(&config, 3).expect("运行应确定");
+        assert_eq!(report.runs, 3);
+        assert!(report.instructions > 0);
+        assert!(report.mean_seconds >= 0.0);
+        assert!(report.stddev_seconds >= 0.0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_custom_device_mmio_and_irq() {
+        use crate::devices::Device;
+
+        // 几个 tick 后拉高中断线的设备，寄存器 0 返回固定标识
+        struct PulseDev {
+            ticks: u64,
+        }
+
+        impl Device for PulseDev {
+            fn base(&self) -> u32 {
+                0x2000_0000
+            }
+            fn size(&self) -> u32 {
+                8
+            }
+            fn mmio_read(&self, offset: u32, _width: u32) -> u32 {
+                if offset == 0 { 0xCAFE_0001 } else { 0 }
+            }
+            fn mmio_write(&mut self, _offset: u32, _width: u32, _value: u32) {}
+            fn tick(&mut self, cycles: u64) {
+                self.ticks += cycles;
+            }
+            fn pending_irq(&self) -> bool {
+                self.ticks >= 3
+            }
+        }
+
+        // lui x2, 0x20000 ; lw x1, 0(x2) ; nop ; nop ; ecall
+        let program: [u32; 5] = [0x20000137, 0x00012083, 0x00000013, 0x00000013, 0x00000073];
+        let bytes: Vec<u8> = program.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let path = std::env::temp_dir().join("allude_sim_device_test.bin");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let config = SimConfig::new()
+            .with_bin_path(path.to_str().unwrap(), 0)
+            .with_entry_pc(0)
+            .with_memory_size(4096)
+            .with_stop_on_trap(true);
+        let mut env = SimEnv::from_config(config).expect("环境构建应成功");
+        assert_eq!(env.add_device(Box::new(PulseDev { ticks: 0 })), 1);
+
+        let (_, state) = env.run(10);
+        assert_eq!(state, CpuState::Running, "ecall 前应执行完 MMIO 读取");
+        assert_eq!(env.cpu.read_reg(1), 0xCAFE_0001, "lw 应读到设备寄存器");
+        assert_eq!(env.pending_irq_source(), Some(1), "设备中断应汇集到源 1");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_with_stack_sets_sp_and_guards_overflow() {
+        // 栈 [0x800, 0x1000)，保护区到内存底：sw 打进保护区应命中
+        let config = SimConfig::new()
+            .with_entry_pc(0)
+            .with_memory_size(4096)
+            .with_stack(0x1000, 0x800);
+        let mut env = SimEnv::from_config(config).expect("环境构建应成功");
+        assert_eq!(env.cpu.read_reg(2), 0x1000, "sp 指向 16 字节对齐的栈顶");
+
+        // addi x1, x0, 1 ; sw x1, 0x700(x0)（保护区内） ; j .
+        env.memory.store32(0, 0x0010_0093).unwrap();
+        env.memory.store32(4, 0x7010_2023).unwrap();
+        env.memory.store32(8, 0x0000_006F).unwrap();
+
+        let (_, state) = env.run(10);
+        assert!(
+            matches!(state, CpuState::WatchpointHit(0x700)),
+            "保护区写入应命中监视点: {state:?}"
+        );
+
+        // 栈区间内的正常压栈不受影响
+        env.cpu.set_state(CpuState::Running);
+        env.reset().unwrap();
+        assert_eq!(env.cpu.read_reg(2), 0x1000, "reset 后 sp 重新就位");
+        env.memory.store32(0, 0xFF01_0113).unwrap(); // addi sp, sp, -16
+        env.memory.store32(4, 0x0011_2023).unwrap(); // sw x1, 0(sp)
+        env.memory.store32(8, 0x0000_006F).unwrap(); // j .
+        let (_, state) = env.run(5);
+        assert_eq!(state, CpuState::Running, "栈内访问不触发保护区");
+    }
+
+    #[test]
+    fn test_backtrace_symbolizes_call_chain() {
+        let config = SimConfig::new()
+            .with_entry_pc(0)
+            .with_memory_size(4096)
+            .with_call_stack();
+        let mut env = SimEnv::from_config(config).expect("环境构建应成功");
+        env.symbols.push(crate::sim_env::ElfSymbol {
+            name: "main".into(),
+            addr: 0,
+            size: 8,
+        });
+        env.symbols.push(crate::sim_env::ElfSymbol {
+            name: "helper".into(),
+            addr: 0x20,
+            size: 8,
+        });
+
+        env.memory.store32(0x00, 0x020000EF).unwrap(); // jal ra, helper
+        env.memory.store32(0x20, 0x0000006F).unwrap(); // helper: j .
+
+        env.run(2);
+        let bt = env.backtrace();
+        assert_eq!(bt.len(), 2);
+        assert!(bt[0].starts_with("#0 0x00000020 <helper>"), "{}", bt[0]);
+        assert!(bt[1].starts_with("#1 0x00000000 <main>"), "{}", bt[1]);
+    }
+
+    #[test]
+    fn test_with_args_builds_argv_layout() {
+        let config = SimConfig::new()
+            .with_entry_pc(0)
+            .with_memory_size(4096)
+            .with_stack(0x1000, 0x800)
+            .with_args(&["prog", "--flag"]);
+        let env = SimEnv::from_config(config).expect("环境构建应成功");
+
+        let argc = env.cpu.read_reg(10);
+        let argv = env.cpu.read_reg(11);
+        let envp = env.cpu.read_reg(12);
+        let sp = env.cpu.read_reg(2);
+        assert_eq!(argc, 2);
+        assert!(sp.is_multiple_of(16), "sp 应 16 字节对齐: {sp:#x}");
+        assert_eq!(env.memory.load32(sp).unwrap(), 2, "sp 指向 argc");
+        assert_eq!(argv, sp + 4, "argv 紧随 argc");
+
+        let argv0 = env.memory.load32(argv).unwrap();
+        let argv1 = env.memory.load32(argv + 4).unwrap();
+        assert_eq!(env.memory.read_bytes(argv0, 5).unwrap(), b"prog\0");
+        assert_eq!(env.memory.read_bytes(argv1, 7).unwrap(), b"--flag\0");
+        assert_eq!(env.memory.load32(argv + 8).unwrap(), 0, "argv 以 NULL 结尾");
+        assert_eq!(env.memory.load32(envp).unwrap(), 0, "envp 为空表");
+    }
+
+    #[test]
+    fn test_call_function_abi_harness() {
+        let config = SimConfig::new().with_entry_pc(0).with_memory_size(4096);
+        let mut env = SimEnv::from_config(config).expect("环境构建应成功");
+
+        // add_u32: add a0, a0, a1 ; ret
+        env.memory.store32(0x100, 0x00B5_0533).unwrap();
+        env.memory.store32(0x104, 0x0000_8067).unwrap();
+        // read_sp: mv a0, sp ; ret
+        env.memory.store32(0x200, 0x0001_0513).unwrap();
+        env.memory.store32(0x204, 0x0000_8067).unwrap();
+
+        assert_eq!(env.call_function(0x100_u32, &[2, 40]).unwrap(), 42);
+        // 同一环境可反复调用
+        assert_eq!(env.call_function(0x100_u32, &[7, 5]).unwrap(), 12);
+
+        let sp = env.call_function(0x200_u32, &[]).unwrap();
+        assert!(sp.is_multiple_of(16), "sp 应 16 字节对齐: {sp:#x}");
+        assert!((sp as usize) < 4096, "sp 应落在内存区间内");
+
+        // 无 ELF 时符号目标报配置错误
+        assert!(matches!(
+            env.call_function("add_u32", &[1, 2]),
+            Err(SimError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_map_hook_tohost_mailbox() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        // lui x2, 0x20000 ; addi x1, x0, 42 ; sw x1, 0(x2) ; lw x3, 4(x2) ; j .
+        let program: [u32; 5] = [0x20000137, 0x02A00093, 0x00112023, 0x00412183, 0x0000006F];
+
+        let config = SimConfig::new().with_entry_pc(0).with_memory_size(4096);
+        let mut env = SimEnv::from_config(config).expect("环境构建应成功");
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+
+        // tohost 式信箱：偏移 0 收客体写出的值，偏移 4 读回固定应答
+        let written = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&written);
+        env.map_hook(0x2000_0000..0x2000_0008, move |access| {
+            if let Some(value) = access.write {
+                log.borrow_mut().push((access.offset, access.width, value));
+                0
+            } else {
+                0xACED_0000 | access.offset
+            }
+        });
+
+        let (_, state) = env.run(10);
+        assert_eq!(state, CpuState::Running, "原地跳转保持运行");
+        assert_eq!(*written.borrow(), vec![(0, 4, 42)], "钩子应看到客体的 sw");
+        assert_eq!(env.cpu.read_reg(3), 0xACED_0004, "lw 应读到钩子返回值");
+    }
+
+    #[test]
+    fn test_block_device_from_config() {
+        let img = std::env::temp_dir().join("allude_sim_blk_cfg_test.img");
+        std::fs::write(&img, vec![0u8; 1024]).unwrap();
+
+        // lui x2, 0x10001 ; lw x1, 0(x2)  ; 读块设备魔数寄存器
+        let program: [u32; 3] = [0x10001137, 0x00012083, 0x00000073];
+        let bytes: Vec<u8> = program.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let bin = std::env::temp_dir().join("allude_sim_blk_cfg_test.bin");
+        std::fs::write(&bin, &bytes).unwrap();
+
+        let config = SimConfig::new()
+            .with_bin_path(bin.to_str().unwrap(), 0)
+            .with_entry_pc(0)
+            .with_memory_size(4096)
+            .with_block_device(img.to_str().unwrap());
+        let mut env = SimEnv::from_config(config).expect("环境构建应成功");
+
+        env.run(2);
+        assert_eq!(env.cpu.read_reg(1), 0x7472_6976, "lw 应读到 virtio 魔数");
+
+        let _ = std::fs::remove_file(&img);
+        let _ = std::fs::remove_file(&bin);
+    }
+
+    #[test]
+    fn test_dtb_placed_and_passed_in_a1() {
+        // 程序只执行 ecall；重点是 DTB 放置与 a1 约定
+        let program: [u32; 1] = [0x00000073];
+        let bytes: Vec<u8> = program.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let bin_path = std::env::temp_dir().join("allude_sim_dtb_test.bin");
+        std::fs::write(&bin_path, &bytes).unwrap();
+
+        let dtb_bytes = [0xD0, 0x0D, 0xFE, 0xED, 0x12, 0x34];
+        let dtb_path = std::env::temp_dir().join("allude_sim_dtb_test.dtb");
+        std::fs::write(&dtb_path, dtb_bytes).unwrap();
+
+        let config = SimConfig::new()
+            .with_bin_path(bin_path.to_str().unwrap(), 0)
+            .with_entry_pc(0)
+            .with_memory_size(4096)
+            .with_dtb(dtb_path.to_str().unwrap(), 0x800);
+
+        let mut env = SimEnv::from_config(config).expect("环境构建应成功");
+        assert_eq!(env.cpu.read_reg(11), 0x800, "a1 应携带 DTB 地址");
+        for (i, &b) in dtb_bytes.iter().enumerate() {
+            assert_eq!(env.memory.load8(0x800 + i as u32).unwrap(), b);
+        }
+
+        // 复位后约定同样成立
+        env.cpu.write_reg(11, 0);
+        env.reset().expect("复位应成功");
+        assert_eq!(env.cpu.read_reg(11), 0x800);
+
+        let _ = std::fs::remove_file(&bin_path);
+        let _ = std::fs::remove_file(&dtb_path);
+    }
+
+    #[test]
+    fn test_repeated_elf_paths_accumulate() {
+        let config = SimConfig::new()
+            .with_elf_path("firmware.elf")
+            .with_elf_path("payload.elf")
+            .with_elf_path("overlay.elf");
+        assert_eq!(config.elf_path.as_deref(), Some("firmware.elf"));
+        assert_eq!(config.extra_elf_paths, vec!["payload.elf", "overlay.elf"]);
+    }
+
+    #[test]
+    fn test_load_verilog_hex_program() {
+        // 演示程序（1+2+...+10 后 ecall）写成 $readmemh 格式
+        let program: [u32; 7] = [
+            0x00000093, 0x00100113, 0x00B00193, 0x002080B3, 0x00110113, 0xFE314CE3, 0x00000073,
+        ];
+        let text: String = program.iter().map(|w| format!("{:08x}\n", w)).collect();
+        let path = std::env::temp_dir().join("allude_sim_loader_test.mem");
+        std::fs::write(&path, &text).unwrap();
+
+        let config = SimConfig::new()
+            .with_bin_path(path.to_str().unwrap(), 0)
+            .with_memory_size(4096)
+            .with_max_instructions(1000)
+            .with_stop_on_trap(true);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        assert_eq!(env.cpu.pc(), 0, "入口应为加载基址");
+
+        env.run_until_halt();
+        assert_eq!(env.cpu.read_reg(1), 55);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_load_intel_hex_program_with_entry() {
+        // 同一程序放到 0x100，入口由类型 05 记录给出
+        let program: [u32; 7] = [
+            0x00000093, 0x00100113, 0x00B00193, 0x002080B3, 0x00110113, 0xFE314CE3, 0x00000073,
+        ];
+        let bytes: Vec<u8> = program.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let mut hex = String::new();
+        for (i, chunk) in bytes.chunks(8).enumerate() {
+            let offset = 0x100 + i as u16 * 8;
+            let mut rec = vec![chunk.len() as u8, (offset >> 8) as u8, offset as u8, 0x00];
+            rec.extend_from_slice(chunk);
+            let sum: u8 = rec.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+            rec.push(sum.wrapping_neg());
+            hex.push(':');
+            for b in &rec {
+                hex.push_str(&format!("{:02X}", b));
+            }
+            hex.push('\n');
+        }
+        hex.push_str(":0400000500000100F6\n"); // entry = 0x100
+        hex.push_str(":00000001FF\n");
+        let path = std::env::temp_dir().join("allude_sim_loader_test.hex");
+        std::fs::write(&path, &hex).unwrap();
+
+        let config = SimConfig::new()
+            .with_bin_path(path.to_str().unwrap(), 0)
+            .with_memory_size(4096)
+            .with_max_instructions(1000)
+            .with_stop_on_trap(true);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        assert_eq!(env.cpu.pc(), 0x100, "入口应取自类型 05 记录");
+
+        env.run_until_halt();
+        assert_eq!(env.cpu.read_reg(1), 55);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_write_signature_riscof_format() {
+        let config = SimConfig::new().with_memory_size(4096);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.memory.store32(0x100, 0xDEAD_BEEF).unwrap();
+        env.memory.store32(0x104, 0x0000_0001).unwrap();
+        env.symbols.push(ElfSymbol { name: "begin_signature".into(), addr: 0x100, size: 0 });
+        env.symbols.push(ElfSymbol { name: "end_signature".into(), addr: 0x108, size: 0 });
+
+        let path = std::env::temp_dir().join("allude_sim_signature_test.sig");
+        env.write_signature(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, "deadbeef\n00000001\n");
+        let _ = std::fs::remove_file(&path);
+
+        // 缺少符号时报配置错误
+        env.symbols.clear();
+        assert!(env.write_signature(&path).is_err());
+    }
+
+    #[test]
+    fn test_symbolize_prefers_covering_symbol() {
+        let symbols = vec![
+            ElfSymbol { name: "main".into(), addr: 0x100, size: 0x40 },
+            ElfSymbol { name: "helper".into(), addr: 0x140, size: 0x20 },
+            ElfSymbol { name: "_etext".into(), addr: 0x140, size: 0 },
+            ElfSymbol { name: "loop_top".into(), addr: 0x200, size: 0 },
+        ];
+        assert_eq!(symbolize_in(&symbols, 0x100), Some(("main", 0)));
+        assert_eq!(symbolize_in(&symbols, 0x114), Some(("main", 0x14)));
+        // 同地址时有大小的符号优先于无大小标号
+        assert_eq!(symbolize_in(&symbols, 0x148), Some(("helper", 0x8)));
+        // 无覆盖符号时回退到最近的前驱标号
+        assert_eq!(symbolize_in(&symbols, 0x208), Some(("loop_top", 0x8)));
+        assert_eq!(symbolize_in(&symbols, 0x80), None);
+    }
+
+    #[test]
+    fn test_host_memory_cap() {
+        // 超过上限的客体内存应在创建时报错
+        let config = SimConfig::new()
+            .with_memory_size(1024 * 1024)
+            .with_entry_pc(0)
+            .with_host_memory_cap(64 * 1024);
+        let err = match SimEnv::from_config(config) {
+            Ok(_) => panic!("超限配置不应成功"),
+            Err(e) => e,
+        };
+        assert!(err.to_string().contains("memory cap"), "错误应指明上限: {err}");
+
+        // 上限内正常创建，且能读回用量
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_host_memory_cap(64 * 1024);
+        let env = SimEnv::from_config(config).expect("Failed to create sim env");
+        let usage = env.host_memory_usage();
+        assert_eq!(usage.guest_ram, 4096);
+        assert!(usage.total() >= 4096);
+    }
+
+    #[test]
+    fn test_big_endian_elf_rejected() {
+        // 手工构造一个大端 RV32 ELF 头（无程序段）
+        let mut data = vec![0u8; 52];
+        data[..4].copy_from_slice(&[0x7F, b'E', b'L', b'F']);
+        data[4] = 1; // ELFCLASS32
+        data[5] = 2; // ELFDATA2MSB（大端）
+        data[6] = 1; // EV_CURRENT
+        data[16..18].copy_from_slice(&2u16.to_be_bytes()); // e_type = EXEC
+        data[18..20].copy_from_slice(&0xF3u16.to_be_bytes()); // e_machine = RISC-V
+        data[20..24].copy_from_slice(&1u32.to_be_bytes()); // e_version
+        data[40..42].copy_from_slice(&52u16.to_be_bytes()); // e_ehsize
+
+        let err = ElfInfo::parse_bytes(&data).unwrap_err();
+        let msg = err.to_string();
+        assert!(
+            msg.contains("little-endian"),
+            "错误信息应指明字节序问题: {msg}"
+        );
+    }
+
+    #[test]
+    fn test_verbosity_from_spec() {
+        // 纯数字：所有子系统同级
+        let v = Verbosity::from_spec("2").unwrap();
+        assert_eq!(v, Verbosity::all(2));
+
+        // 逐子系统规格，未提及的保持 0
+        let v = Verbosity::from_spec("loader=2,htif=1").unwrap();
+        assert_eq!(v.loader, 2);
+        assert_eq!(v.htif, 1);
+        assert_eq!(v.traps, 0);
+        assert_eq!(v.trace, 0);
+
+        // 非法子系统名和非法级别都应报错
+        assert!(Verbosity::from_spec("bogus=1").is_err());
+        assert!(Verbosity::from_spec("loader=x").is_err());
+    }
+
+    #[test]
+    fn test_isa_extensions_parse() {
+        let ext = IsaExtensions::from_str("rv32im").unwrap();
+        assert!(ext.m);
+        assert!(!ext.f);
+
+        let ext = IsaExtensions::from_str("rv32imf").unwrap();
+        assert!(ext.m);
+        assert!(ext.f);
+        assert!(ext.zicsr); // F 隐含 Zicsr
+
+        let ext = IsaExtensions::from_str("rv32g").unwrap();
+        assert!(ext.m);
+        assert!(ext.f);
+        assert!(ext.d);
+        assert!(ext.zicsr);
+
+        // 下划线分隔的多字符扩展
+        let ext = IsaExtensions::from_str("rv32im_zicond_zicbom").unwrap();
+        assert!(ext.m);
+        assert!(ext.zicond);
+        assert!(ext.zicbo);
+        assert!(!ext.f);
+    }
+
+    #[test]
+    fn test_sim_config_builder() {
+        let config = SimConfig::new()
+            .with_memory_size(128 * 1024)
+            .with_memory_base(0x8000_0000)
+            .with_entry_pc(0x8000_0000)
+            .with_max_instructions(1000);
+
+        assert_eq!(config.memory.size, 128 * 1024);
+        assert_eq!(config.memory.base, 0x8000_0000);
+        assert_eq!(config.entry_pc, Some(0x8000_0000));
+        assert_eq!(config.max_instructions, 1000);
+    }
+
+    #[test]
+    fn test_sim_env_basic() {
+        // 创建简单的仿真环境
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // 写入简单程序: addi x1, x0, 42
+        env
+            .memory
+            .store32(0, 0x02A00093)
+            .expect("failed to write test instruction");
+
+        // 执行一步
+        let state = env.step();
+        assert_eq!(state, CpuState::Running);
+        assert_eq!(env.cpu.read_reg(1), 42);
+        assert_eq!(env.instructions_executed, 1);
+    }
+
+    #[test]
+    fn test_sim_env_with_extensions() {
+        let ext = IsaExtensions::rv32imfc();
+        let config = SimConfig::new()
+            .with_extensions(ext)
+            .with_memory_size(4096)
+            .with_entry_pc(0);
+
+        let env = SimEnv::from_config(config).expect("Failed to create sim env");
+        
+        // 验证 F 扩展已启用
+        assert!(env.cpu.has_fp());
+    }
+
+    #[test]
+    fn test_schedule_one_shot_event() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // 填充 NOP（addi x0, x0, 0）
+        for i in 0..16 {
+            env.memory.store32(i * 4, 0x00000013).unwrap();
+        }
+
+        // 3 条指令后把 x5 写为 99
+        env.schedule_after(3, Box::new(|cpu, _mem| {
+            cpu.write_reg(5, 99);
+            None
+        }));
+
+        env.run(2);
+        assert_eq!(env.cpu.read_reg(5), 0, "事件不应提前触发");
+
+        env.run(1);
+        assert_eq!(env.cpu.read_reg(5), 99, "事件应在第 3 条指令后触发");
+    }
+
+    #[test]
+    fn test_uart_console_output() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_uart(0x1000_0000);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        let buf = crate::devices::SharedBuffer::new();
+        env.set_uart_sink(Box::new(buf.clone()));
+
+        // 程序：向 UART THR 写 "Hi"，再读 LSR
+        let program = [
+            0x100002B7, // lui x5, 0x10000     ; x5 = UART 基地址
+            0x04800313, // addi x6, x0, 72     ; 'H'
+            0x00628023, // sb x6, 0(x5)
+            0x06900313, // addi x6, x0, 105    ; 'i'
+            0x00628023, // sb x6, 0(x5)
+            0x0052C383, // lbu x7, 5(x5)       ; 读 LSR
+        ];
+        for (i, &instr) in program.iter().enumerate() {
+            env.memory.store32((i * 4) as u32, instr).unwrap();
+        }
+
+        env.run(program.len() as u64);
+
+        assert_eq!(buf.contents(), "Hi");
+        assert_eq!(env.cpu.read_reg(7), 0x60, "LSR 应报告发送器空闲");
+    }
+
+    #[test]
+    fn test_instr_usage_attestation() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_extensions(IsaExtensions::rv32im())
+            .with_instr_usage();
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // 程序：两条 addi、一条 mul
+        let program = [
+            0x00300293, // addi x5, x0, 3
+            0x00400313, // addi x6, x0, 4
+            0x026283B3, // mul x7, x5, x6
+        ];
+        for (i, &instr) in program.iter().enumerate() {
+            env.memory.store32((i * 4) as u32, instr).unwrap();
+        }
+
+        env.run(program.len() as u64);
+        assert_eq!(env.cpu.read_reg(7), 12);
+
+        let report = env.instr_usage_report();
+        assert_eq!(report.used_extensions(), vec!["I", "M"]);
+
+        let addi = report
+            .entries
+            .iter()
+            .find(|e| e.mnemonic == "addi")
+            .expect("报告应包含 addi");
+        assert_eq!(addi.count, 2);
+        assert_eq!(addi.extension, "I");
+
+        // rv32im 允许集：合规；纯 rv32i 允许集：mul 是违规项
+        assert!(report.is_compliant(&IsaExtensions::rv32im()));
+        let violations = report.violations(&IsaExtensions::rv32i());
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].mnemonic, "mul");
+        assert_eq!(violations[0].extension, "M");
+    }
+
+    #[test]
+    fn test_run_realtime_paces_to_wall_clock() {
+        let config = SimConfig::new()
+            .with_memory_size(4 * 1024)
+            .with_entry_pc(0)
+            .with_max_instructions(50);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.memory.store32(0, 0x0000_006F).unwrap(); // j .（原地跳转）
+
+        // 0.001 MHz = 每秒 1000 条指令：50 条约需 50ms 模拟时间
+        let start = std::time::Instant::now();
+        let (executed, state) = env.run_realtime(0.001);
+
+        assert_eq!(executed, 50);
+        assert_eq!(state, CpuState::Running, "指令数上限到达前保持运行");
+        assert!(
+            start.elapsed() >= std::time::Duration::from_millis(40),
+            "配速运行不应明显快于模拟时间"
+        );
+    }
+
+    #[test]
+    fn test_sparse_memory_backend_runs_program() {
+        // 256 MB 地址空间：稀疏后端按页懒分配，不预拉整个区间
+        let config = SimConfig::new()
+            .with_memory_size(256 * 1024 * 1024)
+            .with_entry_pc(0)
+            .with_max_instructions(10)
+            .with_stop_condition(StopCondition::OnEbreak)
+            .with_sparse_memory();
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        env.memory.store32(0, 0x02A0_0093).unwrap(); // addi x1, x0, 42
+        env.memory.store32(4, 0x1010_2023).unwrap(); // sw x1, 0x100(x0)
+        env.memory.store32(8, 0x1000_2103).unwrap(); // lw x2, 0x100(x0)
+        env.memory.store32(12, 0x0010_0073).unwrap(); // ebreak
+
+        let (executed, _) = env.run_until_halt();
+        assert_eq!(executed, 4);
+        assert_eq!(env.stop_reason, Some(StopCondition::OnEbreak));
+        assert_eq!(env.cpu.read_reg(2), 42);
+        assert_eq!(env.memory.load32(0x100).unwrap(), 42);
+
+        let GuestMemory::Sparse(mem) = &env.memory else {
+            panic!("配置了稀疏后端");
         };
+        assert!(mem.page_count() <= 2, "只有实际写过的页被分配");
+    }
+
+    #[test]
+    fn test_exec_stats_histogram() {
+        use crate::stats::InstrClass;
+
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100)
+            .with_stop_condition(StopCondition::OnEbreak)
+            .with_stats();
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
 
-        self.run(max)
+        // 3 轮循环：每轮 sw + lw + addi + bnez，分支 2 taken 1 not-taken
+        let program = crate::asm::assemble(
+            "
+            li   a1, 3
+        loop:
+            sw   a1, 0x100(zero)
+            lw   a0, 0x100(zero)
+            addi a1, a1, -1
+            bnez a1, loop
+            ebreak
+            ",
+        )
+        .unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+
+        env.run_until_halt();
+        assert_eq!(env.stop_reason, Some(StopCondition::OnEbreak));
+
+        let stats = env.stats().expect("启用 with_stats 后应有统计");
+        assert_eq!(stats.mnemonics["lw"], 3);
+        assert_eq!(stats.mnemonics["sw"], 3);
+        assert_eq!(stats.class_count(InstrClass::Load), 3);
+        assert_eq!(stats.class_count(InstrClass::Store), 3);
+        assert_eq!(stats.class_count(InstrClass::Branch), 3);
+        assert_eq!(stats.branches_taken, 2);
+        assert_eq!(stats.branches_not_taken, 1);
+        assert_eq!(stats.bytes_loaded, 12);
+        assert_eq!(stats.bytes_stored, 12);
+        assert_eq!(stats.branch_taken_ratio(), Some(2.0 / 3.0));
+
+        // 报告包含类别分布与直方图条目
+        let report = env.stats().unwrap().to_string();
+        assert!(report.contains("branch"), "报告应包含类别分布: {}", report);
+        assert!(report.contains("lw"), "报告应包含助记符直方图: {}", report);
     }
 
-    /// 获取 CPU 引用
-    pub fn cpu(&self) -> &CpuCore {
-        &self.cpu
+    #[test]
+    fn test_timing_model_cycles_and_ipc() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100)
+            .with_stop_condition(StopCondition::OnEbreak)
+            .with_timing();
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // 默认模型：ALU 1 周期，load/store 1+2，taken 分支 1+1
+        let program = crate::asm::assemble(
+            "
+            li   a1, 5
+            sw   a1, 0x100(zero)
+            lw   a0, 0x100(zero)
+            ebreak
+            ",
+        )
+        .unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+
+        env.run_until_halt();
+        assert_eq!(env.stop_reason, Some(StopCondition::OnEbreak));
+
+        // li(1) + sw(3) + lw(3) + ebreak(1) = 8 周期 / 4 条指令
+        let report = env.timing().expect("启用 with_timing 后应有计时汇总");
+        assert_eq!(report.instructions, 4);
+        assert_eq!(report.cycles, 8);
+        assert_eq!(report.ipc(), Some(0.5));
     }
 
-    /// 获取 CPU 可变引用
-    pub fn cpu_mut(&mut self) -> &mut CpuCore {
-        &mut self.cpu
+    #[test]
+    fn test_run_until_pc_and_instruction_count() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        let program = crate::asm::assemble(
+            "
+            addi a1, zero, 3      # 0x0
+        loop:
+            addi a1, a1, -1       # 0x4
+            bnez a1, loop         # 0x8
+            sw   a1, 0x100(zero)  # 0xc
+            ebreak                # 0x10
+            ",
+        )
+        .unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+
+        // 在 sw 执行前停止：初始化 + 3 轮 (addi, bnez) = 7 条
+        let (executed, fired) = env.run_until(vec![RunCondition::PcEquals(0xc)]);
+        assert_eq!(executed, 7);
+        assert!(matches!(fired, Some(RunCondition::PcEquals(0xc))));
+        assert_eq!(env.cpu().pc(), 0xc);
+        assert_eq!(env.cpu().state(), CpuState::Running);
+
+        // 条件按传入顺序评估：预算先于永不命中的 PC 条件返回
+        let (executed, fired) = env.run_until(vec![
+            RunCondition::InstructionCount(2),
+            RunCondition::PcEquals(0xdead_0000),
+        ]);
+        assert_eq!(executed, 2);
+        assert!(matches!(fired, Some(RunCondition::InstructionCount(2))));
     }
 
-    /// 获取内存引用
-    pub fn memory(&self) -> &FlatMemory {
-        &self.memory
+    #[test]
+    fn test_run_until_mem_write_and_predicate() {
+        use crate::cpu::WatchKind;
+
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        let program = crate::asm::assemble(
+            "
+            addi a1, zero, 5
+            sw   a1, 0x200(zero)
+            addi a2, zero, 7
+            ebreak
+            ",
+        )
+        .unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+
+        // 已有的用户监视点在 run_until 结束后应原样保留
+        env.cpu_mut().add_watchpoint(0x300..0x304, WatchKind::Write);
+
+        let (executed, fired) = env.run_until(vec![RunCondition::MemWrite(0x200..0x204)]);
+        assert_eq!(executed, 2);
+        assert!(matches!(fired, Some(RunCondition::MemWrite(_))));
+        // 触发的 sw 已执行完毕，状态恢复 Running，可直接继续
+        assert_eq!(env.cpu().state(), CpuState::Running);
+        assert_eq!(env.memory.load32(0x200), Ok(5));
+        assert_eq!(env.cpu().watchpoint_len(), 1);
+
+        // 自定义谓词：a2 (x12) 被写入 7 后停止
+        let (executed, fired) = env.run_until(vec![RunCondition::Predicate(Box::new(
+            |env: &SimEnv| env.cpu().read_reg(12) == 7,
+        ))]);
+        assert_eq!(executed, 1);
+        assert!(matches!(fired, Some(RunCondition::Predicate(_))));
     }
 
-    /// 获取内存可变引用
-    pub fn memory_mut(&mut self) -> &mut FlatMemory {
-        &mut self.memory
+    #[test]
+    fn test_run_until_any_trap() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        let program = crate::asm::assemble("ecall").unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+
+        let (executed, fired) = env.run_until(vec![RunCondition::AnyTrap]);
+        assert_eq!(executed, 1);
+        assert!(matches!(fired, Some(RunCondition::AnyTrap)));
+
+        // 无条件命中时跑满预算，返回 None
+        let mut env2 = SimEnv::from_config(
+            SimConfig::new()
+                .with_memory_size(4096)
+                .with_entry_pc(0)
+                .with_max_instructions(10),
+        )
+        .expect("Failed to create sim env");
+        // 0x0: j 0x0（原地死循环）
+        env2.memory.store32(0, 0x0000_006F).unwrap();
+        let (executed, fired) = env2.run_until(vec![RunCondition::PcEquals(0x100)]);
+        assert_eq!(executed, 10);
+        assert!(fired.is_none());
     }
 
-    /// 打印仿真状态
-    pub fn dump(&self) {
-        println!("=== SimEnv Status ===");
-        println!("Instructions executed: {}", self.instructions_executed);
-        self.cpu.dump_regs();
+    #[test]
+    fn test_expect_mem_golden_check() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // 固件把 0x234 写到 0x200，黄金比对验证运行结果
+        let program = crate::asm::assemble(
+            "
+            addi a1, zero, 0x234
+            sw   a1, 0x200(zero)
+            ebreak
+            ",
+        )
+        .unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+        env.run_until_halt();
+
+        env.expect_mem(0x200, &[0x34, 0x02, 0x00, 0x00])
+            .expect("黄金内存应匹配");
+        env.expect_mem_words(0x200, &[0x234]).expect("字版本应匹配");
+
+        // 不匹配时报告逐字节差异，Display 可读
+        let diff = env
+            .expect_mem(0x200, &[0x34, 0x03, 0xFF, 0x00])
+            .expect_err("应检出差异");
+        assert_eq!(diff.addr, 0x200);
+        assert_eq!(diff.len, 4);
+        assert_eq!(
+            diff.mismatches,
+            vec![(0x201, 0x03, Some(0x02)), (0x202, 0xFF, Some(0x00))]
+        );
+        let report = diff.to_string();
+        assert!(report.contains("0x00000201"), "报告应含出错地址: {}", report);
+        assert!(report.contains("期望 0x03"), "报告应含期望值: {}", report);
+
+        // 未映射地址的实际值标记为 None
+        let diff = env
+            .expect_mem(0xFFFF_0000, &[0xAA])
+            .expect_err("未映射地址应检出差异");
+        assert_eq!(diff.mismatches, vec![(0xFFFF_0000, 0xAA, None)]);
+        assert!(diff.to_string().contains("未映射"));
     }
 
-    /// 检查 tohost 值并在检测到写入时执行 ACK
-    pub fn check_tohost(&mut self) -> Option<u32> {
-        if let Some(addr) = self.tohost_addr {
-            if let Ok(value) = self.memory.load32(addr) {
-                if value != 0 {
-                    self.acknowledge_tohost(value);
-                    return Some(value);
-                }
-            }
+    #[test]
+    fn test_event_bus_publishes_typed_events() {
+        use crate::cpu::TrapCause;
+        use crate::events::SimEvent;
+        use std::cell::RefCell;
+
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.map_hook(0x2000_0000..0x2000_0010, |_| 0);
+
+        let log: Rc<RefCell<Vec<SimEvent>>> = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&log);
+        env.subscribe_events(Box::new(move |e: &SimEvent| {
+            sink.borrow_mut().push(*e);
+        }));
+
+        let program = crate::asm::assemble(
+            "
+            lui  t0, 0x20000
+            addi a1, zero, 7
+            sw   a1, 0(t0)
+            ecall
+            ",
+        )
+        .unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
         }
-        None
+
+        env.run_until(vec![RunCondition::AnyTrap]);
+
+        // 客体执行产生 MMIO 写与 trap 事件，按发生顺序送达
+        assert_eq!(
+            log.borrow().as_slice(),
+            &[
+                SimEvent::MmioAccess { addr: 0x2000_0000, width: 4, write: Some(7) },
+                SimEvent::TrapTaken { pc: 12, cause: TrapCause::EcallFromM, tval: 0 },
+            ]
+        );
+
+        // 宿主侧的 CSR 写入与中断挂起同样走总线
+        log.borrow_mut().clear();
+        env.cpu_mut().csr_write(crate::cpu::csr_def::CSR_MSCRATCH, 5);
+        env.cpu_mut().raise_interrupt(TrapCause::MachineTimerInterrupt);
+        assert_eq!(
+            log.borrow().as_slice(),
+            &[
+                SimEvent::CsrWritten { pc: 12, addr: 0x340, old: 0, new: 5 },
+                SimEvent::InterruptRaised { cause: TrapCause::MachineTimerInterrupt },
+            ]
+        );
     }
 
-    fn clear_htif_mailboxes(&mut self) {
-        if let Some(addr) = self.tohost_addr {
-            let _ = self.memory.store32(addr, 0);
+    #[test]
+    fn test_clint_timer_interrupt_wakes_wfi() {
+        use crate::cpu::csr_def::{CSR_MCAUSE, CSR_MIE, CSR_MSTATUS, CSR_MTVEC};
+        use crate::cpu::trap::{interrupt, mstatus};
+        use crate::isa::WFI_ENCODING;
+
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_extensions(IsaExtensions {
+                priv_instr: true, // WFI 需要特权指令扩展
+                ..IsaExtensions::rv32im()
+            })
+            .with_clint(0x0200_4000); // 基地址选得让 mtimecmp 落在 lui 可达范围
+        // 注意：CLINT 窗口在 RAM 之外，访问全部走设备
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // 程序：设置 mtimecmp = 8，然后 WFI 等待定时器
+        let program = [
+            0x020082B7, // lui x5, 0x2008      ; x5 = clint_base + 0x4000 (mtimecmp)
+            0x00800313, // addi x6, x0, 8
+            0x0062A023, // sw x6, 0(x5)        ; mtimecmp 低 32 位 = 8
+            0x0002A223, // sw x0, 4(x5)        ; mtimecmp 高 32 位 = 0
+            WFI_ENCODING,
+        ];
+        for (i, &instr) in program.iter().enumerate() {
+            env.memory.store32((i * 4) as u32, instr).unwrap();
         }
-        if let Some(addr) = self.fromhost_addr {
-            let _ = self.memory.store32(addr, 0);
+        // 中断处理程序：标记 x7 后原地循环
+        env.memory.store32(0x100, 0x00100393).unwrap(); // addi x7, x0, 1
+        env.memory.store32(0x104, 0x0000006F).unwrap(); // jal x0, 0
+
+        // 使能定时器中断：mtvec、mstatus.MIE、mie.MTIE
+        env.cpu.csr_write(CSR_MTVEC, 0x100);
+        env.cpu.csr_write(CSR_MSTATUS, mstatus::MIE_MASK);
+        env.cpu.csr_write(CSR_MIE, interrupt::MTIP_MASK);
+
+        env.run(50);
+
+        assert_eq!(env.cpu.read_reg(7), 1, "中断处理程序应已执行");
+        assert_eq!(
+            env.cpu.csr_read(CSR_MCAUSE),
+            0x8000_0007,
+            "mcause 应为机器定时器中断"
+        );
+    }
+
+    #[test]
+    fn test_htif_console_putchar() {
+        let config = SimConfig::new()
+            .with_memory_size(64 * 1024)
+            .with_entry_pc(0)
+            .with_max_instructions(10_000);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.tohost_addr = Some(0x1000);
+        env.fromhost_addr = Some(0x1008);
+
+        // 经 HTIF 控制台输出 "Hi"，然后以 tohost=1 退出。
+        // 每次 putchar 先写高字（设备 1、命令 1）再写低字，随后等
+        // 宿主清空 tohost 作为 ACK
+        let program = crate::asm::assemble(
+            "
+            li   t0, 0x1000
+            li   t1, 0x01010000
+            sw   t1, 4(t0)
+            li   t1, 72         # 'H'
+            sw   t1, 0(t0)
+        wait1:
+            lw   t2, 0(t0)
+            bnez t2, wait1
+            li   t1, 0x01010000
+            sw   t1, 4(t0)
+            li   t1, 105        # 'i'
+            sw   t1, 0(t0)
+        wait2:
+            lw   t2, 0(t0)
+            bnez t2, wait2
+            li   t1, 1          # 设备 0：测试通过
+            sw   t1, 0(t0)
+        halt:
+            j    halt
+            ",
+        )
+        .unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
         }
+
+        let out = crate::devices::SharedBuffer::new();
+        env.set_htif_console_sink(Box::new(out.clone()));
+
+        let (_, state) = env.run_until_halt();
+
+        assert_eq!(state, CpuState::Exited(0), "tohost=1 即 pass 退出");
+        assert_eq!(out.contents(), "Hi", "控制台命令不应停机而应输出字符");
+        assert_eq!(env.last_tohost, Some(1));
+        assert_eq!(TestResult::from_tohost(1), TestResult::Pass);
     }
 
-    fn acknowledge_tohost(&mut self, value: u32) {
-        if let Some(addr) = self.tohost_addr {
-            let _ = self.memory.store32(addr, 0);
+    #[test]
+    fn test_test_result_json_roundtrip() {
+        for result in [TestResult::Pass, TestResult::Fail(7), TestResult::Timeout] {
+            let json = result.to_json();
+            assert_eq!(TestResult::from_json(&json).unwrap(), result, "{json}");
         }
-        if let Some(addr) = self.fromhost_addr {
-            let _ = self.memory.store32(addr, value);
+        assert_eq!(
+            TestResult::from_json("{ \"status\": \"fail\", \"case\": 3 }").unwrap(),
+            TestResult::Fail(3),
+            "空白不敏感"
+        );
+        assert!(TestResult::from_json("{\"status\":\"bogus\"}").is_err());
+    }
+
+    #[test]
+    fn test_htif_console_getchar() {
+        let config = SimConfig::new()
+            .with_memory_size(64 * 1024)
+            .with_entry_pc(0)
+            .with_max_instructions(10_000);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.tohost_addr = Some(0x1000);
+        env.fromhost_addr = Some(0x1008);
+
+        // getchar 请求（设备 1、命令 0），等 tohost 高字清零后从
+        // fromhost 低字取回 ch + 1
+        let program = crate::asm::assemble(
+            "
+            li   t0, 0x1000
+            li   t1, 0x01000000
+            sw   t1, 4(t0)
+        wait:
+            lw   t2, 4(t0)
+            bnez t2, wait
+            lw   s0, 8(t0)      # fromhost 载荷
+            li   t1, 1
+            sw   t1, 0(t0)
+        halt:
+            j    halt
+            ",
+        )
+        .unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
         }
+
+        env.set_htif_console_input(Box::new(io::Cursor::new(b"x".to_vec())));
+        env.run_until_halt();
+
+        assert_eq!(env.cpu.read_reg(8), b'x' as u32 + 1, "getchar 应回复 ch + 1");
     }
 
-    /// 运行 ISA 测试
-    ///
-    /// 执行程序直到 tohost 被写入，或达到最大指令数
-    ///
-    /// # 参数
-    ///
-    /// * `max_instructions` - 最大执行指令数（0 表示使用默认值 1000000）
-    ///
-    /// # 返回
-    ///
-    /// * `TestResult` - 测试结果（Pass/Fail/Timeout）
-    /// * `u64` - 执行的指令数
-    pub fn run_isa_test(&mut self, max_instructions: u64) -> (TestResult, u64) {
-        let max = if max_instructions > 0 {
-            max_instructions
-        } else {
-            1_000_000 // 默认最大 100 万条指令
+    #[test]
+    fn test_device_quantum_batches_polling_deterministically() {
+        use crate::cpu::csr_def::{CSR_MCAUSE, CSR_MIE, CSR_MSTATUS, CSR_MTVEC};
+        use crate::cpu::trap::{interrupt, mstatus};
+        use crate::isa::WFI_ENCODING;
+
+        // 默认配额为 1：每条指令评估一次设备
+        assert_eq!(SimConfig::new().device_quantum, 1);
+
+        // 与 test_clint_timer_interrupt_wakes_wfi 相同的程序，设备
+        // 评估改为按配额批量进行
+        let run_with_quantum = |quantum: u64| {
+            let config = SimConfig::new()
+                .with_memory_size(4096)
+                .with_entry_pc(0)
+                .with_extensions(IsaExtensions {
+                    priv_instr: true,
+                    ..IsaExtensions::rv32im()
+                })
+                .with_clint(0x0200_4000)
+                .with_device_quantum(quantum);
+
+            let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+            let program = [
+                0x020082B7, // lui x5, 0x2008
+                0x00800313, // addi x6, x0, 8
+                0x0062A023, // sw x6, 0(x5)    ; mtimecmp = 8
+                0x0002A223, // sw x0, 4(x5)
+                WFI_ENCODING,
+            ];
+            for (i, &instr) in program.iter().enumerate() {
+                env.memory.store32((i * 4) as u32, instr).unwrap();
+            }
+            env.memory.store32(0x100, 0x00100393).unwrap(); // addi x7, x0, 1
+            env.memory.store32(0x104, 0x0000006F).unwrap(); // jal x0, 0
+
+            env.cpu.csr_write(CSR_MTVEC, 0x100);
+            env.cpu.csr_write(CSR_MSTATUS, mstatus::MIE_MASK);
+            env.cpu.csr_write(CSR_MIE, interrupt::MTIP_MASK);
+
+            env.run(50);
+            (
+                env.cpu.read_reg(7),
+                env.cpu.csr_read(CSR_MCAUSE),
+                env.cpu.pc(),
+                env.instructions_executed,
+            )
         };
 
-        // 如果没有 tohost 地址，直接运行到停止
-        if self.tohost_addr.is_none() {
-            let start = self.instructions_executed;
-            let (executed, _state) = self.run(max);
-            let delta = self.instructions_executed - start;
-            let reported = if delta == 0 { executed } else { delta };
-            return (TestResult::Timeout, reported);
+        // 批量评估下定时器中断仍被交付
+        let (flag, mcause, _, _) = run_with_quantum(4);
+        assert_eq!(flag, 1, "中断处理程序应已执行");
+        assert_eq!(mcause, 0x8000_0007);
+
+        // 相同配额的重复运行完全确定
+        assert_eq!(run_with_quantum(4), run_with_quantum(4));
+        assert_eq!(run_with_quantum(1), run_with_quantum(1));
+    }
+
+    #[test]
+    fn test_schedule_periodic_event() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        for i in 0..32 {
+            env.memory.store32(i * 4, 0x00000013).unwrap();
         }
 
-        self.clear_htif_mailboxes();
-        let start = self.instructions_executed;
+        // 每 4 条指令把 x6 加 1
+        env.schedule_after(4, Box::new(|cpu, _mem| {
+            let count = cpu.read_reg(6);
+            cpu.write_reg(6, count + 1);
+            Some(4)
+        }));
+
+        env.run(12);
+        assert_eq!(env.cpu.read_reg(6), 3, "周期事件应触发 3 次");
+    }
+
+    #[test]
+    fn test_stop_on_trap() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100)
+            .with_stop_on_trap(true);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // addi x1, x0, 42; ecall
+        env.memory.store32(0, 0x02A00093).unwrap();
+        env.memory.store32(4, 0x00000073).unwrap();
+
+        let (executed, _state) = env.run_until_halt();
+
+        // 应该在 ecall 触发 trap 后立即停止，而不是跑满 100 条
+        assert_eq!(executed, 2);
+        assert_eq!(env.stop_reason, Some(StopCondition::OnTrap));
+    }
+
+    #[test]
+    fn test_stop_on_ebreak() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100)
+            .with_stop_condition(StopCondition::OnEbreak);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // addi x1, x0, 1; ebreak
+        env.memory.store32(0, 0x00100093).unwrap();
+        env.memory.store32(4, 0x00100073).unwrap();
+
+        let (executed, _state) = env.run_until_halt();
+
+        assert_eq!(executed, 2);
+        assert_eq!(env.stop_reason, Some(StopCondition::OnEbreak));
+    }
+
+    #[test]
+    fn test_stop_on_store_to() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100)
+            .with_stop_condition(StopCondition::OnStoreTo(0x100));
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // addi x1, x0, 42    # x1 = 42
+        env.memory.store32(0, 0x02A00093).unwrap();
+        // addi x2, x0, 0x100 # x2 = 0x100
+        env.memory.store32(4, 0x10000113).unwrap();
+        // sw x1, 0(x2)       # mem[0x100] = 42
+        env.memory.store32(8, 0x00112023).unwrap();
+        // addi x3, x0, 1     # 不应执行
+        env.memory.store32(12, 0x00100193).unwrap();
+
+        let (executed, _state) = env.run_until_halt();
+
+        assert_eq!(executed, 3);
+        assert_eq!(env.stop_reason, Some(StopCondition::OnStoreTo(0x100)));
+        assert_eq!(env.cpu.read_reg(3), 0);
+    }
+
+    #[test]
+    fn test_host_stub_intercepts_call() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // jal x1, 8          # 调用地址 8 处的"函数"
+        env.memory.store32(0, 0x008000EF).unwrap();
+        // addi x3, x0, 7     # 返回地址（ra = 4）
+        env.memory.store32(4, 0x00700193).unwrap();
+        // addi x4, x0, 1     # 被桩替代的函数体，不应执行
+        env.memory.store32(8, 0x00100213).unwrap();
+
+        // 在地址 8 注册桩：返回 123
+        env.register_host_stub(8, Box::new(|_cpu, _mem| Some(123)));
+
+        env.run(3);
+
+        assert_eq!(env.cpu.read_reg(10), 123, "桩返回值应写入 a0");
+        assert_eq!(env.cpu.read_reg(3), 7, "执行应从 ra 恢复");
+        assert_eq!(env.cpu.read_reg(4), 0, "客体函数体不应执行");
+        assert_eq!(env.instructions_executed, 3);
+    }
+
+    #[test]
+    fn test_sbrk_stub_tracks_break_and_detects_collision() {
+        let config = SimConfig::new()
+            .with_memory_size(64 * 1024)
+            .with_entry_pc(0)
+            .with_heap(0x1000, 0x2000);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.register_sbrk_stub(0x100).unwrap();
+
+        // 直接合成一次调用：sp 在堆区上方，a0 为增量
+        env.cpu.write_reg(2, 0x8000); // sp
+        env.cpu.write_reg(1, 0x200); // ra
+        env.cpu.write_reg(10, 64); // 增量
+        env.cpu.set_pc(0x100);
+        env.run(1);
+        assert_eq!(env.cpu.read_reg(10), 0x1000, "sbrk 应返回旧 break");
+        assert_eq!(env.cpu.pc(), 0x200, "执行应从 ra 恢复");
+
+        // 越过堆区上限：返回 -1，break 不变
+        env.cpu.write_reg(10, 0x10000);
+        env.cpu.set_pc(0x100);
+        env.run(1);
+        assert_eq!(env.cpu.read_reg(10), u32::MAX);
+
+        // 栈压到堆附近时，增长进入保护间隙也应失败
+        env.cpu.write_reg(2, 0x1100);
+        env.cpu.write_reg(10, 0x200);
+        env.cpu.set_pc(0x100);
+        env.run(1);
+        assert_eq!(env.cpu.read_reg(10), u32::MAX, "不应悄悄撞进栈区");
+
+        let stats = env.heap_stats().expect("配置了堆区间");
+        assert_eq!(stats.program_break, 0x1040);
+        assert_eq!(stats.peak_usage(), 64);
+        assert_eq!(stats.grow_calls, 1);
+        assert_eq!(stats.failed_calls, 2);
+    }
+
+    #[test]
+    fn test_brk_stub_and_missing_heap_region() {
+        // 未配置堆区间时注册应报错
+        let config = SimConfig::new().with_memory_size(4096).with_entry_pc(0);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        assert!(env.register_brk_stub(0x100).is_err());
+
+        let config = SimConfig::new()
+            .with_memory_size(64 * 1024)
+            .with_entry_pc(0)
+            .with_heap(0x1000, 0x1000);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.register_brk_stub(0x100).unwrap();
+
+        env.cpu.write_reg(2, 0x8000); // sp
+        env.cpu.write_reg(1, 0x200); // ra
+
+        // a0 = 0：查询当前 break
+        env.cpu.write_reg(10, 0);
+        env.cpu.set_pc(0x100);
+        env.run(1);
+        assert_eq!(env.cpu.read_reg(10), 0x1000);
+
+        // 设置到堆区内的新 break
+        env.cpu.write_reg(10, 0x1800);
+        env.cpu.set_pc(0x100);
+        env.run(1);
+        assert_eq!(env.cpu.read_reg(10), 0x1800);
+
+        // 失败的设置返回原值不变的 break
+        env.cpu.write_reg(10, 0x9000);
+        env.cpu.set_pc(0x100);
+        env.run(1);
+        assert_eq!(env.cpu.read_reg(10), 0x1800);
+
+        let stats = env.heap_stats().unwrap();
+        assert_eq!(stats.grow_calls, 1);
+        assert_eq!(stats.failed_calls, 1);
+    }
+
+    #[test]
+    fn test_export_import_resumes_execution() {
+        let make_env = || {
+            let config = SimConfig::new()
+                .with_memory_size(4096)
+                .with_entry_pc(0)
+                .with_max_instructions(100)
+                .with_stop_condition(StopCondition::OnEbreak);
+            SimEnv::from_config(config).expect("Failed to create sim env")
+        };
 
-        for _ in 0..max {
-            let state = self.step();
-            
-            // 检查 tohost
-            if let Some(value) = self.check_tohost() {
-                let delta = self.instructions_executed - start;
-                return (TestResult::from_tohost(value), delta);
-            }
-            
-            // 检查 CPU 状态（非法指令等）
-            if state != CpuState::Running {
-                // 可能是 trap，继续检查 tohost
-                if let Some(value) = self.check_tohost() {
-                    let delta = self.instructions_executed - start;
-                    return (TestResult::from_tohost(value), delta);
-                }
-                // CPU 停止但 tohost 未写入
-                break;
-            }
+        // 1 + 2 + ... + 5 = 15，随后 ebreak
+        let program = crate::asm::assemble(
+            "
+            li   a0, 0
+            li   a1, 5
+        loop:
+            add  a0, a0, a1
+            addi a1, a1, -1
+            bnez a1, loop
+            ebreak
+            ",
+        )
+        .unwrap();
+
+        let mut source = make_env();
+        for (i, word) in program.iter().enumerate() {
+            source.memory.store32(i as u32 * 4, *word).unwrap();
         }
 
-        // 超时或 CPU 异常停止
-        let delta = self.instructions_executed - start;
-        (TestResult::Timeout, delta)
+        // 跑到循环中途后导出
+        source.run(6);
+        let mut blob = Vec::new();
+        source.export_state(&mut blob).unwrap();
+
+        // 在"另一个进程"的全新环境中恢复并跑完
+        let mut target = make_env();
+        target.import_state(&mut io::Cursor::new(&blob)).unwrap();
+        assert_eq!(target.instructions_executed, 6);
+        assert_eq!(target.cpu.pc(), source.cpu.pc());
+
+        target.run_until_halt();
+        assert_eq!(target.stop_reason, Some(StopCondition::OnEbreak));
+        assert_eq!(target.cpu.read_reg(10), 15, "恢复后应算出与不中断运行相同的结果");
     }
 
-    /// 重置仿真环境
-    pub fn reset(&mut self) -> Result<(), SimError> {
-        // 重新创建 CPU
-        let entry_pc = self.config.entry_pc.unwrap_or(self.config.memory.base);
-        self.cpu = Self::build_cpu(&self.config.extensions, entry_pc)?;
-        self.instructions_executed = 0;
-        
-        // 如果有 ELF，重新加载
-        if let Some(ref elf_path) = self.config.elf_path {
-            let elf = ElfInfo::parse(elf_path)?;
-            self.tohost_addr = elf.find_symbol("tohost");
-            self.fromhost_addr = elf.find_symbol("fromhost");
-            load_segments_into_memory(&mut self.memory, &self.config.memory, &elf.segments)?;
-            // 设置入口点
-            if self.config.entry_pc.is_none() {
-                self.cpu.set_pc(elf.entry);
-            }
-        } else if let Some(ref bin_path) = self.config.bin_path {
-            let data = std::fs::read(bin_path)?;
-            ensure_range(&self.config.memory, self.config.bin_load_addr, data.len())?;
-            self.memory
-                .write_bytes(self.config.bin_load_addr, &data)
-                .map_err(SimError::from)?;
-            if self.config.entry_pc.is_none() {
-                self.cpu.set_pc(self.config.bin_load_addr);
-            }
+    #[test]
+    fn test_save_restore_snapshot_rollback() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100)
+            .with_stop_condition(StopCondition::OnEbreak);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        let program = crate::asm::assemble(
+            "
+            li   a0, 0
+            li   a1, 5
+        loop:
+            add  a0, a0, a1
+            addi a1, a1, -1
+            bnez a1, loop
+            ebreak
+            ",
+        )
+        .unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
         }
 
-        self.clear_htif_mailboxes();
+        // 快进到循环中途后拍快照
+        env.run(6);
+        let snap = env.save_snapshot();
+        assert_eq!(snap.instructions, 6);
 
-        Ok(())
+        // 第一次跑完
+        env.run_until_halt();
+        assert_eq!(env.cpu.read_reg(10), 15);
+
+        // 回滚到快照点重放，得到完全相同的结果
+        env.restore_snapshot(&snap).unwrap();
+        assert_eq!(env.instructions_executed, 6);
+        assert_eq!(env.cpu.pc(), snap.pc);
+        env.run_until_halt();
+        assert_eq!(env.stop_reason, Some(StopCondition::OnEbreak));
+        assert_eq!(env.cpu.read_reg(10), 15, "回滚重放应得到相同结果");
+
+        // 快照应能无损经过序列化往返
+        let mut blob = Vec::new();
+        snap.write_to(&mut blob).unwrap();
+        let restored = SimSnapshot::read_from(&mut io::Cursor::new(&blob)).unwrap();
+        assert_eq!(restored, snap);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::memory::Memory;
+    #[test]
+    fn test_snapshot_rejects_incompatible_target() {
+        let config = SimConfig::new().with_memory_size(4096).with_entry_pc(0);
+        let source = SimEnv::from_config(config).expect("Failed to create sim env");
+        let snap = source.save_snapshot();
+
+        // 内存区间不一致的环境应拒绝恢复
+        let config = SimConfig::new().with_memory_size(8192).with_entry_pc(0);
+        let mut target = SimEnv::from_config(config).expect("Failed to create sim env");
+        assert!(target.restore_snapshot(&snap).is_err());
+    }
 
     #[test]
-    fn test_isa_extensions_parse() {
-        let ext = IsaExtensions::from_str("rv32im").unwrap();
-        assert!(ext.m);
-        assert!(!ext.f);
+    fn test_import_rejects_incompatible_blob() {
+        let config = SimConfig::new().with_memory_size(4096).with_entry_pc(0);
+        let source = SimEnv::from_config(config).expect("Failed to create sim env");
+        let mut blob = Vec::new();
+        source.export_state(&mut blob).unwrap();
 
-        let ext = IsaExtensions::from_str("rv32imf").unwrap();
-        assert!(ext.m);
-        assert!(ext.f);
-        assert!(ext.zicsr); // F 隐含 Zicsr
+        // 内存区间不一致
+        let config = SimConfig::new().with_memory_size(8192).with_entry_pc(0);
+        let mut target = SimEnv::from_config(config).expect("Failed to create sim env");
+        assert!(target.import_state(&mut io::Cursor::new(&blob)).is_err());
 
-        let ext = IsaExtensions::from_str("rv32g").unwrap();
-        assert!(ext.m);
-        assert!(ext.f);
-        assert!(ext.d);
-        assert!(ext.zicsr);
+        // 魔数损坏
+        let mut bad = blob.clone();
+        bad[0] ^= 0xFF;
+        let config = SimConfig::new().with_memory_size(4096).with_entry_pc(0);
+        let mut target = SimEnv::from_config(config).expect("Failed to create sim env");
+        assert!(target.import_state(&mut io::Cursor::new(&bad)).is_err());
     }
 
     #[test]
-    fn test_sim_config_builder() {
+    fn test_stop_on_pc() {
         let config = SimConfig::new()
-            .with_memory_size(128 * 1024)
-            .with_memory_base(0x8000_0000)
-            .with_entry_pc(0x8000_0000)
-            .with_max_instructions(1000);
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_max_instructions(100)
+            .with_stop_condition(StopCondition::OnPc(8));
 
-        assert_eq!(config.memory.size, 128 * 1024);
-        assert_eq!(config.memory.base, 0x8000_0000);
-        assert_eq!(config.entry_pc, Some(0x8000_0000));
-        assert_eq!(config.max_instructions, 1000);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // 三条 addi，断点设在第三条（地址 8）
+        env.memory.store32(0, 0x00100093).unwrap(); // addi x1, x0, 1
+        env.memory.store32(4, 0x00200113).unwrap(); // addi x2, x0, 2
+        env.memory.store32(8, 0x00300193).unwrap(); // addi x3, x0, 3
+
+        let (executed, _state) = env.run_until_halt();
+
+        // 断点在指令执行前命中：地址 8 的指令不应执行
+        assert_eq!(executed, 2);
+        assert_eq!(env.cpu.pc(), 8);
+        assert_eq!(env.stop_reason, Some(StopCondition::OnPc(8)));
+        assert_eq!(env.cpu.read_reg(3), 0);
     }
 
     #[test]
-    fn test_sim_env_basic() {
-        // 创建简单的仿真环境
+    fn test_run_until_symbol() {
+        let elf_path = "isa_test/rv32ui-p-and";
+        if !std::path::Path::new(elf_path).exists() {
+            println!("Skipping test: {} not found", elf_path);
+            return;
+        }
+
         let config = SimConfig::new()
-            .with_memory_size(4096)
-            .with_entry_pc(0);
+            .with_elf_path(elf_path)
+            .with_memory("ram", 0x80000000, 64 * 1024)
+            .with_extensions(IsaExtensions::rv32g())
+            .with_max_instructions(100_000);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        let expected = env.find_symbol("test_2").expect("ELF 应包含 test_2 符号");
+        env.run_until_symbol("test_2").unwrap();
+        assert_eq!(env.cpu.pc(), expected);
+        assert_eq!(env.stop_reason, Some(StopCondition::OnPc(expected)));
+        assert!(
+            env.config.stop_conditions.is_empty(),
+            "临时断点应在返回前撤掉"
+        );
+
+        // 运行期注册的持久断点
+        let test3 = env.add_breakpoint("test_3").unwrap();
+        env.run_until_halt();
+        assert_eq!(env.cpu.pc(), test3);
+
+        assert!(matches!(
+            env.run_until_symbol("no_such_symbol"),
+            Err(SimError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_symbol_entry_and_breakpoint() {
+        let elf_path = "isa_test/rv32ui-p-and";
+
+        // 如果测试文件不存在则跳过
+        if !std::path::Path::new(elf_path).exists() {
+            println!("Skipping test: {} not found", elf_path);
+            return;
+        }
+
+        // 按符号名设置断点：应在 test_2 处停止
+        let config = SimConfig::new()
+            .with_elf_path(elf_path)
+            .with_memory("ram", 0x80000000, 64 * 1024)
+            .with_extensions(IsaExtensions::rv32g())
+            .with_max_instructions(100_000)
+            .with_break_symbol("test_2");
 
         let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.run_until_halt();
 
-        // 写入简单程序: addi x1, x0, 42
-        env
-            .memory
-            .store32(0, 0x02A00093)
-            .expect("failed to write test instruction");
+        let expected = ElfInfo::parse(elf_path)
+            .unwrap()
+            .find_symbol("test_2")
+            .expect("ELF 应包含 test_2 符号");
+        assert_eq!(env.cpu.pc(), expected);
+        assert_eq!(env.stop_reason, Some(StopCondition::OnPc(expected)));
 
-        // 执行一步
-        let state = env.step();
-        assert_eq!(state, CpuState::Running);
-        assert_eq!(env.cpu.read_reg(1), 42);
-        assert_eq!(env.instructions_executed, 1);
+        // 按符号名覆盖入口点
+        let config = SimConfig::new()
+            .with_elf_path(elf_path)
+            .with_memory("ram", 0x80000000, 64 * 1024)
+            .with_extensions(IsaExtensions::rv32g())
+            .with_entry_symbol("test_2");
+
+        let env = SimEnv::from_config(config).expect("Failed to create sim env");
+        assert_eq!(env.cpu.pc(), expected);
+
+        // 不存在的符号应报错
+        let config = SimConfig::new()
+            .with_elf_path(elf_path)
+            .with_memory("ram", 0x80000000, 64 * 1024)
+            .with_entry_symbol("no_such_symbol");
+        assert!(SimEnv::from_config(config).is_err());
     }
 
     #[test]
-    fn test_sim_env_with_extensions() {
-        let ext = IsaExtensions::rv32imfc();
+    fn test_stop_on_csr_write() {
+        let ext = IsaExtensions {
+            zicsr: true,
+            ..Default::default()
+        };
         let config = SimConfig::new()
             .with_extensions(ext)
             .with_memory_size(4096)
-            .with_entry_pc(0);
+            .with_entry_pc(0)
+            .with_max_instructions(100)
+            .with_stop_condition(StopCondition::OnCsrWrite(0x305)); // mtvec
 
-        let env = SimEnv::from_config(config).expect("Failed to create sim env");
-        
-        // 验证 F 扩展已启用
-        assert!(env.cpu.has_fp());
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        // addi x1, x0, 0x100   # x1 = 0x100
+        env.memory.store32(0, 0x10000093).unwrap();
+        // csrrw x0, mtvec, x1  # mtvec = 0x100
+        env.memory.store32(4, 0x30509073).unwrap();
+        // addi x3, x0, 1       # 不应执行
+        env.memory.store32(8, 0x00100193).unwrap();
+
+        let (executed, _state) = env.run_until_halt();
+
+        assert_eq!(executed, 2);
+        assert_eq!(env.stop_reason, Some(StopCondition::OnCsrWrite(0x305)));
+
+        // 写入事件带有 PC 和新旧值
+        let ev = env.cpu.last_csr_write().expect("应记录 CSR 写入事件");
+        assert_eq!(ev.addr, 0x305);
+        assert_eq!(ev.pc, 4);
+        assert_eq!(ev.old, 0);
+        assert_eq!(ev.new, 0x100);
+        assert_eq!(env.cpu.read_reg(3), 0);
+    }
+
+    #[test]
+    fn test_run_polls_tohost() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_htif_poll_interval(8);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.tohost_addr = Some(0x100);
+
+        // addi x1, x0, 1     # x1 = 1 (tohost PASS 值)
+        env.memory.store32(0, 0x00100093).unwrap();
+        // addi x2, x0, 0x100 # x2 = tohost 地址
+        env.memory.store32(4, 0x10000113).unwrap();
+        // sw x1, 0(x2)       # tohost = 1
+        env.memory.store32(8, 0x00112023).unwrap();
+        // jal x0, 0          # 死循环
+        env.memory.store32(12, 0x0000006F).unwrap();
+
+        let (executed, state) = env.run(10_000);
+
+        // 通用 run 接口应在下一个轮询点停机，而不是跑满 10000 条
+        assert!(executed <= 8, "应及时响应 tohost 写入，实际执行 {} 条", executed);
+        assert_eq!(state, CpuState::Exited(0));
+        assert_eq!(env.last_tohost, Some(1));
+        // ACK 后 tohost 应被清零
+        assert_eq!(env.memory.load32(0x100), Ok(0));
     }
 
     #[test]
@@ -1034,7 +5764,7 @@ mod tests {
             .with_elf_path(elf_path)
             .with_memory("ram", 0x80000000, 64 * 1024)
             .with_extensions(IsaExtensions::rv32g())
-            .with_verbose(true);
+            .with_verbosity(Verbosity::all(2));
 
         let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
         
@@ -1071,4 +5801,229 @@ mod tests {
         // 期望测试通过（暂时注释掉断言，先调试）
         // assert_eq!(result, TestResult::Pass, "ISA test should pass");
     }
+
+    #[test]
+    fn test_dry_decode_flags_unknown_encodings() {
+        let path = std::env::temp_dir().join("allude_sim_dry_decode_test.bin");
+        let mut image = Vec::new();
+        image.extend_from_slice(&0x00300293u32.to_le_bytes()); // addi x5, x0, 3
+        image.extend_from_slice(&0x026283B3u32.to_le_bytes()); // mul x7, x5, x6
+        image.extend_from_slice(&0x00000000u32.to_le_bytes()); // 非法编码
+        std::fs::write(&path, &image).unwrap();
+
+        // rv32i 配置：mul 与全零字都是未知编码
+        let config = SimConfig::new()
+            .with_bin_path(path.to_str().unwrap(), 0)
+            .with_entry_pc(0)
+            .with_memory_size(4096)
+            .with_extensions(IsaExtensions::rv32i());
+        let env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        let report = env.dry_decode();
+        assert_eq!(report.words_checked, 3);
+        assert_eq!(
+            report.unknown,
+            vec![
+                UnknownEncoding { addr: 4, raw: 0x026283B3 },
+                UnknownEncoding { addr: 8, raw: 0 },
+            ]
+        );
+        assert!(!report.is_clean());
+
+        // rv32im 配置：只剩全零字无法解码
+        let config = SimConfig::new()
+            .with_bin_path(path.to_str().unwrap(), 0)
+            .with_entry_pc(0)
+            .with_memory_size(4096)
+            .with_extensions(IsaExtensions::rv32im());
+        let env = SimEnv::from_config(config).expect("Failed to create sim env");
+
+        let report = env.dry_decode();
+        assert_eq!(report.unknown, vec![UnknownEncoding { addr: 8, raw: 0 }]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_reload_picks_up_new_binary() {
+        // 模拟编辑-编译-调试循环：重载后应读到新的二进制内容
+        let path = std::env::temp_dir().join("allude_sim_reload_test.bin");
+        std::fs::write(&path, 0x00100093u32.to_le_bytes()).unwrap(); // addi x1, x0, 1
+
+        let config = SimConfig::new()
+            .with_bin_path(path.to_str().unwrap(), 0)
+            .with_entry_pc(0)
+            .with_memory_size(4096);
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        env.step();
+        assert_eq!(env.cpu.read_reg(1), 1);
+
+        // “重新编译”：换一条指令后热重载
+        std::fs::write(&path, 0x00200093u32.to_le_bytes()).unwrap(); // addi x1, x0, 2
+        env.reload().expect("reload should succeed");
+
+        assert_eq!(env.instructions_executed, 0, "重载应复位计数");
+        assert_eq!(env.cpu.pc(), 0, "重载应复位 PC");
+        env.step();
+        assert_eq!(env.cpu.read_reg(1), 2, "应执行新镜像中的指令");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_reload_reresolves_symbol_breakpoints() {
+        let elf_path = "isa_test/rv32ui-p-add";
+        if !std::path::Path::new(elf_path).exists() {
+            println!("Skipping test: {} not found", elf_path);
+            return;
+        }
+
+        let config = SimConfig::new()
+            .with_elf_path(elf_path)
+            .with_memory("ram", 0x80000000, 64 * 1024)
+            .with_extensions(IsaExtensions::rv32g())
+            .with_break_symbol("fail");
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        let resolved = env.config.stop_conditions.clone();
+        assert_eq!(resolved.len(), 1, "符号断点应解析为一个 PC 断点");
+
+        env.reload().expect("reload should succeed");
+
+        // 重载后断点重新解析，而不是在旧条件上追加
+        assert_eq!(env.config.stop_conditions, resolved, "断点不应重复或漂移");
+    }
+
+    /// 手工构造一个带 .init_array 的最小 RV32 ELF：
+    /// 入口是 ecall，构造函数把 a0 置 7 后返回
+    fn build_init_array_elf() -> Vec<u8> {
+        let code: [u32; 3] = [
+            0x00000073, // 0x0: ecall（入口）
+            0x00700513, // 0x4: addi x10, x0, 7（构造函数）
+            0x00008067, // 0x8: jalr x0, 0(x1)
+        ];
+        let code_bytes: Vec<u8> = code.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let init_array = 4u32.to_le_bytes(); // 指向构造函数
+        let shstrtab = b"\0.init_array\0.shstrtab\0";
+
+        // 布局：ehdr(52) + phdr(32) + code(12) + init_array(4) + shstrtab(23) + pad + shdrs
+        let code_off = 84u32;
+        let init_off = code_off + code_bytes.len() as u32;
+        let str_off = init_off + 4;
+        let sh_off = (str_off + shstrtab.len() as u32 + 3) & !3;
+
+        let mut out = Vec::new();
+        // ELF 头
+        out.extend_from_slice(&[0x7F, b'E', b'L', b'F', 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        out.extend_from_slice(&2u16.to_le_bytes()); // e_type = EXEC
+        out.extend_from_slice(&0xF3u16.to_le_bytes()); // e_machine = RISC-V
+        out.extend_from_slice(&1u32.to_le_bytes()); // e_version
+        out.extend_from_slice(&0u32.to_le_bytes()); // e_entry
+        out.extend_from_slice(&52u32.to_le_bytes()); // e_phoff
+        out.extend_from_slice(&sh_off.to_le_bytes()); // e_shoff
+        out.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+        out.extend_from_slice(&52u16.to_le_bytes()); // e_ehsize
+        out.extend_from_slice(&32u16.to_le_bytes()); // e_phentsize
+        out.extend_from_slice(&1u16.to_le_bytes()); // e_phnum
+        out.extend_from_slice(&40u16.to_le_bytes()); // e_shentsize
+        out.extend_from_slice(&3u16.to_le_bytes()); // e_shnum
+        out.extend_from_slice(&2u16.to_le_bytes()); // e_shstrndx
+        // 程序头：LOAD code 到 vaddr 0
+        for v in [1u32, code_off, 0, 0, 12, 12, 5, 4] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out.extend_from_slice(&code_bytes);
+        out.extend_from_slice(&init_array);
+        out.extend_from_slice(shstrtab);
+        while out.len() < sh_off as usize {
+            out.push(0);
+        }
+        // 节头：NULL
+        out.extend_from_slice(&[0u8; 40]);
+        // 节头：.init_array (SHT_INIT_ARRAY = 14)
+        for v in [1u32, 14, 3, 0x100, init_off, 4, 0, 0, 4, 4] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        // 节头：.shstrtab (SHT_STRTAB = 3)
+        for v in [13u32, 3, 0, 0, str_off, shstrtab.len() as u32, 0, 0, 1, 0] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn test_init_array_constructors_run_before_entry() {
+        let bytes = build_init_array_elf();
+
+        let config = SimConfig::new()
+            .with_elf_bytes(bytes.clone())
+            .with_memory_size(4096)
+            .with_init_array();
+
+        let env = SimEnv::from_config(config).expect("Failed to create sim env");
+        assert_eq!(env.cpu.pc(), 0, "构造函数跑完后 PC 应回到入口");
+        assert_eq!(env.cpu.read_reg(10), 7, "构造函数应已执行");
+        assert_ne!(env.cpu.read_reg(2), 0, "crt-less 启动应设置 sp");
+
+        // 不开启选项时构造函数不应执行
+        let config = SimConfig::new()
+            .with_elf_bytes(bytes)
+            .with_memory_size(4096);
+        let env = SimEnv::from_config(config).expect("Failed to create sim env");
+        assert_eq!(env.cpu.read_reg(10), 0);
+    }
+
+    #[test]
+    fn test_from_elf_bytes() {
+        // 用磁盘上的 ISA 测试 ELF 验证内存镜像加载路径
+        let elf_path = "isa_test/rv32ui-p-add";
+        if !std::path::Path::new(elf_path).exists() {
+            println!("Skipping test: {} not found", elf_path);
+            return;
+        }
+
+        let bytes = std::fs::read(elf_path).expect("failed to read test ELF");
+
+        // 便捷构造：从镜像推导内存布局
+        let env = SimEnv::from_elf_bytes(bytes.clone()).expect("Failed to create sim env");
+        assert!(env.tohost_addr.is_some(), "应从内存镜像解析出 tohost 符号");
+
+        // 完整配置路径：with_elf_bytes + 扩展，端到端跑通测试
+        let config = SimConfig::new()
+            .with_elf_bytes(bytes)
+            .with_memory("ram", 0x80000000, 64 * 1024)
+            .with_extensions(IsaExtensions::rv32g());
+
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        let (result, _) = env.run_isa_test(1_000_000);
+        assert_eq!(result, TestResult::Pass, "rv32ui-p-add should pass");
+    }
+
+    #[test]
+    fn test_run_rv32ua_isa_tests() {
+        // 运行全部 rv32ua-p 原子扩展测试
+        let names = [
+            "amoadd_w", "amoand_w", "amomax_w", "amomaxu_w", "amomin_w",
+            "amominu_w", "amoor_w", "amoswap_w", "amoxor_w", "lrsc",
+        ];
+
+        for name in names {
+            let elf_path = format!("isa_test/rv32ua-p-{name}");
+            if !std::path::Path::new(&elf_path).exists() {
+                println!("Skipping test: {} not found", elf_path);
+                continue;
+            }
+
+            let config = SimConfig::new()
+                .with_elf_path(&elf_path)
+                .with_memory("ram", 0x80000000, 64 * 1024)
+                .with_extensions(IsaExtensions::rv32g());
+
+            let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+            let (result, executed) = env.run_isa_test(1_000_000);
+            println!("{name}: {result:?} ({executed} instructions)");
+            assert_eq!(result, TestResult::Pass, "rv32ua-p-{name} should pass");
+        }
+    }
 }
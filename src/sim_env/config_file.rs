@@ -0,0 +1,307 @@
+//! TOML 仿真配置文件
+//!
+//! [`SimConfig::from_file`]/[`SimConfig::to_file`] 的实现：把板级
+//! 配置（内存区域、ISA 字符串、外设基地址、入口与运行上限）写成
+//! 可复现、可分享的文本文件，无需写 Rust 代码。
+//!
+//! 解析器是手写的 TOML 子集（与 JSON/JUnit 输出同样不引入依赖）：
+//! 顶层键值、`[memory]` 与 `[devices]` 两个表、字符串/布尔/整数
+//! （支持 0x 前缀与下划线分隔），以及 `size = "1M"` 这样的带
+//! K/M/G 后缀的大小。`#` 之后为注释。未知键报错而不是静默忽略，
+//! 以免拼写错误悄悄生效为默认值。
+//!
+//! ```toml
+//! # 示例板级配置
+//! isa = "rv32imf"
+//! entry_pc = 0x8000_0000
+//! max_instructions = 10000000
+//! stop_on_trap = true
+//!
+//! [memory]
+//! name = "ram"
+//! base = 0x8000_0000
+//! size = "1M"
+//!
+//! [devices]
+//! uart = 0x1000_0000
+//! clint = 0x0200_0000
+//! ```
+
+use super::{IsaExtensions, SimConfig, SimError};
+
+/// 解析配置文本为 [`SimConfig`]（未出现的键保持默认值）
+pub fn parse(text: &str) -> Result<SimConfig, SimError> {
+    let mut config = SimConfig::new();
+    // 当前所在的表：None 为顶层
+    let mut section: Option<String> = None;
+
+    for (idx, raw_line) in text.lines().enumerate() {
+        let lineno = idx + 1;
+        let line = strip_comment(raw_line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| config_err(lineno, "表头缺少 ']'"))?
+                .trim();
+            match name {
+                "memory" | "devices" => section = Some(name.to_string()),
+                other => {
+                    return Err(config_err(lineno, &format!("未知的表: [{}]", other)));
+                }
+            }
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| config_err(lineno, "期望 key = value"))?;
+        let key = key.trim();
+        let value = value.trim();
+
+        match section.as_deref() {
+            None => apply_top_level(&mut config, key, value, lineno)?,
+            Some("memory") => apply_memory(&mut config, key, value, lineno)?,
+            Some("devices") => apply_device(&mut config, key, value, lineno)?,
+            Some(_) => unreachable!("section 只会被设置为已知表名"),
+        }
+    }
+
+    Ok(config)
+}
+
+/// 把配置渲染为可被 [`parse`] 读回的 TOML 文本
+///
+/// 只渲染文件格式覆盖的字段；ELF 字节、宿主回调等运行期内容
+/// 不在其列。
+pub fn render(config: &SimConfig) -> String {
+    let mut out = String::from("# allude_sim 仿真配置\n");
+    out.push_str(&format!("isa = \"{}\"\n", config.extensions.isa_string()));
+    if let Some(ref path) = config.elf_path {
+        out.push_str(&format!("elf = \"{}\"\n", path));
+    }
+    if let Some(pc) = config.entry_pc {
+        out.push_str(&format!("entry_pc = 0x{:08x}\n", pc));
+    }
+    if let Some(ref sym) = config.entry_symbol {
+        out.push_str(&format!("entry_symbol = \"{}\"\n", sym));
+    }
+    out.push_str(&format!("max_instructions = {}\n", config.max_instructions));
+    out.push_str(&format!("stop_on_trap = {}\n", config.stop_on_trap));
+    if config.seed != SimConfig::new().seed {
+        out.push_str(&format!("seed = {}\n", config.seed));
+    }
+
+    out.push_str("\n[memory]\n");
+    out.push_str(&format!("name = \"{}\"\n", config.memory.name));
+    out.push_str(&format!("base = 0x{:08x}\n", config.memory.base));
+    out.push_str(&format!("size = {}\n", config.memory.size));
+
+    let devices: Vec<(&str, Option<u32>)> = vec![
+        ("uart", config.uart_base),
+        ("clint", config.clint_base),
+        ("rng", config.rng_base),
+    ];
+    if devices.iter().any(|(_, base)| base.is_some()) {
+        out.push_str("\n[devices]\n");
+        for (name, base) in devices {
+            if let Some(base) = base {
+                out.push_str(&format!("{} = 0x{:08x}\n", name, base));
+            }
+        }
+    }
+
+    out
+}
+
+fn apply_top_level(
+    config: &mut SimConfig,
+    key: &str,
+    value: &str,
+    lineno: usize,
+) -> Result<(), SimError> {
+    match key {
+        "isa" => {
+            config.extensions = IsaExtensions::from_str(&parse_string(value, lineno)?)?;
+        }
+        "elf" => config.elf_path = Some(parse_string(value, lineno)?),
+        "entry_pc" => config.entry_pc = Some(parse_u32(value, lineno)?),
+        "entry_symbol" => config.entry_symbol = Some(parse_string(value, lineno)?),
+        "max_instructions" => config.max_instructions = parse_u64(value, lineno)?,
+        "stop_on_trap" => config.stop_on_trap = parse_bool(value, lineno)?,
+        "seed" => config.seed = parse_u64(value, lineno)?,
+        other => {
+            return Err(config_err(lineno, &format!("未知的配置键: {}", other)));
+        }
+    }
+    Ok(())
+}
+
+fn apply_memory(
+    config: &mut SimConfig,
+    key: &str,
+    value: &str,
+    lineno: usize,
+) -> Result<(), SimError> {
+    match key {
+        "name" => config.memory.name = parse_string(value, lineno)?,
+        "base" => config.memory.base = parse_u32(value, lineno)?,
+        "size" => config.memory.size = parse_size(value, lineno)?,
+        other => {
+            return Err(config_err(lineno, &format!("未知的 [memory] 键: {}", other)));
+        }
+    }
+    Ok(())
+}
+
+fn apply_device(
+    config: &mut SimConfig,
+    key: &str,
+    value: &str,
+    lineno: usize,
+) -> Result<(), SimError> {
+    let base = parse_u32(value, lineno)?;
+    match key {
+        "uart" => config.uart_base = Some(base),
+        "clint" => config.clint_base = Some(base),
+        "rng" => config.rng_base = Some(base),
+        other => {
+            return Err(config_err(lineno, &format!("未知的 [devices] 键: {}", other)));
+        }
+    }
+    Ok(())
+}
+
+/// 去掉 `#` 注释（不处理字符串内的 `#`，配置值中不允许出现）
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(pos) => &line[..pos],
+        None => line,
+    }
+}
+
+fn config_err(lineno: usize, msg: &str) -> SimError {
+    SimError::Config(format!("config line {}: {}", lineno, msg))
+}
+
+fn parse_string(value: &str, lineno: usize) -> Result<String, SimError> {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .map(str::to_string)
+        .ok_or_else(|| config_err(lineno, &format!("期望带引号的字符串，得到: {}", value)))
+}
+
+fn parse_bool(value: &str, lineno: usize) -> Result<bool, SimError> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        other => Err(config_err(lineno, &format!("期望 true/false，得到: {}", other))),
+    }
+}
+
+fn parse_u64(value: &str, lineno: usize) -> Result<u64, SimError> {
+    let cleaned = value.replace('_', "");
+    let result = if let Some(hex) = cleaned.strip_prefix("0x").or_else(|| cleaned.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else {
+        cleaned.parse()
+    };
+    result.map_err(|_| config_err(lineno, &format!("无效的整数: {}", value)))
+}
+
+fn parse_u32(value: &str, lineno: usize) -> Result<u32, SimError> {
+    let n = parse_u64(value, lineno)?;
+    u32::try_from(n).map_err(|_| config_err(lineno, &format!("超出 32 位范围: {}", value)))
+}
+
+/// 大小：纯整数，或 `"1M"` 这样带 K/M/G 后缀的带引号字符串
+fn parse_size(value: &str, lineno: usize) -> Result<usize, SimError> {
+    let text = if value.starts_with('"') {
+        parse_string(value, lineno)?
+    } else {
+        value.to_string()
+    };
+    let (digits, shift) = match text.as_bytes().last() {
+        Some(b'K' | b'k') => (&text[..text.len() - 1], 10u32),
+        Some(b'M' | b'm') => (&text[..text.len() - 1], 20),
+        Some(b'G' | b'g') => (&text[..text.len() - 1], 30),
+        _ => (text.as_str(), 0),
+    };
+    let base = parse_u64(digits, lineno)? as usize;
+    base.checked_shl(shift)
+        .ok_or_else(|| config_err(lineno, &format!("大小溢出: {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+# 板级配置示例
+isa = "rv32imf"
+entry_pc = 0x8000_0000
+max_instructions = 5000   # 运行上限
+stop_on_trap = true
+
+[memory]
+name = "sram"
+base = 0x8000_0000
+size = "1M"
+
+[devices]
+uart = 0x1000_0000
+clint = 0x0200_0000
+"#;
+
+    #[test]
+    fn test_parse_sample() {
+        let config = parse(SAMPLE).unwrap();
+        assert!(config.extensions.m);
+        assert!(config.extensions.f);
+        assert!(!config.extensions.v);
+        assert_eq!(config.entry_pc, Some(0x8000_0000));
+        assert_eq!(config.max_instructions, 5000);
+        assert!(config.stop_on_trap);
+        assert_eq!(config.memory.name, "sram");
+        assert_eq!(config.memory.base, 0x8000_0000);
+        assert_eq!(config.memory.size, 1024 * 1024);
+        assert_eq!(config.uart_base, Some(0x1000_0000));
+        assert_eq!(config.clint_base, Some(0x0200_0000));
+        assert_eq!(config.rng_base, None);
+    }
+
+    #[test]
+    fn test_render_parse_round_trip() {
+        let original = parse(SAMPLE).unwrap();
+        let rendered = render(&original);
+        let reparsed = parse(&rendered).unwrap();
+        assert_eq!(reparsed.entry_pc, original.entry_pc);
+        assert_eq!(reparsed.max_instructions, original.max_instructions);
+        assert_eq!(reparsed.stop_on_trap, original.stop_on_trap);
+        assert_eq!(reparsed.memory.base, original.memory.base);
+        assert_eq!(reparsed.memory.size, original.memory.size);
+        assert_eq!(reparsed.uart_base, original.uart_base);
+        assert_eq!(
+            reparsed.extensions.isa_string(),
+            original.extensions.isa_string()
+        );
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let err = parse("max_instrs = 100\n").unwrap_err();
+        assert!(matches!(err, SimError::Config(msg) if msg.contains("line 1")));
+        assert!(parse("[memory]\nbse = 0\n").is_err());
+        assert!(parse("[clint]\n").is_err());
+    }
+
+    #[test]
+    fn test_value_errors_carry_line_numbers() {
+        let err = parse("isa = \"rv32i\"\nentry_pc = oops\n").unwrap_err();
+        assert!(matches!(err, SimError::Config(msg) if msg.contains("line 2")));
+    }
+}
@@ -0,0 +1,315 @@
+//! 非 ELF 程序镜像加载器
+//!
+//! RTL 团队常用 Intel HEX（`.hex`）和 Verilog `$readmemh`
+//! （`.vh`/`.mem`）格式交换程序。本模块把这两种文本格式解析成
+//! 数据块列表，由 `SimEnv::from_config` 写入客体内存；格式按文件
+//! 扩展名自动识别（见 [`detect_format`]），其余扩展仍按原始二进制
+//! 处理。
+
+use std::path::Path;
+
+use super::SimError;
+
+/// 二进制程序文件的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinFormat {
+    /// 原始字节镜像
+    Raw,
+    /// Intel HEX（`:LLAAAATT...CC` 记录）
+    IntelHex,
+    /// Verilog `$readmemh` 输出（十六进制 token 与 `@addr` 指令）
+    VerilogHex,
+}
+
+/// 按扩展名识别格式：`.hex` → Intel HEX，`.vh`/`.mem` → Verilog
+/// `$readmemh`，其余 → 原始二进制
+pub fn detect_format(path: &str) -> BinFormat {
+    match Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("hex") => BinFormat::IntelHex,
+        Some("vh") | Some("mem") => BinFormat::VerilogHex,
+        _ => BinFormat::Raw,
+    }
+}
+
+/// 解析出的程序镜像：不连续的数据块与可选入口 PC
+#[derive(Debug, Clone)]
+pub struct LoadedImage {
+    /// (起始地址, 字节) 数据块，按文件出现顺序
+    pub chunks: Vec<(u32, Vec<u8>)>,
+    /// 文件内指定的入口 PC（Intel HEX 记录类型 05）
+    pub entry: Option<u32>,
+}
+
+impl LoadedImage {
+    /// 所有数据块的最低地址（空镜像为 None）
+    pub fn min_addr(&self) -> Option<u32> {
+        self.chunks.iter().map(|(addr, _)| *addr).min()
+    }
+
+    /// 数据总字节数
+    pub fn total_bytes(&self) -> usize {
+        self.chunks.iter().map(|(_, data)| data.len()).sum()
+    }
+}
+
+/// 解析 Intel HEX 文本
+///
+/// 支持的记录类型：00（数据）、01（EOF）、02/04（段/线性扩展
+/// 地址）、03/05（入口地址，03 按 CS<<4+IP 折算）。校验和不符或
+/// 记录格式非法时报错。地址是绝对地址，不叠加加载偏移。
+pub fn parse_intel_hex(text: &str) -> Result<LoadedImage, SimError> {
+    let mut chunks: Vec<(u32, Vec<u8>)> = Vec::new();
+    let mut upper: u32 = 0;
+    let mut entry = None;
+    let mut saw_eof = false;
+
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(record) = line.strip_prefix(':') else {
+            return Err(parse_err(lineno, "record must start with ':'"));
+        };
+        if saw_eof {
+            return Err(parse_err(lineno, "data after EOF record"));
+        }
+        if record.len() < 10 || record.len() % 2 != 0 {
+            return Err(parse_err(lineno, "record too short"));
+        }
+        let bytes: Vec<u8> = (0..record.len() / 2)
+            .map(|i| u8::from_str_radix(&record[i * 2..i * 2 + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| parse_err(lineno, "invalid hex digit"))?;
+
+        let count = bytes[0] as usize;
+        if bytes.len() != count + 5 {
+            return Err(parse_err(lineno, "byte count mismatch"));
+        }
+        let checksum: u8 = bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        if checksum != 0 {
+            return Err(parse_err(lineno, "checksum mismatch"));
+        }
+
+        let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        let rectype = bytes[3];
+        let data = &bytes[4..4 + count];
+        match rectype {
+            0x00 => {
+                let addr = upper.wrapping_add(offset);
+                // 与上一块连续时直接追加，保持块列表紧凑
+                match chunks.last_mut() {
+                    Some((base, buf)) if base.wrapping_add(buf.len() as u32) == addr => {
+                        buf.extend_from_slice(data);
+                    }
+                    _ => chunks.push((addr, data.to_vec())),
+                }
+            }
+            0x01 => saw_eof = true,
+            0x02 => {
+                if count != 2 {
+                    return Err(parse_err(lineno, "type 02 record must carry 2 bytes"));
+                }
+                upper = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4;
+            }
+            0x04 => {
+                if count != 2 {
+                    return Err(parse_err(lineno, "type 04 record must carry 2 bytes"));
+                }
+                upper = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16;
+            }
+            0x03 => {
+                if count != 4 {
+                    return Err(parse_err(lineno, "type 03 record must carry 4 bytes"));
+                }
+                let cs = u16::from_be_bytes([data[0], data[1]]) as u32;
+                let ip = u16::from_be_bytes([data[2], data[3]]) as u32;
+                entry = Some((cs << 4).wrapping_add(ip));
+            }
+            0x05 => {
+                if count != 4 {
+                    return Err(parse_err(lineno, "type 05 record must carry 4 bytes"));
+                }
+                entry = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+            }
+            other => {
+                return Err(parse_err(lineno, &format!("unknown record type {:#04x}", other)));
+            }
+        }
+    }
+
+    if !saw_eof {
+        return Err(SimError::ImageParse("Intel HEX missing EOF record".into()));
+    }
+    Ok(LoadedImage { chunks, entry })
+}
+
+/// 解析 Verilog `$readmemh` 文本
+///
+/// token 是十六进制元素，`@addr` 设置当前元素下标；支持 `//` 行
+/// 注释与 `/* */` 块注释。元素宽度（1/2/4 字节）按文件中最宽的
+/// token 推断，多字节元素按小端序展开。下标以元素为单位，叠加
+/// `base_addr` 折算成字节地址。
+pub fn parse_verilog_hex(text: &str, base_addr: u32) -> Result<LoadedImage, SimError> {
+    // 先剥掉注释再分词
+    let mut cleaned = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find("/*") {
+        cleaned.push_str(&rest[..pos]);
+        match rest[pos..].find("*/") {
+            Some(end) => rest = &rest[pos + end + 2..],
+            None => return Err(SimError::ImageParse("unterminated block comment".into())),
+        }
+    }
+    cleaned.push_str(rest);
+    let cleaned: String = cleaned
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // (元素下标, 值, 行号)；宽度在收集完后统一推断
+    let mut elems: Vec<(u32, u32)> = Vec::new();
+    let mut index: u32 = 0;
+    let mut width = 1usize;
+    for (lineno, line) in cleaned.lines().enumerate() {
+        for token in line.split_whitespace() {
+            if let Some(addr) = token.strip_prefix('@') {
+                index = u32::from_str_radix(addr, 16)
+                    .map_err(|_| parse_err(lineno, "invalid @address"))?;
+                continue;
+            }
+            let digits = token.trim_start_matches('_');
+            let value = u32::from_str_radix(digits, 16)
+                .map_err(|_| parse_err(lineno, &format!("invalid hex token '{}'", token)))?;
+            width = width.max(match digits.len() {
+                0..=2 => 1,
+                3..=4 => 2,
+                5..=8 => 4,
+                _ => return Err(parse_err(lineno, "token wider than 32 bits")),
+            });
+            elems.push((index, value));
+            index += 1;
+        }
+    }
+
+    // 按元素宽度展开成字节块，相邻下标合并
+    let mut chunks: Vec<(u32, Vec<u8>)> = Vec::new();
+    for (idx, value) in elems {
+        let addr = base_addr.wrapping_add(idx * width as u32);
+        let le = value.to_le_bytes();
+        let data = &le[..width];
+        match chunks.last_mut() {
+            Some((base, buf)) if base.wrapping_add(buf.len() as u32) == addr => {
+                buf.extend_from_slice(data);
+            }
+            _ => chunks.push((addr, data.to_vec())),
+        }
+    }
+    Ok(LoadedImage { chunks, entry: None })
+}
+
+fn parse_err(lineno: usize, msg: &str) -> SimError {
+    SimError::ImageParse(format!("line {}: {}", lineno + 1, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format_by_extension() {
+        assert_eq!(detect_format("prog.hex"), BinFormat::IntelHex);
+        assert_eq!(detect_format("prog.HEX"), BinFormat::IntelHex);
+        assert_eq!(detect_format("prog.vh"), BinFormat::VerilogHex);
+        assert_eq!(detect_format("prog.mem"), BinFormat::VerilogHex);
+        assert_eq!(detect_format("prog.bin"), BinFormat::Raw);
+        assert_eq!(detect_format("prog"), BinFormat::Raw);
+    }
+
+    #[test]
+    fn test_intel_hex_data_and_entry() {
+        // 两条数据记录（连续）+ 入口 0x80000000 + EOF
+        let mut hex = String::new();
+        hex.push_str(&record(0x0000, 0x00, &[0x93, 0x00, 0x00, 0x00]));
+        hex.push_str(&record(0x0004, 0x00, &[0x13, 0x01, 0x01, 0x00]));
+        hex.push_str(&record(0x0000, 0x05, &[0x80, 0x00, 0x00, 0x00]));
+        hex.push_str(":00000001FF\n");
+
+        let image = parse_intel_hex(&hex).unwrap();
+        assert_eq!(image.entry, Some(0x8000_0000));
+        assert_eq!(image.chunks.len(), 1, "连续记录应合并成一块");
+        assert_eq!(image.chunks[0].0, 0);
+        assert_eq!(
+            image.chunks[0].1,
+            vec![0x93, 0x00, 0x00, 0x00, 0x13, 0x01, 0x01, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_intel_hex_extended_linear_address() {
+        let mut hex = String::new();
+        hex.push_str(&record(0x0000, 0x04, &[0x80, 0x00])); // upper = 0x8000_0000
+        hex.push_str(&record(0x0010, 0x00, &[0xAA, 0xBB]));
+        hex.push_str(":00000001FF\n");
+
+        let image = parse_intel_hex(&hex).unwrap();
+        assert_eq!(image.chunks[0].0, 0x8000_0010);
+        assert_eq!(image.chunks[0].1, vec![0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn test_intel_hex_rejects_bad_checksum() {
+        let hex = ":02000000AABB00\n:00000001FF\n"; // 校验和错误
+        assert!(parse_intel_hex(hex).is_err());
+    }
+
+    #[test]
+    fn test_intel_hex_requires_eof() {
+        let hex = record(0x0000, 0x00, &[0x01]);
+        assert!(parse_intel_hex(&hex).is_err());
+    }
+
+    #[test]
+    fn test_verilog_hex_words_and_at_address() {
+        // 32 位元素，@4 从第 4 个元素（字节 16）继续
+        let text = "
+            // boot code
+            00000093 00010113
+            @4
+            deadbeef /* data */
+        ";
+        let image = parse_verilog_hex(text, 0x8000_0000).unwrap();
+        assert_eq!(image.chunks.len(), 2);
+        assert_eq!(image.chunks[0].0, 0x8000_0000);
+        assert_eq!(
+            image.chunks[0].1,
+            vec![0x93, 0x00, 0x00, 0x00, 0x13, 0x01, 0x01, 0x00]
+        );
+        assert_eq!(image.chunks[1].0, 0x8000_0010);
+        assert_eq!(image.chunks[1].1, vec![0xEF, 0xBE, 0xAD, 0xDE]);
+    }
+
+    #[test]
+    fn test_verilog_hex_byte_elements() {
+        let image = parse_verilog_hex("12 34 56", 0x100).unwrap();
+        assert_eq!(image.chunks.len(), 1);
+        assert_eq!(image.chunks[0].0, 0x100);
+        assert_eq!(image.chunks[0].1, vec![0x12, 0x34, 0x56]);
+    }
+
+    /// 构造一条带正确校验和的 Intel HEX 记录
+    fn record(offset: u16, rectype: u8, data: &[u8]) -> String {
+        let mut bytes = vec![data.len() as u8, (offset >> 8) as u8, offset as u8, rectype];
+        bytes.extend_from_slice(data);
+        let sum: u8 = bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        bytes.push(sum.wrapping_neg());
+        let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        format!(":{}\n", hex)
+    }
+}
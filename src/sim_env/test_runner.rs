@@ -0,0 +1,403 @@
+//! 批量 ISA 测试运行器
+//!
+//! 把 `examples/run_rv32um.rs` 的套件循环抽成可复用的库 API：
+//! 发现 `isa_test/` 下的 rv32ui/um/uf/ua 套件，用线程池并行执行，
+//! 并能输出 JUnit-XML 或 JSON 汇总供 CI 收集。
+//! `cargo run --bin isa-test` 是对应的命令行入口。
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{IsaExtensions, SimConfig, SimEnv, SimError, TestResult};
+
+/// 默认发现的套件（isa_test/ 下的文件名前缀，`<suite>-p-<case>`）
+pub const DEFAULT_SUITES: &[&str] = &["rv32ui", "rv32um", "rv32uf", "rv32ua"];
+
+/// 批量运行器配置
+#[derive(Debug, Clone)]
+pub struct RunnerConfig {
+    /// 测试 ELF 所在目录
+    pub root: PathBuf,
+    /// 要运行的套件前缀（见 [`DEFAULT_SUITES`]）
+    pub suites: Vec<String>,
+    /// 只运行文件名包含该子串的用例
+    pub filter: Option<String>,
+    /// 并行线程数，0 表示取可用核数
+    pub jobs: usize,
+    /// 单个用例的最大执行指令数
+    pub max_instructions: u64,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("isa_test"),
+            suites: DEFAULT_SUITES.iter().map(|s| s.to_string()).collect(),
+            filter: None,
+            jobs: 0,
+            max_instructions: 2_000_000,
+        }
+    }
+}
+
+/// 单个用例的运行结果
+#[derive(Debug, Clone)]
+pub struct CaseReport {
+    /// 所属套件前缀（如 "rv32ui"）
+    pub suite: String,
+    /// 用例文件名（如 "rv32ui-p-add"）
+    pub name: String,
+    /// ISA 测试判定结果
+    pub result: TestResult,
+    /// 实际执行的指令数
+    pub executed: u64,
+    /// 用例耗时
+    pub duration: Duration,
+    /// 环境构建或加载失败时的错误信息（此时 `result` 无意义）
+    pub error: Option<String>,
+}
+
+impl CaseReport {
+    /// 该用例是否通过
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.result == TestResult::Pass
+    }
+
+    /// 失败/错误的简短描述，通过时返回 None
+    pub fn failure_message(&self) -> Option<String> {
+        if let Some(err) = &self.error {
+            return Some(err.clone());
+        }
+        match self.result {
+            TestResult::Pass => None,
+            TestResult::Fail(n) => Some(format!("test case {} failed", n)),
+            TestResult::Timeout => Some("timeout or incomplete".to_string()),
+        }
+    }
+}
+
+/// 一次批量运行的汇总
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    /// 按（套件，用例名）排序的所有用例结果
+    pub cases: Vec<CaseReport>,
+    /// 整体墙上时间（并行执行，小于各用例耗时之和）
+    pub wall_time: Duration,
+}
+
+impl RunReport {
+    /// 通过的用例数
+    pub fn passed(&self) -> usize {
+        self.cases.iter().filter(|c| c.passed()).count()
+    }
+
+    /// 失败（含错误）的用例数
+    pub fn failed(&self) -> usize {
+        self.cases.len() - self.passed()
+    }
+
+    /// 是否全部通过
+    pub fn all_passed(&self) -> bool {
+        self.failed() == 0
+    }
+
+    /// 输出 JSON 汇总
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        out.push_str("{\n");
+        out.push_str(&format!("  \"total\": {},\n", self.cases.len()));
+        out.push_str(&format!("  \"passed\": {},\n", self.passed()));
+        out.push_str(&format!("  \"failed\": {},\n", self.failed()));
+        out.push_str(&format!(
+            "  \"wall_time_ms\": {},\n",
+            self.wall_time.as_millis()
+        ));
+        out.push_str("  \"cases\": [\n");
+        for (i, case) in self.cases.iter().enumerate() {
+            let status = if case.passed() { "pass" } else { "fail" };
+            out.push_str(&format!(
+                "    {{\"suite\": \"{}\", \"name\": \"{}\", \"status\": \"{}\", \
+                 \"executed\": {}, \"time_ms\": {}",
+                json_escape(&case.suite),
+                json_escape(&case.name),
+                status,
+                case.executed,
+                case.duration.as_millis()
+            ));
+            if let Some(msg) = case.failure_message() {
+                out.push_str(&format!(", \"message\": \"{}\"", json_escape(&msg)));
+            }
+            out.push('}');
+            if i + 1 < self.cases.len() {
+                out.push(',');
+            }
+            out.push('\n');
+        }
+        out.push_str("  ]\n}\n");
+        out
+    }
+
+    /// 输出 JUnit-XML 汇总（每个套件一个 `<testsuite>`）
+    pub fn to_junit_xml(&self) -> String {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(&format!(
+            "<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+            self.cases.len(),
+            self.failed(),
+            self.wall_time.as_secs_f64()
+        ));
+
+        // cases 已按套件排序，按前缀分段输出
+        let mut idx = 0;
+        while idx < self.cases.len() {
+            let suite = &self.cases[idx].suite;
+            let end = self.cases[idx..]
+                .iter()
+                .position(|c| &c.suite != suite)
+                .map(|off| idx + off)
+                .unwrap_or(self.cases.len());
+            let group = &self.cases[idx..end];
+            let failures = group.iter().filter(|c| !c.passed()).count();
+            let time: f64 = group.iter().map(|c| c.duration.as_secs_f64()).sum();
+            out.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+                xml_escape(suite),
+                group.len(),
+                failures,
+                time
+            ));
+            for case in group {
+                if let Some(msg) = case.failure_message() {
+                    out.push_str(&format!(
+                        "    <testcase name=\"{}\" time=\"{:.3}\"><failure message=\"{}\"/></testcase>\n",
+                        xml_escape(&case.name),
+                        case.duration.as_secs_f64(),
+                        xml_escape(&msg)
+                    ));
+                } else {
+                    out.push_str(&format!(
+                        "    <testcase name=\"{}\" time=\"{:.3}\"/>\n",
+                        xml_escape(&case.name),
+                        case.duration.as_secs_f64()
+                    ));
+                }
+            }
+            out.push_str("  </testsuite>\n");
+            idx = end;
+        }
+
+        out.push_str("</testsuites>\n");
+        out
+    }
+}
+
+/// 发现配置指定套件下的所有用例，返回（套件前缀，路径）对
+///
+/// 跳过 `.dump` 反汇编文件；结果按套件、文件名排序
+pub fn discover(config: &RunnerConfig) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(&config.root)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if name.ends_with(".dump") {
+            continue;
+        }
+        let Some(suite) = config
+            .suites
+            .iter()
+            .find(|s| name.starts_with(&format!("{}-p-", s)))
+        else {
+            continue;
+        };
+        if let Some(pattern) = &config.filter
+            && !name.contains(pattern.as_str())
+        {
+            continue;
+        }
+        cases.push((suite.clone(), path));
+    }
+    cases.sort();
+    Ok(cases)
+}
+
+/// 发现并并行运行所有用例
+///
+/// 每个工作线程独立构建 SimEnv（SimEnv 持有 Rc，不跨线程共享），
+/// 从共享的原子游标领取用例
+pub fn run(config: &RunnerConfig) -> Result<RunReport, SimError> {
+    if !config.root.exists() {
+        return Err(SimError::Config(format!(
+            "test root {} does not exist",
+            config.root.display()
+        )));
+    }
+    let cases = discover(config).map_err(SimError::Io)?;
+
+    let jobs = if config.jobs == 0 {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    } else {
+        config.jobs
+    };
+    let jobs = jobs.min(cases.len()).max(1);
+
+    let start = Instant::now();
+    let next = AtomicUsize::new(0);
+    let reports = Mutex::new(Vec::with_capacity(cases.len()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                let Some((suite, path)) = cases.get(idx) else {
+                    break;
+                };
+                let report = run_case(suite, path, config.max_instructions);
+                reports.lock().unwrap().push(report);
+            });
+        }
+    });
+
+    let mut cases = reports.into_inner().unwrap();
+    cases.sort_by(|a, b| (&a.suite, &a.name).cmp(&(&b.suite, &b.name)));
+    Ok(RunReport {
+        cases,
+        wall_time: start.elapsed(),
+    })
+}
+
+/// 运行单个用例；环境构建失败记为带 error 的报告而不是中断整批
+fn run_case(suite: &str, path: &Path, max_instructions: u64) -> CaseReport {
+    let name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let start = Instant::now();
+
+    let config = SimConfig::new()
+        .with_elf_path(path.to_string_lossy().into_owned())
+        .with_memory("ram", 0x8000_0000, 512 * 1024)
+        .with_extensions(IsaExtensions::rv32g());
+
+    match SimEnv::from_config(config) {
+        Ok(mut env) => {
+            let (result, executed) = env.run_isa_test(max_instructions);
+            CaseReport {
+                suite: suite.to_string(),
+                name,
+                result,
+                executed,
+                duration: start.elapsed(),
+                error: None,
+            }
+        }
+        Err(err) => CaseReport {
+            suite: suite.to_string(),
+            name,
+            result: TestResult::Timeout,
+            executed: 0,
+            duration: start.elapsed(),
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+/// JSON 字符串转义（仅处理汇总里会出现的字符）
+fn json_escape(s: &str) -> String {
+    s.chars()
+        .flat_map(|c| match c {
+            '"' => "\\\"".chars().collect::<Vec<_>>(),
+            '\\' => "\\\\".chars().collect(),
+            '\n' => "\\n".chars().collect(),
+            c => vec![c],
+        })
+        .collect()
+}
+
+/// XML 属性转义
+fn xml_escape(s: &str) -> String {
+    s.chars()
+        .flat_map(|c| match c {
+            '&' => "&amp;".chars().collect::<Vec<_>>(),
+            '<' => "&lt;".chars().collect(),
+            '>' => "&gt;".chars().collect(),
+            '"' => "&quot;".chars().collect(),
+            c => vec![c],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_report() -> RunReport {
+        RunReport {
+            cases: vec![
+                CaseReport {
+                    suite: "rv32ui".to_string(),
+                    name: "rv32ui-p-add".to_string(),
+                    result: TestResult::Pass,
+                    executed: 500,
+                    duration: Duration::from_millis(3),
+                    error: None,
+                },
+                CaseReport {
+                    suite: "rv32um".to_string(),
+                    name: "rv32um-p-div".to_string(),
+                    result: TestResult::Fail(5),
+                    executed: 200,
+                    duration: Duration::from_millis(2),
+                    error: None,
+                },
+            ],
+            wall_time: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    fn test_report_counts() {
+        let report = sample_report();
+        assert_eq!(report.passed(), 1);
+        assert_eq!(report.failed(), 1);
+        assert!(!report.all_passed());
+    }
+
+    #[test]
+    fn test_junit_xml_shape() {
+        let xml = sample_report().to_junit_xml();
+        assert!(xml.contains("<testsuites tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("<testsuite name=\"rv32ui\" tests=\"1\" failures=\"0\""));
+        assert!(xml.contains("<testcase name=\"rv32ui-p-add\""));
+        assert!(xml.contains("<failure message=\"test case 5 failed\"/>"));
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let json = sample_report().to_json();
+        assert!(json.contains("\"total\": 2"));
+        assert!(json.contains("\"status\": \"pass\""));
+        assert!(json.contains("\"message\": \"test case 5 failed\""));
+    }
+
+    #[test]
+    fn test_discover_missing_root() {
+        let config = RunnerConfig {
+            root: PathBuf::from("no-such-dir"),
+            ..Default::default()
+        };
+        assert!(discover(&config).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! 逐指令执行统计与直方图报告
+//!
+//! 按助记符和指令类别（load/store/分支/ALU 等)统计执行次数，并
+//! 跟踪分支 taken/not-taken 比例与访存总量。通过
+//! `SimConfig::with_stats` 启用，运行结束后由 `SimEnv::stats` 读取，
+//! [`ExecStats`] 的 `Display` 实现生成直方图报告——在向 GPGPU 演化
+//! 之前，用它刻画负载的指令构成与访存密度。
+//!
+//! 与 `SimEnv::instr_usage_report`（回答"用了哪些扩展"的合规问题）
+//! 互补，本模块回答的是"时间花在哪类指令上"的性能问题。
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::isa::RvInstr;
+
+/// 指令类别（用于按负载特征归类，而非按扩展归类）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InstrClass {
+    /// 算术/逻辑运算（整数、乘除、浮点计算、向量算术等）
+    Alu,
+    /// 内存加载
+    Load,
+    /// 内存存储
+    Store,
+    /// 原子读改写（同时计入 load 与 store 访存量）
+    Amo,
+    /// 条件分支
+    Branch,
+    /// 无条件跳转（jal/jalr）
+    Jump,
+    /// 系统指令（ecall/ebreak/fence/CSR/特权指令）
+    System,
+    /// 其他（非法指令、自定义指令）
+    Other,
+}
+
+impl InstrClass {
+    /// 所有类别，按报告中的展示顺序
+    pub const ALL: [InstrClass; 8] = [
+        InstrClass::Alu,
+        InstrClass::Load,
+        InstrClass::Store,
+        InstrClass::Amo,
+        InstrClass::Branch,
+        InstrClass::Jump,
+        InstrClass::System,
+        InstrClass::Other,
+    ];
+
+    /// 类别名称（报告用）
+    pub fn name(self) -> &'static str {
+        match self {
+            InstrClass::Alu => "alu",
+            InstrClass::Load => "load",
+            InstrClass::Store => "store",
+            InstrClass::Amo => "amo",
+            InstrClass::Branch => "branch",
+            InstrClass::Jump => "jump",
+            InstrClass::System => "system",
+            InstrClass::Other => "other",
+        }
+    }
+
+    /// 指令所属的类别
+    pub fn of(instr: &RvInstr) -> InstrClass {
+        match instr {
+            RvInstr::Lb { .. }
+            | RvInstr::Lh { .. }
+            | RvInstr::Lw { .. }
+            | RvInstr::Lbu { .. }
+            | RvInstr::Lhu { .. }
+            | RvInstr::Flw { .. }
+            | RvInstr::LrW { .. }
+            | RvInstr::VleV { .. } => InstrClass::Load,
+
+            RvInstr::Sb { .. }
+            | RvInstr::Sh { .. }
+            | RvInstr::Sw { .. }
+            | RvInstr::Fsw { .. }
+            | RvInstr::ScW { .. }
+            | RvInstr::VseV { .. } => InstrClass::Store,
+
+            RvInstr::AmoswapW { .. }
+            | RvInstr::AmoaddW { .. }
+            | RvInstr::AmoxorW { .. }
+            | RvInstr::AmoandW { .. }
+            | RvInstr::AmoorW { .. }
+            | RvInstr::AmominW { .. }
+            | RvInstr::AmomaxW { .. }
+            | RvInstr::AmominuW { .. }
+            | RvInstr::AmomaxuW { .. } => InstrClass::Amo,
+
+            RvInstr::Beq { .. }
+            | RvInstr::Bne { .. }
+            | RvInstr::Blt { .. }
+            | RvInstr::Bge { .. }
+            | RvInstr::Bltu { .. }
+            | RvInstr::Bgeu { .. } => InstrClass::Branch,
+
+            RvInstr::Jal { .. } | RvInstr::Jalr { .. } => InstrClass::Jump,
+
+            RvInstr::Ecall
+            | RvInstr::Ebreak
+            | RvInstr::Fence { .. }
+            | RvInstr::FenceI
+            | RvInstr::Csrrw { .. }
+            | RvInstr::Csrrs { .. }
+            | RvInstr::Csrrc { .. }
+            | RvInstr::Csrrwi { .. }
+            | RvInstr::Csrrsi { .. }
+            | RvInstr::Csrrci { .. }
+            | RvInstr::Mret
+            | RvInstr::Sret
+            | RvInstr::Wfi => InstrClass::System,
+
+            RvInstr::Illegal { .. } | RvInstr::Custom { .. } => InstrClass::Other,
+
+            // 其余全部是寄存器到寄存器的运算
+            _ => InstrClass::Alu,
+        }
+    }
+}
+
+impl fmt::Display for InstrClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 指令访存的字节数（读字节数, 写字节数）
+///
+/// 向量 load/store 按整个向量寄存器（VLEN/8 = 16 字节）计；AMO
+/// 同时计一次读和一次写。
+fn mem_bytes(instr: &RvInstr) -> (u64, u64) {
+    match instr {
+        RvInstr::Lb { .. } | RvInstr::Lbu { .. } => (1, 0),
+        RvInstr::Lh { .. } | RvInstr::Lhu { .. } => (2, 0),
+        RvInstr::Lw { .. } | RvInstr::Flw { .. } | RvInstr::LrW { .. } => (4, 0),
+        RvInstr::VleV { .. } => (16, 0),
+        RvInstr::Sb { .. } => (0, 1),
+        RvInstr::Sh { .. } => (0, 2),
+        RvInstr::Sw { .. } | RvInstr::Fsw { .. } | RvInstr::ScW { .. } => (0, 4),
+        RvInstr::VseV { .. } => (0, 16),
+        RvInstr::AmoswapW { .. }
+        | RvInstr::AmoaddW { .. }
+        | RvInstr::AmoxorW { .. }
+        | RvInstr::AmoandW { .. }
+        | RvInstr::AmoorW { .. }
+        | RvInstr::AmominW { .. }
+        | RvInstr::AmomaxW { .. }
+        | RvInstr::AmominuW { .. }
+        | RvInstr::AmomaxuW { .. } => (4, 4),
+        _ => (0, 0),
+    }
+}
+
+/// 逐指令执行统计
+///
+/// 由 `CpuCore` 在启用后逐条累积（见 `CpuCore::enable_stats`），
+/// `Display` 实现输出按执行次数降序的助记符直方图、类别分布、
+/// 分支 taken 比例和访存总量。
+#[derive(Debug, Clone, Default)]
+pub struct ExecStats {
+    /// 各助记符的执行次数
+    pub mnemonics: BTreeMap<&'static str, u64>,
+    /// 各类别的执行次数
+    pub classes: BTreeMap<InstrClass, u64>,
+    /// 统计到的指令总数
+    pub total: u64,
+    /// 发生跳转的条件分支数
+    pub branches_taken: u64,
+    /// 顺序落空的条件分支数
+    pub branches_not_taken: u64,
+    /// 从内存读取的总字节数
+    pub bytes_loaded: u64,
+    /// 写入内存的总字节数
+    pub bytes_stored: u64,
+}
+
+impl ExecStats {
+    /// 记录一条已执行的指令
+    ///
+    /// `branch_taken` 只对条件分支有意义：执行后 PC 是否偏离了
+    /// 顺序下一条指令。
+    pub fn record(&mut self, instr: &RvInstr, branch_taken: bool) {
+        self.total += 1;
+        *self.mnemonics.entry(instr.mnemonic()).or_insert(0) += 1;
+        let class = InstrClass::of(instr);
+        *self.classes.entry(class).or_insert(0) += 1;
+        if class == InstrClass::Branch {
+            if branch_taken {
+                self.branches_taken += 1;
+            } else {
+                self.branches_not_taken += 1;
+            }
+        }
+        let (loaded, stored) = mem_bytes(instr);
+        self.bytes_loaded += loaded;
+        self.bytes_stored += stored;
+    }
+
+    /// 某个类别的执行次数
+    pub fn class_count(&self, class: InstrClass) -> u64 {
+        self.classes.get(&class).copied().unwrap_or(0)
+    }
+
+    /// 条件分支的 taken 比例（没有执行过分支时为 None）
+    pub fn branch_taken_ratio(&self) -> Option<f64> {
+        let total = self.branches_taken + self.branches_not_taken;
+        (total > 0).then(|| self.branches_taken as f64 / total as f64)
+    }
+}
+
+impl fmt::Display for ExecStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "共执行 {} 条指令", self.total)?;
+
+        writeln!(f, "类别分布:")?;
+        for class in InstrClass::ALL {
+            let count = self.class_count(class);
+            if count == 0 {
+                continue;
+            }
+            writeln!(
+                f,
+                "  {:<8} {:>10}  ({:5.1}%)",
+                class.name(),
+                count,
+                count as f64 * 100.0 / self.total.max(1) as f64
+            )?;
+        }
+
+        if let Some(ratio) = self.branch_taken_ratio() {
+            writeln!(
+                f,
+                "分支: taken {} / not-taken {} ({:.1}% taken)",
+                self.branches_taken,
+                self.branches_not_taken,
+                ratio * 100.0
+            )?;
+        }
+        writeln!(
+            f,
+            "访存: 读 {} 字节, 写 {} 字节",
+            self.bytes_loaded, self.bytes_stored
+        )?;
+
+        // 助记符直方图，按执行次数降序，条形按最大计数归一
+        let mut entries: Vec<(&'static str, u64)> =
+            self.mnemonics.iter().map(|(&m, &c)| (m, c)).collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let max = entries.first().map(|&(_, c)| c).unwrap_or(0).max(1);
+
+        writeln!(f, "指令直方图:")?;
+        for (mnemonic, count) in entries {
+            const BAR_WIDTH: u64 = 40;
+            let bar = (count * BAR_WIDTH).div_ceil(max) as usize;
+            writeln!(f, "  {:<12} {:>10}  {}", mnemonic, count, "#".repeat(bar))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_by_variant() {
+        assert_eq!(
+            InstrClass::of(&RvInstr::Lw { rd: 1, rs1: 2, offset: 0 }),
+            InstrClass::Load
+        );
+        assert_eq!(
+            InstrClass::of(&RvInstr::Sb { rs1: 2, rs2: 1, offset: 0 }),
+            InstrClass::Store
+        );
+        assert_eq!(
+            InstrClass::of(&RvInstr::Beq { rs1: 1, rs2: 2, offset: 8 }),
+            InstrClass::Branch
+        );
+        assert_eq!(InstrClass::of(&RvInstr::Jalr { rd: 0, rs1: 1, offset: 0 }), InstrClass::Jump);
+        assert_eq!(
+            InstrClass::of(&RvInstr::Mul { rd: 1, rs1: 2, rs2: 3 }),
+            InstrClass::Alu
+        );
+        assert_eq!(InstrClass::of(&RvInstr::Ecall), InstrClass::System);
+        assert_eq!(InstrClass::of(&RvInstr::Illegal { raw: 0 }), InstrClass::Other);
+    }
+
+    #[test]
+    fn test_record_accumulates_memory_totals() {
+        let mut stats = ExecStats::default();
+        stats.record(&RvInstr::Lw { rd: 1, rs1: 2, offset: 0 }, false);
+        stats.record(&RvInstr::Lh { rd: 1, rs1: 2, offset: 0 }, false);
+        stats.record(&RvInstr::Sb { rs1: 2, rs2: 1, offset: 0 }, false);
+        stats.record(&RvInstr::AmoaddW { rd: 1, rs1: 2, rs2: 3 }, false);
+
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.bytes_loaded, 4 + 2 + 4);
+        assert_eq!(stats.bytes_stored, 1 + 4);
+        assert_eq!(stats.class_count(InstrClass::Load), 2);
+        assert_eq!(stats.class_count(InstrClass::Amo), 1);
+    }
+
+    #[test]
+    fn test_branch_taken_ratio() {
+        let mut stats = ExecStats::default();
+        assert_eq!(stats.branch_taken_ratio(), None);
+
+        let beq = RvInstr::Beq { rs1: 1, rs2: 2, offset: 8 };
+        stats.record(&beq, true);
+        stats.record(&beq, true);
+        stats.record(&beq, true);
+        stats.record(&beq, false);
+        assert_eq!(stats.branch_taken_ratio(), Some(0.75));
+    }
+}
@@ -0,0 +1,327 @@
+//! ECALL 系统调用仿真（代理内核 / newlib semihosting）
+//!
+//! 在 `SimEnv` 中拦截 ECALL，按 RISC-V newlib/pk 约定（调用号在
+//! a7，参数 a0-a2，返回值写回 a0，失败返回负 errno）仿真常见系统
+//! 调用，而不是每次都陷入 mtvec。write/read 直通宿主 stdio（可
+//! 重定向），brk 复用 [`crate::sim_env::GuestHeap`] 的程序 break
+//! 管理。配合 `SimConfig::with_syscalls` 与 `with_heap`，
+//! `riscv32-unknown-elf-gcc` 编译的 C 程序的 printf 开箱即用。
+//!
+//! 未识别的调用号交还正常的 trap 路径，客体自己的处理程序仍可
+//! 接管。
+
+use std::io::{self, Read, Write};
+
+use crate::cpu::CpuCore;
+use crate::memory::GuestMemory;
+use crate::sim_env::GuestHeap;
+
+/// ECALL 的指令编码（`SimEnv` 取指前据此判断是否拦截）
+pub const ECALL_ENCODING: u32 = 0x0000_0073;
+
+/// RISC-V newlib/pk 的系统调用号
+pub mod nr {
+    pub const CLOSE: u32 = 57;
+    pub const READ: u32 = 63;
+    pub const WRITE: u32 = 64;
+    pub const FSTAT: u32 = 80;
+    pub const EXIT: u32 = 93;
+    pub const BRK: u32 = 214;
+}
+
+/// 以负数形式返回的 errno 值
+mod errno {
+    pub const EBADF: u32 = 9;
+    pub const ENOMEM: u32 = 12;
+    pub const EFAULT: u32 = 14;
+}
+
+/// 一次 ECALL 仿真的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallOutcome {
+    /// 已处理：返回值应写入 a0，PC 前进到下一条指令
+    Handled(u32),
+    /// exit 调用：携带退出码，仿真应停机
+    Exit(i32),
+    /// 未识别的调用号：交还正常的 trap 路径执行这条 ECALL
+    Unhandled,
+}
+
+/// 系统调用仿真器
+///
+/// 持有宿主侧的标准流。默认 stdout/stderr 直通宿主进程，stdin
+/// 从宿主 stdin 读取；三者都可替换（测试里典型用法是
+/// [`crate::devices::SharedBuffer`] 捕获输出、`io::Cursor` 喂输入）。
+pub struct SyscallEmulator {
+    stdout: Box<dyn Write>,
+    stderr: Box<dyn Write>,
+    stdin: Box<dyn Read>,
+}
+
+impl Default for SyscallEmulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SyscallEmulator {
+    /// 创建直通宿主 stdio 的仿真器
+    pub fn new() -> Self {
+        SyscallEmulator {
+            stdout: Box::new(io::stdout()),
+            stderr: Box::new(io::stderr()),
+            stdin: Box::new(io::stdin()),
+        }
+    }
+
+    /// 重定向客体的标准输出
+    pub fn set_stdout(&mut self, sink: Box<dyn Write>) {
+        self.stdout = sink;
+    }
+
+    /// 重定向客体的标准错误
+    pub fn set_stderr(&mut self, sink: Box<dyn Write>) {
+        self.stderr = sink;
+    }
+
+    /// 替换客体标准输入的来源
+    pub fn set_stdin(&mut self, source: Box<dyn Read>) {
+        self.stdin = source;
+    }
+
+    /// 处理 PC 停在 ECALL 上的一次系统调用
+    ///
+    /// 只读取寄存器，不推进 PC——写回 a0 和前进由调用方
+    /// （`SimEnv::step`）根据返回的 [`SyscallOutcome`] 完成。
+    pub fn handle(
+        &mut self,
+        cpu: &CpuCore,
+        mem: &mut GuestMemory,
+        heap: Option<&mut GuestHeap>,
+    ) -> SyscallOutcome {
+        let a0 = cpu.read_reg(10);
+        let a1 = cpu.read_reg(11);
+        let a2 = cpu.read_reg(12);
+
+        match cpu.read_reg(17) {
+            nr::EXIT => SyscallOutcome::Exit(a0 as i32),
+            nr::WRITE => SyscallOutcome::Handled(self.sys_write(mem, a0, a1, a2)),
+            nr::READ => SyscallOutcome::Handled(self.sys_read(mem, a0, a1, a2)),
+            nr::FSTAT => SyscallOutcome::Handled(sys_fstat(mem, a0, a1)),
+            nr::CLOSE => SyscallOutcome::Handled(0),
+            nr::BRK => SyscallOutcome::Handled(sys_brk(heap, cpu.read_reg(2), a0)),
+            _ => SyscallOutcome::Unhandled,
+        }
+    }
+
+    /// write(fd, buf, len)：fd 1/2 写到宿主 stdout/stderr
+    fn sys_write(&mut self, mem: &GuestMemory, fd: u32, buf: u32, len: u32) -> u32 {
+        let sink: &mut dyn Write = match fd {
+            1 => &mut self.stdout,
+            2 => &mut self.stderr,
+            _ => return errno::EBADF.wrapping_neg(),
+        };
+        let Ok(bytes) = mem.read_bytes(buf, len as usize) else {
+            return errno::EFAULT.wrapping_neg();
+        };
+        if sink.write_all(&bytes).is_err() {
+            return errno::EBADF.wrapping_neg();
+        }
+        let _ = sink.flush();
+        len
+    }
+
+    /// read(fd, buf, len)：fd 0 从宿主 stdin 读取（单次 read 语义）
+    fn sys_read(&mut self, mem: &mut GuestMemory, fd: u32, buf: u32, len: u32) -> u32 {
+        if fd != 0 {
+            return errno::EBADF.wrapping_neg();
+        }
+        let mut bytes = vec![0u8; len as usize];
+        let Ok(n) = self.stdin.read(&mut bytes) else {
+            return errno::EFAULT.wrapping_neg();
+        };
+        if mem.write_bytes(buf, &bytes[..n]).is_err() {
+            return errno::EFAULT.wrapping_neg();
+        }
+        n as u32
+    }
+}
+
+/// fstat(fd, statbuf)：标准流报告为字符设备
+///
+/// 布局按 libgloss/riscv 传入的 linux 风格 `kernel_stat`（st_mode
+/// 位于偏移 16），只填充 st_mode，其余字段清零。这足以让 newlib
+/// 把 stdout 当作终端选择行缓冲。
+fn sys_fstat(mem: &mut GuestMemory, fd: u32, statbuf: u32) -> u32 {
+    if fd > 2 {
+        return errno::EBADF.wrapping_neg();
+    }
+    const S_IFCHR: u32 = 0x2000;
+    const STAT_SIZE: usize = 64;
+    if mem.fill(statbuf, STAT_SIZE, 0).is_err() {
+        return errno::EFAULT.wrapping_neg();
+    }
+    if mem
+        .write_bytes(statbuf + 16, &S_IFCHR.to_le_bytes())
+        .is_err()
+    {
+        return errno::EFAULT.wrapping_neg();
+    }
+    0
+}
+
+/// brk(addr)：addr 为 0 时查询当前 break，否则尝试设置
+///
+/// 返回调用后的程序 break——成功时等于请求值，newlib 的 `_sbrk`
+/// 据此判断成败。未用 `with_heap` 配置堆区间时返回 -ENOMEM。
+fn sys_brk(heap: Option<&mut GuestHeap>, sp: u32, addr: u32) -> u32 {
+    let Some(heap) = heap else {
+        return errno::ENOMEM.wrapping_neg();
+    };
+    if addr != 0 {
+        heap.brk(addr, sp);
+    }
+    heap.program_break()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asm::assemble;
+    use crate::cpu::CpuState;
+    use crate::devices::SharedBuffer;
+    use crate::memory::Memory;
+    use crate::sim_env::{SimConfig, SimEnv};
+
+    fn env_with_program(source: &str) -> SimEnv {
+        let config = SimConfig::new()
+            .with_memory_size(64 * 1024)
+            .with_entry_pc(0)
+            .with_heap(0x4000, 0x4000)
+            .with_syscalls()
+            .with_max_instructions(1000);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        for (i, word) in assemble(source).unwrap().iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+        env
+    }
+
+    #[test]
+    fn test_write_and_exit() {
+        let mut env = env_with_program(
+            "
+            li a7, 64       # SYS_write
+            li a0, 1        # stdout
+            li a1, 0x200
+            li a2, 5
+            ecall
+            mv s0, a0       # 保存返回值
+            li a7, 93       # SYS_exit
+            li a0, 42
+            ecall
+            ",
+        );
+        env.memory.write_bytes(0x200, b"hello").unwrap();
+
+        let out = SharedBuffer::new();
+        env.syscalls_mut()
+            .unwrap()
+            .set_stdout(Box::new(out.clone()));
+
+        let (_, state) = env.run_until_halt();
+
+        assert_eq!(state, CpuState::Exited(42));
+        assert_eq!(env.exit_code, Some(42));
+        assert_eq!(out.contents(), "hello");
+        assert_eq!(env.cpu.read_reg(8), 5, "write 应返回写入的字节数");
+    }
+
+    #[test]
+    fn test_read_from_redirected_stdin() {
+        let mut env = env_with_program(
+            "
+            li a7, 63       # SYS_read
+            li a0, 0        # stdin
+            li a1, 0x300
+            li a2, 16
+            ecall
+            mv s0, a0
+            li a7, 93       # SYS_exit
+            ecall
+            ",
+        );
+        env.syscalls_mut()
+            .unwrap()
+            .set_stdin(Box::new(std::io::Cursor::new(b"abc".to_vec())));
+
+        env.run_until_halt();
+
+        assert_eq!(env.cpu.read_reg(8), 3, "read 应返回实际读到的字节数");
+        assert_eq!(env.memory.read_bytes(0x300, 3).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn test_brk_uses_guest_heap() {
+        let mut env = env_with_program(
+            "
+            li a7, 214      # SYS_brk
+            li a0, 0        # 查询初始 break
+            ecall
+            mv s0, a0
+            li a0, 0x4800   # 设置新 break
+            li a7, 214
+            ecall
+            mv s1, a0
+            li a7, 93       # SYS_exit
+            ecall
+            ",
+        );
+        env.run_until_halt();
+
+        assert_eq!(env.cpu.read_reg(8), 0x4000, "初始 break 应为堆区起始");
+        assert_eq!(env.cpu.read_reg(9), 0x4800, "成功的 brk 返回新 break");
+        let stats = env.heap_stats().unwrap();
+        assert_eq!(stats.program_break, 0x4800);
+    }
+
+    #[test]
+    fn test_fstat_reports_char_device() {
+        let mut env = env_with_program(
+            "
+            li a7, 80       # SYS_fstat
+            li a0, 1
+            li a1, 0x500
+            ecall
+            mv s0, a0
+            li a7, 93       # SYS_exit
+            ecall
+            ",
+        );
+        env.run_until_halt();
+
+        assert_eq!(env.cpu.read_reg(8), 0, "标准流的 fstat 应成功");
+        assert_eq!(env.memory.load32(0x500 + 16).unwrap(), 0x2000, "st_mode 应为字符设备");
+    }
+
+    #[test]
+    fn test_unknown_syscall_falls_through_to_trap() {
+        let config = SimConfig::new()
+            .with_memory_size(4096)
+            .with_entry_pc(0)
+            .with_syscalls()
+            .with_stop_on_trap(true)
+            .with_max_instructions(10);
+        let mut env = SimEnv::from_config(config).expect("Failed to create sim env");
+        let program = assemble("li a7, 999\necall").unwrap();
+        for (i, word) in program.iter().enumerate() {
+            env.memory.store32(i as u32 * 4, *word).unwrap();
+        }
+
+        env.run_until_halt();
+        assert_eq!(
+            env.stop_reason,
+            Some(crate::sim_env::StopCondition::OnTrap),
+            "未识别的调用号应走正常 trap 路径"
+        );
+    }
+}
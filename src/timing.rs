@@ -0,0 +1,150 @@
+//! 可插拔的周期计时模型
+//!
+//! 功能仿真只回答"结果对不对"；在向 GPGPU 后端演化之前，还需要
+//! 比较不同设计点的开销。本模块定义 [`TimingModel`] trait，由
+//! `CpuCore::step` 在每条指令退休后咨询一次，把指令类别和访存
+//! 折算成周期数累计；[`SimpleTimingModel`] 是逐类别可配置的默认
+//! 实现。总周期与 IPC 通过 `SimEnv::timing`（或
+//! `CpuCore::timing_report`）读取。
+
+use crate::isa::RvInstr;
+use crate::stats::InstrClass;
+
+/// 周期计时模型
+///
+/// 实现者根据指令（及其分支走向）给出本条指令消耗的周期数。
+/// 模型只在指令退休后被咨询，不影响功能语义。
+pub trait TimingModel {
+    /// 本条指令消耗的周期数（含其访存开销）
+    ///
+    /// `branch_taken` 仅对条件分支有意义，供建模 taken 罚时
+    fn cycles(&self, instr: &RvInstr, branch_taken: bool) -> u64;
+}
+
+/// 简单的标量流水线模型：每个指令类别一个基础周期数，
+/// 访存与 taken 分支额外计罚时
+///
+/// 默认值对应理想化的单发射流水线：ALU 类 1 周期，访存在基础
+/// 周期上加 `mem_access_cycles`，taken 分支/跳转加
+/// `branch_penalty_cycles`（冲刷开销）。
+#[derive(Debug, Clone)]
+pub struct SimpleTimingModel {
+    /// 各指令类别的基础周期数，下标对应 [`InstrClass::ALL`]
+    class_cycles: [u64; InstrClass::ALL.len()],
+    /// 一次内存访问的额外周期（load/store 计一次，AMO 计两次）
+    pub mem_access_cycles: u64,
+    /// taken 分支与无条件跳转的冲刷罚时
+    pub branch_penalty_cycles: u64,
+}
+
+impl Default for SimpleTimingModel {
+    fn default() -> Self {
+        Self {
+            class_cycles: [1; InstrClass::ALL.len()],
+            mem_access_cycles: 2,
+            branch_penalty_cycles: 1,
+        }
+    }
+}
+
+impl SimpleTimingModel {
+    /// 某个类别的基础周期数
+    pub fn class_cycles(&self, class: InstrClass) -> u64 {
+        let idx = InstrClass::ALL
+            .iter()
+            .position(|c| *c == class)
+            .expect("InstrClass::ALL covers every class");
+        self.class_cycles[idx]
+    }
+
+    /// 设置某个类别的基础周期数（builder 风格，可链式调用）
+    pub fn with_class_cycles(mut self, class: InstrClass, cycles: u64) -> Self {
+        let idx = InstrClass::ALL
+            .iter()
+            .position(|c| *c == class)
+            .expect("InstrClass::ALL covers every class");
+        self.class_cycles[idx] = cycles;
+        self
+    }
+
+    /// 设置访存罚时
+    pub fn with_mem_access_cycles(mut self, cycles: u64) -> Self {
+        self.mem_access_cycles = cycles;
+        self
+    }
+
+    /// 设置分支冲刷罚时
+    pub fn with_branch_penalty(mut self, cycles: u64) -> Self {
+        self.branch_penalty_cycles = cycles;
+        self
+    }
+}
+
+impl TimingModel for SimpleTimingModel {
+    fn cycles(&self, instr: &RvInstr, branch_taken: bool) -> u64 {
+        let class = InstrClass::of(instr);
+        let mut total = self.class_cycles(class);
+        match class {
+            InstrClass::Load | InstrClass::Store => total += self.mem_access_cycles,
+            // AMO 是一次读加一次写
+            InstrClass::Amo => total += 2 * self.mem_access_cycles,
+            InstrClass::Branch if branch_taken => total += self.branch_penalty_cycles,
+            InstrClass::Jump => total += self.branch_penalty_cycles,
+            _ => {}
+        }
+        total
+    }
+}
+
+/// 计时统计汇总（见 `CpuCore::timing_report`）
+#[derive(Debug, Clone, Copy)]
+pub struct TimingReport {
+    /// 计时期间退休的指令数
+    pub instructions: u64,
+    /// 累计周期数
+    pub cycles: u64,
+}
+
+impl TimingReport {
+    /// 每周期指令数（没有执行过指令时为 None）
+    pub fn ipc(&self) -> Option<f64> {
+        (self.cycles > 0).then(|| self.instructions as f64 / self.cycles as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_model_per_class() {
+        let model = SimpleTimingModel::default();
+        // ALU：基础 1 周期
+        assert_eq!(model.cycles(&RvInstr::Add { rd: 1, rs1: 2, rs2: 3 }, false), 1);
+        // load：基础 1 + 访存 2
+        assert_eq!(model.cycles(&RvInstr::Lw { rd: 1, rs1: 2, offset: 0 }, false), 3);
+        // taken 分支：基础 1 + 罚时 1；not-taken 无罚时
+        let beq = RvInstr::Beq { rs1: 1, rs2: 2, offset: 8 };
+        assert_eq!(model.cycles(&beq, true), 2);
+        assert_eq!(model.cycles(&beq, false), 1);
+    }
+
+    #[test]
+    fn test_configurable_cycles() {
+        let model = SimpleTimingModel::default()
+            .with_class_cycles(InstrClass::Alu, 2)
+            .with_mem_access_cycles(10)
+            .with_branch_penalty(3);
+        assert_eq!(model.cycles(&RvInstr::Add { rd: 1, rs1: 2, rs2: 3 }, false), 2);
+        assert_eq!(model.cycles(&RvInstr::Sw { rs1: 1, rs2: 2, offset: 0 }, false), 11);
+        assert_eq!(model.cycles(&RvInstr::Jal { rd: 0, offset: 8 }, false), 4);
+    }
+
+    #[test]
+    fn test_report_ipc() {
+        let report = TimingReport { instructions: 50, cycles: 100 };
+        assert_eq!(report.ipc(), Some(0.5));
+        let empty = TimingReport { instructions: 0, cycles: 0 };
+        assert_eq!(empty.ipc(), None);
+    }
+}
@@ -0,0 +1,459 @@
+//! 指令跟踪子系统
+//!
+//! 定义跟踪记录的接收端 `TraceSink` trait 以及若干内置实现
+//! （标准输出、文件、环形缓冲区）。安装 sink 后，`CpuCore` 在每条
+//! 指令执行完成时推送一条 `TraceRecord`，无需在被仿真程序中插入
+//! printf 即可观察执行过程。
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::cpu::PrivilegeMode;
+use crate::isa::RvInstr;
+use crate::memory::{AccessSize, MemResult, Memory};
+
+/// 一次被跟踪的内存访问（不含取指）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemTraceAccess {
+    /// 访问地址
+    pub addr: u32,
+    /// 访问粒度
+    pub size: AccessSize,
+    /// 读出/写入的值（零扩展到 32 位）
+    pub value: u32,
+    /// 是否为写访问
+    pub is_store: bool,
+}
+
+/// 单条指令的跟踪记录
+#[derive(Debug, Clone)]
+pub struct TraceRecord {
+    /// 指令 PC
+    pub pc: u32,
+    /// 原始编码
+    pub raw: u32,
+    /// 解码结果
+    pub instr: RvInstr,
+    /// 本条指令的整数寄存器写入 (寄存器号, 新值)
+    pub reg_writes: Vec<(u8, u32)>,
+    /// 本条指令的内存访问
+    pub mem_accesses: Vec<MemTraceAccess>,
+    /// 执行该指令时的特权级
+    pub privilege: PrivilegeMode,
+}
+
+impl TraceRecord {
+    pub(crate) fn new(pc: u32, raw: u32, instr: RvInstr, privilege: PrivilegeMode) -> Self {
+        Self {
+            pc,
+            raw,
+            instr,
+            reg_writes: Vec::new(),
+            mem_accesses: Vec::new(),
+            privilege,
+        }
+    }
+
+    /// 序列化为单行 JSON（与 [`JsonTraceSink`] 输出的行同一格式），
+    /// 供用户自行持久化或比对跟踪记录
+    pub fn to_json(&self) -> String {
+        format_json(self)
+    }
+}
+
+/// 跟踪记录的接收端
+///
+/// 实现该 trait 即可自定义跟踪的输出格式或目的地
+pub trait TraceSink {
+    /// 接收一条指令的跟踪记录
+    fn record(&mut self, rec: &TraceRecord);
+}
+
+/// 允许通过 `Rc<RefCell<...>>` 共享 sink，便于在仿真结束后读回
+/// 内容（典型用法：环形缓冲区）
+impl<T: TraceSink> TraceSink for Rc<RefCell<T>> {
+    fn record(&mut self, rec: &TraceRecord) {
+        self.borrow_mut().record(rec);
+    }
+}
+
+/// 把一条记录格式化为单行文本（stdout/文件 sink 共用）
+fn format_record(rec: &TraceRecord) -> String {
+    let mut line = format!("pc=0x{:08x} [0x{:08x}] {}", rec.pc, rec.raw, rec.instr);
+    for (reg, value) in &rec.reg_writes {
+        line.push_str(&format!(" x{}=0x{:08x}", reg, value));
+    }
+    for acc in &rec.mem_accesses {
+        line.push_str(&format!(
+            " {}[0x{:08x}]=0x{:x}",
+            if acc.is_store { "W" } else { "R" },
+            acc.addr,
+            acc.value,
+        ));
+    }
+    line
+}
+
+/// 输出到标准输出的 sink
+#[derive(Default)]
+pub struct StdoutSink;
+
+impl TraceSink for StdoutSink {
+    fn record(&mut self, rec: &TraceRecord) {
+        println!("{}", format_record(rec));
+    }
+}
+
+/// 输出到文件的 sink（带缓冲，drop 时刷新）
+pub struct FileSink {
+    writer: BufWriter<File>,
+}
+
+impl FileSink {
+    /// 创建（或截断）跟踪文件
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+}
+
+impl TraceSink for FileSink {
+    fn record(&mut self, rec: &TraceRecord) {
+        let _ = writeln!(self.writer, "{}", format_record(rec));
+    }
+}
+
+impl Drop for FileSink {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// 输出 JSON Lines 的 sink：每条退休指令一行 JSON 对象
+///
+/// 为外部分析工具（Python notebook、trace 对比脚本）准备的机器
+/// 可读格式。每行字段：
+///
+/// - `pc`、`raw`：数值（指令 PC 与原始编码）
+/// - `mnemonic`、`text`：助记符与含操作数的反汇编文本
+/// - `priv`：特权级（`"M"` / `"S"` / `"U"`）
+/// - `reg_writes`：`[{"reg", "value"}]` 形式的寄存器写回
+/// - `mem`：`[{"addr", "size", "value", "store"}]` 形式的访存
+///   （size 为字节数）
+///
+/// 输出目标是任意 `Write`：要压缩时把 gzip 编码器包在外面传入
+/// 即可，sink 本身不做压缩。
+pub struct JsonTraceSink {
+    writer: Box<dyn Write>,
+}
+
+impl JsonTraceSink {
+    /// 输出到任意写入端（如 gzip 编码器或内存缓冲）
+    pub fn new(writer: Box<dyn Write>) -> Self {
+        Self { writer }
+    }
+
+    /// 创建（或截断）JSON Lines 跟踪文件（带缓冲，drop 时刷新）
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            writer: Box::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+}
+
+/// 把一条记录序列化为单行 JSON（字符串字段只含 ASCII，按需转义）
+pub(crate) fn format_json(rec: &TraceRecord) -> String {
+    let privilege = match rec.privilege {
+        PrivilegeMode::Machine => "M",
+        PrivilegeMode::Supervisor => "S",
+        PrivilegeMode::User => "U",
+        PrivilegeMode::_Reserved => "?",
+    };
+    let mut line = format!(
+        "{{\"pc\":{},\"raw\":{},\"mnemonic\":\"{}\",\"text\":\"{}\",\"priv\":\"{}\"",
+        rec.pc,
+        rec.raw,
+        json_escape(rec.instr.mnemonic()),
+        json_escape(&rec.instr.to_string()),
+        privilege,
+    );
+    line.push_str(",\"reg_writes\":[");
+    for (i, (reg, value)) in rec.reg_writes.iter().enumerate() {
+        if i > 0 {
+            line.push(',');
+        }
+        line.push_str(&format!("{{\"reg\":{},\"value\":{}}}", reg, value));
+    }
+    line.push_str("],\"mem\":[");
+    for (i, acc) in rec.mem_accesses.iter().enumerate() {
+        if i > 0 {
+            line.push(',');
+        }
+        let size = match acc.size {
+            AccessSize::Byte => 1,
+            AccessSize::Half => 2,
+            AccessSize::Word => 4,
+        };
+        line.push_str(&format!(
+            "{{\"addr\":{},\"size\":{},\"value\":{},\"store\":{}}}",
+            acc.addr, size, acc.value, acc.is_store,
+        ));
+    }
+    line.push_str("]}");
+    line
+}
+
+/// 反斜杠、引号与控制字符的最小 JSON 转义
+fn json_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl TraceSink for JsonTraceSink {
+    fn record(&mut self, rec: &TraceRecord) {
+        let _ = writeln!(self.writer, "{}", format_json(rec));
+    }
+}
+
+impl Drop for JsonTraceSink {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// 保留最近 N 条记录的环形缓冲区 sink
+///
+/// 适合长时间运行后只查看出错前最后若干条指令的场景
+pub struct RingBufferSink {
+    capacity: usize,
+    records: VecDeque<TraceRecord>,
+}
+
+impl RingBufferSink {
+    /// 创建容量为 `capacity`（至少为 1）的环形缓冲区
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            records: VecDeque::new(),
+        }
+    }
+
+    /// 按时间顺序遍历缓冲的记录（最旧的在前）
+    pub fn records(&self) -> impl Iterator<Item = &TraceRecord> {
+        self.records.iter()
+    }
+
+    /// 缓冲的记录数
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl TraceSink for RingBufferSink {
+    fn record(&mut self, rec: &TraceRecord) {
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(rec.clone());
+    }
+}
+
+/// 包装一个 `Memory`，把执行期间的数据访问记录下来
+///
+/// 仅供 `CpuCore` 跟踪执行路径使用。load 接口是 `&self`，
+/// 因此访问列表放在 `RefCell` 中。
+pub(crate) struct TracingMemory<'a> {
+    inner: &'a mut dyn Memory,
+    accesses: RefCell<Vec<MemTraceAccess>>,
+}
+
+impl<'a> TracingMemory<'a> {
+    pub(crate) fn new(inner: &'a mut dyn Memory) -> Self {
+        Self {
+            inner,
+            accesses: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// 取出记录到的访问列表
+    pub(crate) fn into_accesses(self) -> Vec<MemTraceAccess> {
+        self.accesses.into_inner()
+    }
+
+    fn record(&self, addr: u32, size: AccessSize, value: u32, is_store: bool) {
+        self.accesses.borrow_mut().push(MemTraceAccess {
+            addr,
+            size,
+            value,
+            is_store,
+        });
+    }
+}
+
+impl Memory for TracingMemory<'_> {
+    fn load8(&self, addr: u32) -> MemResult<u8> {
+        let value = self.inner.load8(addr)?;
+        self.record(addr, AccessSize::Byte, value as u32, false);
+        Ok(value)
+    }
+
+    fn load16(&self, addr: u32) -> MemResult<u16> {
+        let value = self.inner.load16(addr)?;
+        self.record(addr, AccessSize::Half, value as u32, false);
+        Ok(value)
+    }
+
+    fn load32(&self, addr: u32) -> MemResult<u32> {
+        let value = self.inner.load32(addr)?;
+        self.record(addr, AccessSize::Word, value, false);
+        Ok(value)
+    }
+
+    fn store8(&mut self, addr: u32, value: u8) -> MemResult<()> {
+        self.inner.store8(addr, value)?;
+        self.record(addr, AccessSize::Byte, value as u32, true);
+        Ok(())
+    }
+
+    fn store16(&mut self, addr: u32, value: u16) -> MemResult<()> {
+        self.inner.store16(addr, value)?;
+        self.record(addr, AccessSize::Half, value as u32, true);
+        Ok(())
+    }
+
+    fn store32(&mut self, addr: u32, value: u32) -> MemResult<()> {
+        self.inner.store32(addr, value)?;
+        self.record(addr, AccessSize::Word, value, true);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CpuCore;
+    use crate::memory::FlatMemory;
+
+    #[test]
+    fn test_ring_buffer_sink_records() {
+        let mut cpu = CpuCore::new(0);
+        let mut mem = FlatMemory::new(1024, 0);
+
+        // addi x1, x0, 42
+        mem.store32(0, 0x02A00093).unwrap();
+        // sw x1, 0x100(x0)
+        mem.store32(4, 0x10102023).unwrap();
+
+        let ring = Rc::new(RefCell::new(RingBufferSink::new(4)));
+        cpu.set_trace_sink(Box::new(ring.clone()));
+
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+
+        let ring = ring.borrow();
+        assert_eq!(ring.len(), 2);
+        let recs: Vec<_> = ring.records().collect();
+
+        // addi: 记录 PC、编码和寄存器写入
+        assert_eq!(recs[0].pc, 0);
+        assert_eq!(recs[0].raw, 0x02A00093);
+        assert_eq!(recs[0].reg_writes, vec![(1, 42)]);
+        assert!(recs[0].mem_accesses.is_empty());
+
+        // sw: 记录内存写访问
+        assert_eq!(recs[1].pc, 4);
+        let acc = recs[1].mem_accesses[0];
+        assert!(acc.is_store);
+        assert_eq!(acc.addr, 0x100);
+        assert_eq!(acc.value, 42);
+    }
+
+    #[test]
+    fn test_ring_buffer_capacity() {
+        let mut sink = RingBufferSink::new(2);
+        let nop = RvInstr::Addi { rd: 0, rs1: 0, imm: 0 };
+
+        for pc in [0u32, 4, 8] {
+            sink.record(&TraceRecord::new(pc, 0x13, nop, PrivilegeMode::Machine));
+        }
+
+        // 容量为 2：最旧的记录被淘汰
+        assert_eq!(sink.len(), 2);
+        let pcs: Vec<u32> = sink.records().map(|r| r.pc).collect();
+        assert_eq!(pcs, vec![4, 8]);
+    }
+
+    #[test]
+    fn test_json_sink_emits_one_object_per_instruction() {
+        let mut cpu = CpuCore::new(0);
+        let mut mem = FlatMemory::new(1024, 0);
+
+        // addi x1, x0, 42
+        mem.store32(0, 0x02A00093).unwrap();
+        // sw x1, 0x100(x0)
+        mem.store32(4, 0x10102023).unwrap();
+
+        let out = crate::devices::SharedBuffer::new();
+        cpu.set_trace_sink(Box::new(JsonTraceSink::new(Box::new(out.clone()))));
+
+        cpu.step(&mut mem);
+        cpu.step(&mut mem);
+
+        let text = out.contents();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2, "每条指令一行");
+
+        // addi: PC、编码、助记符、特权级和寄存器写回
+        assert!(lines[0].starts_with("{\"pc\":0,\"raw\":44040339,"));
+        assert!(lines[0].contains("\"mnemonic\":\"addi\""));
+        assert!(lines[0].contains("\"priv\":\"M\""));
+        assert!(lines[0].contains("\"reg_writes\":[{\"reg\":1,\"value\":42}]"));
+        assert!(lines[0].ends_with("\"mem\":[]}"));
+
+        // sw: 访存数组携带地址、粒度与值
+        assert!(lines[1].contains("\"pc\":4"));
+        assert!(
+            lines[1].contains("\"mem\":[{\"addr\":256,\"size\":4,\"value\":42,\"store\":true}]"),
+            "访存记录不符: {}",
+            lines[1]
+        );
+    }
+
+    #[test]
+    fn test_json_escape_special_chars() {
+        assert_eq!(json_escape("addi"), "addi");
+        assert_eq!(json_escape("a\"b\\c"), "a\\\"b\\\\c");
+        assert_eq!(json_escape("x\ny"), "x\\u000ay");
+    }
+
+    #[test]
+    fn test_record_to_json_matches_sink_format() {
+        use crate::isa::RvInstr;
+
+        let rec = TraceRecord::new(
+            0x100,
+            0x02A00093,
+            RvInstr::Addi { rd: 1, rs1: 0, imm: 42 },
+            PrivilegeMode::Machine,
+        );
+        assert_eq!(rec.to_json(), format_json(&rec), "公开方法与 sink 同一格式");
+        assert!(rec.to_json().starts_with("{\"pc\":256,\"raw\":44040339,"));
+    }
+}
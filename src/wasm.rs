@@ -0,0 +1,339 @@
+//! 浏览器侧 JS API（`wasm` feature）
+//!
+//! 为教学/可视化前端提供一层扁平的导出：加载 ELF 字节、单步或
+//! 连跑、读寄存器与内存、取最近若干条跟踪记录（JSON Lines）。
+//! 全部依赖都是纯 Rust，编译到 `wasm32-unknown-unknown` 不需要
+//! 外部绑定生成器——导出走 C ABI，浏览器里用
+//! `WebAssembly.instantiate` 加一小段读写线性内存的 JS 胶水即可
+//! 驱动（流程见 [`wasm_sim_create`]）。
+//!
+//! 文件 IO 路径（`from_elf`、各种文件 sink）在本模块一概不触碰，
+//! 浏览器里没有文件系统；ELF 以字节数组经 [`wasm_alloc`] 拷入
+//! 线性内存后传给 [`wasm_sim_create`]。
+//!
+//! [`WasmSim`] 是底下的 Rust 包装，宿主侧测试与将来接
+//! wasm-bindgen 之类的绑定层都直接用它；C ABI 导出只是在其上
+//! 套了句柄注册表（句柄 = 注册表索引 + 1，0 保留为无效）。
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::cpu::CpuState;
+use crate::sim_env::{SimEnv, SimError};
+use crate::trace::{RingBufferSink, format_json};
+
+/// 面向 JS 的仿真器包装
+///
+/// 持有一个从 ELF 字节构建的 [`SimEnv`]，并挂一个环形缓冲跟踪
+/// sink，前端随时能取最近执行的指令做可视化回放。
+pub struct WasmSim {
+    env: SimEnv,
+    trace: Rc<RefCell<RingBufferSink>>,
+}
+
+impl WasmSim {
+    /// 从 ELF 字节构建，保留最近 `trace_capacity` 条跟踪记录
+    pub fn new(elf_bytes: Vec<u8>, trace_capacity: usize) -> Result<Self, SimError> {
+        let mut env = SimEnv::from_elf_bytes(elf_bytes)?;
+        let trace = Rc::new(RefCell::new(RingBufferSink::new(trace_capacity)));
+        env.cpu_mut().set_trace_sink(Box::new(Rc::clone(&trace)));
+        Ok(Self { env, trace })
+    }
+
+    /// 执行至多 `max_instructions` 条指令，返回实际执行数
+    pub fn run(&mut self, max_instructions: u64) -> u64 {
+        self.env.run(max_instructions).0
+    }
+
+    /// 单步一条指令，返回执行后的状态
+    pub fn step(&mut self) -> CpuState {
+        self.env.step()
+    }
+
+    /// 当前 PC
+    pub fn pc(&self) -> u32 {
+        self.env.cpu().pc()
+    }
+
+    /// 读整数寄存器（`reg` 超出 x0..x31 时返回 0）
+    pub fn reg(&self, reg: u32) -> u32 {
+        if reg < 32 {
+            self.env.cpu().read_reg(reg as u8)
+        } else {
+            0
+        }
+    }
+
+    /// 读一段客体内存（越界时返回 None）
+    pub fn read_memory(&self, addr: u32, len: usize) -> Option<Vec<u8>> {
+        self.env.memory.read_bytes(addr, len).ok()
+    }
+
+    /// 当前 CPU 状态
+    pub fn state(&self) -> CpuState {
+        self.env.cpu().state()
+    }
+
+    /// 最近执行的指令，每条一行 JSON（与 [`crate::trace::JsonTraceSink`]
+    /// 同一格式），最旧的在前
+    pub fn trace_json(&self) -> String {
+        let trace = self.trace.borrow();
+        let mut out = String::new();
+        for rec in trace.records() {
+            out.push_str(&format_json(rec));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// CPU 状态到 JS 侧整数码的映射（与快照格式的状态标签一致）
+fn state_code(state: CpuState) -> i32 {
+    match state {
+        CpuState::Running => 0,
+        CpuState::WaitForInterrupt => 1,
+        CpuState::Halted => 2,
+        CpuState::IllegalInstruction(_) => 3,
+        CpuState::WatchpointHit(_) => 4,
+        CpuState::DebugStep => 5,
+        CpuState::Exited(_) => 6,
+    }
+}
+
+// ========== C ABI 导出 ==========
+//
+// wasm 是单线程环境，实例注册表放 thread_local 即可（宿主侧
+// 测试也能跑）。
+
+thread_local! {
+    static SIMS: RefCell<Vec<Option<WasmSim>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// 对句柄指向的实例执行 `f`；句柄无效时返回 `default`
+fn with_sim<R>(handle: u32, default: R, f: impl FnOnce(&mut WasmSim) -> R) -> R {
+    SIMS.with(|sims| {
+        let mut sims = sims.borrow_mut();
+        match handle
+            .checked_sub(1)
+            .and_then(|i| sims.get_mut(i as usize))
+            .and_then(Option::as_mut)
+        {
+            Some(sim) => f(sim),
+            None => default,
+        }
+    })
+}
+
+/// 分配 `len` 字节的线性内存，供 JS 拷入 ELF 等输入
+///
+/// 所有权交给调用方，用完以同样的长度交还 [`wasm_free`]。
+#[unsafe(no_mangle)]
+pub extern "C" fn wasm_alloc(len: usize) -> *mut u8 {
+    let mut buf = Vec::<u8>::with_capacity(len.max(1));
+    let ptr = buf.as_mut_ptr();
+    std::mem::forget(buf);
+    ptr
+}
+
+/// 释放 [`wasm_alloc`] 或 [`wasm_sim_trace_json`] 交出的缓冲区
+///
+/// # Safety
+///
+/// `ptr`/`len` 必须来自上述分配函数且只释放一次。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wasm_free(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        drop(unsafe { Vec::from_raw_parts(ptr, 0, len.max(1)) });
+    }
+}
+
+/// 从 ELF 字节创建仿真器实例，返回句柄（0 表示失败）
+///
+/// 典型 JS 流程：`wasm_alloc` → 把 ELF 拷进线性内存 →
+/// `wasm_sim_create` → 循环 `wasm_sim_run` / 读寄存器内存 →
+/// `wasm_sim_destroy`。
+///
+/// # Safety
+///
+/// `elf_ptr` 起 `elf_len` 字节必须在线性内存内可读。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wasm_sim_create(
+    elf_ptr: *const u8,
+    elf_len: usize,
+    trace_capacity: usize,
+) -> u32 {
+    let bytes = unsafe { std::slice::from_raw_parts(elf_ptr, elf_len) }.to_vec();
+    let Ok(sim) = WasmSim::new(bytes, trace_capacity) else {
+        return 0;
+    };
+    SIMS.with(|sims| {
+        let mut sims = sims.borrow_mut();
+        // 复用 destroy 留下的空位，避免注册表只增不减
+        if let Some(i) = sims.iter().position(Option::is_none) {
+            sims[i] = Some(sim);
+            i as u32 + 1
+        } else {
+            sims.push(Some(sim));
+            sims.len() as u32
+        }
+    })
+}
+
+/// 销毁实例并释放其内存，句柄随即失效
+#[unsafe(no_mangle)]
+pub extern "C" fn wasm_sim_destroy(handle: u32) {
+    SIMS.with(|sims| {
+        let mut sims = sims.borrow_mut();
+        if let Some(slot) = handle.checked_sub(1).and_then(|i| sims.get_mut(i as usize)) {
+            *slot = None;
+        }
+    });
+}
+
+/// 执行至多 `max_instructions` 条指令，返回实际执行数
+#[unsafe(no_mangle)]
+pub extern "C" fn wasm_sim_run(handle: u32, max_instructions: u64) -> u64 {
+    with_sim(handle, 0, |sim| sim.run(max_instructions))
+}
+
+/// 单步一条指令，返回状态码（同 [`wasm_sim_state`]）
+#[unsafe(no_mangle)]
+pub extern "C" fn wasm_sim_step(handle: u32) -> i32 {
+    with_sim(handle, -1, |sim| state_code(sim.step()))
+}
+
+/// 当前 PC（句柄无效时返回 0）
+#[unsafe(no_mangle)]
+pub extern "C" fn wasm_sim_pc(handle: u32) -> u32 {
+    with_sim(handle, 0, |sim| sim.pc())
+}
+
+/// 读整数寄存器 x0..x31（越界或句柄无效时返回 0）
+#[unsafe(no_mangle)]
+pub extern "C" fn wasm_sim_reg(handle: u32, reg: u32) -> u32 {
+    with_sim(handle, 0, |sim| sim.reg(reg))
+}
+
+/// CPU 状态码：0 运行中、1 WFI、2 停机、3 非法指令、4 监视点、
+/// 5 单步停、6 已退出；-1 句柄无效
+#[unsafe(no_mangle)]
+pub extern "C" fn wasm_sim_state(handle: u32) -> i32 {
+    with_sim(handle, -1, |sim| state_code(sim.state()))
+}
+
+/// 把 `[addr, addr+len)` 的客体内存拷到 `out_ptr`
+///
+/// 返回拷贝的字节数，越界或句柄无效时返回 -1。
+///
+/// # Safety
+///
+/// `out_ptr` 起 `len` 字节必须在线性内存内可写。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wasm_sim_read_memory(
+    handle: u32,
+    addr: u32,
+    out_ptr: *mut u8,
+    len: usize,
+) -> i32 {
+    with_sim(handle, -1, |sim| match sim.read_memory(addr, len) {
+        Some(bytes) => {
+            unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), out_ptr, bytes.len()) };
+            bytes.len() as i32
+        }
+        None => -1,
+    })
+}
+
+/// 取最近的跟踪记录（JSON Lines，UTF-8）
+///
+/// 返回缓冲区指针并把字节数写到 `out_len`；调用方用
+/// [`wasm_free`] 释放。句柄无效时返回空指针、长度 0。
+///
+/// # Safety
+///
+/// `out_len` 必须指向线性内存内可写的 usize。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wasm_sim_trace_json(handle: u32, out_len: *mut usize) -> *mut u8 {
+    let json = with_sim(handle, None, |sim| Some(sim.trace_json()));
+    match json {
+        Some(json) => {
+            let mut bytes = json.into_bytes();
+            bytes.shrink_to_fit();
+            let len = bytes.len();
+            let ptr = if len == 0 {
+                wasm_alloc(0)
+            } else {
+                let ptr = bytes.as_mut_ptr();
+                std::mem::forget(bytes);
+                ptr
+            };
+            unsafe { *out_len = len };
+            ptr
+        }
+        None => {
+            unsafe { *out_len = 0 };
+            std::ptr::null_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 预编译的 riscv-tests 固件（仓库根目录 isa_test/）
+    const FIXTURE: &str = "isa_test/rv32ui-p-and";
+
+    fn fixture_bytes() -> Option<Vec<u8>> {
+        std::fs::read(FIXTURE).ok()
+    }
+
+    #[test]
+    fn test_wasm_sim_runs_elf_and_reads_state() {
+        let Some(bytes) = fixture_bytes() else {
+            eprintln!("跳过：缺少 {FIXTURE}");
+            return;
+        };
+        let mut sim = WasmSim::new(bytes, 16).expect("ELF 字节应能构建");
+        let entry = sim.pc();
+        sim.run(10_000);
+        assert_ne!(sim.pc(), entry, "应已执行若干指令");
+        // 跟踪缓冲里应有 JSON 行
+        let trace = sim.trace_json();
+        assert!(trace.lines().count() <= 16);
+        assert!(trace.lines().all(|l| l.starts_with("{\"pc\":")), "{trace}");
+        // 入口处的内存可读且是合法指令字
+        let code = sim.read_memory(entry, 4).expect("入口内存应可读");
+        assert_eq!(code.len(), 4);
+    }
+
+    #[test]
+    fn test_c_abi_handle_lifecycle() {
+        let Some(bytes) = fixture_bytes() else {
+            eprintln!("跳过：缺少 {FIXTURE}");
+            return;
+        };
+        let ptr = wasm_alloc(bytes.len());
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len()) };
+        let handle = unsafe { wasm_sim_create(ptr, bytes.len(), 8) };
+        unsafe { wasm_free(ptr, bytes.len()) };
+        assert_ne!(handle, 0);
+
+        assert_eq!(wasm_sim_state(handle), 0, "初始应为运行中");
+        let executed = wasm_sim_run(handle, 100);
+        assert!(executed > 0);
+        assert_eq!(wasm_sim_reg(handle, 0), 0, "x0 恒为 0");
+
+        let mut len = 0usize;
+        let json = unsafe { wasm_sim_trace_json(handle, &mut len) };
+        assert!(!json.is_null());
+        assert!(len > 0);
+        unsafe { wasm_free(json, len) };
+
+        wasm_sim_destroy(handle);
+        assert_eq!(wasm_sim_state(handle), -1, "销毁后句柄失效");
+        // 无效 ELF 创建失败返回 0
+        let bad = [0u8; 4];
+        assert_eq!(unsafe { wasm_sim_create(bad.as_ptr(), 4, 8) }, 0);
+    }
+}